@@ -0,0 +1,261 @@
+use glam::{Mat4, UVec2, Vec2, Vec3};
+use rend3::util::typedefs::FastHashMap;
+
+use crate::camera;
+use crate::scene::{ObjectId, Scene, SceneTransform};
+
+/// What an anchor follows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnchorTarget {
+	/// the object's origin, so it keeps up as the object moves
+	Object(ObjectId),
+	/// a fixed world space position
+	Point(Vec3),
+}
+
+/// What an anchor draws.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnchorContent {
+	Label(String),
+	/// a bar filled `value` of the way, 0.0 to 1.0
+	Bar {
+		value: f32,
+		color: egui::Color32,
+	},
+}
+
+/// UI drawn at the screen position of something in the world.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScreenAnchor {
+	pub target: AnchorTarget,
+	/// world space offset from the target, e.g. to float above an object
+	pub offset: Vec3,
+	pub content: AnchorContent,
+	/// keeps the anchor on screen at the nearest edge when its target isn't
+	pub clamp_to_edge: bool,
+	/// hidden beyond this distance from the camera
+	pub max_distance: f32,
+}
+
+impl ScreenAnchor {
+	pub fn label(target: AnchorTarget, text: impl Into<String>) -> Self {
+		Self {
+			target,
+			offset: Vec3::ZERO,
+			content: AnchorContent::Label(text.into()),
+			clamp_to_edge: false,
+			max_distance: f32::INFINITY,
+		}
+	}
+
+	pub fn bar(target: AnchorTarget, value: f32, color: egui::Color32) -> Self {
+		Self {
+			content: AnchorContent::Bar { value, color },
+			..Self::label(target, "")
+		}
+	}
+
+	pub fn with_offset(self, offset: Vec3) -> Self {
+		Self { offset, ..self }
+	}
+
+	pub fn clamped(self) -> Self {
+		Self {
+			clamp_to_edge: true,
+			..self
+		}
+	}
+}
+
+/// Identifies an anchor in [`ScreenAnchors`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AnchorId(u64);
+
+struct AnchorState {
+	anchor: ScreenAnchor,
+	/// eased toward 1.0 while visible and [`OCCLUDED_OPACITY`] while behind
+	/// something
+	opacity: f32,
+}
+
+/// opacity of anchors whose target is hidden behind geometry
+const OCCLUDED_OPACITY: f32 = 0.25;
+/// how quickly opacity follows occlusion changes, per second
+const FADE_RATE: f32 = 8.0;
+/// distance kept from the viewport edges by clamped anchors, in points
+const EDGE_MARGIN: f32 = 12.0;
+
+/// Projects anchored world positions to the screen every frame and draws
+/// their labels and bars there, fading them out while the target is hidden
+/// behind other geometry.
+pub struct ScreenAnchors {
+	pub enabled: bool,
+	/// fade anchors whose target is occluded, tested with
+	/// [`Scene::raycast`]
+	pub occlusion: bool,
+	anchors: FastHashMap<AnchorId, AnchorState>,
+	next_id: u64,
+}
+
+impl Default for ScreenAnchors {
+	fn default() -> Self {
+		Self {
+			enabled: true,
+			occlusion: true,
+			anchors: FastHashMap::default(),
+			next_id: 0,
+		}
+	}
+}
+
+impl ScreenAnchors {
+	pub fn add(&mut self, anchor: ScreenAnchor) -> AnchorId {
+		let id = AnchorId(self.next_id);
+		self.next_id += 1;
+		self.anchors.insert(
+			id,
+			AnchorState {
+				anchor,
+				opacity: 1.0,
+			},
+		);
+		id
+	}
+
+	pub fn remove(&mut self, id: AnchorId) {
+		self.anchors.remove(&id);
+	}
+
+	/// For changing an anchor's content, e.g. a bar's value.
+	pub fn get_mut(&mut self, id: AnchorId) -> Option<&mut ScreenAnchor> {
+		self.anchors.get_mut(&id).map(|state| &mut state.anchor)
+	}
+
+	/// Draws every anchor for a camera at `camera_position` rendering the
+	/// viewport with the world space `view_proj`. Anchors of objects that no
+	/// longer exist are dropped.
+	pub fn draw(
+		&mut self,
+		ctx: &egui::CtxRef,
+		scene: &Scene,
+		view_proj: Mat4,
+		camera_position: Vec3,
+		viewport: (UVec2, UVec2),
+	) {
+		self.anchors.retain(|_, state| match state.anchor.target {
+			AnchorTarget::Object(id) => scene.get(id).is_some(),
+			AnchorTarget::Point(_) => true,
+		});
+		if !self.enabled {
+			return;
+		}
+
+		let pixels_per_point = ctx.pixels_per_point();
+		let to_points = |pixel: Vec2| {
+			let point = pixel / pixels_per_point;
+			egui::pos2(point.x, point.y)
+		};
+		let (offset, size) = viewport;
+		let screen = egui::Rect::from_min_max(
+			to_points(offset.as_vec2()),
+			to_points((offset + size).as_vec2()),
+		)
+		.shrink(EDGE_MARGIN);
+		let painter = ctx.layer_painter(egui::LayerId::background());
+		let fade = 1.0 - (-FADE_RATE * ctx.input().unstable_dt).exp();
+
+		for state in self.anchors.values_mut() {
+			let anchor = &state.anchor;
+			let (target, ignore) = match anchor.target {
+				AnchorTarget::Object(id) => match scene.get(id) {
+					Some(object) => (object.transform().to_mat4().w_axis.truncate(), Some(id)),
+					None => continue,
+				},
+				AnchorTarget::Point(point) => (point, None),
+			};
+			let point = target + anchor.offset;
+			let to_point = point - camera_position;
+			let distance = to_point.length();
+			if distance > anchor.max_distance {
+				continue;
+			}
+
+			let pixel = camera::world_to_screen(view_proj, viewport, point);
+			let position = match pixel {
+				Some(pixel) => {
+					let pos = to_points(pixel);
+					match screen.contains(pos) {
+						true => Some(pos),
+						false if anchor.clamp_to_edge => Some(clamp_to_edge(screen, pos)),
+						false => None,
+					}
+				}
+				// behind the camera, mirrored so it sticks to the side it's on
+				None if anchor.clamp_to_edge => {
+					let behind =
+						camera::world_to_screen(view_proj, viewport, camera_position * 2.0 - point);
+					behind.map(|pixel| {
+						let center = screen.center();
+						let mirrored = center - (to_points(pixel) - center);
+						clamp_to_edge(screen, mirrored)
+					})
+				}
+				None => None,
+			};
+			let position = match position {
+				Some(position) => position,
+				None => continue,
+			};
+
+			let occluded = self.occlusion
+				&& distance > f32::EPSILON
+				&& scene
+					.raycast(camera_position, to_point / distance, distance, ignore)
+					.is_some();
+			let target_opacity = match occluded {
+				true => OCCLUDED_OPACITY,
+				false => 1.0,
+			};
+			state.opacity += (target_opacity - state.opacity) * fade;
+
+			draw_content(&painter, position, &anchor.content, state.opacity);
+		}
+	}
+}
+
+/// Moves a point outside `screen` onto its edge, toward the center.
+fn clamp_to_edge(screen: egui::Rect, pos: egui::Pos2) -> egui::Pos2 {
+	let center = screen.center();
+	let direction = Vec2::new(pos.x - center.x, pos.y - center.y);
+	let half = Vec2::new(screen.width(), screen.height()) * 0.5;
+	let scale = (direction.abs() / half).max_element().max(1.0);
+	let clamped = direction / scale;
+	egui::pos2(center.x + clamped.x, center.y + clamped.y)
+}
+
+fn draw_content(painter: &egui::Painter, pos: egui::Pos2, content: &AnchorContent, opacity: f32) {
+	let background = egui::Color32::from_black_alpha(180).linear_multiply(opacity);
+	match content {
+		AnchorContent::Label(text) => {
+			let shape = painter.add(egui::Shape::Noop);
+			let rect = painter.text(
+				pos,
+				egui::Align2::CENTER_BOTTOM,
+				text,
+				egui::TextStyle::Body,
+				egui::Color32::WHITE.linear_multiply(opacity),
+			);
+			painter.set(
+				shape,
+				egui::Shape::rect_filled(rect.expand(3.0), 2.0, background),
+			);
+		}
+		AnchorContent::Bar { value, color } => {
+			let rect = egui::Rect::from_center_size(pos, egui::vec2(60.0, 6.0));
+			painter.rect_filled(rect.expand(1.0), 1.0, background);
+			let mut filled = rect;
+			filled.set_width(rect.width() * value.clamp(0.0, 1.0));
+			painter.rect_filled(filled, 1.0, color.linear_multiply(opacity));
+		}
+	}
+}
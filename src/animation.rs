@@ -0,0 +1,602 @@
+use std::collections::BTreeMap;
+use std::f32::consts::TAU;
+use std::sync::Arc;
+
+use glam::{Mat4, Quat, Vec3, Vec4};
+use rend3::types::{
+	Handedness, MaterialHandle, Mesh, MeshBuilder, Object, ObjectMeshKind, Skeleton,
+};
+use rend3::Renderer;
+
+use crate::assets;
+use crate::blend_space::BlendSpace;
+use crate::scene::{ObjectId, Scene, SceneTransform, Transform};
+use crate::sequencer::{Track, TransformKey};
+use crate::skeleton::{self, Joint, Retarget, RootMotion};
+
+/// A skeleton along with the pose its mesh was bound in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rig {
+	pub joints: Vec<Joint>,
+	/// object space transform of each joint in the bind pose
+	pub rest: Vec<Mat4>,
+}
+
+impl Rig {
+	/// A figure standing on the origin facing +z, with its hips `legs` up
+	/// and `body` from the hips to the top of its head.
+	pub fn humanoid(legs: f32, body: f32) -> Self {
+		let joints = [
+			("hips", None, Vec3::new(0.0, legs, 0.0)),
+			("spine", Some(0), Vec3::new(0.0, body * 0.5, 0.0)),
+			("head", Some(1), Vec3::new(0.0, body * 0.4, 0.0)),
+			(
+				"upper_arm.l",
+				Some(1),
+				Vec3::new(body * 0.3, body * 0.35, 0.0),
+			),
+			("hand.l", Some(3), Vec3::new(0.0, -body * 0.6, 0.0)),
+			(
+				"upper_arm.r",
+				Some(1),
+				Vec3::new(-body * 0.3, body * 0.35, 0.0),
+			),
+			("hand.r", Some(5), Vec3::new(0.0, -body * 0.6, 0.0)),
+			("thigh.l", Some(0), Vec3::new(legs * 0.15, 0.0, 0.0)),
+			("foot.l", Some(7), Vec3::new(0.0, -legs, 0.0)),
+			("thigh.r", Some(0), Vec3::new(-legs * 0.15, 0.0, 0.0)),
+			("foot.r", Some(9), Vec3::new(0.0, -legs, 0.0)),
+		];
+		let local: Vec<Mat4> = joints
+			.iter()
+			.map(|(_, _, offset)| Mat4::from_translation(*offset))
+			.collect();
+		let joints: Vec<Joint> = joints
+			.iter()
+			.map(|(name, parent, _)| Joint {
+				name: (*name).into(),
+				parent: *parent,
+			})
+			.collect();
+		let parents: Vec<_> = joints.iter().map(|joint| joint.parent).collect();
+		Self {
+			rest: skeleton::to_object(&parents, &local),
+			joints,
+		}
+	}
+
+	pub fn parents(&self) -> Vec<Option<usize>> {
+		self.joints.iter().map(|joint| joint.parent).collect()
+	}
+
+	/// Takes object space back into each joint's space as it was bound.
+	pub fn inverse_bind_transforms(&self) -> Vec<Mat4> {
+		self.rest.iter().map(Mat4::inverse).collect()
+	}
+
+	/// A box along each bone, skinned to the joint the bone turns with, and
+	/// one around each joint without children.
+	pub fn mesh(&self, handedness: Handedness) -> Mesh {
+		let cube = assets::cube(handedness);
+		let position = |joint: usize| self.rest[joint].w_axis.truncate();
+		let mut boxes = Vec::new();
+		for (joint, info) in self.joints.iter().enumerate() {
+			if let Some(parent) = info.parent {
+				boxes.push((parent, position(parent), position(joint)));
+			}
+			if !self.joints.iter().any(|child| child.parent == Some(joint)) {
+				let end = position(joint);
+				boxes.push((joint, end, end + Vec3::Y * 0.1));
+			}
+		}
+
+		let mut positions = Vec::new();
+		let mut indices = Vec::new();
+		let mut joint_indices = Vec::new();
+		for (joint, start, end) in boxes {
+			let along = end - start;
+			let length = along.length();
+			if length <= f32::EPSILON {
+				continue;
+			}
+			// the cube spans -1 to 1, stretched along y from start to end
+			let rotation = Quat::from_rotation_arc(Vec3::Y, along / length);
+			let scale = Vec3::new(0.05, length * 0.5, 0.05);
+			let transform =
+				Mat4::from_scale_rotation_translation(scale, rotation, (start + end) * 0.5);
+			let base = positions.len() as u32;
+			positions.extend(
+				cube.vertex_positions
+					.iter()
+					.map(|position| transform.transform_point3(*position)),
+			);
+			indices.extend(cube.indices.iter().map(|index| base + index));
+			joint_indices.extend(
+				cube.vertex_positions
+					.iter()
+					.map(|_| [joint as u16, 0, 0, 0]),
+			);
+		}
+
+		let weights = vec![Vec4::X; positions.len()];
+		MeshBuilder::new(positions, handedness)
+			.with_indices(indices)
+			.with_vertex_joint_indices(joint_indices)
+			.with_vertex_joint_weights(weights)
+			.build()
+			.unwrap()
+	}
+}
+
+/// Keyframed motion of a skeleton, each joint relative to its parent.
+pub struct Clip {
+	pub name: String,
+	/// the skeleton the clip was authored for
+	pub rig: Rig,
+	/// seconds the clip takes to play once
+	pub duration: f32,
+	/// one track for each joint of `rig`, joints without keys stay at rest
+	pub tracks: Vec<Track<TransformKey>>,
+}
+
+impl Clip {
+	/// A walk cycle for a [`Rig::humanoid`], swinging the legs and arms and
+	/// carrying the hips forward a stride and a little to the side each
+	/// loop, so played on its own the figure walks in a circle.
+	pub fn walk(rig: &Rig) -> Self {
+		const STRIDE: f32 = 1.2;
+		const TURN: f32 = 0.4;
+		const KEYS: usize = 8;
+
+		let local = skeleton::to_local(&rig.parents(), &rig.rest);
+		let mut tracks = vec![Track::default(); rig.joints.len()];
+		let find = |name: &str| rig.joints.iter().position(|joint| joint.name == name);
+		let swings = [
+			("thigh.l", 0.5),
+			("thigh.r", -0.5),
+			("upper_arm.l", -0.4),
+			("upper_arm.r", 0.4),
+		];
+		for key in 0..=KEYS {
+			let t = key as f32 / KEYS as f32;
+			let wave = (t * TAU).sin();
+			if let Some(hips) = find("hips") {
+				let (scale, rotation, translation) = local[hips].to_scale_rotation_translation();
+				let bob = Vec3::Y * (t * TAU * 2.0).cos() * 0.03;
+				tracks[hips].insert(
+					t,
+					TransformKey {
+						translation: translation + bob + Vec3::Z * STRIDE * t,
+						rotation: Quat::from_rotation_y(TURN * t) * rotation,
+						scale,
+					},
+				);
+			}
+			for (name, amount) in swings {
+				if let Some(joint) = find(name) {
+					let mut pose = TransformKey::from_mat4(local[joint]);
+					pose.rotation = Quat::from_rotation_x(wave * amount) * pose.rotation;
+					tracks[joint].insert(t, pose);
+				}
+			}
+		}
+
+		Self {
+			name: "walk".into(),
+			rig: rig.clone(),
+			duration: 1.0,
+			tracks,
+		}
+	}
+
+	/// The rig standing at rest, to blend other clips with.
+	pub fn idle(rig: &Rig) -> Self {
+		Self {
+			name: "idle".into(),
+			rig: rig.clone(),
+			duration: 1.0,
+			tracks: vec![Track::default(); rig.joints.len()],
+		}
+	}
+
+	/// Object space pose of the clip's skeleton `time` seconds in.
+	pub fn sample(&self, time: f32) -> Vec<Mat4> {
+		let parents = self.rig.parents();
+		let rest = skeleton::to_local(&parents, &self.rig.rest);
+		let local: Vec<Mat4> = rest
+			.iter()
+			.enumerate()
+			.map(|(joint, rest)| {
+				self.tracks
+					.get(joint)
+					.and_then(|track| track.sample(time))
+					.map_or(*rest, |key| key.to_mat4())
+			})
+			.collect();
+		skeleton::to_object(&parents, &local)
+	}
+}
+
+/// Where a clip is in its playback on one object.
+pub struct ClipPlayer {
+	pub clip: Arc<Clip>,
+	/// seconds into the clip
+	pub time: f32,
+	/// playback rate, 1.0 for as authored
+	pub speed: f32,
+	/// wraps around at the end, otherwise holds the last pose
+	pub looping: bool,
+	/// takes the root's travel out of the clip to move the object by, the
+	/// per clip toggle for it
+	pub root_motion: RootMotion,
+	/// maps the clip's skeleton onto the object's when they differ
+	retarget: Option<Retarget>,
+	/// joints of the skeleton posed, the clip's or the retarget's target
+	joints: Vec<Joint>,
+	/// the pose at `time` has been given to `root_motion`
+	started: bool,
+}
+
+impl ClipPlayer {
+	pub fn new(clip: Arc<Clip>) -> Self {
+		Self {
+			time: 0.0,
+			speed: 1.0,
+			looping: true,
+			root_motion: RootMotion::default(),
+			retarget: None,
+			joints: clip.rig.joints.clone(),
+			started: false,
+			clip,
+		}
+	}
+
+	/// Plays a clip authored for another skeleton on `target`, matching
+	/// joints through `names`, target joint names to the clip's.
+	pub fn retargeted(clip: Arc<Clip>, target: &Rig, names: &BTreeMap<String, String>) -> Self {
+		let retarget = Retarget::new(
+			&clip.rig.joints,
+			&clip.rig.rest,
+			&target.joints,
+			&target.rest,
+			names,
+		);
+		if retarget.unmatched() > 0 {
+			log::warn!(
+				"{} joints don't follow the clip \"{}\"",
+				retarget.unmatched(),
+				clip.name
+			);
+		}
+		Self {
+			retarget: Some(retarget),
+			joints: target.joints.clone(),
+			..Self::new(clip)
+		}
+	}
+
+	/// Moves playback on by `dt` seconds and returns the pose there, for the
+	/// target skeleton when retargeted, along with how far the root moved
+	/// the object, see [`RootMotion::extract`]. Looping carries the root on
+	/// from the end of each loop, so the object doesn't lose or gain travel
+	/// however the steps fall.
+	pub fn advance(&mut self, dt: f32) -> (Vec<Mat4>, Mat4) {
+		let duration = self.clip.duration.max(f32::EPSILON);
+		if !std::mem::replace(&mut self.started, true) {
+			self.root_motion.reset();
+			self.extract(self.time);
+		}
+
+		let time = self.time + dt * self.speed;
+		let mut motion = Mat4::IDENTITY;
+		if self.looping {
+			// playing backwards wraps from the start to the end instead
+			let wraps = (time / duration).floor();
+			let (end, start) = match wraps > 0.0 {
+				true => (duration, 0.0),
+				false => (0.0, duration),
+			};
+			for _ in 0..wraps.abs() as u32 {
+				motion *= self.extract(end);
+				self.root_motion.reset();
+				self.extract(start);
+			}
+			self.time = time.rem_euclid(duration);
+		} else {
+			self.time = time.clamp(0.0, duration);
+		}
+
+		let mut pose = self.pose(self.time);
+		motion *= self.root_motion.extract(&self.joints, &mut pose);
+		(pose, motion)
+	}
+
+	/// Pose `time` seconds into the clip, for the target skeleton when
+	/// retargeted.
+	fn pose(&self, time: f32) -> Vec<Mat4> {
+		let pose = self.clip.sample(time);
+		match &self.retarget {
+			Some(retarget) => retarget.pose(&pose),
+			None => pose,
+		}
+	}
+
+	/// Root motion up to the pose at `time`.
+	fn extract(&mut self, time: f32) -> Mat4 {
+		let mut pose = self.pose(time);
+		self.root_motion.extract(&self.joints, &mut pose)
+	}
+}
+
+/// What poses an animated object.
+enum Source {
+	Clip(ClipPlayer),
+	Blend(BlendSpace),
+}
+
+struct Playing {
+	id: ObjectId,
+	source: Source,
+	inverse_bind_transforms: Vec<Mat4>,
+}
+
+/// Clips playing on the animated objects of a [`Scene`], evaluated every
+/// simulation step into the joints the renderer skins their meshes with and
+/// objects are attached at.
+#[derive(Default)]
+pub struct Animations {
+	playing: Vec<Playing>,
+}
+
+impl Animations {
+	/// Plays a clip on an animated object bound to `rig`, replacing whatever
+	/// played on it, and names its joints in the scene for the skeleton view
+	/// and sockets.
+	pub fn play(&mut self, scene: &mut Scene, id: ObjectId, rig: &Rig, player: ClipPlayer) {
+		self.start(scene, id, rig, Source::Clip(player));
+	}
+
+	/// Plays the clips of a blend space on an animated object bound to
+	/// `rig`, blended by where the space's parameter is, like
+	/// [`Animations::play`].
+	pub fn play_blend(&mut self, scene: &mut Scene, id: ObjectId, rig: &Rig, space: BlendSpace) {
+		self.start(scene, id, rig, Source::Blend(space));
+	}
+
+	fn start(&mut self, scene: &mut Scene, id: ObjectId, rig: &Rig, source: Source) {
+		self.stop(id);
+		scene.set_skeleton(id, rig.joints.clone());
+		self.playing.push(Playing {
+			id,
+			source,
+			inverse_bind_transforms: rig.inverse_bind_transforms(),
+		});
+	}
+
+	/// Leaves the object in its current pose.
+	pub fn stop(&mut self, id: ObjectId) {
+		self.playing.retain(|playing| playing.id != id);
+	}
+
+	pub fn player_mut(&mut self, id: ObjectId) -> Option<&mut ClipPlayer> {
+		match self.source_mut(id)? {
+			Source::Clip(player) => Some(player),
+			Source::Blend(_) => None,
+		}
+	}
+
+	pub fn blend_space_mut(&mut self, id: ObjectId) -> Option<&mut BlendSpace> {
+		match self.source_mut(id)? {
+			Source::Blend(space) => Some(space),
+			Source::Clip(_) => None,
+		}
+	}
+
+	fn source_mut(&mut self, id: ObjectId) -> Option<&mut Source> {
+		self.playing
+			.iter_mut()
+			.find(|playing| playing.id == id)
+			.map(|playing| &mut playing.source)
+	}
+
+	/// Moves every clip and blend space on a step of `dt` seconds and poses
+	/// its object, which moves whatever is attached at its joints. Call
+	/// after [`Scene::step_modifiers`]. Clips on objects that were removed
+	/// stop.
+	pub fn step(&mut self, scene: &mut Scene, dt: f32) {
+		self.playing
+			.retain(|playing| scene.get(playing.id).is_some());
+		for playing in &mut self.playing {
+			let (pose, motion) = match &mut playing.source {
+				Source::Clip(player) => player.advance(dt),
+				Source::Blend(space) => space.pose(dt),
+			};
+			if motion != Mat4::IDENTITY {
+				scene.apply_root_motion(playing.id, motion);
+			}
+			scene.set_joint_transforms(playing.id, &pose, &playing.inverse_bind_transforms);
+		}
+	}
+}
+
+/// Adds an object skinned to `rig`, standing in its rest pose until a clip
+/// is played on it.
+pub fn add_animated(
+	renderer: &Renderer,
+	scene: &mut Scene,
+	name: &str,
+	rig: &Rig,
+	material: MaterialHandle,
+	transform: Transform,
+	handedness: Handedness,
+) -> ObjectId {
+	let mesh = renderer.add_mesh(rig.mesh(handedness));
+	let skeleton = renderer.add_skeleton(Skeleton::from_joint_transforms(
+		mesh,
+		&rig.rest,
+		&rig.inverse_bind_transforms(),
+	));
+	scene.add_object_at(
+		renderer,
+		name,
+		Object {
+			mesh_kind: ObjectMeshKind::Animated(skeleton),
+			material,
+			transform: transform.to_mat4(),
+		},
+		transform,
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn unkeyed_joints_stay_at_rest() {
+		let rig = Rig::humanoid(0.9, 0.8);
+		let mut clip = Clip::walk(&rig);
+		clip.tracks.iter_mut().for_each(|track| track.keys.clear());
+		for time in [0.0, 0.3, 1.0] {
+			for (posed, rest) in clip.sample(time).iter().zip(&rig.rest) {
+				assert!(posed.abs_diff_eq(*rest, 1e-5));
+			}
+		}
+	}
+
+	#[test]
+	fn swinging_a_thigh_carries_the_foot() {
+		let rig = Rig::humanoid(0.9, 0.8);
+		let clip = Clip::walk(&rig);
+		let foot = rig
+			.joints
+			.iter()
+			.position(|joint| joint.name == "foot.l")
+			.unwrap();
+		let thigh = rig.joints[foot].parent.unwrap();
+
+		// a quarter of the way through the left leg is furthest back
+		let pose = clip.sample(0.25);
+		let hip = pose[thigh].w_axis.truncate();
+		let toe = pose[foot].w_axis.truncate();
+		assert!((hip.distance(toe) - 0.9).abs() < 1e-4);
+		assert!(toe.z < hip.z);
+	}
+
+	#[test]
+	fn players_loop_or_hold_the_end() {
+		let rig = Rig::humanoid(0.9, 0.8);
+		let clip = Arc::new(Clip::walk(&rig));
+		let mut player = ClipPlayer::new(clip.clone());
+		player.root_motion.enabled = false;
+		player.advance(1.25);
+		assert!((player.time - 0.25).abs() < 1e-5);
+
+		player.looping = false;
+		let (pose, motion) = player.advance(2.0);
+		assert_eq!(player.time, 1.0);
+		assert_eq!(pose, clip.sample(1.0));
+		assert_eq!(motion, Mat4::IDENTITY);
+	}
+
+	#[test]
+	fn retargeted_players_keep_the_target_proportions() {
+		let rig = Rig::humanoid(0.9, 0.8);
+		let tall = Rig::humanoid(1.2, 0.7);
+		let clip = Arc::new(Clip::walk(&rig));
+		let mut player = ClipPlayer::retargeted(clip, &tall, &BTreeMap::new());
+		let foot = tall
+			.joints
+			.iter()
+			.position(|joint| joint.name == "foot.r")
+			.unwrap();
+		let thigh = tall.joints[foot].parent.unwrap();
+
+		let (pose, _) = player.advance(0.75);
+		assert_eq!(pose.len(), tall.joints.len());
+		let leg = pose[foot].w_axis.truncate() - pose[thigh].w_axis.truncate();
+		assert!((leg.length() - 1.2).abs() < 1e-4);
+		// swung back like the clip's right leg
+		assert!(leg.z < 0.0);
+	}
+
+	/// A clip carrying the hips a unit along z each second while turning
+	/// them `turn` about y.
+	fn travel(turn: f32) -> Clip {
+		let rig = Rig::humanoid(0.9, 0.8);
+		let mut tracks = vec![Track::default(); rig.joints.len()];
+		let hips = TransformKey::from_mat4(rig.rest[0]);
+		tracks[0].insert(0.0, hips);
+		tracks[0].insert(
+			1.0,
+			TransformKey {
+				translation: hips.translation + Vec3::Z,
+				rotation: Quat::from_rotation_y(turn),
+				..hips
+			},
+		);
+		Clip {
+			name: "travel".into(),
+			rig,
+			duration: 1.0,
+			tracks,
+		}
+	}
+
+	#[test]
+	fn root_motion_keeps_the_hips_over_the_origin() {
+		let mut player = ClipPlayer::new(Arc::new(travel(0.5)));
+		for _ in 0..50 {
+			let (pose, _) = player.advance(0.07);
+			let hips = pose[0].w_axis.truncate();
+			assert!(hips.abs_diff_eq(Vec3::Y * 0.9, 1e-5), "{}", hips);
+			let (_, rotation, _) = pose[0].to_scale_rotation_translation();
+			assert!(rotation.abs_diff_eq(Quat::IDENTITY, 1e-5));
+		}
+	}
+
+	#[test]
+	fn looping_root_motion_does_not_drift() {
+		for dt in [1.0 / 60.0, 0.07, 0.3, 2.5f32] {
+			let mut player = ClipPlayer::new(Arc::new(travel(0.0)));
+			let steps = (10.0 / dt).round() as usize;
+			let mut moved = Mat4::IDENTITY;
+			for _ in 0..steps {
+				moved *= player.advance(dt).1;
+			}
+			let expected = steps as f32 * dt;
+			assert!(
+				(moved.w_axis.z - expected).abs() < 1e-3,
+				"{} != {} stepping {}",
+				moved.w_axis.z,
+				expected,
+				dt
+			);
+			assert!(moved.w_axis.x.abs() < 1e-4);
+		}
+	}
+
+	#[test]
+	fn looping_turns_add_up() {
+		let turn = 0.5;
+		let mut player = ClipPlayer::new(Arc::new(travel(turn)));
+		let mut moved = Mat4::IDENTITY;
+		for _ in 0..40 {
+			moved *= player.advance(0.1).1;
+		}
+		// four loops, each a unit forward then a turn
+		let lap = Mat4::from_translation(Vec3::Z) * Mat4::from_rotation_y(turn);
+		assert!(moved.abs_diff_eq(lap * lap * lap * lap, 1e-3), "{}", moved);
+	}
+
+	#[test]
+	fn playing_backwards_walks_back() {
+		let mut player = ClipPlayer::new(Arc::new(travel(0.0)));
+		player.speed = -1.0;
+		let mut moved = Mat4::IDENTITY;
+		for _ in 0..25 {
+			moved *= player.advance(0.1).1;
+		}
+		assert!((moved.w_axis.z + 2.5).abs() < 1e-3, "{}", moved.w_axis.z);
+	}
+}
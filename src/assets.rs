@@ -0,0 +1,512 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use glam::{Mat3, UVec2, Vec3};
+use rend3::types::{
+	Handedness, Mesh, MeshBuilder, MipmapCount, MipmapSource, Texture, TextureFormat,
+};
+
+use crate::tasks::CancelToken;
+
+/// lines parsed between checks for cancellation
+const CANCEL_CHECK_LINES: usize = 4096;
+/// widest or tallest texture that will be loaded, in pixels
+const MAX_TEXTURE_SIZE: u32 = 16384;
+
+/// Why an asset couldn't be loaded.
+#[derive(Debug)]
+pub enum AssetError {
+	Io(io::Error),
+	/// the file was read but its contents aren't usable
+	Invalid(String),
+	/// the load was cancelled through its [`CancelToken`]
+	Cancelled,
+}
+
+impl fmt::Display for AssetError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			AssetError::Io(e) => write!(f, "{}", e),
+			AssetError::Invalid(reason) => write!(f, "{}", reason),
+			AssetError::Cancelled => write!(f, "cancelled"),
+		}
+	}
+}
+
+impl From<io::Error> for AssetError {
+	fn from(e: io::Error) -> Self {
+		AssetError::Io(e)
+	}
+}
+
+fn invalid<T>(reason: impl Into<String>) -> Result<T, AssetError> {
+	Err(AssetError::Invalid(reason.into()))
+}
+
+/// Axis that points up in a source file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpAxis {
+	Y,
+	Z,
+}
+
+/// How files from other tools are converted into the engine's space on
+/// import: meters, y up and the renderer's handedness.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImportSettings {
+	/// meters per unit in the source file
+	pub unit_scale: f32,
+	pub up_axis: UpAxis,
+	/// handedness of the source file
+	pub handedness: Handedness,
+}
+
+impl Default for ImportSettings {
+	fn default() -> Self {
+		Self {
+			unit_scale: 1.0,
+			up_axis: UpAxis::Y,
+			handedness: Handedness::Left,
+		}
+	}
+}
+
+impl ImportSettings {
+	/// Common source units and their size in meters.
+	pub const UNITS: [(&'static str, f32); 5] = [
+		("m", 1.0),
+		("cm", 0.01),
+		("mm", 0.001),
+		("ft", 0.3048),
+		("in", 0.0254),
+	];
+
+	/// Maps source positions into engine space for a renderer of `target`
+	/// handedness. Converting handedness mirrors the mesh, which turns its
+	/// front faces around on screen, see [`reverse_winding`].
+	pub fn transform(&self, target: Handedness) -> Mat3 {
+		// swapping y and z also swaps handedness
+		let (basis, handedness) = match self.up_axis {
+			UpAxis::Y => (Mat3::IDENTITY, self.handedness),
+			UpAxis::Z => (
+				Mat3::from_cols(Vec3::X, Vec3::Z, Vec3::Y),
+				match self.handedness {
+					Handedness::Left => Handedness::Right,
+					Handedness::Right => Handedness::Left,
+				},
+			),
+		};
+		let flip = match handedness == target {
+			true => Mat3::IDENTITY,
+			false => Mat3::from_diagonal(Vec3::new(1.0, 1.0, -1.0)),
+		};
+		Mat3::from_diagonal(Vec3::splat(self.unit_scale)) * flip * basis
+	}
+}
+
+/// Loads a texture, falling back to [`placeholder_texture`] and logging the
+/// error if the file is missing or can't be decoded.
+pub fn load_texture(path: impl AsRef<Path>, srgb: bool) -> Texture {
+	let path = path.as_ref();
+	try_load_texture(path, srgb).unwrap_or_else(|e| {
+		log::error!("failed to load texture {}: {}", path.display(), e);
+		placeholder_texture()
+	})
+}
+
+/// Loads a mesh, falling back to [`placeholder_mesh`] and logging the error
+/// if the file is missing or can't be parsed.
+pub fn load_mesh(
+	path: impl AsRef<Path>,
+	settings: &ImportSettings,
+	handedness: Handedness,
+) -> Mesh {
+	let path = path.as_ref();
+	try_load_mesh(path, settings, handedness, &CancelToken::default()).unwrap_or_else(|e| {
+		log::error!("failed to load mesh {}: {}", path.display(), e);
+		placeholder_mesh(handedness)
+	})
+}
+
+/// Magenta and black checkerboard, hard to mistake for a real texture.
+pub fn placeholder_texture() -> Texture {
+	const SIZE: u32 = 64;
+	const CHECK: u32 = 8;
+
+	let mut data = Vec::with_capacity((SIZE * SIZE * 4) as usize);
+	for y in 0..SIZE {
+		for x in 0..SIZE {
+			let pixel = match (x / CHECK + y / CHECK) % 2 {
+				0 => [255, 0, 255, 255],
+				_ => [0, 0, 0, 255],
+			};
+			data.extend_from_slice(&pixel);
+		}
+	}
+
+	Texture {
+		label: Some("placeholder".into()),
+		data,
+		format: TextureFormat::Rgba8UnormSrgb,
+		size: UVec2::splat(SIZE),
+		mip_count: MipmapCount::Maximum,
+		mip_source: MipmapSource::Generated,
+	}
+}
+
+/// Stands in for meshes that failed to load.
+pub fn placeholder_mesh(handedness: Handedness) -> Mesh {
+	cube(handedness)
+}
+
+/// Cube spanning -1 to 1 on each axis, wound for a renderer of the given
+/// handedness.
+pub fn cube(handedness: Handedness) -> Mesh {
+	let verts = [
+		// far side (0.0, 0.0, 1.0)
+		[-1.0, -1.0, 1.0],
+		[1.0, -1.0, 1.0],
+		[1.0, 1.0, 1.0],
+		[-1.0, 1.0, 1.0],
+		// near side (0.0, 0.0, -1.0)
+		[-1.0, 1.0, -1.0],
+		[1.0, 1.0, -1.0],
+		[1.0, -1.0, -1.0],
+		[-1.0, -1.0, -1.0],
+		// right side (1.0, 0.0, 0.0)
+		[1.0, -1.0, -1.0],
+		[1.0, 1.0, -1.0],
+		[1.0, 1.0, 1.0],
+		[1.0, -1.0, 1.0],
+		// left side (-1.0, 0.0, 0.0)
+		[-1.0, -1.0, 1.0],
+		[-1.0, 1.0, 1.0],
+		[-1.0, 1.0, -1.0],
+		[-1.0, -1.0, -1.0],
+		// top (0.0, 1.0, 0.0)
+		[1.0, 1.0, -1.0],
+		[-1.0, 1.0, -1.0],
+		[-1.0, 1.0, 1.0],
+		[1.0, 1.0, 1.0],
+		// bottom (0.0, -1.0, 0.0)
+		[1.0, -1.0, 1.0],
+		[-1.0, -1.0, 1.0],
+		[-1.0, -1.0, -1.0],
+		[1.0, -1.0, -1.0],
+	];
+
+	// wound for left handed
+	let mut indices = vec![
+		0, 1, 2, 2, 3, 0, // far
+		4, 5, 6, 6, 7, 4, // near
+		8, 9, 10, 10, 11, 8, // right
+		12, 13, 14, 14, 15, 12, // left
+		16, 17, 18, 18, 19, 16, // top
+		20, 21, 22, 22, 23, 20, // bottom
+	];
+
+	if handedness == Handedness::Right {
+		reverse_winding(&mut indices);
+	}
+
+	MeshBuilder::new(verts.map(Vec3::from).to_vec(), handedness)
+		.with_indices(indices)
+		.build()
+		.unwrap()
+}
+
+/// Sphere of radius 1 split into `segments` around and `rings` from pole to
+/// pole, wound for a renderer of the given handedness.
+pub fn sphere(segments: u32, rings: u32, handedness: Handedness) -> Mesh {
+	let mut positions = Vec::new();
+	for ring in 0..=rings {
+		let lat = std::f32::consts::FRAC_PI_2 - std::f32::consts::PI * ring as f32 / rings as f32;
+		for segment in 0..=segments {
+			let lon = std::f32::consts::TAU * segment as f32 / segments as f32;
+			positions.push(Vec3::new(
+				lat.cos() * lon.cos(),
+				lat.sin(),
+				lat.cos() * lon.sin(),
+			));
+		}
+	}
+
+	// wound for left handed
+	let row = segments + 1;
+	let mut indices = Vec::new();
+	for ring in 0..rings {
+		for segment in 0..segments {
+			let a = ring * row + segment;
+			let (b, c, d) = (a + 1, a + row + 1, a + row);
+			indices.extend_from_slice(&[a, b, c, a, c, d]);
+		}
+	}
+
+	if handedness == Handedness::Right {
+		reverse_winding(&mut indices);
+	}
+
+	MeshBuilder::new(positions, handedness)
+		.with_indices(indices)
+		.build()
+		.unwrap()
+}
+
+/// Flips which side of each triangle is the front. Needed whenever a mesh is
+/// mirrored or moved to a renderer of the other handedness, as front faces are
+/// clockwise on screen for a left handed renderer and counter clockwise for a
+/// right handed one.
+pub fn reverse_winding(indices: &mut [u32]) {
+	for triangle in indices.chunks_exact_mut(3) {
+		triangle.swap(1, 2);
+	}
+}
+
+/// Reads an uncompressed 24 or 32 bit bmp.
+pub fn try_load_texture(path: &Path, srgb: bool) -> Result<Texture, AssetError> {
+	let mut texture = decode_bmp(&fs::read(path)?, srgb)?;
+	texture.label = Some(path.display().to_string());
+	Ok(texture)
+}
+
+/// Decodes the contents of a bmp file, checking every size in the header
+/// against the file so a broken or hostile one can't panic or allocate
+/// more than [`MAX_TEXTURE_SIZE`] allows.
+fn decode_bmp(file: &[u8], srgb: bool) -> Result<Texture, AssetError> {
+	let u16_at = |offset: usize| {
+		file.get(offset..offset + 2)
+			.map(|b| u16::from_le_bytes([b[0], b[1]]))
+	};
+	let u32_at = |offset: usize| {
+		file.get(offset..offset + 4)
+			.map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+	};
+
+	if file.get(0..2) != Some(b"BM") {
+		return invalid("not a bmp file");
+	}
+	let (pixel_offset, width, height, bits, compression) =
+		match (u32_at(10), u32_at(18), u32_at(22), u16_at(28), u32_at(30)) {
+			(Some(offset), Some(width), Some(height), Some(bits), Some(compression)) => {
+				(offset as usize, width, height as i32, bits, compression)
+			}
+			_ => return invalid("truncated bmp header"),
+		};
+	if compression != 0 && !(compression == 3 && bits == 32) {
+		return invalid("compressed bmp files aren't supported");
+	}
+	if bits != 24 && bits != 32 {
+		return invalid(format!("{} bit bmp files aren't supported", bits));
+	}
+
+	let rows = height.unsigned_abs();
+	if width == 0 || rows == 0 {
+		return invalid("empty bmp");
+	}
+	if width > MAX_TEXTURE_SIZE || rows > MAX_TEXTURE_SIZE {
+		return invalid(format!(
+			"{}x{} bmp is over the {} pixel limit",
+			width, rows, MAX_TEXTURE_SIZE
+		));
+	}
+	let rows = rows as usize;
+
+	// rows are padded to 4 bytes and stored bottom up unless height is negative
+	let bytes_per_pixel = bits as usize / 8;
+	let too_large = || AssetError::Invalid("bmp sizes overflow".into());
+	let row_bytes = (width as usize)
+		.checked_mul(bytes_per_pixel)
+		.and_then(|bytes| bytes.checked_add(3))
+		.ok_or_else(too_large)?
+		& !3;
+	let pixels_end = row_bytes
+		.checked_mul(rows)
+		.and_then(|bytes| bytes.checked_add(pixel_offset))
+		.ok_or_else(too_large)?;
+	let pixels = match file.get(pixel_offset..pixels_end) {
+		Some(pixels) => pixels,
+		None => return invalid("truncated bmp pixel data"),
+	};
+
+	let capacity = (width as usize)
+		.checked_mul(rows)
+		.and_then(|pixels| pixels.checked_mul(4))
+		.ok_or_else(too_large)?;
+	let mut data = Vec::with_capacity(capacity);
+	for y in 0..rows {
+		let row = match height < 0 {
+			true => y,
+			false => rows - 1 - y,
+		};
+		let row = &pixels[row * row_bytes..][..width as usize * bytes_per_pixel];
+		for bgr in row.chunks(bytes_per_pixel) {
+			let alpha = if bytes_per_pixel == 4 { bgr[3] } else { 255 };
+			data.extend_from_slice(&[bgr[2], bgr[1], bgr[0], alpha]);
+		}
+	}
+
+	Ok(Texture {
+		label: None,
+		data,
+		format: match srgb {
+			true => TextureFormat::Rgba8UnormSrgb,
+			false => TextureFormat::Rgba8Unorm,
+		},
+		size: UVec2::new(width, rows as u32),
+		mip_count: MipmapCount::Maximum,
+		mip_source: MipmapSource::Generated,
+	})
+}
+
+/// Reads the positions and faces of a wavefront obj, triangulating polygons
+/// as fans, and converts them into engine space. Stops with
+/// [`AssetError::Cancelled`] soon after `cancel` is cancelled.
+pub fn try_load_mesh(
+	path: &Path,
+	settings: &ImportSettings,
+	handedness: Handedness,
+	cancel: &CancelToken,
+) -> Result<Mesh, AssetError> {
+	let text = fs::read_to_string(path)?;
+
+	let mut positions = Vec::new();
+	let mut indices = Vec::new();
+	for (line_number, line) in text.lines().enumerate() {
+		if line_number % CANCEL_CHECK_LINES == 0 && cancel.is_cancelled() {
+			return Err(AssetError::Cancelled);
+		}
+		let mut parts = line.split_whitespace();
+		match parts.next() {
+			Some("v") => {
+				let mut coords = [0.0; 3];
+				for coord in &mut coords {
+					*coord = match parts.next().map(str::parse) {
+						Some(Ok(value)) => value,
+						_ => return invalid(format!("bad vertex on line {}", line_number + 1)),
+					};
+				}
+				positions.push(Vec3::from(coords));
+			}
+			Some("f") => {
+				let mut face = Vec::new();
+				for vertex in parts {
+					// only the position index of `v/vt/vn` is used
+					let index: i64 = match vertex.split('/').next().map(str::parse) {
+						Some(Ok(index)) => index,
+						_ => return invalid(format!("bad face on line {}", line_number + 1)),
+					};
+					// 1 based, negative counts back from the last vertex
+					let index = match index {
+						i if i > 0 => i - 1,
+						i => positions.len() as i64 + i,
+					};
+					if index < 0 || index >= positions.len() as i64 {
+						return invalid(format!(
+							"face index out of range on line {}",
+							line_number + 1
+						));
+					}
+					face.push(index as u32);
+				}
+				for i in 1..face.len().saturating_sub(1) {
+					indices.extend_from_slice(&[face[0], face[i], face[i + 1]]);
+				}
+			}
+			_ => {}
+		}
+	}
+
+	if indices.is_empty() {
+		return invalid("no faces");
+	}
+
+	let transform = settings.transform(handedness);
+	for position in &mut positions {
+		*position = transform * *position;
+	}
+	if transform.determinant() < 0.0 {
+		reverse_winding(&mut indices);
+	}
+
+	MeshBuilder::new(positions, handedness)
+		.with_indices(indices)
+		.build()
+		.or_else(|e| invalid(format!("{:?}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A bmp with a 54 byte header and `pixels` after it.
+	fn bmp(width: u32, height: i32, bits: u16, pixels: &[u8]) -> Vec<u8> {
+		let mut file = b"BM".to_vec();
+		file.extend_from_slice(&(54 + pixels.len() as u32).to_le_bytes());
+		file.extend_from_slice(&[0; 4]);
+		file.extend_from_slice(&54u32.to_le_bytes());
+		file.extend_from_slice(&40u32.to_le_bytes());
+		file.extend_from_slice(&width.to_le_bytes());
+		file.extend_from_slice(&height.to_le_bytes());
+		file.extend_from_slice(&1u16.to_le_bytes());
+		file.extend_from_slice(&bits.to_le_bytes());
+		file.extend_from_slice(&[0; 24]);
+		file.extend_from_slice(pixels);
+		file
+	}
+
+	fn assert_invalid(file: &[u8]) {
+		match decode_bmp(file, true) {
+			Err(AssetError::Invalid(_)) => {}
+			Err(e) => panic!("expected an invalid file, got {}", e),
+			Ok(_) => panic!("expected an invalid file, got a texture"),
+		}
+	}
+
+	#[test]
+	fn decodes_padded_rows_bottom_up() {
+		// two rows of two bgr pixels padded to eight bytes, bottom row first
+		let pixels = [
+			0, 0, 255, 0, 255, 0, 0, 0, //
+			255, 0, 0, 255, 255, 255, 0, 0,
+		];
+		let texture = decode_bmp(&bmp(2, 2, 24, &pixels), false).unwrap();
+		assert_eq!(texture.size, UVec2::new(2, 2));
+		assert_eq!(
+			texture.data,
+			[
+				0, 0, 255, 255, 255, 255, 255, 255, //
+				255, 0, 0, 255, 0, 255, 0, 255,
+			]
+		);
+	}
+
+	#[test]
+	fn rejects_truncated_files() {
+		let file = bmp(2, 2, 32, &[0; 16]);
+		assert!(decode_bmp(&file, true).is_ok());
+		for length in [0, 2, 20, 33, 54, 60, file.len() - 1] {
+			assert_invalid(&file[..length]);
+		}
+	}
+
+	#[test]
+	fn rejects_oversized_headers() {
+		assert_invalid(&bmp(u32::MAX, 1, 32, &[0; 16]));
+		assert_invalid(&bmp(1, i32::MIN, 32, &[0; 16]));
+		assert_invalid(&bmp(MAX_TEXTURE_SIZE + 1, 1, 24, &[0; 16]));
+		assert_invalid(&bmp(0, 4, 24, &[0; 16]));
+		// the largest allowed size, with nowhere near the pixels it claims
+		assert_invalid(&bmp(
+			MAX_TEXTURE_SIZE,
+			-(MAX_TEXTURE_SIZE as i32),
+			32,
+			&[0; 16],
+		));
+
+		let mut far_pixels = bmp(1, 1, 32, &[0; 4]);
+		far_pixels[10..14].copy_from_slice(&u32::MAX.to_le_bytes());
+		assert_invalid(&far_pixels);
+	}
+}
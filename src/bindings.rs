@@ -0,0 +1,438 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use toml::value::{Table, Value};
+use winit::event::{
+	ElementState, Event, KeyboardInput, MouseButton, ScanCode, VirtualKeyCode, WindowEvent,
+};
+
+use crate::config::ConfigError;
+
+/// File the bindings are kept in, next to where the app's run from.
+pub const BINDINGS_PATH: &str = "bindings.toml";
+
+/// A key to bind. Keys with a scancode are matched by where they are on the
+/// keyboard, so movement stays on the keys under the left hand on AZERTY
+/// and Dvorak, and keys without one by what they type, which suits
+/// shortcuts named after their letter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+	pub scancode: Option<ScanCode>,
+	/// what the key types, on a US layout when there's a scancode
+	pub keycode: VirtualKeyCode,
+}
+
+impl KeyBinding {
+	/// The key where `keycode` is on a US layout. Falls back to matching by
+	/// what it types for keys without a known scancode.
+	pub fn physical(keycode: VirtualKeyCode) -> Self {
+		Self {
+			scancode: us_scancode(keycode),
+			keycode,
+		}
+	}
+
+	/// Whichever key types `keycode` on the current layout.
+	pub fn logical(keycode: VirtualKeyCode) -> Self {
+		Self {
+			scancode: None,
+			keycode,
+		}
+	}
+}
+
+/// Scancodes of the movement keys on a US keyboard. winit passes the
+/// platform's own codes through, which are the same set 1 codes on windows
+/// and linux and differ on macos.
+fn us_scancode(keycode: VirtualKeyCode) -> Option<ScanCode> {
+	#[cfg(not(target_os = "macos"))]
+	let scancode = match keycode {
+		VirtualKeyCode::Q => 0x10,
+		VirtualKeyCode::W => 0x11,
+		VirtualKeyCode::E => 0x12,
+		VirtualKeyCode::A => 0x1e,
+		VirtualKeyCode::S => 0x1f,
+		VirtualKeyCode::D => 0x20,
+		VirtualKeyCode::Z => 0x2c,
+		VirtualKeyCode::C => 0x2e,
+		_ => return None,
+	};
+	#[cfg(target_os = "macos")]
+	let scancode = match keycode {
+		VirtualKeyCode::A => 0x00,
+		VirtualKeyCode::S => 0x01,
+		VirtualKeyCode::D => 0x02,
+		VirtualKeyCode::Z => 0x06,
+		VirtualKeyCode::C => 0x08,
+		VirtualKeyCode::Q => 0x0c,
+		VirtualKeyCode::W => 0x0d,
+		VirtualKeyCode::E => 0x0e,
+		_ => return None,
+	};
+	Some(scancode)
+}
+
+/// Something an action can be bound to. Gamepads aren't read yet, so they
+/// can't be bound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+	Key(KeyBinding),
+	Mouse(MouseButton),
+}
+
+impl Binding {
+	pub fn label(&self) -> String {
+		match self {
+			Binding::Key(key) => format!("{:?}", key.keycode),
+			Binding::Mouse(MouseButton::Other(button)) => format!("mouse {}", button),
+			Binding::Mouse(button) => format!("mouse {:?}", button).to_lowercase(),
+		}
+	}
+
+	fn to_toml(self) -> Value {
+		let mut table = Table::new();
+		match self {
+			Binding::Key(key) => {
+				if let Ok(keycode) = Value::try_from(key.keycode) {
+					table.insert("key".into(), keycode);
+				}
+				if let Some(scancode) = key.scancode {
+					table.insert("scancode".into(), Value::Integer(scancode.into()));
+				}
+			}
+			// extra buttons by number, the rest by name
+			Binding::Mouse(MouseButton::Other(button)) => {
+				table.insert("mouse".into(), Value::Integer(button.into()));
+			}
+			Binding::Mouse(button) => {
+				if let Ok(button) = Value::try_from(button) {
+					table.insert("mouse".into(), button);
+				}
+			}
+		}
+		Value::Table(table)
+	}
+
+	fn from_toml(value: &Value) -> Option<Self> {
+		if let Some(button) = value.get("mouse") {
+			let button = match button.as_integer() {
+				Some(button) => MouseButton::Other(button.try_into().ok()?),
+				None => button.clone().try_into().ok()?,
+			};
+			return Some(Binding::Mouse(button));
+		}
+		Some(Binding::Key(KeyBinding {
+			scancode: match value.get("scancode") {
+				Some(scancode) => Some(ScanCode::try_from(scancode.as_integer()?).ok()?),
+				None => None,
+			},
+			keycode: value.get("key")?.clone().try_into().ok()?,
+		}))
+	}
+}
+
+/// Names of the actions the app asks the input manager about.
+pub mod actions {
+	pub const MOVE_FORWARD: &str = "move_forward";
+	pub const MOVE_BACK: &str = "move_back";
+	pub const MOVE_LEFT: &str = "move_left";
+	pub const MOVE_RIGHT: &str = "move_right";
+	pub const MOVE_UP: &str = "move_up";
+	pub const MOVE_DOWN: &str = "move_down";
+	pub const ROLL_LEFT: &str = "roll_left";
+	pub const ROLL_RIGHT: &str = "roll_right";
+	pub const SPRINT: &str = "sprint";
+	pub const SLOW: &str = "slow";
+	pub const LOOK: &str = "look";
+	/// moves the point the orbit camera turns around
+	pub const PAN: &str = "pan";
+	/// switches between flying and orbiting
+	pub const TOGGLE_ORBIT: &str = "toggle_orbit";
+	/// switches between perspective and orthographic
+	pub const TOGGLE_ORTHOGRAPHIC: &str = "toggle_orthographic";
+	/// orthographic views along the world axes
+	pub const VIEW_FRONT: &str = "view_front";
+	pub const VIEW_TOP: &str = "view_top";
+	pub const VIEW_SIDE: &str = "view_side";
+}
+
+/// Which inputs trigger each logical action, so code asks whether
+/// `move_forward` is down rather than W. An action is down while any of its
+/// bindings is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActionBindings {
+	bindings: HashMap<String, Vec<Binding>>,
+}
+
+impl ActionBindings {
+	/// No actions bound.
+	pub fn empty() -> Self {
+		Self {
+			bindings: HashMap::new(),
+		}
+	}
+
+	pub fn get(&self, action: &str) -> &[Binding] {
+		self.bindings.get(action).map_or(&[], Vec::as_slice)
+	}
+
+	pub fn bind(&mut self, action: &str, binding: Binding) {
+		let bindings = self.bindings.entry(action.into()).or_default();
+		if !bindings.contains(&binding) {
+			bindings.push(binding);
+		}
+	}
+
+	pub fn unbind(&mut self, action: &str, binding: &Binding) {
+		if let Some(bindings) = self.bindings.get_mut(action) {
+			bindings.retain(|bound| bound != binding);
+		}
+	}
+
+	pub fn actions(&self) -> impl Iterator<Item = &str> {
+		self.bindings.keys().map(String::as_str)
+	}
+
+	fn to_toml(&self) -> Value {
+		let actions: Table = self
+			.bindings
+			.iter()
+			.map(|(action, bindings)| {
+				let bindings = bindings.iter().map(|binding| binding.to_toml()).collect();
+				(action.clone(), Value::Array(bindings))
+			})
+			.collect();
+		Value::Table(actions)
+	}
+
+	/// Bindings from the bindings file, with the defaults for actions it
+	/// doesn't mention so actions added since it was saved are bound.
+	/// Bindings that can't be read are skipped.
+	fn from_toml(value: &Value) -> Option<Self> {
+		let mut bindings = Self::default();
+		for (action, saved) in value.as_table()? {
+			let saved = saved.as_array()?;
+			let parsed: Vec<Binding> = saved.iter().filter_map(Binding::from_toml).collect();
+			if parsed.len() < saved.len() {
+				log::warn!("skipped invalid bindings of {}", action);
+			}
+			bindings.bindings.insert(action.clone(), parsed);
+		}
+		Some(bindings)
+	}
+
+	/// Reads the bindings from a file like [`BINDINGS_PATH`]. `None` if the
+	/// file is missing or isn't toml.
+	pub fn load(path: &Path) -> Option<Self> {
+		let text = fs::read_to_string(path).ok()?;
+		Self::from_toml(&toml::from_str(&text).ok()?)
+	}
+
+	/// Writes every action's bindings to a file like [`BINDINGS_PATH`].
+	pub fn save(&self, path: &Path) -> Result<(), ConfigError> {
+		let text = toml::to_string_pretty(&self.to_toml())
+			.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+		fs::write(path, text)?;
+		Ok(())
+	}
+}
+
+impl Default for ActionBindings {
+	fn default() -> Self {
+		let key = |keycode| Binding::Key(KeyBinding::physical(keycode));
+		let logical = |keycode| Binding::Key(KeyBinding::logical(keycode));
+		let mut bindings = Self::empty();
+		bindings.bind(actions::MOVE_FORWARD, key(VirtualKeyCode::W));
+		bindings.bind(actions::MOVE_BACK, key(VirtualKeyCode::S));
+		bindings.bind(actions::MOVE_LEFT, key(VirtualKeyCode::A));
+		bindings.bind(actions::MOVE_RIGHT, key(VirtualKeyCode::D));
+		bindings.bind(actions::MOVE_UP, key(VirtualKeyCode::E));
+		bindings.bind(actions::MOVE_DOWN, key(VirtualKeyCode::C));
+		bindings.bind(actions::ROLL_LEFT, key(VirtualKeyCode::Q));
+		bindings.bind(actions::ROLL_RIGHT, key(VirtualKeyCode::Z));
+		bindings.bind(actions::SPRINT, logical(VirtualKeyCode::LShift));
+		bindings.bind(actions::SPRINT, logical(VirtualKeyCode::RShift));
+		bindings.bind(actions::SLOW, logical(VirtualKeyCode::LControl));
+		bindings.bind(actions::SLOW, logical(VirtualKeyCode::RControl));
+		bindings.bind(actions::LOOK, Binding::Mouse(MouseButton::Right));
+		bindings.bind(actions::PAN, Binding::Mouse(MouseButton::Middle));
+		bindings.bind(actions::TOGGLE_ORBIT, logical(VirtualKeyCode::O));
+		bindings.bind(actions::TOGGLE_ORTHOGRAPHIC, key(VirtualKeyCode::Numpad5));
+		bindings.bind(actions::VIEW_FRONT, key(VirtualKeyCode::Numpad1));
+		bindings.bind(actions::VIEW_SIDE, key(VirtualKeyCode::Numpad3));
+		bindings.bind(actions::VIEW_TOP, key(VirtualKeyCode::Numpad7));
+		bindings
+	}
+}
+
+/// Lists every action's bindings to change them while the app runs. Adding a
+/// binding waits for the next key or mouse button pressed.
+#[derive(Default)]
+pub struct BindingsWindow {
+	pub open: bool,
+	/// action the next press gets bound to
+	listening: Option<String>,
+	status: Option<String>,
+}
+
+impl BindingsWindow {
+	/// Binds the next key or button pressed while listening, escape stops
+	/// listening. Returns whether the event was used up and shouldn't reach
+	/// the rest of the app.
+	pub fn handle_event<T>(&mut self, event: &Event<T>, bindings: &mut ActionBindings) -> bool {
+		let action = match &self.listening {
+			Some(action) => action,
+			None => return false,
+		};
+		let binding = match event {
+			Event::WindowEvent {
+				event:
+					WindowEvent::KeyboardInput {
+						input:
+							KeyboardInput {
+								state: ElementState::Pressed,
+								virtual_keycode: Some(keycode),
+								scancode,
+								..
+							},
+						..
+					},
+				..
+			} => match keycode {
+				VirtualKeyCode::Escape => None,
+				// bound by position like the movement keys
+				keycode => Some(Binding::Key(KeyBinding {
+					scancode: Some(*scancode),
+					keycode: *keycode,
+				})),
+			},
+			Event::WindowEvent {
+				event:
+					WindowEvent::MouseInput {
+						state: ElementState::Pressed,
+						button,
+						..
+					},
+				..
+			} => Some(Binding::Mouse(*button)),
+			_ => return false,
+		};
+		if let Some(binding) = binding {
+			bindings.bind(action, binding);
+		}
+		self.listening = None;
+		true
+	}
+
+	pub fn window(&mut self, ctx: &egui::CtxRef, bindings: &mut ActionBindings, path: &Path) {
+		let mut open = self.open;
+		egui::Window::new("key bindings")
+			.open(&mut open)
+			.resizable(false)
+			.show(ctx, |ui| {
+				let mut actions: Vec<String> = bindings.actions().map(String::from).collect();
+				actions.sort_unstable();
+				egui::Grid::new("bindings_grid").show(ui, |ui| {
+					for action in &actions {
+						ui.label(action);
+						ui.horizontal(|ui| {
+							for binding in bindings.get(action).to_vec() {
+								if ui
+									.small_button(binding.label())
+									.on_hover_text("click to unbind")
+									.clicked()
+								{
+									bindings.unbind(action, &binding);
+								}
+							}
+							match &self.listening {
+								Some(listening) if listening == action => {
+									ui.label("press a key or button, escape to stop");
+								}
+								_ => {
+									if ui.small_button("+").clicked() {
+										self.listening = Some(action.clone());
+									}
+								}
+							}
+						});
+						ui.end_row();
+					}
+				});
+				ui.horizontal(|ui| {
+					if ui.button("save").clicked() {
+						self.status = Some(match bindings.save(path) {
+							Ok(()) => format!("saved to {}", path.display()),
+							Err(e) => format!("couldn't save: {}", e),
+						});
+					}
+					if ui.button("reset to defaults").clicked() {
+						*bindings = ActionBindings::default();
+						self.listening = None;
+					}
+				});
+				if let Some(status) = &self.status {
+					ui.label(status);
+				}
+			});
+		self.open = open;
+		if !self.open {
+			self.listening = None;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn bindings_round_trip_through_a_file() {
+		let path = std::env::temp_dir().join(format!("opal-bindings-{}.toml", std::process::id()));
+		let mut bindings = ActionBindings::default();
+		bindings.unbind(
+			actions::MOVE_UP,
+			&Binding::Key(KeyBinding::physical(VirtualKeyCode::E)),
+		);
+		bindings.bind(
+			actions::MOVE_UP,
+			Binding::Key(KeyBinding::logical(VirtualKeyCode::Space)),
+		);
+		bindings.bind(actions::LOOK, Binding::Mouse(MouseButton::Other(4)));
+		bindings.bind(
+			"custom",
+			Binding::Key(KeyBinding::physical(VirtualKeyCode::W)),
+		);
+
+		bindings.save(&path).unwrap();
+		assert_eq!(ActionBindings::load(&path), Some(bindings));
+		fs::remove_file(&path).unwrap();
+	}
+
+	#[test]
+	fn unmentioned_actions_keep_their_defaults() {
+		let text = r#"
+			move_forward = [{ key = "Up" }]
+			roll_left = []
+			look = [{ mouse = "Left" }, { key = "Nope" }]
+		"#;
+		let bindings = ActionBindings::from_toml(&toml::from_str(text).unwrap()).unwrap();
+		let defaults = ActionBindings::default();
+		assert_eq!(
+			bindings.get(actions::MOVE_FORWARD),
+			[Binding::Key(KeyBinding::logical(VirtualKeyCode::Up))]
+		);
+		assert_eq!(bindings.get(actions::ROLL_LEFT), []);
+		// bindings that can't be read are dropped, the rest kept
+		assert_eq!(
+			bindings.get(actions::LOOK),
+			[Binding::Mouse(MouseButton::Left)]
+		);
+		assert_eq!(
+			bindings.get(actions::MOVE_BACK),
+			defaults.get(actions::MOVE_BACK)
+		);
+	}
+}
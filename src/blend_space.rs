@@ -0,0 +1,619 @@
+use std::sync::Arc;
+
+use glam::{Mat4, Quat, Vec2, Vec3, Vec4};
+
+use crate::animation::Clip;
+use crate::skeleton::RootMotion;
+
+/// How many parameters a blend space places its clips by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendAxes {
+	/// speed alone, along x
+	One,
+	/// sideways speed along x and forward speed along y
+	Two,
+}
+
+impl BlendAxes {
+	pub const ALL: [Self; 2] = [Self::One, Self::Two];
+
+	pub fn label(&self) -> &'static str {
+		match self {
+			Self::One => "1d, speed",
+			Self::Two => "2d, velocity",
+		}
+	}
+}
+
+/// A clip placed in a blend space.
+#[derive(Clone)]
+pub struct BlendSample {
+	pub name: String,
+	pub clip: Arc<Clip>,
+	/// parameters where the clip plays alone
+	pub position: Vec2,
+	/// seconds the clip takes to loop once here, however long it was
+	/// authored to be
+	pub duration: f32,
+}
+
+impl BlendSample {
+	/// Pose `time` seconds into a loop of the sample.
+	fn pose(&self, time: f32) -> Vec<Mat4> {
+		let duration = self.duration.max(f32::EPSILON);
+		self.clip.sample(time / duration * self.clip.duration)
+	}
+}
+
+/// Clips placed by the speed and direction they move at, blended by how
+/// close the parameters are to each, for locomotion that picks between idle,
+/// walking, running and strafing on its own. The clips share one phase, so
+/// feet blended from a walk and a run land together however long each clip
+/// is. One dimensional spaces blend the two clips either side of the speed,
+/// two dimensional ones use gradient band interpolation, which doesn't need
+/// the clips on a grid.
+///
+/// Played on an object by [`crate::animation::Animations::play_blend`], which
+/// poses it with [`BlendSpace::pose`] every step.
+pub struct BlendSpace {
+	pub open: bool,
+	pub axes: BlendAxes,
+	pub samples: Vec<BlendSample>,
+	/// where in the space the blend is taken, speed in x for one dimension
+	pub parameter: Vec2,
+	/// takes the root's travel out of the blended pose to move the object by
+	pub root_motion: RootMotion,
+	/// clips samples can be added with, by name
+	clips: Vec<Arc<Clip>>,
+	/// how far through a loop the clips are, 0.0 to 1.0
+	phase: f32,
+	/// index into `clips` of the clip the add button places
+	new_clip: usize,
+	/// the pose at `phase` has been given to `root_motion`
+	started: bool,
+}
+
+impl BlendSpace {
+	/// A space with idle, walking and running clips laid out along `axes`,
+	/// and strafing to either side in two dimensions, from the clips of
+	/// those names. Samples whose clip is missing are left out.
+	pub fn new(axes: BlendAxes, clips: Vec<Arc<Clip>>) -> Self {
+		Self {
+			open: false,
+			axes,
+			samples: Self::layout(axes, &clips),
+			parameter: Vec2::ZERO,
+			root_motion: RootMotion::default(),
+			clips,
+			phase: 0.0,
+			new_clip: 0,
+			started: false,
+		}
+	}
+
+	/// The default samples for `axes`, with speed along x in one dimension
+	/// and forward speed along y in two.
+	fn layout(axes: BlendAxes, clips: &[Arc<Clip>]) -> Vec<BlendSample> {
+		let sample = |name: &str, x, y, duration| {
+			let named = |name: &str| clips.iter().find(|clip| clip.name == name);
+			// without run or strafe clips the walk stands in for them
+			let clip = match name {
+				"idle" | "walk" => named(name),
+				_ => named(name).or_else(|| named("walk")),
+			}?;
+			Some(BlendSample {
+				name: name.into(),
+				clip: clip.clone(),
+				position: Vec2::new(x, y),
+				duration,
+			})
+		};
+		let samples = match axes {
+			BlendAxes::One => vec![
+				sample("idle", 0.0, 0.0, 2.0),
+				sample("walk", 1.5, 0.0, 1.1),
+				sample("run", 4.0, 0.0, 0.7),
+			],
+			BlendAxes::Two => vec![
+				sample("idle", 0.0, 0.0, 2.0),
+				sample("walk", 0.0, 1.5, 1.1),
+				sample("run", 0.0, 4.0, 0.7),
+				sample("strafe left", -1.5, 0.0, 1.1),
+				sample("strafe right", 1.5, 0.0, 1.1),
+			],
+		};
+		samples.into_iter().flatten().collect()
+	}
+
+	/// Weight of each sample at `parameter`, summing to 1.0. All zero when
+	/// there are no samples.
+	pub fn weights(&self, parameter: Vec2) -> Vec<f32> {
+		let mut weights = match self.axes {
+			BlendAxes::One => self.weights_1d(parameter.x),
+			BlendAxes::Two => self.weights_2d(parameter),
+		};
+		let total: f32 = weights.iter().sum();
+		if total > 0.0 {
+			weights.iter_mut().for_each(|weight| *weight /= total);
+		}
+		weights
+	}
+
+	/// Linear between the samples either side, held past the ends. Of
+	/// samples at the same speed the first listed plays.
+	fn weights_1d(&self, speed: f32) -> Vec<f32> {
+		let mut weights = vec![0.0; self.samples.len()];
+		// reversed so ties go to the first sample, max_by keeps the last
+		let below = self
+			.samples
+			.iter()
+			.enumerate()
+			.rev()
+			.filter(|(_, sample)| sample.position.x <= speed)
+			.max_by(|(_, a), (_, b)| a.position.x.total_cmp(&b.position.x));
+		let above = self
+			.samples
+			.iter()
+			.enumerate()
+			.filter(|(_, sample)| sample.position.x > speed)
+			.min_by(|(_, a), (_, b)| a.position.x.total_cmp(&b.position.x));
+		match (below, above) {
+			(Some((below, low)), Some((above, high))) => {
+				let t = (speed - low.position.x) / (high.position.x - low.position.x);
+				weights[below] = 1.0 - t;
+				weights[above] = t;
+			}
+			(Some((only, _)), None) | (None, Some((only, _))) => weights[only] = 1.0,
+			(None, None) => {}
+		}
+		weights
+	}
+
+	/// Each sample weighs how far the parameter is from it towards every
+	/// other sample, taking the least.
+	fn weights_2d(&self, parameter: Vec2) -> Vec<f32> {
+		self.samples
+			.iter()
+			.enumerate()
+			.map(|(i, sample)| {
+				let offset = parameter - sample.position;
+				self.samples
+					.iter()
+					.enumerate()
+					.filter(|(j, _)| *j != i)
+					.map(|(_, other)| {
+						let towards = other.position - sample.position;
+						match towards.length_squared() > f32::EPSILON {
+							true => (1.0 - offset.dot(towards) / towards.length_squared())
+								.clamp(0.0, 1.0),
+							false => 1.0,
+						}
+					})
+					.fold(1.0, f32::min)
+			})
+			.collect()
+	}
+
+	/// Moves the shared phase on by `dt` seconds at the pace of the blended
+	/// clips. Returns the weight of each sample and the time into its clip
+	/// to sample it at.
+	pub fn advance(&mut self, dt: f32) -> Vec<(f32, f32)> {
+		let weights = self.weights(self.parameter);
+		let duration: f32 = weights
+			.iter()
+			.zip(&self.samples)
+			.map(|(weight, sample)| weight * sample.duration)
+			.sum();
+		if duration > f32::EPSILON {
+			self.phase = (self.phase + dt / duration).fract();
+		}
+		weights
+			.into_iter()
+			.zip(&self.samples)
+			.map(|(weight, sample)| (weight, self.phase * sample.duration))
+			.collect()
+	}
+
+	/// Moves the phase on like [`BlendSpace::advance`] and blends the clips'
+	/// poses there, returning the object space pose with the root's travel
+	/// taken out and how far that moved the object, as
+	/// [`crate::animation::ClipPlayer::advance`] does for a single clip.
+	pub fn pose(&mut self, dt: f32) -> (Vec<Mat4>, Mat4) {
+		let joints = match self.samples.first() {
+			Some(sample) => sample.clip.rig.joints.clone(),
+			None => return (Vec::new(), Mat4::IDENTITY),
+		};
+		let weights = self.weights(self.parameter);
+		if !std::mem::replace(&mut self.started, true) {
+			self.root_motion.reset();
+			self.root_motion
+				.extract(&joints, &mut self.blend(&weights, self.phase));
+		}
+		let before = self.phase;
+		self.advance(dt);
+		let mut motion = Mat4::IDENTITY;
+		// carries the root on from the end of the loop into the next one
+		if self.phase < before {
+			motion = self
+				.root_motion
+				.extract(&joints, &mut self.blend(&weights, 1.0));
+			self.root_motion.reset();
+			self.root_motion
+				.extract(&joints, &mut self.blend(&weights, 0.0));
+		}
+		let mut pose = self.blend(&weights, self.phase);
+		motion *= self.root_motion.extract(&joints, &mut pose);
+		(pose, motion)
+	}
+
+	/// The samples' poses `phase` through a loop, blended by `weights`.
+	fn blend(&self, weights: &[f32], phase: f32) -> Vec<Mat4> {
+		let poses: Vec<Vec<Mat4>> = self
+			.samples
+			.iter()
+			.zip(weights)
+			.map(|(sample, weight)| match *weight > 0.0 {
+				true => sample.pose(phase * sample.duration),
+				false => Vec::new(),
+			})
+			.collect();
+		let poses: Vec<&[Mat4]> = poses.iter().map(Vec::as_slice).collect();
+		blend_poses(&poses, weights)
+	}
+
+	/// Places the samples and the parameter, by dragging the parameter or
+	/// editing the list below.
+	pub fn window(&mut self, ctx: &egui::CtxRef) {
+		let mut open = self.open;
+		egui::Window::new("blend space")
+			.open(&mut open)
+			.resizable(false)
+			.show(ctx, |ui| {
+				let mut axes = self.axes;
+				egui::ComboBox::from_label("axes")
+					.selected_text(axes.label())
+					.show_ui(ui, |ui| {
+						for option in BlendAxes::ALL {
+							ui.selectable_value(&mut axes, option, option.label());
+						}
+					});
+				// samples placed for the other axes would be in the wrong spots
+				if axes != self.axes {
+					*self = Self {
+						open: self.open,
+						..Self::new(axes, self.clips.clone())
+					};
+				}
+				self.plot(ui);
+				ui.label(format!("phase {:.2}", self.phase));
+
+				let weights = self.weights(self.parameter);
+				let mut removed = None;
+				egui::Grid::new("blend_space_samples").show(ui, |ui| {
+					ui.label("clip");
+					ui.label("position");
+					ui.label("duration");
+					ui.label("weight");
+					ui.end_row();
+					for (index, sample) in self.samples.iter_mut().enumerate() {
+						ui.add(egui::TextEdit::singleline(&mut sample.name).desired_width(80.0));
+						ui.horizontal(|ui| {
+							ui.add(egui::DragValue::new(&mut sample.position.x).speed(0.05));
+							if self.axes == BlendAxes::Two {
+								ui.add(egui::DragValue::new(&mut sample.position.y).speed(0.05));
+							}
+						});
+						ui.add(
+							egui::DragValue::new(&mut sample.duration)
+								.speed(0.01)
+								.clamp_range(0.01..=60.0)
+								.suffix(" s"),
+						);
+						ui.label(format!("{:.2}", weights[index]));
+						if ui.small_button("x").on_hover_text("remove").clicked() {
+							removed = Some(index);
+						}
+						ui.end_row();
+					}
+				});
+				if let Some(index) = removed {
+					self.samples.remove(index);
+				}
+
+				ui.horizontal(|ui| {
+					let clips = &self.clips;
+					let new_clip = &mut self.new_clip;
+					egui::ComboBox::from_id_source("blend_space_clip")
+						.selected_text(clips.get(*new_clip).map_or("", |clip| &clip.name))
+						.show_ui(ui, |ui| {
+							for (index, clip) in clips.iter().enumerate() {
+								ui.selectable_value(new_clip, index, &clip.name);
+							}
+						});
+					let clip = self.clips.get(self.new_clip).cloned();
+					if ui
+						.add_enabled(clip.is_some(), egui::Button::new("add at parameter"))
+						.clicked()
+					{
+						if let Some(clip) = clip {
+							self.samples.push(BlendSample {
+								name: clip.name.clone(),
+								position: self.parameter,
+								duration: clip.duration,
+								clip,
+							});
+						}
+					}
+				});
+			});
+		self.open = open;
+	}
+
+	/// The space with its samples, shaded by weight, and the parameter,
+	/// which follows the pointer while it's held down.
+	fn plot(&mut self, ui: &mut egui::Ui) {
+		const SIZE: f32 = 200.0;
+		const MARGIN: f32 = 12.0;
+		let height = match self.axes {
+			BlendAxes::One => 40.0,
+			BlendAxes::Two => SIZE,
+		};
+		let (rect, response) =
+			ui.allocate_exact_size(egui::vec2(SIZE, height), egui::Sense::click_and_drag());
+		let painter = ui.painter_at(rect);
+		let visuals = ui.visuals();
+		painter.rect_stroke(rect, 2.0, visuals.widgets.noninteractive.bg_stroke);
+
+		// bounds of the samples and parameter, never flat
+		let (mut min, mut max) = (self.parameter, self.parameter);
+		for sample in &self.samples {
+			min = min.min(sample.position);
+			max = max.max(sample.position);
+		}
+		let (min, max) = (min - Vec2::ONE * 0.5, max + Vec2::ONE * 0.5);
+		let inner = rect.shrink(MARGIN);
+		let to_screen = |position: Vec2| {
+			let t = (position - min) / (max - min);
+			let x = inner.left() + t.x * inner.width();
+			match self.axes {
+				BlendAxes::One => egui::pos2(x, rect.center().y),
+				BlendAxes::Two => egui::pos2(x, inner.bottom() - t.y * inner.height()),
+			}
+		};
+		if let Some(pointer) = response.interact_pointer_pos() {
+			if response.dragged() || response.clicked() {
+				let t = (pointer - inner.min) / inner.size();
+				let x = min.x + t.x.clamp(0.0, 1.0) * (max.x - min.x);
+				let y = min.y + (1.0 - t.y.clamp(0.0, 1.0)) * (max.y - min.y);
+				self.parameter = match self.axes {
+					BlendAxes::One => Vec2::new(x, self.parameter.y),
+					BlendAxes::Two => Vec2::new(x, y),
+				};
+			}
+		}
+
+		let weights = self.weights(self.parameter);
+		for (sample, weight) in self.samples.iter().zip(weights) {
+			let center = to_screen(sample.position);
+			let color = visuals.text_color().linear_multiply(0.3 + weight * 0.7);
+			painter.circle_filled(center, 3.0 + weight * 4.0, color);
+			painter.text(
+				center + egui::vec2(0.0, -6.0),
+				egui::Align2::CENTER_BOTTOM,
+				&sample.name,
+				egui::TextStyle::Small,
+				color,
+			);
+		}
+		painter.circle_stroke(
+			to_screen(self.parameter),
+			5.0,
+			egui::Stroke::new(2.0, egui::Color32::YELLOW),
+		);
+	}
+}
+
+/// Object space poses of one skeleton blended by weight, such as clips
+/// sampled at the times [`BlendSpace::advance`] gives. Weights needn't sum
+/// to 1.0, poses missing joints leave them out of the blend.
+pub fn blend_poses(poses: &[&[Mat4]], weights: &[f32]) -> Vec<Mat4> {
+	let joints = poses.iter().map(|pose| pose.len()).max().unwrap_or(0);
+	(0..joints)
+		.map(|joint| {
+			let mut total = 0.0;
+			let (mut scale, mut translation) = (Vec3::ZERO, Vec3::ZERO);
+			let mut rotation = Vec4::ZERO;
+			let mut reference = None;
+			for (pose, weight) in poses.iter().zip(weights) {
+				let transform = match pose.get(joint) {
+					Some(transform) if *weight > 0.0 => transform,
+					_ => continue,
+				};
+				let (s, r, t) = transform.to_scale_rotation_translation();
+				// rotations on the same side of the hypersphere as the first
+				let reference = *reference.get_or_insert(r);
+				let r = match r.dot(reference) < 0.0 {
+					true => -r,
+					false => r,
+				};
+				total += weight;
+				scale += s * *weight;
+				translation += t * *weight;
+				rotation += Vec4::from(r) * *weight;
+			}
+			match total > 0.0 {
+				true => Mat4::from_scale_rotation_translation(
+					scale / total,
+					Quat::from_vec4(rotation).normalize(),
+					translation / total,
+				),
+				false => Mat4::IDENTITY,
+			}
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use std::f32::consts::PI;
+
+	use super::*;
+	use crate::animation::{ClipPlayer, Rig};
+
+	fn assert_close(a: f32, b: f32) {
+		assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+	}
+
+	fn with_clips(axes: BlendAxes) -> BlendSpace {
+		let rig = Rig::humanoid(0.9, 0.8);
+		let clips = vec![Arc::new(Clip::idle(&rig)), Arc::new(Clip::walk(&rig))];
+		BlendSpace::new(axes, clips)
+	}
+
+	#[test]
+	fn weights_sum_to_one() {
+		for axes in BlendAxes::ALL {
+			let space = with_clips(axes);
+			for parameter in [
+				Vec2::ZERO,
+				Vec2::new(0.7, 0.0),
+				Vec2::new(-0.5, 2.0),
+				Vec2::new(1.0, 3.0),
+				Vec2::new(9.0, -9.0),
+			] {
+				assert_close(space.weights(parameter).iter().sum(), 1.0);
+			}
+		}
+	}
+
+	#[test]
+	fn held_past_the_ends() {
+		let space = with_clips(BlendAxes::One);
+		assert_eq!(space.weights(Vec2::new(-1.0, 0.0)), [1.0, 0.0, 0.0]);
+		assert_eq!(space.weights(Vec2::new(10.0, 0.0)), [0.0, 0.0, 1.0]);
+
+		let halfway = space.weights(Vec2::new(0.75, 0.0));
+		assert_close(halfway[0], 0.5);
+		assert_close(halfway[1], 0.5);
+	}
+
+	#[test]
+	fn sample_plays_alone_at_its_position() {
+		for axes in BlendAxes::ALL {
+			let space = with_clips(axes);
+			for (index, sample) in space.samples.iter().enumerate() {
+				let weights = space.weights(sample.position);
+				for (other, weight) in weights.into_iter().enumerate() {
+					let expected = if other == index { 1.0 } else { 0.0 };
+					assert_close(weight, expected);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn ties_go_to_the_first_sample() {
+		let space = with_clips(BlendAxes::One);
+		assert_eq!(space.weights(Vec2::ZERO), [1.0, 0.0, 0.0]);
+
+		let mut space = with_clips(BlendAxes::One);
+		space.samples[2].position = space.samples[1].position;
+		assert_eq!(space.weights(Vec2::new(1.5, 0.0)), [0.0, 1.0, 0.0]);
+	}
+
+	#[test]
+	fn advance_wraps_the_phase() {
+		let mut space = with_clips(BlendAxes::One);
+		// idle alone, looping every 2 seconds
+		let clips = space.advance(0.5);
+		assert_close(clips[0].0, 1.0);
+		assert_close(clips[0].1, 0.5);
+
+		let clips = space.advance(2.0);
+		assert_close(clips[0].1, 0.5);
+		assert_close(space.phase, 0.25);
+
+		// the walk runs at its own pace through the same phase
+		space.parameter = Vec2::new(1.5, 0.0);
+		let clips = space.advance(0.55);
+		assert_close(space.phase, 0.75);
+		assert_close(clips[1].0, 1.0);
+		assert_close(clips[1].1, 0.75 * 1.1);
+		assert_close(clips[0].1, 0.75 * 2.0);
+	}
+
+	#[test]
+	fn poses_blend_by_weight() {
+		let a = Mat4::from_translation(Vec3::X);
+		let b = Mat4::from_rotation_translation(Quat::from_rotation_y(PI / 2.0), Vec3::Y * 2.0);
+		let blended = blend_poses(&[&[a], &[b]], &[1.0, 1.0]);
+		let expected = Mat4::from_rotation_translation(
+			Quat::from_rotation_y(PI / 4.0),
+			Vec3::new(0.5, 1.0, 0.0),
+		);
+		assert!(blended[0].abs_diff_eq(expected, 1e-5));
+
+		// zero weights are left out
+		let blended = blend_poses(&[&[a], &[b]], &[0.0, 0.5]);
+		assert!(blended[0].abs_diff_eq(b, 1e-5));
+	}
+
+	#[test]
+	fn rotations_blend_the_short_way_round() {
+		// either side of a half turn, whose quaternions point apart
+		let a = Mat4::from_rotation_y(PI - 0.2);
+		let b = Mat4::from_rotation_y(-(PI - 0.2));
+		let blended = blend_poses(&[&[a], &[b]], &[0.5, 0.5]);
+		let (_, rotation, _) = blended[0].to_scale_rotation_translation();
+		assert!(rotation.angle_between(Quat::from_rotation_y(PI)) < 1e-4);
+	}
+
+	#[test]
+	fn joints_missing_from_a_pose_come_from_the_rest() {
+		let short = [Mat4::from_translation(Vec3::X)];
+		let long = [
+			Mat4::from_translation(Vec3::Z),
+			Mat4::from_translation(Vec3::Y),
+		];
+		let blended = blend_poses(&[&short, &long], &[0.5, 0.5]);
+		assert_eq!(blended.len(), 2);
+		assert!(blended[0].abs_diff_eq(Mat4::from_translation(Vec3::new(0.5, 0.0, 0.5)), 1e-5));
+		assert!(blended[1].abs_diff_eq(long[1], 1e-5));
+
+		// nothing weighted to pose a joint leaves it at the identity
+		let blended = blend_poses(&[&short, &long], &[1.0, 0.0]);
+		assert_eq!(blended[1], Mat4::IDENTITY);
+	}
+
+	#[test]
+	fn a_sample_posed_alone_plays_its_clip() {
+		let mut space = with_clips(BlendAxes::One);
+		space.root_motion.enabled = false;
+		space.parameter = Vec2::new(1.5, 0.0);
+		let (pose, motion) = space.pose(0.55);
+		let walk = &space.samples[1];
+		let expected = walk.clip.sample(0.5 * walk.clip.duration);
+		for (posed, expected) in pose.iter().zip(&expected) {
+			assert!(posed.abs_diff_eq(*expected, 1e-5));
+		}
+		assert_eq!(motion, Mat4::IDENTITY);
+	}
+
+	#[test]
+	fn blended_walks_carry_the_root_across_loops() {
+		let mut space = with_clips(BlendAxes::One);
+		space.parameter = Vec2::new(1.5, 0.0);
+		let walk = &space.samples[1];
+		let mut player = ClipPlayer::new(walk.clip.clone());
+		player.speed = walk.clip.duration / walk.duration;
+
+		// a bit over one loop of the walk, in steps that wrap it partway
+		let (mut blended, mut played) = (Mat4::IDENTITY, Mat4::IDENTITY);
+		for _ in 0..12 {
+			blended *= space.pose(0.1).1;
+			played *= player.advance(0.1).1;
+		}
+		assert!(played.w_axis.truncate().length() > 1.2);
+		assert!(blended.abs_diff_eq(played, 1e-4));
+	}
+}
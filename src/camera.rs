@@ -0,0 +1,1024 @@
+use std::ops::RangeInclusive;
+
+use glam::{Mat3A, Mat4, Quat, UVec2, Vec2, Vec3, Vec3A, Vec4};
+use rend3::types::{Camera, CameraProjection, Handedness};
+
+use crate::bindings::actions;
+use crate::input::InputManager;
+use crate::scene::{ObjectId, Position, ScenePosition};
+use crate::settings::CameraControls;
+
+/// Position and orientation of a free flying camera.
+#[derive(Debug, Clone, Copy)]
+pub struct CameraPose {
+	/// f64 with the `double-precision` feature, so views relative to the
+	/// scene's origin stay exact far from the world origin
+	pub position: Position,
+	/// camera to world rotation
+	pub orientation: Quat,
+}
+
+impl CameraPose {
+	/// Camera at `position` turned `yaw` radians to the right and pitched
+	/// `pitch` radians down, without roll.
+	pub fn new(position: Position, pitch: f32, yaw: f32, handedness: Handedness) -> Self {
+		Self {
+			position,
+			orientation: yaw_pitch_rotation(yaw, pitch, handedness),
+		}
+	}
+
+	/// The same pose in a space centered on `origin`.
+	pub fn relative_to(&self, origin: Position) -> Self {
+		Self {
+			position: Position::from_vec3a(self.position.relative_to(origin)),
+			..*self
+		}
+	}
+
+	/// World to view rotation.
+	pub fn rotation(&self) -> Mat3A {
+		Mat3A::from_quat(self.orientation.inverse())
+	}
+
+	pub fn view_matrix(&self) -> Mat4 {
+		let view = Mat4::from_quat(self.orientation.inverse());
+		view * Mat4::from_translation((-self.position.to_vec3a()).into())
+	}
+
+	/// World space direction the camera looks in.
+	pub fn forward(&self, handedness: Handedness) -> Vec3A {
+		self.orientation * view_forward(handedness)
+	}
+
+	/// World space direction to the right of the screen.
+	pub fn right(&self) -> Vec3A {
+		self.orientation * Vec3A::X
+	}
+
+	/// World space direction to the top of the screen.
+	pub fn up(&self) -> Vec3A {
+		self.orientation * Vec3A::Y
+	}
+
+	/// Yaw to the right and pitch down of the view direction, in radians.
+	pub fn yaw_pitch(&self, handedness: Handedness) -> (f32, f32) {
+		let forward = self.forward(handedness);
+		let yaw = forward.x.atan2(forward.z * view_forward(handedness).z);
+		(yaw, (-forward.y).clamp(-1.0, 1.0).asin())
+	}
+
+	/// Clockwise roll of the view away from a level horizon, in radians.
+	pub fn roll(&self, handedness: Handedness) -> f32 {
+		let (yaw, pitch) = self.yaw_pitch(handedness);
+		let level = yaw_pitch_rotation(yaw, pitch, handedness);
+		// what's left is a turn about the view axis
+		let mut turn = level.inverse() * self.orientation;
+		if turn.w < 0.0 {
+			turn = -turn;
+		}
+		-2.0 * turn.z.atan2(turn.w)
+	}
+
+	/// Turns the camera by angles in radians, as seen on screen: `yaw` to the
+	/// right, `pitch` down and `roll` clockwise.
+	///
+	/// With a `pitch_limit` the camera behaves like an fps camera, yawing
+	/// around the world up axis with its pitch clamped, rolling only about
+	/// the view axis so rolling never tips it past the limit. Without one it
+	/// turns around its own axes like an aircraft.
+	pub fn rotate(
+		&mut self,
+		yaw: f32,
+		pitch: f32,
+		roll: f32,
+		pitch_limit: Option<f32>,
+		handedness: Handedness,
+	) {
+		match pitch_limit {
+			Some(limit) => {
+				let (current_yaw, current_pitch) = self.yaw_pitch(handedness);
+				let current_roll = self.roll(handedness);
+				let pitch = (current_pitch + pitch).clamp(-limit, limit);
+				self.orientation = yaw_pitch_rotation(current_yaw + yaw, pitch, handedness)
+					* Quat::from_rotation_z(-(current_roll + roll));
+			}
+			None => {
+				let sign = turn_sign(handedness);
+				let turn = Quat::from_rotation_y(sign * yaw)
+					* Quat::from_rotation_x(sign * pitch)
+					* Quat::from_rotation_z(-roll);
+				self.orientation = (self.orientation * turn).normalize();
+			}
+		}
+	}
+}
+
+/// How the main viewport projects the scene.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraMode {
+	Perspective,
+	/// parallel lines stay parallel, for lining things up. Stereo stays in
+	/// perspective, the clip plane is ignored and clicks still pick along
+	/// rays from the camera, which only match the view at its center.
+	Orthographic,
+}
+
+impl CameraMode {
+	pub const ALL: [Self; 2] = [Self::Perspective, Self::Orthographic];
+
+	pub fn label(self) -> &'static str {
+		match self {
+			Self::Perspective => "perspective",
+			Self::Orthographic => "orthographic",
+		}
+	}
+
+	pub fn toggled(self) -> Self {
+		match self {
+			Self::Perspective => Self::Orthographic,
+			Self::Orthographic => Self::Perspective,
+		}
+	}
+}
+
+/// Views straight along a world axis, like a modeling tool's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AxisView {
+	/// looking forward
+	Front,
+	/// looking down
+	Top,
+	/// from the right, looking left
+	Side,
+}
+
+impl AxisView {
+	pub const ALL: [Self; 3] = [Self::Front, Self::Top, Self::Side];
+
+	pub fn label(self) -> &'static str {
+		match self {
+			Self::Front => "front",
+			Self::Top => "top",
+			Self::Side => "side",
+		}
+	}
+
+	/// Yaw to the right and pitch down in radians, as taken by
+	/// [`CameraPose::new`].
+	pub fn yaw_pitch(self) -> (f32, f32) {
+		use std::f32::consts::FRAC_PI_2;
+		match self {
+			Self::Front => (0.0, 0.0),
+			Self::Top => (0.0, FRAC_PI_2),
+			Self::Side => (-FRAC_PI_2, 0.0),
+		}
+	}
+}
+
+/// Rotations about the y and x axes turn the view the opposite way on screen
+/// in a right handed space.
+fn turn_sign(handedness: Handedness) -> f32 {
+	match handedness {
+		Handedness::Left => 1.0,
+		Handedness::Right => -1.0,
+	}
+}
+
+fn yaw_pitch_rotation(yaw: f32, pitch: f32, handedness: Handedness) -> Quat {
+	let sign = turn_sign(handedness);
+	Quat::from_rotation_y(sign * yaw) * Quat::from_rotation_x(sign * pitch)
+}
+
+/// Eases a fly camera's movement and mouse look, so it speeds up and slows
+/// down over time instead of starting and stopping instantly.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CameraMotion {
+	/// world units per second
+	pub velocity: Vec3A,
+	/// radians per second
+	pub roll: f32,
+	/// mouse look as (yaw, pitch) that hasn't been applied yet
+	pending_look: Vec2,
+}
+
+impl CameraMotion {
+	/// Eases the velocity towards `target` over `dt` seconds and returns it.
+	/// `acceleration` is the rate used while there's input and `damping` the
+	/// rate used to come to a stop, both per second. Zero snaps to the target.
+	pub fn accelerate(&mut self, target: Vec3A, acceleration: f32, damping: f32, dt: f32) -> Vec3A {
+		let rate = match target == Vec3A::ZERO {
+			true => damping,
+			false => acceleration,
+		};
+		self.velocity = self.velocity.lerp(target, ease(rate, dt));
+		self.velocity
+	}
+
+	/// Eases the roll speed towards `target` the same way as
+	/// [`CameraMotion::accelerate`] and returns it.
+	pub fn accelerate_roll(
+		&mut self,
+		target: f32,
+		acceleration: f32,
+		damping: f32,
+		dt: f32,
+	) -> f32 {
+		let rate = match target == 0.0 {
+			true => damping,
+			false => acceleration,
+		};
+		self.roll += (target - self.roll) * ease(rate, dt);
+		self.roll
+	}
+
+	/// Spreads `look` over the next frames and returns the part to apply this
+	/// frame. `smoothing` is a time constant in seconds, zero applies it all at
+	/// once. The total rotation is the same either way.
+	pub fn look(&mut self, look: Vec2, smoothing: f32, dt: f32) -> Vec2 {
+		self.pending_look += look;
+		let applied = match smoothing > 0.0 {
+			true => self.pending_look * (1.0 - (-dt / smoothing).exp()),
+			false => self.pending_look,
+		};
+		self.pending_look -= applied;
+		applied
+	}
+
+	/// Drops all momentum, e.g. when input moves to another camera.
+	pub fn stop(&mut self) {
+		*self = Self::default();
+	}
+}
+
+/// How far to move towards a target over `dt` seconds, closing the gap at
+/// `rate` per second. Zero moves all the way.
+fn ease(rate: f32, dt: f32) -> f32 {
+	match rate > 0.0 {
+		true => 1.0 - (-rate * dt).exp(),
+		false => 1.0,
+	}
+}
+
+/// Shakes a camera's view for impacts. Hits add trauma, which wears off
+/// over time, and the view turns and moves by noise scaled by the trauma
+/// squared, so small hits barely show and big ones shake hard. The noise and
+/// the wearing off both go by time, so a shake looks the same at any frame
+/// rate. The pose itself isn't moved, the shake is only added to what's
+/// drawn.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CameraShake {
+	/// 0.0 to 1.0
+	pub trauma: f32,
+	/// seconds the noise has run for
+	time: f32,
+}
+
+impl CameraShake {
+	/// trauma worn off per second
+	const DECAY: f32 = 0.8;
+	/// radians of yaw and pitch at full trauma, roll gets twice as much
+	const MAX_ANGLE: f32 = 0.05;
+	/// world units the view moves sideways and up at full trauma
+	const MAX_OFFSET: f32 = 0.08;
+	/// how fast the noise changes
+	const FREQUENCY: f32 = 12.0;
+
+	pub fn add_trauma(&mut self, trauma: f32) {
+		self.trauma = (self.trauma + trauma).clamp(0.0, 1.0);
+	}
+
+	/// Runs the noise on and wears the trauma off over `dt` seconds.
+	pub fn update(&mut self, dt: f32) {
+		match self.trauma > 0.0 {
+			true => {
+				self.time += dt;
+				self.trauma = (self.trauma - Self::DECAY * dt).max(0.0);
+			}
+			false => self.time = 0.0,
+		}
+	}
+
+	/// `pose` turned and moved by the shake, in its own space, to draw with.
+	pub fn shaken(&self, pose: &CameraPose) -> CameraPose {
+		if self.trauma <= 0.0 {
+			return *pose;
+		}
+		let shake = self.trauma * self.trauma;
+		let angle = shake * Self::MAX_ANGLE;
+		let offset = shake * Self::MAX_OFFSET;
+		let time = self.time * Self::FREQUENCY;
+		let turn = Quat::from_euler(
+			glam::EulerRot::YXZ,
+			angle * noise(time, 0),
+			angle * noise(time, 1),
+			angle * 2.0 * noise(time, 2),
+		);
+		let offset = Vec3A::new(offset * noise(time, 3), offset * noise(time, 4), 0.0);
+		CameraPose {
+			position: pose.position.offset(pose.orientation * offset),
+			orientation: pose.orientation * turn,
+		}
+	}
+}
+
+/// Perlin noise along `time`, from -1.0 to 1.0 and passing through 0.0 at
+/// whole numbers, a different curve for each `seed`.
+fn noise(time: f32, seed: u32) -> f32 {
+	// slope at a whole number, from a hash of it
+	let gradient = |cell: f32| {
+		let mut hash = (cell as i32 as u32) ^ seed.wrapping_mul(0x9e37_79b9);
+		hash = (hash ^ (hash >> 16)).wrapping_mul(0x7feb_352d);
+		hash = (hash ^ (hash >> 15)).wrapping_mul(0x846c_a68b);
+		hash ^= hash >> 16;
+		hash as f32 / u32::MAX as f32 * 2.0 - 1.0
+	};
+	let cell = time.floor();
+	let t = time - cell;
+	let fade = t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+	let before = gradient(cell) * t;
+	let after = gradient(cell + 1.0) * (t - 1.0);
+	// the largest a 1d perlin curve gets is a half
+	((before + (after - before) * fade) * 2.0).clamp(-1.0, 1.0)
+}
+
+/// A camera flown with the movement actions and turned by look input, easing
+/// in and out of both the way the camera controls ask for.
+#[derive(Debug, Clone, Copy)]
+pub struct FlyCamera {
+	pub pose: CameraPose,
+	pub motion: CameraMotion,
+	pub shake: CameraShake,
+	handedness: Handedness,
+}
+
+impl FlyCamera {
+	pub fn new(pose: CameraPose, handedness: Handedness) -> Self {
+		Self {
+			pose,
+			motion: CameraMotion::default(),
+			shake: CameraShake::default(),
+			handedness,
+		}
+	}
+
+	/// Shakes the view, adding `trauma` from 0.0 to 1.0 for how hard.
+	pub fn add_trauma(&mut self, trauma: f32) {
+		self.shake.add_trauma(trauma);
+	}
+
+	/// Turns by `look`, yaw to the right and pitch down in radians, then rolls
+	/// and flies with the actions held over `dt` seconds. Look input comes
+	/// from the caller since it depends on the window, like whether the
+	/// cursor is grabbed.
+	pub fn update(&mut self, input: &InputManager, look: Vec2, controls: &CameraControls, dt: f32) {
+		let (acceleration, damping) = controls.easing();
+		let look = self.motion.look(look, controls.look_smoothing, dt);
+		let mut roll = 0.0;
+		if controls.can_roll() && input.is_action_down(actions::ROLL_LEFT) {
+			roll -= controls.roll_speed;
+		}
+		if controls.can_roll() && input.is_action_down(actions::ROLL_RIGHT) {
+			roll += controls.roll_speed;
+		}
+		let roll = self.motion.accelerate_roll(roll, acceleration, damping, dt);
+		// roll left over from before rolling was turned off is levelled out
+		let level = match controls.can_roll() {
+			true => 0.0,
+			false => self.pose.roll(self.handedness),
+		};
+		self.pose.rotate(
+			look.x,
+			look.y,
+			roll * dt - level,
+			controls.pitch_limit(),
+			self.handedness,
+		);
+
+		let forward = self.pose.forward(self.handedness);
+		let side = self.pose.right();
+		let mut speed = controls.fly_speed;
+		if input.is_action_down(actions::SPRINT) {
+			speed *= controls.sprint_multiplier;
+		}
+		if input.is_action_down(actions::SLOW) {
+			speed *= controls.slow_multiplier;
+		}
+		let mut target = Vec3A::ZERO;
+		if input.is_action_down(actions::MOVE_FORWARD) {
+			target += forward * speed;
+		}
+		if input.is_action_down(actions::MOVE_BACK) {
+			target -= forward * speed;
+		}
+		if input.is_action_down(actions::MOVE_LEFT) {
+			target -= side * speed;
+		}
+		if input.is_action_down(actions::MOVE_RIGHT) {
+			target += side * speed;
+		}
+		// up and down follow the world's up rather than the view's
+		if input.is_action_down(actions::MOVE_UP) {
+			target += Vec3A::Y * speed;
+		}
+		if input.is_action_down(actions::MOVE_DOWN) {
+			target -= Vec3A::Y * speed;
+		}
+
+		let velocity = self.motion.accelerate(target, acceleration, damping, dt);
+		self.pose.position = self.pose.position.offset(velocity * dt);
+	}
+
+	/// Puts the camera somewhere without easing there, dropping its momentum.
+	pub fn teleport(&mut self, position: Position) {
+		self.pose.position = position;
+		self.motion.stop();
+	}
+
+	pub fn view_matrix(&self) -> Mat4 {
+		self.pose.view_matrix()
+	}
+}
+
+/// Turns a camera around a target point, for looking over one object. The
+/// look input orbits, the pan action drags the target across the screen and
+/// zooming moves in and out. The horizon stays level. Orbiting is smoothed
+/// like a fly camera's look, panning and zooming ease in with its damping.
+#[derive(Debug, Clone, Copy)]
+pub struct OrbitCamera {
+	/// where the target is headed, see [`OrbitCamera::pose`]
+	pub target: Position,
+	/// where the distance is headed
+	pub distance: f32,
+	/// radians to the right
+	pub yaw: f32,
+	/// radians down
+	pub pitch: f32,
+	/// target and distance the camera is at while easing towards them
+	shown: (Position, f32),
+	motion: CameraMotion,
+	handedness: Handedness,
+}
+
+impl OrbitCamera {
+	pub const DISTANCE_RANGE: RangeInclusive<f32> = 0.01..=100_000.0;
+	/// how close to straight up or down the camera may look, short of it so
+	/// the yaw stays defined
+	const PITCH_LIMIT: f32 = 89.0 * std::f32::consts::PI / 180.0;
+	/// target movement per point dragged, as a fraction of the distance
+	const PAN_SPEED: f32 = 0.002;
+	/// distance scale per mouse wheel line
+	const ZOOM_STEP: f32 = 1.2;
+
+	/// Orbits the point `distance` in front of a pose, turned the same way.
+	pub fn from_pose(pose: &CameraPose, distance: f32, handedness: Handedness) -> Self {
+		let (yaw, pitch) = pose.yaw_pitch(handedness);
+		let target = pose.position.offset(pose.forward(handedness) * distance);
+		Self {
+			target,
+			distance,
+			yaw,
+			pitch,
+			shown: (target, distance),
+			motion: CameraMotion::default(),
+			handedness,
+		}
+	}
+
+	/// Orbits by `look`, yaw and pitch in radians like
+	/// [`FlyCamera::update`], pans while the pan action is held and zooms in
+	/// by `zoom` mouse wheel lines, negative to zoom out, over `dt` seconds.
+	pub fn update(
+		&mut self,
+		input: &InputManager,
+		look: Vec2,
+		zoom: f32,
+		controls: &CameraControls,
+		scale_factor: f64,
+		dt: f32,
+	) {
+		let look = self.motion.look(look, controls.look_smoothing, dt);
+		self.yaw += look.x;
+		self.pitch = (self.pitch + look.y).clamp(-Self::PITCH_LIMIT, Self::PITCH_LIMIT);
+
+		if input.is_action_down(actions::PAN) {
+			// the target follows the cursor, so the scene moves with it
+			let drag = input.look_delta(false, scale_factor).as_vec2();
+			let pose = self.pose();
+			let scale = self.distance * Self::PAN_SPEED;
+			self.target = self
+				.target
+				.offset((pose.up() * drag.y - pose.right() * drag.x) * scale);
+		}
+
+		let distance = self.distance * Self::ZOOM_STEP.powf(-zoom);
+		self.distance = distance.clamp(*Self::DISTANCE_RANGE.start(), *Self::DISTANCE_RANGE.end());
+
+		let (_, damping) = controls.easing();
+		let amount = ease(damping, dt);
+		let (target, distance) = &mut self.shown;
+		*target = target.offset(self.target.relative_to(*target) * amount);
+		*distance += (self.distance - *distance) * amount;
+	}
+
+	/// Turns to look at the target from `yaw` and `pitch` without smoothing,
+	/// past the pitch limit if asked to, like for a view from the top.
+	pub fn look_along(&mut self, yaw: f32, pitch: f32) {
+		self.yaw = yaw;
+		self.pitch = pitch;
+		self.motion.stop();
+	}
+
+	/// Distance the camera is at, while zooming eases towards `distance`.
+	pub fn shown_distance(&self) -> f32 {
+		self.shown.1
+	}
+
+	/// Orbits the point `pose` looks at from the same distance, after the
+	/// camera was moved some other way.
+	pub fn look_from(&mut self, pose: &CameraPose) {
+		*self = Self::from_pose(pose, self.distance, self.handedness);
+	}
+
+	/// Where the camera is, looking at the target. Panning and zooming may
+	/// not have got there yet.
+	pub fn pose(&self) -> CameraPose {
+		let (target, distance) = self.shown;
+		let mut pose = CameraPose::new(target, self.pitch, self.yaw, self.handedness);
+		pose.position = pose
+			.position
+			.offset(-pose.forward(self.handedness) * distance);
+		pose
+	}
+
+	pub fn view_matrix(&self) -> Mat4 {
+		self.pose().view_matrix()
+	}
+}
+
+/// Chases a scene object, keeping an offset from it in its own space so the
+/// camera stays behind it as it turns. The camera lags behind the object as
+/// it moves and either keeps looking at it or keeps the heading it had
+/// relative to it.
+#[derive(Debug, Clone, Copy)]
+pub struct FollowCamera {
+	pub target: ObjectId,
+	/// where the camera sits, in the target's space
+	pub offset: Vec3,
+	/// time constant in seconds of the camera catching up, 0 for none
+	pub lag: f32,
+	/// turns the camera to look at the target's origin rather than turning
+	/// it with the target
+	pub look_at: bool,
+	/// where the camera's got to
+	position: Position,
+	/// camera to target rotation, kept while not looking at the target
+	rotation: Quat,
+	handedness: Handedness,
+}
+
+impl FollowCamera {
+	/// Follows `target`, at `target_transform`, from where `pose` is.
+	pub fn new(
+		target: ObjectId,
+		target_transform: Mat4,
+		pose: &CameraPose,
+		handedness: Handedness,
+	) -> Self {
+		let (_, target_rotation, _) = target_transform.to_scale_rotation_translation();
+		Self {
+			target,
+			offset: target_transform
+				.inverse()
+				.transform_point3(pose.position.to_vec3a().into()),
+			lag: 0.3,
+			look_at: true,
+			position: pose.position,
+			rotation: target_rotation.inverse() * pose.orientation,
+			handedness,
+		}
+	}
+
+	/// Moves towards the offset from the target, at `target_transform`, over
+	/// `dt` seconds, returning the camera's pose.
+	pub fn update(&mut self, target_transform: Mat4, dt: f32) -> CameraPose {
+		let goal = Vec3A::from(target_transform.transform_point3(self.offset));
+		let rate = match self.lag > 0.0 {
+			true => 1.0 / self.lag,
+			false => 0.0,
+		};
+		let to_goal = Position::from_vec3a(goal).relative_to(self.position);
+		self.position = self.position.offset(to_goal * ease(rate, dt));
+
+		let (_, target_rotation, center) = target_transform.to_scale_rotation_translation();
+		let towards = Position::from_vec3a(center.into()).relative_to(self.position);
+		match (self.look_at, towards.try_normalize()) {
+			(true, Some(forward)) => {
+				let yaw = forward.x.atan2(forward.z * view_forward(self.handedness).z);
+				let pitch = (-forward.y).clamp(-1.0, 1.0).asin();
+				CameraPose::new(self.position, pitch, yaw, self.handedness)
+			}
+			_ => CameraPose {
+				position: self.position,
+				orientation: (target_rotation * self.rotation).normalize(),
+			},
+		}
+	}
+}
+
+/// Direction cameras look in view space.
+pub fn view_forward(handedness: Handedness) -> Vec3A {
+	match handedness {
+		Handedness::Left => Vec3A::Z,
+		Handedness::Right => -Vec3A::Z,
+	}
+}
+
+/// Projection matrix rend3 builds for `camera` with a renderer of the given
+/// handedness.
+pub fn projection_matrix(camera: &Camera, aspect_ratio: f32, handedness: Handedness) -> Mat4 {
+	match (camera.projection, handedness) {
+		(CameraProjection::Orthographic { size }, Handedness::Left) => {
+			let half = size * 0.5;
+			Mat4::orthographic_lh(-half.x, half.x, -half.y, half.y, half.z, -half.z)
+		}
+		(CameraProjection::Orthographic { size }, Handedness::Right) => {
+			let half = size * 0.5;
+			Mat4::orthographic_rh(-half.x, half.x, -half.y, half.y, half.z, -half.z)
+		}
+		(CameraProjection::Perspective { vfov, near }, Handedness::Left) => {
+			Mat4::perspective_infinite_reverse_lh(vfov.to_radians(), aspect_ratio, near)
+		}
+		(CameraProjection::Perspective { vfov, near }, Handedness::Right) => {
+			Mat4::perspective_infinite_reverse_rh(vfov.to_radians(), aspect_ratio, near)
+		}
+		(CameraProjection::Raw(projection), _) => projection,
+	}
+}
+
+/// `camera` with a view of a space centered on `origin` turned back into a
+/// view of world space.
+pub fn world_camera(camera: Camera, origin: Vec3) -> Camera {
+	Camera {
+		view: camera.view * Mat4::from_translation(-origin),
+		..camera
+	}
+}
+
+/// Position of a world space point in a viewport given as `(offset, size)` in
+/// physical pixels. `None` for points behind the camera.
+pub fn world_to_screen(view_proj: Mat4, viewport: (UVec2, UVec2), point: Vec3) -> Option<Vec2> {
+	let clip = view_proj * point.extend(1.0);
+	if clip.w <= 0.0 {
+		return None;
+	}
+	let ndc = clip.truncate() / clip.w;
+	let (offset, size) = viewport;
+	let pixel = Vec2::new(ndc.x + 1.0, 1.0 - ndc.y) * 0.5 * size.as_vec2();
+	Some(pixel + offset.as_vec2())
+}
+
+/// World space direction of the ray from the camera through `pixel`, the
+/// inverse of [`world_to_screen`].
+pub fn screen_to_direction(view_proj: Mat4, viewport: (UVec2, UVec2), pixel: Vec2) -> Vec3 {
+	let (offset, size) = viewport;
+	let ndc = (pixel - offset.as_vec2()) / size.as_vec2() * 2.0 - 1.0;
+	let inverse = view_proj.inverse();
+	// reverse z, so 1.0 is the near plane and 0.5 further away
+	let near = inverse.project_point3(Vec3::new(ndc.x, -ndc.y, 1.0));
+	let far = inverse.project_point3(Vec3::new(ndc.x, -ndc.y, 0.5));
+	(far - near).normalize_or_zero()
+}
+
+/// Volume a camera sees, as the planes bounding it, for culling and picking
+/// on the cpu. Each plane's normal points inwards, so points inside have
+/// `normal.dot(p) + w >= 0` for every plane. Planes rend3's infinite far
+/// plane leaves out never reject anything.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+	/// left, right, bottom, top, near and far, as normal and w
+	pub planes: [Vec4; 6],
+}
+
+impl Frustum {
+	/// Extracts the planes of a reverse z view projection matrix (Gribb and
+	/// Hartmann), in whatever space it takes points from.
+	pub fn from_view_proj(view_proj: Mat4) -> Self {
+		let row = |i| view_proj.row(i);
+		let planes = [
+			row(3) + row(0),
+			row(3) - row(0),
+			row(3) + row(1),
+			row(3) - row(1),
+			// reverse z, depth runs from 1 at the near plane to 0 at the far one
+			row(3) - row(2),
+			row(2),
+		]
+		.map(|plane| {
+			let length = plane.truncate().length();
+			match length > f32::EPSILON {
+				true => plane / length,
+				// the far plane of an infinite projection
+				false => Vec4::W,
+			}
+		});
+		Self { planes }
+	}
+
+	/// The frustum of `camera` with the projection rend3 gives it, relative
+	/// to the same origin as the camera's view.
+	pub fn of_camera(camera: &Camera, aspect_ratio: f32, handedness: Handedness) -> Self {
+		Self::from_view_proj(projection_matrix(camera, aspect_ratio, handedness) * camera.view)
+	}
+
+	pub fn contains_point(&self, point: Vec3) -> bool {
+		self.planes
+			.iter()
+			.all(|plane| plane.truncate().dot(point) + plane.w >= 0.0)
+	}
+
+	/// Whether a sphere is at least partly inside.
+	pub fn intersects_sphere(&self, center: Vec3, radius: f32) -> bool {
+		self.planes
+			.iter()
+			.all(|plane| plane.truncate().dot(center) + plane.w >= -radius)
+	}
+
+	/// Whether an axis aligned box is at least partly inside. Conservative,
+	/// a large box near a corner of the frustum can pass without being in
+	/// it.
+	pub fn intersects_aabb(&self, min: Vec3, max: Vec3) -> bool {
+		self.planes.iter().all(|plane| {
+			let normal = plane.truncate();
+			// the corner furthest along the normal
+			let corner = Vec3::select(normal.cmpge(Vec3::ZERO), max, min);
+			normal.dot(corner) + plane.w >= 0.0
+		})
+	}
+}
+
+/// Plane that cuts away geometry on the side its normal points to, in world
+/// space. Points where `normal.dot(p) > distance` are removed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClipPlane {
+	pub normal: Vec3,
+	pub distance: f32,
+}
+
+impl ClipPlane {
+	/// The same plane in a space centered on `origin`.
+	pub fn relative_to(&self, origin: Position) -> Self {
+		let along = self.normal.as_dvec3().dot(origin.to_f64());
+		Self {
+			normal: self.normal,
+			distance: (self.distance as f64 - along) as f32,
+		}
+	}
+
+	/// Plane as a `Vec4` that is positive on the kept side.
+	pub fn kept_side(&self) -> Vec4 {
+		(-self.normal.normalize_or_zero()).extend(self.distance)
+	}
+}
+
+/// Near and far planes of the perspective cameras, plus an optional plane for
+/// cutaway views.
+///
+/// rend3's shaders can't clip against extra planes, so the clip plane replaces
+/// the near plane of the projection (an oblique near plane). That only works
+/// while the camera is on the removed side of the plane, otherwise the plane
+/// is ignored. It also costs depth precision and moves the far plane, which
+/// is placed at `far` or [`ClipPlanes::OBLIQUE_FAR`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClipPlanes {
+	pub near: f32,
+	/// `None` for rend3's infinite far plane
+	pub far: Option<f32>,
+	pub clip_plane: Option<ClipPlane>,
+}
+
+impl Default for ClipPlanes {
+	fn default() -> Self {
+		Self {
+			near: 0.1,
+			far: None,
+			clip_plane: None,
+		}
+	}
+}
+
+impl ClipPlanes {
+	/// Far plane used by an oblique projection without a far plane.
+	pub const OBLIQUE_FAR: f32 = 10_000.0;
+
+	/// Camera looking through `view` with these planes. Falls back to a plain
+	/// rend3 perspective projection when nothing needs a custom matrix.
+	pub fn camera(
+		&self,
+		vfov: f32,
+		aspect_ratio: f32,
+		view: Mat4,
+		handedness: Handedness,
+	) -> Camera {
+		if self.far.is_none() && self.clip_plane.is_none() {
+			return Camera {
+				projection: CameraProjection::Perspective {
+					vfov,
+					near: self.near,
+				},
+				view,
+			};
+		}
+
+		let fov = vfov.to_radians();
+		// swapping near and far turns the regular projection into reverse z
+		let projection = match (self.far, handedness) {
+			(Some(far), Handedness::Left) => {
+				Mat4::perspective_lh(fov, aspect_ratio, far, self.near)
+			}
+			(Some(far), Handedness::Right) => {
+				Mat4::perspective_rh(fov, aspect_ratio, far, self.near)
+			}
+			(None, Handedness::Left) => {
+				Mat4::perspective_infinite_reverse_lh(fov, aspect_ratio, self.near)
+			}
+			(None, Handedness::Right) => {
+				Mat4::perspective_infinite_reverse_rh(fov, aspect_ratio, self.near)
+			}
+		};
+		let projection = self
+			.clip_plane
+			.and_then(|plane| {
+				let far = self.far.unwrap_or(Self::OBLIQUE_FAR);
+				oblique_projection(projection, view, plane, far)
+			})
+			.unwrap_or(projection);
+
+		Camera {
+			projection: CameraProjection::Raw(projection),
+			view,
+		}
+	}
+
+	/// Orthographic camera looking through `view`, `height` world units tall.
+	/// rend3 centers the depth range on the camera, so it sees as far behind
+	/// as in front, up to `far` or [`ClipPlanes::OBLIQUE_FAR`] each way. The
+	/// clip plane isn't used.
+	pub fn orthographic_camera(&self, height: f32, aspect_ratio: f32, view: Mat4) -> Camera {
+		let depth = self.far.unwrap_or(Self::OBLIQUE_FAR) * 2.0;
+		Camera {
+			projection: CameraProjection::Orthographic {
+				size: Vec3A::new(height * aspect_ratio, height, depth),
+			},
+			view,
+		}
+	}
+}
+
+/// Replaces the near plane of a reverse z perspective `projection` with
+/// `plane` (Lengyel's oblique frustum), putting the new far plane through the
+/// corner of the frustum `far` units away. `None` if the camera isn't on the
+/// removed side or the plane doesn't cross the frustum before `far`.
+fn oblique_projection(projection: Mat4, view: Mat4, plane: ClipPlane, far: f32) -> Option<Mat4> {
+	// planes transform by the inverse transpose
+	let plane = view.inverse().transpose() * plane.kept_side();
+	if plane.w >= 0.0 {
+		return None;
+	}
+
+	// the far corner the plane reaches furthest into. w is the view depth
+	// along the forward axis, which is -z in a right handed projection
+	let corner = Vec3::new(
+		far * plane.x.signum() / projection.x_axis.x,
+		far * plane.y.signum() / projection.y_axis.y,
+		far * projection.z_axis.w,
+	);
+	let plane_at_corner = plane.dot(corner.extend(1.0));
+	if plane_at_corner <= 0.0 {
+		return None;
+	}
+	let scale = far / plane_at_corner;
+
+	// depth row becomes w - scale * plane, so depth is 1.0 on the plane and
+	// 0.0 on the new far plane
+	let row = projection.row(3) - plane * scale;
+	let mut projection = projection;
+	projection.x_axis.z = row.x;
+	projection.y_axis.z = row.y;
+	projection.z_axis.z = row.z;
+	projection.w_axis.z = row.w;
+	Some(projection)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// 90 degrees both ways from a camera at the origin, with the near plane
+	/// at 1, so points inside have `z >= 1`, `|x| <= z` and `|y| <= z`.
+	fn frustum(handedness: Handedness) -> Frustum {
+		let camera = Camera {
+			projection: CameraProjection::Perspective {
+				vfov: 90.0,
+				near: 1.0,
+			},
+			view: Mat4::IDENTITY,
+		};
+		Frustum::of_camera(&camera, 1.0, handedness)
+	}
+
+	#[test]
+	fn frustum_contains_points_in_view() {
+		let left = frustum(Handedness::Left);
+		for inside in [
+			Vec3::new(0.0, 0.0, 5.0),
+			Vec3::new(4.9, -4.9, 5.0),
+			Vec3::new(0.0, 0.0, 1.01),
+			// no far plane
+			Vec3::new(0.0, 0.0, 1e6),
+		] {
+			assert!(left.contains_point(inside), "{} is inside", inside);
+		}
+		for outside in [
+			Vec3::new(0.0, 0.0, 0.99),
+			Vec3::new(0.0, 0.0, -5.0),
+			Vec3::new(5.1, 0.0, 5.0),
+			Vec3::new(-5.1, 0.0, 5.0),
+			Vec3::new(0.0, 5.1, 5.0),
+			Vec3::new(0.0, -5.1, 5.0),
+		] {
+			assert!(!left.contains_point(outside), "{} is outside", outside);
+		}
+
+		// right handed cameras look down -z
+		let right = frustum(Handedness::Right);
+		assert!(right.contains_point(Vec3::new(4.9, 0.0, -5.0)));
+		assert!(!right.contains_point(Vec3::new(0.0, 0.0, 5.0)));
+	}
+
+	#[test]
+	fn frustum_culls_boxes_and_spheres_outside() {
+		let frustum = frustum(Handedness::Left);
+		let intersects = |min: Vec3, max: Vec3| frustum.intersects_aabb(min, max);
+		// inside
+		assert!(intersects(
+			Vec3::new(-1.0, -1.0, 4.0),
+			Vec3::new(1.0, 1.0, 6.0)
+		));
+		// straddling the near plane, the right plane and all of them
+		assert!(intersects(
+			Vec3::new(-0.5, -0.5, 0.0),
+			Vec3::new(0.5, 0.5, 2.0)
+		));
+		assert!(intersects(
+			Vec3::new(4.0, -1.0, 4.0),
+			Vec3::new(8.0, 1.0, 6.0)
+		));
+		assert!(intersects(Vec3::splat(-100.0), Vec3::splat(100.0)));
+		// behind the camera, beside and above the frustum
+		assert!(!intersects(
+			Vec3::new(-1.0, -1.0, -6.0),
+			Vec3::new(1.0, 1.0, -4.0)
+		));
+		assert!(!intersects(
+			Vec3::new(6.0, -1.0, 4.0),
+			Vec3::new(8.0, 1.0, 5.0)
+		));
+		assert!(!intersects(
+			Vec3::new(-1.0, 6.0, 4.0),
+			Vec3::new(1.0, 8.0, 5.0)
+		));
+
+		assert!(frustum.intersects_sphere(Vec3::new(0.0, 0.0, 5.0), 0.5));
+		assert!(frustum.intersects_sphere(Vec3::new(0.0, 0.0, 0.0), 1.5));
+		assert!(!frustum.intersects_sphere(Vec3::new(0.0, 0.0, 0.0), 0.5));
+		// 1 from the right plane, which is at 45 degrees
+		let beside = Vec3::new(5.0 + 2f32.sqrt(), 0.0, 5.0);
+		assert!(frustum.intersects_sphere(beside, 1.1));
+		assert!(!frustum.intersects_sphere(beside, 0.9));
+	}
+
+	#[test]
+	fn frustum_follows_the_view_and_finite_far_planes() {
+		// the camera moved back 10, so the origin is 10 in front of it
+		let camera = Camera {
+			projection: CameraProjection::Perspective {
+				vfov: 90.0,
+				near: 1.0,
+			},
+			view: Mat4::from_translation(Vec3::Z * 10.0),
+		};
+		let frustum = Frustum::of_camera(&camera, 1.0, Handedness::Left);
+		assert!(frustum.contains_point(Vec3::new(9.0, 0.0, 0.0)));
+		assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, -9.5)));
+
+		// reverse z with the far plane at 100
+		let frustum =
+			Frustum::from_view_proj(Mat4::perspective_lh(90f32.to_radians(), 1.0, 100.0, 1.0));
+		assert!(frustum.contains_point(Vec3::new(0.0, 0.0, 99.0)));
+		assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, 101.0)));
+		assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, 0.5)));
+		assert!(frustum.intersects_aabb(Vec3::new(-1.0, -1.0, 90.0), Vec3::new(1.0, 1.0, 110.0)));
+		assert!(!frustum.intersects_aabb(Vec3::new(-1.0, -1.0, 101.0), Vec3::new(1.0, 1.0, 110.0)));
+	}
+}
@@ -0,0 +1,315 @@
+use glam::{Quat, Vec4};
+
+use crate::camera::CameraPose;
+use crate::scene::ScenePosition;
+use crate::sequencer::Track;
+
+/// Keyframed flight of the main camera for fly-through captures. Keys are
+/// recorded from wherever the camera is, and playback moves the camera
+/// through them along Catmull-Rom splines, its position and rotation both,
+/// so it passes through every key without stopping at them.
+pub struct CameraPath {
+	pub open: bool,
+	pub track: Track<CameraPose>,
+	/// seconds along the path while it plays
+	playing: Option<f32>,
+	pub looping: bool,
+	/// seconds after the last key a recorded key goes
+	pub spacing: f32,
+}
+
+impl Default for CameraPath {
+	fn default() -> Self {
+		Self {
+			open: false,
+			track: Track::default(),
+			playing: None,
+			looping: false,
+			spacing: 2.0,
+		}
+	}
+}
+
+impl CameraPath {
+	/// Adds a key for `pose` after the last one.
+	pub fn record(&mut self, pose: CameraPose) {
+		let time = self
+			.track
+			.keys
+			.last()
+			.map_or(0.0, |key| key.time + self.spacing);
+		self.track.insert(time, pose);
+	}
+
+	pub fn is_playing(&self) -> bool {
+		self.playing.is_some()
+	}
+
+	/// Plays from the first key, if there are two to fly between.
+	pub fn play(&mut self) {
+		if self.track.keys.len() >= 2 {
+			self.playing = self.track.keys.first().map(|key| key.time);
+		}
+	}
+
+	pub fn stop(&mut self) {
+		self.playing = None;
+	}
+
+	/// Moves playback on by `dt` seconds, returning where the camera goes.
+	/// `None` while stopped.
+	pub fn update(&mut self, dt: f32) -> Option<CameraPose> {
+		let time = self.playing? + dt;
+		let (start, end) = match (self.track.keys.first(), self.track.keys.last()) {
+			(Some(first), Some(last)) => (first.time, last.time),
+			_ => {
+				self.playing = None;
+				return None;
+			}
+		};
+		self.playing = match (time <= end, self.looping) {
+			(true, _) => Some(time),
+			(false, true) if end > start => Some(start + (time - start) % (end - start)),
+			(false, _) => None,
+		};
+		self.sample(self.playing.unwrap_or(end))
+	}
+
+	/// Pose along the path at `time`, held at the first and last keys
+	/// outside of them.
+	pub fn sample(&self, time: f32) -> Option<CameraPose> {
+		let keys = &self.track.keys;
+		let last = keys.len().checked_sub(1)?;
+		let next = keys
+			.partition_point(|key| key.time <= time)
+			.clamp(1, last.max(1));
+		let (before, after) = (next - 1, next.min(last));
+		let span = keys[after].time - keys[before].time;
+		let t = match span > f32::EPSILON {
+			true => ((time - keys[before].time) / span).clamp(0.0, 1.0),
+			false => 0.0,
+		};
+		let pose = |index: usize| keys[index.min(last)].value;
+		let points = [
+			pose(before.saturating_sub(1)),
+			pose(before),
+			pose(after),
+			pose(after + 1),
+		];
+
+		// relative to the segment's start, so far from the world origin the
+		// spline is still worked out from small offsets
+		let base = points[1].position;
+		let offsets = points.map(|pose| pose.position.relative_to(base).extend(0.0));
+		let position = base.offset(catmull_rom(offsets, t).truncate().into());
+		// rotations on the same side of the hypersphere as the segment's start
+		let start = points[1].orientation;
+		let orientations = points.map(|pose| match pose.orientation.dot(start) < 0.0 {
+			true => Vec4::from(-pose.orientation),
+			false => Vec4::from(pose.orientation),
+		});
+		let orientation = Quat::from_vec4(catmull_rom(orientations, t)).normalize();
+		Some(CameraPose {
+			position,
+			orientation,
+		})
+	}
+
+	/// Records keys from `camera`, the main camera's pose, lists them and
+	/// starts and stops playback. Returns a pose to move the camera to when
+	/// a key is jumped to.
+	pub fn window(&mut self, ctx: &egui::CtxRef, camera: &CameraPose) -> Option<CameraPose> {
+		let mut open = self.open;
+		let mut jump = None;
+		egui::Window::new("camera path")
+			.open(&mut open)
+			.resizable(false)
+			.show(ctx, |ui| {
+				ui.horizontal(|ui| {
+					match self.playing {
+						Some(_) => {
+							if ui.button("stop").clicked() {
+								self.stop();
+							}
+						}
+						None => {
+							let playable = self.track.keys.len() >= 2;
+							if ui
+								.add_enabled(playable, egui::Button::new("play"))
+								.clicked()
+							{
+								self.play();
+							}
+						}
+					}
+					ui.checkbox(&mut self.looping, "loop");
+					if let Some(time) = self.playing {
+						ui.label(format!("{:.2} s", time));
+					}
+				});
+				ui.horizontal(|ui| {
+					if ui.button("record key").clicked() {
+						self.record(*camera);
+					}
+					ui.add(
+						egui::DragValue::new(&mut self.spacing)
+							.speed(0.1)
+							.clamp_range(0.1..=60.0)
+							.prefix("after ")
+							.suffix(" s"),
+					);
+				});
+
+				let mut retimed = None;
+				let mut removed = None;
+				egui::Grid::new("camera_path_keys").show(ui, |ui| {
+					for (index, key) in self.track.keys.iter().enumerate() {
+						let mut time = key.time;
+						let drag = egui::DragValue::new(&mut time)
+							.speed(0.01)
+							.clamp_range(0.0..=3600.0)
+							.suffix(" s");
+						if ui.add(drag).changed() {
+							retimed = Some((index, time));
+						}
+						let position = key.value.position;
+						ui.label(format!(
+							"x{:.1} y{:.1} z{:.1}",
+							position.x, position.y, position.z
+						));
+						if ui.small_button("go").clicked() {
+							jump = Some(key.value);
+						}
+						if ui.small_button("x").on_hover_text("remove").clicked() {
+							removed = Some(index);
+						}
+						ui.end_row();
+					}
+				});
+				// onto another key's time is refused rather than replacing it
+				if let Some((index, time)) = retimed {
+					self.track.retime(index, time);
+				}
+				if let Some(index) = removed {
+					self.track.keys.remove(index);
+				}
+			});
+		self.open = open;
+		jump
+	}
+}
+
+/// Catmull-Rom spline through `p1` and `p2`, with `p0` and `p3` the points
+/// before and after, `t` going from 0.0 (`p1`) to 1.0 (`p2`).
+fn catmull_rom([p0, p1, p2, p3]: [Vec4; 4], t: f32) -> Vec4 {
+	let (t2, t3) = (t * t, t * t * t);
+	(p1 * 2.0
+		+ (p2 - p0) * t
+		+ (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+		+ (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3)
+		* 0.5
+}
+
+#[cfg(test)]
+mod tests {
+	use glam::{Vec3, Vec3A};
+
+	use super::*;
+	use crate::scene::Position;
+
+	fn pose(x: f32) -> CameraPose {
+		CameraPose {
+			position: Position::from_vec3a(Vec3A::new(x, 0.0, 0.0)),
+			orientation: Quat::from_rotation_y(x * 0.1),
+		}
+	}
+
+	fn x(pose: CameraPose) -> f32 {
+		pose.position.to_vec3a().x
+	}
+
+	/// Keys a unit along x apart, two seconds apart.
+	fn path(keys: usize) -> CameraPath {
+		let mut path = CameraPath::default();
+		for key in 0..keys {
+			path.record(pose(key as f32));
+		}
+		path
+	}
+
+	fn assert_close(a: f32, b: f32) {
+		assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+	}
+
+	#[test]
+	fn catmull_rom_passes_through_its_keys() {
+		let points = [
+			Vec4::new(0.0, 1.0, 2.0, 3.0),
+			Vec4::new(1.0, -1.0, 0.5, 0.0),
+			Vec4::new(4.0, 2.0, -3.0, 1.0),
+			Vec4::new(5.0, 0.0, 0.0, 2.0),
+		];
+		assert!(catmull_rom(points, 0.0).abs_diff_eq(points[1], 1e-6));
+		assert!(catmull_rom(points, 1.0).abs_diff_eq(points[2], 1e-6));
+	}
+
+	#[test]
+	fn samples_pass_through_keys_and_hold_past_the_ends() {
+		let path = path(4);
+		for key in 0..4 {
+			assert_close(x(path.sample(key as f32 * 2.0).unwrap()), key as f32);
+		}
+		assert_close(x(path.sample(-1.0).unwrap()), 0.0);
+		assert_close(x(path.sample(100.0).unwrap()), 3.0);
+		let orientation = path.sample(100.0).unwrap().orientation;
+		assert!(orientation.abs_diff_eq(pose(3.0).orientation, 1e-5));
+		// evenly spaced keys in a line are flown through at an even pace
+		assert_close(x(path.sample(3.0).unwrap()), 1.5);
+	}
+
+	#[test]
+	fn single_segments_and_keys() {
+		assert!(path(0).sample(1.0).is_none());
+		assert_close(x(path(1).sample(1.0).unwrap()), 0.0);
+
+		let path = path(2);
+		assert_close(x(path.sample(0.0).unwrap()), 0.0);
+		assert_close(x(path.sample(1.0).unwrap()), 0.5);
+		assert_close(x(path.sample(2.0).unwrap()), 1.0);
+		assert_eq!(path.sample(0.5).unwrap().position.to_vec3a().y, 0.0);
+	}
+
+	#[test]
+	fn playback_stops_or_wraps_at_the_end() {
+		let mut path = path(3);
+		path.play();
+		assert_close(x(path.update(2.0).unwrap()), 1.0);
+		assert_close(x(path.update(4.0).unwrap()), 2.0);
+		assert!(!path.is_playing());
+		assert!(path.update(1.0).is_none());
+
+		path.looping = true;
+		path.play();
+		// two seconds past the end of the four second path
+		assert_close(x(path.update(6.0).unwrap()), 1.0);
+		assert!(path.is_playing());
+	}
+
+	#[test]
+	fn retiming_onto_another_key_is_refused() {
+		let mut path = path(3);
+		assert_eq!(path.track.retime(0, 2.0), None);
+		assert_eq!(path.track.retime(0, 2.0005), None);
+		assert_eq!(path.track.keys.len(), 3);
+		assert_close(x(path.track.keys[0].value), 0.0);
+
+		// past it is fine
+		assert_eq!(path.track.retime(0, 3.0), Some(1));
+		let times: Vec<f32> = path.track.keys.iter().map(|key| key.time).collect();
+		assert_eq!(times, [2.0, 3.0, 4.0]);
+		assert_eq!(
+			path.sample(3.0).unwrap().position.to_vec3a(),
+			Vec3::ZERO.into()
+		);
+	}
+}
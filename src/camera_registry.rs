@@ -0,0 +1,114 @@
+use crate::camera::CameraPose;
+
+/// most cameras number keys switch between, 1 to 9
+pub const NUMBERED: usize = 9;
+
+/// Named cameras the main viewport can look through, like "editor", "chase"
+/// and "cinematic". The main camera is always the active one, the others
+/// keep the pose they were left at and switching swaps poses in and out.
+/// The first nine are switched to with the number keys.
+pub struct CameraRegistry {
+	pub open: bool,
+	cameras: Vec<(String, CameraPose)>,
+	active: usize,
+	new_name: String,
+}
+
+impl CameraRegistry {
+	/// Starts with just the "editor" camera, active at `pose`.
+	pub fn new(pose: CameraPose) -> Self {
+		Self {
+			open: false,
+			cameras: vec![("editor".into(), pose)],
+			active: 0,
+			new_name: String::new(),
+		}
+	}
+
+	pub fn active_name(&self) -> &str {
+		&self.cameras[self.active].0
+	}
+
+	/// Adds a camera at `pose`, or moves the one named `name` there.
+	pub fn add(&mut self, name: &str, pose: CameraPose) {
+		match self
+			.cameras
+			.iter_mut()
+			.find(|(existing, _)| existing == name)
+		{
+			Some((_, existing)) => *existing = pose,
+			None => self.cameras.push((name.into(), pose)),
+		}
+	}
+
+	/// Makes camera `index` the active one, keeping `current`, the main
+	/// camera's pose, for the one that was. Returns the pose to move the main
+	/// camera to, `None` if it's already active or there's no such camera.
+	pub fn switch(&mut self, index: usize, current: &CameraPose) -> Option<CameraPose> {
+		if index == self.active || index >= self.cameras.len() {
+			return None;
+		}
+		self.cameras[self.active].1 = *current;
+		self.active = index;
+		log::info!("switched to the {} camera", self.active_name());
+		Some(self.cameras[index].1)
+	}
+
+	/// Lists the cameras to switch between, add to and remove from. Returns
+	/// the pose to move the main camera to when another one is picked.
+	pub fn window(&mut self, ctx: &egui::CtxRef, current: &CameraPose) -> Option<CameraPose> {
+		let mut open = self.open;
+		let mut switched = None;
+		egui::Window::new("cameras")
+			.open(&mut open)
+			.resizable(false)
+			.show(ctx, |ui| {
+				let mut removed = None;
+				egui::Grid::new("cameras").show(ui, |ui| {
+					for (index, (name, _)) in self.cameras.iter().enumerate() {
+						let key = match index < NUMBERED {
+							true => format!("{}", index + 1),
+							false => String::new(),
+						};
+						ui.label(key);
+						if ui.selectable_label(index == self.active, name).clicked() {
+							switched = Some(index);
+						}
+						let removable = index != self.active;
+						if ui
+							.add_enabled(removable, egui::Button::new("x").small())
+							.on_hover_text("remove")
+							.clicked()
+						{
+							removed = Some(index);
+						}
+						ui.end_row();
+					}
+				});
+				if let Some(index) = removed {
+					self.cameras.remove(index);
+					if index < self.active {
+						self.active -= 1;
+					}
+				}
+
+				ui.horizontal(|ui| {
+					ui.add(
+						egui::TextEdit::singleline(&mut self.new_name)
+							.hint_text("name")
+							.desired_width(100.0),
+					);
+					let name = self.new_name.trim().to_owned();
+					if ui
+						.add_enabled(!name.is_empty(), egui::Button::new("add from view"))
+						.clicked()
+					{
+						self.add(&name, *current);
+						self.new_name.clear();
+					}
+				});
+			});
+		self.open = open;
+		self.switch(switched?, current)
+	}
+}
@@ -0,0 +1,67 @@
+use winit::event::{
+	ElementState, Event, KeyboardInput, ModifiersState, VirtualKeyCode, WindowEvent,
+};
+
+use crate::ui_platform::UiPlatform;
+
+/// The system clipboard, for egui and the editor. Text egui copies is put
+/// on it from egui's output, and Ctrl+V pastes from it into egui.
+pub struct SystemClipboard {
+	/// `None` when there's no clipboard to connect to, like on a headless
+	/// session
+	clipboard: Option<arboard::Clipboard>,
+	modifiers: ModifiersState,
+}
+
+impl Default for SystemClipboard {
+	fn default() -> Self {
+		let clipboard = arboard::Clipboard::new()
+			.map_err(|e| log::warn!("no system clipboard, copy and paste won't work: {}", e))
+			.ok();
+		Self {
+			clipboard,
+			modifiers: ModifiersState::empty(),
+		}
+	}
+}
+
+impl SystemClipboard {
+	pub fn set_text(&mut self, text: &str) {
+		if let Some(clipboard) = &mut self.clipboard {
+			if let Err(e) = clipboard.set_text(text) {
+				log::warn!("couldn't copy to the clipboard: {}", e);
+			}
+		}
+	}
+
+	/// Text on the clipboard, `None` if it's empty or holds something else.
+	pub fn text(&mut self) -> Option<String> {
+		self.clipboard.as_mut()?.get_text().ok()
+	}
+
+	/// Pastes into egui on Ctrl+V. Call before the platform handles the
+	/// event.
+	pub fn handle_event<T>(&mut self, platform: &mut UiPlatform, event: &Event<T>) {
+		let event = match event {
+			Event::WindowEvent { event, .. } => event,
+			_ => return,
+		};
+		match event {
+			WindowEvent::ModifiersChanged(modifiers) => self.modifiers = *modifiers,
+			WindowEvent::KeyboardInput {
+				input:
+					KeyboardInput {
+						state: ElementState::Pressed,
+						virtual_keycode: Some(VirtualKeyCode::V),
+						..
+					},
+				..
+			} if self.modifiers.ctrl() || cfg!(target_os = "macos") && self.modifiers.logo() => {
+				if let Some(text) = self.text() {
+					platform.push_text(&text);
+				}
+			}
+			_ => {}
+		}
+	}
+}
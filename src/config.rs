@@ -0,0 +1,110 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde_json::{Map, Value};
+
+/// File the app's config is kept in, next to where it's run from.
+pub const CONFIG_PATH: &str = "opal.json";
+
+/// Why a config entry couldn't be saved.
+#[derive(Debug)]
+pub enum ConfigError {
+	Io(io::Error),
+	/// the file exists but isn't a json object, so it's left alone rather
+	/// than replaced with just the entry being saved
+	Unparsable(String),
+}
+
+impl fmt::Display for ConfigError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ConfigError::Io(e) => write!(f, "{}", e),
+			ConfigError::Unparsable(reason) => {
+				write!(
+					f,
+					"config isn't a json object ({}), not overwriting it",
+					reason
+				)
+			}
+		}
+	}
+}
+
+impl From<io::Error> for ConfigError {
+	fn from(e: io::Error) -> Self {
+		ConfigError::Io(e)
+	}
+}
+
+/// One entry of a config file like [`CONFIG_PATH`]. `None` if the file or the
+/// entry is missing.
+pub fn load_config_entry(path: &Path, key: &str) -> Option<Value> {
+	let text = fs::read_to_string(path).ok()?;
+	let mut config: Value = serde_json::from_str(&text).ok()?;
+	Some(config.get_mut(key)?.take())
+}
+
+/// Writes one entry of a config file, keeping the file's other entries. A
+/// missing file is created, but one that can't be parsed is an error.
+pub fn save_config_entry(path: &Path, key: &str, value: Value) -> Result<(), ConfigError> {
+	let mut config = match fs::read_to_string(path) {
+		Ok(text) => match serde_json::from_str::<Value>(&text) {
+			Ok(Value::Object(config)) => config,
+			Ok(_) => return Err(ConfigError::Unparsable("not an object".into())),
+			Err(e) => return Err(ConfigError::Unparsable(e.to_string())),
+		},
+		Err(e) if e.kind() == io::ErrorKind::NotFound => Map::new(),
+		Err(e) => return Err(e.into()),
+	};
+	config.insert(key.into(), value);
+	let text = serde_json::to_string_pretty(&Value::Object(config))
+		.map_err(|e| ConfigError::Unparsable(e.to_string()))?;
+	fs::write(path, text)?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::path::PathBuf;
+
+	use serde_json::json;
+
+	use super::*;
+
+	/// A config path in the temp directory, with no file there.
+	fn config_path(test: &str) -> PathBuf {
+		let path = std::env::temp_dir().join(format!("opal-{}-{}.json", test, std::process::id()));
+		let _ = fs::remove_file(&path);
+		path
+	}
+
+	#[test]
+	fn entries_keep_each_other() {
+		let path = config_path("config-entries");
+		assert_eq!(load_config_entry(&path, "a"), None);
+
+		save_config_entry(&path, "a", json!(1)).unwrap();
+		save_config_entry(&path, "b", json!({ "c": true })).unwrap();
+		save_config_entry(&path, "a", json!(2)).unwrap();
+		assert_eq!(load_config_entry(&path, "a"), Some(json!(2)));
+		assert_eq!(load_config_entry(&path, "b"), Some(json!({ "c": true })));
+		assert_eq!(load_config_entry(&path, "d"), None);
+		fs::remove_file(&path).unwrap();
+	}
+
+	#[test]
+	fn unparsable_files_are_left_alone() {
+		let path = config_path("config-unparsable");
+		for text in ["{ \"a\": 1, ", "[1, 2]"] {
+			fs::write(&path, text).unwrap();
+			assert!(matches!(
+				save_config_entry(&path, "b", json!(2)),
+				Err(ConfigError::Unparsable(_))
+			));
+			assert_eq!(fs::read_to_string(&path).unwrap(), text);
+		}
+		fs::remove_file(&path).unwrap();
+	}
+}
@@ -0,0 +1,144 @@
+use winit::window::{CursorIcon, Window};
+
+/// Cursor shapes tools and scripts can ask for. winit 0.26 can only show the
+/// system's own cursors, so there are no image cursors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorKind {
+	Arrow,
+	Hand,
+	Crosshair,
+	Move,
+	Text,
+	Wait,
+	ResizeHorizontal,
+	ResizeVertical,
+	/// top right to bottom left
+	ResizeNeSw,
+	/// top left to bottom right
+	ResizeNwSe,
+	Hidden,
+}
+
+impl CursorKind {
+	pub const ALL: [CursorKind; 11] = [
+		CursorKind::Arrow,
+		CursorKind::Hand,
+		CursorKind::Crosshair,
+		CursorKind::Move,
+		CursorKind::Text,
+		CursorKind::Wait,
+		CursorKind::ResizeHorizontal,
+		CursorKind::ResizeVertical,
+		CursorKind::ResizeNeSw,
+		CursorKind::ResizeNwSe,
+		CursorKind::Hidden,
+	];
+
+	pub fn label(&self) -> &'static str {
+		match self {
+			CursorKind::Arrow => "arrow",
+			CursorKind::Hand => "hand",
+			CursorKind::Crosshair => "crosshair",
+			CursorKind::Move => "move",
+			CursorKind::Text => "text",
+			CursorKind::Wait => "wait",
+			CursorKind::ResizeHorizontal => "resize_horizontal",
+			CursorKind::ResizeVertical => "resize_vertical",
+			CursorKind::ResizeNeSw => "resize_ne_sw",
+			CursorKind::ResizeNwSe => "resize_nw_se",
+			CursorKind::Hidden => "hidden",
+		}
+	}
+
+	pub fn from_label(label: &str) -> Option<Self> {
+		Self::ALL.into_iter().find(|kind| kind.label() == label)
+	}
+
+	/// `None` for [`CursorKind::Hidden`].
+	fn icon(&self) -> Option<CursorIcon> {
+		Some(match self {
+			CursorKind::Arrow => CursorIcon::Default,
+			CursorKind::Hand => CursorIcon::Hand,
+			CursorKind::Crosshair => CursorIcon::Crosshair,
+			CursorKind::Move => CursorIcon::Move,
+			CursorKind::Text => CursorIcon::Text,
+			CursorKind::Wait => CursorIcon::Wait,
+			CursorKind::ResizeHorizontal => CursorIcon::EwResize,
+			CursorKind::ResizeVertical => CursorIcon::NsResize,
+			CursorKind::ResizeNeSw => CursorIcon::NeswResize,
+			CursorKind::ResizeNwSe => CursorIcon::NwseResize,
+			CursorKind::Hidden => return None,
+		})
+	}
+}
+
+/// Picks the window's cursor each frame: egui's over the ui, otherwise what a
+/// script set, otherwise what the active tool wants.
+#[derive(Default)]
+pub struct Cursor {
+	/// set by scripts until they clear it
+	pub script: Option<CursorKind>,
+	/// the icon last shown, `None` while hidden, to only tell the window when
+	/// it changes
+	shown: Option<Option<CursorIcon>>,
+}
+
+impl Cursor {
+	/// Shows the cursor for the frame. `tool` is what the active tool wants
+	/// over the viewport, and `ui` is egui's cursor while the pointer is over
+	/// the ui.
+	pub fn apply(
+		&mut self,
+		window: &Window,
+		ui: Option<egui::CursorIcon>,
+		tool: Option<CursorKind>,
+	) {
+		let icon = match ui {
+			Some(icon) => egui_icon(icon),
+			None => self.script.or(tool).unwrap_or(CursorKind::Arrow).icon(),
+		};
+		if self.shown == Some(icon) {
+			return;
+		}
+		self.shown = Some(icon);
+		match icon {
+			Some(icon) => {
+				window.set_cursor_icon(icon);
+				window.set_cursor_visible(true);
+			}
+			None => window.set_cursor_visible(false),
+		}
+	}
+}
+
+/// `None` when egui hides the cursor.
+fn egui_icon(icon: egui::CursorIcon) -> Option<CursorIcon> {
+	use egui::CursorIcon::*;
+	Some(match icon {
+		Default => CursorIcon::Default,
+		None => return Option::None,
+		ContextMenu => CursorIcon::ContextMenu,
+		Help => CursorIcon::Help,
+		PointingHand => CursorIcon::Hand,
+		Progress => CursorIcon::Progress,
+		Wait => CursorIcon::Wait,
+		Cell => CursorIcon::Cell,
+		Crosshair => CursorIcon::Crosshair,
+		Text => CursorIcon::Text,
+		VerticalText => CursorIcon::VerticalText,
+		Alias => CursorIcon::Alias,
+		Copy => CursorIcon::Copy,
+		Move => CursorIcon::Move,
+		NoDrop => CursorIcon::NoDrop,
+		NotAllowed => CursorIcon::NotAllowed,
+		Grab => CursorIcon::Grab,
+		Grabbing => CursorIcon::Grabbing,
+		AllScroll => CursorIcon::AllScroll,
+		ResizeHorizontal => CursorIcon::EwResize,
+		ResizeNeSw => CursorIcon::NeswResize,
+		ResizeNwSe => CursorIcon::NwseResize,
+		ResizeVertical => CursorIcon::NsResize,
+		ZoomIn => CursorIcon::ZoomIn,
+		ZoomOut => CursorIcon::ZoomOut,
+	})
+}
@@ -0,0 +1,335 @@
+use glam::{Vec3, Vec4};
+use rend3::types::{MaterialHandle, Texture, TextureFormat, TextureHandle};
+use rend3::util::typedefs::FastHashMap;
+use rend3::Renderer;
+use rend3_routine::pbr::{AlbedoComponent, MaterialComponent, PbrMaterial};
+
+/// How a material input is interpreted by the shader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSpace {
+	Srgb,
+	Linear,
+	/// texture wasn't added through the audit so its format isn't known
+	Unknown,
+}
+
+impl ColorSpace {
+	fn of_format(format: TextureFormat) -> Self {
+		match format.describe().srgb {
+			true => ColorSpace::Srgb,
+			false => ColorSpace::Linear,
+		}
+	}
+}
+
+/// One input of a material and how it will be decoded.
+#[derive(Debug, Clone)]
+pub struct SlotReport {
+	pub slot: &'static str,
+	pub source: &'static str,
+	pub space: ColorSpace,
+	pub issue: Option<String>,
+}
+
+struct MaterialRecord {
+	name: String,
+	handle: MaterialHandle,
+	original: PbrMaterial,
+	slots: Vec<SlotReport>,
+}
+
+impl MaterialRecord {
+	fn has_issues(&self) -> bool {
+		self.slots.iter().any(|s| s.issue.is_some())
+	}
+
+	/// Flat color shown in place of the material while the audit view is on.
+	fn debug_color(&self) -> Vec4 {
+		if self.has_issues() {
+			Vec4::new(1.0, 0.0, 0.0, 1.0)
+		} else if self.slots.iter().any(|s| s.space == ColorSpace::Srgb) {
+			Vec4::new(0.0, 1.0, 0.0, 1.0)
+		} else if self.slots.iter().any(|s| s.space == ColorSpace::Unknown) {
+			Vec4::new(1.0, 1.0, 0.0, 1.0)
+		} else {
+			Vec4::new(0.0, 0.3, 1.0, 1.0)
+		}
+	}
+}
+
+/// Tracks texture formats and material inputs so color-space mistakes can be
+/// flagged when a material is assembled, and shown in the viewport on demand.
+///
+/// Color inputs (albedo, emissive) are expected to come from sRGB textures or
+/// linear values in 0..1. Data inputs (normals, ao/metallic/roughness) are
+/// expected to come from linear textures.
+#[derive(Default)]
+pub struct ColorSpaceAudit {
+	textures: FastHashMap<usize, (String, TextureFormat)>,
+	materials: Vec<MaterialRecord>,
+	enabled: bool,
+}
+
+impl ColorSpaceAudit {
+	/// Adds a texture to the renderer, remembering its format for later checks.
+	pub fn add_texture_2d(&mut self, renderer: &Renderer, texture: Texture) -> TextureHandle {
+		let label = texture.label.clone().unwrap_or_else(|| "unnamed".into());
+		let format = texture.format;
+		let handle = renderer.add_texture_2d(texture);
+		self.textures.insert(handle.get_raw().idx, (label, format));
+		handle
+	}
+
+	/// Adds a material to the renderer after checking the color space of each
+	/// of its inputs. Mismatches are logged as warnings.
+	pub fn add_material(
+		&mut self,
+		renderer: &Renderer,
+		name: &str,
+		material: PbrMaterial,
+	) -> MaterialHandle {
+		let slots = self.audit(&material);
+		for slot in &slots {
+			if let Some(issue) = &slot.issue {
+				log::warn!("material \"{}\" {}: {}", name, slot.slot, issue);
+			}
+		}
+
+		let handle = renderer.add_material(clone_material(&material));
+		let record = MaterialRecord {
+			name: name.into(),
+			handle: handle.clone(),
+			original: material,
+			slots,
+		};
+		if self.enabled {
+			renderer.update_material(&record.handle, debug_material(record.debug_color()));
+		}
+		self.materials.push(record);
+		handle
+	}
+
+	/// Name a material was added with, if it went through the audit.
+	pub fn material_name(&self, handle: &MaterialHandle) -> Option<&str> {
+		let idx = handle.get_raw().idx;
+		self.materials
+			.iter()
+			.find(|record| record.handle.get_raw().idx == idx)
+			.map(|record| record.name.as_str())
+	}
+
+	/// Swaps every audited material for a flat color showing its color space:
+	/// red has issues, green samples sRGB textures, blue is linear values only
+	/// and yellow uses textures the audit doesn't know about.
+	pub fn set_enabled(&mut self, renderer: &Renderer, enabled: bool) {
+		if self.enabled == enabled {
+			return;
+		}
+		self.enabled = enabled;
+
+		for record in &self.materials {
+			if enabled {
+				renderer.update_material(&record.handle, debug_material(record.debug_color()));
+			} else {
+				renderer.update_material(&record.handle, clone_material(&record.original));
+			}
+		}
+	}
+
+	fn texture_space(&self, texture: &TextureHandle) -> ColorSpace {
+		self.textures
+			.get(&texture.get_raw().idx)
+			.map_or(ColorSpace::Unknown, |(_, format)| {
+				ColorSpace::of_format(*format)
+			})
+	}
+
+	fn texture_label(&self, texture: &TextureHandle) -> String {
+		self.textures
+			.get(&texture.get_raw().idx)
+			.map_or_else(|| "texture".into(), |(label, _)| label.clone())
+	}
+
+	fn color_texture(&self, slot: &'static str, texture: &TextureHandle) -> SlotReport {
+		let space = self.texture_space(texture);
+		SlotReport {
+			slot,
+			source: "texture",
+			space,
+			issue: (space == ColorSpace::Linear).then(|| {
+				format!(
+					"\"{}\" holds color data but has a linear format, it will look washed out",
+					self.texture_label(texture)
+				)
+			}),
+		}
+	}
+
+	fn data_texture(&self, slot: &'static str, texture: &TextureHandle) -> SlotReport {
+		let space = self.texture_space(texture);
+		SlotReport {
+			slot,
+			source: "texture",
+			space,
+			issue: (space == ColorSpace::Srgb).then(|| {
+				format!(
+					"\"{}\" holds non-color data but has an sRGB format and will be gamma decoded",
+					self.texture_label(texture)
+				)
+			}),
+		}
+	}
+
+	fn vertex(slot: &'static str, srgb: bool) -> SlotReport {
+		SlotReport {
+			slot,
+			source: "vertex",
+			space: match srgb {
+				true => ColorSpace::Srgb,
+				false => ColorSpace::Linear,
+			},
+			issue: None,
+		}
+	}
+
+	fn color_value(slot: &'static str, value: Vec3) -> SlotReport {
+		SlotReport {
+			slot,
+			source: "value",
+			space: ColorSpace::Linear,
+			issue: (value.max_element() > 1.0).then(|| {
+				format!(
+					"value {:?} is outside 0..1, was it written as 0..255 or gamma encoded?",
+					value
+				)
+			}),
+		}
+	}
+
+	/// Checks every input of the material.
+	pub fn audit(&self, material: &PbrMaterial) -> Vec<SlotReport> {
+		let mut slots = Vec::new();
+
+		match &material.albedo {
+			AlbedoComponent::None => {}
+			AlbedoComponent::Vertex { srgb } => slots.push(Self::vertex("albedo", *srgb)),
+			AlbedoComponent::Value(value) => {
+				slots.push(Self::color_value("albedo", value.truncate()))
+			}
+			AlbedoComponent::ValueVertex { value, srgb } => {
+				slots.push(Self::color_value("albedo", value.truncate()));
+				slots.push(Self::vertex("albedo", *srgb));
+			}
+			AlbedoComponent::Texture(texture) => slots.push(self.color_texture("albedo", texture)),
+			AlbedoComponent::TextureVertex { texture, srgb } => {
+				slots.push(self.color_texture("albedo", texture));
+				slots.push(Self::vertex("albedo", *srgb));
+			}
+			AlbedoComponent::TextureValue { texture, value } => {
+				slots.push(self.color_texture("albedo", texture));
+				slots.push(Self::color_value("albedo", value.truncate()));
+			}
+			AlbedoComponent::TextureVertexValue {
+				texture,
+				srgb,
+				value,
+			} => {
+				slots.push(self.color_texture("albedo", texture));
+				slots.push(Self::vertex("albedo", *srgb));
+				slots.push(Self::color_value("albedo", value.truncate()));
+			}
+		}
+
+		match &material.emissive {
+			MaterialComponent::None => {}
+			MaterialComponent::Value(value) => slots.push(SlotReport {
+				// emissive values are intensities so they are allowed past 1.0
+				issue: None,
+				..Self::color_value("emissive", *value)
+			}),
+			MaterialComponent::Texture(texture)
+			| MaterialComponent::TextureValue { texture, .. } => {
+				slots.push(self.color_texture("emissive", texture))
+			}
+		}
+
+		if let Some(texture) = material.normal.to_texture() {
+			slots.push(self.data_texture("normal", texture));
+		}
+
+		let aomr = &material.aomr_textures;
+		for (slot, texture) in [
+			("ao", aomr.to_ao_texture()),
+			("metallic", aomr.to_metallic_texture()),
+			("roughness", aomr.to_roughness_texture()),
+		] {
+			if let Some(texture) = texture {
+				slots.push(self.data_texture(slot, texture));
+			}
+		}
+
+		slots
+	}
+
+	pub fn window(&mut self, ctx: &egui::CtxRef, renderer: &Renderer) {
+		let mut enabled = self.enabled;
+
+		egui::Window::new("color audit")
+			.resizable(true)
+			.show(ctx, |ui| {
+				ui.checkbox(&mut enabled, "show color spaces in viewport");
+				ui.separator();
+
+				egui::Grid::new("color_audit_grid")
+					.num_columns(4)
+					.striped(true)
+					.show(ui, |ui| {
+						for record in &self.materials {
+							for slot in &record.slots {
+								ui.label(&record.name);
+								ui.label(format!("{} ({})", slot.slot, slot.source));
+								ui.label(format!("{:?}", slot.space));
+								match &slot.issue {
+									Some(issue) => ui.colored_label(egui::Color32::RED, issue),
+									None => ui.label("ok"),
+								};
+								ui.end_row();
+							}
+						}
+					});
+			});
+
+		self.set_enabled(renderer, enabled);
+	}
+}
+
+fn debug_material(color: Vec4) -> PbrMaterial {
+	PbrMaterial {
+		albedo: AlbedoComponent::Value(color),
+		unlit: true,
+		..PbrMaterial::default()
+	}
+}
+
+/// `PbrMaterial` isn't `Clone` but all of its fields are.
+fn clone_material(material: &PbrMaterial) -> PbrMaterial {
+	PbrMaterial {
+		albedo: material.albedo.clone(),
+		transparency: material.transparency,
+		normal: material.normal.clone(),
+		aomr_textures: material.aomr_textures.clone(),
+		ao_factor: material.ao_factor,
+		metallic_factor: material.metallic_factor,
+		roughness_factor: material.roughness_factor,
+		clearcoat_textures: material.clearcoat_textures.clone(),
+		clearcoat_factor: material.clearcoat_factor,
+		clearcoat_roughness_factor: material.clearcoat_roughness_factor,
+		emissive: material.emissive.clone(),
+		reflectance: material.reflectance.clone(),
+		anisotropy: material.anisotropy.clone(),
+		uv_transform0: material.uv_transform0,
+		uv_transform1: material.uv_transform1,
+		unlit: material.unlit,
+		sample_type: material.sample_type,
+	}
+}
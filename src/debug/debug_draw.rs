@@ -0,0 +1,292 @@
+use std::borrow::Cow;
+
+use glam::{Mat4, Vec3, Vec4};
+use rend3::graph::{
+	DepthHandle, RenderGraph, RenderPassDepthTarget, RenderPassTarget, RenderPassTargets,
+};
+use rend3::util::bind_merge::{BindGroupBuilder, BindGroupLayoutBuilder};
+use rend3::Renderer;
+use rend3_routine::base::BaseRenderGraphIntermediateState;
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+	BindGroup, BindingType, BlendState, Buffer, BufferAddress, BufferBindingType, BufferDescriptor,
+	BufferUsages, Color, ColorTargetState, ColorWrites, CompareFunction, DepthStencilState,
+	FragmentState, MultisampleState, PipelineLayoutDescriptor, PrimitiveState, PrimitiveTopology,
+	RenderPipeline, RenderPipelineDescriptor, ShaderModuleDescriptor, ShaderSource, ShaderStages,
+	StencilState, TextureFormat, VertexAttribute, VertexBufferLayout, VertexFormat, VertexState,
+	VertexStepMode,
+};
+
+use crate::staging::StagingPool;
+
+/// position and color
+const VERTEX_SIZE: BufferAddress = 28;
+
+/// Immediate mode line drawing for debug visualizations.
+///
+/// Shapes are queued during the frame and drawn over the lit scene of every
+/// viewport, tested against its depth. The queue is kept until [`Self::clear`]
+/// so callers decide whether shapes last a frame or longer.
+pub struct DebugDraw {
+	pipeline: RenderPipeline,
+	uniforms: Buffer,
+	bg: BindGroup,
+	vertices: Buffer,
+	capacity: usize,
+
+	queued: Vec<u8>,
+	vertex_count: u32,
+}
+
+impl DebugDraw {
+	pub fn new(renderer: &Renderer) -> Self {
+		let device = &renderer.device;
+
+		let bgl = BindGroupLayoutBuilder::new()
+			.append(
+				ShaderStages::VERTEX,
+				BindingType::Buffer {
+					ty: BufferBindingType::Uniform,
+					has_dynamic_offset: false,
+					min_binding_size: None,
+				},
+				None,
+			)
+			.build(device, Some("debug draw bgl"));
+
+		let module = device.create_shader_module(&ShaderModuleDescriptor {
+			label: Some("debug draw"),
+			source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("debug_draw.wgsl"))),
+		});
+
+		let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+			label: Some("debug draw"),
+			bind_group_layouts: &[&bgl],
+			push_constant_ranges: &[],
+		});
+
+		let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+			label: Some("debug draw"),
+			layout: Some(&layout),
+			vertex: VertexState {
+				module: &module,
+				entry_point: "vs_main",
+				buffers: &[VertexBufferLayout {
+					array_stride: VERTEX_SIZE,
+					step_mode: VertexStepMode::Vertex,
+					attributes: &[
+						VertexAttribute {
+							format: VertexFormat::Float32x3,
+							offset: 0,
+							shader_location: 0,
+						},
+						VertexAttribute {
+							format: VertexFormat::Float32x4,
+							offset: 12,
+							shader_location: 1,
+						},
+					],
+				}],
+			},
+			primitive: PrimitiveState {
+				topology: PrimitiveTopology::LineList,
+				..PrimitiveState::default()
+			},
+			depth_stencil: Some(DepthStencilState {
+				format: TextureFormat::Depth32Float,
+				depth_write_enabled: false,
+				depth_compare: CompareFunction::GreaterEqual,
+				stencil: StencilState::default(),
+				bias: Default::default(),
+			}),
+			multisample: MultisampleState::default(),
+			fragment: Some(FragmentState {
+				module: &module,
+				entry_point: "fs_main",
+				targets: &[ColorTargetState {
+					format: TextureFormat::Rgba16Float,
+					blend: Some(BlendState::ALPHA_BLENDING),
+					write_mask: ColorWrites::all(),
+				}],
+			}),
+			multiview: None,
+		});
+
+		let uniforms = device.create_buffer_init(&BufferInitDescriptor {
+			label: Some("debug draw uniforms"),
+			contents: &[0; 64],
+			usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+		});
+		let bg = BindGroupBuilder::new().append_buffer(&uniforms).build(
+			device,
+			Some("debug draw bg"),
+			&bgl,
+		);
+
+		let capacity = 1024;
+		Self {
+			pipeline,
+			uniforms,
+			bg,
+			vertices: create_vertex_buffer(renderer, capacity),
+			capacity,
+			queued: Vec::new(),
+			vertex_count: 0,
+		}
+	}
+
+	/// Drops everything queued so far.
+	pub fn clear(&mut self) {
+		self.queued.clear();
+		self.vertex_count = 0;
+	}
+
+	/// `color` is linear and alpha blended.
+	pub fn line(&mut self, start: Vec3, end: Vec3, color: Vec4) {
+		for position in [start, end] {
+			let values = position.to_array().into_iter().chain(color.to_array());
+			self.queued.extend(values.flat_map(f32::to_ne_bytes));
+		}
+		self.vertex_count += 2;
+	}
+
+	/// Line with a small head at `end`.
+	pub fn arrow(&mut self, start: Vec3, end: Vec3, color: Vec4) {
+		self.line(start, end, color);
+
+		let direction = end - start;
+		let length = direction.length();
+		if length <= f32::EPSILON {
+			return;
+		}
+		let direction = direction / length;
+		let side = direction.any_orthonormal_vector();
+		let up = direction.cross(side);
+		let head = length * 0.1;
+		for offset in [side, -side, up, -up] {
+			self.line(end, end - (direction - offset * 0.5) * head, color);
+		}
+	}
+
+	/// Box between eight corners, the first four making up one face and the
+	/// last four the opposite one in the same winding.
+	pub fn hexahedron(&mut self, corners: [Vec3; 8], color: Vec4) {
+		for i in 0..4 {
+			let next = (i + 1) % 4;
+			self.line(corners[i], corners[next], color);
+			self.line(corners[i + 4], corners[next + 4], color);
+			self.line(corners[i], corners[i + 4], color);
+		}
+	}
+
+	/// Outlines the volume a view projection sees between two depths in
+	/// normalized device coordinates.
+	pub fn frustum(&mut self, view_proj: Mat4, near_depth: f32, far_depth: f32, color: Vec4) {
+		let inverse = view_proj.inverse();
+		let mut corners = [Vec3::ZERO; 8];
+		for (i, corner) in corners.iter_mut().enumerate() {
+			let depth = if i < 4 { near_depth } else { far_depth };
+			let (x, y) = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)][i % 4];
+			*corner = inverse.project_point3(Vec3::new(x, y, depth));
+		}
+		self.hexahedron(corners, color);
+	}
+
+	/// Square patch of the plane `normal.dot(p) == distance` around the point
+	/// closest to the origin, with an arrow along the normal.
+	pub fn plane(&mut self, normal: Vec3, distance: f32, size: f32, color: Vec4) {
+		let normal = normal.normalize_or_zero();
+		let center = normal * distance;
+		let (u, v) = normal.any_orthonormal_pair();
+
+		let half = size * 0.5;
+		let lines = 8;
+		for i in 0..=lines {
+			let offset = -half + size * i as f32 / lines as f32;
+			self.line(
+				center + u * offset - v * half,
+				center + u * offset + v * half,
+				color,
+			);
+			self.line(
+				center + v * offset - u * half,
+				center + v * offset + u * half,
+				color,
+			);
+		}
+		self.arrow(center, center + normal * half * 0.5, color);
+	}
+
+	/// Uploads the queued lines and the camera of the viewport about to be
+	/// rendered. Call once per viewport per frame, right before building its
+	/// graph.
+	pub fn update(&mut self, renderer: &Renderer, staging: &mut StagingPool, view_proj: Mat4) {
+		let matrix: Vec<u8> = view_proj
+			.to_cols_array()
+			.iter()
+			.flat_map(|v| v.to_ne_bytes())
+			.collect();
+		renderer.queue.write_buffer(&self.uniforms, 0, &matrix);
+
+		if self.vertex_count as usize > self.capacity {
+			self.capacity = (self.vertex_count as usize).next_power_of_two();
+			self.vertices = create_vertex_buffer(renderer, self.capacity);
+		}
+		if !self.queued.is_empty() {
+			staging.write(renderer, &self.vertices, 0, &self.queued);
+		}
+	}
+
+	/// Draws the queued lines into the scene's hdr color. Must be added after
+	/// forward rendering.
+	pub fn add_to_graph<'node>(
+		&'node self,
+		graph: &mut RenderGraph<'node>,
+		state: &BaseRenderGraphIntermediateState,
+	) {
+		if self.vertex_count == 0 {
+			return;
+		}
+
+		let mut builder = graph.add_node("Debug Draw");
+
+		let color_handle = builder.add_render_target_output(state.color);
+		let depth_handle = builder.add_render_target_input(state.depth);
+
+		let rpass_handle = builder.add_renderpass(RenderPassTargets {
+			targets: vec![RenderPassTarget {
+				color: color_handle,
+				clear: Color::BLACK,
+				resolve: None,
+			}],
+			depth_stencil: Some(RenderPassDepthTarget {
+				target: DepthHandle::RenderTarget(depth_handle),
+				depth_clear: Some(0.0),
+				stencil_clear: None,
+			}),
+		});
+
+		let pt_handle = builder.passthrough_ref(self);
+
+		builder.build(
+			move |pt, _renderer, encoder_or_pass, _temps, _ready, _graph_data| {
+				let this = pt.get(pt_handle);
+				let rpass = encoder_or_pass.get_rpass(rpass_handle);
+
+				rpass.set_pipeline(&this.pipeline);
+				rpass.set_bind_group(0, &this.bg, &[]);
+				rpass.set_vertex_buffer(0, this.vertices.slice(..));
+				rpass.draw(0..this.vertex_count, 0..1);
+			},
+		);
+	}
+}
+
+fn create_vertex_buffer(renderer: &Renderer, capacity: usize) -> Buffer {
+	renderer.device.create_buffer(&BufferDescriptor {
+		label: Some("debug draw vertices"),
+		size: capacity as BufferAddress * VERTEX_SIZE,
+		usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+		mapped_at_creation: false,
+	})
+}
@@ -0,0 +1,265 @@
+use std::path::Path;
+
+use rend3::{
+	InstanceAdapterDevice, Renderer, RendererProfile, GPU_DRIVEN_REQUIRED_FEATURES,
+	GPU_REQUIRED_LIMITS,
+};
+use serde_json::Value;
+use wgpu::Features;
+
+use crate::config::{load_config_entry, save_config_entry, ConfigError};
+
+const CONFIG_KEY: &str = "renderer_profile";
+
+/// Which rend3 profile to ask for when the device is created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfilePreference {
+	/// gpu driven when the adapter supports it, cpu driven otherwise
+	Auto,
+	GpuDriven,
+	CpuDriven,
+}
+
+impl ProfilePreference {
+	pub const ALL: [Self; 3] = [Self::Auto, Self::GpuDriven, Self::CpuDriven];
+
+	pub fn label(self) -> &'static str {
+		match self {
+			Self::Auto => "auto",
+			Self::GpuDriven => "gpu driven",
+			Self::CpuDriven => "cpu driven",
+		}
+	}
+
+	pub fn profile(self) -> Option<RendererProfile> {
+		match self {
+			Self::Auto => None,
+			Self::GpuDriven => Some(RendererProfile::GpuDriven),
+			Self::CpuDriven => Some(RendererProfile::CpuDriven),
+		}
+	}
+
+	/// Reads the preference from a config file, `Auto` if it's missing.
+	pub fn load(path: &Path) -> Self {
+		load_config_entry(path, CONFIG_KEY)
+			.and_then(|value| {
+				let label = value.as_str()?.to_owned();
+				Self::ALL
+					.into_iter()
+					.find(|preference| preference.label() == label)
+			})
+			.unwrap_or(Self::Auto)
+	}
+
+	pub fn save(self, path: &Path) -> Result<(), ConfigError> {
+		save_config_entry(path, CONFIG_KEY, Value::from(self.label()))
+	}
+}
+
+/// What the adapter lacks of rend3's gpu driven profile, which indexes every
+/// texture from one large array and culls on the gpu. Without it rend3 falls
+/// back to the cpu driven profile, which binds each material's textures on
+/// their own and culls on the cpu.
+#[derive(Debug, Clone)]
+pub struct GpuCapabilities {
+	pub preference: ProfilePreference,
+	pub profile: RendererProfile,
+	pub missing_features: Features,
+	/// limits below what the gpu driven profile needs, as the limit, the
+	/// adapter's value and the needed one
+	pub low_limits: Vec<(&'static str, u32, u32)>,
+}
+
+impl GpuCapabilities {
+	/// Checks the adapter of a created device against the gpu driven profile.
+	pub fn check(iad: &InstanceAdapterDevice, preference: ProfilePreference) -> Self {
+		let limits = iad.adapter.limits();
+		let required = GPU_REQUIRED_LIMITS;
+		let low_limits = [
+			(
+				"2d texture size",
+				limits.max_texture_dimension_2d,
+				required.max_texture_dimension_2d,
+			),
+			(
+				"bind groups",
+				limits.max_bind_groups,
+				required.max_bind_groups,
+			),
+			(
+				"sampled textures per stage",
+				limits.max_sampled_textures_per_shader_stage,
+				required.max_sampled_textures_per_shader_stage,
+			),
+			(
+				"storage buffers per stage",
+				limits.max_storage_buffers_per_shader_stage,
+				required.max_storage_buffers_per_shader_stage,
+			),
+			(
+				"storage buffer binding size",
+				limits.max_storage_buffer_binding_size,
+				required.max_storage_buffer_binding_size,
+			),
+			(
+				"vertex buffers",
+				limits.max_vertex_buffers,
+				required.max_vertex_buffers,
+			),
+			(
+				"push constant size",
+				limits.max_push_constant_size,
+				required.max_push_constant_size,
+			),
+		]
+		.into_iter()
+		.filter(|(_, adapter, required)| adapter < required)
+		.collect();
+
+		Self {
+			preference,
+			profile: iad.profile,
+			missing_features: GPU_DRIVEN_REQUIRED_FEATURES - iad.adapter.features(),
+			low_limits,
+		}
+	}
+
+	/// Why the renderer runs cpu driven, `None` when it's gpu driven.
+	pub fn fallback_reason(&self) -> Option<String> {
+		if self.profile == RendererProfile::GpuDriven {
+			return None;
+		}
+		if self.preference == ProfilePreference::CpuDriven {
+			return Some("cpu driven as set in the config".into());
+		}
+		let mut reasons = Vec::new();
+		if !self.missing_features.is_empty() {
+			reasons.push(format!("missing features {:?}", self.missing_features));
+		}
+		for (name, adapter, required) in &self.low_limits {
+			reasons.push(format!("{} is {}, needs {}", name, adapter, required));
+		}
+		Some(format!(
+			"the adapter can't run gpu driven: {}",
+			reasons.join(", ")
+		))
+	}
+
+	pub fn log(&self) {
+		match self.fallback_reason() {
+			None => log::info!("rendering gpu driven"),
+			Some(reason) if self.preference == ProfilePreference::CpuDriven => {
+				log::info!("rendering {}", reason)
+			}
+			Some(reason) => log::warn!(
+				"rendering cpu driven, textures are bound per material and culling runs on \
+				 the cpu, {}",
+				reason
+			),
+		}
+	}
+}
+
+/// Adapter, renderer profile and the limits scenes run into, like how many
+/// textures materials can use.
+pub struct GpuInfoWindow {
+	pub open: bool,
+	capabilities: Option<GpuCapabilities>,
+	/// preference picked in the window, saved for the next run
+	preference: ProfilePreference,
+	status: String,
+}
+
+impl GpuInfoWindow {
+	/// `capabilities` is `None` if the device wasn't created by the app.
+	pub fn new(capabilities: Option<GpuCapabilities>) -> Self {
+		Self {
+			open: false,
+			preference: capabilities
+				.as_ref()
+				.map_or(ProfilePreference::Auto, |capabilities| {
+					capabilities.preference
+				}),
+			capabilities,
+			status: String::new(),
+		}
+	}
+
+	pub fn window(&mut self, ctx: &egui::CtxRef, renderer: &Renderer, config: &Path) {
+		let mut open = self.open;
+		egui::Window::new("gpu info")
+			.open(&mut open)
+			.resizable(false)
+			.show(ctx, |ui| {
+				let info = &renderer.adapter_info;
+				let limits = &renderer.limits;
+				egui::Grid::new("gpu_info").show(ui, |ui| {
+					ui.label("adapter");
+					ui.label(&info.name);
+					ui.end_row();
+					ui.label("vendor");
+					ui.label(format!("{:?}", info.vendor));
+					ui.end_row();
+					ui.label("type");
+					ui.label(format!("{:?}", info.device_type));
+					ui.end_row();
+					ui.label("backend");
+					ui.label(format!("{:?}", info.backend));
+					ui.end_row();
+					ui.label("profile");
+					ui.label(format!("{:?}", renderer.profile));
+					ui.end_row();
+					ui.label("textures");
+					ui.label(match renderer.profile {
+						RendererProfile::GpuDriven => format!(
+							"{} in the array materials index",
+							limits.max_sampled_textures_per_shader_stage
+						),
+						RendererProfile::CpuDriven => "bound per material".into(),
+					});
+					ui.end_row();
+					ui.label("texture size");
+					ui.label(format!("{} px", limits.max_texture_dimension_2d));
+					ui.end_row();
+					ui.label("object buffers");
+					ui.label(format!(
+						"{} MiB each",
+						limits.max_storage_buffer_binding_size >> 20
+					));
+					ui.end_row();
+				});
+
+				if let Some(reason) = self
+					.capabilities
+					.as_ref()
+					.and_then(GpuCapabilities::fallback_reason)
+				{
+					ui.colored_label(egui::Color32::YELLOW, reason);
+				}
+
+				ui.separator();
+				let before = self.preference;
+				egui::ComboBox::from_label("profile on start")
+					.selected_text(self.preference.label())
+					.show_ui(ui, |ui| {
+						for preference in ProfilePreference::ALL {
+							ui.selectable_value(
+								&mut self.preference,
+								preference,
+								preference.label(),
+							);
+						}
+					});
+				if self.preference != before {
+					self.status = match self.preference.save(config) {
+						Ok(()) => "applies on restart".into(),
+						Err(e) => format!("couldn't save: {}", e),
+					};
+				}
+				if !self.status.is_empty() {
+					ui.label(&self.status);
+				}
+			});
+		self.open = open;
+	}
+}
@@ -0,0 +1,184 @@
+use std::fs;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+/// file recordings are saved to unless another is typed in
+const DEFAULT_PATH: &str = "input.jsonl";
+
+struct RecordedFrame {
+	delta_time: Duration,
+	input: Value,
+}
+
+enum RecorderState {
+	Idle,
+	Recording(Vec<RecordedFrame>),
+	Playing {
+		frames: Vec<RecordedFrame>,
+		next: usize,
+	},
+}
+
+/// Records the input state and frame time of every update to a file and
+/// plays them back in place of the real ones, to reproduce bugs that depend
+/// on exact input timing. Files have one json object per frame.
+///
+/// Playback starts from wherever the camera and scene are, and egui isn't
+/// recorded, so clicks on windows don't replay.
+pub struct InputRecorder {
+	pub open: bool,
+	path: String,
+	state: RecorderState,
+	/// result of the last save or load
+	status: String,
+}
+
+impl Default for InputRecorder {
+	fn default() -> Self {
+		Self {
+			open: false,
+			path: DEFAULT_PATH.into(),
+			state: RecorderState::Idle,
+			status: String::new(),
+		}
+	}
+}
+
+impl InputRecorder {
+	/// Whether updates should use [`InputRecorder::playback`] instead of the
+	/// real input.
+	pub fn is_playing(&self) -> bool {
+		matches!(self.state, RecorderState::Playing { .. })
+	}
+
+	pub fn is_recording(&self) -> bool {
+		matches!(self.state, RecorderState::Recording(_))
+	}
+
+	/// Keeps a frame's input while recording. Call once per update with the
+	/// input the update used.
+	pub fn record(&mut self, delta_time: Duration, input: Value) {
+		if let RecorderState::Recording(frames) = &mut self.state {
+			frames.push(RecordedFrame { delta_time, input });
+		}
+	}
+
+	/// The next recorded frame's time and input while playing, `None` once the
+	/// recording runs out.
+	pub fn playback(&mut self) -> Option<(Duration, Value)> {
+		let (frames, next) = match &mut self.state {
+			RecorderState::Playing { frames, next } => (frames, next),
+			_ => return None,
+		};
+		match frames.get(*next) {
+			Some(frame) => {
+				*next += 1;
+				Some((frame.delta_time, frame.input.clone()))
+			}
+			None => {
+				self.status = format!("played {} frames", frames.len());
+				self.state = RecorderState::Idle;
+				None
+			}
+		}
+	}
+
+	/// Stops playing, like when the snapshot being played is invalid.
+	pub fn stop(&mut self, reason: &str) {
+		log::warn!("input playback stopped: {}", reason);
+		self.status = reason.into();
+		self.state = RecorderState::Idle;
+	}
+
+	fn save(&mut self, frames: &[RecordedFrame]) {
+		let text: String = frames
+			.iter()
+			.map(|frame| {
+				let line = json!({
+					"dt": frame.delta_time.as_secs_f64(),
+					"input": frame.input,
+				});
+				format!("{}\n", line)
+			})
+			.collect();
+		self.status = match fs::write(&self.path, text) {
+			Ok(()) => format!("saved {} frames to {}", frames.len(), self.path),
+			Err(e) => format!("couldn't save {}: {}", self.path, e),
+		};
+	}
+
+	fn load(&self) -> Result<Vec<RecordedFrame>, String> {
+		let text = fs::read_to_string(&self.path).map_err(|e| e.to_string())?;
+		text.lines()
+			.enumerate()
+			.filter(|(_, line)| !line.trim().is_empty())
+			.map(|(number, line)| {
+				let frame = serde_json::from_str::<Value>(line).ok().and_then(|value| {
+					Some(RecordedFrame {
+						delta_time: Duration::from_secs_f64(value.get("dt")?.as_f64()?.max(0.0)),
+						input: value.get("input")?.clone(),
+					})
+				});
+				frame.ok_or_else(|| format!("line {} is invalid", number + 1))
+			})
+			.collect()
+	}
+
+	pub fn window(&mut self, ctx: &egui::CtxRef) {
+		let mut open = self.open;
+		egui::Window::new("input recorder")
+			.open(&mut open)
+			.resizable(false)
+			.show(ctx, |ui| {
+				ui.horizontal(|ui| {
+					ui.label("file");
+					ui.add_enabled(
+						matches!(self.state, RecorderState::Idle),
+						egui::TextEdit::singleline(&mut self.path),
+					);
+				});
+				match std::mem::replace(&mut self.state, RecorderState::Idle) {
+					RecorderState::Idle => {
+						ui.horizontal(|ui| {
+							if ui.button("record").clicked() {
+								self.state = RecorderState::Recording(Vec::new());
+								self.status.clear();
+							}
+							if ui.button("play").clicked() {
+								match self.load() {
+									Ok(frames) => {
+										self.status = format!("playing {} frames", frames.len());
+										self.state = RecorderState::Playing { frames, next: 0 };
+									}
+									Err(e) => {
+										self.status = format!("couldn't load {}: {}", self.path, e)
+									}
+								}
+							}
+						});
+					}
+					RecorderState::Recording(frames) => {
+						ui.label(format!("recording, {} frames", frames.len()));
+						if ui.button("stop and save").clicked() {
+							self.save(&frames);
+						} else {
+							self.state = RecorderState::Recording(frames);
+						}
+					}
+					RecorderState::Playing { frames, next } => {
+						ui.label(format!("playing frame {} of {}", next, frames.len()));
+						if ui.button("stop").clicked() {
+							self.status = format!("stopped at frame {}", next);
+						} else {
+							self.state = RecorderState::Playing { frames, next };
+						}
+					}
+				}
+				if !self.status.is_empty() {
+					ui.label(&self.status);
+				}
+			});
+		self.open = open;
+	}
+}
@@ -0,0 +1,121 @@
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use winit::event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent};
+
+/// measurements kept for the summary
+const HISTORY: usize = 20;
+/// size of the flashed square, in points
+const FLASH_SIZE: f32 = 64.0;
+
+struct Measurement {
+	milliseconds: f32,
+	/// frames presented from the key press up to and including the flash
+	frames: u64,
+}
+
+/// Measures input latency: pressing F9 while the window is open flashes a
+/// square in the corner of the screen on the next frame that shows it, and
+/// reports the time and frames from the key event arriving to that frame
+/// being presented. The square can also be filmed or put under a photodiode
+/// to check the reading against the display.
+#[derive(Default)]
+pub struct LatencyTester {
+	pub open: bool,
+	/// when the key press arrived and the presented frame count then, until
+	/// a frame with the flash is presented
+	pending: Option<(Instant, u64)>,
+	presented: u64,
+	measurements: VecDeque<Measurement>,
+}
+
+impl LatencyTester {
+	/// Notes when the test key is pressed. Call as soon as events arrive.
+	pub fn handle_event<T>(&mut self, event: &Event<T>) {
+		if !self.open || self.pending.is_some() {
+			return;
+		}
+		if let Event::WindowEvent {
+			event:
+				WindowEvent::KeyboardInput {
+					input:
+						KeyboardInput {
+							state: ElementState::Pressed,
+							virtual_keycode: Some(VirtualKeyCode::F9),
+							..
+						},
+					..
+				},
+			..
+		} = event
+		{
+			self.pending = Some((Instant::now(), self.presented));
+		}
+	}
+
+	/// Draws the square, white while a key press waits to be shown and black
+	/// otherwise.
+	pub fn draw(&self, ctx: &egui::CtxRef) {
+		if !self.open {
+			return;
+		}
+		let color = match self.pending {
+			Some(_) => egui::Color32::WHITE,
+			None => egui::Color32::BLACK,
+		};
+		let rect = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::Vec2::splat(FLASH_SIZE));
+		ctx.layer_painter(egui::LayerId::new(
+			egui::Order::Foreground,
+			egui::Id::new("latency_flash"),
+		))
+		.rect_filled(rect, 0.0, color);
+	}
+
+	/// Call after a frame was presented to the window, not for frames rendered
+	/// off screen.
+	pub fn presented(&mut self) {
+		self.presented += 1;
+		if let Some((pressed, frame)) = self.pending.take() {
+			if self.measurements.len() == HISTORY {
+				self.measurements.pop_front();
+			}
+			self.measurements.push_back(Measurement {
+				milliseconds: pressed.elapsed().as_secs_f32() * 1000.0,
+				frames: self.presented - frame,
+			});
+		}
+	}
+
+	pub fn window(&mut self, ctx: &egui::CtxRef) {
+		let mut open = self.open;
+		egui::Window::new("latency tester")
+			.open(&mut open)
+			.resizable(false)
+			.show(ctx, |ui| {
+				ui.label("press F9 to flash the corner square");
+				if ui.button("clear").clicked() {
+					self.measurements.clear();
+				}
+				let last = match self.measurements.back() {
+					Some(last) => last,
+					None => return,
+				};
+				ui.label(format!(
+					"last: {:.1}ms, {} frames",
+					last.milliseconds, last.frames
+				));
+				let times = self.measurements.iter().map(|m| m.milliseconds);
+				let min = times.clone().fold(f32::MAX, f32::min);
+				let max = times.clone().fold(0.0, f32::max);
+				let average = times.sum::<f32>() / self.measurements.len() as f32;
+				ui.label(format!(
+					"{} presses: min {:.1}ms, avg {:.1}ms, max {:.1}ms",
+					self.measurements.len(),
+					min,
+					average,
+					max
+				));
+			});
+		self.open = open;
+	}
+}
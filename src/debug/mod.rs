@@ -0,0 +1,21 @@
+//! Debugging tools for inspecting what the renderer is doing.
+
+mod color_audit;
+mod debug_draw;
+mod gpu_info;
+mod input_recorder;
+mod latency_tester;
+mod pass_budgets;
+mod pixel_picker;
+mod target_inspector;
+mod volume_viz;
+
+pub use color_audit::{ColorSpace, ColorSpaceAudit, SlotReport};
+pub use debug_draw::DebugDraw;
+pub use gpu_info::{GpuCapabilities, GpuInfoWindow, ProfilePreference};
+pub use input_recorder::InputRecorder;
+pub use latency_tester::LatencyTester;
+pub use pass_budgets::PassBudgets;
+pub use pixel_picker::{clicked_pixel, PickResult, PickSource, PixelPicker};
+pub use target_inspector::{InspectedTarget, InspectorSources, TargetInspector};
+pub use volume_viz::{VizCamera, VizLight, VolumeViz};
@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use rend3::util::typedefs::RendererStatistics;
+use rend3::Renderer;
+use serde_json::{json, Map, Value};
+use wgpu::Features;
+
+use crate::config::{load_config_entry, save_config_entry, ConfigError};
+
+const CONFIG_KEY: &str = "pass_budgets";
+/// how long a warning stays in the corner
+const TOAST_DURATION: Duration = Duration::from_secs(6);
+
+struct PassTiming {
+	label: String,
+	milliseconds: f32,
+	/// frames in a row the pass went over its budget
+	over: u32,
+}
+
+/// Gpu time of each render graph pass in the main viewport, from rend3's
+/// profiler, checked against budgets set per pass. A pass over its budget
+/// for enough frames in a row is logged and shown in the corner with a
+/// button to open the timings, so a slow shader is noticed while working
+/// on it. Budgets are saved to the config.
+pub struct PassBudgets {
+	pub open: bool,
+	/// passes of the last frame timed, in the order they ran
+	passes: Vec<PassTiming>,
+	/// budgets in milliseconds by pass label
+	budgets: HashMap<String, f32>,
+	/// frames a pass may go over its budget in a row before it's reported
+	frames: u32,
+	toast: Option<(String, Instant)>,
+	status: String,
+}
+
+impl PassBudgets {
+	/// Reads the budgets from a config file, none if it's missing.
+	pub fn load(path: &Path) -> Self {
+		let config = load_config_entry(path, CONFIG_KEY);
+		let budgets = config
+			.as_ref()
+			.and_then(|config| config.get("budgets")?.as_object())
+			.map(|budgets| {
+				budgets
+					.iter()
+					.filter_map(|(label, ms)| Some((label.clone(), ms.as_f64()? as f32)))
+					.collect()
+			})
+			.unwrap_or_default();
+		let frames = config
+			.as_ref()
+			.and_then(|config| config.get("frames")?.as_u64())
+			.map_or(30, |frames| frames.max(1) as u32);
+		Self {
+			open: false,
+			passes: Vec::new(),
+			budgets,
+			frames,
+			toast: None,
+			status: String::new(),
+		}
+	}
+
+	pub fn save(&self, path: &Path) -> Result<(), ConfigError> {
+		let budgets: Map<String, Value> = self
+			.budgets
+			.iter()
+			.map(|(label, ms)| (label.clone(), json!(ms)))
+			.collect();
+		save_config_entry(
+			path,
+			CONFIG_KEY,
+			json!({ "frames": self.frames, "budgets": budgets }),
+		)
+	}
+
+	/// Takes the timings `RenderGraph::execute` returns. They're a few frames
+	/// old, the gpu has to finish a frame before its timings can be read.
+	/// Passes with the same label are added together.
+	pub fn record(&mut self, statistics: Option<RendererStatistics>) {
+		let statistics = match statistics {
+			Some(statistics) if !statistics.is_empty() => statistics,
+			_ => return,
+		};
+		let mut passes: Vec<PassTiming> = Vec::with_capacity(statistics.len());
+		for scope in &statistics {
+			let milliseconds = ((scope.time.end - scope.time.start) * 1000.0) as f32;
+			match passes.iter_mut().find(|pass| pass.label == scope.label) {
+				Some(pass) => pass.milliseconds += milliseconds,
+				None => passes.push(PassTiming {
+					label: scope.label.clone(),
+					milliseconds,
+					over: 0,
+				}),
+			}
+		}
+
+		for pass in &mut passes {
+			let budget = match self.budgets.get(&pass.label) {
+				Some(budget) => *budget,
+				None => continue,
+			};
+			if pass.milliseconds <= budget {
+				continue;
+			}
+			let before = self
+				.passes
+				.iter()
+				.find(|previous| previous.label == pass.label)
+				.map_or(0, |previous| previous.over);
+			pass.over = before + 1;
+			// once per run of frames over
+			if pass.over == self.frames {
+				let message = format!(
+					"{} took {:.2}ms, over its {:.2}ms budget for {} frames",
+					pass.label, pass.milliseconds, budget, self.frames
+				);
+				log::warn!("{}", message);
+				self.toast = Some((message, Instant::now()));
+			}
+		}
+		self.passes = passes;
+	}
+
+	/// Gpu time of the last frame timed, `None` until one is.
+	pub fn total_milliseconds(&self) -> Option<f32> {
+		(!self.passes.is_empty()).then(|| self.passes.iter().map(|pass| pass.milliseconds).sum())
+	}
+
+	pub fn window(&mut self, ctx: &egui::CtxRef, renderer: &Renderer, config: &Path) {
+		self.toast(ctx);
+
+		let mut open = self.open;
+		egui::Window::new("gpu timings")
+			.open(&mut open)
+			.resizable(false)
+			.show(ctx, |ui| {
+				if !renderer.features.contains(Features::TIMESTAMP_QUERY) {
+					ui.label("the adapter doesn't support timestamp queries");
+					return;
+				}
+				let mut changed = false;
+				changed |= ui
+					.add(
+						egui::Slider::new(&mut self.frames, 1..=600)
+							.logarithmic(true)
+							.text("frames over before warning"),
+					)
+					.changed();
+				let total = self.total_milliseconds().unwrap_or(0.0);
+				ui.label(format!("{:.2}ms in all", total));
+
+				egui::ScrollArea::vertical()
+					.max_height(400.0)
+					.show(ui, |ui| {
+						egui::Grid::new("pass_budgets")
+							.striped(true)
+							.show(ui, |ui| {
+								ui.label("pass");
+								ui.label("gpu");
+								ui.label("budget");
+								ui.end_row();
+								for pass in &self.passes {
+									let budget = self.budgets.get(&pass.label).copied();
+									ui.label(&pass.label);
+									let time = format!("{:.3}ms", pass.milliseconds);
+									match budget {
+										Some(budget) if pass.milliseconds > budget => {
+											ui.colored_label(egui::Color32::YELLOW, time)
+										}
+										_ => ui.label(time),
+									};
+									ui.horizontal(|ui| {
+										let mut budgeted = budget.is_some();
+										if ui.checkbox(&mut budgeted, "").changed() {
+											match budgeted {
+												// starts out with some headroom over now
+												true => self.budgets.insert(
+													pass.label.clone(),
+													pass.milliseconds * 1.5,
+												),
+												false => self.budgets.remove(&pass.label),
+											};
+											changed = true;
+										}
+										if let Some(budget) = self.budgets.get_mut(&pass.label) {
+											changed |= ui
+												.add(
+													egui::DragValue::new(budget)
+														.speed(0.01)
+														.clamp_range(0.0..=100.0)
+														.suffix("ms"),
+												)
+												.changed();
+										}
+									});
+									ui.end_row();
+								}
+							});
+					});
+
+				if changed {
+					self.status = match self.save(config) {
+						Ok(()) => String::new(),
+						Err(e) => format!("couldn't save: {}", e),
+					};
+				}
+				if !self.status.is_empty() {
+					ui.label(&self.status);
+				}
+			});
+		self.open = open;
+	}
+
+	/// The last warning, until it times out or the timings are opened.
+	fn toast(&mut self, ctx: &egui::CtxRef) {
+		let message = match &self.toast {
+			Some((message, at)) if at.elapsed() < TOAST_DURATION && !self.open => message,
+			_ => {
+				self.toast = None;
+				return;
+			}
+		};
+		let mut open_timings = false;
+		egui::Area::new("pass_budget_toast")
+			.anchor(egui::Align2::RIGHT_TOP, egui::vec2(-8.0, 32.0))
+			.show(ctx, |ui| {
+				egui::Frame::popup(ui.style()).show(ui, |ui| {
+					ui.set_max_width(260.0);
+					ui.colored_label(egui::Color32::YELLOW, message);
+					open_timings = ui.button("open gpu timings").clicked();
+				});
+			});
+		if open_timings {
+			self.open = true;
+			self.toast = None;
+		}
+	}
+}
@@ -0,0 +1,416 @@
+use std::borrow::Cow;
+
+use glam::{Mat4, UVec2, Vec2, Vec3, Vec4};
+use rend3::graph::{RenderGraph, RenderTargetHandle};
+use rend3::util::bind_merge::{BindGroupBuilder, BindGroupLayoutBuilder};
+use rend3::Renderer;
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+	BindGroupLayout, BindingType, Buffer, BufferBindingType, BufferDescriptor, BufferUsages, Color,
+	ColorTargetState, ColorWrites, Extent3d, FragmentState, ImageCopyBuffer, ImageCopyTexture,
+	ImageDataLayout, LoadOp, Maintain, MapMode, MultisampleState, Operations, Origin3d,
+	PipelineLayoutDescriptor, PrimitiveState, RenderPassColorAttachment, RenderPassDescriptor,
+	RenderPipeline, RenderPipelineDescriptor, ShaderModuleDescriptor, ShaderSource, ShaderStages,
+	Texture, TextureAspect, TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType,
+	TextureUsages, TextureView, TextureViewDescriptor, TextureViewDimension, VertexState,
+};
+
+const PICK_FORMAT: TextureFormat = TextureFormat::Rgba32Float;
+
+/// Which color the picker reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PickSource {
+	/// lit scene before post processing
+	Scene,
+	/// color handed to tonemapping, after post processing
+	Final,
+}
+
+/// Values read back from one pixel.
+#[derive(Debug, Clone, Copy)]
+pub struct PickResult {
+	pub pixel: UVec2,
+	pub source: PickSource,
+	/// linear hdr color
+	pub linear: Vec4,
+	/// color as displayed, clamped and encoded to srgb
+	pub srgb: Vec3,
+	/// reverse z, 0.0 is infinitely far away
+	pub depth: f32,
+	/// `None` for pixels with nothing drawn
+	pub world_position: Option<Vec3>,
+}
+
+/// Reads back the color and depth under the cursor in the main viewport.
+///
+/// Clicking the viewport while the picker is open queues a pick. The pixel is
+/// copied out during the next frame's graph and read back once it finishes,
+/// stalling that frame until the gpu catches up.
+pub struct PixelPicker {
+	pub open: bool,
+	pub source: PickSource,
+	pub result: Option<PickResult>,
+
+	bgl: BindGroupLayout,
+	pipeline: RenderPipeline,
+	uniforms: Buffer,
+	texture: Texture,
+	view: TextureView,
+	readback: Buffer,
+
+	/// pixel waiting to be read, relative to the main viewport
+	pending: Option<UVec2>,
+	/// inverse view projection of the frame being picked
+	inverse_view_proj: Mat4,
+	resolution: UVec2,
+}
+
+impl PixelPicker {
+	pub fn new(renderer: &Renderer) -> Self {
+		let device = &renderer.device;
+
+		let bgl = BindGroupLayoutBuilder::new()
+			.append(
+				ShaderStages::FRAGMENT,
+				BindingType::Texture {
+					sample_type: TextureSampleType::Float { filterable: false },
+					view_dimension: TextureViewDimension::D2,
+					multisampled: false,
+				},
+				None,
+			)
+			.append(
+				ShaderStages::FRAGMENT,
+				BindingType::Texture {
+					sample_type: TextureSampleType::Depth,
+					view_dimension: TextureViewDimension::D2,
+					multisampled: false,
+				},
+				None,
+			)
+			.append(
+				ShaderStages::FRAGMENT,
+				BindingType::Buffer {
+					ty: BufferBindingType::Uniform,
+					has_dynamic_offset: false,
+					min_binding_size: None,
+				},
+				None,
+			)
+			.build(device, Some("pixel picker bgl"));
+
+		let module = device.create_shader_module(&ShaderModuleDescriptor {
+			label: Some("pixel picker"),
+			source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("pixel_picker.wgsl"))),
+		});
+
+		let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+			label: Some("pixel picker"),
+			bind_group_layouts: &[&bgl],
+			push_constant_ranges: &[],
+		});
+
+		let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+			label: Some("pixel picker"),
+			layout: Some(&layout),
+			vertex: VertexState {
+				module: &module,
+				entry_point: "vs_main",
+				buffers: &[],
+			},
+			primitive: PrimitiveState::default(),
+			depth_stencil: None,
+			multisample: MultisampleState::default(),
+			fragment: Some(FragmentState {
+				module: &module,
+				entry_point: "fs_main",
+				targets: &[ColorTargetState {
+					format: PICK_FORMAT,
+					blend: None,
+					write_mask: ColorWrites::all(),
+				}],
+			}),
+			multiview: None,
+		});
+
+		let uniforms = device.create_buffer_init(&BufferInitDescriptor {
+			label: Some("pixel picker uniforms"),
+			contents: &[0; 16],
+			usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+		});
+
+		let texture = device.create_texture(&TextureDescriptor {
+			label: Some("pixel picker"),
+			size: Extent3d {
+				width: 2,
+				height: 1,
+				depth_or_array_layers: 1,
+			},
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: TextureDimension::D2,
+			format: PICK_FORMAT,
+			usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+		});
+		let view = texture.create_view(&TextureViewDescriptor::default());
+
+		let readback = device.create_buffer(&BufferDescriptor {
+			label: Some("pixel picker readback"),
+			size: 32,
+			usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+			mapped_at_creation: false,
+		});
+
+		Self {
+			open: false,
+			source: PickSource::Scene,
+			result: None,
+			bgl,
+			pipeline,
+			uniforms,
+			texture,
+			view,
+			readback,
+			pending: None,
+			inverse_view_proj: Mat4::IDENTITY,
+			resolution: UVec2::ONE,
+		}
+	}
+
+	/// Shows the last pick and queues a new one when the viewport is clicked.
+	/// `viewport` is the main viewport's `(offset, size)` in physical pixels.
+	pub fn window(&mut self, ctx: &egui::CtxRef, viewport: (UVec2, UVec2)) {
+		if !self.open {
+			return;
+		}
+
+		if let Some(pixel) = clicked_pixel(ctx, viewport) {
+			self.pick(pixel);
+		}
+
+		let mut open = self.open;
+		egui::Window::new("pixel picker")
+			.open(&mut open)
+			.resizable(true)
+			.show(ctx, |ui| {
+				ui.horizontal(|ui| {
+					ui.radio_value(&mut self.source, PickSource::Scene, "scene");
+					ui.radio_value(&mut self.source, PickSource::Final, "final");
+				});
+				ui.label("click the viewport to pick");
+				ui.separator();
+
+				let result = match &self.result {
+					Some(result) => result,
+					None => return,
+				};
+				egui::Grid::new("pixel_picker_grid")
+					.num_columns(2)
+					.striped(true)
+					.show(ui, |ui| {
+						ui.label("pixel");
+						ui.label(format!(
+							"{} {} ({:?})",
+							result.pixel.x, result.pixel.y, result.source
+						));
+						ui.end_row();
+						ui.label("linear");
+						ui.label(format!(
+							"{:.4} {:.4} {:.4} {:.4}",
+							result.linear.x, result.linear.y, result.linear.z, result.linear.w
+						));
+						ui.end_row();
+						ui.label("srgb");
+						let srgb = (result.srgb * 255.0).round();
+						ui.horizontal(|ui| {
+							ui.label(format!("{} {} {}", srgb.x, srgb.y, srgb.z));
+							let (rect, _) = ui
+								.allocate_exact_size(egui::vec2(16.0, 16.0), egui::Sense::hover());
+							ui.painter().rect_filled(
+								rect,
+								0.0,
+								egui::Color32::from_rgb(srgb.x as u8, srgb.y as u8, srgb.z as u8),
+							);
+						});
+						ui.end_row();
+						ui.label("depth");
+						ui.label(format!("{:.6}", result.depth));
+						ui.end_row();
+						ui.label("world");
+						ui.label(match result.world_position {
+							Some(p) => format!("x{:.3} y{:.3} z{:.3}", p.x, p.y, p.z),
+							None => "background".into(),
+						});
+						ui.end_row();
+					});
+			});
+		self.open = open;
+	}
+
+	/// Queues a pick of `pixel` in the main viewport for the next frame.
+	pub fn pick(&mut self, pixel: UVec2) {
+		self.pending = Some(pixel);
+	}
+
+	/// Records the camera of the main viewport for reconstructing positions
+	/// and uploads the pending pixel. Call before building the main graph.
+	pub fn update(&mut self, renderer: &Renderer, view_proj: Mat4, resolution: UVec2) {
+		self.inverse_view_proj = view_proj.inverse();
+		self.resolution = resolution;
+
+		if let Some(pixel) = self.pending {
+			let data = [pixel.x as f32, pixel.y as f32, 0.0, 0.0];
+			let bytes: Vec<u8> = data.iter().flat_map(|v| v.to_ne_bytes()).collect();
+			renderer.queue.write_buffer(&self.uniforms, 0, &bytes);
+		}
+	}
+
+	/// Copies the pending pixel out of `color` and `depth`. Does nothing if no
+	/// pick is queued.
+	pub fn add_to_graph<'node>(
+		&'node self,
+		graph: &mut RenderGraph<'node>,
+		scene_color: RenderTargetHandle,
+		final_color: RenderTargetHandle,
+		depth: RenderTargetHandle,
+	) {
+		if self.pending.is_none() {
+			return;
+		}
+		let color = match self.source {
+			PickSource::Scene => scene_color,
+			PickSource::Final => final_color,
+		};
+
+		let mut builder = graph.add_node("Pixel Picker");
+
+		let color_handle = builder.add_render_target_input(color);
+		let depth_handle = builder.add_render_target_input(depth);
+		// the readback buffer lives outside the graph
+		builder.add_external_output();
+
+		let pt_handle = builder.passthrough_ref(self);
+
+		builder.build(
+			move |pt, renderer, encoder_or_pass, temps, _ready, graph_data| {
+				let this = pt.get(pt_handle);
+				let encoder = encoder_or_pass.get_encoder();
+
+				let bg = temps.add(
+					BindGroupBuilder::new()
+						.append_texture_view(graph_data.get_render_target(color_handle))
+						.append_texture_view(graph_data.get_render_target(depth_handle))
+						.append_buffer(&this.uniforms)
+						.build(&renderer.device, Some("pixel picker bg"), &this.bgl),
+				);
+
+				{
+					let mut rpass = encoder.begin_render_pass(&RenderPassDescriptor {
+						label: Some("pixel picker"),
+						color_attachments: &[RenderPassColorAttachment {
+							view: &this.view,
+							resolve_target: None,
+							ops: Operations {
+								load: LoadOp::Clear(Color::BLACK),
+								store: true,
+							},
+						}],
+						depth_stencil_attachment: None,
+					});
+					rpass.set_pipeline(&this.pipeline);
+					rpass.set_bind_group(0, bg, &[]);
+					rpass.draw(0..3, 0..1);
+				}
+
+				encoder.copy_texture_to_buffer(
+					ImageCopyTexture {
+						texture: &this.texture,
+						mip_level: 0,
+						origin: Origin3d::ZERO,
+						aspect: TextureAspect::All,
+					},
+					ImageCopyBuffer {
+						buffer: &this.readback,
+						layout: ImageDataLayout {
+							offset: 0,
+							bytes_per_row: None,
+							rows_per_image: None,
+						},
+					},
+					Extent3d {
+						width: 2,
+						height: 1,
+						depth_or_array_layers: 1,
+					},
+				);
+			},
+		);
+	}
+
+	/// Reads back the pick queued this frame and returns it. Call after the
+	/// main graph has executed.
+	pub fn finish(&mut self, renderer: &Renderer) -> Option<PickResult> {
+		let pixel = self.pending.take()?;
+
+		let slice = self.readback.slice(..);
+		let mapping = slice.map_async(MapMode::Read);
+		renderer.device.poll(Maintain::Wait);
+		if let Err(e) = pollster::block_on(mapping) {
+			log::error!("pixel picker readback failed: {:?}", e);
+			return None;
+		}
+		let values: Vec<f32> = slice
+			.get_mapped_range()
+			.chunks(4)
+			.map(|b| f32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+			.collect();
+		self.readback.unmap();
+
+		let linear = Vec4::new(values[0], values[1], values[2], values[3]);
+		let depth = values[4];
+
+		// pixel centers to ndc, y points up
+		let uv = (pixel.as_vec2() + 0.5) / self.resolution.as_vec2();
+		let ndc = Vec2::new(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0);
+		let world_position = (depth > 0.0).then(|| {
+			let p = self.inverse_view_proj * Vec4::new(ndc.x, ndc.y, depth, 1.0);
+			p.truncate() / p.w
+		});
+
+		self.result = Some(PickResult {
+			pixel,
+			source: self.source,
+			linear,
+			srgb: linear
+				.truncate()
+				.clamp(Vec3::ZERO, Vec3::ONE)
+				.to_array()
+				.map(linear_to_srgb)
+				.into(),
+			depth,
+			world_position,
+		});
+		self.result
+	}
+}
+
+/// Pixel of the viewport, given as `(offset, size)` in physical pixels, that
+/// was clicked this frame. Ignores clicks on egui windows.
+pub fn clicked_pixel(ctx: &egui::CtxRef, viewport: (UVec2, UVec2)) -> Option<UVec2> {
+	let pointer = &ctx.input().pointer;
+	if !pointer.any_click() || ctx.is_pointer_over_area() {
+		return None;
+	}
+	let pos = pointer.interact_pos()?;
+	let (offset, size) = viewport;
+	let pixel = Vec2::new(pos.x, pos.y) * ctx.pixels_per_point() - offset.as_vec2();
+	(pixel.cmpge(Vec2::ZERO).all() && pixel.cmplt(size.as_vec2()).all()).then(|| pixel.as_uvec2())
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+	if value <= 0.0031308 {
+		value * 12.92
+	} else {
+		1.055 * value.powf(1.0 / 2.4) - 0.055
+	}
+}
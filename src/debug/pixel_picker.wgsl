@@ -0,0 +1,39 @@
+// Copies the color and depth under the cursor into a 2x1 target for readback.
+
+struct VertexOutput {
+	[[builtin(position)]] position: vec4<f32>;
+};
+
+struct PickerUniforms {
+	// xy: pixel to read
+	pixel: vec4<f32>;
+};
+
+[[group(0), binding(0)]]
+var color: texture_2d<f32>;
+
+[[group(0), binding(1)]]
+var depth: texture_depth_2d;
+
+[[group(0), binding(2)]]
+var<uniform> uniforms: PickerUniforms;
+
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] vertex_index: u32) -> VertexOutput {
+	// single triangle covering the whole screen
+	let x = f32(i32(vertex_index & 1u) * 4 - 1);
+	let y = f32(i32(vertex_index & 2u) * 2 - 1);
+	var out: VertexOutput;
+	out.position = vec4<f32>(x, y, 0.0, 1.0);
+	return out;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let pixel = vec2<i32>(uniforms.pixel.xy);
+	// first texel gets the color, second the depth
+	if (in.position.x < 1.0) {
+		return textureLoad(color, pixel, 0);
+	}
+	return vec4<f32>(textureLoad(depth, pixel, 0), 0.0, 0.0, 0.0);
+}
@@ -0,0 +1,353 @@
+use std::borrow::Cow;
+
+use rend3::graph::{RenderGraph, RenderTargetHandle};
+use rend3::util::bind_merge::{BindGroupBuilder, BindGroupLayoutBuilder};
+use rend3::Renderer;
+use rend3_egui::EguiRenderRoutine;
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+	BindGroup, BindGroupLayout, BindingType, Buffer, BufferBindingType, BufferUsages, Color,
+	ColorTargetState, ColorWrites, Extent3d, FilterMode, FragmentState, LoadOp, MultisampleState,
+	Operations, PipelineLayoutDescriptor, PrimitiveState, RenderPassColorAttachment,
+	RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, ShaderModuleDescriptor,
+	ShaderSource, ShaderStages, TextureDescriptor, TextureDimension, TextureFormat,
+	TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor, TextureViewDimension,
+	VertexState,
+};
+
+const PREVIEW_SIZE: u32 = 512;
+const PREVIEW_FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
+
+/// Intermediate render target that can be shown in the inspector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InspectedTarget {
+	HdrColor,
+	Depth,
+	/// depth as stripes showing its precision
+	DepthPrecision,
+	MotionVectors,
+}
+
+impl InspectedTarget {
+	pub const ALL: [InspectedTarget; 4] = [
+		InspectedTarget::HdrColor,
+		InspectedTarget::Depth,
+		InspectedTarget::DepthPrecision,
+		InspectedTarget::MotionVectors,
+	];
+
+	pub fn label(&self) -> &'static str {
+		match self {
+			InspectedTarget::HdrColor => "hdr color",
+			InspectedTarget::Depth => "depth",
+			InspectedTarget::DepthPrecision => "depth precision",
+			InspectedTarget::MotionVectors => "motion vectors",
+		}
+	}
+
+	/// Value range that makes the target readable without adjusting.
+	pub fn default_range(&self) -> (f32, f32) {
+		match self {
+			InspectedTarget::HdrColor => (0.0, 1.0),
+			// reverse z, almost everything is close to zero
+			InspectedTarget::Depth | InspectedTarget::DepthPrecision => (0.0, 0.05),
+			InspectedTarget::MotionVectors => (-0.05, 0.05),
+		}
+	}
+}
+
+/// Render targets of the current frame the inspector can read from.
+pub struct InspectorSources {
+	pub hdr_color: RenderTargetHandle,
+	pub depth: RenderTargetHandle,
+	/// only rendered while motion blur is on
+	pub motion_vectors: Option<RenderTargetHandle>,
+}
+
+/// Shows an intermediate render target in an egui window.
+///
+/// Targets only live for the duration of a graph, so the selected one is
+/// copied into a preview texture registered with egui, isolating channels
+/// and remapping its values on the way.
+pub struct TargetInspector {
+	pub open: bool,
+	pub target: InspectedTarget,
+	/// red, green, blue and alpha
+	pub channels: [bool; 4],
+	/// values shown as black and white
+	pub range: (f32, f32),
+	/// stripes of the depth precision view span `2^precision_bits`
+	/// representable depth values
+	pub precision_bits: u32,
+
+	uniforms: Buffer,
+	uniform_bg: BindGroup,
+	color_bgl: BindGroupLayout,
+	depth_bgl: BindGroupLayout,
+	color_pipeline: RenderPipeline,
+	depth_pipeline: RenderPipeline,
+	depth_precision_pipeline: RenderPipeline,
+
+	preview: TextureView,
+	texture_id: egui::TextureId,
+}
+
+impl TargetInspector {
+	pub fn new(renderer: &Renderer, egui_routine: &mut EguiRenderRoutine) -> Self {
+		let device = &renderer.device;
+
+		let uniform_bgl = BindGroupLayoutBuilder::new()
+			.append(
+				ShaderStages::FRAGMENT,
+				BindingType::Buffer {
+					ty: BufferBindingType::Uniform,
+					has_dynamic_offset: false,
+					min_binding_size: None,
+				},
+				None,
+			)
+			.build(device, Some("target inspector uniform bgl"));
+		let source_bgl = |sample_type, label| {
+			BindGroupLayoutBuilder::new()
+				.append(
+					ShaderStages::FRAGMENT,
+					BindingType::Texture {
+						sample_type,
+						view_dimension: TextureViewDimension::D2,
+						multisampled: false,
+					},
+					None,
+				)
+				.build(device, Some(label))
+		};
+		let color_bgl = source_bgl(
+			TextureSampleType::Float { filterable: false },
+			"target inspector color bgl",
+		);
+		let depth_bgl = source_bgl(TextureSampleType::Depth, "target inspector depth bgl");
+
+		let module = device.create_shader_module(&ShaderModuleDescriptor {
+			label: Some("target inspector"),
+			source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("target_inspector.wgsl"))),
+		});
+
+		let create_pipeline = |source_bgl, entry_point, label| {
+			let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+				label: Some(label),
+				bind_group_layouts: &[&uniform_bgl, source_bgl],
+				push_constant_ranges: &[],
+			});
+			device.create_render_pipeline(&RenderPipelineDescriptor {
+				label: Some(label),
+				layout: Some(&layout),
+				vertex: VertexState {
+					module: &module,
+					entry_point: "vs_main",
+					buffers: &[],
+				},
+				primitive: PrimitiveState::default(),
+				depth_stencil: None,
+				multisample: MultisampleState::default(),
+				fragment: Some(FragmentState {
+					module: &module,
+					entry_point,
+					targets: &[ColorTargetState {
+						format: PREVIEW_FORMAT,
+						blend: None,
+						write_mask: ColorWrites::all(),
+					}],
+				}),
+				multiview: None,
+			})
+		};
+		let color_pipeline = create_pipeline(&color_bgl, "fs_color", "target inspector color");
+		let depth_pipeline = create_pipeline(&depth_bgl, "fs_depth", "target inspector depth");
+		let depth_precision_pipeline = create_pipeline(
+			&depth_bgl,
+			"fs_depth_precision",
+			"target inspector depth precision",
+		);
+
+		let uniforms = device.create_buffer_init(&BufferInitDescriptor {
+			label: Some("target inspector uniforms"),
+			contents: &[0; 48],
+			usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+		});
+		let uniform_bg = BindGroupBuilder::new().append_buffer(&uniforms).build(
+			device,
+			Some("target inspector uniform bg"),
+			&uniform_bgl,
+		);
+
+		let preview = device.create_texture(&TextureDescriptor {
+			label: Some("target inspector preview"),
+			size: Extent3d {
+				width: PREVIEW_SIZE,
+				height: PREVIEW_SIZE,
+				depth_or_array_layers: 1,
+			},
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: TextureDimension::D2,
+			format: PREVIEW_FORMAT,
+			usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+		});
+		let texture_id = egui_routine.internal.egui_texture_from_wgpu_texture(
+			device,
+			&preview,
+			FilterMode::Nearest,
+		);
+
+		let target = InspectedTarget::HdrColor;
+		Self {
+			open: false,
+			target,
+			channels: [true, true, true, false],
+			range: target.default_range(),
+			precision_bits: 12,
+			uniforms,
+			uniform_bg,
+			color_bgl,
+			depth_bgl,
+			color_pipeline,
+			depth_pipeline,
+			depth_precision_pipeline,
+			preview: preview.create_view(&TextureViewDescriptor::default()),
+			texture_id,
+		}
+	}
+
+	/// Uploads the channel and range settings for the next frame.
+	pub fn update(&self, renderer: &Renderer) {
+		let channel = |enabled: bool| if enabled { 1.0 } else { 0.0 };
+		let data: [f32; 12] = [
+			channel(self.channels[0]),
+			channel(self.channels[1]),
+			channel(self.channels[2]),
+			channel(self.channels[3]),
+			self.range.0,
+			self.range.1,
+			self.precision_bits as f32,
+			0.0,
+			PREVIEW_SIZE as f32,
+			PREVIEW_SIZE as f32,
+			0.0,
+			0.0,
+		];
+		let bytes: Vec<u8> = data.iter().flat_map(|v| v.to_ne_bytes()).collect();
+
+		renderer.queue.write_buffer(&self.uniforms, 0, &bytes);
+	}
+
+	/// `aspect_ratio` is the width over height of the inspected targets.
+	pub fn window(&mut self, ctx: &egui::CtxRef, aspect_ratio: f32) {
+		let mut open = self.open;
+		egui::Window::new("render targets")
+			.open(&mut open)
+			.resizable(true)
+			.show(ctx, |ui| {
+				let previous = self.target;
+				egui::ComboBox::from_label("target")
+					.selected_text(self.target.label())
+					.show_ui(ui, |ui| {
+						for target in InspectedTarget::ALL {
+							ui.selectable_value(&mut self.target, target, target.label());
+						}
+					});
+				if self.target != previous {
+					self.range = self.target.default_range();
+				}
+
+				ui.horizontal(|ui| {
+					for (enabled, label) in self.channels.iter_mut().zip(["r", "g", "b", "a"]) {
+						ui.checkbox(enabled, label);
+					}
+				});
+				if self.target == InspectedTarget::DepthPrecision {
+					ui.add(egui::Slider::new(&mut self.precision_bits, 0..=23).text("stripe bits"));
+				}
+				ui.add_enabled_ui(self.target != InspectedTarget::DepthPrecision, |ui| {
+					ui.horizontal(|ui| {
+						ui.label("range");
+						ui.add(egui::DragValue::new(&mut self.range.0).speed(0.001));
+						ui.add(egui::DragValue::new(&mut self.range.1).speed(0.001));
+						if ui.button("reset").clicked() {
+							self.range = self.target.default_range();
+						}
+					});
+				});
+				if self.target == InspectedTarget::MotionVectors {
+					ui.label("only rendered while motion blur is on");
+				}
+
+				let width = ui.available_width().max(64.0);
+				ui.image(self.texture_id, egui::vec2(width, width / aspect_ratio));
+			});
+		self.open = open;
+	}
+
+	/// Copies the selected target into the preview. Does nothing while the
+	/// window is closed or the target isn't part of this frame.
+	pub fn add_to_graph<'node>(
+		&'node self,
+		graph: &mut RenderGraph<'node>,
+		sources: &InspectorSources,
+	) {
+		if !self.open {
+			return;
+		}
+		let source = match self.target {
+			InspectedTarget::HdrColor => sources.hdr_color,
+			InspectedTarget::Depth | InspectedTarget::DepthPrecision => sources.depth,
+			InspectedTarget::MotionVectors => match sources.motion_vectors {
+				Some(motion_vectors) => motion_vectors,
+				None => return,
+			},
+		};
+		let (pipeline, bgl) = match self.target {
+			InspectedTarget::Depth => (&self.depth_pipeline, &self.depth_bgl),
+			InspectedTarget::DepthPrecision => (&self.depth_precision_pipeline, &self.depth_bgl),
+			_ => (&self.color_pipeline, &self.color_bgl),
+		};
+
+		let mut builder = graph.add_node("Target Inspector");
+
+		let source_handle = builder.add_render_target_input(source);
+		// the preview lives outside the graph
+		builder.add_external_output();
+
+		let pt_handle = builder.passthrough_ref(self);
+
+		builder.build(
+			move |pt, renderer, encoder_or_pass, temps, _ready, graph_data| {
+				let this = pt.get(pt_handle);
+				let encoder = encoder_or_pass.get_encoder();
+				let source = graph_data.get_render_target(source_handle);
+
+				let source_bg =
+					temps.add(BindGroupBuilder::new().append_texture_view(source).build(
+						&renderer.device,
+						Some("target inspector source bg"),
+						bgl,
+					));
+
+				let mut rpass = encoder.begin_render_pass(&RenderPassDescriptor {
+					label: Some("target inspector"),
+					color_attachments: &[RenderPassColorAttachment {
+						view: &this.preview,
+						resolve_target: None,
+						ops: Operations {
+							load: LoadOp::Clear(Color::BLACK),
+							store: true,
+						},
+					}],
+					depth_stencil_attachment: None,
+				});
+				rpass.set_pipeline(pipeline);
+				rpass.set_bind_group(0, &this.uniform_bg, &[]);
+				rpass.set_bind_group(1, source_bg, &[]);
+				rpass.draw(0..3, 0..1);
+			},
+		);
+	}
+}
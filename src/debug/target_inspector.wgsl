@@ -0,0 +1,80 @@
+// Copies a render target into the inspector preview, isolating channels and
+// remapping values into a visible range.
+
+struct VertexOutput {
+	[[builtin(position)]] position: vec4<f32>;
+};
+
+struct InspectorUniforms {
+	// 1.0 for each channel to show
+	channels: vec4<f32>;
+	// x: value shown as black, y: value shown as white, z: depth precision
+	// stripe width as a power of two of representable depth values
+	range: vec4<f32>;
+	// xy: preview size in pixels
+	size: vec4<f32>;
+};
+
+[[group(0), binding(0)]]
+var<uniform> uniforms: InspectorUniforms;
+
+[[group(1), binding(0)]]
+var color_source: texture_2d<f32>;
+
+[[group(1), binding(0)]]
+var depth_source: texture_depth_2d;
+
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] vertex_index: u32) -> VertexOutput {
+	// single triangle covering the whole screen
+	let x = f32(i32(vertex_index & 1u) * 4 - 1);
+	let y = f32(i32(vertex_index & 2u) * 2 - 1);
+	var out: VertexOutput;
+	out.position = vec4<f32>(x, y, 0.0, 1.0);
+	return out;
+}
+
+fn source_pixel(position: vec2<f32>, source_size: vec2<i32>) -> vec2<i32> {
+	let uv = position / uniforms.size.xy;
+	return min(vec2<i32>(uv * vec2<f32>(source_size)), source_size - vec2<i32>(1));
+}
+
+fn remap(value: vec4<f32>) -> vec4<f32> {
+	let remapped = clamp((value - uniforms.range.x) / (uniforms.range.y - uniforms.range.x), vec4<f32>(0.0), vec4<f32>(1.0));
+	let channels = uniforms.channels;
+	let count = dot(channels, vec4<f32>(1.0));
+	if (count == 1.0) {
+		// a single channel is shown as grayscale
+		return vec4<f32>(vec3<f32>(dot(remapped, channels)), 1.0);
+	}
+	return vec4<f32>(remapped.rgb * channels.rgb, 1.0);
+}
+
+[[stage(fragment)]]
+fn fs_color(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let pixel = source_pixel(in.position.xy, textureDimensions(color_source));
+	return remap(textureLoad(color_source, pixel, 0));
+}
+
+[[stage(fragment)]]
+fn fs_depth(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let pixel = source_pixel(in.position.xy, textureDimensions(depth_source));
+	let depth = textureLoad(depth_source, pixel, 0);
+	return remap(vec4<f32>(depth, depth, depth, 1.0));
+}
+
+// alternates bands of 2^n representable depth values, so the width of a band
+// on screen shows how coarse depth is there
+[[stage(fragment)]]
+fn fs_depth_precision(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let pixel = source_pixel(in.position.xy, textureDimensions(depth_source));
+	let depth = textureLoad(depth_source, pixel, 0);
+	if (depth <= 0.0) {
+		return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+	}
+	let band = (bitcast<u32>(depth) >> u32(uniforms.range.z)) & 1u;
+	if (band == 0u) {
+		return vec4<f32>(0.2, 0.2, 0.25, 1.0);
+	}
+	return vec4<f32>(0.9, 0.85, 0.6, 1.0);
+}
@@ -0,0 +1,169 @@
+use std::collections::HashSet;
+
+use glam::{const_vec4, Mat4, Vec3, Vec3A, Vec4};
+use rend3::types::{Camera, CameraProjection, DirectionalLight, Handedness};
+use rend3::SHADOW_DIMENSIONS;
+
+use super::DebugDraw;
+use crate::camera;
+
+const CAMERA_COLOR: Vec4 = const_vec4!([0.2, 0.8, 1.0, 1.0]);
+const CASCADE_COLOR: Vec4 = const_vec4!([1.0, 0.5, 0.1, 1.0]);
+const LIGHT_COLOR: Vec4 = const_vec4!([1.0, 0.9, 0.2, 1.0]);
+
+/// Camera whose frustum can be drawn.
+pub struct VizCamera<'a> {
+	pub name: &'a str,
+	pub camera: Camera,
+	pub aspect_ratio: f32,
+}
+
+/// Light whose volume and shadow cascades can be drawn.
+pub struct VizLight<'a> {
+	pub name: &'a str,
+	pub light: &'a DirectionalLight,
+}
+
+/// Draws camera frusta and light volumes through [`DebugDraw`], toggled per
+/// camera and light.
+///
+/// A directional light reaches everywhere, so its volume is drawn as the box
+/// its shadows are rendered in with an arrow along the light direction. rend3
+/// currently renders a single shadow cascade per light.
+pub struct VolumeViz {
+	pub open: bool,
+	/// how far perspective frusta are drawn, they reach infinitely far
+	pub frustum_distance: f32,
+	shown: HashSet<String>,
+}
+
+impl Default for VolumeViz {
+	fn default() -> Self {
+		Self {
+			open: false,
+			frustum_distance: 10.0,
+			shown: HashSet::new(),
+		}
+	}
+}
+
+impl VolumeViz {
+	/// Lists the cameras and lights by name so each can be toggled.
+	pub fn window(&mut self, ctx: &egui::CtxRef, cameras: &[&str], lights: &[&str]) {
+		let mut open = self.open;
+		egui::Window::new("volumes")
+			.open(&mut open)
+			.resizable(false)
+			.show(ctx, |ui| {
+				ui.label("cameras");
+				for camera in cameras {
+					self.toggle(ui, camera_key(camera), camera);
+				}
+				ui.add(
+					egui::Slider::new(&mut self.frustum_distance, 1.0..=100.0)
+						.logarithmic(true)
+						.text("frustum distance"),
+				);
+				ui.separator();
+				ui.label("lights");
+				for light in lights {
+					self.toggle(ui, light_key(light), light);
+				}
+			});
+		self.open = open;
+	}
+
+	fn toggle(&mut self, ui: &mut egui::Ui, key: String, label: &str) {
+		let mut shown = self.shown.contains(&key);
+		if ui.checkbox(&mut shown, label).changed() {
+			match shown {
+				true => self.shown.insert(key),
+				false => self.shown.remove(&key),
+			};
+		}
+	}
+
+	/// Queues the enabled volumes. Shadow cascades follow `user_camera` the
+	/// same way rend3 places them.
+	pub fn draw(
+		&self,
+		debug_draw: &mut DebugDraw,
+		cameras: &[VizCamera],
+		lights: &[VizLight],
+		user_camera: &Camera,
+		handedness: Handedness,
+	) {
+		for viz in cameras {
+			if !self.shown.contains(&camera_key(viz.name)) {
+				continue;
+			}
+			let projection = camera::projection_matrix(&viz.camera, viz.aspect_ratio, handedness);
+			let (near, far) = match viz.camera.projection {
+				CameraProjection::Orthographic { .. } => (0.0, 1.0),
+				// reverse z, cut off at the display distance if the far plane is
+				// further away or infinite
+				_ => {
+					let distant = camera::view_forward(handedness) * self.frustum_distance;
+					(1.0, projection.project_point3(distant.into()).z.max(0.0))
+				}
+			};
+			let view_proj = projection * viz.camera.view;
+			debug_draw.frustum(view_proj, near, far, CAMERA_COLOR);
+		}
+
+		let user_location = user_camera.view.inverse().w_axis.truncate();
+		for viz in lights {
+			if !self.shown.contains(&light_key(viz.name)) {
+				continue;
+			}
+			for cascade in shadow_cascades(viz.light, user_location, handedness) {
+				let view_proj = camera::projection_matrix(&cascade, 1.0, handedness) * cascade.view;
+				debug_draw.frustum(view_proj, 0.0, 1.0, CASCADE_COLOR);
+
+				let center = cascade.view.inverse().w_axis.truncate();
+				let direction = viz.light.direction.normalize_or_zero();
+				debug_draw.arrow(
+					center - direction * viz.light.distance * 0.5,
+					center,
+					LIGHT_COLOR,
+				);
+			}
+		}
+	}
+}
+
+/// Cameras rend3 renders the shadows of `light` with, centered on the user
+/// camera and snapped to shadow map texels.
+fn shadow_cascades(
+	light: &DirectionalLight,
+	user_location: Vec3,
+	handedness: Handedness,
+) -> Vec<Camera> {
+	let texel_size = light.distance / SHADOW_DIMENSIONS as f32;
+
+	let look_at = match handedness {
+		Handedness::Left => Mat4::look_at_lh,
+		Handedness::Right => Mat4::look_at_rh,
+	};
+	let origin_view = look_at(Vec3::ZERO, light.direction, Vec3::Y);
+	let camera_origin_view = origin_view.transform_point3(user_location);
+	let offset = camera_origin_view.truncate() % texel_size;
+	let location = origin_view
+		.inverse()
+		.transform_point3(camera_origin_view - offset.extend(0.0));
+
+	vec![Camera {
+		projection: CameraProjection::Orthographic {
+			size: Vec3A::splat(light.distance),
+		},
+		view: look_at(location, location + light.direction, Vec3::Y),
+	}]
+}
+
+fn camera_key(name: &str) -> String {
+	format!("camera {}", name)
+}
+
+fn light_key(name: &str) -> String {
+	format!("light {}", name)
+}
@@ -0,0 +1,149 @@
+use glam::{Mat4, UVec2, Vec3};
+
+use super::pointer::{self, ClickDetector};
+use crate::scene::{ObjectId, Scene};
+
+/// Shape added from the context menu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Primitive {
+	Cube,
+	Sphere,
+}
+
+impl Primitive {
+	pub const ALL: [Primitive; 2] = [Primitive::Cube, Primitive::Sphere];
+
+	pub fn label(&self) -> &'static str {
+		match self {
+			Primitive::Cube => "cube",
+			Primitive::Sphere => "sphere",
+		}
+	}
+}
+
+/// What was chosen from the context menu.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MenuAction {
+	Duplicate(ObjectId),
+	Delete(ObjectId),
+	/// move the camera in front of the object
+	Focus(ObjectId),
+	/// hide everything else
+	Isolate(ObjectId),
+	Copy(ObjectId),
+	/// add a shape at a world position
+	AddPrimitive(Primitive, Vec3),
+	/// add the copied object at a world position
+	Paste(Vec3),
+}
+
+/// What the menu was opened on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MenuTarget {
+	Object(ObjectId),
+	/// empty space, with the world position new objects go
+	Empty(Vec3),
+}
+
+/// how far in front of the camera things are added in empty space
+const EMPTY_DISTANCE: f32 = 5.0;
+
+/// Menu opened by right clicking the viewport, with actions for the object
+/// under the pointer or for empty space.
+#[derive(Default)]
+pub struct ContextMenu {
+	clicks: ClickDetector,
+	open: Option<(egui::Pos2, MenuTarget)>,
+}
+
+impl ContextMenu {
+	/// Opens the menu on a right click in the viewport and shows it. Returns
+	/// the action picked this frame. `can_paste` enables pasting.
+	pub fn show(
+		&mut self,
+		ctx: &egui::CtxRef,
+		scene: &Scene,
+		view_proj: Mat4,
+		camera_position: Vec3,
+		viewport: (UVec2, UVec2),
+		can_paste: bool,
+	) -> Option<MenuAction> {
+		if let Some((pos, _)) = self.clicks.click(ctx, egui::PointerButton::Secondary) {
+			self.open = Self::target(ctx, scene, view_proj, camera_position, viewport, pos)
+				.map(|target| (pos, target));
+		}
+		let (pos, target) = self.open?;
+		if let MenuTarget::Object(id) = target {
+			if scene.get(id).is_none() {
+				self.open = None;
+				return None;
+			}
+		}
+
+		let mut action = None;
+		let response = egui::Area::new("viewport context menu")
+			.order(egui::Order::Foreground)
+			.fixed_pos(pos)
+			.show(ctx, |ui| {
+				egui::Frame::popup(ui.style()).show(ui, |ui| {
+					ui.set_min_width(120.0);
+					match target {
+						MenuTarget::Object(id) => {
+							if let Some(object) = scene.get(id) {
+								ui.label(&object.name);
+								ui.separator();
+							}
+							let buttons = [
+								("duplicate", MenuAction::Duplicate(id)),
+								("delete", MenuAction::Delete(id)),
+								("focus", MenuAction::Focus(id)),
+								("isolate", MenuAction::Isolate(id)),
+								("copy", MenuAction::Copy(id)),
+							];
+							for (label, choice) in buttons {
+								if ui.button(label).clicked() {
+									action = Some(choice);
+								}
+							}
+						}
+						MenuTarget::Empty(position) => {
+							for primitive in Primitive::ALL {
+								if ui.button(format!("add {}", primitive.label())).clicked() {
+									action = Some(MenuAction::AddPrimitive(primitive, position));
+								}
+							}
+							if ui
+								.add_enabled(can_paste, egui::Button::new("paste"))
+								.clicked()
+							{
+								action = Some(MenuAction::Paste(position));
+							}
+						}
+					}
+				});
+			})
+			.response;
+
+		if action.is_some() || response.clicked_elsewhere() {
+			self.open = None;
+		}
+		action
+	}
+
+	fn target(
+		ctx: &egui::CtxRef,
+		scene: &Scene,
+		view_proj: Mat4,
+		camera_position: Vec3,
+		viewport: (UVec2, UVec2),
+		pos: egui::Pos2,
+	) -> Option<MenuTarget> {
+		let direction = pointer::pointer_direction(ctx, view_proj, viewport, pos)?;
+		Some(
+			match scene.raycast(camera_position, direction, f32::INFINITY, None) {
+				Some((id, _)) => MenuTarget::Object(id),
+				None => MenuTarget::Empty(camera_position + direction * EMPTY_DISTANCE),
+			},
+		)
+	}
+}
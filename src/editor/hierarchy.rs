@@ -0,0 +1,123 @@
+use super::Selection;
+use crate::scene::{ObjectId, Scene};
+
+/// Window listing the scene's objects under the objects they're attached to.
+/// Dragging one onto another attaches it there, dragging it to the space
+/// below the list detaches it, both keeping it where it is. Clicking one
+/// selects it. Parents aren't saved to the scene file and re-parenting isn't
+/// undone.
+#[derive(Default)]
+pub struct Hierarchy {
+	pub open: bool,
+	dragging: Option<ObjectId>,
+}
+
+impl Hierarchy {
+	/// points each level of attachment is indented by
+	const INDENT: f32 = 16.0;
+
+	pub fn window(&mut self, ctx: &egui::CtxRef, scene: &mut Scene, selection: &mut Selection) {
+		let mut open = self.open;
+		// where an object was dropped, `None` for detaching it
+		let mut dropped = None;
+		egui::Window::new("hierarchy")
+			.open(&mut open)
+			.resizable(true)
+			.show(ctx, |ui| {
+				let mut roots: Vec<_> = scene
+					.iter()
+					.filter(|(_, object)| object.parent().is_none())
+					.map(|(id, object)| (object.name.as_str(), id))
+					.collect();
+				roots.sort();
+				let pointer = ctx.input().pointer.hover_pos();
+				let released = ctx.input().pointer.any_released();
+
+				egui::ScrollArea::vertical()
+					.max_height(400.0)
+					.show(ui, |ui| {
+						for (_, id) in roots {
+							self.row(ui, scene, selection, id, 0, &mut dropped);
+						}
+					});
+				let detach = ui.allocate_response(
+					egui::vec2(ui.available_width(), 24.0),
+					egui::Sense::hover(),
+				);
+				if self.dragging.is_some() {
+					ui.painter().text(
+						detach.rect.center(),
+						egui::Align2::CENTER_CENTER,
+						"drop here to detach",
+						egui::TextStyle::Small,
+						ui.visuals().weak_text_color(),
+					);
+				}
+				let over_detach = pointer.is_some_and(|pos| detach.rect.contains(pos));
+				if released && over_detach {
+					dropped = Some(None);
+				}
+			});
+		self.open = open;
+
+		if !ctx.input().pointer.any_released() {
+			return;
+		}
+		if let (Some(child), Some(parent)) = (self.dragging.take(), dropped) {
+			match parent {
+				Some(parent) if parent != child => {
+					if !scene.attach(child, parent, true) {
+						log::warn!("can't attach an object to one attached to it");
+					}
+				}
+				Some(_) => {}
+				None => scene.detach(child),
+			}
+		}
+	}
+
+	/// An object and everything attached to it, indented by `depth`.
+	fn row(
+		&mut self,
+		ui: &mut egui::Ui,
+		scene: &Scene,
+		selection: &mut Selection,
+		id: ObjectId,
+		depth: usize,
+		dropped: &mut Option<Option<ObjectId>>,
+	) {
+		let object = match scene.get(id) {
+			Some(object) => object,
+			None => return,
+		};
+		let response = ui
+			.horizontal(|ui| {
+				ui.add_space(depth as f32 * Self::INDENT);
+				ui.selectable_label(selection.contains(id), &object.name)
+					.interact(egui::Sense::drag())
+			})
+			.inner;
+		if response.clicked() {
+			selection.select(id);
+		}
+		if response.drag_started() {
+			self.dragging = Some(id);
+		}
+		let over = ui
+			.input()
+			.pointer
+			.hover_pos()
+			.is_some_and(|pos| response.rect.contains(pos));
+		if over && self.dragging.is_some_and(|dragging| dragging != id) {
+			ui.painter()
+				.rect_stroke(response.rect, 2.0, ui.visuals().selection.stroke);
+			if ui.input().pointer.any_released() {
+				*dropped = Some(Some(id));
+			}
+		}
+
+		for child in scene.children(id) {
+			self.row(ui, scene, selection, *child, depth + 1, dropped);
+		}
+	}
+}
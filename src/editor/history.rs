@@ -0,0 +1,105 @@
+use rend3::Renderer;
+
+use crate::scene::{ObjectId, ObjectSnapshot, Scene};
+
+/// A reversible change to the scene.
+#[derive(Clone)]
+pub enum Edit {
+	/// an object was added, undone by removing it
+	Add(ObjectId, ObjectSnapshot),
+	/// an object was removed, undone by restoring it under the same id
+	Remove(ObjectId, ObjectSnapshot),
+	/// several edits undone together
+	Batch(Vec<Edit>),
+}
+
+impl Edit {
+	/// Removal of `id` as it is now, or `None` if it doesn't exist.
+	pub fn remove(scene: &Scene, id: ObjectId) -> Option<Self> {
+		scene
+			.get(id)
+			.map(|object| Edit::Remove(id, object.snapshot()))
+	}
+
+	fn apply(&self, renderer: &Renderer, scene: &mut Scene) {
+		match self {
+			Edit::Add(id, snapshot) => scene.restore(renderer, *id, snapshot.clone()),
+			Edit::Remove(id, _) => scene.remove_object(*id),
+			Edit::Batch(edits) => {
+				for edit in edits {
+					edit.apply(renderer, scene);
+				}
+			}
+		}
+	}
+
+	fn inverse(&self) -> Self {
+		match self {
+			Edit::Add(id, snapshot) => Edit::Remove(*id, snapshot.clone()),
+			Edit::Remove(id, snapshot) => Edit::Add(*id, snapshot.clone()),
+			Edit::Batch(edits) => Edit::Batch(edits.iter().rev().map(Edit::inverse).collect()),
+		}
+	}
+
+	pub fn label(&self) -> String {
+		match self {
+			Edit::Add(_, snapshot) => format!("add {}", snapshot.name),
+			Edit::Remove(_, snapshot) => format!("delete {}", snapshot.name),
+			Edit::Batch(edits) => format!("{} edits", edits.len()),
+		}
+	}
+}
+
+/// Undo and redo stacks of the edits made to the scene.
+#[derive(Default)]
+pub struct History {
+	undo: Vec<Edit>,
+	redo: Vec<Edit>,
+}
+
+impl History {
+	/// oldest edits are forgotten past this
+	const LIMIT: usize = 256;
+
+	/// Makes an edit that can be undone, clearing the redo stack.
+	pub fn apply(&mut self, renderer: &Renderer, scene: &mut Scene, edit: Edit) {
+		edit.apply(renderer, scene);
+		log::debug!("{}", edit.label());
+		self.undo.push(edit);
+		if self.undo.len() > Self::LIMIT {
+			self.undo.remove(0);
+		}
+		self.redo.clear();
+	}
+
+	/// Reverts the last edit. Returns false if there was nothing to undo.
+	pub fn undo(&mut self, renderer: &Renderer, scene: &mut Scene) -> bool {
+		let edit = match self.undo.pop() {
+			Some(edit) => edit,
+			None => return false,
+		};
+		edit.inverse().apply(renderer, scene);
+		self.redo.push(edit);
+		true
+	}
+
+	/// Makes the last undone edit again. Returns false if there was nothing to
+	/// redo.
+	pub fn redo(&mut self, renderer: &Renderer, scene: &mut Scene) -> bool {
+		let edit = match self.redo.pop() {
+			Some(edit) => edit,
+			None => return false,
+		};
+		edit.apply(renderer, scene);
+		self.undo.push(edit);
+		true
+	}
+
+	pub fn can_undo(&self) -> bool {
+		!self.undo.is_empty()
+	}
+
+	pub fn can_redo(&self) -> bool {
+		!self.redo.is_empty()
+	}
+}
@@ -0,0 +1,290 @@
+use std::path::Path;
+
+use super::Selection;
+use crate::metadata::{MetadataKind, MetadataValue};
+use crate::modifiers::TransformModifier;
+use crate::scene::{ObjectId, Scene, SceneTransform};
+use crate::scene_file::{save_scene, SaveKind, SceneFile};
+
+/// Window showing the selected object, where its metadata is edited, and
+/// saving and loading the scene file.
+pub struct Inspector {
+	pub open: bool,
+	new_key: String,
+	new_kind: MetadataKind,
+	path: String,
+	/// result of the last save or load
+	status: Option<String>,
+}
+
+impl Default for Inspector {
+	fn default() -> Self {
+		Self {
+			open: false,
+			new_key: String::new(),
+			new_kind: MetadataKind::String,
+			path: "scene.json".into(),
+			status: None,
+		}
+	}
+}
+
+impl Inspector {
+	/// `bone` is the joint picked in the skeleton view, shown along with its
+	/// object.
+	pub fn window(
+		&mut self,
+		ctx: &egui::CtxRef,
+		scene: &mut Scene,
+		selection: &Selection,
+		bone: Option<(ObjectId, usize)>,
+	) {
+		let mut open = self.open;
+		egui::Window::new("inspector")
+			.open(&mut open)
+			.resizable(true)
+			.show(ctx, |ui| {
+				match selection.objects() {
+					[] => {
+						ui.label("click an object to select it");
+					}
+					[id, ..] => {
+						self.object_ui(ui, scene, *id);
+						if let Some((_, joint)) = bone.filter(|(bone, _)| bone == id) {
+							bone_ui(ui, scene, *id, joint);
+						}
+					}
+				}
+				if selection.objects().len() > 1 {
+					ui.label(format!(
+						"and {} more selected",
+						selection.objects().len() - 1
+					));
+				}
+
+				ui.separator();
+				self.file_ui(ui, scene);
+			});
+		self.open = open;
+	}
+
+	fn object_ui(&mut self, ui: &mut egui::Ui, scene: &mut Scene, id: ObjectId) {
+		let object = match scene.get(id) {
+			Some(object) => object,
+			None => return,
+		};
+		ui.heading(&object.name);
+		let position = object.transform().to_mat4().w_axis;
+		ui.label(format!(
+			"x{:.2} y{:.2} z{:.2}",
+			position.x, position.y, position.z
+		));
+
+		self.socket_ui(ui, scene, id);
+		self.modifiers_ui(ui, scene, id);
+
+		let metadata = match scene.metadata_mut(id) {
+			Some(metadata) => metadata,
+			None => return,
+		};
+		ui.label("metadata");
+		let mut removed = None;
+		egui::Grid::new("inspector_metadata")
+			.num_columns(3)
+			.striped(true)
+			.show(ui, |ui| {
+				for (key, value) in metadata.iter_mut() {
+					ui.label(key);
+					match value {
+						MetadataValue::String(text) => {
+							ui.text_edit_singleline(text);
+						}
+						MetadataValue::Number(number) => {
+							ui.add(egui::DragValue::new(number).speed(0.1));
+						}
+						MetadataValue::Bool(flag) => {
+							ui.checkbox(flag, "");
+						}
+					}
+					if ui.small_button("x").on_hover_text("remove").clicked() {
+						removed = Some(key.clone());
+					}
+					ui.end_row();
+				}
+			});
+		if let Some(key) = removed {
+			metadata.remove(&key);
+		}
+
+		ui.horizontal(|ui| {
+			ui.add(
+				egui::TextEdit::singleline(&mut self.new_key)
+					.hint_text("key")
+					.desired_width(100.0),
+			);
+			egui::ComboBox::from_id_source("inspector_metadata_kind")
+				.selected_text(self.new_kind.label())
+				.show_ui(ui, |ui| {
+					for kind in MetadataKind::ALL {
+						ui.selectable_value(&mut self.new_kind, kind, kind.label());
+					}
+				});
+			let key = self.new_key.trim();
+			let addable = !key.is_empty() && !metadata.contains_key(key);
+			if ui.add_enabled(addable, egui::Button::new("add")).clicked() {
+				metadata.insert(key.into(), self.new_kind.default_value());
+				self.new_key.clear();
+			}
+		});
+	}
+
+	/// What the object is attached to, and the joint of it when it's
+	/// animated.
+	fn socket_ui(&mut self, ui: &mut egui::Ui, scene: &mut Scene, id: ObjectId) {
+		let (socket, parent) = match scene.get(id) {
+			Some(object) => (object.socket(), object.parent()),
+			None => return,
+		};
+		let parent = match parent.and_then(|parent| scene.get(parent)) {
+			Some(parent) => parent,
+			None => return,
+		};
+		ui.label(format!("attached to {}", parent.name));
+		let joints = parent.joints().len();
+		if joints == 0 {
+			return;
+		}
+		let label = |joint: Option<usize>| match joint {
+			Some(joint) => format!("joint {}", joint),
+			None => "none".to_owned(),
+		};
+		let mut picked = socket;
+		egui::ComboBox::from_label("socket")
+			.selected_text(label(socket))
+			.show_ui(ui, |ui| {
+				ui.selectable_value(&mut picked, None, label(None));
+				for joint in 0..joints {
+					ui.selectable_value(&mut picked, Some(joint), label(Some(joint)));
+				}
+			});
+		if picked != socket {
+			scene.set_socket(id, picked);
+		}
+	}
+
+	/// The object's transform modifiers and a menu to add more.
+	fn modifiers_ui(&mut self, ui: &mut egui::Ui, scene: &mut Scene, id: ObjectId) {
+		let modifiers = match scene.modifiers_mut(id) {
+			Some(modifiers) => modifiers,
+			None => return,
+		};
+		let mut removed = None;
+		for (index, modifier) in modifiers.iter_mut().enumerate() {
+			egui::CollapsingHeader::new(modifier.label())
+				.id_source(("inspector_modifier", index))
+				.default_open(true)
+				.show(ui, |ui| {
+					modifier.ui(ui);
+					if ui.small_button("remove").clicked() {
+						removed = Some(index);
+					}
+				});
+		}
+		if let Some(index) = removed {
+			modifiers.remove(index);
+		}
+		ui.menu_button("add component", |ui| {
+			for modifier in TransformModifier::ALL {
+				if ui.button(modifier.label()).clicked() {
+					modifiers.push(modifier);
+					ui.close_menu();
+				}
+			}
+		});
+		ui.separator();
+	}
+
+	/// Saves the scene to the scene file, either just the objects changed
+	/// since the last save (Ctrl+S) or everything (Ctrl+Shift+S).
+	pub fn save(&mut self, scene: &mut Scene, incremental: bool) {
+		self.status = Some(
+			match save_scene(scene, Path::new(&self.path), incremental) {
+				Ok(SaveKind::Full) => format!("saved {}", self.path),
+				Ok(SaveKind::Patch(0)) => "no changes to save".into(),
+				Ok(SaveKind::Patch(count)) => format!("saved {} changed objects", count),
+				Err(e) => format!("couldn't save: {}", e),
+			},
+		);
+	}
+
+	fn file_ui(&mut self, ui: &mut egui::Ui, scene: &mut Scene) {
+		ui.horizontal(|ui| {
+			ui.label("scene file");
+			ui.text_edit_singleline(&mut self.path);
+		});
+		ui.horizontal(|ui| {
+			if ui.button("save").on_hover_text("ctrl+s").clicked() {
+				self.save(scene, true);
+			}
+			if ui
+				.button("save all")
+				.on_hover_text("ctrl+shift+s")
+				.clicked()
+			{
+				self.save(scene, false);
+			}
+			if ui.button("load").clicked() {
+				self.status = Some(match SceneFile::load(Path::new(&self.path)) {
+					Ok(file) => {
+						let missing = file.apply(scene);
+						// the scene now matches the file
+						scene.clear_changes();
+						match missing.as_slice() {
+							[] => format!("loaded {}", self.path),
+							missing => format!("loaded, no objects named {}", missing.join(", ")),
+						}
+					}
+					Err(e) => format!("couldn't load: {}", e),
+				});
+			}
+		});
+		if let Some(status) = &self.status {
+			ui.label(status);
+		}
+	}
+}
+
+/// Name, parent and position of a joint of an animated object.
+fn bone_ui(ui: &mut egui::Ui, scene: &Scene, id: ObjectId, joint: usize) {
+	let object = match scene.get(id) {
+		Some(object) => object,
+		None => return,
+	};
+	let name = |joint: usize| match object.skeleton().get(joint) {
+		Some(info) => info.name.clone(),
+		None => format!("joint {}", joint),
+	};
+	ui.separator();
+	ui.label(format!("bone {}", name(joint)));
+	let parent = object.skeleton().get(joint).and_then(|info| info.parent);
+	ui.label(match parent {
+		Some(parent) => format!("child of {}", name(parent)),
+		None => "root".to_owned(),
+	});
+	if let Some(transform) = object.joints().get(joint) {
+		let position = transform.w_axis;
+		ui.label(format!(
+			"object space x{:.2} y{:.2} z{:.2}",
+			position.x, position.y, position.z
+		));
+	}
+	let attached = scene
+		.children(id)
+		.iter()
+		.filter_map(|child| scene.get(*child))
+		.filter(|child| child.socket() == Some(joint))
+		.count();
+	if attached > 0 {
+		ui.label(format!("{} attached", attached));
+	}
+}
@@ -0,0 +1,312 @@
+//! Editing the scene from the viewport: selection, isolation, the inspector,
+//! the hierarchy, skeletons, the right click context menu, the command
+//! palette and the undo history their edits go through.
+
+mod context_menu;
+mod hierarchy;
+mod history;
+mod inspector;
+mod palette;
+mod pointer;
+mod selection;
+mod skeletons;
+
+pub use context_menu::{ContextMenu, MenuAction, Primitive};
+pub use hierarchy::Hierarchy;
+pub use history::{Edit, History};
+pub use inspector::Inspector;
+pub use palette::{fuzzy_score, CommandPalette, EditorCommand, PaletteChoice};
+pub use selection::Selection;
+pub use skeletons::SkeletonView;
+
+use glam::{DVec3, Vec4};
+use rend3::types::{Handedness, MaterialHandle, MeshHandle, ObjectMeshKind};
+use rend3::Renderer;
+use rend3_routine::pbr::{AlbedoComponent, PbrMaterial};
+
+use crate::assets;
+use crate::debug::ColorSpaceAudit;
+use crate::metadata::Metadata;
+use crate::scene::{ObjectId, ObjectSnapshot, Scene, SceneTransform, Transform};
+use crate::scene_file::ObjectRecord;
+
+/// Meshes and material primitives are added with.
+struct Primitives {
+	cube: MeshHandle,
+	sphere: MeshHandle,
+	material: MaterialHandle,
+}
+
+/// Scene editing state: selection, undo history, clipboard and isolation.
+pub struct Editor {
+	pub selection: Selection,
+	pub history: History,
+	pub context_menu: ContextMenu,
+	pub hierarchy: Hierarchy,
+	pub palette: CommandPalette,
+	pub inspector: Inspector,
+	pub skeletons: SkeletonView,
+	clipboard: Option<ObjectSnapshot>,
+	/// the last copied object as text, for the system clipboard
+	copied_text: Option<String>,
+	primitives: Primitives,
+	/// objects being isolated and the objects hidden for them
+	isolated: Option<(Vec<ObjectId>, Vec<ObjectId>)>,
+}
+
+impl Editor {
+	pub fn new(
+		renderer: &Renderer,
+		scene: &mut Scene,
+		color_audit: &mut ColorSpaceAudit,
+		handedness: Handedness,
+	) -> Self {
+		let primitives = Primitives {
+			cube: scene.add_mesh(renderer, assets::cube(handedness)),
+			sphere: scene.add_mesh(renderer, assets::sphere(32, 16, handedness)),
+			material: color_audit.add_material(
+				renderer,
+				"primitive",
+				PbrMaterial {
+					albedo: AlbedoComponent::Value(Vec4::new(0.6, 0.6, 0.6, 1.0)),
+					..PbrMaterial::default()
+				},
+			),
+		};
+		Self {
+			selection: Selection::default(),
+			history: History::default(),
+			context_menu: ContextMenu::default(),
+			hierarchy: Hierarchy::default(),
+			palette: CommandPalette::default(),
+			inspector: Inspector::default(),
+			skeletons: SkeletonView::default(),
+			clipboard: None,
+			copied_text: None,
+			primitives,
+			isolated: None,
+		}
+	}
+
+	pub fn can_paste(&self) -> bool {
+		self.clipboard.is_some()
+	}
+
+	/// The object copied since the last call, as the json it's saved as in
+	/// scene files, for the system clipboard.
+	pub fn take_copied_text(&mut self) -> Option<String> {
+		self.copied_text.take()
+	}
+
+	/// Runs a context menu action. Focusing moves the camera, so it's left to
+	/// the caller.
+	pub fn run(&mut self, renderer: &Renderer, scene: &mut Scene, action: MenuAction) {
+		match action {
+			MenuAction::Duplicate(id) => {
+				if let Some(edit) = duplicate(scene, id) {
+					self.history.apply(renderer, scene, edit);
+				}
+			}
+			MenuAction::Delete(id) => {
+				if let Some(edit) = Edit::remove(scene, id) {
+					self.history.apply(renderer, scene, edit);
+				}
+			}
+			MenuAction::Focus(_) => {}
+			// the whole selection if the object is part of it
+			MenuAction::Isolate(id) => {
+				if !self.selection.contains(id) {
+					self.selection.select(id);
+				}
+				let objects = self.selection.objects().to_vec();
+				self.isolate(renderer, scene, objects);
+			}
+			MenuAction::Copy(id) => {
+				let object = match scene.get(id) {
+					Some(object) => object,
+					None => return,
+				};
+				self.clipboard = Some(object.snapshot());
+				self.copied_text =
+					serde_json::to_string_pretty(&ObjectRecord::of(object).to_json()).ok();
+			}
+			MenuAction::AddPrimitive(primitive, position) => {
+				let mesh = match primitive {
+					Primitive::Cube => &self.primitives.cube,
+					Primitive::Sphere => &self.primitives.sphere,
+				};
+				let snapshot = ObjectSnapshot {
+					name: scene.unique_name(primitive.label()),
+					mesh_kind: ObjectMeshKind::Static(mesh.clone()),
+					material: self.primitives.material.clone(),
+					transform: Transform::from_f64_translation(position.as_dvec3()),
+					material_override: None,
+					metadata: Metadata::new(),
+					modifiers: Vec::new(),
+					parent: None,
+					socket: None,
+				};
+				self.add(renderer, scene, snapshot);
+			}
+			MenuAction::Paste(position) => {
+				let mut snapshot = match &self.clipboard {
+					Some(snapshot) => snapshot.clone(),
+					None => return,
+				};
+				snapshot.name = scene.unique_name(&snapshot.name);
+				// keeps the copied rotation and scale
+				let mut transform = snapshot.transform.to_mat4();
+				transform.w_axis = position.extend(1.0);
+				snapshot.transform = Transform::from_mat4(transform);
+				self.add(renderer, scene, snapshot);
+			}
+		}
+	}
+
+	/// Runs an editor command from the palette. Focusing and screenshots
+	/// are left to the caller.
+	pub fn run_command(&mut self, renderer: &Renderer, scene: &mut Scene, command: EditorCommand) {
+		let selection = self.selection.objects().to_vec();
+		match command {
+			EditorCommand::Undo => {
+				self.history.undo(renderer, scene);
+			}
+			EditorCommand::Redo => {
+				self.history.redo(renderer, scene);
+			}
+			EditorCommand::DuplicateSelection => {
+				let edits: Vec<Edit> = selection
+					.iter()
+					.filter_map(|&id| duplicate(scene, id))
+					.collect();
+				if !edits.is_empty() {
+					self.history.apply(renderer, scene, Edit::Batch(edits));
+				}
+			}
+			EditorCommand::DeleteSelection => self.delete(renderer, scene, &selection),
+			EditorCommand::CopySelection => {
+				if let Some(&id) = selection.first() {
+					self.run(renderer, scene, MenuAction::Copy(id));
+				}
+			}
+			EditorCommand::ToggleIsolation => self.toggle_isolation(renderer, scene),
+			EditorCommand::SelectAll => {
+				self.selection.clear();
+				for (id, _) in scene.visible() {
+					self.selection.add(id);
+				}
+			}
+			EditorCommand::ClearSelection => self.selection.clear(),
+			EditorCommand::FocusSelection | EditorCommand::Screenshot => {}
+		}
+	}
+
+	/// Removes objects as one edit.
+	pub fn delete(&mut self, renderer: &Renderer, scene: &mut Scene, objects: &[ObjectId]) {
+		let edits: Vec<Edit> = objects
+			.iter()
+			.filter_map(|&id| Edit::remove(scene, id))
+			.collect();
+		if !edits.is_empty() {
+			self.history.apply(renderer, scene, Edit::Batch(edits));
+		}
+	}
+
+	fn add(&mut self, renderer: &Renderer, scene: &mut Scene, snapshot: ObjectSnapshot) {
+		let id = scene.reserve_id();
+		self.history.apply(renderer, scene, Edit::Add(id, snapshot));
+	}
+
+	/// Hides every object but `objects` until [`Editor::exit_isolation`].
+	pub fn isolate(&mut self, renderer: &Renderer, scene: &mut Scene, objects: Vec<ObjectId>) {
+		self.exit_isolation(renderer, scene);
+		let hidden: Vec<ObjectId> = scene
+			.visible()
+			.map(|(id, _)| id)
+			.filter(|id| !objects.contains(id))
+			.collect();
+		for id in &hidden {
+			scene.set_visible(renderer, *id, false);
+		}
+		self.isolated = Some((objects, hidden));
+	}
+
+	/// Isolates the selection, or exits isolation if already isolating.
+	pub fn toggle_isolation(&mut self, renderer: &Renderer, scene: &mut Scene) {
+		match self.isolated {
+			Some(_) => self.exit_isolation(renderer, scene),
+			None if !self.selection.is_empty() => {
+				let objects = self.selection.objects().to_vec();
+				self.isolate(renderer, scene, objects);
+			}
+			None => {}
+		}
+	}
+
+	/// Shows the objects hidden by [`Editor::isolate`] again.
+	pub fn exit_isolation(&mut self, renderer: &Renderer, scene: &mut Scene) {
+		if let Some((_, hidden)) = self.isolated.take() {
+			for id in hidden {
+				scene.set_visible(renderer, id, true);
+			}
+		}
+	}
+
+	pub fn isolated(&self) -> Option<&[ObjectId]> {
+		self.isolated
+			.as_ref()
+			.map(|(objects, _)| objects.as_slice())
+	}
+
+	/// Shows what is isolated at the top of the screen, with a button to
+	/// exit.
+	pub fn isolation_breadcrumb(
+		&mut self,
+		ctx: &egui::CtxRef,
+		renderer: &Renderer,
+		scene: &mut Scene,
+	) {
+		let objects = match self.isolated() {
+			Some(objects) => objects,
+			None => return,
+		};
+		let names: Vec<&str> = objects
+			.iter()
+			.filter_map(|&id| scene.get(id))
+			.map(|object| object.name.as_str())
+			.collect();
+		let text = match names.as_slice() {
+			[] => "nothing".to_string(),
+			[name] => name.to_string(),
+			[name, rest @ ..] => format!("{} and {} more", name, rest.len()),
+		};
+
+		let mut exit = false;
+		egui::Area::new("isolation breadcrumb")
+			.anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 8.0))
+			.show(ctx, |ui| {
+				egui::Frame::popup(ui.style()).show(ui, |ui| {
+					ui.horizontal(|ui| {
+						ui.label("scene ›");
+						ui.strong(format!("isolated: {}", text));
+						exit = ui.button("exit (shift+h)").clicked();
+					});
+				});
+			});
+		if exit {
+			self.exit_isolation(renderer, scene);
+		}
+	}
+}
+
+/// Adding a copy of `id` next to it, or `None` if it doesn't exist.
+fn duplicate(scene: &mut Scene, id: ObjectId) -> Option<Edit> {
+	let object = scene.get(id)?;
+	// next to the original so both can be seen
+	let offset = object.geometry.as_ref().map_or(1.0, |g| g.radius * 2.0);
+	let mut snapshot = object.snapshot();
+	snapshot.name = scene.unique_name(&snapshot.name);
+	snapshot.transform =
+		Transform::from_f64_translation(DVec3::X * offset as f64) * snapshot.transform;
+	Some(Edit::Add(scene.reserve_id(), snapshot))
+}
@@ -0,0 +1,267 @@
+use crate::scene::{ObjectId, Scene};
+use crate::teleport::CONSOLE_COMMANDS;
+
+/// Editor actions listed in the command palette.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditorCommand {
+	Undo,
+	Redo,
+	DuplicateSelection,
+	DeleteSelection,
+	CopySelection,
+	FocusSelection,
+	ToggleIsolation,
+	SelectAll,
+	ClearSelection,
+	Screenshot,
+}
+
+impl EditorCommand {
+	pub const ALL: [EditorCommand; 10] = [
+		EditorCommand::Undo,
+		EditorCommand::Redo,
+		EditorCommand::DuplicateSelection,
+		EditorCommand::DeleteSelection,
+		EditorCommand::CopySelection,
+		EditorCommand::FocusSelection,
+		EditorCommand::ToggleIsolation,
+		EditorCommand::SelectAll,
+		EditorCommand::ClearSelection,
+		EditorCommand::Screenshot,
+	];
+
+	pub fn label(&self) -> &'static str {
+		match self {
+			EditorCommand::Undo => "undo",
+			EditorCommand::Redo => "redo",
+			EditorCommand::DuplicateSelection => "duplicate selection",
+			EditorCommand::DeleteSelection => "delete selection",
+			EditorCommand::CopySelection => "copy selection",
+			EditorCommand::FocusSelection => "focus selection",
+			EditorCommand::ToggleIsolation => "isolate selection",
+			EditorCommand::SelectAll => "select all",
+			EditorCommand::ClearSelection => "clear selection",
+			EditorCommand::Screenshot => "screenshot",
+		}
+	}
+
+	pub fn shortcut(&self) -> Option<&'static str> {
+		match self {
+			EditorCommand::Undo => Some("ctrl+z"),
+			EditorCommand::Redo => Some("ctrl+y"),
+			EditorCommand::ToggleIsolation => Some("shift+h"),
+			EditorCommand::Screenshot => Some("f12"),
+			_ => None,
+		}
+	}
+}
+
+/// What was picked from the command palette.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PaletteChoice {
+	Command(EditorCommand),
+	Object(ObjectId),
+	/// a console command line to run
+	Console(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Entry {
+	Command(EditorCommand),
+	Object(ObjectId, String),
+	/// fills in the verb of a console command
+	Usage(&'static str, &'static str),
+	/// runs the query as a console command
+	Run(String),
+}
+
+impl Entry {
+	fn text(&self) -> String {
+		match self {
+			Entry::Command(command) => match command.shortcut() {
+				Some(shortcut) => format!("{}  ({})", command.label(), shortcut),
+				None => command.label().into(),
+			},
+			Entry::Object(_, name) => format!("select {}", name),
+			Entry::Usage(_, usage) => usage.to_string(),
+			Entry::Run(line) => format!("run {}", line),
+		}
+	}
+
+	fn searched(&self) -> &str {
+		match self {
+			Entry::Command(command) => command.label(),
+			Entry::Object(_, name) => name,
+			Entry::Usage(_, usage) => usage,
+			Entry::Run(line) => line,
+		}
+	}
+}
+
+/// most entries listed at once
+const MAX_ENTRIES: usize = 12;
+
+/// Fuzzy search over editor commands, console commands and scene objects,
+/// opened with Ctrl+P and driven from the keyboard.
+#[derive(Default)]
+pub struct CommandPalette {
+	open: bool,
+	query: String,
+	highlighted: usize,
+	/// focus the search field on the next frame
+	focus: bool,
+}
+
+impl CommandPalette {
+	pub fn toggle(&mut self) {
+		self.open = !self.open;
+		self.query.clear();
+		self.highlighted = 0;
+		self.focus = self.open;
+	}
+
+	/// Shows the palette if it's open and returns what was picked this frame.
+	pub fn show(&mut self, ctx: &egui::CtxRef, scene: &Scene) -> Option<PaletteChoice> {
+		if !self.open {
+			return None;
+		}
+		let entries = self.entries(scene);
+		self.highlighted = self.highlighted.min(entries.len().saturating_sub(1));
+
+		let (up, down, enter, escape) = {
+			let input = ctx.input();
+			(
+				input.key_pressed(egui::Key::ArrowUp),
+				input.key_pressed(egui::Key::ArrowDown),
+				input.key_pressed(egui::Key::Enter),
+				input.key_pressed(egui::Key::Escape),
+			)
+		};
+		if up {
+			self.highlighted = self.highlighted.saturating_sub(1);
+		}
+		if down && self.highlighted + 1 < entries.len() {
+			self.highlighted += 1;
+		}
+
+		let mut picked = enter.then_some(self.highlighted);
+		egui::Window::new("command palette")
+			.title_bar(false)
+			.collapsible(false)
+			.resizable(false)
+			.anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 48.0))
+			.fixed_size(egui::vec2(360.0, 0.0))
+			.show(ctx, |ui| {
+				let search = ui.add(
+					egui::TextEdit::singleline(&mut self.query)
+						.hint_text("search commands and objects")
+						.desired_width(f32::INFINITY),
+				);
+				if std::mem::take(&mut self.focus) || search.lost_focus() && !enter {
+					search.request_focus();
+				}
+				if search.changed() {
+					self.highlighted = 0;
+				}
+				ui.separator();
+				if entries.is_empty() {
+					ui.label("no matches");
+				}
+				for (i, entry) in entries.iter().enumerate() {
+					if ui
+						.selectable_label(i == self.highlighted, entry.text())
+						.clicked()
+					{
+						picked = Some(i);
+					}
+				}
+			});
+
+		if escape {
+			self.open = false;
+			return None;
+		}
+		let choice = match picked.and_then(|i| entries.get(i))? {
+			Entry::Command(command) => PaletteChoice::Command(*command),
+			Entry::Object(id, _) => PaletteChoice::Object(*id),
+			Entry::Usage(verb, _) => {
+				self.query = format!("{} ", verb);
+				self.focus = true;
+				return None;
+			}
+			Entry::Run(line) => PaletteChoice::Console(line.clone()),
+		};
+		self.open = false;
+		Some(choice)
+	}
+
+	/// Entries matching the query, best first.
+	fn entries(&self, scene: &Scene) -> Vec<Entry> {
+		let query = self.query.trim();
+		// a console command with its arguments runs as typed
+		let verb = query.split_whitespace().next().unwrap_or("");
+		if query.contains(' ') && CONSOLE_COMMANDS.iter().any(|(v, _)| *v == verb) {
+			return vec![Entry::Run(query.into())];
+		}
+
+		let mut objects: Vec<(ObjectId, &str)> = scene
+			.iter()
+			.map(|(id, object)| (id, object.name.as_str()))
+			.collect();
+		objects.sort_by_key(|(id, _)| *id);
+		let candidates = EditorCommand::ALL
+			.iter()
+			.map(|command| Entry::Command(*command))
+			.chain(
+				CONSOLE_COMMANDS
+					.iter()
+					.map(|(verb, usage)| Entry::Usage(verb, usage)),
+			)
+			.chain(
+				objects
+					.into_iter()
+					.map(|(id, name)| Entry::Object(id, name.into())),
+			);
+
+		let mut scored: Vec<(i32, Entry)> = candidates
+			.filter_map(|entry| fuzzy_score(query, entry.searched()).map(|score| (score, entry)))
+			.collect();
+		// stable, so equal scores keep commands before objects
+		scored.sort_by_key(|(score, _)| -score);
+		scored
+			.into_iter()
+			.take(MAX_ENTRIES)
+			.map(|(_, entry)| entry)
+			.collect()
+	}
+}
+
+/// Scores how well `text` matches `query` when the query's characters appear
+/// in order, ignoring case. Runs of consecutive characters and matches at the
+/// start of words score higher. `None` if it doesn't match.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+	if query.trim().is_empty() {
+		return Some(0);
+	}
+	let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+	let mut score = 0;
+	let mut next = 0;
+	let mut previous: Option<usize> = None;
+	for wanted in query.chars().flat_map(char::to_lowercase) {
+		if wanted.is_whitespace() {
+			continue;
+		}
+		let found = next + text[next..].iter().position(|&c| c == wanted)?;
+		score += 1;
+		if previous.is_some_and(|previous| previous + 1 == found) {
+			score += 4;
+		}
+		if found == 0 || !text[found - 1].is_alphanumeric() {
+			score += 3;
+		}
+		previous = Some(found);
+		next = found + 1;
+	}
+	// shorter texts match more closely
+	Some(score * 16 - text.len() as i32)
+}
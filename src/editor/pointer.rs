@@ -0,0 +1,66 @@
+use glam::{Mat4, UVec2, Vec2, Vec3};
+
+use crate::camera;
+
+/// how far the pointer can move between pressing and releasing a button for
+/// it to still be a click rather than a drag, in points
+const CLICK_DISTANCE: f32 = 4.0;
+
+/// Tells clicks in the viewport apart from drags, like looking around with
+/// the right mouse button held.
+#[derive(Default)]
+pub struct ClickDetector {
+	/// where the button was pressed
+	press: Option<egui::Pos2>,
+}
+
+impl ClickDetector {
+	/// Position and modifiers of a click of `button` that ended this frame
+	/// and didn't start on the ui.
+	pub fn click(
+		&mut self,
+		ctx: &egui::CtxRef,
+		button: egui::PointerButton,
+	) -> Option<(egui::Pos2, egui::Modifiers)> {
+		let over_ui = ctx.is_pointer_over_area();
+		let mut click = None;
+		for event in &ctx.input().events {
+			match *event {
+				egui::Event::PointerButton {
+					pos,
+					button: pressed_button,
+					pressed,
+					modifiers,
+				} if pressed_button == button => match pressed {
+					true if !over_ui => self.press = Some(pos),
+					true => {}
+					false => {
+						let press = self.press.take();
+						if press.is_some_and(|press| press.distance(pos) <= CLICK_DISTANCE) {
+							click = Some((pos, modifiers));
+						}
+					}
+				},
+				_ => {}
+			}
+		}
+		click
+	}
+}
+
+/// World space direction from the camera through `pos`, or `None` if it's
+/// outside the viewport.
+pub fn pointer_direction(
+	ctx: &egui::CtxRef,
+	view_proj: Mat4,
+	viewport: (UVec2, UVec2),
+	pos: egui::Pos2,
+) -> Option<Vec3> {
+	let (offset, size) = viewport;
+	let pixel = Vec2::new(pos.x, pos.y) * ctx.pixels_per_point();
+	let local = pixel - offset.as_vec2();
+	if local.cmplt(Vec2::ZERO).any() || local.cmpge(size.as_vec2()).any() {
+		return None;
+	}
+	Some(camera::screen_to_direction(view_proj, viewport, pixel))
+}
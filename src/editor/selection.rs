@@ -0,0 +1,103 @@
+use glam::{Mat4, UVec2, Vec3, Vec4};
+
+use super::pointer::{self, ClickDetector};
+use crate::debug::DebugDraw;
+use crate::scene::{ObjectId, Scene, SceneTransform};
+
+/// Objects picked by left clicking them in the viewport.
+#[derive(Default)]
+pub struct Selection {
+	objects: Vec<ObjectId>,
+	clicks: ClickDetector,
+}
+
+impl Selection {
+	pub fn objects(&self) -> &[ObjectId] {
+		&self.objects
+	}
+
+	pub fn contains(&self, id: ObjectId) -> bool {
+		self.objects.contains(&id)
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.objects.is_empty()
+	}
+
+	/// Replaces the selection with just `id`.
+	pub fn select(&mut self, id: ObjectId) {
+		self.objects.clear();
+		self.objects.push(id);
+	}
+
+	pub fn add(&mut self, id: ObjectId) {
+		if !self.contains(id) {
+			self.objects.push(id);
+		}
+	}
+
+	pub fn clear(&mut self) {
+		self.objects.clear();
+	}
+
+	/// Selects the object under a left click in the viewport, toggling it in
+	/// and out of the selection while shift is held. Clicking empty space
+	/// clears the selection.
+	pub fn click(
+		&mut self,
+		ctx: &egui::CtxRef,
+		scene: &Scene,
+		view_proj: Mat4,
+		camera_position: Vec3,
+		viewport: (UVec2, UVec2),
+	) {
+		let (pos, modifiers) = match self.clicks.click(ctx, egui::PointerButton::Primary) {
+			Some(click) => click,
+			None => return,
+		};
+		let direction = match pointer::pointer_direction(ctx, view_proj, viewport, pos) {
+			Some(direction) => direction,
+			None => return,
+		};
+		let hit = scene.raycast(camera_position, direction, f32::INFINITY, None);
+		match (hit, modifiers.shift) {
+			(Some((id, _)), true) if self.contains(id) => self.objects.retain(|&other| other != id),
+			(Some((id, _)), true) => self.add(id),
+			(Some((id, _)), false) => self.select(id),
+			(None, true) => {}
+			(None, false) => self.clear(),
+		}
+	}
+
+	/// Outlines the bounding boxes of the selected objects, dropping any that
+	/// no longer exist.
+	pub fn draw(&mut self, debug_draw: &mut DebugDraw, scene: &Scene) {
+		self.objects.retain(|&id| scene.get(id).is_some());
+		for object in self.objects.iter().filter_map(|&id| scene.get(id)) {
+			let geometry = match &object.geometry {
+				Some(geometry) if object.is_visible() => geometry,
+				_ => continue,
+			};
+			let min = geometry
+				.positions
+				.iter()
+				.fold(Vec3::splat(f32::MAX), |a, b| a.min(*b));
+			let max = geometry
+				.positions
+				.iter()
+				.fold(Vec3::splat(f32::MIN), |a, b| a.max(*b));
+			let transform = object.transform().to_mat4();
+			let corners = [0, 1, 2, 3, 4, 5, 6, 7].map(|i| {
+				let (x, y) = [
+					(min.x, min.y),
+					(max.x, min.y),
+					(max.x, max.y),
+					(min.x, max.y),
+				][i % 4];
+				let z = if i < 4 { min.z } else { max.z };
+				transform.transform_point3(Vec3::new(x, y, z))
+			});
+			debug_draw.hexahedron(corners, Vec4::new(1.0, 0.6, 0.0, 1.0));
+		}
+	}
+}
@@ -0,0 +1,242 @@
+use glam::{const_vec4, Mat4, UVec2, Vec2, Vec3, Vec4};
+
+use super::pointer::ClickDetector;
+use crate::camera;
+use crate::debug::DebugDraw;
+use crate::scene::{ObjectId, Scene, SceneTransform};
+
+const BONE_COLOR: Vec4 = const_vec4!([0.3, 0.9, 0.5, 1.0]);
+const SELECTED_COLOR: Vec4 = const_vec4!([1.0, 0.6, 0.0, 1.0]);
+/// how close to a joint a click has to be to pick it, in points
+const PICK_DISTANCE: f32 = 8.0;
+
+/// Draws the skeletons of animated objects as bones from each joint to its
+/// parent, to debug skinning and attachments. Clicking near a joint picks
+/// its bone, which the inspector shows.
+#[derive(Default)]
+pub struct SkeletonView {
+	pub shown: bool,
+	/// object and joint picked
+	selected: Option<(ObjectId, usize)>,
+	clicks: ClickDetector,
+}
+
+impl SkeletonView {
+	pub fn selected(&self) -> Option<(ObjectId, usize)> {
+		self.selected
+	}
+
+	/// Picks the joint nearest to a left click in the viewport, returning
+	/// the object it belongs to. Clicking away from every joint clears it.
+	pub fn click(
+		&mut self,
+		ctx: &egui::CtxRef,
+		scene: &Scene,
+		view_proj: Mat4,
+		viewport: (UVec2, UVec2),
+	) -> Option<ObjectId> {
+		let (pos, _) = self.clicks.click(ctx, egui::PointerButton::Primary)?;
+		if !self.shown {
+			return None;
+		}
+		let pixel = Vec2::new(pos.x, pos.y) * ctx.pixels_per_point();
+		let max_distance = PICK_DISTANCE * ctx.pixels_per_point();
+		// only the skeletons that are drawn can be picked
+		self.selected = scene
+			.visible()
+			.filter_map(|(id, object)| {
+				let transform = object.transform().to_mat4();
+				let (joint, distance) = nearest_joint(
+					transform,
+					object.joints(),
+					view_proj,
+					viewport,
+					pixel,
+					max_distance,
+				)?;
+				Some((id, joint, distance))
+			})
+			.min_by(|a, b| a.2.total_cmp(&b.2))
+			.map(|(id, joint, _)| (id, joint));
+		self.selected.map(|(id, _)| id)
+	}
+
+	pub fn draw(&mut self, debug_draw: &mut DebugDraw, scene: &Scene) {
+		if let Some((id, joint)) = self.selected {
+			let exists = scene
+				.get(id)
+				.is_some_and(|object| joint < object.joints().len());
+			if !exists {
+				self.selected = None;
+			}
+		}
+		if !self.shown {
+			return;
+		}
+		for (id, object) in scene.visible() {
+			let transform = object.transform().to_mat4();
+			let positions: Vec<Vec3> = joint_positions(transform, object.joints()).collect();
+			for (joint, position) in positions.iter().enumerate() {
+				let color = match self.selected == Some((id, joint)) {
+					true => SELECTED_COLOR,
+					false => BONE_COLOR,
+				};
+				let parent = object
+					.skeleton()
+					.get(joint)
+					.and_then(|joint| joint.parent)
+					.and_then(|parent| positions.get(parent));
+				match parent {
+					Some(parent) => bone(debug_draw, *parent, *position, color),
+					None => cross(debug_draw, *position, 0.05, color),
+				}
+			}
+		}
+	}
+}
+
+/// World space positions of an object's joints, given its transform and
+/// the object space transforms of the joints.
+fn joint_positions(transform: Mat4, joints: &[Mat4]) -> impl Iterator<Item = Vec3> + '_ {
+	joints
+		.iter()
+		.map(move |joint| (transform * *joint).w_axis.truncate())
+}
+
+/// The joint drawn nearest to `pixel` within `max_distance` pixels, and how
+/// far from it, of an object with `transform` posed with `joints`.
+fn nearest_joint(
+	transform: Mat4,
+	joints: &[Mat4],
+	view_proj: Mat4,
+	viewport: (UVec2, UVec2),
+	pixel: Vec2,
+	max_distance: f32,
+) -> Option<(usize, f32)> {
+	joint_positions(transform, joints)
+		.enumerate()
+		.filter_map(|(joint, position)| {
+			let screen = camera::world_to_screen(view_proj, viewport, position)?;
+			Some((joint, screen.distance(pixel)))
+		})
+		.filter(|(_, distance)| *distance <= max_distance)
+		.min_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+/// An octahedron from `start` to `end`, widest a fifth of the way along.
+fn bone(debug_draw: &mut DebugDraw, start: Vec3, end: Vec3, color: Vec4) {
+	let along = end - start;
+	let length = along.length();
+	if length <= f32::EPSILON {
+		return;
+	}
+	let direction = along / length;
+	let other = match direction.y.abs() < 0.9 {
+		true => Vec3::Y,
+		false => Vec3::X,
+	};
+	let side = direction.cross(other).normalize() * length * 0.1;
+	let up = direction.cross(side);
+	let center = start + along * 0.2;
+	let ring = [side, up, -side, -up].map(|offset| center + offset);
+	for (i, corner) in ring.iter().enumerate() {
+		debug_draw.line(start, *corner, color);
+		debug_draw.line(*corner, end, color);
+		debug_draw.line(*corner, ring[(i + 1) % ring.len()], color);
+	}
+}
+
+/// Marks a root joint, which has no bone to draw.
+fn cross(debug_draw: &mut DebugDraw, position: Vec3, size: f32, color: Vec4) {
+	for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+		debug_draw.line(position - axis * size, position + axis * size, color);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::skeleton::{self, Joint};
+
+	/// A root with a joint a unit above it and another a unit right of that.
+	fn pose() -> Vec<Mat4> {
+		let joints = [
+			Joint {
+				name: "root".into(),
+				parent: None,
+			},
+			Joint {
+				name: "neck".into(),
+				parent: Some(0),
+			},
+			Joint {
+				name: "shoulder".into(),
+				parent: Some(1),
+			},
+		];
+		let parents: Vec<_> = joints.iter().map(|joint| joint.parent).collect();
+		let local = [
+			Mat4::IDENTITY,
+			Mat4::from_translation(Vec3::Y),
+			Mat4::from_translation(Vec3::X),
+		];
+		skeleton::to_object(&parents, &local)
+	}
+
+	/// Two units either way of the origin across a 100 pixel viewport.
+	fn view_proj() -> Mat4 {
+		Mat4::orthographic_rh(-2.0, 2.0, -2.0, 2.0, -10.0, 10.0)
+	}
+
+	fn viewport() -> (UVec2, UVec2) {
+		(UVec2::ZERO, UVec2::new(100, 100))
+	}
+
+	#[test]
+	fn picks_the_nearest_joint() {
+		let joints = pose();
+		let pick = |pixel: Vec2, max_distance: f32| {
+			nearest_joint(
+				Mat4::IDENTITY,
+				&joints,
+				view_proj(),
+				viewport(),
+				pixel,
+				max_distance,
+			)
+			.map(|(joint, _)| joint)
+		};
+		// the neck is at (50, 25) and the shoulder at (75, 25)
+		assert_eq!(pick(Vec2::new(50.0, 50.0), 8.0), Some(0));
+		assert_eq!(pick(Vec2::new(52.0, 27.0), 8.0), Some(1));
+		assert_eq!(pick(Vec2::new(74.0, 24.0), 8.0), Some(2));
+		assert_eq!(pick(Vec2::new(90.0, 90.0), 8.0), None);
+		// between the two, out of reach of both until the reach is raised
+		assert_eq!(pick(Vec2::new(64.0, 25.0), 8.0), None);
+		assert_eq!(pick(Vec2::new(64.0, 25.0), 20.0), Some(2));
+	}
+
+	#[test]
+	fn picks_where_the_object_is() {
+		let joints = pose();
+		let transform = Mat4::from_translation(Vec3::new(-1.0, 0.0, 0.0));
+		let picked = nearest_joint(
+			transform,
+			&joints,
+			view_proj(),
+			viewport(),
+			Vec2::new(50.0, 25.0),
+			8.0,
+		);
+		assert_eq!(picked, Some((2, 0.0)));
+		let missed = nearest_joint(
+			transform,
+			&joints,
+			view_proj(),
+			viewport(),
+			Vec2::new(50.0, 50.0),
+			8.0,
+		);
+		assert_eq!(missed, None);
+	}
+}
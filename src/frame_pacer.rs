@@ -0,0 +1,186 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Waker};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rend3::Renderer;
+use wgpu::Maintain;
+use winit::monitor::MonitorHandle;
+use winit::window::Window;
+
+use crate::fullscreen;
+use crate::settings::GraphicsSettings;
+use crate::staging::NoopWaker;
+
+/// how long to sleep between checks while waiting on the gpu
+const POLL_INTERVAL: Duration = Duration::from_micros(200);
+/// how long to wait on the gpu before giving up and starting the frame
+/// anyway, in case a frame never reports being done
+const WAIT_TIMEOUT: Duration = Duration::from_secs(2);
+
+struct InFlight {
+	/// when input was sampled for the frame
+	sampled: Instant,
+	done: Pin<Box<dyn Future<Output = ()> + Send>>,
+}
+
+/// Limits how many frames the cpu gets ahead of the gpu and how often frames
+/// start, and estimates the latency from sampling input to the frame being on
+/// screen.
+///
+/// More frames in flight keep the gpu busy, fewer make input show up
+/// sooner. Low latency mode waits for the gpu to finish rendering the
+/// previous frame before input is sampled for the next one. Nothing here
+/// waits for frames to be presented, which can take another refresh.
+pub struct FramePacer {
+	/// oldest first
+	in_flight: VecDeque<InFlight>,
+	waker: Waker,
+	/// sample time of the frame being built
+	current: Option<Instant>,
+	/// smoothed time from input being sampled to the gpu finishing the frame
+	gpu_latency: Option<Duration>,
+	/// highest the monitor the window is on supports, in hz
+	max_refresh_rate: Option<f32>,
+	/// when the frame limiter lets the next frame start
+	next_frame: Option<Instant>,
+}
+
+impl Default for FramePacer {
+	fn default() -> Self {
+		Self {
+			in_flight: VecDeque::new(),
+			waker: Arc::new(NoopWaker).into(),
+			current: None,
+			gpu_latency: None,
+			max_refresh_rate: None,
+			next_frame: None,
+		}
+	}
+}
+
+impl FramePacer {
+	/// Waits for the frame limit and until the gpu has finished rendering
+	/// enough frames that fewer than the settings allow are in flight, then
+	/// marks the time input is sampled for the next frame. Gives up waiting
+	/// after [`WAIT_TIMEOUT`].
+	pub fn begin_frame(&mut self, renderer: &Renderer, settings: &GraphicsSettings) {
+		self.limit_rate(settings);
+		let limit = match settings.low_latency {
+			true => 1,
+			false => settings.frames_in_flight.max(1) as usize,
+		};
+		let start = Instant::now();
+		loop {
+			renderer.device.poll(Maintain::Poll);
+			self.collect();
+			if self.in_flight.len() < limit {
+				break;
+			}
+			if start.elapsed() > WAIT_TIMEOUT {
+				log::warn!(
+					"gpu hasn't finished a frame in {:?}, not waiting on it",
+					WAIT_TIMEOUT
+				);
+				break;
+			}
+			thread::sleep(POLL_INTERVAL);
+		}
+		self.current = Some(Instant::now());
+	}
+
+	fn limit_rate(&mut self, settings: &GraphicsSettings) {
+		let interval = match settings.frame_limit.interval(self.refresh_rate(settings)) {
+			Some(interval) => interval,
+			None => {
+				self.next_frame = None;
+				return;
+			}
+		};
+		let now = Instant::now();
+		// a frame that ran long starts the schedule over instead of the next
+		// ones rushing to catch up
+		let start = self
+			.next_frame
+			.filter(|start| *start + interval > now)
+			.unwrap_or(now);
+		if start > now {
+			thread::sleep(start - now);
+		}
+		self.next_frame = Some(start + interval);
+	}
+
+	/// Looks up the highest refresh rate of the monitor the window is on.
+	/// Call on startup and whenever the window moves.
+	pub fn update_monitor(&mut self, window: &Window) {
+		let rate = window.current_monitor().as_ref().and_then(max_refresh_rate);
+		if rate != self.max_refresh_rate {
+			log::info!("monitor refreshes at up to {:?}hz", rate);
+			self.max_refresh_rate = rate;
+		}
+	}
+
+	/// The highest refresh rate the monitor supports, which is all winit can
+	/// tell. It may be running slower.
+	pub fn max_refresh_rate(&self) -> Option<f32> {
+		self.max_refresh_rate
+	}
+
+	/// The rate frames are paced to, the user's override or else the
+	/// monitor's highest.
+	pub fn refresh_rate(&self, settings: &GraphicsSettings) -> Option<f32> {
+		settings.refresh_rate.or(self.max_refresh_rate)
+	}
+
+	/// Call after the frame's work was submitted to the gpu.
+	pub fn end_frame(&mut self, renderer: &Renderer) {
+		let sampled = match self.current.take() {
+			Some(sampled) => sampled,
+			// a redraw without a logic update in between
+			None => return,
+		};
+		self.in_flight.push_back(InFlight {
+			sampled,
+			done: Box::pin(renderer.queue.on_submitted_work_done()),
+		});
+	}
+
+	/// Drops the frames the gpu finished, timing them as of now. Frames finish
+	/// in order, so this stops at the first one still running.
+	fn collect(&mut self) {
+		let mut context = Context::from_waker(&self.waker);
+		while let Some(frame) = self.in_flight.front_mut() {
+			if frame.done.as_mut().poll(&mut context).is_pending() {
+				break;
+			}
+			let latency = frame.sampled.elapsed();
+			self.gpu_latency = Some(match self.gpu_latency {
+				Some(smoothed) => smoothed.mul_f32(0.9) + latency.mul_f32(0.1),
+				None => latency,
+			});
+			self.in_flight.pop_front();
+		}
+	}
+
+	pub fn frames_in_flight(&self) -> usize {
+		self.in_flight.len()
+	}
+
+	/// Estimated time from input being sampled to the frame showing up,
+	/// assuming it's shown on the next refresh `frame_interval` after the
+	/// gpu finishes it. Frames are only checked on once a frame unless the
+	/// pacer is waiting on them, so this runs high by up to a frame.
+	pub fn latency(&self, frame_interval: Duration) -> Option<Duration> {
+		self.gpu_latency.map(|latency| latency + frame_interval)
+	}
+}
+
+fn max_refresh_rate(monitor: &MonitorHandle) -> Option<f32> {
+	fullscreen::fastest_video_mode(monitor)
+		.map(|mode| mode.refresh_rate())
+		.filter(|rate| *rate > 0)
+		.map(f32::from)
+}
@@ -0,0 +1,66 @@
+use winit::monitor::{MonitorHandle, VideoMode};
+use winit::window::{Fullscreen, Window};
+
+/// How the window fills the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FullscreenMode {
+	Windowed,
+	/// a window covering the monitor, quick to alt-tab out of
+	Borderless,
+	/// takes over the monitor's video mode, which can present with less
+	/// latency but loses the swapchain when alt-tabbing
+	Exclusive,
+}
+
+impl FullscreenMode {
+	pub const ALL: [FullscreenMode; 3] = [
+		FullscreenMode::Windowed,
+		FullscreenMode::Borderless,
+		FullscreenMode::Exclusive,
+	];
+
+	pub fn label(&self) -> &'static str {
+		match self {
+			FullscreenMode::Windowed => "windowed",
+			FullscreenMode::Borderless => "borderless",
+			FullscreenMode::Exclusive => "exclusive",
+		}
+	}
+
+	pub fn of(window: &Window) -> Self {
+		match window.fullscreen() {
+			None => FullscreenMode::Windowed,
+			Some(Fullscreen::Borderless(_)) => FullscreenMode::Borderless,
+			Some(Fullscreen::Exclusive(_)) => FullscreenMode::Exclusive,
+		}
+	}
+
+	/// Switches the window to this mode on the monitor it's on. The surface
+	/// is reconfigured by the resize that follows.
+	pub fn apply(self, window: &Window) {
+		let monitor = window.current_monitor();
+		let fullscreen = match self {
+			FullscreenMode::Windowed => None,
+			FullscreenMode::Borderless => Some(Fullscreen::Borderless(monitor)),
+			FullscreenMode::Exclusive => match monitor.as_ref().and_then(fastest_video_mode) {
+				Some(mode) => Some(Fullscreen::Exclusive(mode)),
+				None => {
+					log::warn!("no video mode for exclusive fullscreen, going borderless");
+					Some(Fullscreen::Borderless(monitor))
+				}
+			},
+		};
+		window.set_fullscreen(fullscreen);
+	}
+}
+
+/// The monitor's video mode with the highest refresh rate at its current
+/// resolution. winit can't tell which mode is current, so the monitor may
+/// well be running slower than this.
+pub fn fastest_video_mode(monitor: &MonitorHandle) -> Option<VideoMode> {
+	let size = monitor.size();
+	monitor
+		.video_modes()
+		.filter(|mode| mode.size() == size)
+		.max_by_key(|mode| mode.refresh_rate())
+}
@@ -0,0 +1,9 @@
+//! Streaming of real world terrain and imagery tiles into the scene.
+
+mod source;
+mod streamer;
+mod tile;
+
+pub use source::{DirectorySource, Heights, TileData, TileSource};
+pub use streamer::{GeoRegion, GeoStreamer};
+pub use tile::{GeoFrame, TileId};
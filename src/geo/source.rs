@@ -0,0 +1,109 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use rend3::types::Texture;
+
+use super::TileId;
+use crate::assets::{self, AssetError};
+
+/// Contents of one tile. Either part may be missing.
+pub struct TileData {
+	/// color imagery, sRGB
+	pub imagery: Option<Texture>,
+	/// heights in meters as rows of `size` samples, north to south
+	pub heights: Option<Heights>,
+}
+
+/// Square grid of elevation samples covering a tile edge to edge.
+pub struct Heights {
+	pub size: u32,
+	pub samples: Vec<f32>,
+}
+
+impl Heights {
+	/// Bilinear sample at `u` west to east and `v` north to south, 0.0 to 1.0.
+	pub fn sample(&self, u: f64, v: f64) -> f64 {
+		let max = (self.size - 1) as f64;
+		let (x, y) = (u.clamp(0.0, 1.0) * max, v.clamp(0.0, 1.0) * max);
+		let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+		let (x1, y1) = ((x0 + 1).min(self.size - 1), (y0 + 1).min(self.size - 1));
+		let at = |x: u32, y: u32| self.samples[(y * self.size + x) as usize] as f64;
+		let (fx, fy) = (x.fract(), y.fract());
+		let top = at(x0, y0) * (1.0 - fx) + at(x1, y0) * fx;
+		let bottom = at(x0, y1) * (1.0 - fx) + at(x1, y1) * fx;
+		top * (1.0 - fy) + bottom * fy
+	}
+}
+
+/// Where tiles come from. Fetching runs on a background thread.
+pub trait TileSource: Send + Sync {
+	fn fetch(&self, tile: TileId) -> Result<TileData, AssetError>;
+}
+
+/// Tiles in a directory using the usual slippy map layout: imagery at
+/// `{z}/{x}/{y}.bmp` and heights at `{z}/{x}/{y}.height.bmp`, encoded the
+/// way Terrarium tiles are (`r * 256 + g + b / 256 - 32768` meters).
+///
+/// Tiles from a server can be mirrored into this layout and converted to
+/// bmp, the only image format the loader reads.
+pub struct DirectorySource {
+	pub root: PathBuf,
+}
+
+impl DirectorySource {
+	pub fn new(root: impl Into<PathBuf>) -> Self {
+		Self { root: root.into() }
+	}
+
+	fn path(&self, tile: TileId, extension: &str) -> PathBuf {
+		self.root
+			.join(tile.z.to_string())
+			.join(tile.x.to_string())
+			.join(format!("{}.{}", tile.y, extension))
+	}
+}
+
+impl TileSource for DirectorySource {
+	fn fetch(&self, tile: TileId) -> Result<TileData, AssetError> {
+		let imagery = optional(assets::try_load_texture(&self.path(tile, "bmp"), true))?;
+		let heights = optional(load_heights(&self.path(tile, "height.bmp")))?;
+		if imagery.is_none() && heights.is_none() {
+			return Err(AssetError::Io(io::Error::new(
+				io::ErrorKind::NotFound,
+				format!(
+					"no imagery or heights for tile {}/{}/{}",
+					tile.z, tile.x, tile.y
+				),
+			)));
+		}
+		Ok(TileData { imagery, heights })
+	}
+}
+
+/// Treats a missing file as `None`.
+fn optional<T>(result: Result<T, AssetError>) -> Result<Option<T>, AssetError> {
+	match result {
+		Ok(value) => Ok(Some(value)),
+		Err(AssetError::Io(e)) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+		Err(e) => Err(e),
+	}
+}
+
+fn load_heights(path: &Path) -> Result<Heights, AssetError> {
+	let texture = assets::try_load_texture(path, false)?;
+	if texture.size.x != texture.size.y || texture.size.x < 2 {
+		return Err(AssetError::Invalid(format!(
+			"height tiles must be square, got {}x{}",
+			texture.size.x, texture.size.y
+		)));
+	}
+	let samples = texture
+		.data
+		.chunks_exact(4)
+		.map(|p| p[0] as f32 * 256.0 + p[1] as f32 + p[2] as f32 / 256.0 - 32768.0)
+		.collect();
+	Ok(Heights {
+		size: texture.size.x,
+		samples,
+	})
+}
@@ -0,0 +1,480 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use glam::{DVec3, Vec2, Vec3, Vec4};
+use rend3::types::{
+	Handedness, MaterialHandle, MeshBuilder, MeshHandle, Object, ObjectMeshKind, TextureHandle,
+};
+use rend3::util::typedefs::FastHashMap;
+use rend3::Renderer;
+use rend3_routine::pbr::{AlbedoComponent, PbrMaterial};
+
+use super::{DirectorySource, GeoFrame, TileData, TileId, TileSource};
+use crate::assets::AssetError;
+use crate::scene::{ObjectId, Scene, SceneTransform, Transform};
+use crate::work_queue::WorkQueue;
+
+/// quads along each edge of a tile mesh
+const GRID: u32 = 16;
+
+/// Area of the earth to stream, and how finely.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoRegion {
+	/// directory laid out as described on [`DirectorySource`]
+	pub root: String,
+	/// center of the region and origin of the scene, in degrees
+	pub lat: f64,
+	pub lon: f64,
+	/// zoom of the coarsest tiles, the region is the 3x3 of them around the
+	/// center
+	pub base_zoom: u8,
+	pub max_zoom: u8,
+	/// tiles are split while the camera is closer than their size times this
+	pub lod_factor: f64,
+	/// loaded tiles kept around while not shown
+	pub cache_size: usize,
+}
+
+impl Default for GeoRegion {
+	fn default() -> Self {
+		Self {
+			root: "tiles".into(),
+			lat: 46.8523,
+			lon: -121.7603,
+			base_zoom: 10,
+			max_zoom: 15,
+			lod_factor: 1.5,
+			cache_size: 256,
+		}
+	}
+}
+
+enum TileState {
+	Loading,
+	Failed,
+	Loaded(LoadedTile),
+}
+
+struct LoadedTile {
+	mesh: MeshHandle,
+	material: MaterialHandle,
+	_texture: Option<TextureHandle>,
+	/// world space position of the mesh origin
+	center: DVec3,
+	/// scene object while the tile is shown
+	object: Option<ObjectId>,
+	last_used: u64,
+}
+
+/// Streams terrain and imagery tiles of a [`GeoRegion`] into the scene.
+///
+/// Tiles are picked from a quadtree by distance to the camera, loaded on a
+/// background thread and turned into scene objects placed in a [`GeoFrame`]
+/// centered on the region. While a tile loads its closest loaded ancestor is
+/// shown instead.
+#[derive(Default)]
+pub struct GeoStreamer {
+	pub open: bool,
+	pub region: GeoRegion,
+
+	frame: Option<GeoFrame>,
+	requests: Option<Sender<TileId>>,
+	results: Option<Receiver<(TileId, Result<TileData, AssetError>)>>,
+	/// thread fetching tiles for the current region
+	worker: Option<JoinHandle<()>>,
+	tiles: FastHashMap<TileId, TileState>,
+	frame_index: u64,
+}
+
+impl GeoStreamer {
+	/// Starts streaming `self.region` from a [`DirectorySource`], dropping
+	/// the tiles of the previous region.
+	pub fn load(&mut self, scene: &mut Scene, handedness: Handedness) {
+		let source = DirectorySource::new(&self.region.root);
+		self.load_from(Arc::new(source), scene, handedness);
+	}
+
+	/// Starts streaming `self.region` from any source.
+	pub fn load_from(
+		&mut self,
+		source: Arc<dyn TileSource>,
+		scene: &mut Scene,
+		handedness: Handedness,
+	) {
+		self.unload(scene);
+
+		let (request_sender, request_receiver) = mpsc::channel::<TileId>();
+		let (result_sender, result_receiver) = mpsc::channel();
+		// exits once the streamer drops its end of either channel
+		let spawned = thread::Builder::new()
+			.name("geo tiles".into())
+			.spawn(move || {
+				for tile in request_receiver {
+					if result_sender.send((tile, source.fetch(tile))).is_err() {
+						break;
+					}
+				}
+			});
+		let worker = match spawned {
+			Ok(worker) => worker,
+			Err(e) => {
+				log::error!("failed to start the geo tile thread: {}", e);
+				return;
+			}
+		};
+
+		self.frame = Some(GeoFrame::new(
+			self.region.lat,
+			self.region.lon,
+			0.0,
+			handedness,
+		));
+		self.requests = Some(request_sender);
+		self.results = Some(result_receiver);
+		// the previous region's thread exits on its own after its last fetch
+		self.worker = Some(worker);
+	}
+
+	/// Stops streaming and removes every tile from the scene.
+	pub fn unload(&mut self, scene: &mut Scene) {
+		for state in self.tiles.values() {
+			if let TileState::Loaded(LoadedTile {
+				object: Some(object),
+				..
+			}) = state
+			{
+				scene.remove_object(*object);
+			}
+		}
+		self.tiles.clear();
+		self.frame = None;
+		self.requests = None;
+		self.results = None;
+	}
+
+	/// Unloads and waits for the tile thread to finish its last fetch.
+	pub fn shutdown(&mut self, scene: &mut Scene) {
+		self.unload(scene);
+		if let Some(worker) = self.worker.take() {
+			if worker.join().is_err() {
+				log::error!("the geo tile thread panicked");
+			}
+		}
+	}
+
+	pub fn is_loaded(&self) -> bool {
+		self.frame.is_some()
+	}
+
+	/// Picks the tiles for a camera at `camera_position` in world space,
+	/// requests the missing ones and swaps shown tiles in the scene.
+	pub fn update(
+		&mut self,
+		renderer: &Renderer,
+		scene: &mut Scene,
+		camera_position: Vec3,
+		work: &mut WorkQueue,
+	) {
+		let frame = match self.frame {
+			Some(frame) => frame,
+			None => return,
+		};
+		self.frame_index += 1;
+		self.receive(renderer, &frame, work);
+
+		let camera = camera_position.as_dvec3();
+		let root = TileId::containing(self.region.lat, self.region.lon, self.region.base_zoom);
+		let mut wanted = Vec::new();
+		for dy in -1..=1 {
+			for dx in -1..=1 {
+				if let Some(tile) = root.offset(dx, dy) {
+					self.select(&frame, tile, camera, &mut wanted);
+				}
+			}
+		}
+
+		// show each wanted tile, or its closest loaded ancestor until it loads
+		let mut shown = Vec::new();
+		for &tile in &wanted {
+			match self.tiles.get(&tile) {
+				Some(TileState::Loaded(_)) => shown.push(tile),
+				Some(_) => {}
+				None => {
+					if let Some(requests) = &self.requests {
+						if requests.send(tile).is_ok() {
+							self.tiles.insert(tile, TileState::Loading);
+						}
+					}
+				}
+			}
+			if !matches!(self.tiles.get(&tile), Some(TileState::Loaded(_))) {
+				let ancestor = std::iter::successors(tile.parent(), TileId::parent)
+					.take_while(|parent| parent.z >= self.region.base_zoom)
+					.find(|parent| matches!(self.tiles.get(parent), Some(TileState::Loaded(_))));
+				shown.extend(ancestor);
+			}
+		}
+		shown.sort_unstable();
+		shown.dedup();
+		// an ancestor covers its descendants, don't draw both
+		let covered = |tile: &TileId| {
+			std::iter::successors(tile.parent(), TileId::parent)
+				.any(|parent| shown.binary_search(&parent).is_ok())
+		};
+		let shown: Vec<TileId> = shown.iter().copied().filter(|t| !covered(t)).collect();
+
+		let frame_index = self.frame_index;
+		for (id, state) in self.tiles.iter_mut() {
+			let tile = match state {
+				TileState::Loaded(tile) => tile,
+				_ => continue,
+			};
+			let show = shown.contains(id);
+			match (show, tile.object) {
+				(true, None) => tile.object = Some(add_tile_object(renderer, scene, *id, tile)),
+				(false, Some(object)) => {
+					scene.remove_object(object);
+					tile.object = None;
+				}
+				_ => {}
+			}
+			if show || wanted.contains(id) {
+				tile.last_used = frame_index;
+			}
+		}
+
+		self.evict();
+	}
+
+	/// Turns finished loads into meshes and materials, as many as fit in the
+	/// frame's budget. The rest wait for the next frame.
+	fn receive(&mut self, renderer: &Renderer, frame: &GeoFrame, work: &mut WorkQueue) {
+		let results = match &self.results {
+			Some(results) => results,
+			None => return,
+		};
+		let mut first = true;
+		while first || work.has_time() {
+			let (tile, result) = match results.try_recv() {
+				Ok(received) => received,
+				Err(_) => break,
+			};
+			first = false;
+			let state = match result {
+				Ok(data) => {
+					TileState::Loaded(work.measure(|| build_tile(renderer, frame, tile, data)))
+				}
+				Err(e) => {
+					log::warn!(
+						"failed to load tile {}/{}/{}: {}",
+						tile.z,
+						tile.x,
+						tile.y,
+						e
+					);
+					TileState::Failed
+				}
+			};
+			self.tiles.insert(tile, state);
+		}
+	}
+
+	/// Adds `tile` to `wanted`, or its children if the camera is close enough
+	/// for more detail.
+	fn select(&self, frame: &GeoFrame, tile: TileId, camera: DVec3, wanted: &mut Vec<TileId>) {
+		let (north_lat, west_lon) = tile.lat_lon_at(0.0, 0.0);
+		let (south_lat, east_lon) = tile.lat_lon_at(1.0, 1.0);
+		let corner_a = frame.to_local(north_lat, west_lon, 0.0);
+		let corner_b = frame.to_local(south_lat, east_lon, 0.0);
+		let center = (corner_a + corner_b) * 0.5;
+		let size = corner_a.distance(corner_b);
+
+		// ignore height so mountains don't keep flat tiles from refining
+		let distance = (camera - center) * DVec3::new(1.0, 0.0, 1.0);
+		let split = distance.length() < size * self.region.lod_factor;
+		if split && tile.z < self.region.max_zoom {
+			for child in tile.children() {
+				self.select(frame, child, camera, wanted);
+			}
+		} else {
+			wanted.push(tile);
+		}
+	}
+
+	/// Drops the least recently used hidden tiles beyond the cache size.
+	/// Failed tiles are kept so they aren't requested again until the region
+	/// is reloaded.
+	fn evict(&mut self) {
+		let mut hidden: Vec<(u64, TileId)> = self
+			.tiles
+			.iter()
+			.filter_map(|(id, state)| match state {
+				TileState::Loaded(tile) if tile.object.is_none() => Some((tile.last_used, *id)),
+				_ => None,
+			})
+			.collect();
+		if hidden.len() <= self.region.cache_size {
+			return;
+		}
+		hidden.sort_unstable();
+		let excess = hidden.len() - self.region.cache_size;
+		for (_, id) in &hidden[..excess] {
+			self.tiles.remove(id);
+		}
+	}
+
+	/// Counts of tiles loading, loaded and shown.
+	fn counts(&self) -> (usize, usize, usize) {
+		let mut counts = (0, 0, 0);
+		for state in self.tiles.values() {
+			match state {
+				TileState::Loading => counts.0 += 1,
+				TileState::Loaded(tile) => {
+					counts.1 += 1;
+					if tile.object.is_some() {
+						counts.2 += 1;
+					}
+				}
+				TileState::Failed => {}
+			}
+		}
+		counts
+	}
+
+	pub fn window(&mut self, ctx: &egui::CtxRef, scene: &mut Scene, handedness: Handedness) {
+		let mut open = self.open;
+		egui::Window::new("geo tiles")
+			.open(&mut open)
+			.resizable(false)
+			.show(ctx, |ui| {
+				let region = &mut self.region;
+				ui.horizontal(|ui| {
+					ui.label("directory");
+					ui.text_edit_singleline(&mut region.root);
+				});
+				ui.horizontal(|ui| {
+					ui.add(
+						egui::DragValue::new(&mut region.lat)
+							.speed(0.001)
+							.clamp_range(-85.0..=85.0)
+							.prefix("lat "),
+					);
+					ui.add(
+						egui::DragValue::new(&mut region.lon)
+							.speed(0.001)
+							.clamp_range(-180.0..=180.0)
+							.prefix("lon "),
+					);
+				});
+				ui.add(egui::Slider::new(&mut region.base_zoom, 0..=20).text("base zoom"));
+				let min_zoom = region.base_zoom;
+				ui.add(egui::Slider::new(&mut region.max_zoom, min_zoom..=22).text("max zoom"));
+				region.max_zoom = region.max_zoom.max(min_zoom);
+				ui.add(egui::Slider::new(&mut region.lod_factor, 0.5..=4.0).text("detail"));
+
+				ui.horizontal(|ui| {
+					if ui.button("load region").clicked() {
+						self.load(scene, handedness);
+					}
+					if ui
+						.add_enabled(self.is_loaded(), egui::Button::new("unload"))
+						.clicked()
+					{
+						self.unload(scene);
+					}
+				});
+				if self.is_loaded() {
+					let (loading, loaded, shown) = self.counts();
+					ui.label(format!(
+						"{} shown, {} loaded, {} loading",
+						shown, loaded, loading
+					));
+				}
+			});
+		self.open = open;
+	}
+}
+
+/// Builds the mesh and material of a loaded tile, centered on the middle of
+/// the tile so the vertices stay small.
+fn build_tile(renderer: &Renderer, frame: &GeoFrame, tile: TileId, data: TileData) -> LoadedTile {
+	let height_at = |u: f64, v: f64| data.heights.as_ref().map_or(0.0, |h| h.sample(u, v));
+	let (center_lat, center_lon) = tile.lat_lon_at(0.5, 0.5);
+	let center = frame.to_local(center_lat, center_lon, height_at(0.5, 0.5));
+
+	let mut positions = Vec::new();
+	let mut uvs = Vec::new();
+	for j in 0..=GRID {
+		for i in 0..=GRID {
+			let (u, v) = (i as f64 / GRID as f64, j as f64 / GRID as f64);
+			let (lat, lon) = tile.lat_lon_at(u, v);
+			let position = frame.to_local(lat, lon, height_at(u, v)) - center;
+			positions.push(position.as_vec3());
+			uvs.push(Vec2::new(u as f32, v as f32));
+		}
+	}
+
+	// clockwise from above. The frame mirrors north and south for a right
+	// handed renderer, which already flips the winding to match it.
+	let row = GRID + 1;
+	let mut indices = Vec::new();
+	for j in 0..GRID {
+		for i in 0..GRID {
+			let north_west = j * row + i;
+			let north_east = north_west + 1;
+			let south_west = north_west + row;
+			let south_east = south_west + 1;
+			indices.extend_from_slice(&[north_west, north_east, south_east]);
+			indices.extend_from_slice(&[north_west, south_east, south_west]);
+		}
+	}
+	let mesh = MeshBuilder::new(positions, renderer.handedness)
+		.with_vertex_uv0(uvs)
+		.with_indices(indices)
+		.build()
+		.expect("tile mesh is valid by construction");
+
+	// tiles come and go, so they skip the color space audit. Imagery is
+	// always decoded as sRGB.
+	let texture = data.imagery.map(|imagery| renderer.add_texture_2d(imagery));
+	let albedo = match &texture {
+		Some(texture) => AlbedoComponent::Texture(texture.clone()),
+		None => AlbedoComponent::Value(Vec4::new(0.5, 0.5, 0.5, 1.0)),
+	};
+	let material = renderer.add_material(PbrMaterial {
+		albedo,
+		roughness_factor: Some(1.0),
+		..PbrMaterial::default()
+	});
+
+	LoadedTile {
+		mesh: renderer.add_mesh(mesh),
+		material,
+		_texture: texture,
+		center,
+		object: None,
+		last_used: 0,
+	}
+}
+
+fn add_tile_object(
+	renderer: &Renderer,
+	scene: &mut Scene,
+	id: TileId,
+	tile: &LoadedTile,
+) -> ObjectId {
+	let object = scene.add_object(
+		renderer,
+		&format!("tile {}/{}/{}", id.z, id.x, id.y),
+		Object {
+			mesh_kind: ObjectMeshKind::Static(tile.mesh.clone()),
+			material: tile.material.clone(),
+			transform: glam::Mat4::IDENTITY,
+		},
+	);
+	// placed afterwards so the position keeps its precision with f64
+	// transforms
+	scene.teleport(object, Transform::from_f64_translation(tile.center));
+	object
+}
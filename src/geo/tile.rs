@@ -0,0 +1,132 @@
+use std::f64::consts::PI;
+
+use glam::DVec3;
+use rend3::types::Handedness;
+
+/// A web mercator (slippy map) tile: zoom level `z`, column `x` counting east
+/// and row `y` counting south.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TileId {
+	pub z: u8,
+	pub x: u32,
+	pub y: u32,
+}
+
+impl TileId {
+	/// Tile at zoom `z` containing a point given in degrees.
+	pub fn containing(lat: f64, lon: f64, z: u8) -> Self {
+		let n = (1u64 << z) as f64;
+		let lat = lat.clamp(-85.0511, 85.0511).to_radians();
+		let x = (lon + 180.0) / 360.0 * n;
+		let y = (1.0 - (lat.tan() + 1.0 / lat.cos()).ln() / PI) / 2.0 * n;
+		let max = (1u64 << z) as f64 - 1.0;
+		Self {
+			z,
+			x: x.clamp(0.0, max) as u32,
+			y: y.clamp(0.0, max) as u32,
+		}
+	}
+
+	/// Latitude and longitude in degrees of a point in the tile, `u` going
+	/// west to east and `v` north to south from 0.0 to 1.0.
+	pub fn lat_lon_at(&self, u: f64, v: f64) -> (f64, f64) {
+		let n = (1u64 << self.z) as f64;
+		let lon = (self.x as f64 + u) / n * 360.0 - 180.0;
+		let lat = (PI * (1.0 - 2.0 * (self.y as f64 + v) / n)).sinh().atan();
+		(lat.to_degrees(), lon)
+	}
+
+	/// Tile `dx` columns east and `dy` rows south, if it exists. Columns wrap
+	/// around the antimeridian.
+	pub fn offset(&self, dx: i64, dy: i64) -> Option<Self> {
+		let n = 1i64 << self.z;
+		let y = self.y as i64 + dy;
+		if !(0..n).contains(&y) {
+			return None;
+		}
+		Some(Self {
+			z: self.z,
+			x: (self.x as i64 + dx).rem_euclid(n) as u32,
+			y: y as u32,
+		})
+	}
+
+	pub fn children(&self) -> [TileId; 4] {
+		[(0, 0), (1, 0), (0, 1), (1, 1)].map(|(dx, dy)| TileId {
+			z: self.z + 1,
+			x: self.x * 2 + dx,
+			y: self.y * 2 + dy,
+		})
+	}
+
+	pub fn parent(&self) -> Option<TileId> {
+		(self.z > 0).then(|| TileId {
+			z: self.z - 1,
+			x: self.x / 2,
+			y: self.y / 2,
+		})
+	}
+}
+
+/// WGS84 ellipsoid
+const SEMI_MAJOR_AXIS: f64 = 6_378_137.0;
+const ECCENTRICITY_SQUARED: f64 = 6.694_379_990_14e-3;
+
+/// Local tangent plane around a point on the earth that the scene is built
+/// in, so geographic coordinates land in meters around the world origin.
+///
+/// The scene's x axis points east, y up and z north (south for a right
+/// handed renderer, keeping the axes in the renderer's handedness).
+#[derive(Debug, Clone, Copy)]
+pub struct GeoFrame {
+	origin: DVec3,
+	east: DVec3,
+	north: DVec3,
+	up: DVec3,
+	handedness: Handedness,
+}
+
+impl GeoFrame {
+	/// Frame centered on a point given in degrees and meters above the
+	/// ellipsoid.
+	pub fn new(lat: f64, lon: f64, height: f64, handedness: Handedness) -> Self {
+		let (sin_lat, cos_lat) = lat.to_radians().sin_cos();
+		let (sin_lon, cos_lon) = lon.to_radians().sin_cos();
+		Self {
+			origin: ecef(lat, lon, height),
+			east: DVec3::new(-sin_lon, cos_lon, 0.0),
+			north: DVec3::new(-sin_lat * cos_lon, -sin_lat * sin_lon, cos_lat),
+			up: DVec3::new(cos_lat * cos_lon, cos_lat * sin_lon, sin_lat),
+			handedness,
+		}
+	}
+
+	/// Scene position of a point given in degrees and meters above the
+	/// ellipsoid. The earth curves away from the frame's origin, so far
+	/// points sit lower than their height.
+	pub fn to_local(&self, lat: f64, lon: f64, height: f64) -> DVec3 {
+		let offset = ecef(lat, lon, height) - self.origin;
+		let north = offset.dot(self.north);
+		DVec3::new(
+			offset.dot(self.east),
+			offset.dot(self.up),
+			match self.handedness {
+				Handedness::Left => north,
+				Handedness::Right => -north,
+			},
+		)
+	}
+}
+
+/// Earth centered, earth fixed position of a point given in degrees and
+/// meters above the ellipsoid.
+fn ecef(lat: f64, lon: f64, height: f64) -> DVec3 {
+	let (sin_lat, cos_lat) = lat.to_radians().sin_cos();
+	let (sin_lon, cos_lon) = lon.to_radians().sin_cos();
+	let radius = SEMI_MAJOR_AXIS / (1.0 - ECCENTRICITY_SQUARED * sin_lat * sin_lat).sqrt();
+	DVec3::new(
+		(radius + height) * cos_lat * cos_lon,
+		(radius + height) * cos_lat * sin_lon,
+		(radius * (1.0 - ECCENTRICITY_SQUARED) + height) * sin_lat,
+	)
+}
@@ -0,0 +1,65 @@
+use glam::{Mat4, UVec2, Vec2, Vec3};
+
+use crate::camera;
+use crate::debug::ColorSpaceAudit;
+use crate::scene::Scene;
+
+/// Shows a tooltip at the pointer describing the scene object under it: its
+/// name, distance from the camera, triangle count and material. Nothing is
+/// shown while the pointer is over the ui or a mouse button is held, e.g. to
+/// look around.
+pub fn object_tooltip(
+	ctx: &egui::CtxRef,
+	scene: &Scene,
+	materials: &ColorSpaceAudit,
+	view_proj: Mat4,
+	camera_position: Vec3,
+	viewport: (UVec2, UVec2),
+) {
+	let pointer = &ctx.input().pointer;
+	if pointer.any_down() || ctx.is_pointer_over_area() {
+		return;
+	}
+	let pos = match pointer.hover_pos() {
+		Some(pos) => pos,
+		None => return,
+	};
+	let (offset, size) = viewport;
+	let pixel = Vec2::new(pos.x, pos.y) * ctx.pixels_per_point();
+	let local = pixel - offset.as_vec2();
+	if local.cmplt(Vec2::ZERO).any() || local.cmpge(size.as_vec2()).any() {
+		return;
+	}
+
+	let direction = camera::screen_to_direction(view_proj, viewport, pixel);
+	let (id, distance) = match scene.raycast(camera_position, direction, f32::INFINITY, None) {
+		Some(hit) => hit,
+		None => return,
+	};
+	let object = match scene.get(id) {
+		Some(object) => object,
+		None => return,
+	};
+
+	egui::show_tooltip_at_pointer(ctx, egui::Id::new("object tooltip"), |ui| {
+		ui.strong(&object.name);
+		egui::Grid::new("object_tooltip_grid")
+			.num_columns(2)
+			.show(ui, |ui| {
+				ui.label("distance");
+				ui.label(format!("{:.2}", distance));
+				ui.end_row();
+				if let Some(geometry) = &object.geometry {
+					ui.label("triangles");
+					ui.label(format!("{}", geometry.indices.len() / 3));
+					ui.end_row();
+				}
+				ui.label("material");
+				match materials.material_name(object.material()) {
+					Some(name) => ui.label(name),
+					None => ui.label(format!("#{}", object.material().get_raw().idx)),
+				};
+				ui.end_row();
+			});
+	});
+}
@@ -0,0 +1,1002 @@
+//! Keyboard, mouse and touch input collected from window events. Apps feed
+//! every event to [`InputManager::handle_event`] and call
+//! [`InputManager::push_state`] once per update, after reading the input, so
+//! presses and releases can be told apart from keys held across updates.
+
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::time::{Duration, Instant};
+
+use glam::DVec2;
+use rend3::util::typedefs::FastHashMap;
+use serde_json::{json, Value};
+use winit::event::{
+	DeviceEvent, ElementState, Event, ModifiersState, MouseButton, MouseScrollDelta, ScanCode,
+	Touch, TouchPhase, VirtualKeyCode, WindowEvent,
+};
+
+use crate::bindings::{ActionBindings, Binding, KeyBinding};
+
+/// A discrete input, kept in the order it happened so a key pressed and let
+/// go within one update isn't missed like it is by the held state.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputEvent {
+	Key {
+		scancode: ScanCode,
+		keycode: Option<VirtualKeyCode>,
+		pressed: bool,
+		/// the os repeating a held key
+		repeat: bool,
+	},
+	MouseButton {
+		button: MouseButton,
+		pressed: bool,
+	},
+	/// a character typed, after the keyboard layout and dead keys
+	Text(char),
+	/// a short touch that barely moved, where it lifted in physical pixels
+	Tap(DVec2),
+	/// the window lost keyboard focus and everything held was let go, so
+	/// whatever it was driving, like a drag, should stop
+	FocusLost,
+}
+
+impl InputEvent {
+	fn to_json(self) -> Value {
+		match self {
+			InputEvent::Key {
+				scancode,
+				keycode,
+				pressed,
+				repeat,
+			} => json!({
+				"key": scancode,
+				"keycode": keycode.and_then(|keycode| serde_json::to_value(keycode).ok()),
+				"pressed": pressed,
+				"repeat": repeat,
+			}),
+			InputEvent::MouseButton { button, pressed } => json!({
+				"mouse": serde_json::to_value(button).ok(),
+				"pressed": pressed,
+			}),
+			InputEvent::Text(c) => json!({ "text": c.to_string() }),
+			InputEvent::Tap(position) => json!({ "tap": [position.x, position.y] }),
+			InputEvent::FocusLost => json!({ "focus_lost": true }),
+		}
+	}
+
+	fn from_json(value: &Value) -> Option<Self> {
+		if value.get("focus_lost").is_some() {
+			return Some(InputEvent::FocusLost);
+		}
+		if let Some(text) = value.get("text") {
+			return text.as_str()?.chars().next().map(InputEvent::Text);
+		}
+		if let Some(tap) = value.get("tap") {
+			return match tap.as_array()?.as_slice() {
+				[x, y] => Some(InputEvent::Tap(DVec2::new(x.as_f64()?, y.as_f64()?))),
+				_ => None,
+			};
+		}
+		let pressed = value.get("pressed")?.as_bool()?;
+		if let Some(button) = value.get("mouse") {
+			return Some(InputEvent::MouseButton {
+				button: serde_json::from_value(button.clone()).ok()?,
+				pressed,
+			});
+		}
+		Some(InputEvent::Key {
+			scancode: value.get("key")?.as_u64()? as ScanCode,
+			keycode: match value.get("keycode")? {
+				Value::Null => None,
+				keycode => Some(serde_json::from_value(keycode.clone()).ok()?),
+			},
+			pressed,
+			repeat: value.get("repeat")?.as_bool()?,
+		})
+	}
+}
+
+/// farthest a touch may move and still be a tap, in physical pixels
+const TAP_SLOP: f64 = 10.0;
+/// longest a touch may last and still be a tap
+const TAP_TIME: Duration = Duration::from_millis(300);
+/// longest between two clicks of a double click, the windows default
+const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(500);
+/// farthest the cursor may move between two clicks of a double click, in
+/// physical pixels
+const DOUBLE_CLICK_SLOP: f64 = 4.0;
+
+#[derive(Clone, Copy)]
+struct TouchPoint {
+	position: DVec2,
+	start: DVec2,
+	started: Instant,
+}
+
+#[derive(Default, Clone)]
+struct InputState {
+	keyboard_scancode_state: FastHashMap<ScanCode, bool>,
+	keyboard_keycode_state: FastHashMap<VirtualKeyCode, bool>,
+	mouse_button_state: FastHashMap<MouseButton, bool>,
+	/// raw mouse motion since the last [`InputManager::push_state`]
+	mouse_delta: DVec2,
+	/// cursor motion in physical pixels since the last
+	/// [`InputManager::push_state`]
+	cursor_delta: DVec2,
+	/// `None` while the cursor is outside the window
+	cursor_position: Option<DVec2>,
+	/// mouse wheel lines since the last [`InputManager::push_state`]
+	scroll_delta: f32,
+	/// pixels scrolled on touchpads and precise wheels since the last
+	/// [`InputManager::push_state`], on both axes
+	scroll_pixel_delta: DVec2,
+	/// ctrl, shift, alt and logo, whichever side is held
+	modifiers: ModifiersState,
+	/// presses, releases and typing since the last
+	/// [`InputManager::push_state`], oldest first
+	events: Vec<InputEvent>,
+	/// fingers on the screen by touch id
+	touches: FastHashMap<u64, TouchPoint>,
+	/// one finger drags in physical pixels since the last
+	/// [`InputManager::push_state`]
+	touch_drag: DVec2,
+	/// how far two fingers spread apart in physical pixels since the last
+	/// [`InputManager::push_state`], negative pinching together
+	pinch_delta: f64,
+	/// when held keys went down. Timing isn't recorded, keys held in a
+	/// recording have no press time.
+	key_pressed_at: FastHashMap<VirtualKeyCode, Instant>,
+	/// how long keys let go of since the last
+	/// [`InputManager::push_state`] were held
+	key_released_after: FastHashMap<VirtualKeyCode, Duration>,
+	/// each button's last click that could start a double click, and where
+	/// the cursor was
+	last_clicks: FastHashMap<MouseButton, (Instant, Option<DVec2>)>,
+	/// buttons double clicked since the last
+	/// [`InputManager::push_state`]
+	double_clicks: Vec<MouseButton>,
+}
+
+impl InputState {
+	/// Only what's held is kept, a key missing from a snapshot is released.
+	fn to_json(&self) -> Value {
+		fn held<K: Copy>(map: &FastHashMap<K, bool>) -> impl Iterator<Item = K> + '_ {
+			map.iter().filter(|(_, down)| **down).map(|(key, _)| *key)
+		}
+		let keycodes: Vec<Value> = held(&self.keyboard_keycode_state)
+			.filter_map(|keycode| serde_json::to_value(keycode).ok())
+			.collect();
+		let buttons: Vec<Value> = held(&self.mouse_button_state)
+			.filter_map(|button| serde_json::to_value(button).ok())
+			.collect();
+		json!({
+			"scancodes": held(&self.keyboard_scancode_state).collect::<Vec<_>>(),
+			"keycodes": keycodes,
+			"mouse_buttons": buttons,
+			"mouse_delta": [self.mouse_delta.x, self.mouse_delta.y],
+			"cursor_delta": [self.cursor_delta.x, self.cursor_delta.y],
+			"cursor_position": self.cursor_position.map(|position| [position.x, position.y]),
+			"scroll_delta": self.scroll_delta,
+			"scroll_pixel_delta": [self.scroll_pixel_delta.x, self.scroll_pixel_delta.y],
+			"modifiers": self.modifiers.bits(),
+			"events": self.events.iter().map(|event| event.to_json()).collect::<Vec<_>>(),
+			"touch_drag": [self.touch_drag.x, self.touch_drag.y],
+			"pinch_delta": self.pinch_delta,
+		})
+	}
+
+	fn from_json(value: &Value) -> Option<Self> {
+		let vector = |value: &Value| -> Option<DVec2> {
+			match value.as_array()?.as_slice() {
+				[x, y] => Some(DVec2::new(x.as_f64()?, y.as_f64()?)),
+				_ => None,
+			}
+		};
+		let list = |key: &str| value.get(key)?.as_array();
+		let scancodes = list("scancodes")?
+			.iter()
+			.map(|scancode| Some((scancode.as_u64()? as ScanCode, true)))
+			.collect::<Option<_>>()?;
+		let keycodes = list("keycodes")?
+			.iter()
+			.map(|keycode| Some((serde_json::from_value(keycode.clone()).ok()?, true)))
+			.collect::<Option<_>>()?;
+		let buttons = list("mouse_buttons")?
+			.iter()
+			.map(|button| Some((serde_json::from_value(button.clone()).ok()?, true)))
+			.collect::<Option<_>>()?;
+		let cursor_position = match value.get("cursor_position")? {
+			Value::Null => None,
+			position => Some(vector(position)?),
+		};
+		Some(Self {
+			keyboard_scancode_state: scancodes,
+			keyboard_keycode_state: keycodes,
+			mouse_button_state: buttons,
+			mouse_delta: vector(value.get("mouse_delta")?)?,
+			cursor_delta: vector(value.get("cursor_delta")?)?,
+			cursor_position,
+			scroll_delta: value.get("scroll_delta")?.as_f64()? as f32,
+			scroll_pixel_delta: vector(value.get("scroll_pixel_delta")?)?,
+			// recordings from before modifiers were tracked have none
+			modifiers: ModifiersState::from_bits_truncate(
+				value.get("modifiers").and_then(Value::as_u64).unwrap_or(0) as u32,
+			),
+			events: match value.get("events") {
+				Some(events) => events
+					.as_array()?
+					.iter()
+					.map(InputEvent::from_json)
+					.collect::<Option<_>>()?,
+				None => Vec::new(),
+			},
+			// fingers held aren't recorded, only what they did
+			touches: FastHashMap::default(),
+			key_pressed_at: FastHashMap::default(),
+			key_released_after: FastHashMap::default(),
+			last_clicks: FastHashMap::default(),
+			double_clicks: Vec::new(),
+			touch_drag: value.get("touch_drag").and_then(vector).unwrap_or_default(),
+			pinch_delta: value
+				.get("pinch_delta")
+				.and_then(Value::as_f64)
+				.unwrap_or(0.0),
+		})
+	}
+}
+
+/// Keyboard, mouse and touch state collected from window events, with what
+/// was pressed as of the previous update to tell presses and releases apart.
+#[derive(Default, Clone)]
+pub struct InputManager {
+	input_state: InputState,
+	prev_input_state: InputState,
+	bindings: ActionBindings,
+	/// the window starts out focused
+	unfocused: bool,
+	/// whether focus was lost since the last update
+	focus_lost: bool,
+	/// whether this update's typing was taken by
+	/// [`InputManager::take_text_input`]
+	text_taken: bool,
+	/// whether the ui wants the keyboard or the pointer, see
+	/// [`InputManager::set_ui_capture`]
+	ui_keyboard: bool,
+	ui_pointer: bool,
+}
+
+impl InputManager {
+	/// Ends the update, so what's held now counts as held before for the next
+	/// one. Motion, scrolling and events start over from nothing.
+	pub fn push_state(&mut self) {
+		self.prev_input_state = self.input_state.clone();
+		self.input_state.mouse_delta = DVec2::ZERO;
+		self.input_state.cursor_delta = DVec2::ZERO;
+		self.input_state.scroll_delta = 0.0;
+		self.input_state.scroll_pixel_delta = DVec2::ZERO;
+		self.input_state.events.clear();
+		self.input_state.touch_drag = DVec2::ZERO;
+		self.input_state.pinch_delta = 0.0;
+		self.input_state.key_released_after.clear();
+		self.input_state.double_clicks.clear();
+		self.text_taken = false;
+		self.focus_lost = false;
+	}
+
+	/// Counts a click towards a double click. The click finishing one doesn't
+	/// start another, so triple clicks don't double click twice.
+	fn click(&mut self, button: MouseButton) {
+		let state = &mut self.input_state;
+		let now = Instant::now();
+		let position = state.cursor_position;
+		let double = match state.last_clicks.get(&button) {
+			Some((at, at_position)) => {
+				let still = match (position, at_position) {
+					(Some(position), Some(at_position)) => {
+						position.distance(*at_position) <= DOUBLE_CLICK_SLOP
+					}
+					(position, at_position) => position.is_none() && at_position.is_none(),
+				};
+				now - *at <= DOUBLE_CLICK_TIME && still
+			}
+			None => false,
+		};
+		match double {
+			true => {
+				state.last_clicks.remove(&button);
+				state.double_clicks.push(button);
+			}
+			false => {
+				state.last_clicks.insert(button, (now, position));
+			}
+		}
+	}
+
+	/// Hides presses from the game while the ui wants the keyboard or pointer,
+	/// so typing w into a text field doesn't also fly forward. Keys and
+	/// buttons already held stay held until they're let go, so a look drag
+	/// that passes over a window keeps going. Call before handling events.
+	pub fn set_ui_capture(&mut self, keyboard: bool, pointer: bool) {
+		self.ui_keyboard = keyboard;
+		self.ui_pointer = pointer;
+	}
+
+	/// One finger dragging looks around, two pinching or spreading zoom, and a
+	/// quick touch that stays put is a tap.
+	fn handle_touch(&mut self, touch: &Touch) {
+		let state = &mut self.input_state;
+		let position = DVec2::new(touch.location.x, touch.location.y);
+		match touch.phase {
+			// fingers that start on the ui are the ui's
+			TouchPhase::Started if self.ui_pointer => {}
+			TouchPhase::Started => {
+				state.touches.insert(
+					touch.id,
+					TouchPoint {
+						position,
+						start: position,
+						started: Instant::now(),
+					},
+				);
+			}
+			TouchPhase::Moved => {
+				let previous = match state.touches.get(&touch.id) {
+					Some(point) => point.position,
+					None => return,
+				};
+				let others: Vec<DVec2> = state
+					.touches
+					.iter()
+					.filter(|(id, _)| **id != touch.id)
+					.map(|(_, point)| point.position)
+					.collect();
+				match others.as_slice() {
+					[] => state.touch_drag += position - previous,
+					[other] => {
+						state.pinch_delta += position.distance(*other) - previous.distance(*other)
+					}
+					// more fingers are left to the os
+					_ => {}
+				}
+				if let Some(point) = state.touches.get_mut(&touch.id) {
+					point.position = position;
+				}
+			}
+			TouchPhase::Ended | TouchPhase::Cancelled => {
+				let point = match state.touches.remove(&touch.id) {
+					Some(point) => point,
+					None => return,
+				};
+				let tap = touch.phase == TouchPhase::Ended
+					&& state.touches.is_empty()
+					&& point.start.distance(position) <= TAP_SLOP
+					&& point.started.elapsed() <= TAP_TIME;
+				if tap {
+					state.events.push(InputEvent::Tap(position));
+				}
+			}
+		}
+	}
+
+	/// Collects input from an event, others are ignored.
+	pub fn handle_event<T>(&mut self, event: &Event<T>) {
+		match event {
+			Event::WindowEvent {
+				event: WindowEvent::KeyboardInput { input, .. },
+				..
+			} => {
+				let pressed = input.state == ElementState::Pressed;
+				// releases always get through, so nothing stays held
+				if pressed && self.ui_keyboard {
+					return;
+				}
+				let held = self
+					.input_state
+					.keyboard_scancode_state
+					.insert(input.scancode, pressed);
+				self.input_state.events.push(InputEvent::Key {
+					scancode: input.scancode,
+					keycode: input.virtual_keycode,
+					pressed,
+					repeat: pressed && held == Some(true),
+				});
+				if let Some(keycode) = input.virtual_keycode {
+					self.input_state
+						.keyboard_keycode_state
+						.insert(keycode, pressed);
+					let now = Instant::now();
+					match pressed {
+						true => {
+							self.input_state
+								.key_pressed_at
+								.entry(keycode)
+								.or_insert(now);
+						}
+						false => {
+							if let Some(at) = self.input_state.key_pressed_at.remove(&keycode) {
+								self.input_state
+									.key_released_after
+									.insert(keycode, now - at);
+							}
+						}
+					}
+				}
+			}
+			Event::WindowEvent {
+				event: WindowEvent::ModifiersChanged(modifiers),
+				..
+			} => {
+				self.input_state.modifiers = *modifiers;
+			}
+			Event::WindowEvent {
+				event: WindowEvent::MouseInput { state, button, .. },
+				..
+			} => {
+				let pressed = *state == ElementState::Pressed;
+				if pressed && self.ui_pointer {
+					return;
+				}
+				self.input_state.mouse_button_state.insert(*button, pressed);
+				self.input_state.events.push(InputEvent::MouseButton {
+					button: *button,
+					pressed,
+				});
+				if pressed {
+					self.click(*button);
+				}
+			}
+			Event::WindowEvent {
+				event: WindowEvent::ReceivedCharacter(c),
+				..
+			} if !c.is_control() && !self.ui_keyboard => {
+				self.input_state.events.push(InputEvent::Text(*c));
+			}
+			Event::WindowEvent {
+				event: WindowEvent::Touch(touch),
+				..
+			} => self.handle_touch(touch),
+			Event::WindowEvent {
+				event: WindowEvent::MouseWheel { delta, .. },
+				..
+			} if !self.ui_pointer => {
+				self.input_state.scroll_delta += match delta {
+					MouseScrollDelta::LineDelta(_, y) => *y,
+					// roughly one line per 40 pixels of touchpad scrolling
+					MouseScrollDelta::PixelDelta(position) => {
+						self.input_state.scroll_pixel_delta += DVec2::new(position.x, position.y);
+						position.y as f32 / 40.0
+					}
+				};
+			}
+			Event::WindowEvent {
+				event: WindowEvent::CursorMoved { position, .. },
+				..
+			} => {
+				let position = DVec2::new(position.x, position.y);
+				if let Some(previous) = self.input_state.cursor_position {
+					self.input_state.cursor_delta += position - previous;
+				}
+				self.input_state.cursor_position = Some(position);
+			}
+			Event::WindowEvent {
+				event: WindowEvent::CursorLeft { .. },
+				..
+			} => {
+				self.input_state.cursor_position = None;
+			}
+			// keys and buttons released while another window has focus are
+			// never reported, so anything held when focus changes is let go
+			Event::WindowEvent {
+				event: WindowEvent::Focused(focused),
+				..
+			} => {
+				// releases while unfocused go to the newly focused window, so
+				// nothing held now would ever be let go
+				self.unfocused = !focused;
+				if !focused {
+					self.focus_lost = true;
+					self.input_state.events.push(InputEvent::FocusLost);
+				}
+				self.input_state.keyboard_scancode_state.clear();
+				self.input_state.keyboard_keycode_state.clear();
+				self.input_state.mouse_button_state.clear();
+				self.input_state.modifiers = ModifiersState::empty();
+				self.input_state.touches.clear();
+				self.input_state.key_pressed_at.clear();
+			}
+			// device events keep arriving while the window isn't focused
+			Event::DeviceEvent { .. } if self.unfocused => {}
+			Event::DeviceEvent {
+				event: DeviceEvent::MouseMotion {
+					delta: (delta_x, delta_y),
+					..
+				},
+				..
+			} => {
+				// several motion events can arrive between updates
+				self.input_state.mouse_delta += DVec2::new(*delta_x, *delta_y);
+			}
+			_ => {}
+		}
+	}
+
+	#[inline]
+	fn is_pressed<K, H: BuildHasher>(map: &HashMap<K, bool, H>, code: &K) -> bool
+	where
+		K: Eq + core::hash::Hash,
+	{
+		map.get(code) == Some(&true)
+	}
+
+	#[inline]
+	fn is_just_pressed<K, H: BuildHasher>(
+		prev_map: &HashMap<K, bool, H>,
+		map: &HashMap<K, bool, H>,
+		code: &K,
+	) -> bool
+	where
+		K: Eq + core::hash::Hash,
+	{
+		Self::is_pressed(map, code) && !Self::is_pressed(prev_map, code)
+	}
+
+	#[inline]
+	fn is_just_released<K, H: BuildHasher>(
+		prev_map: &HashMap<K, bool, H>,
+		map: &HashMap<K, bool, H>,
+		code: &K,
+	) -> bool
+	where
+		K: Eq + core::hash::Hash,
+	{
+		Self::is_just_pressed(map, prev_map, code)
+	}
+
+	/// Whether the key is held, by the character it types in the layout.
+	#[inline]
+	pub fn is_keycode_down(&self, code: &VirtualKeyCode) -> bool {
+		Self::is_pressed(&self.input_state.keyboard_keycode_state, code)
+	}
+
+	/// Whether the key went down since the last update.
+	#[inline]
+	pub fn is_keycode_just_pressed(&self, code: &VirtualKeyCode) -> bool {
+		Self::is_just_pressed(
+			&self.prev_input_state.keyboard_keycode_state,
+			&self.input_state.keyboard_keycode_state,
+			code,
+		)
+	}
+
+	/// Whether the key was let go since the last update.
+	#[inline]
+	pub fn is_keycode_just_released(&self, code: &VirtualKeyCode) -> bool {
+		Self::is_just_released(
+			&self.prev_input_state.keyboard_keycode_state,
+			&self.input_state.keyboard_keycode_state,
+			code,
+		)
+	}
+
+	/// Whether the button is held.
+	#[inline]
+	pub fn is_mouse_button_down(&self, button: &MouseButton) -> bool {
+		Self::is_pressed(&self.input_state.mouse_button_state, button)
+	}
+
+	/// Whether the button went down since the last update.
+	#[inline]
+	pub fn is_mouse_button_just_pressed(&self, button: &MouseButton) -> bool {
+		Self::is_just_pressed(
+			&self.prev_input_state.mouse_button_state,
+			&self.input_state.mouse_button_state,
+			button,
+		)
+	}
+
+	/// Whether the button was let go since the last update.
+	#[inline]
+	pub fn is_mouse_button_just_released(&self, button: &MouseButton) -> bool {
+		Self::is_just_released(
+			&self.prev_input_state.mouse_button_state,
+			&self.input_state.mouse_button_state,
+			button,
+		)
+	}
+
+	/// Where the cursor is in the window, in physical pixels. `None` while it's
+	/// outside the window.
+	#[inline]
+	pub fn cursor_position(&self) -> Option<DVec2> {
+		self.input_state.cursor_position
+	}
+
+	/// Presses, releases and typing since the last update in the order they
+	/// happened, for input that has to see every press rather than what's
+	/// held.
+	#[inline]
+	pub fn events(&self) -> &[InputEvent] {
+		&self.input_state.events
+	}
+
+	/// Characters typed since the last update, for consoles and text fields
+	/// drawn outside egui. Only the first caller in an update gets them, so
+	/// typing goes to one field. Editing keys like backspace and enter aren't
+	/// included, read those from [`Self::events`]. Text composed with an IME
+	/// arrives as characters once it's committed, winit doesn't report the
+	/// composition while it's in progress yet. Typing while the ui wants the
+	/// keyboard goes to the ui instead, see [`Self::set_ui_capture`].
+	pub fn take_text_input(&mut self) -> String {
+		if std::mem::replace(&mut self.text_taken, true) {
+			return String::new();
+		}
+		self.input_state
+			.events
+			.iter()
+			.filter_map(|event| match event {
+				InputEvent::Text(c) => Some(*c),
+				_ => None,
+			})
+			.collect()
+	}
+
+	/// Ctrl, shift, alt and logo held as of this update.
+	#[inline]
+	pub fn modifiers(&self) -> ModifiersState {
+		self.input_state.modifiers
+	}
+
+	/// Whether every key of the chord is held with no other modifiers, so
+	/// ctrl+shift+s doesn't also count as ctrl+s. Modifier keys match either
+	/// side, `LControl` also matches the right control key.
+	fn is_chord_down(state: &InputState, chord: &[VirtualKeyCode]) -> bool {
+		let mut modifiers = ModifiersState::empty();
+		for key in chord {
+			match modifier_of(*key) {
+				Some(modifier) => modifiers |= modifier,
+				None if !Self::is_pressed(&state.keyboard_keycode_state, key) => return false,
+				None => {}
+			}
+		}
+		!chord.is_empty() && state.modifiers == modifiers
+	}
+
+	/// Whether the chord was completed since the last update, like
+	/// `&[VirtualKeyCode::LControl, VirtualKeyCode::S]` for ctrl+s. Holding
+	/// the chord doesn't repeat it.
+	pub fn is_chord_just_pressed(&self, chord: &[VirtualKeyCode]) -> bool {
+		Self::is_chord_down(&self.input_state, chord)
+			&& !Self::is_chord_down(&self.prev_input_state, chord)
+	}
+
+	/// Checks the key by scancode if the binding has one, and by keycode
+	/// otherwise.
+	#[inline]
+	pub fn is_key_down(&self, key: &KeyBinding) -> bool {
+		match key.scancode {
+			Some(scancode) => {
+				Self::is_pressed(&self.input_state.keyboard_scancode_state, &scancode)
+			}
+			None => self.is_keycode_down(&key.keycode),
+		}
+	}
+
+	/// Whether the key or button of the binding is held.
+	pub fn is_binding_down(&self, binding: &Binding) -> bool {
+		Self::is_binding_held(&self.input_state, binding)
+	}
+
+	fn is_binding_held(state: &InputState, binding: &Binding) -> bool {
+		match binding {
+			Binding::Key(KeyBinding {
+				scancode: Some(scancode),
+				..
+			}) => Self::is_pressed(&state.keyboard_scancode_state, scancode),
+			Binding::Key(key) => Self::is_pressed(&state.keyboard_keycode_state, &key.keycode),
+			Binding::Mouse(button) => Self::is_pressed(&state.mouse_button_state, button),
+		}
+	}
+
+	/// Starts with these bindings instead of the defaults.
+	pub fn with_bindings(bindings: ActionBindings) -> Self {
+		Self {
+			bindings,
+			..Self::default()
+		}
+	}
+
+	/// Bindings [`Self::is_action_down`] looks actions up in.
+	pub fn bindings_mut(&mut self) -> &mut ActionBindings {
+		&mut self.bindings
+	}
+
+	/// Whether any input bound to the action is held.
+	pub fn is_action_down(&self, action: &str) -> bool {
+		self.bindings
+			.get(action)
+			.iter()
+			.any(|binding| self.is_binding_down(binding))
+	}
+
+	/// Whether an input bound to the action went down since the last update
+	/// while none of them were held, for actions that toggle something.
+	pub fn is_action_just_pressed(&self, action: &str) -> bool {
+		let bindings = self.bindings.get(action);
+		let held = |state| {
+			bindings
+				.iter()
+				.any(|binding| Self::is_binding_held(state, binding))
+		};
+		held(&self.input_state) && !held(&self.prev_input_state)
+	}
+
+	/// Whether the window has keyboard focus.
+	pub fn is_focused(&self) -> bool {
+		!self.unfocused
+	}
+
+	/// Whether the window lost focus since the last update, also queued in
+	/// [`Self::events`] as [`InputEvent::FocusLost`].
+	pub fn focus_lost(&self) -> bool {
+		self.focus_lost
+	}
+
+	/// Call after moving the cursor, so the move isn't taken for motion.
+	pub fn warp_cursor(&mut self, position: DVec2) {
+		self.input_state.cursor_position = Some(position);
+	}
+
+	/// Mouse movement to look around with, either raw mouse counts or the
+	/// cursor's movement in points. Points rather than pixels so looking
+	/// turns as far for the same cursor movement on monitors of any dpi.
+	pub fn look_delta(&self, raw: bool, scale_factor: f64) -> DVec2 {
+		match raw {
+			true => self.input_state.mouse_delta,
+			false => self.input_state.cursor_delta / scale_factor,
+		}
+	}
+
+	/// One finger dragging across a touch screen, in points like
+	/// [`Self::look_delta`].
+	pub fn touch_drag(&self, scale_factor: f64) -> DVec2 {
+		self.input_state.touch_drag / scale_factor
+	}
+
+	/// How far two fingers spread apart on a touch screen, in points. Negative
+	/// when they pinch together.
+	pub fn pinch_delta(&self, scale_factor: f64) -> f64 {
+		self.input_state.pinch_delta / scale_factor
+	}
+
+	/// Whether the button was clicked twice in quick succession, without the
+	/// cursor moving, since the last update.
+	pub fn is_double_clicked(&self, button: MouseButton) -> bool {
+		self.input_state.double_clicks.contains(&button)
+	}
+
+	/// How long the key has been held, for charging up while it's held. On
+	/// the update it's let go this is how long it was held in all, so a
+	/// charge can be released with its full strength. Zero otherwise.
+	pub fn held_duration(&self, keycode: VirtualKeyCode) -> Duration {
+		if let Some(at) = self.input_state.key_pressed_at.get(&keycode) {
+			return at.elapsed();
+		}
+		self.input_state
+			.key_released_after
+			.get(&keycode)
+			.copied()
+			.unwrap_or(Duration::ZERO)
+	}
+
+	/// Fingers on the touch screen.
+	pub fn touch_count(&self) -> usize {
+		self.input_state.touches.len()
+	}
+
+	/// Vertical scrolling in mouse wheel lines, with touchpad scrolling
+	/// converted to lines.
+	#[inline]
+	pub fn scroll_delta(&self) -> f32 {
+		self.input_state.scroll_delta
+	}
+
+	/// Touchpad scrolling in pixels as reported, for smooth scrolling that
+	/// shouldn't be rounded to lines. Mouse wheels only show up in
+	/// [`InputManager::scroll_delta`].
+	#[inline]
+	pub fn scroll_pixel_delta(&self) -> DVec2 {
+		self.input_state.scroll_pixel_delta
+	}
+
+	/// This update's input, for [`crate::debug::InputRecorder`].
+	pub fn snapshot(&self) -> Value {
+		self.input_state.to_json()
+	}
+
+	/// Replaces this update's input with a recorded snapshot, keeping the
+	/// previous update's so presses still register. Returns `false` if the
+	/// snapshot is invalid.
+	pub fn restore(&mut self, snapshot: &Value) -> bool {
+		match InputState::from_json(snapshot) {
+			Some(state) => {
+				self.input_state = state;
+				true
+			}
+			None => false,
+		}
+	}
+}
+
+/// The modifier a modifier key sets, `None` for other keys.
+fn modifier_of(key: VirtualKeyCode) -> Option<ModifiersState> {
+	match key {
+		VirtualKeyCode::LControl | VirtualKeyCode::RControl => Some(ModifiersState::CTRL),
+		VirtualKeyCode::LShift | VirtualKeyCode::RShift => Some(ModifiersState::SHIFT),
+		VirtualKeyCode::LAlt | VirtualKeyCode::RAlt => Some(ModifiersState::ALT),
+		VirtualKeyCode::LWin | VirtualKeyCode::RWin => Some(ModifiersState::LOGO),
+		_ => None,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use winit::event::{DeviceId, KeyboardInput};
+	use winit::window::WindowId;
+
+	use super::*;
+	use crate::bindings::actions;
+
+	fn window_event(event: WindowEvent<'static>) -> Event<'static, ()> {
+		Event::WindowEvent {
+			// SAFETY: the ids are only compared, never passed to the platform
+			window_id: unsafe { WindowId::dummy() },
+			event,
+		}
+	}
+
+	fn key(keycode: VirtualKeyCode, pressed: bool) -> Event<'static, ()> {
+		#[allow(deprecated)]
+		window_event(WindowEvent::KeyboardInput {
+			device_id: unsafe { DeviceId::dummy() },
+			input: KeyboardInput {
+				scancode: keycode as ScanCode,
+				state: match pressed {
+					true => ElementState::Pressed,
+					false => ElementState::Released,
+				},
+				virtual_keycode: Some(keycode),
+				modifiers: ModifiersState::empty(),
+			},
+			is_synthetic: false,
+		})
+	}
+
+	fn mouse(button: MouseButton, pressed: bool) -> Event<'static, ()> {
+		#[allow(deprecated)]
+		window_event(WindowEvent::MouseInput {
+			device_id: unsafe { DeviceId::dummy() },
+			state: match pressed {
+				true => ElementState::Pressed,
+				false => ElementState::Released,
+			},
+			button,
+			modifiers: ModifiersState::empty(),
+		})
+	}
+
+	#[test]
+	fn key_press_and_release() {
+		let mut input = InputManager::default();
+		let w = VirtualKeyCode::W;
+		assert!(!input.is_keycode_down(&w));
+
+		input.handle_event(&key(w, true));
+		assert!(input.is_keycode_down(&w));
+		assert!(input.is_keycode_just_pressed(&w));
+		assert!(!input.is_keycode_just_released(&w));
+
+		input.push_state();
+		assert!(input.is_keycode_down(&w));
+		assert!(!input.is_keycode_just_pressed(&w));
+
+		// the os repeating the held key isn't another press
+		input.handle_event(&key(w, true));
+		assert!(!input.is_keycode_just_pressed(&w));
+
+		input.push_state();
+		input.handle_event(&key(w, false));
+		assert!(!input.is_keycode_down(&w));
+		assert!(input.is_keycode_just_released(&w));
+		assert!(!input.is_keycode_just_pressed(&w));
+
+		input.push_state();
+		assert!(!input.is_keycode_just_released(&w));
+	}
+
+	#[test]
+	fn key_tapped_within_one_update() {
+		let mut input = InputManager::default();
+		let space = VirtualKeyCode::Space;
+		input.handle_event(&key(space, true));
+		input.handle_event(&key(space, false));
+		assert!(!input.is_keycode_down(&space));
+		assert!(!input.is_keycode_just_pressed(&space));
+		let presses = input
+			.events()
+			.iter()
+			.filter(|event| matches!(event, InputEvent::Key { pressed: true, .. }))
+			.count();
+		assert_eq!(presses, 1);
+	}
+
+	#[test]
+	fn action_press() {
+		let mut input = InputManager::default();
+		input.handle_event(&key(VirtualKeyCode::O, true));
+		assert!(input.is_action_just_pressed(actions::TOGGLE_ORBIT));
+		assert!(!input.is_action_just_pressed(actions::MOVE_FORWARD));
+
+		input.push_state();
+		assert!(input.is_action_down(actions::TOGGLE_ORBIT));
+		assert!(!input.is_action_just_pressed(actions::TOGGLE_ORBIT));
+	}
+
+	#[test]
+	fn mouse_button_press_and_release() {
+		let mut input = InputManager::default();
+		let left = MouseButton::Left;
+		input.handle_event(&mouse(left, true));
+		assert!(input.is_mouse_button_down(&left));
+		assert!(input.is_mouse_button_just_pressed(&left));
+		assert!(!input.is_mouse_button_down(&MouseButton::Right));
+
+		input.push_state();
+		assert!(input.is_mouse_button_down(&left));
+		assert!(!input.is_mouse_button_just_pressed(&left));
+
+		input.handle_event(&mouse(left, false));
+		assert!(input.is_mouse_button_just_released(&left));
+
+		input.push_state();
+		assert!(!input.is_mouse_button_just_released(&left));
+	}
+
+	#[test]
+	fn ui_capture_hides_presses_but_not_releases() {
+		let mut input = InputManager::default();
+		let w = VirtualKeyCode::W;
+		input.handle_event(&key(w, true));
+		input.push_state();
+
+		input.set_ui_capture(true, true);
+		input.handle_event(&key(VirtualKeyCode::S, true));
+		input.handle_event(&mouse(MouseButton::Left, true));
+		assert!(!input.is_keycode_just_pressed(&VirtualKeyCode::S));
+		assert!(!input.is_mouse_button_down(&MouseButton::Left));
+
+		input.handle_event(&key(w, false));
+		assert!(input.is_keycode_just_released(&w));
+	}
+
+	#[test]
+	fn focus_loss_releases_held_keys() {
+		let mut input = InputManager::default();
+		let w = VirtualKeyCode::W;
+		input.handle_event(&key(w, true));
+		input.push_state();
+
+		input.handle_event(&window_event(WindowEvent::Focused(false)));
+		assert!(input.focus_lost());
+		assert_eq!(input.events(), [InputEvent::FocusLost]);
+		// and replayed from recordings
+		let event = InputEvent::FocusLost;
+		assert_eq!(InputEvent::from_json(&event.to_json()), Some(event));
+		assert!(!input.is_focused());
+		assert!(input.is_keycode_just_released(&w));
+
+		input.push_state();
+		assert!(!input.focus_lost());
+		assert!(input.events().is_empty());
+	}
+}
@@ -1,84 +1,132 @@
-use std::collections::HashMap;
-use std::hash::BuildHasher;
+pub mod anchors;
+pub mod animation;
+pub mod assets;
+pub mod bindings;
+pub mod blend_space;
+pub mod camera;
+pub mod camera_path;
+pub mod camera_registry;
+pub mod clipboard;
+pub mod config;
+pub mod cursor;
+pub mod debug;
+pub mod editor;
+pub mod frame_pacer;
+pub mod fullscreen;
+pub mod geo;
+pub mod hover;
+pub mod input;
+pub mod manifest;
+pub mod material_override;
+pub mod measure;
+pub mod mesh_cache;
+pub mod metadata;
+pub mod modifiers;
+pub mod plot;
+pub mod post;
+pub mod preset_benchmark;
+pub mod render_passes;
+pub mod scene;
+pub mod scene_diff;
+pub mod scene_file;
+pub mod scene_loader;
+pub mod screenshot;
+pub mod section;
+pub mod sequencer;
+pub mod settings;
+pub mod skeleton;
+pub mod split_screen;
+pub mod staging;
+pub mod surface;
+pub mod tasks;
+pub mod teleport;
+pub mod texture_streaming;
+pub mod ui_platform;
+pub mod vr;
+pub mod warmup;
+pub mod window_geometry;
+pub mod window_icon;
+pub mod work_queue;
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
 use std::sync::Arc;
 use std::time::Duration;
 use std::time::Instant;
 
-use glam::{DVec2, EulerRot, Mat3A, Mat4, UVec2, Vec3, Vec3A, Vec4};
-use winit::event::DeviceEvent;
+use glam::{DVec2, Mat4, UVec2, Vec2, Vec3, Vec3A, Vec4};
+use winit::dpi::PhysicalPosition;
+use winit::event::VirtualKeyCode;
 use winit::event::WindowEvent as WinitWindowEvent;
-use winit::event::{ElementState, ScanCode, VirtualKeyCode};
 use winit::event_loop::ControlFlow;
 use winit::window::{Window, WindowBuilder};
 
-use egui_winit_platform::{Platform, PlatformDescriptor};
-use rend3::graph::RenderGraph;
+use rend3::graph::{ReadyData, RenderGraph, RenderTargetDescriptor, RenderTargetHandle};
 use rend3::types::{
-	Camera, CameraProjection, DirectionalLight, DirectionalLightHandle, Handedness, Mesh,
-	MeshBuilder, Object, ObjectHandle, ObjectMeshKind, SampleCount, Surface, TextureFormat,
+	Camera, DirectionalLight, DirectionalLightChange, DirectionalLightHandle, Handedness, Object,
+	ObjectMeshKind, PresentMode, SampleCount, Surface, TextureFormat, TextureUsages,
 };
 use rend3::util::output::OutputFrame;
-use rend3::util::typedefs::FastHashMap;
-use rend3::Renderer;
+use rend3::{InstanceAdapterDevice, Renderer};
 use rend3_egui::EguiRenderRoutine;
 use rend3_framework::{DefaultRoutines, Event};
-use rend3_routine::base::BaseRenderGraph;
-use rend3_routine::pbr::{AlbedoComponent, PbrMaterial};
+use rend3_routine::base::{BaseRenderGraph, BaseRenderGraphIntermediateState};
+use rend3_routine::pbr::{AlbedoComponent, PbrMaterial, PbrRoutine};
+use rend3_routine::tonemapping::TonemappingRoutine;
+use wgpu::TextureView;
 
 use histogram::Histogram;
 
-fn vertex(pos: [f32; 3]) -> Vec3 {
-	return Vec3::from(pos);
-}
-
-fn create_mesh() -> Mesh {
-	let verts = [
-		// far side (0.0, 0.0, 1.0)
-		vertex([-1.0, -1.0, 1.0]),
-		vertex([1.0, -1.0, 1.0]),
-		vertex([1.0, 1.0, 1.0]),
-		vertex([-1.0, 1.0, 1.0]),
-		// near side (0.0, 0.0, -1.0)
-		vertex([-1.0, 1.0, -1.0]),
-		vertex([1.0, 1.0, -1.0]),
-		vertex([1.0, -1.0, -1.0]),
-		vertex([-1.0, -1.0, -1.0]),
-		// right side (1.0, 0.0, 0.0)
-		vertex([1.0, -1.0, -1.0]),
-		vertex([1.0, 1.0, -1.0]),
-		vertex([1.0, 1.0, 1.0]),
-		vertex([1.0, -1.0, 1.0]),
-		// left side (-1.0, 0.0, 0.0)
-		vertex([-1.0, -1.0, 1.0]),
-		vertex([-1.0, 1.0, 1.0]),
-		vertex([-1.0, 1.0, -1.0]),
-		vertex([-1.0, -1.0, -1.0]),
-		// top (0.0, 1.0, 0.0)
-		vertex([1.0, 1.0, -1.0]),
-		vertex([-1.0, 1.0, -1.0]),
-		vertex([-1.0, 1.0, 1.0]),
-		vertex([1.0, 1.0, 1.0]),
-		// bottom (0.0, -1.0, 0.0)
-		vertex([1.0, -1.0, 1.0]),
-		vertex([-1.0, -1.0, 1.0]),
-		vertex([-1.0, -1.0, -1.0]),
-		vertex([1.0, -1.0, -1.0]),
-	];
-
-	let indices: &[u32] = &[
-		0, 1, 2, 2, 3, 0, // far
-		4, 5, 6, 6, 7, 4, // near
-		8, 9, 10, 10, 11, 8, // right
-		12, 13, 14, 14, 15, 12, // left
-		16, 17, 18, 18, 19, 16, // top
-		20, 21, 22, 22, 23, 20, // bottom
-	];
-
-	MeshBuilder::new(verts.to_vec(), Handedness::Left)
-		.with_indices(indices.to_vec())
-		.build()
-		.unwrap()
-}
+use anchors::{AnchorTarget, ScreenAnchor, ScreenAnchors};
+use animation::{Animations, Clip, ClipPlayer, Rig};
+use bindings::{actions, ActionBindings, BindingsWindow, BINDINGS_PATH};
+use blend_space::{BlendAxes, BlendSpace};
+use camera::{AxisView, CameraMode, CameraPose, ClipPlanes, FlyCamera, FollowCamera, OrbitCamera};
+use camera_path::CameraPath;
+use camera_registry::CameraRegistry;
+use clipboard::SystemClipboard;
+use config::CONFIG_PATH;
+use cursor::{Cursor, CursorKind};
+use debug::{
+	ColorSpaceAudit, DebugDraw, GpuCapabilities, GpuInfoWindow, InputRecorder, InspectorSources,
+	LatencyTester, PassBudgets, PixelPicker, ProfilePreference, TargetInspector, VizCamera,
+	VizLight, VolumeViz,
+};
+use editor::{Editor, EditorCommand, MenuAction, PaletteChoice};
+use frame_pacer::FramePacer;
+use fullscreen::FullscreenMode;
+use geo::GeoStreamer;
+use input::{InputEvent, InputManager};
+use manifest::ManifestWindow;
+use material_override::{MaterialOverride, MaterialOverrideRoutine};
+use measure::MeasureTool;
+use plot::{FieldPlot, PointPlot};
+use post::{ColorFilterRoutine, MotionBlurRoutine};
+use preset_benchmark::PresetBenchmark;
+use render_passes::RenderPasses;
+use scene::{FixedTimestep, ObjectId, Position, Scene, ScenePosition, SceneTransform, Transform};
+use scene_diff::SceneDiffWindow;
+use scene_loader::SceneLoader;
+use screenshot::{ScreenshotMetadata, Screenshots};
+use section::{SectionCapRoutine, SectionPlane};
+use sequencer::{EventCommand, EventMarker, Sequencer};
+use settings::{CameraSettings, ColorBlindMode, Settings};
+use split_screen::{SplitScreen, Viewport};
+use staging::StagingPool;
+use tasks::TaskManager;
+use teleport::{ConsoleCommand, TeleportWindow};
+use texture_streaming::TextureStreamer;
+use ui_platform::UiPlatform;
+use vr::StereoRig;
+#[cfg(feature = "vr")]
+use vr::{XrDevice, XrTracking};
+use warmup::{Warmup, WarmupStep};
+use window_geometry::WindowGeometry;
+use window_icon::{IconOverlay, WindowIcon};
+use work_queue::WorkQueue;
 
 #[derive(Default)]
 struct OpalAppRenderStats {
@@ -91,16 +139,94 @@ struct OpalAppRenderStats {
 
 struct OpalAppRenderState {
 	// scene handles
-	object: ObjectHandle,
+	scene: Scene,
+	timestep: FixedTimestep,
+	object: ObjectId,
 	directional_light: DirectionalLightHandle,
-
-	camera_pos: Vec3A,
-	camera_pitch: f32,
-	camera_yaw: f32,
+	/// what `directional_light` was created with
+	light: DirectionalLight,
+
+	camera: FlyCamera,
+	/// named poses the main camera switches between
+	cameras: CameraRegistry,
+	/// keyframed fly-through of the main camera
+	camera_path: CameraPath,
+	/// walks by the blend space played on it
+	blended_walker: ObjectId,
+	/// clips posing the animated objects
+	animations: Animations,
+	/// orbits the camera input goes to instead of flying it, while set
+	orbit: Option<OrbitCamera>,
+	/// chases an object with the main camera, over its input, while set
+	follow: Option<FollowCamera>,
+	camera_mode: CameraMode,
+	/// world units the orthographic view spans vertically while not orbiting,
+	/// orbiting spans what the perspective view does at the target
+	orthographic_height: f32,
+	/// when the fly speed was last scrolled, for the on-screen indicator
+	fly_speed_changed: Option<Instant>,
+	/// looking around with the cursor grabbed
+	mouse_look: bool,
+	cursor: Cursor,
+	split_screen: SplitScreen,
+	stereo: StereoRig,
+	#[cfg(feature = "vr")]
+	xr: Option<XrTracking>,
 
 	// egui
 	egui_routine: EguiRenderRoutine,
-	egui_platform: Platform,
+	egui_platform: UiPlatform,
+
+	material_overrides: MaterialOverrideRoutine,
+	section: SectionPlane,
+	section_caps: SectionCapRoutine,
+	measure: MeasureTool,
+	teleport: TeleportWindow,
+	geo: GeoStreamer,
+	point_plot: PointPlot,
+	field_plot: FieldPlot,
+	sequencer: Sequencer,
+	anchors: ScreenAnchors,
+	editor: Editor,
+	scene_diff: SceneDiffWindow,
+	render_passes: RenderPasses,
+	/// made the first time the depth prepass is turned off
+	pbr_without_prepass: Option<PbrRoutine>,
+	manifest: ManifestWindow,
+	scene_loader: SceneLoader,
+	/// the scene built at startup or the file the last one was built or
+	/// loaded from, for screenshots
+	scene_name: String,
+	tasks: TaskManager,
+	work_queue: WorkQueue,
+	texture_streamer: TextureStreamer,
+	warmup: Warmup,
+	staging: StagingPool,
+	frame_pacer: FramePacer,
+	window_geometry: WindowGeometry,
+	/// world space view projection the main viewport was last rendered with
+	main_view_proj: Mat4,
+
+	// post processing
+	surface_format: TextureFormat,
+	color_filter: ColorFilterRoutine,
+	motion_blur: MotionBlurRoutine,
+	settings: Settings,
+	preset_benchmark: PresetBenchmark,
+
+	// debug
+	color_audit: ColorSpaceAudit,
+	target_inspector: TargetInspector,
+	pixel_picker: PixelPicker,
+	debug_draw: DebugDraw,
+	volume_viz: VolumeViz,
+	latency_tester: LatencyTester,
+	input_recorder: InputRecorder,
+	gpu_info: GpuInfoWindow,
+	pass_budgets: PassBudgets,
+	clipboard: SystemClipboard,
+	show_clip_plane: bool,
+	screenshots: Screenshots,
 
 	// rendering
 	last_frame_time: Instant,
@@ -109,150 +235,651 @@ struct OpalAppRenderState {
 	frame_times: Histogram,
 	stats: OpalAppRenderStats,
 
-	input: OpalAppInputManager,
+	input: InputManager,
+	bindings_window: BindingsWindow,
 }
 
-#[derive(Default, Clone)]
-struct OpalAppInputState {
-	keyboard_scancode_state: FastHashMap<ScanCode, bool>,
-	keyboard_keycode_state: FastHashMap<VirtualKeyCode, bool>,
-	mouse_delta: DVec2,
+/// Keeps the cursor in the window while looking around with the mouse. It's
+/// hidden by [`Cursor`] meanwhile.
+fn grab_cursor(window: &Window, grab: bool) {
+	if let Err(e) = window.set_cursor_grab(grab) {
+		log::warn!("couldn't grab the cursor: {}", e);
+	}
 }
 
-#[derive(Default, Clone)]
-struct OpalAppInputManager {
-	input_state: OpalAppInputState,
-	prev_input_state: OpalAppInputState,
+/// Shows the fly speed at the bottom of the screen for a moment after it
+/// changed, fading out.
+fn fly_speed_indicator(ctx: &egui::CtxRef, speed: f32, changed: Instant) {
+	const SHOWN_FOR: f32 = 1.5;
+	let age = changed.elapsed().as_secs_f32();
+	if age > SHOWN_FOR {
+		return;
+	}
+	let alpha = (1.0 - age / SHOWN_FOR).min(0.5) * 2.0;
+
+	let painter = ctx.layer_painter(egui::LayerId::background());
+	let screen = ctx.input().screen_rect();
+	let background = painter.add(egui::Shape::Noop);
+	let rect = painter.text(
+		screen.center_bottom() - egui::vec2(0.0, 40.0),
+		egui::Align2::CENTER_BOTTOM,
+		format!("fly speed {:.2} u/s", speed),
+		egui::TextStyle::Heading,
+		egui::Color32::WHITE.linear_multiply(alpha),
+	);
+	painter.set(
+		background,
+		egui::Shape::rect_filled(
+			rect.expand(6.0),
+			4.0,
+			egui::Color32::from_black_alpha(180).linear_multiply(alpha),
+		),
+	);
 }
 
-impl OpalAppInputManager {
-	pub fn push_state(&mut self) {
-		self.prev_input_state = self.input_state.clone();
+/// Runs the command of a sequencer event.
+fn run_event(
+	render_state: &mut OpalAppRenderState,
+	renderer: &Renderer,
+	event: &EventMarker,
+	handedness: Handedness,
+) {
+	log::info!("sequence event \"{}\"", event.name);
+	let command = match EventCommand::parse(&event.command) {
+		Ok(Some(command)) => command,
+		Ok(None) => return,
+		Err(e) => {
+			log::warn!("event \"{}\": {}", event.name, e);
+			return;
+		}
+	};
+	match command {
+		EventCommand::Teleport(target) => {
+			let camera = &render_state.camera.pose;
+			match teleport::resolve(&target, &render_state.scene, camera, handedness) {
+				Ok(position) => move_camera(render_state, position),
+				Err(e) => log::warn!("event \"{}\": {}", event.name, e),
+			}
+		}
+		EventCommand::Spawn { template, position } => {
+			let scene = &mut render_state.scene;
+			let found = scene.iter().find(|(_, object)| object.name == template);
+			let (id, transform) = match found {
+				Some((id, object)) => (id, object.transform()),
+				None => {
+					log::warn!("event \"{}\": no object named {}", event.name, template);
+					return;
+				}
+			};
+			// keeps the template's rotation and scale
+			let transform = match position {
+				Some(position) => {
+					let mut transform = transform.to_mat4();
+					transform.w_axis = position.extend(1.0);
+					Transform::from_mat4(transform)
+				}
+				None => transform,
+			};
+			let name = scene.unique_name(&template);
+			scene.spawn(renderer, id, &name, transform);
+		}
+		EventCommand::PlaySound(name) => {
+			log::warn!("event \"{}\": no audio output to play {}", event.name, name)
+		}
+		EventCommand::Screenshot => render_state.screenshots.request(),
+		EventCommand::Log(message) => log::info!("{}", message),
+		EventCommand::Cursor(kind) => render_state.cursor.script = kind,
+		EventCommand::Shake(trauma) => render_state.camera.add_trauma(trauma),
 	}
+}
 
-	pub fn handle_event<T>(&mut self, event: &Event<T>) {
-		match event {
-			Event::WindowEvent {
-				event: WinitWindowEvent::KeyboardInput { input, .. },
-				..
-			} => {
-				self.input_state.keyboard_scancode_state.insert(
-					input.scancode,
-					match input.state {
-						ElementState::Pressed => true,
-						ElementState::Released => false,
-					},
-				);
-				if input.virtual_keycode.is_some() {
-					self.input_state.keyboard_keycode_state.insert(
-						input.virtual_keycode.unwrap(),
-						match input.state {
-							ElementState::Pressed => true,
-							ElementState::Released => false,
-						},
-					);
-				}
-			}
-			Event::DeviceEvent {
-				event: DeviceEvent::MouseMotion {
-					delta: (delta_x, delta_y),
-					..
-				},
-				..
-			} => {
-				self.input_state.mouse_delta = DVec2::new(*delta_x, *delta_y);
-			}
-			_ => {}
+/// Moves the main camera without easing or blurring from where it was.
+/// What a scene built or loaded from `path` is called, the file's name
+/// without its extension.
+fn scene_name(path: &str) -> String {
+	match Path::new(path).file_stem() {
+		Some(stem) => stem.to_string_lossy().into_owned(),
+		None => path.into(),
+	}
+}
+
+fn move_camera(render_state: &mut OpalAppRenderState, position: Position) {
+	render_state.camera.teleport(position);
+	render_state.motion_blur.reset_camera_history();
+	reorbit(render_state);
+}
+
+/// Cuts the main camera to `pose`, without easing or blurring from where it
+/// was.
+fn cut_to(render_state: &mut OpalAppRenderState, pose: CameraPose, handedness: Handedness) {
+	render_state.camera = FlyCamera::new(pose, handedness);
+	render_state.motion_blur.reset_camera_history();
+	reorbit(render_state);
+}
+
+/// Switches the camera input goes to between flying and orbiting the point
+/// in front of it.
+fn toggle_orbit(render_state: &mut OpalAppRenderState, handedness: Handedness) {
+	render_state.orbit = match render_state.orbit {
+		Some(_) => None,
+		None => {
+			let camera = match render_state.split_screen.routes_input_to_second() {
+				true => &mut render_state.split_screen.camera,
+				false => &mut render_state.camera,
+			};
+			camera.motion.stop();
+			Some(OrbitCamera::from_pose(
+				&camera.pose,
+				ORBIT_DISTANCE,
+				handedness,
+			))
 		}
+	};
+}
+
+/// Keeps orbiting from where the camera input goes to was moved, instead of
+/// pulling it back.
+fn reorbit(render_state: &mut OpalAppRenderState) {
+	if let Some(orbit) = &mut render_state.orbit {
+		let camera = match render_state.split_screen.routes_input_to_second() {
+			true => &render_state.split_screen.camera,
+			false => &render_state.camera,
+		};
+		orbit.look_from(&camera.pose);
 	}
+}
 
-	#[inline]
-	fn is_pressed<K, H: BuildHasher>(map: &HashMap<K, bool, H>, code: &K) -> bool
-	where
-		K: Eq + core::hash::Hash,
-	{
-		map.get(code).map_or(false, |v| *v)
-	}
-
-	#[inline]
-	fn is_just_pressed<K, H: BuildHasher>(
-		prev_map: &HashMap<K, bool, H>,
-		map: &HashMap<K, bool, H>,
-		code: &K,
-	) -> bool
-	where
-		K: Eq + core::hash::Hash,
-	{
-		Self::is_pressed(map, code) && !Self::is_pressed(prev_map, code)
-	}
-
-	#[inline]
-	fn is_just_released<K, H: BuildHasher>(
-		prev_map: &HashMap<K, bool, H>,
-		map: &HashMap<K, bool, H>,
-		code: &K,
-	) -> bool
-	where
-		K: Eq + core::hash::Hash,
-	{
-		Self::is_just_pressed(map, prev_map, code)
-	}
-
-	#[inline]
-	pub fn is_keycode_down(&mut self, code: &VirtualKeyCode) -> bool {
-		Self::is_pressed(&self.input_state.keyboard_keycode_state, code)
-	}
-
-	#[inline]
-	pub fn is_keycode_just_pressed(&mut self, code: &VirtualKeyCode) -> bool {
-		Self::is_just_pressed(
-			&self.prev_input_state.keyboard_keycode_state,
-			&self.input_state.keyboard_keycode_state,
-			code,
-		)
+/// Looks straight along a world axis in an orthographic view. Orbiting keeps
+/// the target, flying keeps the position.
+fn look_along(render_state: &mut OpalAppRenderState, view: AxisView, handedness: Handedness) {
+	render_state.camera_mode = CameraMode::Orthographic;
+	let (yaw, pitch) = view.yaw_pitch();
+	let camera = match render_state.split_screen.routes_input_to_second() {
+		true => &mut render_state.split_screen.camera,
+		false => &mut render_state.camera,
+	};
+	camera.motion.stop();
+	match &mut render_state.orbit {
+		Some(orbit) => {
+			orbit.look_along(yaw, pitch);
+			camera.pose = orbit.pose();
+		}
+		None => camera.pose = CameraPose::new(camera.pose.position, pitch, yaw, handedness),
 	}
+	render_state.motion_blur.reset_camera_history();
+}
 
-	#[inline]
-	pub fn is_keycode_just_released(&mut self, code: &VirtualKeyCode) -> bool {
-		Self::is_just_released(
-			&self.prev_input_state.keyboard_keycode_state,
-			&self.input_state.keyboard_keycode_state,
-			code,
+/// Starts the main camera chasing the selected object from where it is, or
+/// stops it, with the chase's settings while it's on.
+fn follow_ui(ui: &mut egui::Ui, render_state: &mut OpalAppRenderState, handedness: Handedness) {
+	let selected = match render_state.editor.selection.objects() {
+		[id] => Some(*id),
+		_ => None,
+	};
+	let mut following = render_state.follow.is_some();
+	let toggle = ui
+		.add_enabled(
+			following || selected.is_some(),
+			egui::Checkbox::new(&mut following, "follow selected"),
 		)
+		.on_disabled_hover_text("select one object to follow");
+	if toggle.changed() {
+		let target = selected.and_then(|id| Some((id, render_state.scene.get(id)?)));
+		render_state.follow = match (following, target) {
+			(true, Some((id, object))) => {
+				render_state.orbit = None;
+				let transform = object.transform().to_mat4();
+				Some(FollowCamera::new(
+					id,
+					transform,
+					&render_state.camera.pose,
+					handedness,
+				))
+			}
+			_ => None,
+		};
+	}
+	if let Some(follow) = &mut render_state.follow {
+		ui.horizontal(|ui| {
+			ui.add(
+				egui::Slider::new(&mut follow.lag, 0.0..=2.0)
+					.text("lag")
+					.suffix(" s"),
+			);
+			ui.checkbox(&mut follow.look_at, "look at");
+		});
+		ui.horizontal(|ui| {
+			ui.label("offset");
+			ui.add(
+				egui::DragValue::new(&mut follow.offset.x)
+					.speed(0.05)
+					.prefix("x "),
+			);
+			ui.add(
+				egui::DragValue::new(&mut follow.offset.y)
+					.speed(0.05)
+					.prefix("y "),
+			);
+			ui.add(
+				egui::DragValue::new(&mut follow.offset.z)
+					.speed(0.05)
+					.prefix("z "),
+			);
+		});
+	}
+}
+
+/// Moves the main camera in front of an object.
+fn focus_object(render_state: &mut OpalAppRenderState, id: ObjectId, handedness: Handedness) {
+	if let Some(object) = render_state.scene.get(id) {
+		let position = teleport::frame(object, &render_state.camera.pose, handedness);
+		move_camera(render_state, position);
+	}
+}
+
+/// Tears the app down in order rather than leaving it to process exit: saves
+/// the config, stops the worker threads, drops everything holding renderer
+/// handles and waits for the gpu to finish with them.
+fn shutdown(mut render_state: OpalAppRenderState, window: &Window, renderer: &Renderer) {
+	log::info!("shutting down");
+	render_state.window_geometry.track(window);
+	if let Err(e) = render_state.window_geometry.save(Path::new(CONFIG_PATH)) {
+		log::error!("failed to save {}: {}", CONFIG_PATH, e);
+	}
+
+	// workers first, so nothing adds to the scene while it's torn down
+	render_state.scene_loader.shutdown();
+	render_state.geo.shutdown(&mut render_state.scene);
+	drop(render_state);
+
+	// rend3 frees the dropped handles when it readies the next frame
+	let (cmd_bufs, _) = renderer.ready();
+	renderer.queue.submit(cmd_bufs);
+	renderer.device.poll(wgpu::Maintain::Wait);
+}
+
+/// Turns the features warmup steps through on or off, returning what they
+/// were before.
+fn set_frame_features(render_state: &mut OpalAppRenderState, step: WarmupStep) -> WarmupStep {
+	let previous = WarmupStep {
+		motion_blur: render_state.settings.graphics.motion_blur,
+		split_screen: render_state.split_screen.enabled,
+		section: render_state.section.enabled,
+	};
+	render_state.settings.graphics.motion_blur = step.motion_blur;
+	render_state.split_screen.enabled = step.split_screen;
+	render_state.section.enabled = step.section;
+	previous
+}
+
+/// Render target for tonemapped color before it reaches the surface.
+fn ldr_target(format: TextureFormat, resolution: UVec2) -> RenderTargetDescriptor {
+	RenderTargetDescriptor {
+		label: Some("ldr color".into()),
+		resolution,
+		samples: SampleCount::One,
+		format,
+		usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+	}
+}
+
+/// Passes drawn over the lit scene, before post processing.
+struct SceneOverlays<'node> {
+	material_overrides: &'node MaterialOverrideRoutine,
+	section_caps: &'node SectionCapRoutine,
+	debug_draw: &'node DebugDraw,
+}
+
+/// Same passes as `BaseRenderGraph::add_to_graph` up to tonemapping, split up
+/// so post processing can be inserted after it. `pbr_routine` has to be one
+/// from [`render_passes::pbr_without_prepass`] while the prepass is off.
+fn add_scene_passes<'node>(
+	graph: &mut RenderGraph<'node>,
+	base_rendergraph: &'node BaseRenderGraph,
+	ready: &ReadyData,
+	pbr_routine: &'node PbrRoutine,
+	overlays: SceneOverlays<'node>,
+	passes: &'node RenderPasses,
+	resolution: UVec2,
+) -> BaseRenderGraphIntermediateState {
+	let mut state = BaseRenderGraphIntermediateState::new(graph, ready, resolution, SAMPLE_COUNT);
+	// same as the base depth target but readable by debug passes
+	state.depth = graph.add_render_target(RenderTargetDescriptor {
+		label: Some("hdr depth".into()),
+		resolution,
+		samples: SAMPLE_COUNT,
+		format: TextureFormat::Depth32Float,
+		usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+	});
+	state.pre_skinning(graph);
+	state.pbr_pre_culling(graph);
+	state.create_frame_uniforms(graph, base_rendergraph, Vec4::ZERO);
+	state.skinning(graph, base_rendergraph);
+	let shadows = passes.is_enabled(render_passes::SHADOWS);
+	if shadows {
+		state.pbr_shadow_culling(graph, base_rendergraph, pbr_routine);
+	}
+	state.pbr_culling(graph, base_rendergraph, pbr_routine);
+	if shadows {
+		state.pbr_shadow_rendering(graph, pbr_routine);
+	}
+	let lit = passes.is_enabled(render_passes::PBR);
+	// the prepass also clears the color target, which nothing else does with
+	// lighting off
+	if passes.is_enabled(render_passes::DEPTH_PREPASS) || !lit {
+		state.pbr_prepass_rendering(graph, pbr_routine, SAMPLE_COUNT);
+	}
+	if passes.is_enabled(render_passes::OPAQUE_ONLY) {
+		// opaque objects come first, then cutout and blended ones
+		state.per_transparency.truncate(1);
+	}
+	if lit {
+		state.pbr_forward_rendering(graph, pbr_routine, SAMPLE_COUNT);
+	}
+	if passes.is_enabled(render_passes::MATERIAL_OVERRIDES) {
+		overlays.material_overrides.add_to_graph(graph, &state);
+	}
+	if passes.is_enabled(render_passes::SECTION_CAPS) {
+		overlays.section_caps.add_to_graph(graph, &state);
+	}
+	if passes.is_enabled(render_passes::DEBUG_DRAW) {
+		overlays.debug_draw.add_to_graph(graph, &state);
+	}
+	passes.add_to_graph(graph, &state);
+	state
+}
+
+/// Debug tools that read the main viewport's render targets.
+struct DebugPasses<'node> {
+	target_inspector: &'node TargetInspector,
+	pixel_picker: &'node PixelPicker,
+}
+
+/// Tonemaps the scene into `target`, running the enabled post passes on the way.
+#[allow(clippy::too_many_arguments)]
+fn add_post_passes<'node>(
+	graph: &mut RenderGraph<'node>,
+	mut state: BaseRenderGraphIntermediateState,
+	tonemapping_routine: &'node TonemappingRoutine,
+	motion_blur: &'node MotionBlurRoutine,
+	color_filter: &'node ColorFilterRoutine,
+	debug: Option<DebugPasses<'node>>,
+	settings: &Settings,
+	surface_format: TextureFormat,
+	resolution: UVec2,
+	target: RenderTargetHandle,
+) {
+	let hdr_color = state.resolve.unwrap_or(state.color);
+	let motion_vectors = settings
+		.graphics
+		.motion_blur
+		.then(|| motion_blur.add_to_graph(graph, &mut state, resolution));
+
+	if let Some(debug) = debug {
+		debug.target_inspector.add_to_graph(
+			graph,
+			&InspectorSources {
+				hdr_color,
+				depth: state.depth,
+				motion_vectors,
+			},
+		);
+		debug.pixel_picker.add_to_graph(
+			graph,
+			hdr_color,
+			state.resolve.unwrap_or(state.color),
+			state.depth,
+		);
+	}
+
+	if settings.accessibility.color_blind_mode != ColorBlindMode::Off {
+		// tonemap into an intermediate target for the filter to read from
+		let ldr = graph.add_render_target(ldr_target(surface_format, resolution));
+		state.tonemapping(graph, tonemapping_routine, ldr);
+		color_filter.add_to_graph(graph, ldr, target);
+	} else {
+		state.tonemapping(graph, tonemapping_routine, target);
+	}
+}
+
+/// A camera rendered into a texture of its own by [`render_view`].
+struct OffscreenView {
+	camera: Camera,
+	/// the viewport whose motion blur history the view continues, left
+	/// unblurred without one
+	motion_blur: Option<Viewport>,
+	/// moves world space debug drawing to the render space origin
+	to_origin: Mat4,
+	resolution: UVec2,
+	target: Arc<TextureView>,
+}
+
+/// Renders `view` with its own graph, for the left viewport of split screen
+/// and stereo and for the headset's eyes. `default_pbr` is replaced by the
+/// app's routine while the prepass is off, as for the main view.
+fn render_view(
+	render_state: &mut OpalAppRenderState,
+	renderer: &Arc<Renderer>,
+	base_rendergraph: &BaseRenderGraph,
+	default_pbr: &PbrRoutine,
+	tonemapping_routine: &TonemappingRoutine,
+	view: OffscreenView,
+	handedness: Handedness,
+) {
+	let resolution = view.resolution;
+	let aspect_ratio = resolution.x as f32 / resolution.y as f32;
+	let view_proj =
+		camera::projection_matrix(&view.camera, aspect_ratio, handedness) * view.camera.view;
+	let staging = &mut render_state.staging;
+	let mut settings = render_state.settings;
+	match view.motion_blur {
+		Some(viewport) => render_state.motion_blur.update(
+			renderer,
+			staging,
+			viewport,
+			view_proj,
+			&render_state.scene,
+			&render_state.settings.graphics,
+		),
+		None => settings.graphics.motion_blur = false,
 	}
+	render_state
+		.material_overrides
+		.update(renderer, staging, view_proj, &render_state.scene);
+	render_state.section_caps.update(
+		renderer,
+		staging,
+		view_proj,
+		&render_state.scene,
+		&render_state.section,
+	);
+	render_state
+		.debug_draw
+		.update(renderer, staging, view_proj * view.to_origin);
+	staging.submit(renderer);
+
+	renderer.set_aspect_ratio(aspect_ratio);
+	renderer.set_camera_data(view.camera);
+	let (cmd_bufs, ready) = renderer.ready();
+
+	let prepass = render_state
+		.render_passes
+		.is_enabled(render_passes::DEPTH_PREPASS);
+	let pbr_routine = match (prepass, &render_state.pbr_without_prepass) {
+		(false, Some(routine)) => routine,
+		_ => default_pbr,
+	};
+	let mut graph = RenderGraph::new();
+	let state = add_scene_passes(
+		&mut graph,
+		base_rendergraph,
+		&ready,
+		pbr_routine,
+		SceneOverlays {
+			material_overrides: &render_state.material_overrides,
+			section_caps: &render_state.section_caps,
+			debug_draw: &render_state.debug_draw,
+		},
+		&render_state.render_passes,
+		resolution,
+	);
+	let target = graph.add_surface_texture();
+	add_post_passes(
+		&mut graph,
+		state,
+		tonemapping_routine,
+		&render_state.motion_blur,
+		&render_state.color_filter,
+		None,
+		&settings,
+		render_state.surface_format,
+		resolution,
+		target,
+	);
+	graph.execute(renderer, OutputFrame::View(view.target), cmd_bufs, &ready);
 }
 
-struct OpalApp {
+/// Picks the handedness of the renderer. rend3_framework reads it from a
+/// const, so each handedness is its own app type.
+trait HandednessMarker {
+	const HANDEDNESS: Handedness;
+}
+
+struct LeftHanded;
+
+impl HandednessMarker for LeftHanded {
+	const HANDEDNESS: Handedness = Handedness::Left;
+}
+
+struct RightHanded;
+
+impl HandednessMarker for RightHanded {
+	const HANDEDNESS: Handedness = Handedness::Right;
+}
+
+struct OpalApp<H> {
 	render_state: Option<OpalAppRenderState>,
+	icon: WindowIcon,
+	/// checked when the device is created, handed to the gpu info window
+	capabilities: Option<GpuCapabilities>,
+	/// the headset's device when it was created through OpenXR, handed to the
+	/// session at setup
+	#[cfg(feature = "vr")]
+	xr_device: Option<XrDevice>,
+	_handedness: PhantomData<H>,
 }
 
 const SAMPLE_COUNT: SampleCount = SampleCount::One;
+/// what rend3_framework configures the surface with, so the app's own
+/// reconfigures match it
+const PRESENT_MODE: PresentMode = PresentMode::Mailbox;
+/// points two fingers spread apart to fly forward for as long as a second at
+/// the fly speed
+const PINCH_POINTS_PER_SECOND: f32 = 200.0;
+/// points two fingers spread apart per mouse wheel line of orbit zoom
+const PINCH_POINTS_PER_LINE: f32 = 40.0;
+/// how far in front of the camera the orbit camera's target starts
+const ORBIT_DISTANCE: f32 = 5.0;
+/// what the orthographic view spans vertically to start with, about what the
+/// perspective view spans at the orbit distance
+const ORTHOGRAPHIC_HEIGHT: f32 = 6.0;
+
+impl<H> OpalApp<H> {
+	pub fn new(icon: WindowIcon) -> Self {
+		Self {
+			render_state: None,
+			icon,
+			capabilities: None,
+			#[cfg(feature = "vr")]
+			xr_device: None,
+			_handedness: PhantomData,
+		}
+	}
+
+	/// Creates the device through the OpenXR runtime when there's a headset,
+	/// so the eyes can be rendered into its swapchains.
+	#[cfg(feature = "vr")]
+	fn create_headset_iad(
+		&mut self,
+		preference: ProfilePreference,
+	) -> Option<InstanceAdapterDevice> {
+		let (device, iad) = XrDevice::new(preference.profile())
+			.map_err(|e| log::warn!("vr unavailable: {}", e))
+			.ok()?;
+		self.xr_device = Some(device);
+		Some(iad)
+	}
 
-impl OpalApp {
-	pub fn new() -> Self {
-		Self { render_state: None }
+	#[cfg(not(feature = "vr"))]
+	fn create_headset_iad(&mut self, _: ProfilePreference) -> Option<InstanceAdapterDevice> {
+		None
 	}
 }
 
-impl rend3_framework::App for OpalApp {
-	const HANDEDNESS: Handedness = Handedness::Left;
+impl<H: HandednessMarker> rend3_framework::App for OpalApp<H> {
+	const HANDEDNESS: Handedness = H::HANDEDNESS;
 
 	fn sample_count(&self) -> SampleCount {
 		SAMPLE_COUNT
 	}
 
+	/// Creates the device in the profile the config asks for, through the
+	/// headset's runtime when there is one, and records what the adapter
+	/// lacks when it ends up cpu driven.
+	fn create_iad<'a>(
+		&'a mut self,
+	) -> Pin<Box<dyn Future<Output = anyhow::Result<InstanceAdapterDevice>> + 'a>> {
+		Box::pin(async move {
+			let mut preference = ProfilePreference::load(Path::new(CONFIG_PATH));
+			let iad = match self.create_headset_iad(preference) {
+				Some(iad) => iad,
+				None => match rend3::create_iad(None, None, preference.profile(), None).await {
+					Ok(iad) => iad,
+					// a forced profile the adapter can't run shouldn't keep the app
+					// from starting
+					Err(e) if preference != ProfilePreference::Auto => {
+						log::error!(
+							"can't render {}, picking a profile automatically: {}",
+							preference.label(),
+							e
+						);
+						preference = ProfilePreference::Auto;
+						rend3::create_iad(None, None, None, None).await?
+					}
+					Err(e) => return Err(e.into()),
+				},
+			};
+			let capabilities = GpuCapabilities::check(&iad, preference);
+			capabilities.log();
+			self.capabilities = Some(capabilities);
+			Ok(iad)
+		})
+	}
+
 	/// Called right before the window is made visible.
 	fn setup(
 		&mut self,
 		window: &Window,
 		renderer: &Arc<Renderer>,
-		routines: &Arc<DefaultRoutines>,
+		_routines: &Arc<DefaultRoutines>,
 		surface_format: TextureFormat,
 	) {
+		self.icon.apply(window, None);
+
+		// reopen where the window was left last run
+		let window_geometry = match WindowGeometry::load(Path::new(CONFIG_PATH)) {
+			Some(geometry) => {
+				geometry.restore(window);
+				geometry
+			}
+			None => WindowGeometry::capture(window),
+		};
+		let mut frame_pacer = FramePacer::default();
+		frame_pacer.update_monitor(window);
 		let window_size = window.inner_size();
 
 		// setup egui
-		let egui_routine = EguiRenderRoutine::new(
+		let mut egui_routine = EguiRenderRoutine::new(
 			renderer,
 			surface_format,
 			SAMPLE_COUNT,
@@ -262,48 +889,227 @@ impl rend3_framework::App for OpalApp {
 		);
 
 		// integrate with winit
-		let egui_platform = Platform::new(PlatformDescriptor {
-			physical_width: window_size.width as u32,
-			physical_height: window_size.height as u32,
-			scale_factor: window.scale_factor(),
-			font_definitions: egui::FontDefinitions::default(),
-			style: Default::default(),
-		});
+		let egui_platform = UiPlatform::new(window_size, window.scale_factor());
+
+		let target_inspector = TargetInspector::new(renderer, &mut egui_routine);
+
+		let mut settings = Settings {
+			view: CameraSettings::load(Path::new(CONFIG_PATH)),
+			..Settings::default()
+		};
+		let mut tasks = TaskManager::default();
+		let preset_benchmark = PresetBenchmark::start(
+			Path::new(CONFIG_PATH),
+			&renderer.adapter_info,
+			&mut settings.graphics,
+			&mut tasks,
+		);
+		settings::apply_ui_palette(&egui_platform.context(), &settings.accessibility);
+
+		// materials are added through the audit so color space mistakes get flagged
+		let mut color_audit = ColorSpaceAudit::default();
+
+		let mut scene = Scene::default();
 
 		// create a cube
 		let object = Object {
-			mesh_kind: ObjectMeshKind::Static(renderer.add_mesh(create_mesh())),
-			material: renderer.add_material(PbrMaterial {
-				albedo: AlbedoComponent::Value(Vec4::new(0.0, 0.5, 0.5, 1.0)),
-				..PbrMaterial::default()
-			}),
+			mesh_kind: ObjectMeshKind::Static(
+				scene.add_mesh(renderer, assets::cube(Self::HANDEDNESS)),
+			),
+			material: color_audit.add_material(
+				renderer,
+				"cube",
+				PbrMaterial {
+					albedo: AlbedoComponent::Value(Vec4::new(0.0, 0.5, 0.5, 1.0)),
+					..PbrMaterial::default()
+				},
+			),
 			transform: Mat4::IDENTITY,
 		};
 
 		// add the mesh object to the scene and keep the handle for it.
-		let object = renderer.add_object(object);
+		let object = scene.add_object(renderer, "cube", object);
+
+		// a figure walking around the cube, to attach props to
+		let rig = Rig::humanoid(0.9, 0.8);
+		let walker = animation::add_animated(
+			renderer,
+			&mut scene,
+			"walker",
+			&rig,
+			color_audit.add_material(
+				renderer,
+				"walker",
+				PbrMaterial {
+					albedo: AlbedoComponent::Value(Vec4::new(0.8, 0.6, 0.4, 1.0)),
+					..PbrMaterial::default()
+				},
+			),
+			Transform::from_mat4(Mat4::from_translation(Vec3::new(3.0, 0.0, 0.0))),
+			Self::HANDEDNESS,
+		);
+		let mut animations = Animations::default();
+		let walk = Arc::new(Clip::walk(&rig));
+		animations.play(&mut scene, walker, &rig, ClipPlayer::new(walk.clone()));
+
+		// longer legs and a shorter body, playing the same clip retargeted
+		let tall_rig = Rig::humanoid(1.2, 0.7);
+		let tall_walker = animation::add_animated(
+			renderer,
+			&mut scene,
+			"tall walker",
+			&tall_rig,
+			color_audit.add_material(
+				renderer,
+				"tall walker",
+				PbrMaterial {
+					albedo: AlbedoComponent::Value(Vec4::new(0.4, 0.6, 0.8, 1.0)),
+					..PbrMaterial::default()
+				},
+			),
+			Transform::from_mat4(Mat4::from_translation(Vec3::new(-3.0, 0.0, 0.0))),
+			Self::HANDEDNESS,
+		);
+		let player = ClipPlayer::retargeted(walk.clone(), &tall_rig, &BTreeMap::new());
+		animations.play(&mut scene, tall_walker, &tall_rig, player);
 
-		let directional_light = renderer.add_directional_light(DirectionalLight {
+		// standing until the blend space's parameter is dragged over to walking
+		let blended_walker = animation::add_animated(
+			renderer,
+			&mut scene,
+			"blended walker",
+			&rig,
+			color_audit.add_material(
+				renderer,
+				"blended walker",
+				PbrMaterial {
+					albedo: AlbedoComponent::Value(Vec4::new(0.6, 0.8, 0.4, 1.0)),
+					..PbrMaterial::default()
+				},
+			),
+			Transform::from_mat4(Mat4::from_translation(Vec3::new(0.0, 0.0, -3.0))),
+			Self::HANDEDNESS,
+		);
+		let clips = vec![Arc::new(Clip::idle(&rig)), walk];
+		let space = BlendSpace::new(BlendAxes::Two, clips);
+		animations.play_blend(&mut scene, blended_walker, &rig, space);
+
+		let mut anchors = ScreenAnchors::default();
+		anchors.add(
+			ScreenAnchor::label(AnchorTarget::Object(object), "cube").with_offset(Vec3::Y * 1.5),
+		);
+
+		let editor = Editor::new(renderer, &mut scene, &mut color_audit, Self::HANDEDNESS);
+		let scene_loader = SceneLoader::new(renderer, &mut color_audit);
+
+		let light = DirectionalLight {
 			color: Vec3::ONE,
 			intensity: 10.0,
 			direction: Vec3::new(-1.0, -4.0, 2.0),
 			distance: 400.0,
+		};
+		let directional_light = renderer.add_directional_light(light.clone());
+
+		// behind the cube, which is +z for right handed
+		let back = -camera::view_forward(Self::HANDEDNESS).z;
+		let camera = FlyCamera::new(
+			CameraPose::new(
+				Position::from_vec3a(Vec3A::new(3.0, 3.0, 5.0 * back)),
+				0.55,
+				-0.5,
+				Self::HANDEDNESS,
+			),
+			Self::HANDEDNESS,
+		);
+
+		// head tracking turns stereo on when the device came from a headset
+		#[cfg(feature = "vr")]
+		let xr = self.xr_device.take().and_then(|device| {
+			XrTracking::new(device, renderer, surface_format)
+				.map_err(|e| log::warn!("vr unavailable: {}", e))
+				.ok()
 		});
+		let stereo = StereoRig {
+			#[cfg(feature = "vr")]
+			enabled: xr.is_some(),
+			..StereoRig::default()
+		};
 
 		self.render_state = Some(OpalAppRenderState {
+			scene,
+			timestep: FixedTimestep::new(60.0),
 			object,
 			directional_light,
-			camera_pos: Vec3A::new(3.0, 3.0, -5.0),
-			camera_pitch: 0.55,
-			camera_yaw: -0.5,
+			light,
+			cameras: CameraRegistry::new(camera.pose),
+			camera_path: CameraPath::default(),
+			blended_walker,
+			animations,
+			camera,
+			orbit: None,
+			follow: None,
+			camera_mode: CameraMode::Perspective,
+			orthographic_height: ORTHOGRAPHIC_HEIGHT,
+			fly_speed_changed: None,
+			mouse_look: false,
+			cursor: Cursor::default(),
+			split_screen: SplitScreen::new(renderer, surface_format, camera),
+			stereo,
+			#[cfg(feature = "vr")]
+			xr,
 			egui_routine,
 			egui_platform,
+			surface_format,
+			material_overrides: MaterialOverrideRoutine::new(renderer),
+			section: SectionPlane::default(),
+			section_caps: SectionCapRoutine::new(renderer),
+			measure: MeasureTool::default(),
+			teleport: TeleportWindow::default(),
+			geo: GeoStreamer::default(),
+			point_plot: PointPlot::default(),
+			field_plot: FieldPlot::default(),
+			sequencer: Sequencer::default(),
+			anchors,
+			editor,
+			scene_diff: SceneDiffWindow::default(),
+			render_passes: RenderPasses::default(),
+			pbr_without_prepass: None,
+			manifest: ManifestWindow::default(),
+			scene_loader,
+			scene_name: "cube".into(),
+			tasks,
+			work_queue: WorkQueue::default(),
+			texture_streamer: TextureStreamer::default(),
+			warmup: Warmup::new(surface_format),
+			staging: StagingPool::default(),
+			frame_pacer,
+			window_geometry,
+			main_view_proj: Mat4::IDENTITY,
+			color_filter: ColorFilterRoutine::new(renderer, surface_format),
+			motion_blur: MotionBlurRoutine::new(renderer),
+			settings,
+			preset_benchmark,
+			color_audit,
+			target_inspector,
+			pixel_picker: PixelPicker::new(renderer),
+			debug_draw: DebugDraw::new(renderer),
+			volume_viz: VolumeViz::default(),
+			latency_tester: LatencyTester::default(),
+			input_recorder: InputRecorder::default(),
+			gpu_info: GpuInfoWindow::new(self.capabilities.take()),
+			pass_budgets: PassBudgets::load(Path::new(CONFIG_PATH)),
+			clipboard: SystemClipboard::default(),
+			show_clip_plane: false,
+			screenshots: Screenshots::new(renderer, surface_format, SAMPLE_COUNT),
 			last_frame_time: Instant::now(),
 			start_time: Instant::now(),
 			last_capture_time: Instant::now(),
 			frame_times: Histogram::new(),
 			stats: OpalAppRenderStats::default(),
-			input: OpalAppInputManager::default(),
+			input: InputManager::with_bindings(
+				ActionBindings::load(Path::new(BINDINGS_PATH)).unwrap_or_default(),
+			),
+			bindings_window: BindingsWindow::default(),
 		});
 	}
 
@@ -319,14 +1125,30 @@ impl rend3_framework::App for OpalApp {
 		event: Event<'_, ()>,
 		control_flow: impl FnOnce(ControlFlow),
 	) {
-		// get the render state object
-		let render_state = self.render_state.as_mut().unwrap();
+		// get the render state object, gone once the app has shut down
+		let render_state = match self.render_state.as_mut() {
+			Some(render_state) => render_state,
+			None => return,
+		};
 
 		// pass winit events to egui platform integration
+		render_state
+			.clipboard
+			.handle_event(&mut render_state.egui_platform, &event);
 		render_state.egui_platform.handle_event(&event);
 
-		// pass events to input manager
-		render_state.input.handle_event(&event);
+		// pass events to input manager, except a press that was just bound
+		let rebound = render_state
+			.bindings_window
+			.handle_event(&event, render_state.input.bindings_mut());
+		if !rebound {
+			let ctx = render_state.egui_platform.context();
+			render_state
+				.input
+				.set_ui_capture(ctx.wants_keyboard_input(), ctx.wants_pointer_input());
+			render_state.input.handle_event(&event);
+		}
+		render_state.latency_tester.handle_event(&event);
 
 		match event {
 			// OS events
@@ -341,19 +1163,89 @@ impl rend3_framework::App for OpalApp {
 						size.height,
 						window.scale_factor() as f32,
 					);
+					render_state.window_geometry.track(window);
+				}
+				WinitWindowEvent::Moved(_) => {
+					render_state.window_geometry.track(window);
+					render_state.frame_pacer.update_monitor(window);
+				}
+				// moved to a monitor with a different dpi. egui picks up the
+				// new scale from the platform, and the window is resized to
+				// keep its logical size. The window still has its old size
+				// here, so egui and the surface take the size it's about to
+				// be rather than waiting for the resize.
+				WinitWindowEvent::ScaleFactorChanged {
+					scale_factor,
+					new_inner_size,
+				} => {
+					log::info!("scale factor changed to {}", scale_factor);
+					let size = *new_inner_size;
+					render_state
+						.egui_routine
+						.resize(size.width, size.height, scale_factor as f32);
+					match surface {
+						Some(surface) if size.width > 0 && size.height > 0 => {
+							rend3::configure_surface(
+								surface,
+								&renderer.device,
+								render_state.surface_format,
+								UVec2::new(size.width, size.height),
+								PRESENT_MODE,
+							);
+							renderer.set_aspect_ratio(size.width as f32 / size.height as f32);
+						}
+						_ => {}
+					}
+					render_state.window_geometry.track(window);
+					render_state.frame_pacer.update_monitor(window);
 				}
 				_ => {}
 			},
 			// logic loop
 			Event::MainEventsCleared => {
+				render_state
+					.frame_pacer
+					.begin_frame(renderer, &render_state.settings.graphics);
+
 				// get frame time
 				let now = Instant::now();
-				let delta_time = now - render_state.last_frame_time;
+				let mut delta_time = now - render_state.last_frame_time;
+
+				// recorded frames replace the real input and frame time
+				if render_state.input_recorder.is_playing() {
+					if let Some((recorded_time, snapshot)) = render_state.input_recorder.playback()
+					{
+						if render_state.input.restore(&snapshot) {
+							delta_time = recorded_time;
+						} else {
+							render_state.input_recorder.stop("invalid input snapshot");
+						}
+					}
+				} else {
+					render_state
+						.input_recorder
+						.record(delta_time, render_state.input.snapshot());
+				}
+				let overlay = render_state
+					.input_recorder
+					.is_recording()
+					.then_some(IconOverlay::Recording);
+				self.icon.apply(window, overlay);
 
 				render_state
 					.frame_times
 					.increment(delta_time.as_micros() as u64)
 					.unwrap();
+				if !render_state.warmup.is_running() {
+					render_state.preset_benchmark.frame(
+						delta_time,
+						render_state
+							.frame_pacer
+							.refresh_rate(&render_state.settings.graphics),
+						&mut render_state.settings.graphics,
+						Path::new(CONFIG_PATH),
+					);
+				}
 
 				let time_since_last_second = now - render_state.last_capture_time;
 				if time_since_last_second > Duration::from_secs(1) {
@@ -371,47 +1263,236 @@ impl rend3_framework::App for OpalApp {
 
 				render_state.last_frame_time = now;
 
-				if (render_state
-					.input
-					.is_keycode_just_pressed(&VirtualKeyCode::Escape))
+				// fixed rate simulation, the renderer interpolates between steps
+				let steps = render_state.timestep.advance(delta_time.as_secs_f32());
+				for _ in 0..steps {
+					render_state.scene.begin_step();
+					render_state
+						.sequencer
+						.step(&mut render_state.scene, render_state.timestep.step);
+					render_state
+						.scene
+						.step_modifiers(render_state.timestep.step);
+					render_state
+						.animations
+						.step(&mut render_state.scene, render_state.timestep.step);
+				}
+				for event in render_state.sequencer.take_events() {
+					run_event(render_state, renderer, &event, Self::HANDEDNESS);
+				}
+
+				// escape closes whatever ui has the keyboard first
+				let typing = render_state.egui_platform.context().wants_keyboard_input();
+				if !typing
+					&& render_state
+						.input
+						.is_keycode_just_pressed(&VirtualKeyCode::Escape)
 				{
 					control_flow(ControlFlow::Exit);
 					return;
 				}
 
-				let rotation = Mat3A::from_euler(
-					glam::EulerRot::XYZ,
-					-render_state.camera_pitch,
-					-render_state.camera_yaw,
-					0.0,
-				)
-				.transpose();
-				let forward = -rotation.z_axis;
-				let up = rotation.y_axis;
-				let side = -rotation.x_axis;
+				// tab moves between widgets while the ui has the keyboard
+				if render_state.split_screen.enabled
+					&& !typing && render_state
+					.input
+					.is_keycode_just_pressed(&VirtualKeyCode::Tab)
+				{
+					render_state.split_screen.toggle_input_viewport();
+					render_state.camera.motion.stop();
+					render_state.split_screen.camera.motion.stop();
+					reorbit(render_state);
+				}
+				// number keys switch between the registered cameras
+				let number_keys = [
+					VirtualKeyCode::Key1,
+					VirtualKeyCode::Key2,
+					VirtualKeyCode::Key3,
+					VirtualKeyCode::Key4,
+					VirtualKeyCode::Key5,
+					VirtualKeyCode::Key6,
+					VirtualKeyCode::Key7,
+					VirtualKeyCode::Key8,
+					VirtualKeyCode::Key9,
+				];
+				let number = number_keys
+					.iter()
+					.position(|key| render_state.input.is_keycode_just_pressed(key));
+				if let (false, Some(index)) = (typing, number) {
+					let current = render_state.camera.pose;
+					if let Some(pose) = render_state.cameras.switch(index, &current) {
+						cut_to(render_state, pose, Self::HANDEDNESS);
+					}
+				}
+				if render_state
+					.input
+					.is_action_just_pressed(actions::TOGGLE_ORBIT)
+				{
+					toggle_orbit(render_state, Self::HANDEDNESS);
+				}
+				if render_state
+					.input
+					.is_action_just_pressed(actions::TOGGLE_ORTHOGRAPHIC)
+				{
+					render_state.camera_mode = render_state.camera_mode.toggled();
+				}
+				for (action, view) in [
+					(actions::VIEW_FRONT, AxisView::Front),
+					(actions::VIEW_TOP, AxisView::Top),
+					(actions::VIEW_SIDE, AxisView::Side),
+				] {
+					if render_state.input.is_action_just_pressed(action) {
+						look_along(render_state, view, Self::HANDEDNESS);
+					}
+				}
+
+				if render_state
+					.input
+					.is_keycode_just_pressed(&VirtualKeyCode::F12)
+				{
+					render_state.screenshots.request();
+				}
+
+				if render_state
+					.input
+					.is_keycode_just_pressed(&VirtualKeyCode::F11)
+				{
+					match FullscreenMode::of(window) {
+						FullscreenMode::Windowed => FullscreenMode::Borderless,
+						_ => FullscreenMode::Windowed,
+					}
+					.apply(window);
+				}
 
-				let velocity = 10.0 * delta_time.as_secs_f32();
+				// queued with the other input, so replayed recordings stop too
+				let events = render_state.input.events();
+				if events.contains(&InputEvent::FocusLost) {
+					render_state.camera.motion.stop();
+					render_state.split_screen.camera.motion.stop();
+				}
 
-				if render_state.input.is_keycode_down(&VirtualKeyCode::W) {
-					render_state.camera_pos -= forward * velocity;
+				// look around while the right mouse button is held outside the ui,
+				// scrolling there changes the fly speed. some platforms scroll
+				// whatever window is under the pointer, focused or not.
+				let over_ui = render_state.egui_platform.context().is_pointer_over_area();
+				let scroll = match !over_ui && render_state.input.is_focused() {
+					true => render_state.input.scroll_delta(),
+					false => 0.0,
+				};
+				// orbiting zooms instead, and so does the orthographic view
+				match (&render_state.orbit, render_state.camera_mode) {
+					(Some(_), _) => {}
+					(None, CameraMode::Orthographic) => {
+						let height = render_state.orthographic_height * 1.2f32.powf(-scroll);
+						render_state.orthographic_height = height.clamp(0.01, 100_000.0);
+					}
+					(None, CameraMode::Perspective) if scroll != 0.0 => {
+						render_state.settings.camera.scroll_fly_speed(scroll);
+						render_state.fly_speed_changed = Some(now);
+					}
+					(None, CameraMode::Perspective) => {}
 				}
-				if render_state.input.is_keycode_down(&VirtualKeyCode::S) {
-					render_state.camera_pos += forward * velocity;
+				let controls = render_state.settings.camera;
+				// keeps looking when the hidden cursor passes over the ui
+				let looking = render_state.input.is_action_down(actions::LOOK)
+					&& (render_state.mouse_look || !over_ui);
+				if looking != render_state.mouse_look {
+					render_state.mouse_look = looking;
+					grab_cursor(window, looking);
 				}
-				if render_state.input.is_keycode_down(&VirtualKeyCode::A) {
-					render_state.camera_pos += side * velocity;
+				let look = match looking {
+					true => {
+						let delta = render_state
+							.input
+							.look_delta(controls.raw_mouse, window.scale_factor());
+						if !controls.raw_mouse {
+							// the cursor would stop at the edge of the window
+							let size = window.inner_size();
+							let center = DVec2::new(size.width as f64, size.height as f64) / 2.0;
+							let position = PhysicalPosition::new(center.x, center.y);
+							if window.set_cursor_position(position).is_ok() {
+								render_state.input.warp_cursor(center);
+							}
+						}
+						delta.as_vec2() * controls.look_sensitivity
+					}
+					false => Vec2::ZERO,
+				};
+				// dragging a finger on a touch screen looks around too
+				let touch_look = match over_ui {
+					true => Vec2::ZERO,
+					false => {
+						let drag = render_state.input.touch_drag(window.scale_factor());
+						drag.as_vec2() * controls.look_sensitivity
+					}
+				};
+				// move whichever camera the input is routed to
+				let camera = match render_state.split_screen.routes_input_to_second() {
+					true => &mut render_state.split_screen.camera,
+					false => &mut render_state.camera,
+				};
+				let pinch = match over_ui {
+					true => 0.0,
+					false => render_state.input.pinch_delta(window.scale_factor()) as f32,
+				};
+				match &mut render_state.orbit {
+					Some(orbit) => {
+						orbit.update(
+							&render_state.input,
+							look + touch_look,
+							scroll + pinch / PINCH_POINTS_PER_LINE,
+							&controls,
+							window.scale_factor(),
+							delta_time.as_secs_f32(),
+						);
+						camera.pose = orbit.pose();
+					}
+					None => {
+						camera.update(
+							&render_state.input,
+							look + touch_look,
+							&controls,
+							delta_time.as_secs_f32(),
+						);
+						// pinching zooms by flying along the view
+						let forward = camera.pose.forward(Self::HANDEDNESS);
+						camera.pose.position = camera.pose.position.offset(
+							forward * (pinch / PINCH_POINTS_PER_SECOND * controls.fly_speed),
+						);
+					}
 				}
-				if render_state.input.is_keycode_down(&VirtualKeyCode::D) {
-					render_state.camera_pos -= side * velocity;
+				// the path flies the main camera while it plays, over any input
+				let flown = render_state.camera_path.update(delta_time.as_secs_f32());
+				if let Some(pose) = flown {
+					render_state.camera.pose = pose;
+					render_state.camera.motion.stop();
 				}
-
-				if render_state.input.is_keycode_down(&VirtualKeyCode::E) {
-					// render_state.camera_pos += up * velocity;
-					render_state.camera_pos += Vec3A::new(0.0, velocity, 0.0);
+				if let Some(follow) = &mut render_state.follow {
+					match render_state.scene.get(follow.target) {
+						Some(object) => {
+							let target = object.transform().to_mat4();
+							render_state.camera.pose =
+								follow.update(target, delta_time.as_secs_f32());
+							render_state.camera.motion.stop();
+						}
+						None => {
+							log::info!("stopped following, the object was removed");
+							render_state.follow = None;
+						}
+					}
 				}
-				if render_state.input.is_keycode_down(&VirtualKeyCode::C) {
-					// render_state.camera_pos -= up * velocity;
-					render_state.camera_pos -= Vec3A::new(0.0, velocity, 0.0);
+				render_state.camera.shake.update(delta_time.as_secs_f32());
+
+				#[cfg(feature = "vr")]
+				if let Some(xr) = &mut render_state.xr {
+					match xr.poll() {
+						Ok(eyes) => render_state.stereo.tracked_eyes = eyes,
+						Err(e) => {
+							log::error!("lost vr tracking: {}", e);
+							render_state.xr = None;
+							render_state.stereo.tracked_eyes = None;
+						}
+					}
 				}
 
 				// request a redraw of the scene
@@ -448,17 +1529,547 @@ impl rend3_framework::App for OpalApp {
 							ui.label("max");
 							ui.label(format!("{:0>5.2}ms", render_state.stats.max_frame_time));
 							ui.end_row();
-							ui.label("pos");
+							ui.label("latency");
+							let frame_interval =
+								Duration::from_secs_f32(render_state.stats.avg_frame_time / 1000.0);
+							ui.label(match render_state.frame_pacer.latency(frame_interval) {
+								Some(latency) => format!(
+									"~{:.1}ms, {} in flight",
+									latency.as_secs_f32() * 1000.0,
+									render_state.frame_pacer.frames_in_flight()
+								),
+								None => "-".into(),
+							})
+							.on_hover_text(
+								"estimated input to photon latency, from input being sampled \
+								 to the gpu finishing the frame plus a refresh",
+							);
+							ui.end_row();
+							ui.label("refresh");
+							let graphics = &render_state.settings.graphics;
+							let pacer = &render_state.frame_pacer;
+							ui.label(match (graphics.refresh_rate, pacer.max_refresh_rate()) {
+								(Some(rate), _) => format!("{}hz, set", rate),
+								(None, Some(rate)) => format!("{}hz max", rate),
+								(None, None) => "unknown".into(),
+							})
+							.on_hover_text(
+								"the fastest the monitor the window is on supports, which it \
+								 may not be running at. Set it in the graphics settings",
+							);
+							ui.end_row();
+							ui.label("queued work");
 							ui.label(format!(
-								"x{:0>5.2} y{:0>5.2} z{:0>5.2}",
-								render_state.camera_pos.x,
-								render_state.camera_pos.y,
-								render_state.camera_pos.z
-							));
+								"{} jobs, {:.2}ms last frame",
+								render_state.work_queue.pending(),
+								render_state.work_queue.last_spent().as_secs_f32() * 1000.0
+							))
+							.on_hover_text(
+								"main thread work spread over frames to fit the upload budget",
+							);
+							ui.end_row();
+							ui.label("uploads");
+							let uploads = render_state.staging.last_frame();
+							ui.label(format!(
+								"{:.1}kb in {} writes, {} in flight",
+								uploads.bytes as f32 / 1024.0,
+								uploads.writes,
+								uploads.in_flight
+							))
+							.on_hover_text("staging buffer uploads last frame");
+							ui.end_row();
+							ui.label("pos");
+							let position = render_state.camera.pose.position;
+							let readout = ui
+								.add(
+									egui::Label::new(format!(
+										"x{:0>5.2} y{:0>5.2} z{:0>5.2}",
+										position.x, position.y, position.z
+									))
+									.sense(egui::Sense::click()),
+								)
+								.on_hover_text("click to copy");
+							if readout.clicked() {
+								// in the form `tp` takes
+								ui.output().copied_text =
+									format!("{} {} {}", position.x, position.y, position.z);
+							}
+						});
+					if ui.button("copy stats").clicked() {
+						let stats = &render_state.stats;
+						let uploads = render_state.staging.last_frame();
+						let position = render_state.camera.pose.position;
+						ui.output().copied_text = format!(
+							"{} frames over {:.2}s\navg {:.2}ms\nmin {:.2}ms\nmax {:.2}ms\n\
+							 uploads {:.1}kb in {} writes\npos {} {} {}",
+							stats.frame_count,
+							stats.sample_duration,
+							stats.avg_frame_time,
+							stats.min_frame_time,
+							stats.max_frame_time,
+							uploads.bytes as f32 / 1024.0,
+							uploads.writes,
+							position.x,
+							position.y,
+							position.z
+						);
+					}
+					let mut fullscreen = FullscreenMode::of(window);
+					egui::ComboBox::from_label("fullscreen (F11)")
+						.selected_text(fullscreen.label())
+						.show_ui(ui, |ui| {
+							for mode in FullscreenMode::ALL {
+								ui.selectable_value(&mut fullscreen, mode, mode.label());
+							}
 						});
+					if fullscreen != FullscreenMode::of(window) {
+						fullscreen.apply(window);
+					}
+					let split_screen =
+						ui.checkbox(&mut render_state.split_screen.enabled, "split screen");
+					let stereo = ui.checkbox(&mut render_state.stereo.enabled, "stereo");
+					let mut orbiting = render_state.orbit.is_some();
+					if ui.checkbox(&mut orbiting, "orbit camera (O)").changed() {
+						toggle_orbit(render_state, Self::HANDEDNESS);
+					}
+					follow_ui(ui, render_state, Self::HANDEDNESS);
+					ui.horizontal(|ui| {
+						egui::ComboBox::from_id_source("camera_mode")
+							.selected_text(render_state.camera_mode.label())
+							.show_ui(ui, |ui| {
+								for mode in CameraMode::ALL {
+									ui.selectable_value(
+										&mut render_state.camera_mode,
+										mode,
+										mode.label(),
+									);
+								}
+							})
+							.response
+							.on_hover_text("numpad 5");
+						for view in AxisView::ALL {
+							if ui
+								.small_button(view.label())
+								.on_hover_text("numpad 1, 7 and 3")
+								.clicked()
+							{
+								look_along(render_state, view, Self::HANDEDNESS);
+							}
+						}
+					});
+					// viewports swap cameras, don't blur across the cut
+					if split_screen.changed() || stereo.changed() {
+						render_state.motion_blur.reset_camera_history();
+					}
+
+					let object = render_state.object;
+					let mut highlighted = render_state
+						.scene
+						.get(object)
+						.and_then(|object| object.material_override)
+						.is_some();
+					if ui.checkbox(&mut highlighted, "highlight cube").changed() {
+						render_state.scene.set_material_override(
+							object,
+							highlighted
+								.then(|| MaterialOverride::highlight(Vec3::new(1.0, 0.6, 0.0))),
+						);
+					}
+
+					ui.horizontal(|ui| {
+						if ui.button("screenshot (F12)").clicked() {
+							render_state.screenshots.request();
+						}
+						ui.checkbox(&mut render_state.screenshots.metadata_strip, "metadata");
+					});
+					ui.checkbox(&mut render_state.target_inspector.open, "render targets");
+					ui.checkbox(&mut render_state.pixel_picker.open, "pixel picker");
+					ui.checkbox(&mut render_state.volume_viz.open, "volumes");
+					ui.checkbox(&mut render_state.latency_tester.open, "latency tester");
+					ui.checkbox(&mut render_state.input_recorder.open, "input recorder");
+					ui.checkbox(&mut render_state.gpu_info.open, "gpu info");
+					ui.checkbox(&mut render_state.pass_budgets.open, "gpu timings");
+					let benchmarking = render_state.preset_benchmark.is_running();
+					if ui
+						.add_enabled(!benchmarking, egui::Button::new("benchmark graphics"))
+						.on_hover_text(
+							"time each graphics preset and use the best one that keeps up",
+						)
+						.clicked()
+					{
+						render_state
+							.preset_benchmark
+							.restart(&mut render_state.settings.graphics, &mut render_state.tasks);
+					}
+					ui.checkbox(&mut render_state.bindings_window.open, "key bindings");
+					ui.checkbox(&mut render_state.show_clip_plane, "clip plane");
+					ui.checkbox(&mut render_state.section.open, "section plane");
+					ui.checkbox(&mut render_state.measure.open, "measure");
+					ui.checkbox(&mut render_state.teleport.open, "teleport");
+					let cameras = format!("cameras ({})", render_state.cameras.active_name());
+					ui.checkbox(&mut render_state.cameras.open, cameras);
+					ui.checkbox(&mut render_state.camera_path.open, "camera path");
+					ui.checkbox(&mut render_state.geo.open, "geo tiles");
+					ui.checkbox(&mut render_state.point_plot.open, "point plot");
+					ui.checkbox(&mut render_state.field_plot.open, "field plot");
+					ui.checkbox(&mut render_state.sequencer.open, "sequencer");
+					let blend_space = render_state
+						.animations
+						.blend_space_mut(render_state.blended_walker);
+					if let Some(blend_space) = blend_space {
+						ui.checkbox(&mut blend_space.open, "blend space");
+					}
+					ui.checkbox(&mut render_state.anchors.enabled, "labels");
+					ui.checkbox(&mut render_state.editor.inspector.open, "inspector");
+					ui.checkbox(&mut render_state.editor.hierarchy.open, "hierarchy");
+					ui.checkbox(&mut render_state.editor.skeletons.shown, "skeletons");
+					ui.checkbox(&mut render_state.scene_diff.open, "scene diff");
+					ui.checkbox(&mut render_state.manifest.open, "scene manifest");
+					ui.checkbox(&mut render_state.scene_loader.open, "scene loader");
+					ui.checkbox(&mut render_state.texture_streamer.open, "texture streaming");
+					let running = render_state.tasks.running();
+					ui.checkbox(&mut render_state.tasks.open, format!("jobs ({})", running));
 				});
 
-				let (_output, paint_commands) = render_state.egui_platform.end_frame(Some(window));
+				render_state
+					.split_screen
+					.overlay(&ctx, resolution, window.scale_factor() as f32);
+
+				let view = render_state.settings.view;
+				if settings::settings_window(
+					&ctx,
+					&mut render_state.settings,
+					&mut render_state.render_passes,
+				) {
+					if render_state.settings.view != view {
+						if let Err(e) = render_state.settings.view.save(Path::new(CONFIG_PATH)) {
+							log::error!("failed to save the camera settings: {}", e);
+						}
+					}
+					settings::apply_ui_palette(&ctx, &render_state.settings.accessibility);
+					render_state
+						.preset_benchmark
+						.remember(&render_state.settings.graphics, Path::new(CONFIG_PATH));
+				}
+				let shadow_distance = render_state.settings.graphics.shadow_distance;
+				if render_state.light.distance != shadow_distance {
+					render_state.light.distance = shadow_distance;
+					renderer.update_directional_light(
+						&render_state.directional_light,
+						DirectionalLightChange {
+							color: None,
+							intensity: None,
+							direction: None,
+							distance: Some(shadow_distance),
+						},
+					);
+				}
+
+				render_state.color_audit.window(&ctx, renderer);
+
+				// the inspector and picker look at the main viewport
+				let inspected_viewport =
+					match render_state.stereo.enabled || render_state.split_screen.enabled {
+						true => Viewport::Right.rect(resolution),
+						false => (UVec2::ZERO, resolution),
+					};
+				let inspected_resolution = inspected_viewport.1;
+				render_state.target_inspector.window(
+					&ctx,
+					inspected_resolution.x as f32 / inspected_resolution.y as f32,
+				);
+				render_state.pixel_picker.window(&ctx, inspected_viewport);
+				render_state.section.window(&ctx, &render_state.scene);
+				render_state
+					.section
+					.drag(&ctx, render_state.main_view_proj, inspected_viewport);
+				render_state.measure.window(&ctx);
+				if let Some(pixel) = render_state.measure.click(&ctx, inspected_viewport) {
+					render_state.pixel_picker.pick(pixel);
+				}
+				render_state
+					.measure
+					.labels(&ctx, render_state.main_view_proj, inspected_viewport);
+				render_state.anchors.draw(
+					&ctx,
+					&render_state.scene,
+					render_state.main_view_proj,
+					render_state.camera.pose.position.to_vec3a().into(),
+					inspected_viewport,
+				);
+				let can_paste = render_state.editor.can_paste();
+				let action = render_state.editor.context_menu.show(
+					&ctx,
+					&render_state.scene,
+					render_state.main_view_proj,
+					render_state.camera.pose.position.to_vec3a().into(),
+					inspected_viewport,
+					can_paste,
+				);
+				match action {
+					Some(MenuAction::Focus(id)) => focus_object(render_state, id, Self::HANDEDNESS),
+					Some(action) => {
+						render_state
+							.editor
+							.run(renderer, &mut render_state.scene, action)
+					}
+					None => {}
+				}
+				render_state.editor.selection.click(
+					&ctx,
+					&render_state.scene,
+					render_state.main_view_proj,
+					render_state.camera.pose.position.to_vec3a().into(),
+					inspected_viewport,
+				);
+				// a picked bone selects its object over whatever was hit
+				let boned = render_state.editor.skeletons.click(
+					&ctx,
+					&render_state.scene,
+					render_state.main_view_proj,
+					inspected_viewport,
+				);
+				if let Some(id) = boned {
+					render_state.editor.selection.select(id);
+				}
+				render_state
+					.editor
+					.isolation_breadcrumb(&ctx, renderer, &mut render_state.scene);
+				let editor = &mut render_state.editor;
+				editor.inspector.window(
+					&ctx,
+					&mut render_state.scene,
+					&editor.selection,
+					editor.skeletons.selected(),
+				);
+				editor
+					.hierarchy
+					.window(&ctx, &mut render_state.scene, &mut editor.selection);
+				let removed: Vec<ObjectId> = render_state
+					.scene_diff
+					.window(&ctx, &mut render_state.scene)
+					.iter()
+					.filter_map(|name| render_state.scene.find_by_name(name))
+					.collect();
+				render_state
+					.editor
+					.delete(renderer, &mut render_state.scene, &removed);
+				let built = render_state.manifest.window(
+					&ctx,
+					renderer,
+					&mut render_state.scene,
+					&mut render_state.color_audit,
+					Self::HANDEDNESS,
+				);
+				if built {
+					render_state.scene_name = scene_name(render_state.manifest.path());
+				}
+				let choice = render_state.editor.palette.show(&ctx, &render_state.scene);
+				match choice {
+					Some(PaletteChoice::Command(EditorCommand::FocusSelection)) => {
+						if let Some(&id) = render_state.editor.selection.objects().first() {
+							focus_object(render_state, id, Self::HANDEDNESS);
+						}
+					}
+					Some(PaletteChoice::Command(EditorCommand::Screenshot)) => {
+						render_state.screenshots.request()
+					}
+					Some(PaletteChoice::Command(command)) => {
+						render_state
+							.editor
+							.run_command(renderer, &mut render_state.scene, command)
+					}
+					Some(PaletteChoice::Object(id)) => {
+						render_state.editor.selection.select(id);
+						focus_object(render_state, id, Self::HANDEDNESS);
+					}
+					Some(PaletteChoice::Console(line)) => match teleport::parse_command(&line) {
+						Ok(ConsoleCommand::Teleport(target)) => {
+							let position = teleport::resolve(
+								&target,
+								&render_state.scene,
+								&render_state.camera.pose,
+								Self::HANDEDNESS,
+							);
+							match position {
+								Ok(position) => move_camera(render_state, position),
+								Err(e) => log::warn!("{}: {}", line, e),
+							}
+						}
+						Ok(ConsoleCommand::Shake(trauma)) => render_state.camera.add_trauma(trauma),
+						Err(e) => log::warn!("{}: {}", line, e),
+					},
+					None => {}
+				}
+				let (undo, redo, isolate, palette, save) = {
+					let input = ctx.input();
+					let shortcuts = !ctx.wants_keyboard_input();
+					let command = input.modifiers.command && shortcuts;
+					let shift = input.modifiers.shift;
+					(
+						command && !shift && input.key_pressed(egui::Key::Z),
+						command
+							&& (input.key_pressed(egui::Key::Y)
+								|| shift && input.key_pressed(egui::Key::Z)),
+						shortcuts && shift && !command && input.key_pressed(egui::Key::H),
+						// also closes the palette while its search field has focus
+						input.modifiers.command && input.key_pressed(egui::Key::P),
+						// whether to save only the changes
+						(command && input.key_pressed(egui::Key::S)).then_some(!shift),
+					)
+				};
+				if palette {
+					render_state.editor.palette.toggle();
+				}
+				if let Some(incremental) = save {
+					render_state
+						.editor
+						.inspector
+						.save(&mut render_state.scene, incremental);
+				}
+				if isolate {
+					render_state
+						.editor
+						.toggle_isolation(renderer, &mut render_state.scene);
+				}
+				if undo {
+					render_state
+						.editor
+						.history
+						.undo(renderer, &mut render_state.scene);
+				}
+				if redo {
+					render_state
+						.editor
+						.history
+						.redo(renderer, &mut render_state.scene);
+				}
+				if render_state.settings.interface.show_hover_tooltips() {
+					hover::object_tooltip(
+						&ctx,
+						&render_state.scene,
+						&render_state.color_audit,
+						render_state.main_view_proj,
+						render_state.camera.pose.position.to_vec3a().into(),
+						inspected_viewport,
+					);
+				}
+				render_state
+					.volume_viz
+					.window(&ctx, &["main", "split screen"], &["sun"]);
+				render_state.latency_tester.window(&ctx);
+				render_state.input_recorder.window(&ctx);
+				render_state
+					.gpu_info
+					.window(&ctx, renderer, Path::new(CONFIG_PATH));
+				render_state
+					.pass_budgets
+					.window(&ctx, renderer, Path::new(CONFIG_PATH));
+				render_state.bindings_window.window(
+					&ctx,
+					render_state.input.bindings_mut(),
+					Path::new(BINDINGS_PATH),
+				);
+				render_state.latency_tester.draw(&ctx);
+				// teleports move whichever camera the input is routed to
+				let camera = match render_state.split_screen.routes_input_to_second() {
+					true => &mut render_state.split_screen.camera,
+					false => &mut render_state.camera,
+				};
+				let teleport = render_state.teleport.window(
+					&ctx,
+					&render_state.scene,
+					camera.pose.position.to_vec3a().into(),
+				);
+				if let Some(target) = teleport {
+					let resolved = teleport::resolve(
+						&target,
+						&render_state.scene,
+						&camera.pose,
+						Self::HANDEDNESS,
+					);
+					match resolved {
+						Ok(position) => {
+							camera.teleport(position);
+							render_state.motion_blur.reset_camera_history();
+							render_state.teleport.clear_error();
+							reorbit(render_state);
+						}
+						Err(e) => render_state.teleport.set_error(e),
+					}
+				}
+				let current = render_state.camera.pose;
+				if let Some(pose) = render_state.cameras.window(&ctx, &current) {
+					cut_to(render_state, pose, Self::HANDEDNESS);
+				}
+				if let Some(pose) = render_state.camera_path.window(&ctx, &current) {
+					cut_to(render_state, pose, Self::HANDEDNESS);
+				}
+				render_state
+					.geo
+					.window(&ctx, &mut render_state.scene, Self::HANDEDNESS);
+				render_state.scene_loader.window(
+					&ctx,
+					render_state.settings.import,
+					Self::HANDEDNESS,
+					&mut render_state.tasks,
+				);
+				render_state.tasks.window(&ctx);
+				render_state.texture_streamer.window(&ctx);
+				render_state.tasks.toasts(&ctx);
+				render_state.point_plot.window(
+					&ctx,
+					renderer,
+					&mut render_state.scene,
+					Self::HANDEDNESS,
+				);
+				render_state.field_plot.window(
+					&ctx,
+					renderer,
+					&mut render_state.scene,
+					Self::HANDEDNESS,
+				);
+				render_state.sequencer.window(
+					&ctx,
+					&render_state.scene,
+					&render_state.light,
+					&render_state.camera.pose,
+				);
+				let blend_space = render_state
+					.animations
+					.blend_space_mut(render_state.blended_walker);
+				if let Some(blend_space) = blend_space {
+					blend_space.window(&ctx);
+				}
+				let cut = render_state.sequencer.update(
+					renderer,
+					&mut render_state.scene,
+					&render_state.directional_light,
+					&mut render_state.light,
+				);
+				if let Some(pose) = cut {
+					cut_to(render_state, pose, Self::HANDEDNESS);
+				}
+				if let Some(changed) = render_state.fly_speed_changed {
+					fly_speed_indicator(&ctx, render_state.settings.camera.fly_speed, changed);
+				}
+
+				let (output, paint_commands) = render_state.egui_platform.end_frame();
+				// hidden while looking around, and egui's over the ui
+				let ui_cursor = match render_state.mouse_look {
+					true => Some(egui::CursorIcon::None),
+					false => (ctx.is_pointer_over_area() || ctx.is_using_pointer())
+						.then_some(output.cursor_icon),
+				};
+				let tool_cursor = render_state.measure.open.then_some(CursorKind::Crosshair);
+				render_state.cursor.apply(window, ui_cursor, tool_cursor);
+				let copied = match render_state.editor.take_copied_text() {
+					Some(text) => text,
+					None => output.copied_text,
+				};
+				if !copied.is_empty() {
+					render_state.clipboard.set_text(&copied);
+				}
 				let paint_jobs = render_state
 					.egui_platform
 					.context()
@@ -469,64 +2080,544 @@ impl rend3_framework::App for OpalApp {
 					context: render_state.egui_platform.context(),
 				};
 
-				let frame = OutputFrame::Surface {
-					surface: Arc::clone(surface.unwrap()),
+				// a pending screenshot takes the place of the surface for this frame
+				let capturing = render_state.screenshots.is_pending();
+				// and so do warmup frames, with the step's features turned on
+				// until the frame is done
+				let warmup = match capturing {
+					true => None,
+					false => render_state.warmup.next_step(),
 				};
-
-				let view = Mat4::from_euler(
-					glam::EulerRot::XYZ,
-					-render_state.camera_pitch,
-					-render_state.camera_yaw,
-					0.0,
-				);
-				let view = view * Mat4::from_translation((-render_state.camera_pos).into());
-
-				renderer.set_camera_data(Camera {
-					projection: CameraProjection::Perspective {
-						vfov: 60.0,
-						near: 0.1,
+				let restore = warmup.map(|step| set_frame_features(render_state, step));
+				let frame = match (capturing, warmup) {
+					(true, _) => {
+						OutputFrame::View(render_state.screenshots.target(renderer, resolution))
+					}
+					(false, Some(_)) => {
+						OutputFrame::View(render_state.warmup.target(renderer, resolution))
+					}
+					(false, None) => match surface::acquire(
+						renderer,
+						window,
+						surface.unwrap(),
+						render_state.surface_format,
+						resolution,
+						PRESENT_MODE,
+					) {
+						Some(frame) => frame,
+						None => return,
 					},
-					view,
-				});
+				};
 
-				let (cmd_bufs, ready) = renderer.ready();
+				let vfov = render_state.settings.view.vfov;
+				let mut clipping = render_state.settings.view.clipping;
+				if let Some(plane) = render_state.section.clip_plane() {
+					clipping.clip_plane = Some(plane);
+				}
 
 				// lock routines
-				let pbr_routine = rend3_framework::lock(&routines.pbr);
+				let default_pbr = rend3_framework::lock(&routines.pbr);
+				let prepass = render_state
+					.render_passes
+					.is_enabled(render_passes::DEPTH_PREPASS);
+				if !prepass && render_state.pbr_without_prepass.is_none() {
+					render_state.pbr_without_prepass = Some(render_passes::pbr_without_prepass(
+						renderer,
+						&base_rendergraph.interfaces,
+					));
+				}
 				let tonemapping_routine = rend3_framework::lock(&routines.tonemapping);
 
+				render_state
+					.color_filter
+					.update(renderer, &render_state.settings.accessibility);
+				render_state.target_inspector.update(renderer);
+
+				let budget = render_state.settings.graphics.work_budget;
+				render_state
+					.work_queue
+					.begin_frame(Duration::from_secs_f32(budget / 1000.0));
+				render_state.geo.update(
+					renderer,
+					&mut render_state.scene,
+					render_state.camera.pose.position.to_vec3a().into(),
+					&mut render_state.work_queue,
+				);
+				// new meshes may need pipelines nothing has drawn with yet
+				if render_state.scene_loader.update(
+					renderer,
+					&mut render_state.scene,
+					&mut render_state.work_queue,
+					&mut render_state.texture_streamer,
+				) {
+					render_state.scene_name = scene_name(render_state.scene_loader.path());
+					render_state.warmup.restart();
+				}
+				render_state
+					.work_queue
+					.run(renderer, &mut render_state.scene);
+				render_state.tasks.update();
+
+				// keep the render space origin near the main camera so far away
+				// geometry doesn't jitter
+				let world = render_state.settings.world;
+				let previous_origin = render_state.scene.origin();
+				let rebased = match world.floating_origin {
+					true => render_state
+						.scene
+						.rebase_near(render_state.camera.pose.position, world.rebase_distance),
+					false if previous_origin != Position::ZERO => {
+						render_state.scene.rebase(Position::ZERO);
+						true
+					}
+					false => false,
+				};
+				let origin = render_state.scene.origin();
+				if rebased {
+					render_state
+						.motion_blur
+						.rebase(origin.relative_to(previous_origin).into());
+				}
+
+				let alpha = render_state.timestep.alpha();
+				render_state.scene.submit(renderer, alpha);
+
+				// cameras and clip planes given to the renderer are relative to
+				// the origin, debug drawing stays in world space
+				let main_pose = render_state.camera.shake.shaken(&render_state.camera.pose);
+				let main_pose = main_pose.relative_to(origin);
+				let split_screen_pose = render_state.split_screen.camera.pose.relative_to(origin);
+				let render_clipping = ClipPlanes {
+					clip_plane: clipping.clip_plane.map(|plane| plane.relative_to(origin)),
+					..clipping
+				};
+				// world space matrices for picking and debug drawing are f32
+				// anyway
+				let world_origin = Vec3::from(origin.to_vec3a());
+				let to_origin = Mat4::from_translation(-world_origin);
+
+				// split screen and stereo both draw two viewports. The left one
+				// gets its own graph, then the graph below renders the right one
+				// and composites both halves onto the surface.
+				let two_viewports =
+					render_state.stereo.enabled || render_state.split_screen.enabled;
+				let main_resolution = match two_viewports {
+					true => Viewport::Right.rect(resolution).1,
+					false => resolution,
+				};
+				render_state.texture_streamer.update(
+					renderer,
+					&render_state.scene,
+					main_pose.position.to_vec3a().into(),
+					vfov,
+					main_resolution.y,
+					&mut render_state.work_queue,
+				);
+
+				let main_aspect_ratio = main_resolution.x as f32 / main_resolution.y as f32;
+				let orthographic_height = match &render_state.orbit {
+					Some(orbit) => 2.0 * orbit.shown_distance() * (vfov.to_radians() * 0.5).tan(),
+					None => render_state.orthographic_height,
+				};
+				let project = |view| match render_state.camera_mode {
+					CameraMode::Perspective => {
+						render_clipping.camera(vfov, main_aspect_ratio, view, Self::HANDEDNESS)
+					}
+					CameraMode::Orthographic => render_clipping.orthographic_camera(
+						orthographic_height,
+						main_aspect_ratio,
+						view,
+					),
+				};
+				let camera = project(main_pose.view_matrix());
+				let split_screen_camera = project(split_screen_pose.view_matrix());
+
+				let (left_camera, main_camera) = if render_state.stereo.enabled {
+					let [left, right] = render_state.stereo.eye_cameras(
+						&main_pose,
+						vfov,
+						render_clipping.near,
+						main_aspect_ratio,
+						Self::HANDEDNESS,
+					);
+					(Some(left), right)
+				} else if render_state.split_screen.enabled {
+					(Some(camera), split_screen_camera)
+				} else {
+					(None, camera)
+				};
+
+				render_state.debug_draw.clear();
+				render_state.volume_viz.draw(
+					&mut render_state.debug_draw,
+					&[
+						VizCamera {
+							name: "main",
+							camera: camera::world_camera(camera, world_origin),
+							aspect_ratio: main_aspect_ratio,
+						},
+						VizCamera {
+							name: "split screen",
+							camera: camera::world_camera(split_screen_camera, world_origin),
+							aspect_ratio: main_aspect_ratio,
+						},
+					],
+					&[VizLight {
+						name: "sun",
+						light: &render_state.light,
+					}],
+					&camera::world_camera(main_camera, world_origin),
+					Self::HANDEDNESS,
+				);
+				render_state
+					.section
+					.draw_gizmo(&mut render_state.debug_draw);
+				render_state.measure.draw(&mut render_state.debug_draw);
+				render_state
+					.editor
+					.selection
+					.draw(&mut render_state.debug_draw, &render_state.scene);
+				render_state
+					.editor
+					.skeletons
+					.draw(&mut render_state.debug_draw, &render_state.scene);
+				if let (true, Some(plane)) = (render_state.show_clip_plane, clipping.clip_plane) {
+					// nudged to the kept side so the lines aren't clipped by the plane
+					render_state.debug_draw.plane(
+						plane.normal,
+						plane.distance - 0.001,
+						4.0,
+						Vec4::new(1.0, 0.2, 0.6, 1.0),
+					);
+				}
+
+				if let Some(left_camera) = left_camera {
+					let (_, left_resolution) = Viewport::Left.rect(resolution);
+					let left_target = render_state
+						.split_screen
+						.left_target(renderer, left_resolution);
+					render_view(
+						render_state,
+						renderer,
+						base_rendergraph,
+						&default_pbr,
+						&tonemapping_routine,
+						OffscreenView {
+							camera: left_camera,
+							motion_blur: Some(Viewport::Left),
+							to_origin,
+							resolution: left_resolution,
+							target: left_target,
+						},
+						Self::HANDEDNESS,
+					);
+				}
+
+				let aspect_ratio = main_resolution.x as f32 / main_resolution.y as f32;
+				let view_proj =
+					camera::projection_matrix(&main_camera, aspect_ratio, Self::HANDEDNESS)
+						* main_camera.view;
+				let staging = &mut render_state.staging;
+				render_state.motion_blur.update(
+					renderer,
+					staging,
+					Viewport::Right,
+					view_proj,
+					&render_state.scene,
+					&render_state.settings.graphics,
+				);
+				render_state.material_overrides.update(
+					renderer,
+					staging,
+					view_proj,
+					&render_state.scene,
+				);
+				render_state.section_caps.update(
+					renderer,
+					staging,
+					view_proj,
+					&render_state.scene,
+					&render_state.section,
+				);
+				// world space, for the debug tools
+				render_state.main_view_proj = view_proj * to_origin;
+				render_state
+					.debug_draw
+					.update(renderer, staging, render_state.main_view_proj);
+				render_state.pixel_picker.update(
+					renderer,
+					render_state.main_view_proj,
+					main_resolution,
+				);
+
+				staging.submit(renderer);
+
+				renderer.set_aspect_ratio(aspect_ratio);
+				renderer.set_camera_data(main_camera);
+
+				let (cmd_bufs, ready) = renderer.ready();
+
 				// build rendergraph
 				let mut graph = RenderGraph::new();
+				let pbr_routine = match (prepass, &render_state.pbr_without_prepass) {
+					(false, Some(routine)) => routine,
+					_ => &*default_pbr,
+				};
 
-				base_rendergraph.add_to_graph(
+				let state = add_scene_passes(
 					&mut graph,
+					base_rendergraph,
 					&ready,
-					&pbr_routine,
-					None,
-					&tonemapping_routine,
-					resolution,
-					SAMPLE_COUNT,
-					Vec4::ZERO,
-					// Vec4::new(0.1, 0.05, 0.1, 1.0),
+					pbr_routine,
+					SceneOverlays {
+						material_overrides: &render_state.material_overrides,
+						section_caps: &render_state.section_caps,
+						debug_draw: &render_state.debug_draw,
+					},
+					&render_state.render_passes,
+					main_resolution,
 				);
 
 				let surface = graph.add_surface_texture();
-				render_state
-					.egui_routine
-					.add_to_graph(&mut graph, input, surface);
+				let debug_passes = render_state
+					.render_passes
+					.is_enabled(render_passes::DEBUG_TOOLS)
+					.then_some(DebugPasses {
+						target_inspector: &render_state.target_inspector,
+						pixel_picker: &render_state.pixel_picker,
+					});
+
+				if two_viewports {
+					let right = graph.add_render_target(ldr_target(
+						render_state.surface_format,
+						main_resolution,
+					));
+					add_post_passes(
+						&mut graph,
+						state,
+						&tonemapping_routine,
+						&render_state.motion_blur,
+						&render_state.color_filter,
+						debug_passes,
+						&render_state.settings,
+						render_state.surface_format,
+						main_resolution,
+						right,
+					);
+					render_state
+						.split_screen
+						.add_composite_to_graph(&mut graph, right, surface, resolution);
+				} else {
+					add_post_passes(
+						&mut graph,
+						state,
+						&tonemapping_routine,
+						&render_state.motion_blur,
+						&render_state.color_filter,
+						debug_passes,
+						&render_state.settings,
+						render_state.surface_format,
+						main_resolution,
+						surface,
+					);
+				}
 
-				graph.execute(renderer, frame, cmd_bufs, &ready);
+				if capturing {
+					let graphics = &render_state.settings.graphics;
+					let metadata = ScreenshotMetadata {
+						scene: render_state.scene_name.clone(),
+						gpu: format!(
+							"{} ({:?})",
+							renderer.adapter_info.name, renderer.adapter_info.backend
+						),
+						settings: format!(
+							"motion blur: {}, color blindness: {}",
+							match graphics.motion_blur {
+								true => "on",
+								false => "off",
+							},
+							render_state.settings.accessibility.color_blind_mode.label()
+						),
+						frame_stats: format!(
+							"avg {:.2}ms, min {:.2}ms, max {:.2}ms",
+							render_state.stats.avg_frame_time,
+							render_state.stats.min_frame_time,
+							render_state.stats.max_frame_time
+						),
+					};
+					render_state.screenshots.add_metadata_to_graph(
+						&mut graph,
+						&metadata,
+						resolution,
+						window.scale_factor() as f32,
+						surface,
+					);
+				} else if render_state.render_passes.is_enabled(render_passes::EGUI) {
+					render_state
+						.egui_routine
+						.add_to_graph(&mut graph, input, surface);
+				}
+
+				let timings = graph.execute(renderer, frame, cmd_bufs, &ready);
+
+				// the headset's eyes go into its swapchains for the frame the
+				// update began, without motion blur, which smears head movement
+				#[cfg(feature = "vr")]
+				if let Some(mut xr) = render_state.xr.take() {
+					let eye_resolution = xr.eye_resolution;
+					let eyes = render_state.stereo.eye_cameras(
+						&main_pose,
+						vfov,
+						render_clipping.near,
+						eye_resolution.x as f32 / eye_resolution.y as f32,
+						Self::HANDEDNESS,
+					);
+					let rendered = xr.render_eyes(|eye, target| {
+						render_view(
+							render_state,
+							renderer,
+							base_rendergraph,
+							&default_pbr,
+							&tonemapping_routine,
+							OffscreenView {
+								camera: eyes[eye],
+								motion_blur: None,
+								to_origin,
+								resolution: eye_resolution,
+								target,
+							},
+							Self::HANDEDNESS,
+						)
+					});
+					match rendered {
+						Ok(()) => render_state.xr = Some(xr),
+						Err(e) => {
+							log::error!("lost the headset: {}", e);
+							render_state.stereo.tracked_eyes = None;
+						}
+					}
+				}
+				render_state.pass_budgets.record(timings);
+				let frame_time = render_state
+					.pass_budgets
+					.total_milliseconds()
+					.unwrap_or(render_state.stats.avg_frame_time);
+				render_state.render_passes.frame_time(frame_time);
+				render_state.staging.end_frame();
+				render_state.frame_pacer.end_frame(renderer);
+				if !capturing && warmup.is_none() {
+					render_state.latency_tester.presented();
+				}
+				if let Some(previous) = restore {
+					set_frame_features(render_state, previous);
+				}
+				// picks wait while the debug tools are off
+				let picked = match render_state
+					.render_passes
+					.is_enabled(render_passes::DEBUG_TOOLS)
+				{
+					true => render_state.pixel_picker.finish(renderer),
+					false => None,
+				};
+				if let Some(result) = picked {
+					render_state.measure.picked(&result);
+				}
+
+				if capturing {
+					match render_state.screenshots.save(renderer) {
+						Ok(path) => log::info!("saved screenshot to {}", path.display()),
+						Err(e) => log::error!("failed to save screenshot: {}", e),
+					}
+				}
 
 				control_flow(ControlFlow::Poll);
 			}
 
+			// the event loop is exiting, nothing runs after this
+			Event::LoopDestroyed => {
+				if let Some(render_state) = self.render_state.take() {
+					shutdown(render_state, window, renderer);
+				}
+			}
+
 			// ignore the rest
 			_ => {}
 		}
 	}
 }
 
+/// Sets up the app's window and starts it.
+pub struct AppBuilder {
+	title: String,
+	icon: Option<PathBuf>,
+	/// app id on wayland and window class on x11, which desktops group windows
+	/// and find launcher entries by
+	class: String,
+	right_handed: bool,
+}
+
+impl AppBuilder {
+	pub fn new(title: &str) -> Self {
+		Self {
+			title: title.into(),
+			icon: None,
+			class: "opal".into(),
+			right_handed: false,
+		}
+	}
+
+	/// Image asset for the window and taskbar icon, see
+	/// [`assets::try_load_texture`]. A generated icon is used without one.
+	pub fn with_icon(mut self, path: impl Into<PathBuf>) -> Self {
+		self.icon = Some(path.into());
+		self
+	}
+
+	pub fn with_class(mut self, class: &str) -> Self {
+		self.class = class.into();
+		self
+	}
+
+	/// Renders with a right handed renderer, for content authored that way.
+	pub fn with_right_handed(mut self, right_handed: bool) -> Self {
+		self.right_handed = right_handed;
+		self
+	}
+
+	pub fn run(self) {
+		let icon = match &self.icon {
+			Some(path) => WindowIcon::load(path).unwrap_or_else(|e| {
+				log::warn!("failed to load window icon {}: {}", path.display(), e);
+				WindowIcon::generated()
+			}),
+			None => WindowIcon::generated(),
+		};
+		#[allow(unused_mut)]
+		let mut window = WindowBuilder::new().with_title(&self.title);
+		#[cfg(any(
+			target_os = "linux",
+			target_os = "dragonfly",
+			target_os = "freebsd",
+			target_os = "netbsd",
+			target_os = "openbsd"
+		))]
+		{
+			use winit::platform::unix::WindowBuilderExtUnix;
+			window = window
+				.with_class(self.class.clone(), self.class.clone())
+				.with_app_id(self.class.clone());
+		}
+		match self.right_handed {
+			true => rend3_framework::start(OpalApp::<RightHanded>::new(icon), window),
+			false => rend3_framework::start(OpalApp::<LeftHanded>::new(icon), window),
+		}
+	}
+}
+
+/// Starts the test app. Passing `--right-handed` renders with a right handed
+/// renderer, for content authored that way.
 pub fn main() {
-	let app = OpalApp::new();
-	rend3_framework::start(app, WindowBuilder::new().with_title("Opal Test"));
+	AppBuilder::new("Opal Test")
+		.with_right_handed(std::env::args().any(|arg| arg == "--right-handed"))
+		.run();
 }
@@ -0,0 +1,445 @@
+use std::fs;
+use std::path::Path;
+
+use glam::{Mat4, UVec3, Vec3, Vec4};
+use rend3::types::{
+	DirectionalLight, DirectionalLightHandle, Handedness, MaterialHandle, Object, ObjectMeshKind,
+};
+use rend3::Renderer;
+use rend3_routine::pbr::{AlbedoComponent, PbrMaterial};
+use serde_json::Value;
+
+use crate::assets::{self, AssetError};
+use crate::debug::ColorSpaceAudit;
+use crate::scene::{ObjectId, Scene};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+	Cube,
+	Sphere,
+}
+
+impl Shape {
+	fn from_json(value: &Value) -> Option<Self> {
+		match value.as_str()? {
+			"cube" => Some(Shape::Cube),
+			"sphere" => Some(Shape::Sphere),
+			_ => None,
+		}
+	}
+}
+
+/// A number that's either the same for every object or drawn for each from
+/// `[min, max]`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Param {
+	Fixed(f32),
+	Range(f32, f32),
+}
+
+impl Param {
+	fn from_json(value: &Value) -> Option<Self> {
+		if let Some(value) = value.as_f64() {
+			return Some(Param::Fixed(value as f32));
+		}
+		match value.as_array()?.as_slice() {
+			[min, max] => Some(Param::Range(min.as_f64()? as f32, max.as_f64()? as f32)),
+			_ => None,
+		}
+	}
+
+	fn sample(self, rng: &mut Rng) -> f32 {
+		match self {
+			Param::Fixed(value) => value,
+			Param::Range(min, max) => min + (max - min) * rng.next_f32(),
+		}
+	}
+}
+
+/// `albedo` is an rgb or rgba color, or `"random"` for a random opaque one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MaterialSpec {
+	/// `None` for a random color per object
+	pub albedo: Option<Vec4>,
+	pub metallic: Param,
+	pub roughness: Param,
+}
+
+impl Default for MaterialSpec {
+	fn default() -> Self {
+		Self {
+			albedo: Some(Vec4::new(0.8, 0.8, 0.8, 1.0)),
+			metallic: Param::Fixed(0.0),
+			roughness: Param::Fixed(0.5),
+		}
+	}
+}
+
+impl MaterialSpec {
+	fn from_json(value: &Value) -> Option<Self> {
+		let default = Self::default();
+		let albedo = match value.get("albedo") {
+			None => default.albedo,
+			Some(Value::String(random)) if random == "random" => None,
+			Some(color) => Some(match color.as_array()?.as_slice() {
+				[r, g, b] => Vec4::new(
+					r.as_f64()? as f32,
+					g.as_f64()? as f32,
+					b.as_f64()? as f32,
+					1.0,
+				),
+				[r, g, b, a] => Vec4::new(
+					r.as_f64()? as f32,
+					g.as_f64()? as f32,
+					b.as_f64()? as f32,
+					a.as_f64()? as f32,
+				),
+				_ => return None,
+			}),
+		};
+		let param = |key: &str, default: Param| match value.get(key) {
+			Some(param) => Param::from_json(param),
+			None => Some(default),
+		};
+		Some(Self {
+			albedo,
+			metallic: param("metallic", default.metallic)?,
+			roughness: param("roughness", default.roughness)?,
+		})
+	}
+
+	/// Whether every object gets the same material, so one can be shared.
+	fn is_fixed(&self) -> bool {
+		self.albedo.is_some()
+			&& matches!(self.metallic, Param::Fixed(_))
+			&& matches!(self.roughness, Param::Fixed(_))
+	}
+
+	fn sample(&self, rng: &mut Rng) -> PbrMaterial {
+		let albedo = self
+			.albedo
+			.unwrap_or_else(|| Vec4::new(rng.next_f32(), rng.next_f32(), rng.next_f32(), 1.0));
+		PbrMaterial {
+			albedo: AlbedoComponent::Value(albedo),
+			metallic_factor: Some(self.metallic.sample(rng)),
+			roughness_factor: Some(self.roughness.sample(rng)),
+			..PbrMaterial::default()
+		}
+	}
+}
+
+/// `count` objects along each axis, `spacing` apart between centers and
+/// starting at `origin`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridSpec {
+	pub name: String,
+	pub shape: Shape,
+	pub count: UVec3,
+	pub spacing: f32,
+	pub origin: Vec3,
+	/// half the size of each object, the meshes span -1 to 1
+	pub scale: f32,
+	pub material: MaterialSpec,
+}
+
+impl GridSpec {
+	fn from_json(value: &Value) -> Option<Self> {
+		let vector = |key: &str| -> Option<Option<[f64; 3]>> {
+			match value.get(key) {
+				None => Some(None),
+				Some(vector) => match vector.as_array()?.as_slice() {
+					[x, y, z] => Some(Some([x.as_f64()?, y.as_f64()?, z.as_f64()?])),
+					_ => None,
+				},
+			}
+		};
+		let count = vector("count")?.unwrap_or([1.0; 3]);
+		let origin = vector("origin")?.unwrap_or([0.0; 3]);
+		Some(Self {
+			name: value.get("name")?.as_str()?.into(),
+			shape: match value.get("shape") {
+				Some(shape) => Shape::from_json(shape)?,
+				None => Shape::Cube,
+			},
+			count: UVec3::new(count[0] as u32, count[1] as u32, count[2] as u32),
+			spacing: value.get("spacing").and_then(Value::as_f64).unwrap_or(3.0) as f32,
+			origin: Vec3::new(origin[0] as f32, origin[1] as f32, origin[2] as f32),
+			scale: value.get("scale").and_then(Value::as_f64).unwrap_or(1.0) as f32,
+			material: match value.get("material") {
+				Some(material) => MaterialSpec::from_json(material)?,
+				None => MaterialSpec::default(),
+			},
+		})
+	}
+}
+
+fn light_from_json(value: &Value) -> Option<DirectionalLight> {
+	let vector = |key: &str| -> Option<Vec3> {
+		match value.get(key)?.as_array()?.as_slice() {
+			[x, y, z] => Some(Vec3::new(
+				x.as_f64()? as f32,
+				y.as_f64()? as f32,
+				z.as_f64()? as f32,
+			)),
+			_ => None,
+		}
+	};
+	Some(DirectionalLight {
+		color: match value.get("color") {
+			Some(_) => vector("color")?,
+			None => Vec3::ONE,
+		},
+		intensity: value
+			.get("intensity")
+			.and_then(Value::as_f64)
+			.unwrap_or(10.0) as f32,
+		direction: vector("direction")?,
+		distance: value
+			.get("distance")
+			.and_then(Value::as_f64)
+			.unwrap_or(400.0) as f32,
+	})
+}
+
+/// A json description of generated content, like a 50x50 grid of cubes with
+/// random materials, for benchmarks and feature demos that are the same on
+/// every run:
+///
+/// ```json
+/// {
+///   "seed": 1,
+///   "grids": [{
+///     "name": "cubes", "shape": "cube", "count": [50, 1, 50], "spacing": 3,
+///     "material": { "albedo": "random", "metallic": [0, 1], "roughness": [0.1, 1] }
+///   }],
+///   "lights": [{ "direction": [-1, -4, 2], "intensity": 10 }]
+/// }
+/// ```
+///
+/// Lights are directional, the only kind the renderer has.
+#[derive(Debug, Clone)]
+pub struct SceneManifest {
+	/// random values are the same for the same seed
+	pub seed: u64,
+	pub grids: Vec<GridSpec>,
+	pub lights: Vec<DirectionalLight>,
+}
+
+impl SceneManifest {
+	pub fn load(path: &Path) -> Result<Self, AssetError> {
+		let text = fs::read_to_string(path)?;
+		let value: Value =
+			serde_json::from_str(&text).map_err(|e| AssetError::Invalid(e.to_string()))?;
+		Self::from_json(&value).ok_or_else(|| AssetError::Invalid("invalid scene manifest".into()))
+	}
+
+	fn from_json(value: &Value) -> Option<Self> {
+		let list = |key: &str| match value.get(key) {
+			Some(list) => list.as_array().map(Vec::as_slice),
+			None => Some(&[][..]),
+		};
+		Some(Self {
+			seed: value.get("seed").and_then(Value::as_u64).unwrap_or(0),
+			grids: list("grids")?
+				.iter()
+				.map(GridSpec::from_json)
+				.collect::<Option<_>>()?,
+			lights: list("lights")?
+				.iter()
+				.map(light_from_json)
+				.collect::<Option<_>>()?,
+		})
+	}
+
+	pub fn object_count(&self) -> usize {
+		self.grids
+			.iter()
+			.map(|grid| (grid.count.x * grid.count.y * grid.count.z) as usize)
+			.sum()
+	}
+
+	/// Adds the manifest's objects and lights. Grids with a fixed material
+	/// share one, others get a material per object.
+	pub fn build(
+		&self,
+		renderer: &Renderer,
+		scene: &mut Scene,
+		color_audit: &mut ColorSpaceAudit,
+		handedness: Handedness,
+	) -> ManifestContent {
+		let mut objects = Vec::with_capacity(self.object_count());
+		let cube = scene.add_mesh(renderer, assets::cube(handedness));
+		let sphere = scene.add_mesh(renderer, assets::sphere(32, 16, handedness));
+
+		for (index, grid) in self.grids.iter().enumerate() {
+			// each grid draws from its own sequence, so editing one grid
+			// doesn't change the others
+			let mut rng = Rng::new(self.seed.wrapping_add(index as u64));
+			let mesh = match grid.shape {
+				Shape::Cube => cube.clone(),
+				Shape::Sphere => sphere.clone(),
+			};
+			let shared: Option<MaterialHandle> = grid.material.is_fixed().then(|| {
+				let material = grid.material.sample(&mut rng);
+				color_audit.add_material(renderer, &grid.name, material)
+			});
+			for z in 0..grid.count.z {
+				for y in 0..grid.count.y {
+					for x in 0..grid.count.x {
+						let name = format!("{}_{}_{}_{}", grid.name, x, y, z);
+						let material = match &shared {
+							Some(material) => material.clone(),
+							None => {
+								let material = grid.material.sample(&mut rng);
+								color_audit.add_material(renderer, &name, material)
+							}
+						};
+						let position = grid.origin + UVec3::new(x, y, z).as_vec3() * grid.spacing;
+						let transform = Mat4::from_scale_rotation_translation(
+							Vec3::splat(grid.scale),
+							Default::default(),
+							position,
+						);
+						let object = Object {
+							mesh_kind: ObjectMeshKind::Static(mesh.clone()),
+							material,
+							transform,
+						};
+						let id = scene.add_object(renderer, &name, object);
+						objects.push(id);
+					}
+				}
+			}
+		}
+
+		let lights = self
+			.lights
+			.iter()
+			.map(|light| renderer.add_directional_light(light.clone()))
+			.collect();
+		ManifestContent { objects, lights }
+	}
+}
+
+/// What [`SceneManifest::build`] added, kept so it can be removed again.
+pub struct ManifestContent {
+	pub objects: Vec<ObjectId>,
+	/// the lights are removed when their handles drop
+	pub lights: Vec<DirectionalLightHandle>,
+}
+
+impl ManifestContent {
+	pub fn remove(self, scene: &mut Scene) {
+		for id in self.objects {
+			scene.remove_object(id);
+		}
+	}
+}
+
+/// splitmix64, enough for scattering material values and the same on every
+/// platform.
+struct Rng(u64);
+
+impl Rng {
+	fn new(seed: u64) -> Self {
+		Self(seed)
+	}
+
+	fn next_u64(&mut self) -> u64 {
+		self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+		let mut z = self.0;
+		z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+		z ^ (z >> 31)
+	}
+
+	/// Uniform in `[0, 1)`.
+	fn next_f32(&mut self) -> f32 {
+		(self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+	}
+}
+
+/// Builds a manifest file into the scene, replacing what the last build
+/// added.
+pub struct ManifestWindow {
+	pub open: bool,
+	path: String,
+	content: Option<ManifestContent>,
+	status: Option<String>,
+}
+
+impl Default for ManifestWindow {
+	fn default() -> Self {
+		Self {
+			open: false,
+			path: "manifest.json".into(),
+			content: None,
+			status: None,
+		}
+	}
+}
+
+impl ManifestWindow {
+	/// Returns true on the frame a scene is built.
+	pub fn window(
+		&mut self,
+		ctx: &egui::CtxRef,
+		renderer: &Renderer,
+		scene: &mut Scene,
+		color_audit: &mut ColorSpaceAudit,
+		handedness: Handedness,
+	) -> bool {
+		let mut built = false;
+		let mut open = self.open;
+		egui::Window::new("scene manifest")
+			.open(&mut open)
+			.resizable(false)
+			.show(ctx, |ui| {
+				ui.horizontal(|ui| {
+					ui.label("manifest");
+					ui.text_edit_singleline(&mut self.path);
+				});
+				ui.horizontal(|ui| {
+					if ui.button("build").clicked() {
+						if let Some(content) = self.content.take() {
+							content.remove(scene);
+						}
+						self.status = Some(match SceneManifest::load(Path::new(&self.path)) {
+							Ok(manifest) => {
+								let content =
+									manifest.build(renderer, scene, color_audit, handedness);
+								let status = format!(
+									"built {} objects and {} lights",
+									content.objects.len(),
+									content.lights.len()
+								);
+								self.content = Some(content);
+								built = true;
+								status
+							}
+							Err(e) => format!("failed to load {}: {}", self.path, e),
+						});
+					}
+					if ui
+						.add_enabled(self.content.is_some(), egui::Button::new("clear"))
+						.clicked()
+					{
+						if let Some(content) = self.content.take() {
+							content.remove(scene);
+						}
+						self.status = None;
+					}
+				});
+				if let Some(status) = &self.status {
+					ui.label(status);
+				}
+			});
+		self.open = open;
+		built
+	}
+
+	/// The manifest file the window builds from.
+	pub fn path(&self) -> &str {
+		&self.path
+	}
+}
@@ -0,0 +1,320 @@
+use std::borrow::Cow;
+use std::num::NonZeroU64;
+
+use glam::{Mat4, Vec3};
+use rend3::graph::{
+	DepthHandle, RenderGraph, RenderPassDepthTarget, RenderPassTarget, RenderPassTargets,
+};
+use rend3::util::bind_merge::{BindGroupBuilder, BindGroupLayoutBuilder};
+use rend3::Renderer;
+use rend3_routine::base::BaseRenderGraphIntermediateState;
+use wgpu::{
+	BindGroup, BindGroupLayout, BindingType, BlendComponent, BlendFactor, BlendOperation,
+	BlendState, Buffer, BufferAddress, BufferBindingType, BufferDescriptor, BufferUsages, Color,
+	ColorTargetState, ColorWrites, CompareFunction, DepthBiasState, DepthStencilState,
+	FragmentState, MultisampleState, PipelineLayoutDescriptor, PrimitiveState, RenderPipeline,
+	RenderPipelineDescriptor, ShaderModuleDescriptor, ShaderSource, ShaderStages, StencilState,
+	TextureFormat, VertexAttribute, VertexBufferLayout, VertexFormat, VertexState, VertexStepMode,
+};
+
+use crate::mesh_cache::MeshCache;
+use crate::scene::Scene;
+use crate::staging::StagingPool;
+
+/// matrix, tint and emissive, padded to the dynamic offset alignment
+const OBJECT_UNIFORM_SIZE: BufferAddress = 96;
+const OBJECT_UNIFORM_STRIDE: BufferAddress = 256;
+
+/// Per-object adjustment of the lit color, applied without creating a new
+/// material.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MaterialOverride {
+	/// multiplies the lit color
+	pub tint: Vec3,
+	/// color added on top, scaled by `emissive_strength`
+	pub emissive: Vec3,
+	pub emissive_strength: f32,
+}
+
+impl Default for MaterialOverride {
+	fn default() -> Self {
+		Self {
+			tint: Vec3::ONE,
+			emissive: Vec3::ONE,
+			emissive_strength: 0.0,
+		}
+	}
+}
+
+impl MaterialOverride {
+	/// Brightens an object, for selection and hover feedback.
+	pub fn highlight(color: Vec3) -> Self {
+		Self {
+			tint: Vec3::ONE,
+			emissive: color,
+			emissive_strength: 0.5,
+		}
+	}
+}
+
+struct Draw {
+	mesh: usize,
+	offset: u32,
+}
+
+/// Draws the [`MaterialOverride`] of each scene object over the forward pass.
+///
+/// rend3 materials can't take per-object parameters, so objects with an
+/// override are drawn a second time with a small uniform block: once
+/// multiplying the hdr color by the tint, then once adding the emission.
+/// Expects a single sampled depth buffer.
+pub struct MaterialOverrideRoutine {
+	tint_pipeline: RenderPipeline,
+	emissive_pipeline: RenderPipeline,
+	bgl: BindGroupLayout,
+	uniforms: Buffer,
+	bg: BindGroup,
+	capacity: usize,
+
+	meshes: MeshCache,
+	draws: Vec<Draw>,
+}
+
+impl MaterialOverrideRoutine {
+	pub fn new(renderer: &Renderer) -> Self {
+		let device = &renderer.device;
+
+		let bgl = BindGroupLayoutBuilder::new()
+			.append(
+				ShaderStages::VERTEX_FRAGMENT,
+				BindingType::Buffer {
+					ty: BufferBindingType::Uniform,
+					has_dynamic_offset: true,
+					min_binding_size: NonZeroU64::new(OBJECT_UNIFORM_SIZE),
+				},
+				None,
+			)
+			.build(device, Some("material override bgl"));
+
+		let module = device.create_shader_module(&ShaderModuleDescriptor {
+			label: Some("material override"),
+			source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("material_override.wgsl"))),
+		});
+
+		let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+			label: Some("material override pass"),
+			bind_group_layouts: &[&bgl],
+			push_constant_ranges: &[],
+		});
+
+		let create_pipeline = |label, entry_point, blend| {
+			device.create_render_pipeline(&RenderPipelineDescriptor {
+				label: Some(label),
+				layout: Some(&layout),
+				vertex: VertexState {
+					module: &module,
+					entry_point: "vs_main",
+					buffers: &[VertexBufferLayout {
+						array_stride: 12,
+						step_mode: VertexStepMode::Vertex,
+						attributes: &[VertexAttribute {
+							format: VertexFormat::Float32x3,
+							offset: 0,
+							shader_location: 0,
+						}],
+					}],
+				},
+				primitive: PrimitiveState::default(),
+				depth_stencil: Some(depth_state()),
+				multisample: MultisampleState::default(),
+				fragment: Some(FragmentState {
+					module: &module,
+					entry_point,
+					targets: &[ColorTargetState {
+						format: TextureFormat::Rgba16Float,
+						blend: Some(blend),
+						write_mask: ColorWrites::all(),
+					}],
+				}),
+				multiview: None,
+			})
+		};
+
+		let keep_alpha = BlendComponent {
+			src_factor: BlendFactor::Zero,
+			dst_factor: BlendFactor::One,
+			operation: BlendOperation::Add,
+		};
+		let tint_pipeline = create_pipeline(
+			"material override tint",
+			"fs_tint",
+			BlendState {
+				color: BlendComponent {
+					src_factor: BlendFactor::Zero,
+					dst_factor: BlendFactor::Src,
+					operation: BlendOperation::Add,
+				},
+				alpha: keep_alpha,
+			},
+		);
+		let emissive_pipeline = create_pipeline(
+			"material override emissive",
+			"fs_emissive",
+			BlendState {
+				color: BlendComponent {
+					src_factor: BlendFactor::One,
+					dst_factor: BlendFactor::One,
+					operation: BlendOperation::Add,
+				},
+				alpha: keep_alpha,
+			},
+		);
+
+		let capacity = 16;
+		let (uniforms, bg) = create_uniforms(renderer, &bgl, capacity);
+
+		Self {
+			tint_pipeline,
+			emissive_pipeline,
+			bgl,
+			uniforms,
+			bg,
+			capacity,
+			meshes: MeshCache::default(),
+			draws: Vec::new(),
+		}
+	}
+
+	/// Uploads the overrides for the viewport about to be rendered. Call once
+	/// per viewport per frame, right before building its graph.
+	pub fn update(
+		&mut self,
+		renderer: &Renderer,
+		staging: &mut StagingPool,
+		view_proj: Mat4,
+		scene: &Scene,
+	) {
+		self.draws.clear();
+		let mut data = Vec::new();
+		for (_, object) in scene.visible() {
+			let (material_override, geometry) = match (&object.material_override, &object.geometry)
+			{
+				(Some(material_override), Some(geometry)) => (material_override, geometry),
+				_ => continue,
+			};
+
+			self.meshes.upload(renderer, geometry);
+			self.draws.push(Draw {
+				mesh: geometry.key,
+				offset: data.len() as u32,
+			});
+
+			let (_, transform) = object.rendered_transforms();
+			let emissive = material_override.emissive * material_override.emissive_strength;
+			data.extend(
+				(view_proj * transform)
+					.to_cols_array()
+					.iter()
+					.flat_map(|v| v.to_ne_bytes()),
+			);
+			data.extend(
+				[material_override.tint.extend(1.0), emissive.extend(0.0)]
+					.iter()
+					.flat_map(|v| v.to_array())
+					.flat_map(|v| v.to_ne_bytes()),
+			);
+			data.resize(
+				data.len() + (OBJECT_UNIFORM_STRIDE - OBJECT_UNIFORM_SIZE) as usize,
+				0,
+			);
+		}
+
+		if self.draws.len() > self.capacity {
+			self.capacity = self.draws.len().next_power_of_two();
+			let (uniforms, bg) = create_uniforms(renderer, &self.bgl, self.capacity);
+			self.uniforms = uniforms;
+			self.bg = bg;
+		}
+		if !data.is_empty() {
+			staging.write(renderer, &self.uniforms, 0, &data);
+		}
+	}
+
+	/// Draws the overrides into the scene's hdr color. Must be added after
+	/// forward rendering.
+	pub fn add_to_graph<'node>(
+		&'node self,
+		graph: &mut RenderGraph<'node>,
+		state: &BaseRenderGraphIntermediateState,
+	) {
+		let mut builder = graph.add_node("Material Overrides");
+
+		let color_handle = builder.add_render_target_output(state.color);
+		let depth_handle = builder.add_render_target_input(state.depth);
+
+		let rpass_handle = builder.add_renderpass(RenderPassTargets {
+			targets: vec![RenderPassTarget {
+				color: color_handle,
+				clear: Color::BLACK,
+				resolve: None,
+			}],
+			depth_stencil: Some(RenderPassDepthTarget {
+				target: DepthHandle::RenderTarget(depth_handle),
+				depth_clear: Some(0.0),
+				stencil_clear: None,
+			}),
+		});
+
+		let pt_handle = builder.passthrough_ref(self);
+
+		builder.build(
+			move |pt, _renderer, encoder_or_pass, _temps, _ready, _graph_data| {
+				let this = pt.get(pt_handle);
+				let rpass = encoder_or_pass.get_rpass(rpass_handle);
+
+				for pipeline in [&this.tint_pipeline, &this.emissive_pipeline] {
+					rpass.set_pipeline(pipeline);
+					for draw in &this.draws {
+						if let Some(mesh) = this.meshes.get(draw.mesh) {
+							rpass.set_bind_group(0, &this.bg, &[draw.offset]);
+							mesh.draw(rpass);
+						}
+					}
+				}
+			},
+		);
+	}
+}
+
+/// Tests against the scene depth without writing to it, pulled towards the
+/// camera so surfaces pass against their own depth.
+fn depth_state() -> DepthStencilState {
+	DepthStencilState {
+		format: TextureFormat::Depth32Float,
+		depth_write_enabled: false,
+		depth_compare: CompareFunction::GreaterEqual,
+		stencil: StencilState::default(),
+		bias: DepthBiasState {
+			constant: 2,
+			slope_scale: 1.0,
+			clamp: 0.0,
+		},
+	}
+}
+
+fn create_uniforms(
+	renderer: &Renderer,
+	bgl: &BindGroupLayout,
+	capacity: usize,
+) -> (Buffer, BindGroup) {
+	let buffer = renderer.device.create_buffer(&BufferDescriptor {
+		label: Some("material override uniforms"),
+		size: capacity as BufferAddress * OBJECT_UNIFORM_STRIDE,
+		usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+		mapped_at_creation: false,
+	});
+	let bg = BindGroupBuilder::new()
+		.append_buffer_with_size(&buffer, OBJECT_UNIFORM_SIZE)
+		.build(&renderer.device, Some("material override bg"), bgl);
+	(buffer, bg)
+}
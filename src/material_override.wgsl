@@ -0,0 +1,29 @@
+// Applies per-object tint and emission on top of the lit scene.
+
+struct OverrideUniforms {
+	view_proj_model: mat4x4<f32>;
+	// rgb multiplier for the lit color
+	tint: vec4<f32>;
+	// rgb emission added after tinting, already scaled by strength
+	emissive: vec4<f32>;
+};
+
+[[group(0), binding(0)]]
+var<uniform> object: OverrideUniforms;
+
+[[stage(vertex)]]
+fn vs_main([[location(0)]] position: vec3<f32>) -> [[builtin(position)]] vec4<f32> {
+	return object.view_proj_model * vec4<f32>(position, 1.0);
+}
+
+// blended as dst * src
+[[stage(fragment)]]
+fn fs_tint() -> [[location(0)]] vec4<f32> {
+	return vec4<f32>(object.tint.rgb, 1.0);
+}
+
+// blended as dst + src
+[[stage(fragment)]]
+fn fs_emissive() -> [[location(0)]] vec4<f32> {
+	return vec4<f32>(object.emissive.rgb, 0.0);
+}
@@ -0,0 +1,200 @@
+use glam::{const_vec4, Mat4, UVec2, Vec3, Vec4};
+
+use crate::camera;
+use crate::debug::{clicked_pixel, DebugDraw, PickResult};
+
+const LINE_COLOR: Vec4 = const_vec4!([1.0, 1.0, 0.2, 1.0]);
+
+/// Unit lengths are shown in. The scene itself is in meters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LengthUnit {
+	Meters,
+	Centimeters,
+	Feet,
+}
+
+impl LengthUnit {
+	pub const ALL: [LengthUnit; 3] = [
+		LengthUnit::Meters,
+		LengthUnit::Centimeters,
+		LengthUnit::Feet,
+	];
+
+	pub fn label(&self) -> &'static str {
+		match self {
+			LengthUnit::Meters => "m",
+			LengthUnit::Centimeters => "cm",
+			LengthUnit::Feet => "ft",
+		}
+	}
+
+	pub fn from_meters(&self, meters: f32) -> f32 {
+		match self {
+			LengthUnit::Meters => meters,
+			LengthUnit::Centimeters => meters * 100.0,
+			LengthUnit::Feet => meters / 0.3048,
+		}
+	}
+
+	pub fn format(&self, meters: f32) -> String {
+		format!("{:.3} {}", self.from_meters(meters), self.label())
+	}
+}
+
+/// What a measurement is made of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeasureMode {
+	/// between two points
+	Distance,
+	/// at the second of three points
+	Angle,
+}
+
+impl MeasureMode {
+	fn points(&self) -> usize {
+		match self {
+			MeasureMode::Distance => 2,
+			MeasureMode::Angle => 3,
+		}
+	}
+}
+
+/// Measures distances and angles between points clicked on geometry.
+///
+/// Clicks are resolved through the [`crate::debug::PixelPicker`] readback, so
+/// each point lands a frame after the click. Clicking the background is
+/// ignored and clicking after a finished measurement starts a new one.
+pub struct MeasureTool {
+	/// the tool takes clicks while its window is open
+	pub open: bool,
+	pub mode: MeasureMode,
+	pub unit: LengthUnit,
+
+	points: Vec<Vec3>,
+	awaiting_pick: bool,
+}
+
+impl Default for MeasureTool {
+	fn default() -> Self {
+		Self {
+			open: false,
+			mode: MeasureMode::Distance,
+			unit: LengthUnit::Meters,
+			points: Vec::new(),
+			awaiting_pick: false,
+		}
+	}
+}
+
+impl MeasureTool {
+	pub fn window(&mut self, ctx: &egui::CtxRef) {
+		let mut open = self.open;
+		egui::Window::new("measure")
+			.open(&mut open)
+			.resizable(false)
+			.show(ctx, |ui| {
+				ui.horizontal(|ui| {
+					let previous = self.mode;
+					ui.radio_value(&mut self.mode, MeasureMode::Distance, "distance");
+					ui.radio_value(&mut self.mode, MeasureMode::Angle, "angle");
+					if self.mode != previous {
+						self.points.clear();
+					}
+				});
+				egui::ComboBox::from_label("units")
+					.selected_text(self.unit.label())
+					.show_ui(ui, |ui| {
+						for unit in LengthUnit::ALL {
+							ui.selectable_value(&mut self.unit, unit, unit.label());
+						}
+					});
+				ui.label(format!("click {} points on geometry", self.mode.points()));
+				if let Some(text) = self.result() {
+					ui.label(text);
+				}
+				if ui.button("clear").clicked() {
+					self.points.clear();
+				}
+			});
+		self.open = open;
+	}
+
+	/// Pixel to pick when the viewport, given as `(offset, size)` in physical
+	/// pixels, was clicked this frame.
+	pub fn click(&mut self, ctx: &egui::CtxRef, viewport: (UVec2, UVec2)) -> Option<UVec2> {
+		if !self.open {
+			return None;
+		}
+		let pixel = clicked_pixel(ctx, viewport)?;
+		self.awaiting_pick = true;
+		Some(pixel)
+	}
+
+	/// Adds the point of a pick requested by [`Self::click`].
+	pub fn picked(&mut self, result: &PickResult) {
+		if !std::mem::take(&mut self.awaiting_pick) {
+			return;
+		}
+		let position = match result.world_position {
+			Some(position) => position,
+			None => return,
+		};
+		if self.points.len() >= self.mode.points() {
+			self.points.clear();
+		}
+		self.points.push(position);
+	}
+
+	/// Text for the finished measurement.
+	pub fn result(&self) -> Option<String> {
+		match (self.mode, self.points.as_slice()) {
+			(MeasureMode::Distance, [a, b]) => Some(self.unit.format(a.distance(*b))),
+			(MeasureMode::Angle, [a, vertex, b]) => {
+				let angle = (*a - *vertex).angle_between(*b - *vertex).to_degrees();
+				Some(format!("{:.2}°", angle))
+			}
+			_ => None,
+		}
+	}
+
+	/// Queues the points and the lines between them.
+	pub fn draw(&self, debug_draw: &mut DebugDraw) {
+		for point in &self.points {
+			for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+				debug_draw.line(*point - axis * 0.05, *point + axis * 0.05, LINE_COLOR);
+			}
+		}
+		for pair in self.points.windows(2) {
+			debug_draw.line(pair[0], pair[1], LINE_COLOR);
+		}
+	}
+
+	/// Paints the measurement next to the geometry, behind the ui windows.
+	/// `view_proj` and `viewport` describe the main viewport as it was last
+	/// rendered.
+	pub fn labels(&self, ctx: &egui::CtxRef, view_proj: Mat4, viewport: (UVec2, UVec2)) {
+		let (text, anchor) = match (self.result(), self.points.as_slice()) {
+			(Some(text), [a, b]) => (text, (*a + *b) * 0.5),
+			(Some(text), [_, vertex, _]) => (text, *vertex),
+			_ => return,
+		};
+		let pixel = match camera::world_to_screen(view_proj, viewport, anchor) {
+			Some(pixel) => pixel / ctx.pixels_per_point(),
+			None => return,
+		};
+
+		let painter = ctx.layer_painter(egui::LayerId::background());
+		let background = painter.add(egui::Shape::Noop);
+		let rect = painter.text(
+			egui::pos2(pixel.x, pixel.y),
+			egui::Align2::CENTER_BOTTOM,
+			text,
+			egui::TextStyle::Body,
+			egui::Color32::WHITE,
+		);
+		painter.set(
+			background,
+			egui::Shape::rect_filled(rect.expand(3.0), 2.0, egui::Color32::from_black_alpha(180)),
+		);
+	}
+}
@@ -0,0 +1,72 @@
+use rend3::util::typedefs::FastHashMap;
+use rend3::Renderer;
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{Buffer, IndexFormat, RenderPass};
+
+use crate::scene::MeshGeometry;
+
+/// Vertex positions and indices of a mesh on the gpu.
+pub struct GpuMesh {
+	pub vertices: Buffer,
+	pub indices: Buffer,
+	pub index_count: u32,
+}
+
+impl GpuMesh {
+	/// Binds the buffers to vertex slot 0 and draws the mesh once.
+	pub fn draw<'a>(&'a self, rpass: &mut RenderPass<'a>) {
+		rpass.set_vertex_buffer(0, self.vertices.slice(..));
+		rpass.set_index_buffer(self.indices.slice(..), IndexFormat::Uint32);
+		rpass.draw_indexed(0..self.index_count, 0, 0..1);
+	}
+}
+
+/// Gpu copies of [`MeshGeometry`] for passes that draw scene objects with
+/// their own pipelines.
+#[derive(Default)]
+pub struct MeshCache {
+	meshes: FastHashMap<usize, GpuMesh>,
+}
+
+impl MeshCache {
+	/// Uploads the geometry if it isn't already on the gpu.
+	pub fn upload(&mut self, renderer: &Renderer, geometry: &MeshGeometry) {
+		if self.meshes.contains_key(&geometry.key) {
+			return;
+		}
+
+		let vertices: Vec<u8> = geometry
+			.positions
+			.iter()
+			.flat_map(|p| p.to_array())
+			.flat_map(|v| v.to_ne_bytes())
+			.collect();
+		let indices: Vec<u8> = geometry
+			.indices
+			.iter()
+			.flat_map(|i| i.to_ne_bytes())
+			.collect();
+
+		let device = &renderer.device;
+		self.meshes.insert(
+			geometry.key,
+			GpuMesh {
+				vertices: device.create_buffer_init(&BufferInitDescriptor {
+					label: Some("mesh cache vertices"),
+					contents: &vertices,
+					usage: wgpu::BufferUsages::VERTEX,
+				}),
+				indices: device.create_buffer_init(&BufferInitDescriptor {
+					label: Some("mesh cache indices"),
+					contents: &indices,
+					usage: wgpu::BufferUsages::INDEX,
+				}),
+				index_count: geometry.indices.len() as u32,
+			},
+		);
+	}
+
+	pub fn get(&self, key: usize) -> Option<&GpuMesh> {
+		self.meshes.get(&key)
+	}
+}
@@ -0,0 +1,127 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde_json::Value;
+
+/// Value of a custom property on an object.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetadataValue {
+	String(String),
+	Number(f64),
+	Bool(bool),
+}
+
+impl MetadataValue {
+	pub fn kind(&self) -> MetadataKind {
+		match self {
+			MetadataValue::String(_) => MetadataKind::String,
+			MetadataValue::Number(_) => MetadataKind::Number,
+			MetadataValue::Bool(_) => MetadataKind::Bool,
+		}
+	}
+
+	pub fn as_str(&self) -> Option<&str> {
+		match self {
+			MetadataValue::String(value) => Some(value),
+			_ => None,
+		}
+	}
+
+	pub fn as_number(&self) -> Option<f64> {
+		match self {
+			MetadataValue::Number(value) => Some(*value),
+			_ => None,
+		}
+	}
+
+	pub fn as_bool(&self) -> Option<bool> {
+		match self {
+			MetadataValue::Bool(value) => Some(*value),
+			_ => None,
+		}
+	}
+
+	pub fn to_json(&self) -> Value {
+		match self {
+			MetadataValue::String(value) => Value::from(value.as_str()),
+			MetadataValue::Number(value) => Value::from(*value),
+			MetadataValue::Bool(value) => Value::from(*value),
+		}
+	}
+
+	/// `None` for json values other than strings, numbers and bools.
+	pub fn from_json(value: &Value) -> Option<Self> {
+		match value {
+			Value::String(value) => Some(MetadataValue::String(value.clone())),
+			Value::Number(value) => value.as_f64().map(MetadataValue::Number),
+			Value::Bool(value) => Some(MetadataValue::Bool(*value)),
+			_ => None,
+		}
+	}
+}
+
+impl fmt::Display for MetadataValue {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			MetadataValue::String(value) => write!(f, "{}", value),
+			MetadataValue::Number(value) => write!(f, "{}", value),
+			MetadataValue::Bool(value) => write!(f, "{}", value),
+		}
+	}
+}
+
+/// Type of a [`MetadataValue`], for picking one in the ui.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataKind {
+	String,
+	Number,
+	Bool,
+}
+
+impl MetadataKind {
+	pub const ALL: [MetadataKind; 3] = [
+		MetadataKind::String,
+		MetadataKind::Number,
+		MetadataKind::Bool,
+	];
+
+	pub fn label(&self) -> &'static str {
+		match self {
+			MetadataKind::String => "string",
+			MetadataKind::Number => "number",
+			MetadataKind::Bool => "bool",
+		}
+	}
+
+	pub fn default_value(&self) -> MetadataValue {
+		match self {
+			MetadataKind::String => MetadataValue::String(String::new()),
+			MetadataKind::Number => MetadataValue::Number(0.0),
+			MetadataKind::Bool => MetadataValue::Bool(false),
+		}
+	}
+}
+
+/// Custom key-value properties of an object, for gameplay data and for
+/// carrying data through exports. Sorted by key so files diff cleanly.
+pub type Metadata = BTreeMap<String, MetadataValue>;
+
+pub fn metadata_to_json(metadata: &Metadata) -> Value {
+	Value::Object(
+		metadata
+			.iter()
+			.map(|(key, value)| (key.clone(), value.to_json()))
+			.collect(),
+	)
+}
+
+/// Reads a json object of properties, skipping values of other types.
+pub fn metadata_from_json(value: &Value) -> Option<Metadata> {
+	let object = value.as_object()?;
+	Some(
+		object
+			.iter()
+			.filter_map(|(key, value)| Some((key.clone(), MetadataValue::from_json(value)?)))
+			.collect(),
+	)
+}
@@ -0,0 +1,153 @@
+use std::f32::consts::TAU;
+
+use glam::{Mat4, Quat, Vec3};
+
+/// Motion an object runs through on its own every simulation step, for
+/// scenes that move without keyframes. Each one moves the object on from
+/// wherever it is, so they stack and keep going after it's moved by hand.
+/// Attached objects are moved relative to their parent.
+/// Kept on the object through copies and undo, but not saved to the scene
+/// file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransformModifier {
+	/// turns about an axis through the object's position
+	Spin {
+		axis: Vec3,
+		/// radians per second
+		speed: f32,
+	},
+	/// moves back and forth along an axis
+	Bob {
+		axis: Vec3,
+		/// units either way
+		amplitude: f32,
+		/// cycles per second
+		frequency: f32,
+		/// seconds into the cycle
+		time: f32,
+	},
+	/// circles a point, in the parent's space for attached objects, keeping
+	/// the object's orientation
+	Orbit {
+		center: Vec3,
+		axis: Vec3,
+		/// radians per second
+		speed: f32,
+	},
+}
+
+impl TransformModifier {
+	pub const ALL: [Self; 3] = [
+		Self::Spin {
+			axis: Vec3::Y,
+			speed: 1.0,
+		},
+		Self::Bob {
+			axis: Vec3::Y,
+			amplitude: 0.25,
+			frequency: 0.5,
+			time: 0.0,
+		},
+		Self::Orbit {
+			center: Vec3::ZERO,
+			axis: Vec3::Y,
+			speed: 0.5,
+		},
+	];
+
+	pub fn label(&self) -> &'static str {
+		match self {
+			Self::Spin { .. } => "spin",
+			Self::Bob { .. } => "bob",
+			Self::Orbit { .. } => "orbit",
+		}
+	}
+
+	/// Moves `transform` on by `dt` seconds. An axis of zero length doesn't
+	/// move it.
+	pub fn apply(&mut self, transform: Mat4, dt: f32) -> Mat4 {
+		let (scale, rotation, translation) = transform.to_scale_rotation_translation();
+		match self {
+			Self::Spin { axis, speed } => {
+				let turn = match axis.try_normalize() {
+					Some(axis) => Quat::from_axis_angle(axis, *speed * dt),
+					None => return transform,
+				};
+				Mat4::from_scale_rotation_translation(scale, turn * rotation, translation)
+			}
+			Self::Bob {
+				axis,
+				amplitude,
+				frequency,
+				time,
+			} => {
+				let before = (*time * *frequency * TAU).sin();
+				*time += dt;
+				// wraps so the phase keeps its precision
+				if *frequency > 0.0 {
+					*time %= 1.0 / *frequency;
+				}
+				let after = (*time * *frequency * TAU).sin();
+				let offset = axis.normalize_or_zero() * *amplitude * (after - before);
+				Mat4::from_scale_rotation_translation(scale, rotation, translation + offset)
+			}
+			Self::Orbit {
+				center,
+				axis,
+				speed,
+			} => {
+				let turn = match axis.try_normalize() {
+					Some(axis) => Quat::from_axis_angle(axis, *speed * dt),
+					None => return transform,
+				};
+				let translation = *center + turn * (translation - *center);
+				Mat4::from_scale_rotation_translation(scale, rotation, translation)
+			}
+		}
+	}
+
+	/// Fields of the modifier, edited in place.
+	pub fn ui(&mut self, ui: &mut egui::Ui) {
+		let vec3 = |ui: &mut egui::Ui, label: &str, value: &mut Vec3| {
+			ui.horizontal(|ui| {
+				ui.label(label);
+				ui.add(egui::DragValue::new(&mut value.x).speed(0.01).prefix("x "));
+				ui.add(egui::DragValue::new(&mut value.y).speed(0.01).prefix("y "));
+				ui.add(egui::DragValue::new(&mut value.z).speed(0.01).prefix("z "));
+			});
+		};
+		match self {
+			Self::Spin { axis, speed } => {
+				vec3(ui, "axis", axis);
+				ui.add(egui::Slider::new(speed, -10.0..=10.0).text("radians per second"));
+			}
+			Self::Bob {
+				axis,
+				amplitude,
+				frequency,
+				..
+			} => {
+				vec3(ui, "axis", axis);
+				ui.add(
+					egui::Slider::new(amplitude, 0.0..=10.0)
+						.logarithmic(true)
+						.text("amplitude"),
+				);
+				ui.add(
+					egui::Slider::new(frequency, 0.0..=10.0)
+						.logarithmic(true)
+						.text("cycles per second"),
+				);
+			}
+			Self::Orbit {
+				center,
+				axis,
+				speed,
+			} => {
+				vec3(ui, "center", center);
+				vec3(ui, "axis", axis);
+				ui.add(egui::Slider::new(speed, -10.0..=10.0).text("radians per second"));
+			}
+		}
+	}
+}
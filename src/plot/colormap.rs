@@ -0,0 +1,135 @@
+use glam::Vec4;
+
+/// Maps values from 0.0 to 1.0 to colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Colormap {
+	Viridis,
+	Inferno,
+	Coolwarm,
+	Grayscale,
+}
+
+impl Colormap {
+	pub const ALL: [Colormap; 4] = [
+		Colormap::Viridis,
+		Colormap::Inferno,
+		Colormap::Coolwarm,
+		Colormap::Grayscale,
+	];
+
+	pub fn label(&self) -> &'static str {
+		match self {
+			Colormap::Viridis => "viridis",
+			Colormap::Inferno => "inferno",
+			Colormap::Coolwarm => "coolwarm",
+			Colormap::Grayscale => "grayscale",
+		}
+	}
+
+	/// evenly spaced sRGB colors, interpolated between
+	fn stops(&self) -> &'static [[u8; 3]] {
+		match self {
+			Colormap::Viridis => &[
+				[68, 1, 84],
+				[72, 40, 120],
+				[62, 73, 137],
+				[49, 104, 142],
+				[38, 130, 142],
+				[31, 158, 137],
+				[53, 183, 121],
+				[110, 206, 88],
+				[181, 222, 43],
+				[253, 231, 37],
+			],
+			Colormap::Inferno => &[
+				[0, 0, 4],
+				[27, 12, 65],
+				[74, 12, 107],
+				[120, 28, 109],
+				[165, 44, 96],
+				[207, 68, 70],
+				[237, 105, 37],
+				[251, 155, 6],
+				[247, 209, 61],
+				[252, 255, 164],
+			],
+			Colormap::Coolwarm => &[
+				[59, 76, 192],
+				[98, 130, 234],
+				[141, 176, 254],
+				[184, 208, 249],
+				[221, 221, 221],
+				[245, 196, 173],
+				[244, 154, 123],
+				[222, 96, 77],
+				[180, 4, 38],
+			],
+			Colormap::Grayscale => &[[0, 0, 0], [255, 255, 255]],
+		}
+	}
+
+	/// sRGB color at `t`, clamped to 0.0 to 1.0.
+	pub fn sample(&self, t: f32) -> egui::Color32 {
+		let stops = self.stops();
+		let x = t.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+		let i = (x.floor() as usize).min(stops.len() - 2);
+		let f = x - i as f32;
+		let channel = |c: usize| {
+			let (a, b) = (stops[i][c] as f32, stops[i + 1][c] as f32);
+			(a + (b - a) * f).round() as u8
+		};
+		egui::Color32::from_rgb(channel(0), channel(1), channel(2))
+	}
+
+	/// Linear color at `t`, for material values.
+	pub fn sample_linear(&self, t: f32) -> Vec4 {
+		let color = egui::Rgba::from(self.sample(t));
+		Vec4::new(color.r(), color.g(), color.b(), 1.0)
+	}
+
+	pub fn combo(&mut self, ui: &mut egui::Ui, id: &str) {
+		egui::ComboBox::from_id_source(id)
+			.selected_text(self.label())
+			.show_ui(ui, |ui| {
+				for colormap in Colormap::ALL {
+					ui.selectable_value(self, colormap, colormap.label());
+				}
+			});
+	}
+}
+
+/// Draws a horizontal gradient of `colormap` labelled with the values at
+/// either end.
+pub fn legend_bar(ui: &mut egui::Ui, colormap: Colormap, min: f32, max: f32) {
+	const STEPS: usize = 32;
+	const BAR_HEIGHT: f32 = 12.0;
+	let (rect, _) =
+		ui.allocate_exact_size(egui::vec2(160.0, BAR_HEIGHT + 14.0), egui::Sense::hover());
+	let painter = ui.painter();
+	let step = rect.width() / STEPS as f32;
+	for i in 0..STEPS {
+		let left = rect.left() + step * i as f32;
+		let strip = egui::Rect::from_min_max(
+			egui::pos2(left, rect.top()),
+			egui::pos2(left + step + 0.5, rect.top() + BAR_HEIGHT),
+		);
+		let t = (i as f32 + 0.5) / STEPS as f32;
+		painter.rect_filled(strip, 0.0, colormap.sample(t));
+	}
+	let color = ui.visuals().text_color();
+	let top = rect.top() + BAR_HEIGHT + 2.0;
+	painter.text(
+		egui::pos2(rect.left(), top),
+		egui::Align2::LEFT_TOP,
+		format!("{:.3}", min),
+		egui::TextStyle::Small,
+		color,
+	);
+	painter.text(
+		egui::pos2(rect.right(), top),
+		egui::Align2::RIGHT_TOP,
+		format!("{:.3}", max),
+		egui::TextStyle::Small,
+		color,
+	);
+}
@@ -0,0 +1,662 @@
+use std::fs;
+use std::path::Path;
+
+use glam::{Mat4, Quat, Vec3};
+use rend3::types::{Handedness, MaterialHandle, Mesh, MeshBuilder, Object, ObjectMeshKind};
+use rend3::Renderer;
+use rend3_routine::pbr::{AlbedoComponent, PbrMaterial};
+
+use super::{legend_bar, Colormap, PointData};
+use crate::assets::{self, AssetError};
+use crate::scene::{ObjectId, Scene};
+
+/// arrow glyph proportions, along its length and in glyph widths across
+const ARROW_SIDES: usize = 6;
+const ARROW_HEAD_START: f32 = 0.7;
+const ARROW_SHAFT_RADIUS: f32 = 0.5;
+const ARROW_HEAD_RADIUS: f32 = 1.25;
+
+/// Values on a regular grid, `width` along x by `depth` along z, row by row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScalarField {
+	pub width: usize,
+	pub depth: usize,
+	pub values: Vec<f32>,
+}
+
+impl ScalarField {
+	/// Samples `f` on a grid covering -1.0 to 1.0 on both axes.
+	pub fn from_fn(width: usize, depth: usize, f: impl Fn(f32, f32) -> f32) -> Self {
+		let coord = |i: usize, n: usize| i as f32 / (n - 1) as f32 * 2.0 - 1.0;
+		let values = (0..depth)
+			.flat_map(|j| (0..width).map(move |i| (i, j)))
+			.map(|(i, j)| f(coord(i, width), coord(j, depth)))
+			.collect();
+		Self {
+			width,
+			depth,
+			values,
+		}
+	}
+
+	/// Rows of comma separated numbers, one row per line along z. Every row
+	/// needs the same number of values.
+	pub fn parse_grid(text: &str) -> Result<Self, AssetError> {
+		let mut width = 0;
+		let mut values = Vec::new();
+		let rows = text
+			.lines()
+			.enumerate()
+			.filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'));
+		for (depth, (index, line)) in rows.enumerate() {
+			let row = line
+				.split(',')
+				.map(|cell| cell.trim().parse::<f32>())
+				.collect::<Result<Vec<_>, _>>()
+				.map_err(|e| AssetError::Invalid(format!("line {}: {}", index + 1, e)))?;
+			if depth == 0 {
+				width = row.len();
+			} else if row.len() != width {
+				return Err(AssetError::Invalid(format!(
+					"line {} has {} values, the first row has {}",
+					index + 1,
+					row.len(),
+					width
+				)));
+			}
+			values.extend(row);
+		}
+		if width < 2 || values.len() / width < 2 {
+			return Err(AssetError::Invalid(
+				"a grid needs at least 2 rows and columns".into(),
+			));
+		}
+		Ok(Self {
+			width,
+			depth: values.len() / width,
+			values,
+		})
+	}
+
+	pub fn range(&self) -> (f32, f32) {
+		value_range(self.values.iter().copied())
+	}
+
+	/// Height map surface `extent` wide on its longer side and centered on
+	/// the origin, each value raised `height` per unit and colored by
+	/// `colormap`.
+	pub fn surface(
+		&self,
+		extent: f32,
+		height: f32,
+		colormap: Colormap,
+		handedness: Handedness,
+	) -> Mesh {
+		let range = self.range();
+		let spacing = extent / (self.width.max(self.depth) - 1) as f32;
+		let half = Vec3::new(
+			(self.width - 1) as f32 * spacing,
+			0.0,
+			(self.depth - 1) as f32 * spacing,
+		) * 0.5;
+
+		let mut positions = Vec::with_capacity(self.values.len());
+		let mut colors = Vec::with_capacity(self.values.len());
+		for j in 0..self.depth {
+			for i in 0..self.width {
+				let value = self.values[j * self.width + i];
+				let y = if value.is_finite() {
+					value * height
+				} else {
+					0.0
+				};
+				positions.push(Vec3::new(i as f32 * spacing, y, j as f32 * spacing) - half);
+				colors.push(colormap.sample(normalize(value, range)).to_array());
+			}
+		}
+
+		// wound for left handed
+		let row = self.width as u32;
+		let mut indices = Vec::new();
+		for j in 0..self.depth as u32 - 1 {
+			for i in 0..row - 1 {
+				let a = j * row + i;
+				let (b, c, d) = (a + 1, a + row + 1, a + row);
+				indices.extend_from_slice(&[a, c, b, a, d, c]);
+			}
+		}
+		if handedness == Handedness::Right {
+			assets::reverse_winding(&mut indices);
+		}
+
+		MeshBuilder::new(positions, handedness)
+			.with_vertex_colors(colors)
+			.with_indices(indices)
+			.with_double_sided()
+			.build()
+			.unwrap()
+	}
+}
+
+/// Vectors at scattered points.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VectorField {
+	pub origins: Vec<Vec3>,
+	pub vectors: Vec<Vec3>,
+}
+
+impl VectorField {
+	/// Samples `f` on a `count` per axis grid covering -1.0 to 1.0.
+	pub fn from_fn(count: usize, f: impl Fn(Vec3) -> Vec3) -> Self {
+		let coord = |i: usize| match count {
+			1 => 0.0,
+			_ => i as f32 / (count - 1) as f32 * 2.0 - 1.0,
+		};
+		let origins: Vec<Vec3> = (0..count * count * count)
+			.map(|i| {
+				Vec3::new(
+					coord(i % count),
+					coord(i / count % count),
+					coord(i / count / count),
+				)
+			})
+			.collect();
+		let vectors = origins.iter().map(|p| f(*p)).collect();
+		Self { origins, vectors }
+	}
+
+	/// Takes vectors from the `vx`, `vy` and `vz` (or `u`, `v` and `w`)
+	/// attributes of point data.
+	pub fn from_points(data: &PointData) -> Result<Self, AssetError> {
+		let components = [["vx", "vy", "vz"], ["u", "v", "w"]]
+			.iter()
+			.find_map(|names| {
+				let [x, y, z] = names.map(|name| data.attribute(name));
+				Some((x?, y?, z?))
+			})
+			.ok_or_else(|| {
+				AssetError::Invalid("vector data needs vx, vy and vz attributes".into())
+			})?;
+		let (x, y, z) = components;
+		let vectors = (0..data.positions.len())
+			.map(|i| Vec3::new(x[i], y[i], z[i]))
+			.collect();
+		Ok(Self {
+			origins: data.positions.clone(),
+			vectors,
+		})
+	}
+
+	/// Moves the origins `scale` times further from the world origin, leaving
+	/// the vectors.
+	fn scaled(mut self, scale: f32) -> Self {
+		for origin in &mut self.origins {
+			*origin *= scale;
+		}
+		self
+	}
+
+	pub fn magnitude_range(&self) -> (f32, f32) {
+		value_range(self.vectors.iter().map(|v| v.length()))
+	}
+
+	/// One mesh holding an arrow glyph per vector, `scale` times its length
+	/// and `width` thick, colored by magnitude. `None` if every vector is
+	/// zero.
+	pub fn arrows(
+		&self,
+		scale: f32,
+		width: f32,
+		colormap: Colormap,
+		handedness: Handedness,
+	) -> Option<Mesh> {
+		let range = self.magnitude_range();
+		let (template, mut template_indices) = arrow_template();
+		// the glyphs are only rotated and scaled, so the template's winding
+		// carries over
+		if handedness == Handedness::Right {
+			assets::reverse_winding(&mut template_indices);
+		}
+
+		let mut positions = Vec::new();
+		let mut colors = Vec::new();
+		let mut indices = Vec::new();
+		for (origin, vector) in self.origins.iter().zip(&self.vectors) {
+			let length = vector.length();
+			if !length.is_finite() || length <= f32::EPSILON {
+				continue;
+			}
+			let transform = Mat4::from_scale_rotation_translation(
+				Vec3::new(width, length * scale, width),
+				Quat::from_rotation_arc(Vec3::Y, *vector / length),
+				*origin,
+			);
+			let base = positions.len() as u32;
+			positions.extend(template.iter().map(|p| transform.transform_point3(*p)));
+			let color = colormap.sample(normalize(length, range)).to_array();
+			colors.resize(colors.len() + template.len(), color);
+			indices.extend(template_indices.iter().map(|i| base + i));
+		}
+		if indices.is_empty() {
+			return None;
+		}
+
+		let mesh = MeshBuilder::new(positions, handedness)
+			.with_vertex_colors(colors)
+			.with_indices(indices)
+			.build()
+			.unwrap();
+		Some(mesh)
+	}
+}
+
+/// Arrow from the origin to +y 1.0, one unit wide. Faces don't share
+/// vertices so they're shaded flat.
+fn arrow_template() -> (Vec<Vec3>, Vec<u32>) {
+	let ring = |radius: f32, y: f32, k: usize| {
+		let angle = std::f32::consts::TAU * (k % ARROW_SIDES) as f32 / ARROW_SIDES as f32;
+		Vec3::new(angle.cos() * radius, y, angle.sin() * radius)
+	};
+	let (head_y, shaft, head) = (ARROW_HEAD_START, ARROW_SHAFT_RADIUS, ARROW_HEAD_RADIUS);
+
+	// wound for left handed
+	let mut positions = Vec::new();
+	for k in 0..ARROW_SIDES {
+		let (a, b) = (ring(shaft, 0.0, k), ring(shaft, 0.0, k + 1));
+		let (c, d) = (ring(shaft, head_y, k + 1), ring(shaft, head_y, k));
+		positions.extend_from_slice(&[a, c, b, a, d, c]);
+		// bottom of the shaft and of the head
+		positions.extend_from_slice(&[Vec3::ZERO, a, b]);
+		let (e, f) = (ring(head, head_y, k), ring(head, head_y, k + 1));
+		positions.extend_from_slice(&[Vec3::new(0.0, head_y, 0.0), e, f]);
+		positions.extend_from_slice(&[e, Vec3::Y, f]);
+	}
+	let indices = (0..positions.len() as u32).collect();
+	(positions, indices)
+}
+
+/// Smallest and largest finite value.
+fn value_range(values: impl Iterator<Item = f32>) -> (f32, f32) {
+	values
+		.filter(|v| v.is_finite())
+		.fold(None, |range, v| match range {
+			None => Some((v, v)),
+			Some((min, max)) => Some((f32::min(min, v), f32::max(max, v))),
+		})
+		.unwrap_or((0.0, 0.0))
+}
+
+fn normalize(value: f32, (min, max): (f32, f32)) -> f32 {
+	match max > min && value.is_finite() {
+		true => ((value - min) / (max - min)).clamp(0.0, 1.0),
+		false => 0.0,
+	}
+}
+
+/// Built in scalar functions of x and z in -1.0 to 1.0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScalarPreset {
+	Ripple,
+	Saddle,
+	Peaks,
+}
+
+impl ScalarPreset {
+	pub const ALL: [ScalarPreset; 3] = [
+		ScalarPreset::Ripple,
+		ScalarPreset::Saddle,
+		ScalarPreset::Peaks,
+	];
+
+	pub fn label(&self) -> &'static str {
+		match self {
+			ScalarPreset::Ripple => "ripple",
+			ScalarPreset::Saddle => "saddle",
+			ScalarPreset::Peaks => "peaks",
+		}
+	}
+
+	pub fn eval(&self, x: f32, z: f32) -> f32 {
+		match self {
+			ScalarPreset::Ripple => {
+				let r = (x * x + z * z).sqrt() * 12.0;
+				match r > f32::EPSILON {
+					true => r.sin() / r,
+					false => 1.0,
+				}
+			}
+			ScalarPreset::Saddle => x * x - z * z,
+			ScalarPreset::Peaks => {
+				let (x, z) = (x * 3.0, z * 3.0);
+				3.0 * (1.0 - x).powi(2) * (-x * x - (z + 1.0).powi(2)).exp()
+					- 10.0 * (x / 5.0 - x.powi(3) - z.powi(5)) * (-x * x - z * z).exp()
+					- (-(x + 1.0).powi(2) - z * z).exp() / 3.0
+			}
+		}
+	}
+}
+
+/// Built in vector fields over -1.0 to 1.0 on each axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VectorPreset {
+	Vortex,
+	Source,
+	Saddle,
+}
+
+impl VectorPreset {
+	pub const ALL: [VectorPreset; 3] = [
+		VectorPreset::Vortex,
+		VectorPreset::Source,
+		VectorPreset::Saddle,
+	];
+
+	pub fn label(&self) -> &'static str {
+		match self {
+			VectorPreset::Vortex => "vortex",
+			VectorPreset::Source => "source",
+			VectorPreset::Saddle => "saddle flow",
+		}
+	}
+
+	pub fn eval(&self, p: Vec3) -> Vec3 {
+		match self {
+			VectorPreset::Vortex => Vec3::new(-p.z, 0.2, p.x),
+			VectorPreset::Source => p,
+			VectorPreset::Saddle => Vec3::new(p.x, 0.0, -p.z),
+		}
+	}
+}
+
+/// What the field plot shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldSource {
+	Scalar(ScalarPreset),
+	Vector(VectorPreset),
+	/// a grid of values, see [`ScalarField::parse_grid`]
+	ScalarFile,
+	/// point data with vector attributes, see [`VectorField::from_points`]
+	VectorFile,
+}
+
+impl FieldSource {
+	fn label(&self) -> String {
+		match self {
+			FieldSource::Scalar(preset) => format!("scalar: {}", preset.label()),
+			FieldSource::Vector(preset) => format!("vector: {}", preset.label()),
+			FieldSource::ScalarFile => "scalar grid file".into(),
+			FieldSource::VectorFile => "vector data file".into(),
+		}
+	}
+
+	fn all() -> impl Iterator<Item = FieldSource> {
+		let scalars = ScalarPreset::ALL.into_iter().map(FieldSource::Scalar);
+		let vectors = VectorPreset::ALL.into_iter().map(FieldSource::Vector);
+		scalars
+			.chain(vectors)
+			.chain([FieldSource::ScalarFile, FieldSource::VectorFile])
+	}
+}
+
+enum Field {
+	Scalar(ScalarField),
+	Vector(VectorField),
+}
+
+/// Shows a scalar field as a colored height map or a vector field as arrow
+/// glyphs, for looking at simulation results in the scene.
+pub struct FieldPlot {
+	pub open: bool,
+	pub source: FieldSource,
+	pub path: String,
+	pub colormap: Colormap,
+	/// where the plot is centered
+	pub position: Vec3,
+	/// size of the area presets and grids cover
+	pub extent: f32,
+	/// samples per axis for presets
+	pub resolution: usize,
+	/// height per unit of a scalar value
+	pub height: f32,
+	/// arrow length per unit of a vector, and arrow thickness
+	pub arrow_scale: f32,
+	pub arrow_width: f32,
+
+	field: Option<Field>,
+	object: Option<ObjectId>,
+	material: Option<MaterialHandle>,
+	error: Option<String>,
+}
+
+impl Default for FieldPlot {
+	fn default() -> Self {
+		Self {
+			open: false,
+			source: FieldSource::Scalar(ScalarPreset::Ripple),
+			path: String::new(),
+			colormap: Colormap::Viridis,
+			position: Vec3::ZERO,
+			extent: 10.0,
+			resolution: 64,
+			height: 2.0,
+			arrow_scale: 0.5,
+			arrow_width: 0.05,
+			field: None,
+			object: None,
+			material: None,
+			error: None,
+		}
+	}
+}
+
+impl FieldPlot {
+	/// Samples the selected preset or reads the selected file.
+	pub fn load(&mut self) {
+		let field = match self.source {
+			FieldSource::Scalar(preset) => Ok(Field::Scalar(ScalarField::from_fn(
+				self.resolution,
+				self.resolution,
+				|x, z| preset.eval(x, z),
+			))),
+			FieldSource::Vector(preset) => {
+				// arrows get crowded long before surfaces do
+				let count = (self.resolution / 4).max(2);
+				let half_extent = self.extent * 0.5;
+				Ok(Field::Vector(
+					VectorField::from_fn(count, |p| preset.eval(p)).scaled(half_extent),
+				))
+			}
+			FieldSource::ScalarFile => fs::read_to_string(&self.path)
+				.map_err(AssetError::from)
+				.and_then(|text| ScalarField::parse_grid(&text))
+				.map(Field::Scalar),
+			FieldSource::VectorFile => PointData::load(Path::new(&self.path))
+				.and_then(|data| VectorField::from_points(&data))
+				.map(Field::Vector),
+		};
+		match field {
+			Ok(field) => {
+				self.field = Some(field);
+				self.error = None;
+			}
+			Err(e) => {
+				self.field = None;
+				self.error = Some(e.to_string());
+			}
+		}
+	}
+
+	/// Replaces the plot's object with one built from the current field.
+	pub fn plot(&mut self, renderer: &Renderer, scene: &mut Scene, handedness: Handedness) {
+		self.clear(scene);
+		let mesh = match &self.field {
+			Some(Field::Scalar(field)) => {
+				Some(field.surface(self.extent, self.height, self.colormap, handedness))
+			}
+			Some(Field::Vector(field)) => field.arrows(
+				self.arrow_scale,
+				self.arrow_width,
+				self.colormap,
+				handedness,
+			),
+			None => None,
+		};
+		let mesh = match mesh {
+			Some(mesh) => mesh,
+			None => return,
+		};
+
+		let material = self
+			.material
+			.get_or_insert_with(|| {
+				renderer.add_material(PbrMaterial {
+					albedo: AlbedoComponent::Vertex { srgb: true },
+					..PbrMaterial::default()
+				})
+			})
+			.clone();
+		let object = Object {
+			mesh_kind: ObjectMeshKind::Static(scene.add_mesh(renderer, mesh)),
+			material,
+			transform: Mat4::from_translation(self.position),
+		};
+		self.object = Some(scene.add_object(renderer, "field plot", object));
+	}
+
+	pub fn clear(&mut self, scene: &mut Scene) {
+		if let Some(object) = self.object.take() {
+			scene.remove_object(object);
+		}
+	}
+
+	pub fn window(
+		&mut self,
+		ctx: &egui::CtxRef,
+		renderer: &Renderer,
+		scene: &mut Scene,
+		handedness: Handedness,
+	) {
+		let mut open = self.open;
+		let mut reload = false;
+		let mut replot = false;
+		egui::Window::new("field plot")
+			.open(&mut open)
+			.resizable(false)
+			.show(ctx, |ui| {
+				let source = self.source;
+				egui::ComboBox::from_id_source("field source")
+					.selected_text(self.source.label())
+					.width(180.0)
+					.show_ui(ui, |ui| {
+						for source in FieldSource::all() {
+							ui.selectable_value(&mut self.source, source, source.label());
+						}
+					});
+				let from_file = matches!(
+					self.source,
+					FieldSource::ScalarFile | FieldSource::VectorFile
+				);
+				// presets update right away, files wait for the button
+				if source != self.source {
+					match from_file {
+						true => {
+							self.field = None;
+							replot = true;
+						}
+						false => reload = true,
+					}
+				}
+				if from_file {
+					ui.horizontal(|ui| {
+						ui.label("file");
+						ui.text_edit_singleline(&mut self.path);
+						reload |= ui.button("load").clicked();
+					});
+				} else {
+					reload |= ui
+						.add(egui::Slider::new(&mut self.resolution, 4..=256).text("resolution"))
+						.changed();
+				}
+				let extent = ui.add(
+					egui::Slider::new(&mut self.extent, 0.1..=1000.0)
+						.logarithmic(true)
+						.text("extent"),
+				);
+				// files only use it for the size of scalar grids
+				match from_file {
+					true => replot |= extent.changed(),
+					false => reload |= extent.changed(),
+				}
+
+				let colormap = self.colormap;
+				ui.horizontal(|ui| {
+					ui.label("colormap");
+					self.colormap.combo(ui, "field colormap");
+				});
+				replot |= colormap != self.colormap;
+				ui.horizontal(|ui| {
+					ui.label("position");
+					for value in self.position.as_mut() {
+						replot |= ui.add(egui::DragValue::new(value).speed(0.1)).changed();
+					}
+				});
+				match &self.field {
+					Some(Field::Scalar(field)) => {
+						replot |= ui
+							.add(egui::Slider::new(&mut self.height, -10.0..=10.0).text("height"))
+							.changed();
+						let (min, max) = field.range();
+						ui.label(format!("{}x{} values", field.width, field.depth));
+						legend_bar(ui, self.colormap, min, max);
+					}
+					Some(Field::Vector(field)) => {
+						replot |= ui
+							.add(
+								egui::Slider::new(&mut self.arrow_scale, 0.01..=10.0)
+									.logarithmic(true)
+									.text("arrow length"),
+							)
+							.changed();
+						replot |= ui
+							.add(
+								egui::Slider::new(&mut self.arrow_width, 0.001..=1.0)
+									.logarithmic(true)
+									.text("arrow width"),
+							)
+							.changed();
+						let (min, max) = field.magnitude_range();
+						ui.label(format!(
+							"{} vectors, colored by magnitude",
+							field.vectors.len()
+						));
+						legend_bar(ui, self.colormap, min, max);
+					}
+					None => {}
+				}
+
+				ui.horizontal(|ui| {
+					if ui.button("plot").clicked() {
+						reload |= !from_file;
+						replot = true;
+					}
+					if ui
+						.add_enabled(self.object.is_some(), egui::Button::new("clear"))
+						.clicked()
+					{
+						self.clear(scene);
+					}
+				});
+				if let Some(error) = &self.error {
+					ui.colored_label(egui::Color32::RED, error);
+				}
+			});
+		self.open = open;
+
+		if reload {
+			self.load();
+		}
+		if reload || replot {
+			self.plot(renderer, scene, handedness);
+		}
+	}
+}
@@ -0,0 +1,10 @@
+//! Data visualization: plotting values from files and simulations in the
+//! scene.
+
+mod colormap;
+mod fields;
+mod points;
+
+pub use colormap::{legend_bar, Colormap};
+pub use fields::{FieldPlot, FieldSource, ScalarField, ScalarPreset, VectorField, VectorPreset};
+pub use points::{PointData, PointPlot};
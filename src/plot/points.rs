@@ -0,0 +1,466 @@
+use std::fs;
+use std::path::Path;
+
+use glam::{Mat4, Vec3};
+use rend3::types::{Handedness, MaterialHandle, MeshHandle, Object, ObjectMeshKind};
+use rend3::Renderer;
+use rend3_routine::pbr::{AlbedoComponent, PbrMaterial};
+use serde_json::Value;
+
+use super::Colormap;
+use crate::assets::{self, AssetError};
+use crate::scene::{ObjectId, Scene, SceneTransform, Transform};
+
+/// markers sharing a material, colors are rounded to one of these
+const COLOR_BINS: usize = 64;
+/// points beyond this are left out of the plot
+const MAX_POINTS: usize = 100_000;
+
+/// Points read from a data file, each with a position and the same named
+/// scalar attributes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PointData {
+	pub positions: Vec<Vec3>,
+	/// attribute names with one value per point
+	pub attributes: Vec<(String, Vec<f32>)>,
+}
+
+impl PointData {
+	/// Reads a `.csv` or `.json` file, see [`PointData::parse_csv`] and
+	/// [`PointData::parse_json`].
+	pub fn load(path: &Path) -> Result<Self, AssetError> {
+		let text = fs::read_to_string(path)?;
+		match path.extension().and_then(|e| e.to_str()) {
+			Some(e) if e.eq_ignore_ascii_case("csv") => Self::parse_csv(&text),
+			Some(e) if e.eq_ignore_ascii_case("json") => Self::parse_json(&text),
+			_ => invalid("point data must be a .csv or .json file"),
+		}
+	}
+
+	/// Comma separated values with a header row. Positions come from the `x`,
+	/// `y` and `z` columns and every other column that's numeric in every
+	/// row becomes an attribute. Empty lines and lines starting with `#` are
+	/// skipped.
+	pub fn parse_csv(text: &str) -> Result<Self, AssetError> {
+		let mut lines = text
+			.lines()
+			.enumerate()
+			.filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'));
+		let header: Vec<String> = match lines.next() {
+			Some((_, line)) => split_csv(line).map(str::to_lowercase).collect(),
+			None => return invalid("csv has no header row"),
+		};
+		let column = |name: &str| {
+			header
+				.iter()
+				.position(|h| h == name)
+				.ok_or_else(|| AssetError::Invalid(format!("csv has no {} column", name)))
+		};
+		let xyz = [column("x")?, column("y")?, column("z")?];
+
+		let mut cells: Vec<Vec<Option<f32>>> = vec![Vec::new(); header.len()];
+		for (index, line) in lines {
+			let row: Vec<&str> = split_csv(line).collect();
+			if row.len() != header.len() {
+				return invalid(format!(
+					"line {} has {} values, the header has {}",
+					index + 1,
+					row.len(),
+					header.len()
+				));
+			}
+			for (column, cell) in cells.iter_mut().zip(&row) {
+				column.push(cell.parse().ok());
+			}
+			if let Some(&c) = xyz.iter().find(|&&c| cells[c].last() == Some(&None)) {
+				return invalid(format!("line {}: {} is not a number", index + 1, row[c]));
+			}
+		}
+
+		let positions = (0..cells[xyz[0]].len())
+			.map(|i| Vec3::from(xyz.map(|c| cells[c][i].unwrap_or_default())))
+			.collect();
+		let attributes = header
+			.into_iter()
+			.zip(cells)
+			.enumerate()
+			.filter(|(c, _)| !xyz.contains(c))
+			.filter_map(|(_, (name, column))| {
+				Some((name, column.into_iter().collect::<Option<_>>()?))
+			})
+			.collect();
+		Ok(Self {
+			positions,
+			attributes,
+		})
+	}
+
+	/// An array of objects, or an object with such an array under `points`.
+	/// Positions come from `x`, `y` and `z` fields or a `position` array and
+	/// every other field that's a number in every point becomes an
+	/// attribute.
+	pub fn parse_json(text: &str) -> Result<Self, AssetError> {
+		let value: Value = serde_json::from_str(text)
+			.map_err(|e| AssetError::Invalid(format!("invalid json: {}", e)))?;
+		let points = match value.get("points").unwrap_or(&value) {
+			Value::Array(points) => points,
+			_ => return invalid("expected an array of points"),
+		};
+
+		let mut positions = Vec::with_capacity(points.len());
+		for (i, point) in points.iter().enumerate() {
+			let number = |value: Option<&Value>| value.and_then(Value::as_f64).map(|v| v as f32);
+			let position = match point.get("position").and_then(Value::as_array) {
+				Some(p) if p.len() == 3 => [number(p.first()), number(p.get(1)), number(p.get(2))],
+				_ => ["x", "y", "z"].map(|axis| number(point.get(axis))),
+			};
+			match position {
+				[Some(x), Some(y), Some(z)] => positions.push(Vec3::new(x, y, z)),
+				_ => return invalid(format!("point {} has no position", i)),
+			}
+		}
+
+		let names: Vec<&String> = match points.first().and_then(Value::as_object) {
+			Some(first) => first
+				.keys()
+				.filter(|key| !matches!(key.as_str(), "x" | "y" | "z" | "position"))
+				.collect(),
+			None => Vec::new(),
+		};
+		let attributes = names
+			.into_iter()
+			.filter_map(|name| {
+				let values = points
+					.iter()
+					.map(|point| point.get(name).and_then(Value::as_f64).map(|v| v as f32))
+					.collect::<Option<Vec<f32>>>()?;
+				Some((name.clone(), values))
+			})
+			.collect();
+		Ok(Self {
+			positions,
+			attributes,
+		})
+	}
+
+	pub fn attribute(&self, name: &str) -> Option<&[f32]> {
+		self.attributes
+			.iter()
+			.find(|(n, _)| n == name)
+			.map(|(_, values)| values.as_slice())
+	}
+}
+
+/// Splits a csv line, trimming whitespace and quotes around each value.
+fn split_csv(line: &str) -> impl Iterator<Item = &str> {
+	line.split(',').map(|cell| cell.trim().trim_matches('"'))
+}
+
+fn invalid<T>(reason: impl Into<String>) -> Result<T, AssetError> {
+	Err(AssetError::Invalid(reason.into()))
+}
+
+/// Smallest and largest finite value, or `None` if there are none.
+fn range(values: &[f32]) -> Option<(f32, f32)> {
+	values
+		.iter()
+		.filter(|v| v.is_finite())
+		.fold(None, |range, &v| match range {
+			None => Some((v, v)),
+			Some((min, max)) => Some((min.min(v), max.max(v))),
+		})
+}
+
+/// Where `value` falls between `min` and `max`, 0.0 to 1.0.
+fn normalize(value: f32, (min, max): (f32, f32)) -> f32 {
+	match max > min && value.is_finite() {
+		true => ((value - min) / (max - min)).clamp(0.0, 1.0),
+		false => 0.0,
+	}
+}
+
+/// Plots [`PointData`] as sphere markers, colored and sized by attributes.
+///
+/// Markers share one mesh and colors are rounded to `COLOR_BINS` shared
+/// materials, so large data sets stay cheap to add.
+pub struct PointPlot {
+	pub open: bool,
+	/// shows the color and size mapping while points are plotted
+	pub legend: bool,
+	pub path: String,
+	/// attribute mapped to color through the colormap
+	pub color_by: Option<String>,
+	pub colormap: Colormap,
+	/// attribute mapped to size
+	pub size_by: Option<String>,
+	/// marker radius, and how many times larger the largest value's marker
+	/// is when sizing by an attribute
+	pub marker_size: f32,
+	pub size_ratio: f32,
+
+	data: Option<PointData>,
+	name: String,
+	objects: Vec<ObjectId>,
+	marker: Option<MeshHandle>,
+	error: Option<String>,
+}
+
+impl Default for PointPlot {
+	fn default() -> Self {
+		Self {
+			open: false,
+			legend: true,
+			path: String::new(),
+			color_by: None,
+			colormap: Colormap::Viridis,
+			size_by: None,
+			marker_size: 0.1,
+			size_ratio: 4.0,
+			data: None,
+			name: String::new(),
+			objects: Vec::new(),
+			marker: None,
+			error: None,
+		}
+	}
+}
+
+impl PointPlot {
+	/// Replaces the plotted data with the file at `self.path`.
+	pub fn load(&mut self, renderer: &Renderer, scene: &mut Scene, handedness: Handedness) {
+		let path = Path::new(&self.path);
+		match PointData::load(path) {
+			Ok(mut data) => {
+				if data.positions.len() > MAX_POINTS {
+					log::warn!(
+						"plotting the first {} of {} points",
+						MAX_POINTS,
+						data.positions.len()
+					);
+					data.positions.truncate(MAX_POINTS);
+					for (_, values) in &mut data.attributes {
+						values.truncate(MAX_POINTS);
+					}
+				}
+				let first = data.attributes.first().map(|(name, _)| name.clone());
+				self.color_by = first;
+				self.size_by = None;
+				self.name = path
+					.file_stem()
+					.map_or("point".into(), |stem| stem.to_string_lossy().into());
+				self.data = Some(data);
+				self.error = None;
+				self.plot(renderer, scene, handedness);
+			}
+			Err(e) => self.error = Some(e.to_string()),
+		}
+	}
+
+	/// Removes the markers and forgets the data.
+	pub fn clear(&mut self, scene: &mut Scene) {
+		for object in self.objects.drain(..) {
+			scene.remove_object(object);
+		}
+		self.data = None;
+	}
+
+	/// Adds a marker for every point, replacing the previous ones.
+	fn plot(&mut self, renderer: &Renderer, scene: &mut Scene, handedness: Handedness) {
+		for object in self.objects.drain(..) {
+			scene.remove_object(object);
+		}
+		let data = match &self.data {
+			Some(data) => data,
+			None => return,
+		};
+
+		let marker = self
+			.marker
+			.get_or_insert_with(|| scene.add_mesh(renderer, assets::sphere(12, 8, handedness)))
+			.clone();
+		let materials: Vec<MaterialHandle> = (0..COLOR_BINS)
+			.map(|bin| {
+				let t = bin as f32 / (COLOR_BINS - 1) as f32;
+				renderer.add_material(PbrMaterial {
+					albedo: AlbedoComponent::Value(self.colormap.sample_linear(t)),
+					..PbrMaterial::default()
+				})
+			})
+			.collect();
+
+		let colors = self
+			.color_by
+			.as_deref()
+			.and_then(|name| data.attribute(name));
+		let color_range = colors.and_then(range);
+		for (i, position) in data.positions.iter().enumerate() {
+			let t = match (colors, color_range) {
+				(Some(values), Some(range)) => normalize(values[i], range),
+				_ => 0.0,
+			};
+			let bin = (t * (COLOR_BINS - 1) as f32).round() as usize;
+			let object = scene.add_object(
+				renderer,
+				&format!("{} {}", self.name, i),
+				Object {
+					mesh_kind: ObjectMeshKind::Static(marker.clone()),
+					material: materials[bin].clone(),
+					transform: Mat4::from_translation(*position),
+				},
+			);
+			self.objects.push(object);
+		}
+		self.resize(scene);
+	}
+
+	/// Scales the markers without adding them again.
+	fn resize(&self, scene: &mut Scene) {
+		let data = match &self.data {
+			Some(data) => data,
+			None => return,
+		};
+		let sizes = self
+			.size_by
+			.as_deref()
+			.and_then(|name| data.attribute(name));
+		let size_range = sizes.and_then(range);
+		for (i, (object, position)) in self.objects.iter().zip(&data.positions).enumerate() {
+			let scale = match (sizes, size_range) {
+				(Some(values), Some(range)) => {
+					1.0 + (self.size_ratio - 1.0) * normalize(values[i], range)
+				}
+				_ => 1.0,
+			};
+			let transform = Mat4::from_scale_rotation_translation(
+				Vec3::splat(self.marker_size * scale),
+				Default::default(),
+				*position,
+			);
+			scene.teleport(*object, Transform::from_mat4(transform));
+		}
+	}
+
+	pub fn window(
+		&mut self,
+		ctx: &egui::CtxRef,
+		renderer: &Renderer,
+		scene: &mut Scene,
+		handedness: Handedness,
+	) {
+		let mut open = self.open;
+		let mut replot = false;
+		let mut resize = false;
+		egui::Window::new("point plot")
+			.open(&mut open)
+			.resizable(false)
+			.show(ctx, |ui| {
+				ui.horizontal(|ui| {
+					ui.label("file");
+					ui.add(egui::TextEdit::singleline(&mut self.path).hint_text("points.csv"));
+					if ui.button("load").clicked() {
+						self.load(renderer, scene, handedness);
+					}
+				});
+				if let Some(error) = &self.error {
+					ui.colored_label(egui::Color32::RED, error);
+				}
+				let data = match &self.data {
+					Some(data) => data,
+					None => return,
+				};
+				ui.label(format!(
+					"{} points, {} attributes",
+					data.positions.len(),
+					data.attributes.len()
+				));
+
+				let names: Vec<&str> = data.attributes.iter().map(|(n, _)| n.as_str()).collect();
+				let color_by = self.color_by.clone();
+				let colormap = self.colormap;
+				ui.horizontal(|ui| {
+					ui.label("color");
+					attribute_combo(ui, "plot color", &names, &mut self.color_by);
+					self.colormap.combo(ui, "plot colormap");
+				});
+				replot = color_by != self.color_by || colormap != self.colormap;
+
+				let size_by = self.size_by.clone();
+				ui.horizontal(|ui| {
+					ui.label("size");
+					attribute_combo(ui, "plot size", &names, &mut self.size_by);
+				});
+				resize = size_by != self.size_by;
+				resize |= ui
+					.add(
+						egui::Slider::new(&mut self.marker_size, 0.001..=10.0)
+							.logarithmic(true)
+							.text("marker size"),
+					)
+					.changed();
+				resize |= ui
+					.add_enabled(
+						self.size_by.is_some(),
+						egui::Slider::new(&mut self.size_ratio, 1.0..=20.0).text("largest"),
+					)
+					.changed();
+
+				ui.horizontal(|ui| {
+					ui.checkbox(&mut self.legend, "legend");
+					if ui.button("clear").clicked() {
+						self.clear(scene);
+					}
+				});
+			});
+		self.open = open;
+
+		if replot {
+			self.plot(renderer, scene, handedness);
+		} else if resize {
+			self.resize(scene);
+		}
+		if self.legend && !self.objects.is_empty() {
+			self.legend_window(ctx);
+		}
+	}
+
+	fn legend_window(&self, ctx: &egui::CtxRef) {
+		let data = match &self.data {
+			Some(data) => data,
+			None => return,
+		};
+		egui::Window::new("plot legend")
+			.resizable(false)
+			.anchor(egui::Align2::LEFT_BOTTOM, egui::vec2(8.0, -8.0))
+			.show(ctx, |ui| {
+				if let Some(name) = &self.color_by {
+					ui.label(format!("color: {}", name));
+					if let Some((min, max)) = data.attribute(name).and_then(range) {
+						super::legend_bar(ui, self.colormap, min, max);
+					}
+				}
+				if let Some(name) = &self.size_by {
+					if let Some((min, max)) = data.attribute(name).and_then(range) {
+						ui.label(format!(
+							"size: {} ({:.3} to {:.3}, {}x)",
+							name, min, max, self.size_ratio
+						));
+					}
+				}
+				if self.color_by.is_none() && self.size_by.is_none() {
+					ui.label(format!("{} points", self.objects.len()));
+				}
+			});
+	}
+}
+
+/// Picks one of `names` or none.
+fn attribute_combo(ui: &mut egui::Ui, id: &str, names: &[&str], selected: &mut Option<String>) {
+	egui::ComboBox::from_id_source(id)
+		.selected_text(selected.as_deref().unwrap_or("none"))
+		.show_ui(ui, |ui| {
+			ui.selectable_value(selected, None, "none");
+			for name in names {
+				ui.selectable_value(selected, Some(name.to_string()), *name);
+			}
+		});
+}
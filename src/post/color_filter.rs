@@ -0,0 +1,174 @@
+use std::borrow::Cow;
+
+use rend3::graph::{RenderGraph, RenderPassTarget, RenderPassTargets, RenderTargetHandle};
+use rend3::util::bind_merge::{BindGroupBuilder, BindGroupLayoutBuilder};
+use rend3::Renderer;
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+	BindGroupLayout, BindingType, Buffer, BufferBindingType, BufferUsages, Color, ColorTargetState,
+	ColorWrites, FragmentState, MultisampleState, PipelineLayoutDescriptor, PrimitiveState,
+	RenderPipeline, RenderPipelineDescriptor, ShaderModuleDescriptor, ShaderSource, ShaderStages,
+	TextureFormat, TextureSampleType, TextureViewDimension, VertexState,
+};
+
+use crate::settings::{AccessibilitySettings, ColorBlindMode, ColorFilterKind};
+
+/// Full-screen pass that simulates or corrects for color blindness.
+///
+/// Runs after tonemapping, reading the tonemapped frame from an intermediate
+/// target and writing the filtered result to `dst`.
+pub struct ColorFilterRoutine {
+	bgl: BindGroupLayout,
+	pipeline: RenderPipeline,
+	uniforms: Buffer,
+	gamma_source: bool,
+}
+
+impl ColorFilterRoutine {
+	pub fn new(renderer: &Renderer, output_format: TextureFormat) -> Self {
+		let device = &renderer.device;
+
+		let bgl = BindGroupLayoutBuilder::new()
+			.append(
+				ShaderStages::FRAGMENT,
+				BindingType::Texture {
+					sample_type: TextureSampleType::Float { filterable: false },
+					view_dimension: TextureViewDimension::D2,
+					multisampled: false,
+				},
+				None,
+			)
+			.append(
+				ShaderStages::FRAGMENT,
+				BindingType::Buffer {
+					ty: BufferBindingType::Uniform,
+					has_dynamic_offset: false,
+					min_binding_size: None,
+				},
+				None,
+			)
+			.build(device, Some("color filter bgl"));
+
+		let module = device.create_shader_module(&ShaderModuleDescriptor {
+			label: Some("color filter"),
+			source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("color_filter.wgsl"))),
+		});
+
+		let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+			label: Some("color filter pass"),
+			bind_group_layouts: &[&bgl],
+			push_constant_ranges: &[],
+		});
+
+		let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+			label: Some("color filter pass"),
+			layout: Some(&layout),
+			vertex: VertexState {
+				module: &module,
+				entry_point: "vs_main",
+				buffers: &[],
+			},
+			primitive: PrimitiveState::default(),
+			depth_stencil: None,
+			multisample: MultisampleState::default(),
+			fragment: Some(FragmentState {
+				module: &module,
+				entry_point: "fs_main",
+				targets: &[ColorTargetState {
+					format: output_format,
+					blend: None,
+					write_mask: ColorWrites::all(),
+				}],
+			}),
+			multiview: None,
+		});
+
+		let uniforms = device.create_buffer_init(&BufferInitDescriptor {
+			label: Some("color filter uniforms"),
+			contents: &[0; 64],
+			usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+		});
+
+		Self {
+			bgl,
+			pipeline,
+			uniforms,
+			// non-srgb outputs get gamma encoded values from the tonemapper
+			gamma_source: !output_format.describe().srgb,
+		}
+	}
+
+	/// Uploads the filter parameters for the next frame.
+	pub fn update(&self, renderer: &Renderer, settings: &AccessibilitySettings) {
+		let matrix = settings.color_blind_mode.simulation_matrix().transpose();
+		let mode = match (settings.color_blind_mode, settings.color_filter) {
+			(ColorBlindMode::Off, _) => 0.0,
+			(_, ColorFilterKind::Simulate) => 1.0,
+			(_, ColorFilterKind::Daltonize) => 2.0,
+		};
+
+		let data: [f32; 16] = [
+			matrix.x_axis.x,
+			matrix.x_axis.y,
+			matrix.x_axis.z,
+			0.0,
+			matrix.y_axis.x,
+			matrix.y_axis.y,
+			matrix.y_axis.z,
+			0.0,
+			matrix.z_axis.x,
+			matrix.z_axis.y,
+			matrix.z_axis.z,
+			0.0,
+			mode,
+			settings.color_filter_strength,
+			if self.gamma_source { 1.0 } else { 0.0 },
+			0.0,
+		];
+		let bytes: Vec<u8> = data.iter().flat_map(|v| v.to_ne_bytes()).collect();
+
+		renderer.queue.write_buffer(&self.uniforms, 0, &bytes);
+	}
+
+	pub fn add_to_graph<'node>(
+		&'node self,
+		graph: &mut RenderGraph<'node>,
+		src: RenderTargetHandle,
+		dst: RenderTargetHandle,
+	) {
+		let mut builder = graph.add_node("Color Filter");
+
+		let input_handle = builder.add_render_target_input(src);
+		let output_handle = builder.add_render_target_output(dst);
+
+		let rpass_handle = builder.add_renderpass(RenderPassTargets {
+			targets: vec![RenderPassTarget {
+				color: output_handle,
+				clear: Color::BLACK,
+				resolve: None,
+			}],
+			depth_stencil: None,
+		});
+
+		let pt_handle = builder.passthrough_ref(self);
+
+		builder.build(
+			move |pt, renderer, encoder_or_pass, temps, _ready, graph_data| {
+				let this = pt.get(pt_handle);
+				let rpass = encoder_or_pass.get_rpass(rpass_handle);
+				let source = graph_data.get_render_target(input_handle);
+
+				let bg = temps.add(
+					BindGroupBuilder::new()
+						.append_texture_view(source)
+						.append_buffer(&this.uniforms)
+						.build(&renderer.device, Some("color filter bg"), &this.bgl),
+				);
+
+				rpass.set_pipeline(&this.pipeline);
+				rpass.set_bind_group(0, bg, &[]);
+				rpass.draw(0..3, 0..1);
+			},
+		);
+	}
+}
@@ -0,0 +1,85 @@
+// Full-screen color vision deficiency filter.
+//
+// Reads the tonemapped frame and either simulates how it is perceived with a
+// given deficiency or daltonizes it by shifting the lost information into
+// channels that are still distinguishable.
+
+struct VertexOutput {
+	[[builtin(position)]] position: vec4<f32>;
+};
+
+struct ColorFilterUniforms {
+	// rows of the 3x3 simulation matrix (linear rgb)
+	row0: vec4<f32>;
+	row1: vec4<f32>;
+	row2: vec4<f32>;
+	// x: mode (0 = passthrough, 1 = simulate, 2 = daltonize)
+	// y: strength
+	// z: 1.0 if the source holds gamma encoded values
+	params: vec4<f32>;
+};
+
+[[group(0), binding(0)]]
+var source: texture_2d<f32>;
+
+[[group(0), binding(1)]]
+var<uniform> uniforms: ColorFilterUniforms;
+
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] vertex_index: u32) -> VertexOutput {
+	// single triangle covering the whole screen
+	let x = f32(i32(vertex_index & 1u) * 4 - 1);
+	let y = f32(i32(vertex_index & 2u) * 2 - 1);
+	var out: VertexOutput;
+	out.position = vec4<f32>(x, y, 0.0, 1.0);
+	return out;
+}
+
+fn srgb_to_linear(c: vec3<f32>) -> vec3<f32> {
+	let low = c / 12.92;
+	let high = pow((c + 0.055) / 1.055, vec3<f32>(2.4));
+	return select(high, low, c <= vec3<f32>(0.04045));
+}
+
+fn linear_to_srgb(c: vec3<f32>) -> vec3<f32> {
+	let low = c * 12.92;
+	let high = 1.055 * pow(c, vec3<f32>(1.0 / 2.4)) - 0.055;
+	return select(high, low, c <= vec3<f32>(0.0031308));
+}
+
+fn simulate(c: vec3<f32>) -> vec3<f32> {
+	return vec3<f32>(
+		dot(uniforms.row0.xyz, c),
+		dot(uniforms.row1.xyz, c),
+		dot(uniforms.row2.xyz, c),
+	);
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let texel = textureLoad(source, vec2<i32>(in.position.xy), 0);
+	let gamma_source = uniforms.params.z > 0.5;
+
+	var color = texel.rgb;
+	if (gamma_source) {
+		color = srgb_to_linear(color);
+	}
+
+	let mode = u32(uniforms.params.x);
+	var filtered = color;
+	if (mode == 1u) {
+		filtered = simulate(color);
+	} else if (mode == 2u) {
+		// redistribute the error the deficiency can't see into the other channels
+		let error = color - simulate(color);
+		let shift = vec3<f32>(0.0, 0.7 * error.r + error.g, 0.7 * error.r + error.b);
+		filtered = color + shift;
+	}
+
+	var result = clamp(mix(color, filtered, uniforms.params.y), vec3<f32>(0.0), vec3<f32>(1.0));
+	if (gamma_source) {
+		result = linear_to_srgb(result);
+	}
+
+	return vec4<f32>(result, texel.a);
+}
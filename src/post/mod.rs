@@ -0,0 +1,7 @@
+//! Post-processing passes that run around tonemapping.
+
+mod color_filter;
+mod motion_blur;
+
+pub use color_filter::ColorFilterRoutine;
+pub use motion_blur::MotionBlurRoutine;
@@ -0,0 +1,431 @@
+use std::borrow::Cow;
+use std::num::NonZeroU64;
+
+use glam::{Mat4, UVec2, Vec3};
+use rend3::graph::{
+	DepthHandle, RenderGraph, RenderPassDepthTarget, RenderPassTarget, RenderPassTargets,
+	RenderTargetDescriptor, RenderTargetHandle,
+};
+use rend3::types::SampleCount;
+use rend3::util::bind_merge::{BindGroupBuilder, BindGroupLayoutBuilder};
+use rend3::Renderer;
+use rend3_routine::base::BaseRenderGraphIntermediateState;
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+	BindGroup, BindGroupLayout, BindingType, Buffer, BufferAddress, BufferBindingType,
+	BufferDescriptor, BufferUsages, Color, ColorTargetState, ColorWrites, CompareFunction,
+	DepthBiasState, DepthStencilState, FragmentState, MultisampleState, PipelineLayoutDescriptor,
+	PrimitiveState, RenderPipeline, RenderPipelineDescriptor, ShaderModuleDescriptor, ShaderSource,
+	ShaderStages, StencilState, TextureFormat, TextureSampleType, TextureUsages,
+	TextureViewDimension, VertexAttribute, VertexBufferLayout, VertexFormat, VertexState,
+	VertexStepMode,
+};
+
+use crate::mesh_cache::MeshCache;
+use crate::scene::Scene;
+use crate::settings::GraphicsSettings;
+use crate::split_screen::Viewport;
+use crate::staging::StagingPool;
+
+/// current and previous matrix, padded to the dynamic offset alignment
+const OBJECT_UNIFORM_SIZE: BufferAddress = 128;
+const OBJECT_UNIFORM_STRIDE: BufferAddress = 256;
+
+const VELOCITY_FORMAT: TextureFormat = TextureFormat::Rg16Float;
+
+struct Draw {
+	mesh: usize,
+	offset: u32,
+}
+
+/// Camera and per-object motion blur.
+///
+/// Objects from the [`Scene`] are redrawn into a velocity buffer holding how
+/// far each pixel moved since the last frame, depth tested against the scene
+/// so only visible surfaces contribute. The hdr color is then blurred along
+/// that vector before tonemapping. Expects a single sampled depth buffer.
+pub struct MotionBlurRoutine {
+	vector_pipeline: RenderPipeline,
+	object_bgl: BindGroupLayout,
+	object_uniforms: Buffer,
+	object_bg: BindGroup,
+	object_capacity: usize,
+
+	blur_bgl: BindGroupLayout,
+	blur_pipeline: RenderPipeline,
+	blur_uniforms: Buffer,
+
+	meshes: MeshCache,
+	draws: Vec<Draw>,
+	/// view projection of the last frame, per viewport
+	previous_view_proj: [Option<Mat4>; 2],
+}
+
+impl MotionBlurRoutine {
+	pub fn new(renderer: &Renderer) -> Self {
+		let device = &renderer.device;
+
+		let object_bgl = BindGroupLayoutBuilder::new()
+			.append(
+				ShaderStages::VERTEX,
+				BindingType::Buffer {
+					ty: BufferBindingType::Uniform,
+					has_dynamic_offset: true,
+					min_binding_size: NonZeroU64::new(OBJECT_UNIFORM_SIZE),
+				},
+				None,
+			)
+			.build(device, Some("motion vectors bgl"));
+
+		let vector_module = device.create_shader_module(&ShaderModuleDescriptor {
+			label: Some("motion vectors"),
+			source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("motion_vectors.wgsl"))),
+		});
+
+		let vector_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+			label: Some("motion vectors pass"),
+			bind_group_layouts: &[&object_bgl],
+			push_constant_ranges: &[],
+		});
+
+		let vector_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+			label: Some("motion vectors pass"),
+			layout: Some(&vector_layout),
+			vertex: VertexState {
+				module: &vector_module,
+				entry_point: "vs_main",
+				buffers: &[VertexBufferLayout {
+					array_stride: 12,
+					step_mode: VertexStepMode::Vertex,
+					attributes: &[VertexAttribute {
+						format: VertexFormat::Float32x3,
+						offset: 0,
+						shader_location: 0,
+					}],
+				}],
+			},
+			primitive: PrimitiveState::default(),
+			depth_stencil: Some(DepthStencilState {
+				format: TextureFormat::Depth32Float,
+				depth_write_enabled: false,
+				depth_compare: CompareFunction::GreaterEqual,
+				stencil: StencilState::default(),
+				// pull towards the camera so surfaces pass against their own depth
+				bias: DepthBiasState {
+					constant: 2,
+					slope_scale: 1.0,
+					clamp: 0.0,
+				},
+			}),
+			multisample: MultisampleState::default(),
+			fragment: Some(FragmentState {
+				module: &vector_module,
+				entry_point: "fs_main",
+				targets: &[ColorTargetState {
+					format: VELOCITY_FORMAT,
+					blend: None,
+					write_mask: ColorWrites::all(),
+				}],
+			}),
+			multiview: None,
+		});
+
+		let blur_bgl = BindGroupLayoutBuilder::new()
+			.append(
+				ShaderStages::FRAGMENT,
+				BindingType::Texture {
+					sample_type: TextureSampleType::Float { filterable: false },
+					view_dimension: TextureViewDimension::D2,
+					multisampled: false,
+				},
+				None,
+			)
+			.append(
+				ShaderStages::FRAGMENT,
+				BindingType::Texture {
+					sample_type: TextureSampleType::Float { filterable: false },
+					view_dimension: TextureViewDimension::D2,
+					multisampled: false,
+				},
+				None,
+			)
+			.append(
+				ShaderStages::FRAGMENT,
+				BindingType::Buffer {
+					ty: BufferBindingType::Uniform,
+					has_dynamic_offset: false,
+					min_binding_size: None,
+				},
+				None,
+			)
+			.build(device, Some("motion blur bgl"));
+
+		let blur_module = device.create_shader_module(&ShaderModuleDescriptor {
+			label: Some("motion blur"),
+			source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("motion_blur.wgsl"))),
+		});
+
+		let blur_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+			label: Some("motion blur pass"),
+			bind_group_layouts: &[&blur_bgl],
+			push_constant_ranges: &[],
+		});
+
+		let blur_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+			label: Some("motion blur pass"),
+			layout: Some(&blur_layout),
+			vertex: VertexState {
+				module: &blur_module,
+				entry_point: "vs_main",
+				buffers: &[],
+			},
+			primitive: PrimitiveState::default(),
+			depth_stencil: None,
+			multisample: MultisampleState::default(),
+			fragment: Some(FragmentState {
+				module: &blur_module,
+				entry_point: "fs_main",
+				targets: &[ColorTargetState {
+					format: TextureFormat::Rgba16Float,
+					blend: None,
+					write_mask: ColorWrites::all(),
+				}],
+			}),
+			multiview: None,
+		});
+
+		let blur_uniforms = device.create_buffer_init(&BufferInitDescriptor {
+			label: Some("motion blur uniforms"),
+			contents: &[0; 16],
+			usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+		});
+
+		let object_capacity = 16;
+		let (object_uniforms, object_bg) =
+			create_object_uniforms(renderer, &object_bgl, object_capacity);
+
+		Self {
+			vector_pipeline,
+			object_bgl,
+			object_uniforms,
+			object_bg,
+			object_capacity,
+			blur_bgl,
+			blur_pipeline,
+			blur_uniforms,
+			meshes: MeshCache::default(),
+			draws: Vec::new(),
+			previous_view_proj: [None; 2],
+		}
+	}
+
+	/// Uploads the motion of every object for the viewport about to be
+	/// rendered. Call once per viewport per frame, right before building its
+	/// graph.
+	pub fn update(
+		&mut self,
+		renderer: &Renderer,
+		staging: &mut StagingPool,
+		viewport: Viewport,
+		view_proj: Mat4,
+		scene: &Scene,
+		settings: &GraphicsSettings,
+	) {
+		let slot = match viewport {
+			Viewport::Left => 0,
+			Viewport::Right => 1,
+		};
+		let previous_view_proj = match settings.camera_motion_blur {
+			true => self.previous_view_proj[slot].unwrap_or(view_proj),
+			false => view_proj,
+		};
+		self.previous_view_proj[slot] = Some(view_proj);
+
+		self.draws.clear();
+		let mut data = Vec::new();
+		for (_, object) in scene.visible() {
+			let geometry = match &object.geometry {
+				Some(geometry) => geometry,
+				None => continue,
+			};
+			let (previous, current) = object.rendered_transforms();
+			let previous = match settings.object_motion_blur {
+				true => previous,
+				false => current,
+			};
+
+			self.meshes.upload(renderer, geometry);
+			self.draws.push(Draw {
+				mesh: geometry.key,
+				offset: data.len() as u32,
+			});
+
+			let matrices = [view_proj * current, previous_view_proj * previous];
+			data.extend(
+				matrices
+					.iter()
+					.flat_map(|m| m.to_cols_array())
+					.flat_map(|v| v.to_ne_bytes()),
+			);
+			data.resize(
+				data.len() + (OBJECT_UNIFORM_STRIDE - OBJECT_UNIFORM_SIZE) as usize,
+				0,
+			);
+		}
+
+		if self.draws.len() > self.object_capacity {
+			self.object_capacity = self.draws.len().next_power_of_two();
+			let (buffer, bg) =
+				create_object_uniforms(renderer, &self.object_bgl, self.object_capacity);
+			self.object_uniforms = buffer;
+			self.object_bg = bg;
+		}
+		if !data.is_empty() {
+			staging.write(renderer, &self.object_uniforms, 0, &data);
+		}
+
+		let params: [f32; 4] = [
+			settings.motion_blur_samples as f32,
+			settings.shutter_angle / 360.0,
+			0.0,
+			0.0,
+		];
+		let bytes: Vec<u8> = params.iter().flat_map(|v| v.to_ne_bytes()).collect();
+		renderer.queue.write_buffer(&self.blur_uniforms, 0, &bytes);
+	}
+
+	/// Moves the previous camera of each viewport along with a scene origin
+	/// that moved by `offset`, see [`Scene::rebase`].
+	pub fn rebase(&mut self, offset: Vec3) {
+		let shift = Mat4::from_translation(offset);
+		for view_proj in self.previous_view_proj.iter_mut().flatten() {
+			*view_proj *= shift;
+		}
+	}
+
+	/// Forgets the previous camera of each viewport, so the next frame isn't
+	/// blurred across a camera cut.
+	pub fn reset_camera_history(&mut self) {
+		self.previous_view_proj = [None; 2];
+	}
+
+	/// Adds the velocity and blur passes, replacing the scene color in
+	/// `state` with the blurred result so tonemapping picks it up. Returns the
+	/// velocity target.
+	pub fn add_to_graph<'node>(
+		&'node self,
+		graph: &mut RenderGraph<'node>,
+		state: &mut BaseRenderGraphIntermediateState,
+		resolution: UVec2,
+	) -> RenderTargetHandle {
+		let velocity = graph.add_render_target(RenderTargetDescriptor {
+			label: Some("motion vectors".into()),
+			resolution,
+			samples: SampleCount::One,
+			format: VELOCITY_FORMAT,
+			usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+		});
+		let blurred = graph.add_render_target(RenderTargetDescriptor {
+			label: Some("motion blurred color".into()),
+			resolution,
+			samples: SampleCount::One,
+			format: TextureFormat::Rgba16Float,
+			usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+		});
+
+		let mut builder = graph.add_node("Motion Vectors");
+
+		let velocity_handle = builder.add_render_target_output(velocity);
+		let depth_handle = builder.add_render_target_input(state.depth);
+
+		let rpass_handle = builder.add_renderpass(RenderPassTargets {
+			targets: vec![RenderPassTarget {
+				color: velocity_handle,
+				clear: Color::BLACK,
+				resolve: None,
+			}],
+			depth_stencil: Some(RenderPassDepthTarget {
+				target: DepthHandle::RenderTarget(depth_handle),
+				depth_clear: Some(0.0),
+				stencil_clear: None,
+			}),
+		});
+
+		let pt_handle = builder.passthrough_ref(self);
+
+		builder.build(
+			move |pt, _renderer, encoder_or_pass, _temps, _ready, _graph_data| {
+				let this = pt.get(pt_handle);
+				let rpass = encoder_or_pass.get_rpass(rpass_handle);
+
+				rpass.set_pipeline(&this.vector_pipeline);
+				for draw in &this.draws {
+					if let Some(mesh) = this.meshes.get(draw.mesh) {
+						rpass.set_bind_group(0, &this.object_bg, &[draw.offset]);
+						mesh.draw(rpass);
+					}
+				}
+			},
+		);
+
+		let mut builder = graph.add_node("Motion Blur");
+
+		let color_handle = builder.add_render_target_input(state.resolve.unwrap_or(state.color));
+		let velocity_handle = builder.add_render_target_input(velocity);
+		let output_handle = builder.add_render_target_output(blurred);
+
+		let rpass_handle = builder.add_renderpass(RenderPassTargets {
+			targets: vec![RenderPassTarget {
+				color: output_handle,
+				clear: Color::BLACK,
+				resolve: None,
+			}],
+			depth_stencil: None,
+		});
+
+		let pt_handle = builder.passthrough_ref(self);
+
+		builder.build(
+			move |pt, renderer, encoder_or_pass, temps, _ready, graph_data| {
+				let this = pt.get(pt_handle);
+				let rpass = encoder_or_pass.get_rpass(rpass_handle);
+				let color = graph_data.get_render_target(color_handle);
+				let velocity = graph_data.get_render_target(velocity_handle);
+
+				let bg = temps.add(
+					BindGroupBuilder::new()
+						.append_texture_view(color)
+						.append_texture_view(velocity)
+						.append_buffer(&this.blur_uniforms)
+						.build(&renderer.device, Some("motion blur bg"), &this.blur_bgl),
+				);
+
+				rpass.set_pipeline(&this.blur_pipeline);
+				rpass.set_bind_group(0, bg, &[]);
+				rpass.draw(0..3, 0..1);
+			},
+		);
+
+		state.color = blurred;
+		state.resolve = None;
+
+		velocity
+	}
+}
+
+fn create_object_uniforms(
+	renderer: &Renderer,
+	bgl: &BindGroupLayout,
+	capacity: usize,
+) -> (Buffer, BindGroup) {
+	let buffer = renderer.device.create_buffer(&BufferDescriptor {
+		label: Some("motion vectors object uniforms"),
+		size: capacity as BufferAddress * OBJECT_UNIFORM_STRIDE,
+		usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+		mapped_at_creation: false,
+	});
+	let bg = BindGroupBuilder::new()
+		.append_buffer_with_size(&buffer, OBJECT_UNIFORM_SIZE)
+		.build(&renderer.device, Some("motion vectors object bg"), bgl);
+	(buffer, bg)
+}
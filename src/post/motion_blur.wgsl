@@ -0,0 +1,51 @@
+// Blurs the hdr color along the motion vector of each pixel.
+
+struct VertexOutput {
+	[[builtin(position)]] position: vec4<f32>;
+};
+
+struct MotionBlurUniforms {
+	// x: sample count
+	// y: fraction of the frame the shutter is open (shutter angle / 360)
+	params: vec4<f32>;
+};
+
+[[group(0), binding(0)]]
+var color: texture_2d<f32>;
+
+[[group(0), binding(1)]]
+var velocity: texture_2d<f32>;
+
+[[group(0), binding(2)]]
+var<uniform> uniforms: MotionBlurUniforms;
+
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] vertex_index: u32) -> VertexOutput {
+	// single triangle covering the whole screen
+	let x = f32(i32(vertex_index & 1u) * 4 - 1);
+	let y = f32(i32(vertex_index & 2u) * 2 - 1);
+	var out: VertexOutput;
+	out.position = vec4<f32>(x, y, 0.0, 1.0);
+	return out;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let size = textureDimensions(color);
+	let pixel = vec2<i32>(in.position.xy);
+
+	// ndc delta to pixels, y is flipped between the two
+	let motion = textureLoad(velocity, pixel, 0).xy * vec2<f32>(0.5, -0.5) * vec2<f32>(size)
+		* uniforms.params.y;
+
+	let samples = max(i32(uniforms.params.x), 1);
+	var sum = vec4<f32>(0.0);
+	for (var i: i32 = 0; i < samples; i = i + 1) {
+		// spread samples across the shutter interval, centered on this frame
+		let t = (f32(i) + 0.5) / f32(samples) - 0.5;
+		let offset = vec2<i32>(in.position.xy + motion * t);
+		sum = sum + textureLoad(color, clamp(offset, vec2<i32>(0), size - vec2<i32>(1)), 0);
+	}
+
+	return sum / f32(samples);
+}
@@ -0,0 +1,35 @@
+// Screen space motion of each object between the previous and current frame,
+// covering both object and camera movement.
+
+struct ObjectUniforms {
+	// view projection * model for this frame and the last
+	current: mat4x4<f32>;
+	previous: mat4x4<f32>;
+};
+
+struct VertexOutput {
+	[[builtin(position)]] position: vec4<f32>;
+	[[location(0)]] current: vec4<f32>;
+	[[location(1)]] previous: vec4<f32>;
+};
+
+[[group(0), binding(0)]]
+var<uniform> object: ObjectUniforms;
+
+[[stage(vertex)]]
+fn vs_main([[location(0)]] position: vec3<f32>) -> VertexOutput {
+	let p = vec4<f32>(position, 1.0);
+	var out: VertexOutput;
+	out.position = object.current * p;
+	out.current = out.position;
+	out.previous = object.previous * p;
+	return out;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec2<f32> {
+	let current = in.current.xy / in.current.w;
+	let previous = in.previous.xy / in.previous.w;
+	// ndc delta, converted to pixels by the blur pass
+	return current - previous;
+}
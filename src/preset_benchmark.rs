@@ -0,0 +1,207 @@
+use std::path::Path;
+use std::time::Duration;
+
+use rend3::ExtendedAdapterInfo;
+use serde_json::{json, Map, Value};
+use wgpu::DeviceType;
+
+use crate::config::{load_config_entry, save_config_entry};
+use crate::settings::{GraphicsPreset, GraphicsSettings};
+use crate::tasks::{TaskHandle, TaskManager};
+
+const CONFIG_KEY: &str = "graphics_preset";
+/// adapter the benchmark ran on and each preset's frame time
+const RESULTS_KEY: &str = "graphics_benchmark";
+/// frames left untimed after switching presets, while pipelines compile and
+/// uploads settle
+const SETTLE_FRAMES: u32 = 60;
+const TIMED_FRAMES: u32 = 120;
+/// how far behind the refresh rate frames may fall and still keep up
+const KEEPING_UP: f32 = 1.2;
+
+/// Picks the graphics preset for the machine by rendering the scene the app
+/// starts with at each preset, from high down, until one keeps up with the
+/// monitor's refresh rate. Runs on the first launch and again when the
+/// adapter changes. The pick and the frame times are saved to the config,
+/// and so is any preset chosen in the settings later.
+pub struct PresetBenchmark {
+	/// preset being timed, `None` while the benchmark isn't running
+	timing: Option<GraphicsPreset>,
+	frames: u32,
+	timed: Duration,
+	/// average frame time of the presets timed so far, in milliseconds
+	results: Vec<(GraphicsPreset, f32)>,
+	task: Option<TaskHandle>,
+	adapter: String,
+	/// preset in the config
+	saved: Option<GraphicsPreset>,
+}
+
+impl PresetBenchmark {
+	/// Applies the preset saved in the config, and starts the benchmark if
+	/// this adapter hasn't been benchmarked. Software adapters get the low
+	/// preset without one.
+	pub fn start(
+		path: &Path,
+		adapter: &ExtendedAdapterInfo,
+		graphics: &mut GraphicsSettings,
+		tasks: &mut TaskManager,
+	) -> Self {
+		let saved = load_config_entry(path, CONFIG_KEY).and_then(|value| {
+			let label = value.as_str()?.to_owned();
+			GraphicsPreset::ALL
+				.into_iter()
+				.find(|preset| preset.label() == label)
+		});
+		let benchmarked = load_config_entry(path, RESULTS_KEY)
+			.and_then(|results| Some(results.get("adapter")?.as_str()? == adapter.name))
+			.unwrap_or(false);
+		let mut benchmark = Self {
+			timing: None,
+			frames: 0,
+			timed: Duration::ZERO,
+			results: Vec::new(),
+			task: None,
+			adapter: adapter.name.clone(),
+			saved,
+		};
+		if let Some(preset) = saved {
+			preset.apply(graphics);
+		}
+		if benchmarked {
+			return benchmark;
+		}
+		if adapter.device_type == DeviceType::Cpu {
+			log::info!("{} renders in software, using the low preset", adapter.name);
+			GraphicsPreset::Low.apply(graphics);
+			benchmark.finish(path, GraphicsPreset::Low);
+		} else {
+			benchmark.restart(graphics, tasks);
+		}
+		benchmark
+	}
+
+	/// Times the presets again from high down, like after a driver update.
+	pub fn restart(&mut self, graphics: &mut GraphicsSettings, tasks: &mut TaskManager) {
+		self.results.clear();
+		self.task = Some(tasks.begin("graphics benchmark"));
+		self.time(GraphicsPreset::High, graphics);
+	}
+
+	pub fn is_running(&self) -> bool {
+		self.timing.is_some()
+	}
+
+	fn time(&mut self, preset: GraphicsPreset, graphics: &mut GraphicsSettings) {
+		preset.apply(graphics);
+		self.timing = Some(preset);
+		self.frames = 0;
+		self.timed = Duration::ZERO;
+	}
+
+	/// Times a frame while the benchmark runs, moving on to the next preset
+	/// once it has enough of them. `refresh_rate` is the monitor's, frames
+	/// faster than it can't be told apart with vsync on.
+	pub fn frame(
+		&mut self,
+		delta_time: Duration,
+		refresh_rate: Option<f32>,
+		graphics: &mut GraphicsSettings,
+		path: &Path,
+	) {
+		let preset = match self.timing {
+			Some(preset) => preset,
+			None => return,
+		};
+		if self.task.as_ref().is_some_and(TaskHandle::is_cancelled) {
+			// the dropped handle shows the task as cancelled
+			self.task = None;
+			self.timing = None;
+			self.saved.unwrap_or(GraphicsPreset::Medium).apply(graphics);
+			return;
+		}
+
+		self.frames += 1;
+		if let Some(task) = &self.task {
+			let per_preset = (SETTLE_FRAMES + TIMED_FRAMES) as f32;
+			let done = self.results.len() as f32 + self.frames as f32 / per_preset;
+			task.set_progress(
+				done / GraphicsPreset::ALL.len() as f32,
+				format!("timing the {} preset", preset.label()),
+			);
+		}
+		if self.frames <= SETTLE_FRAMES {
+			return;
+		}
+		self.timed += delta_time;
+		if self.frames < SETTLE_FRAMES + TIMED_FRAMES {
+			return;
+		}
+
+		let average = self.timed.as_secs_f32() / TIMED_FRAMES as f32;
+		self.results.push((preset, average * 1000.0));
+		let target = 1.0 / refresh_rate.unwrap_or(60.0);
+		match cheaper(preset) {
+			Some(next) if average > target * KEEPING_UP => self.time(next, graphics),
+			_ => {
+				self.timing = None;
+				self.finish(path, preset);
+			}
+		}
+	}
+
+	/// Saves the preset the settings are at if it's a different one, so it's
+	/// used on the next launch. Settings changed by hand keep the last
+	/// preset saved.
+	pub fn remember(&mut self, graphics: &GraphicsSettings, path: &Path) {
+		if self.is_running() {
+			return;
+		}
+		if let Some(preset) = GraphicsPreset::matching(graphics) {
+			if self.saved != Some(preset) {
+				self.save(path, preset);
+			}
+		}
+	}
+
+	/// Saves the picked preset along with the frame times that led to it.
+	fn finish(&mut self, path: &Path, preset: GraphicsPreset) {
+		let times: Vec<String> = self
+			.results
+			.iter()
+			.map(|(preset, ms)| format!("{} {:.1}ms", preset.label(), ms))
+			.collect();
+		let summary = format!("using the {} preset ({})", preset.label(), times.join(", "));
+		log::info!("graphics benchmark: {}", summary);
+		if let Some(task) = self.task.take() {
+			task.finish(Ok(summary));
+		}
+
+		self.save(path, preset);
+		let frame_ms: Map<String, Value> = self
+			.results
+			.iter()
+			.map(|(preset, ms)| (preset.label().to_owned(), json!(ms)))
+			.collect();
+		let results = json!({ "adapter": self.adapter, "frame_ms": frame_ms });
+		if let Err(e) = save_config_entry(path, RESULTS_KEY, results) {
+			log::error!("failed to save the graphics benchmark: {}", e);
+		}
+	}
+
+	fn save(&mut self, path: &Path, preset: GraphicsPreset) {
+		self.saved = Some(preset);
+		if let Err(e) = save_config_entry(path, CONFIG_KEY, Value::from(preset.label())) {
+			log::error!("failed to save the graphics preset: {}", e);
+		}
+	}
+}
+
+/// The next preset down, `None` for the cheapest.
+fn cheaper(preset: GraphicsPreset) -> Option<GraphicsPreset> {
+	match preset {
+		GraphicsPreset::High => Some(GraphicsPreset::Medium),
+		GraphicsPreset::Medium => Some(GraphicsPreset::Low),
+		GraphicsPreset::Low => None,
+	}
+}
@@ -0,0 +1,219 @@
+use rend3::graph::RenderGraph;
+use rend3::Renderer;
+use rend3_routine::base::BaseRenderGraphIntermediateState;
+use rend3_routine::common::{PerMaterialArchetypeInterface, WholeFrameInterfaces};
+use rend3_routine::depth::DepthRoutine;
+use rend3_routine::forward::ForwardRoutine;
+use rend3_routine::pbr::{PbrMaterial, PbrRoutine};
+use wgpu::{BlendState, Features};
+
+/// shadow culling and rendering. The shadow maps keep what was last drawn
+/// into them while it's off.
+pub const SHADOWS: &str = "shadows";
+/// lit materials. The depth prepass still runs, so overlays are drawn over
+/// black while it's off.
+pub const PBR: &str = "pbr";
+/// depth of opaque and cutout objects drawn before they're lit, so only the
+/// nearest surface is lit. Lit passes test depth themselves while it's off.
+pub const DEPTH_PREPASS: &str = "depth prepass";
+/// off by default. Lights only opaque objects, cutout ones show as black
+/// shapes since the prepass already drew their depth, blended ones vanish.
+pub const OPAQUE_ONLY: &str = "opaque only";
+pub const MATERIAL_OVERRIDES: &str = "material overrides";
+pub const SECTION_CAPS: &str = "section caps";
+/// lines and shapes from [`crate::debug::DebugDraw`]
+pub const DEBUG_DRAW: &str = "debug draw";
+/// the target inspector and pixel picker. Picks wait until it's back on.
+pub const DEBUG_TOOLS: &str = "debug tools";
+pub const EGUI: &str = "egui";
+
+/// A pass drawn over the lit scene before post processing, added to the
+/// graph with [`RenderPasses::register`].
+pub trait ScenePass {
+	fn add_to_graph<'node>(
+		&'node self,
+		graph: &mut RenderGraph<'node>,
+		state: &BaseRenderGraphIntermediateState,
+	);
+}
+
+struct Entry {
+	name: &'static str,
+	enabled: bool,
+	/// `None` for the passes the app adds itself
+	pass: Option<Box<dyn ScenePass>>,
+	/// frame time when the pass was last turned on or off, in milliseconds
+	toggled_at: Option<f32>,
+}
+
+/// The passes the render graph is built from each frame, by name, and
+/// whether they're drawn. Passes the app builds in are checked with
+/// [`RenderPasses::is_enabled`] as the graph is put together, registered
+/// ones are drawn in the order they were registered after the built in
+/// scene overlays.
+pub struct RenderPasses {
+	entries: Vec<Entry>,
+	/// smoothed frame time in milliseconds, see [`RenderPasses::frame_time`]
+	frame_time: Option<f32>,
+}
+
+impl Default for RenderPasses {
+	fn default() -> Self {
+		let builtin = |name| Entry {
+			name,
+			enabled: name != OPAQUE_ONLY,
+			pass: None,
+			toggled_at: None,
+		};
+		Self {
+			entries: [
+				SHADOWS,
+				DEPTH_PREPASS,
+				PBR,
+				OPAQUE_ONLY,
+				MATERIAL_OVERRIDES,
+				SECTION_CAPS,
+				DEBUG_DRAW,
+				DEBUG_TOOLS,
+				EGUI,
+			]
+			.into_iter()
+			.map(builtin)
+			.collect(),
+			frame_time: None,
+		}
+	}
+}
+
+impl RenderPasses {
+	/// Whether the pass is drawn this frame. Names that aren't registered
+	/// aren't.
+	pub fn is_enabled(&self, name: &str) -> bool {
+		self.entries
+			.iter()
+			.any(|entry| entry.name == name && entry.enabled)
+	}
+
+	pub fn set_enabled(&mut self, name: &str, enabled: bool) {
+		let frame_time = self.frame_time;
+		match self.entries.iter_mut().find(|entry| entry.name == name) {
+			Some(entry) if entry.enabled != enabled => {
+				entry.enabled = enabled;
+				entry.toggled_at = frame_time;
+			}
+			Some(_) => {}
+			None => log::warn!("no render pass named {}", name),
+		}
+	}
+
+	/// Adds a scene pass, enabled. A pass registered under a name that's
+	/// taken replaces it, built in passes can't be replaced.
+	pub fn register(&mut self, name: &'static str, pass: Box<dyn ScenePass>) {
+		match self.entries.iter_mut().find(|entry| entry.name == name) {
+			Some(entry) if entry.pass.is_none() => {
+				log::warn!("{} is a built in render pass, not replacing it", name)
+			}
+			Some(entry) => {
+				entry.enabled = true;
+				entry.pass = Some(pass);
+			}
+			None => self.entries.push(Entry {
+				name,
+				enabled: true,
+				pass: Some(pass),
+				toggled_at: None,
+			}),
+		}
+	}
+
+	/// Takes the time the last frame took, gpu time where the adapter can
+	/// measure it, to show how much turning passes on or off changed it.
+	pub fn frame_time(&mut self, milliseconds: f32) {
+		self.frame_time = Some(match self.frame_time {
+			// evens out the noise between frames
+			Some(average) => average + (milliseconds - average) * 0.05,
+			None => milliseconds,
+		});
+	}
+
+	/// Adds the enabled registered passes to the graph.
+	pub fn add_to_graph<'node>(
+		&'node self,
+		graph: &mut RenderGraph<'node>,
+		state: &BaseRenderGraphIntermediateState,
+	) {
+		for entry in &self.entries {
+			if let (true, Some(pass)) = (entry.enabled, &entry.pass) {
+				pass.add_to_graph(graph, state);
+			}
+		}
+	}
+
+	/// Checkboxes for every pass, next to how much the frame time changed
+	/// since each was last turned on or off. The ui can't be turned off from
+	/// here, it would take the checkbox to turn it back on with it.
+	pub fn ui(&mut self, ui: &mut egui::Ui) {
+		egui::Grid::new("render_passes").show(ui, |ui| {
+			for entry in &mut self.entries {
+				let toggled = ui
+					.add_enabled_ui(entry.name != EGUI, |ui| {
+						ui.checkbox(&mut entry.enabled, entry.name)
+					})
+					.inner;
+				if toggled.changed() {
+					entry.toggled_at = self.frame_time;
+				}
+				match (entry.toggled_at, self.frame_time) {
+					(Some(before), Some(now)) => {
+						ui.label(format!("{:+.2}ms", now - before))
+							.on_hover_text("frame time change since it was last toggled");
+					}
+					_ => {
+						ui.label("");
+					}
+				}
+				ui.end_row();
+			}
+		});
+	}
+}
+
+/// The pbr routine rend3 makes, but with lit passes that test and write
+/// depth themselves rather than drawing where the depth prepass matches.
+/// Drawn with while the prepass is off.
+pub fn pbr_without_prepass(renderer: &Renderer, interfaces: &WholeFrameInterfaces) -> PbrRoutine {
+	let mut data_core = renderer.data_core.lock();
+	data_core
+		.material_manager
+		.ensure_archetype::<PbrMaterial>(&renderer.device, renderer.profile);
+	let per_material =
+		PerMaterialArchetypeInterface::<PbrMaterial>::new(&renderer.device, renderer.profile);
+	let depth_pipelines = DepthRoutine::<PbrMaterial>::new(
+		renderer,
+		&data_core,
+		interfaces,
+		&per_material,
+		renderer.features.contains(Features::DEPTH_CLIP_CONTROL),
+	);
+	let mut forward = |blend, label| {
+		ForwardRoutine::new(
+			renderer,
+			&mut data_core,
+			interfaces,
+			&per_material,
+			None,
+			None,
+			&[],
+			blend,
+			false,
+			label,
+		)
+	};
+	PbrRoutine {
+		opaque_routine: forward(None, "opaque pass"),
+		cutout_routine: forward(None, "cutout pass"),
+		blend_routine: forward(Some(BlendState::ALPHA_BLENDING), "blend forward pass"),
+		depth_pipelines,
+		per_material,
+	}
+}
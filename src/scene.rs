@@ -0,0 +1,1366 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use glam::{DMat4, DQuat, DVec3, Mat4, Quat, Vec3, Vec3A};
+use rend3::types::{MaterialHandle, Mesh, MeshHandle, Object, ObjectHandle, ObjectMeshKind};
+use rend3::util::typedefs::FastHashMap;
+use rend3::Renderer;
+
+use crate::material_override::MaterialOverride;
+use crate::metadata::{Metadata, MetadataValue};
+use crate::modifiers::TransformModifier;
+use crate::skeleton::Joint;
+
+/// Matrix object transforms are stored in. With the `double-precision`
+/// feature it's f64, for scenes with coordinates too large for f32 like
+/// geospatial or space scenes. Either way the renderer gets f32 transforms
+/// relative to the [`Scene::origin`].
+#[cfg(not(feature = "double-precision"))]
+pub type Transform = Mat4;
+#[cfg(feature = "double-precision")]
+pub type Transform = DMat4;
+
+/// Conversions between the scene's [`Transform`] and the f32 matrices the
+/// renderer takes.
+pub trait SceneTransform: Copy {
+	fn from_mat4(matrix: Mat4) -> Self;
+	fn from_f64_translation(translation: DVec3) -> Self;
+	fn to_mat4(&self) -> Mat4;
+	/// Column major, for saving without losing f64 precision.
+	fn to_f64_array(&self) -> [f64; 16];
+	fn from_f64_array(array: &[f64; 16]) -> Self;
+	/// Blends scale, rotation and translation separately, `alpha` going from
+	/// 0.0 (`self`) to 1.0 (`to`).
+	fn interpolate(&self, to: &Self, alpha: f32) -> Self;
+}
+
+impl SceneTransform for Mat4 {
+	fn from_mat4(matrix: Mat4) -> Self {
+		matrix
+	}
+
+	fn from_f64_translation(translation: DVec3) -> Self {
+		Mat4::from_translation(translation.as_vec3())
+	}
+
+	fn to_mat4(&self) -> Mat4 {
+		*self
+	}
+
+	fn to_f64_array(&self) -> [f64; 16] {
+		self.as_dmat4().to_cols_array()
+	}
+
+	fn from_f64_array(array: &[f64; 16]) -> Self {
+		DMat4::from_cols_array(array).as_mat4()
+	}
+
+	fn interpolate(&self, to: &Self, alpha: f32) -> Self {
+		let (from_scale, from_rotation, from_translation) = self.to_scale_rotation_translation();
+		let (to_scale, to_rotation, to_translation) = to.to_scale_rotation_translation();
+
+		Mat4::from_scale_rotation_translation(
+			Vec3::lerp(from_scale, to_scale, alpha),
+			Quat::slerp(from_rotation, to_rotation, alpha),
+			Vec3::lerp(from_translation, to_translation, alpha),
+		)
+	}
+}
+
+impl SceneTransform for DMat4 {
+	fn from_mat4(matrix: Mat4) -> Self {
+		matrix.as_dmat4()
+	}
+
+	fn from_f64_translation(translation: DVec3) -> Self {
+		DMat4::from_translation(translation)
+	}
+
+	fn to_mat4(&self) -> Mat4 {
+		self.as_mat4()
+	}
+
+	fn to_f64_array(&self) -> [f64; 16] {
+		self.to_cols_array()
+	}
+
+	fn from_f64_array(array: &[f64; 16]) -> Self {
+		DMat4::from_cols_array(array)
+	}
+
+	fn interpolate(&self, to: &Self, alpha: f32) -> Self {
+		let alpha = alpha as f64;
+		let (from_scale, from_rotation, from_translation) = self.to_scale_rotation_translation();
+		let (to_scale, to_rotation, to_translation) = to.to_scale_rotation_translation();
+
+		DMat4::from_scale_rotation_translation(
+			DVec3::lerp(from_scale, to_scale, alpha),
+			DQuat::slerp(from_rotation, to_rotation, alpha),
+			DVec3::lerp(from_translation, to_translation, alpha),
+		)
+	}
+}
+
+/// World space point in the same precision as [`Transform`], like the scene's
+/// origin and the camera's position. Offsets from the origin are small, so
+/// they're taken at full precision and only then narrowed to f32.
+#[cfg(not(feature = "double-precision"))]
+pub type Position = Vec3A;
+#[cfg(feature = "double-precision")]
+pub type Position = DVec3;
+
+/// Conversions between the scene's [`Position`] and f32 vectors.
+pub trait ScenePosition: Copy {
+	fn from_vec3a(vector: Vec3A) -> Self;
+	fn from_f64(vector: DVec3) -> Self;
+	/// Loses precision far from the world origin.
+	fn to_vec3a(&self) -> Vec3A;
+	fn to_f64(&self) -> DVec3;
+	/// Moved by a small f32 offset.
+	fn offset(&self, offset: Vec3A) -> Self;
+	/// `self - origin`, subtracted before narrowing to f32.
+	fn relative_to(&self, origin: Self) -> Vec3A;
+}
+
+impl ScenePosition for Vec3A {
+	fn from_vec3a(vector: Vec3A) -> Self {
+		vector
+	}
+
+	fn from_f64(vector: DVec3) -> Self {
+		vector.as_vec3().into()
+	}
+
+	fn to_vec3a(&self) -> Vec3A {
+		*self
+	}
+
+	fn to_f64(&self) -> DVec3 {
+		Vec3::from(*self).as_dvec3()
+	}
+
+	fn offset(&self, offset: Vec3A) -> Self {
+		*self + offset
+	}
+
+	fn relative_to(&self, origin: Self) -> Vec3A {
+		*self - origin
+	}
+}
+
+impl ScenePosition for DVec3 {
+	fn from_vec3a(vector: Vec3A) -> Self {
+		Vec3::from(vector).as_dvec3()
+	}
+
+	fn from_f64(vector: DVec3) -> Self {
+		vector
+	}
+
+	fn to_vec3a(&self) -> Vec3A {
+		self.as_vec3().into()
+	}
+
+	fn to_f64(&self) -> DVec3 {
+		*self
+	}
+
+	fn offset(&self, offset: Vec3A) -> Self {
+		*self + Vec3::from(offset).as_dvec3()
+	}
+
+	fn relative_to(&self, origin: Self) -> Vec3A {
+		(*self - origin).as_vec3().into()
+	}
+}
+
+/// Identifies an object in the [`Scene`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ObjectId(u64);
+
+/// CPU copy of a mesh's geometry, for passes that draw objects outside of
+/// rend3's own routines.
+pub struct MeshGeometry {
+	/// raw index of the mesh handle, unique while the mesh is alive
+	pub key: usize,
+	pub positions: Vec<Vec3>,
+	pub indices: Vec<u32>,
+	/// bounding sphere in mesh space
+	pub center: Vec3,
+	pub radius: f32,
+}
+
+/// An object in the scene along with the transform it had on the previous
+/// simulation step.
+pub struct SceneObject {
+	pub name: String,
+	/// `None` while the object is hidden
+	handle: Option<ObjectHandle>,
+	/// geometry if the mesh was added through [`Scene::add_mesh`]
+	pub geometry: Option<Arc<MeshGeometry>>,
+	/// tint and emission drawn over the object's material
+	pub material_override: Option<MaterialOverride>,
+	pub metadata: Metadata,
+	/// run in order by [`Scene::step_modifiers`]
+	pub modifiers: Vec<TransformModifier>,
+	/// what the object was added with, for copies
+	mesh_kind: ObjectMeshKind,
+	material: MaterialHandle,
+	parent: Option<ObjectId>,
+	/// joint of the parent the object is attached at
+	socket: Option<usize>,
+	/// relative to the parent or its joint, the same as `transform` without
+	/// one
+	local_transform: Transform,
+	/// object space transforms of the skeleton's joints from the last
+	/// [`Scene::set_joint_transforms`]
+	joints: Vec<Mat4>,
+	inverse_bind_transforms: Vec<Mat4>,
+	/// joints have to be sent to the renderer on the next `submit`
+	joints_changed: bool,
+	/// names and hierarchy of the joints, from [`Scene::set_skeleton`]
+	skeleton: Vec<Joint>,
+	transform: Transform,
+	previous_transform: Transform,
+	/// last transform sent to the renderer
+	submitted_transform: Mat4,
+	/// transform sent to the renderer the frame before
+	previous_submitted_transform: Mat4,
+	/// skip motion vectors for the next submitted frame
+	teleported: bool,
+}
+
+impl SceneObject {
+	pub fn transform(&self) -> Transform {
+		self.transform
+	}
+
+	/// The object it moves with, see [`Scene::attach`].
+	pub fn parent(&self) -> Option<ObjectId> {
+		self.parent
+	}
+
+	/// Joint of the parent it's attached at, see [`Scene::set_socket`].
+	pub fn socket(&self) -> Option<usize> {
+		self.socket
+	}
+
+	/// Joints objects can be attached at, empty for objects that aren't
+	/// animated.
+	pub fn joints(&self) -> &[Mat4] {
+		&self.joints
+	}
+
+	/// Names and parents of the joints, empty if they weren't given.
+	pub fn skeleton(&self) -> &[Joint] {
+		&self.skeleton
+	}
+
+	/// Transform relative to the parent, or the world without one.
+	pub fn local_transform(&self) -> Transform {
+		self.local_transform
+	}
+
+	pub fn is_visible(&self) -> bool {
+		self.handle.is_some()
+	}
+
+	pub fn material(&self) -> &MaterialHandle {
+		&self.material
+	}
+
+	pub fn previous_transform(&self) -> Transform {
+		self.previous_transform
+	}
+
+	/// Transform between the previous and current simulation step, `alpha`
+	/// going from 0.0 (previous) to 1.0 (current).
+	pub fn interpolated_transform(&self, alpha: f32) -> Transform {
+		self.previous_transform.interpolate(&self.transform, alpha)
+	}
+
+	/// Transforms the object was rendered with on the previous and current
+	/// frame, for motion vectors. Relative to the scene's origin.
+	pub fn rendered_transforms(&self) -> (Mat4, Mat4) {
+		(self.previous_submitted_transform, self.submitted_transform)
+	}
+
+	/// Copy of everything the object was added with, to add it again later.
+	pub fn snapshot(&self) -> ObjectSnapshot {
+		ObjectSnapshot {
+			name: self.name.clone(),
+			mesh_kind: self.mesh_kind.clone(),
+			material: self.material.clone(),
+			transform: self.transform,
+			material_override: self.material_override,
+			metadata: self.metadata.clone(),
+			modifiers: self.modifiers.clone(),
+			parent: self.parent,
+			socket: self.socket,
+		}
+	}
+}
+
+/// An object as it was when [`SceneObject::snapshot`] was taken, so it can be
+/// restored after being removed or added as a copy.
+#[derive(Clone)]
+pub struct ObjectSnapshot {
+	pub name: String,
+	pub mesh_kind: ObjectMeshKind,
+	pub material: MaterialHandle,
+	pub transform: Transform,
+	pub material_override: Option<MaterialOverride>,
+	pub metadata: Metadata,
+	pub modifiers: Vec<TransformModifier>,
+	/// attached to again on restore if it still exists
+	pub parent: Option<ObjectId>,
+	pub socket: Option<usize>,
+}
+
+/// Objects added to the renderer, keeping a transform history so the
+/// renderer can interpolate between fixed simulation steps and generate
+/// motion vectors.
+///
+/// Transforms are in world space, but the renderer is given them relative to
+/// a floating [`Scene::origin`] so objects far from the world origin keep
+/// their precision. [`SceneObject::rendered_transforms`] and the view of the
+/// camera are relative to the same origin.
+#[derive(Default)]
+pub struct Scene {
+	objects: FastHashMap<ObjectId, SceneObject>,
+	meshes: FastHashMap<usize, Arc<MeshGeometry>>,
+	next_id: u64,
+	origin: Position,
+	/// every transform has to be sent again after the origin moved
+	rebased: bool,
+	/// objects attached to each object, see [`Scene::attach`]
+	children: FastHashMap<ObjectId, Vec<ObjectId>>,
+	/// objects added or changed since [`Scene::clear_changes`]
+	dirty: BTreeSet<ObjectId>,
+	/// names of objects removed since [`Scene::clear_changes`]
+	removed: Vec<String>,
+}
+
+impl Scene {
+	/// Adds a mesh to the renderer, keeping a copy of its geometry for objects
+	/// that use it.
+	pub fn add_mesh(&mut self, renderer: &Renderer, mesh: Mesh) -> MeshHandle {
+		let positions = mesh.vertex_positions.clone();
+		let indices = mesh.indices.clone();
+		let handle = renderer.add_mesh(mesh);
+
+		let min = positions
+			.iter()
+			.fold(Vec3::splat(f32::MAX), |a, b| a.min(*b));
+		let max = positions
+			.iter()
+			.fold(Vec3::splat(f32::MIN), |a, b| a.max(*b));
+		let center = (min + max) * 0.5;
+		let radius = positions
+			.iter()
+			.fold(0.0, |r: f32, p| r.max(p.distance(center)));
+
+		let key = handle.get_raw().idx;
+		self.meshes.insert(
+			key,
+			Arc::new(MeshGeometry {
+				key,
+				positions,
+				indices,
+				center,
+				radius,
+			}),
+		);
+		handle
+	}
+
+	pub fn add_object(&mut self, renderer: &Renderer, name: &str, object: Object) -> ObjectId {
+		let transform = Transform::from_mat4(object.transform);
+		self.add_object_at(renderer, name, object, transform)
+	}
+
+	/// Adds an object at a transform in the scene's precision, which takes
+	/// the place of `object`'s f32 one.
+	pub fn add_object_at(
+		&mut self,
+		renderer: &Renderer,
+		name: &str,
+		object: Object,
+		transform: Transform,
+	) -> ObjectId {
+		let id = self.reserve_id();
+		self.insert(renderer, id, name, object, transform);
+		id
+	}
+
+	/// Id for an object that will be added with [`Scene::restore`].
+	pub fn reserve_id(&mut self) -> ObjectId {
+		let id = ObjectId(self.next_id);
+		self.next_id += 1;
+		id
+	}
+
+	/// Adds an object from a snapshot under `id`, replacing any object that
+	/// has it. Used to undo removals without changing the object's id.
+	pub fn restore(&mut self, renderer: &Renderer, id: ObjectId, snapshot: ObjectSnapshot) {
+		let object = Object {
+			mesh_kind: snapshot.mesh_kind,
+			material: snapshot.material,
+			transform: snapshot.transform.to_mat4(),
+		};
+		self.insert(renderer, id, &snapshot.name, object, snapshot.transform);
+		self.next_id = self.next_id.max(id.0 + 1);
+		self.set_material_override(id, snapshot.material_override);
+		if let Some(object) = self.objects.get_mut(&id) {
+			object.metadata = snapshot.metadata;
+			object.modifiers = snapshot.modifiers;
+		}
+		if let Some(parent) = snapshot.parent {
+			if self.attach(id, parent, true) {
+				self.set_socket(id, snapshot.socket);
+			}
+		}
+	}
+
+	fn insert(
+		&mut self,
+		renderer: &Renderer,
+		id: ObjectId,
+		name: &str,
+		object: Object,
+		transform: Transform,
+	) {
+		self.insert_hidden(id, name, object, transform);
+		self.set_visible(renderer, id, true);
+	}
+
+	/// Adds an object at `transform` without giving it to the renderer, like
+	/// a hidden one. Names are kept unique, since saves match objects up by
+	/// name.
+	pub(crate) fn insert_hidden(
+		&mut self,
+		id: ObjectId,
+		name: &str,
+		object: Object,
+		transform: Transform,
+	) {
+		// replaced rather than renamed around
+		self.objects.remove(&id);
+		let name = match self.find_by_name(name) {
+			Some(_) => self.unique_name(name),
+			None => name.into(),
+		};
+		// the renderer works relative to the origin
+		let submitted_transform = relative_to_origin(self.origin, transform);
+		let geometry = match &object.mesh_kind {
+			ObjectMeshKind::Static(mesh) => self.meshes.get(&mesh.get_raw().idx).cloned(),
+			_ => None,
+		};
+
+		self.objects.insert(
+			id,
+			SceneObject {
+				name,
+				handle: None,
+				geometry,
+				material_override: None,
+				metadata: Metadata::new(),
+				modifiers: Vec::new(),
+				mesh_kind: object.mesh_kind,
+				material: object.material,
+				parent: None,
+				socket: None,
+				local_transform: transform,
+				joints: Vec::new(),
+				inverse_bind_transforms: Vec::new(),
+				joints_changed: false,
+				skeleton: Vec::new(),
+				transform,
+				previous_transform: transform,
+				submitted_transform,
+				previous_submitted_transform: submitted_transform,
+				teleported: false,
+			},
+		);
+		self.dirty.insert(id);
+	}
+
+	/// Adds a copy of an object with the same mesh and material. Returns
+	/// `None` if `template` doesn't exist.
+	pub fn spawn(
+		&mut self,
+		renderer: &Renderer,
+		template: ObjectId,
+		name: &str,
+		transform: Transform,
+	) -> Option<ObjectId> {
+		let template = self.objects.get(&template)?;
+		let object = Object {
+			mesh_kind: template.mesh_kind.clone(),
+			material: template.material.clone(),
+			transform: transform.to_mat4(),
+		};
+		Some(self.add_object_at(renderer, name, object, transform))
+	}
+
+	/// Removes an object from the scene and the renderer.
+	/// Objects attached to it are detached where they are.
+	pub fn remove_object(&mut self, id: ObjectId) {
+		for child in self.children(id).to_vec() {
+			self.detach(child);
+		}
+		self.detach(id);
+		self.children.remove(&id);
+		if let Some(object) = self.objects.remove(&id) {
+			self.dirty.remove(&id);
+			self.removed.push(object.name);
+		}
+	}
+
+	/// Makes `child` move with `parent`. With `keep_world_transform` it stays
+	/// where it is, otherwise its transform so far is taken as relative to
+	/// the parent and it jumps there. Attaching an object to itself or to one
+	/// of its descendants does nothing and returns false.
+	pub fn attach(
+		&mut self,
+		child: ObjectId,
+		parent: ObjectId,
+		keep_world_transform: bool,
+	) -> bool {
+		if !self.objects.contains_key(&child) || !self.objects.contains_key(&parent) {
+			return false;
+		}
+		// walking up from the parent mustn't reach the child
+		let mut ancestor = Some(parent);
+		while let Some(id) = ancestor {
+			if id == child {
+				return false;
+			}
+			ancestor = self.objects.get(&id).and_then(|object| object.parent);
+		}
+
+		self.detach(child);
+		let parent_transform = self.objects[&parent].transform;
+		if let Some(object) = self.objects.get_mut(&child) {
+			object.parent = Some(parent);
+			if keep_world_transform {
+				object.local_transform = parent_transform.inverse() * object.transform;
+			} else {
+				object.local_transform = object.transform;
+			}
+		}
+		self.children.entry(parent).or_default().push(child);
+		if !keep_world_transform {
+			let transform = parent_transform * self.objects[&child].local_transform;
+			self.teleport(child, transform);
+		}
+		true
+	}
+
+	/// Stops `child` moving with its parent, leaving it where it is.
+	pub fn detach(&mut self, child: ObjectId) {
+		let object = match self.objects.get_mut(&child) {
+			Some(object) => object,
+			None => return,
+		};
+		let parent = match object.parent.take() {
+			Some(parent) => parent,
+			None => return,
+		};
+		object.socket = None;
+		object.local_transform = object.transform;
+		if let Some(siblings) = self.children.get_mut(&parent) {
+			siblings.retain(|sibling| *sibling != child);
+			if siblings.is_empty() {
+				self.children.remove(&parent);
+			}
+		}
+	}
+
+	/// Attaches `child` at a joint of its parent, like a prop held in a hand,
+	/// or back to the parent itself with `None`. It stays where it is until
+	/// the joint moves. Does nothing to objects without a parent.
+	pub fn set_socket(&mut self, child: ObjectId, joint: Option<usize>) {
+		let object = match self.objects.get_mut(&child) {
+			Some(object) if object.parent.is_some() => object,
+			_ => return,
+		};
+		object.socket = joint;
+		let transform = object.transform;
+		self.move_object(child, transform, false);
+	}
+
+	/// Sets the object space transform of every joint of an animated object,
+	/// as evaluated by [`crate::animation::Animations`], and moves the
+	/// objects attached at them. The skeleton's joint matrices are updated
+	/// with `inverse_bind_transforms` on the next `submit` when the object
+	/// was added with one.
+	pub fn set_joint_transforms(
+		&mut self,
+		id: ObjectId,
+		joint_transforms: &[Mat4],
+		inverse_bind_transforms: &[Mat4],
+	) {
+		let object = match self.objects.get_mut(&id) {
+			Some(object) => object,
+			None => return,
+		};
+		object.joints.clear();
+		object.joints.extend_from_slice(joint_transforms);
+		object.inverse_bind_transforms.clear();
+		object
+			.inverse_bind_transforms
+			.extend_from_slice(inverse_bind_transforms);
+		object.joints_changed = true;
+		self.move_children(id, false);
+	}
+
+	/// Moves an object by the motion taken out of its animation's root joint
+	/// with [`crate::skeleton::RootMotion::extract`], relative to how it's
+	/// turned.
+	pub fn apply_root_motion(&mut self, id: ObjectId, motion: Mat4) {
+		if let Some(object) = self.objects.get(&id) {
+			let transform = object.transform * Transform::from_mat4(motion);
+			self.move_object(id, transform, false);
+		}
+	}
+
+	/// Names the joints of an animated object and says which hang off
+	/// which, in the order of its joint transforms.
+	pub fn set_skeleton(&mut self, id: ObjectId, skeleton: Vec<Joint>) {
+		if let Some(object) = self.objects.get_mut(&id) {
+			object.skeleton = skeleton;
+		}
+	}
+
+	/// Objects attached to `id`, in the order they were attached.
+	pub fn children(&self, id: ObjectId) -> &[ObjectId] {
+		self.children.get(&id).map_or(&[], Vec::as_slice)
+	}
+
+	pub fn get(&self, id: ObjectId) -> Option<&SceneObject> {
+		self.objects.get(&id)
+	}
+
+	/// First object named `name`.
+	pub fn find_by_name(&self, name: &str) -> Option<ObjectId> {
+		self.iter()
+			.find(|(_, object)| object.name == name)
+			.map(|(id, _)| id)
+	}
+
+	/// `base` or, if an object already has that name, `base` with the lowest
+	/// free number after it. A number already on the end of `base` is replaced.
+	pub fn unique_name(&self, base: &str) -> String {
+		let base = match base.rsplit_once(' ') {
+			Some((stem, number)) if number.parse::<u32>().is_ok() => stem,
+			_ => base,
+		};
+		let taken = |name: &str| self.find_by_name(name).is_some();
+		if !taken(base) {
+			return base.into();
+		}
+		(2..)
+			.map(|i| format!("{} {}", base, i))
+			.find(|name| !taken(name))
+			.unwrap()
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = (ObjectId, &SceneObject)> {
+		self.objects.iter().map(|(id, object)| (*id, object))
+	}
+
+	/// Marks the object changed, whether or not the metadata is.
+	pub fn metadata_mut(&mut self, id: ObjectId) -> Option<&mut Metadata> {
+		let object = self.objects.get_mut(&id)?;
+		self.dirty.insert(id);
+		Some(&mut object.metadata)
+	}
+
+	/// Modifiers aren't saved, so this doesn't mark the object changed.
+	pub fn modifiers_mut(&mut self, id: ObjectId) -> Option<&mut Vec<TransformModifier>> {
+		Some(&mut self.objects.get_mut(&id)?.modifiers)
+	}
+
+	/// Objects with the property `key` and its value on each.
+	pub fn with_metadata<'a>(
+		&'a self,
+		key: &'a str,
+	) -> impl Iterator<Item = (ObjectId, &'a MetadataValue)> + 'a {
+		self.iter()
+			.filter_map(move |(id, object)| Some((id, object.metadata.get(key)?)))
+	}
+
+	/// Objects whose property `key` is `value`, e.g. every object tagged as a
+	/// spawn point.
+	pub fn find_by_metadata<'a>(
+		&'a self,
+		key: &'a str,
+		value: &'a MetadataValue,
+	) -> impl Iterator<Item = ObjectId> + 'a {
+		self.with_metadata(key)
+			.filter(move |(_, found)| *found == value)
+			.map(|(id, _)| id)
+	}
+
+	/// Objects that aren't hidden, the ones the renderer draws.
+	pub fn visible(&self) -> impl Iterator<Item = (ObjectId, &SceneObject)> {
+		self.iter().filter(|(_, object)| object.is_visible())
+	}
+
+	/// Hides an object by taking it out of the renderer, or adds it back.
+	pub fn set_visible(&mut self, renderer: &Renderer, id: ObjectId, visible: bool) {
+		let object = match self.objects.get_mut(&id) {
+			Some(object) => object,
+			None => return,
+		};
+		match (visible, object.handle.is_some()) {
+			(true, false) => {
+				object.handle = Some(renderer.add_object(Object {
+					mesh_kind: object.mesh_kind.clone(),
+					material: object.material.clone(),
+					transform: object.submitted_transform,
+				}));
+				// don't blur from wherever it was when it was hidden
+				object.teleported = true;
+			}
+			(false, true) => object.handle = None,
+			_ => {}
+		}
+	}
+
+	/// Moves an object, and the objects attached to it along with it. The
+	/// renderer sees the change on the next `submit`.
+	pub fn set_transform(&mut self, id: ObjectId, transform: Transform) {
+		self.move_object(id, transform, false);
+	}
+
+	/// Changes the tint and emission of one object without touching its
+	/// material, which may be shared.
+	pub fn set_material_override(
+		&mut self,
+		id: ObjectId,
+		material_override: Option<MaterialOverride>,
+	) {
+		if let Some(object) = self.objects.get_mut(&id) {
+			object.material_override = material_override;
+		}
+	}
+
+	/// Moves an object without leaving a trail, so it won't be interpolated
+	/// or motion blurred from its old position.
+	pub fn teleport(&mut self, id: ObjectId, transform: Transform) {
+		self.move_object(id, transform, true);
+	}
+
+	/// Sets a world transform, keeping the local one in step, then moves the
+	/// attached objects to follow.
+	fn move_object(&mut self, id: ObjectId, transform: Transform, teleport: bool) {
+		let parent_transform = self.parent_frame(id);
+		let object = match self.objects.get_mut(&id) {
+			Some(object) => object,
+			None => return,
+		};
+		if object.transform != transform {
+			self.dirty.insert(id);
+		}
+		object.transform = transform;
+		object.local_transform = match parent_transform {
+			Some(parent) => parent.inverse() * transform,
+			None => transform,
+		};
+		if teleport {
+			object.previous_transform = transform;
+			object.teleported = true;
+		}
+
+		self.move_children(id, teleport);
+	}
+
+	fn move_children(&mut self, id: ObjectId, teleport: bool) {
+		for child in self.children(id).to_vec() {
+			if let Some(frame) = self.parent_frame(child) {
+				let local = self.objects[&child].local_transform;
+				self.move_object(child, frame * local, teleport);
+			}
+		}
+	}
+
+	/// World transform an object's local transform is relative to, its
+	/// parent's or that of the joint it's attached at. `None` without a
+	/// parent.
+	fn parent_frame(&self, id: ObjectId) -> Option<Transform> {
+		let object = self.objects.get(&id)?;
+		let parent = self.objects.get(&object.parent?)?;
+		let joint = object.socket.and_then(|joint| parent.joints.get(joint));
+		Some(match joint {
+			Some(joint) => parent.transform * Transform::from_mat4(*joint),
+			None => parent.transform,
+		})
+	}
+
+	/// Nearest object a world space ray hits within `max_distance`, and how
+	/// far along the ray. `direction` must be normalized. Only visible objects
+	/// with geometry from [`Scene::add_mesh`] are tested, from both sides.
+	pub fn raycast(
+		&self,
+		origin: Vec3,
+		direction: Vec3,
+		max_distance: f32,
+		ignore: Option<ObjectId>,
+	) -> Option<(ObjectId, f32)> {
+		let mut nearest: Option<(ObjectId, f32)> = None;
+		for (id, object) in self.visible() {
+			let geometry = match &object.geometry {
+				Some(geometry) if Some(id) != ignore => geometry,
+				_ => continue,
+			};
+			let limit = nearest.map_or(max_distance, |(_, t)| t);
+
+			// skip objects whose bounding sphere the ray misses
+			let transform = object.transform.to_mat4();
+			let scale = [transform.x_axis, transform.y_axis, transform.z_axis]
+				.iter()
+				.fold(0.0, |s: f32, axis| s.max(axis.truncate().length()));
+			let center = transform.transform_point3(geometry.center) - origin;
+			let radius = geometry.radius * scale;
+			let along = center.dot(direction);
+			if center.length_squared() - along * along > radius * radius
+				|| along + radius < 0.0
+				|| along - radius > limit
+			{
+				continue;
+			}
+
+			// the ray in mesh space keeps distances in world units
+			let inverse = transform.inverse();
+			let local_origin = inverse.transform_point3(origin);
+			let local_direction = inverse.transform_vector3(direction);
+			let hit = geometry
+				.indices
+				.chunks_exact(3)
+				.filter_map(|triangle| {
+					let [a, b, c] = [0, 1, 2].map(|i| geometry.positions[triangle[i] as usize]);
+					ray_triangle(local_origin, local_direction, a, b, c)
+				})
+				.filter(|t| *t <= limit)
+				.reduce(f32::min);
+			if let Some(t) = hit {
+				nearest = Some((id, t));
+			}
+		}
+		nearest
+	}
+
+	/// Objects added or changed, and names of objects removed, since the
+	/// last [`Scene::clear_changes`], for saving only what changed.
+	pub fn changes(&self) -> (impl Iterator<Item = ObjectId> + '_, &[String]) {
+		(self.dirty.iter().copied(), &self.removed)
+	}
+
+	/// Call once the scene's state has been saved or loaded.
+	pub fn clear_changes(&mut self) {
+		self.dirty.clear();
+		self.removed.clear();
+	}
+
+	/// Call at the start of every simulation step, before anything moves.
+	pub fn begin_step(&mut self) {
+		for object in self.objects.values_mut() {
+			object.previous_transform = object.transform;
+		}
+	}
+
+	/// Moves objects by their [`TransformModifier`]s over a step of `dt`
+	/// seconds, relative to their parents. Parents move before their
+	/// children, so an attached object's modifiers carry on from where its
+	/// parent has taken it. Call after [`Scene::begin_step`].
+	pub fn step_modifiers(&mut self, dt: f32) {
+		let mut pending: Vec<ObjectId> = self
+			.iter()
+			.filter(|(_, object)| object.parent.is_none())
+			.map(|(id, _)| id)
+			.collect();
+		while let Some(id) = pending.pop() {
+			pending.extend_from_slice(self.children(id));
+			let object = match self.objects.get_mut(&id) {
+				Some(object) if !object.modifiers.is_empty() => object,
+				_ => continue,
+			};
+			let mut local = object.local_transform.to_mat4();
+			for modifier in &mut object.modifiers {
+				local = modifier.apply(local, dt);
+			}
+			let local = Transform::from_mat4(local);
+			let transform = match self.parent_frame(id) {
+				Some(frame) => frame * local,
+				None => local,
+			};
+			self.set_transform(id, transform);
+		}
+	}
+
+	/// World space position the renderer's space is centered on.
+	pub fn origin(&self) -> Position {
+		self.origin
+	}
+
+	/// Moves the render space origin to `origin`. Every object is sent to the
+	/// renderer again on the next `submit`, without motion vectors from the
+	/// jump.
+	pub fn rebase(&mut self, origin: Position) {
+		let shift = Mat4::from_translation(self.origin.relative_to(origin).into());
+		for object in self.objects.values_mut() {
+			object.submitted_transform = shift * object.submitted_transform;
+			object.previous_submitted_transform = shift * object.previous_submitted_transform;
+		}
+		self.origin = origin;
+		self.rebased = true;
+	}
+
+	/// Rebases onto `position` if it's more than `max_distance` from the
+	/// origin. Returns true if the origin moved.
+	pub fn rebase_near(&mut self, position: Position, max_distance: f32) -> bool {
+		if position.relative_to(self.origin).length() <= max_distance {
+			return false;
+		}
+		log::debug!("rebasing scene origin to {}", position);
+		self.rebase(position);
+		true
+	}
+
+	/// Sends interpolated transforms to the renderer, relative to the origin.
+	pub fn submit(&mut self, renderer: &Renderer, alpha: f32) {
+		let origin = self.origin;
+		let rebased = std::mem::take(&mut self.rebased);
+		for object in self.objects.values_mut() {
+			if std::mem::take(&mut object.joints_changed) {
+				if let ObjectMeshKind::Animated(skeleton) = &object.mesh_kind {
+					renderer.set_skeleton_joint_transforms(
+						skeleton,
+						&object.joints,
+						&object.inverse_bind_transforms,
+					);
+				}
+			}
+			let transform = relative_to_origin(origin, object.interpolated_transform(alpha));
+			object.previous_submitted_transform = match object.teleported {
+				true => transform,
+				false => object.submitted_transform,
+			};
+			object.teleported = false;
+			if rebased || transform != object.submitted_transform {
+				if let Some(handle) = &object.handle {
+					renderer.set_object_transform(handle, transform);
+				}
+				object.submitted_transform = transform;
+			}
+		}
+	}
+}
+
+/// `transform` as the renderer is given it, moved relative to `origin` before
+/// dropping to f32 so it's exact near the origin however far out that is.
+fn relative_to_origin(origin: Position, transform: Transform) -> Mat4 {
+	(Transform::from_f64_translation(-origin.to_f64()) * transform).to_mat4()
+}
+
+/// Distance along a ray to where it crosses a triangle from either side
+/// (Möller–Trumbore).
+fn ray_triangle(origin: Vec3, direction: Vec3, a: Vec3, b: Vec3, c: Vec3) -> Option<f32> {
+	let (ab, ac) = (b - a, c - a);
+	let p = direction.cross(ac);
+	let determinant = ab.dot(p);
+	if determinant.abs() < f32::EPSILON {
+		return None;
+	}
+	let inverse = 1.0 / determinant;
+	let to_origin = origin - a;
+	let u = to_origin.dot(p) * inverse;
+	if !(0.0..=1.0).contains(&u) {
+		return None;
+	}
+	let q = to_origin.cross(ab);
+	let v = direction.dot(q) * inverse;
+	if v < 0.0 || u + v > 1.0 {
+		return None;
+	}
+	let t = ac.dot(q) * inverse;
+	(t >= 0.0).then_some(t)
+}
+
+/// Runs the simulation at a fixed rate independent of the frame rate.
+pub struct FixedTimestep {
+	pub step: f32,
+	accumulator: f32,
+}
+
+impl FixedTimestep {
+	/// Longest frame that will be simulated, to avoid a spiral of death after
+	/// a hitch.
+	const MAX_FRAME_TIME: f32 = 0.25;
+
+	pub fn new(steps_per_second: f32) -> Self {
+		Self {
+			step: 1.0 / steps_per_second,
+			accumulator: 0.0,
+		}
+	}
+
+	/// Adds the frame's time and returns how many steps should be run.
+	pub fn advance(&mut self, delta_time: f32) -> u32 {
+		self.accumulator += delta_time.min(Self::MAX_FRAME_TIME);
+		let steps = (self.accumulator / self.step) as u32;
+		self.accumulator -= steps as f32 * self.step;
+		steps
+	}
+
+	/// How far between the last two steps the current frame is.
+	pub fn alpha(&self) -> f32 {
+		self.accumulator / self.step
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::f32::consts::FRAC_PI_2;
+
+	use rend3::types::{MaterialHandle, MeshHandle};
+
+	use super::*;
+
+	/// Adds an object without a renderer, hidden as far as the scene knows.
+	fn add(scene: &mut Scene, transform: Mat4) -> ObjectId {
+		let id = scene.reserve_id();
+		scene.insert_hidden(
+			id,
+			"object",
+			Object {
+				mesh_kind: ObjectMeshKind::Static(MeshHandle::new(0)),
+				material: MaterialHandle::new(0),
+				transform,
+			},
+			Transform::from_mat4(transform),
+		);
+		id
+	}
+
+	fn world(scene: &Scene, id: ObjectId) -> Mat4 {
+		scene.get(id).unwrap().transform().to_mat4()
+	}
+
+	fn assert_close(a: Mat4, b: Mat4) {
+		assert!(a.abs_diff_eq(b, 1e-4), "{} != {}", a, b);
+	}
+
+	#[test]
+	fn joints_move_the_objects_attached_at_them() {
+		let mut scene = Scene::default();
+		let parent = add(&mut scene, Mat4::from_translation(Vec3::X));
+		let joints = [Mat4::IDENTITY, Mat4::from_translation(Vec3::Y * 2.0)];
+		scene.set_joint_transforms(parent, &joints, &[]);
+
+		let prop = add(&mut scene, Mat4::from_translation(Vec3::Z));
+		assert!(scene.attach(prop, parent, true));
+		scene.set_socket(prop, Some(1));
+		// attaching at the joint doesn't move it
+		assert_close(world(&scene, prop), Mat4::from_translation(Vec3::Z));
+		assert_close(
+			scene.get(prop).unwrap().local_transform().to_mat4(),
+			Mat4::from_translation(Vec3::new(-1.0, -2.0, 1.0)),
+		);
+
+		// the joint turning a quarter about y carries the prop around it
+		let turned = Mat4::from_translation(Vec3::Y * 2.0) * Mat4::from_rotation_y(FRAC_PI_2);
+		scene.set_joint_transforms(parent, &[Mat4::IDENTITY, turned], &[]);
+		assert_close(
+			world(&scene, prop),
+			Mat4::from_translation(Vec3::X)
+				* turned * Mat4::from_translation(Vec3::new(-1.0, -2.0, 1.0)),
+		);
+		assert_eq!(
+			scene.get(parent).unwrap().joints(),
+			[Mat4::IDENTITY, turned]
+		);
+	}
+
+	#[test]
+	fn socket_follows_the_parent() {
+		let mut scene = Scene::default();
+		let parent = add(&mut scene, Mat4::IDENTITY);
+		let joints = [Mat4::from_translation(Vec3::Y)];
+		scene.set_joint_transforms(parent, &joints, &[]);
+		let prop = add(&mut scene, Mat4::from_translation(Vec3::Y));
+		scene.attach(prop, parent, true);
+		scene.set_socket(prop, Some(0));
+
+		scene.set_transform(
+			parent,
+			Transform::from_mat4(Mat4::from_translation(Vec3::X * 5.0)),
+		);
+		assert_close(
+			world(&scene, prop),
+			Mat4::from_translation(Vec3::new(5.0, 1.0, 0.0)),
+		);
+
+		// back on the parent itself it keeps its place and follows the object
+		scene.set_socket(prop, None);
+		scene.set_joint_transforms(parent, &[Mat4::from_translation(Vec3::Y * 3.0)], &[]);
+		assert_close(
+			world(&scene, prop),
+			Mat4::from_translation(Vec3::new(5.0, 1.0, 0.0)),
+		);
+
+		scene.detach(prop);
+		assert_eq!(scene.get(prop).unwrap().socket(), None);
+		scene.set_socket(prop, Some(0));
+		assert_eq!(scene.get(prop).unwrap().socket(), None);
+	}
+
+	#[test]
+	fn root_motion_moves_the_way_the_object_faces() {
+		let mut scene = Scene::default();
+		let turned = Mat4::from_translation(Vec3::X) * Mat4::from_rotation_y(FRAC_PI_2);
+		let id = add(&mut scene, turned);
+		scene.apply_root_motion(id, Mat4::from_translation(Vec3::Z));
+		assert_close(
+			world(&scene, id),
+			Mat4::from_translation(Vec3::X * 2.0) * Mat4::from_rotation_y(FRAC_PI_2),
+		);
+	}
+
+	#[test]
+	fn children_spin_on_from_where_their_parent_spun_them() {
+		let spin = TransformModifier::Spin {
+			axis: Vec3::Y,
+			speed: FRAC_PI_2,
+		};
+		// either order of adding them, so hash order can't decide it
+		for child_first in [false, true] {
+			let mut scene = Scene::default();
+			let (parent, child) = match child_first {
+				false => {
+					let parent = add(&mut scene, Mat4::IDENTITY);
+					(parent, add(&mut scene, Mat4::from_translation(Vec3::X)))
+				}
+				true => {
+					let child = add(&mut scene, Mat4::from_translation(Vec3::X));
+					(add(&mut scene, Mat4::IDENTITY), child)
+				}
+			};
+			scene.attach(child, parent, true);
+			scene.modifiers_mut(parent).unwrap().push(spin);
+			scene.modifiers_mut(child).unwrap().push(spin);
+
+			scene.begin_step();
+			scene.step_modifiers(1.0);
+			// carried a quarter turn around the parent, and turned a quarter
+			// more on the spot
+			assert_close(world(&scene, parent), Mat4::from_rotation_y(FRAC_PI_2));
+			assert_close(
+				world(&scene, child),
+				Mat4::from_translation(Vec3::new(0.0, 0.0, -1.0))
+					* Mat4::from_rotation_y(FRAC_PI_2 * 2.0),
+			);
+			assert_close(
+				scene.get(child).unwrap().local_transform().to_mat4(),
+				Mat4::from_translation(Vec3::X) * Mat4::from_rotation_y(FRAC_PI_2),
+			);
+		}
+	}
+
+	#[test]
+	fn socket_past_the_last_joint_uses_the_parent() {
+		let mut scene = Scene::default();
+		let parent = add(&mut scene, Mat4::from_translation(Vec3::X));
+		let prop = add(&mut scene, Mat4::IDENTITY);
+		scene.attach(prop, parent, true);
+		scene.set_socket(prop, Some(3));
+
+		scene.set_transform(
+			parent,
+			Transform::from_mat4(Mat4::from_translation(Vec3::X * 2.0)),
+		);
+		assert_close(world(&scene, prop), Mat4::from_translation(Vec3::X));
+	}
+
+	#[test]
+	fn attaching_never_makes_a_cycle() {
+		let mut scene = Scene::default();
+		let a = add(&mut scene, Mat4::IDENTITY);
+		let b = add(&mut scene, Mat4::IDENTITY);
+		let c = add(&mut scene, Mat4::IDENTITY);
+		assert!(scene.attach(b, a, true));
+		assert!(scene.attach(c, b, true));
+
+		assert!(!scene.attach(a, a, true));
+		assert!(!scene.attach(a, b, true));
+		assert!(!scene.attach(a, c, true));
+		assert!(!scene.attach(a, ObjectId(99), true));
+		assert_eq!(scene.get(a).unwrap().parent(), None);
+		assert_eq!(scene.get(c).unwrap().parent(), Some(b));
+	}
+
+	#[test]
+	fn attach_and_detach_keep_local_and_world_transforms_in_step() {
+		let mut scene = Scene::default();
+		let parent = add(&mut scene, Mat4::from_translation(Vec3::X));
+		let child = add(&mut scene, Mat4::from_translation(Vec3::Y));
+		let local = |scene: &Scene| scene.get(child).unwrap().local_transform().to_mat4();
+
+		// keeping its world transform, the local one is what's left over
+		scene.attach(child, parent, true);
+		assert_close(world(&scene, child), Mat4::from_translation(Vec3::Y));
+		assert_close(local(&scene), Mat4::from_translation(Vec3::Y - Vec3::X));
+
+		scene.set_transform(
+			parent,
+			Transform::from_mat4(Mat4::from_translation(Vec3::X * 2.0)),
+		);
+		assert_close(
+			world(&scene, child),
+			Mat4::from_translation(Vec3::X + Vec3::Y),
+		);
+
+		// detached, it stays put and stops following
+		scene.detach(child);
+		assert_eq!(scene.get(child).unwrap().parent(), None);
+		assert_close(local(&scene), world(&scene, child));
+		scene.set_transform(parent, Transform::from_mat4(Mat4::IDENTITY));
+		assert_close(
+			world(&scene, child),
+			Mat4::from_translation(Vec3::X + Vec3::Y),
+		);
+
+		// otherwise its transform is taken as relative to the parent
+		scene.set_transform(
+			parent,
+			Transform::from_mat4(Mat4::from_translation(Vec3::X * 2.0)),
+		);
+		scene.attach(child, parent, false);
+		assert_close(local(&scene), Mat4::from_translation(Vec3::X + Vec3::Y));
+		assert_close(
+			world(&scene, child),
+			Mat4::from_translation(Vec3::X * 3.0 + Vec3::Y),
+		);
+	}
+
+	#[test]
+	fn attaching_elsewhere_leaves_the_old_parent() {
+		let mut scene = Scene::default();
+		let first = add(&mut scene, Mat4::IDENTITY);
+		let second = add(&mut scene, Mat4::from_translation(Vec3::Z));
+		let child = add(&mut scene, Mat4::from_translation(Vec3::Y));
+		scene.attach(child, first, true);
+		scene.attach(child, second, true);
+		assert_eq!(scene.get(child).unwrap().parent(), Some(second));
+
+		scene.set_transform(first, Transform::from_mat4(Mat4::from_translation(Vec3::X)));
+		assert_close(world(&scene, child), Mat4::from_translation(Vec3::Y));
+		scene.set_transform(
+			second,
+			Transform::from_mat4(Mat4::from_translation(Vec3::Z + Vec3::X)),
+		);
+		assert_close(
+			world(&scene, child),
+			Mat4::from_translation(Vec3::Y + Vec3::X),
+		);
+	}
+
+	fn at(position: Vec3) -> Position {
+		Position::from_vec3a(position.into())
+	}
+
+	#[test]
+	fn rebasing_moves_what_the_renderer_sees_not_the_world() {
+		let mut scene = Scene::default();
+		let far = Vec3::new(10_000.0, 0.0, -20_000.0);
+		let object = add(&mut scene, Mat4::from_translation(far + Vec3::X));
+
+		scene.rebase(at(far));
+		assert_eq!(scene.origin(), at(far));
+		assert!(scene.rebased);
+		let submitted = |scene: &Scene, id| scene.objects[&id].submitted_transform;
+		assert_close(world(&scene, object), Mat4::from_translation(far + Vec3::X));
+		assert_close(submitted(&scene, object), Mat4::from_translation(Vec3::X));
+		assert_close(
+			scene.objects[&object].previous_submitted_transform,
+			Mat4::from_translation(Vec3::X),
+		);
+
+		// objects added afterwards start out relative to the new origin
+		let added = add(&mut scene, Mat4::from_translation(far - Vec3::Y));
+		assert_close(submitted(&scene, added), Mat4::from_translation(-Vec3::Y));
+
+		scene.rebase(Position::ZERO);
+		assert_close(
+			submitted(&scene, object),
+			Mat4::from_translation(far + Vec3::X),
+		);
+	}
+
+	#[test]
+	fn rebase_near_waits_until_far_enough() {
+		let mut scene = Scene::default();
+		assert!(!scene.rebase_near(at(Vec3::new(0.0, 0.0, 100.0)), 100.0));
+		assert_eq!(scene.origin(), Position::ZERO);
+		assert!(!scene.rebased);
+
+		let position = at(Vec3::new(0.0, 0.0, 100.5));
+		assert!(scene.rebase_near(position, 100.0));
+		assert_eq!(scene.origin(), position);
+		// distances are from the new origin now
+		assert!(!scene.rebase_near(position.offset(Vec3A::X * 50.0), 100.0));
+		assert_eq!(scene.origin(), position);
+	}
+
+	#[cfg(feature = "double-precision")]
+	#[test]
+	fn far_out_objects_are_exact_relative_to_the_camera() {
+		use crate::camera::CameraPose;
+
+		// f32 steps by 1 or more out here, so none of the fractions survive it
+		let camera = CameraPose {
+			position: DVec3::new(1e7 + 0.25, -2e7 + 0.5, 3e7 + 0.125),
+			orientation: Quat::IDENTITY,
+		};
+		let offset = Vec3::new(1.5, -0.25, 2.0);
+		let mut scene = Scene::default();
+		let object = add(&mut scene, Mat4::IDENTITY);
+		scene.teleport(
+			object,
+			Transform::from_f64_translation(camera.position + offset.as_dvec3()),
+		);
+
+		let submitted_offset = |scene: &Scene| {
+			let transform = scene.get(object).unwrap().transform();
+			let submitted = relative_to_origin(scene.origin(), transform);
+			let view = camera.relative_to(scene.origin());
+			submitted.w_axis.truncate() - Vec3::from(view.position.to_vec3a())
+		};
+		assert!(scene.rebase_near(camera.position, 100.0));
+		assert_eq!(submitted_offset(&scene), offset);
+		// and with the camera wandered off from the origin
+		scene.rebase(camera.position.offset(Vec3A::new(40.0, -8.0, 0.5)));
+		assert_eq!(submitted_offset(&scene), offset);
+	}
+
+	#[test]
+	fn transforms_round_trip_through_f64_arrays() {
+		let transform = Transform::from_mat4(Mat4::from_scale_rotation_translation(
+			Vec3::new(1.0, 2.0, 0.5),
+			Quat::from_rotation_y(0.3),
+			Vec3::new(-4.0, 5.5, 6.25),
+		));
+		let array = transform.to_f64_array();
+		// column major, translation last
+		assert_eq!(&array[12..], &[-4.0, 5.5, 6.25, 1.0]);
+		assert_eq!(Transform::from_f64_array(&array), transform);
+
+		// further out than f32 can place to the millimeter
+		let far = Transform::from_f64_translation(DVec3::new(1e9 + 0.125, -3e8, 0.001));
+		assert_eq!(Transform::from_f64_array(&far.to_f64_array()), far);
+		#[cfg(feature = "double-precision")]
+		assert_eq!(&far.to_f64_array()[12..15], &[1e9 + 0.125, -3e8, 0.001]);
+	}
+}
@@ -0,0 +1,424 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::Path;
+
+use crate::metadata::MetadataValue;
+use crate::scene::{Scene, SceneTransform, Transform};
+use crate::scene_file::{ObjectRecord, SceneFile};
+
+/// One difference between two scene files, going from the base to the other
+/// file. Objects are matched by name.
+#[derive(Clone)]
+pub enum DiffItem {
+	/// an object only in the other file
+	Added(ObjectRecord),
+	/// an object only in the base file
+	Removed(String),
+	Transform {
+		name: String,
+		from: Transform,
+		to: Transform,
+	},
+	/// a metadata property that was added, removed or changed
+	Property {
+		name: String,
+		key: String,
+		from: Option<MetadataValue>,
+		to: Option<MetadataValue>,
+	},
+}
+
+impl DiffItem {
+	/// Name of the object the difference is on.
+	pub fn object(&self) -> &str {
+		match self {
+			DiffItem::Added(record) => &record.name,
+			DiffItem::Removed(name)
+			| DiffItem::Transform { name, .. }
+			| DiffItem::Property { name, .. } => name,
+		}
+	}
+
+	pub fn label(&self) -> String {
+		let value = |value: &Option<MetadataValue>| match value {
+			Some(value) => value.to_string(),
+			None => "(none)".into(),
+		};
+		match self {
+			DiffItem::Added(_) => "added".into(),
+			DiffItem::Removed(_) => "removed".into(),
+			DiffItem::Transform { from, to, .. } => {
+				let (from, to) = (from.to_mat4().w_axis, to.to_mat4().w_axis);
+				format!(
+					"moved ({:.2}, {:.2}, {:.2}) → ({:.2}, {:.2}, {:.2})",
+					from.x, from.y, from.z, to.x, to.y, to.z
+				)
+			}
+			DiffItem::Property { key, from, to, .. } => {
+				format!("{}: {} → {}", key, value(from), value(to))
+			}
+		}
+	}
+}
+
+/// Why two scene files can't be compared.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffError {
+	/// objects are matched by name, so namesakes in either file can't be
+	/// told apart
+	DuplicateName(String),
+}
+
+impl fmt::Display for DiffError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			DiffError::DuplicateName(name) => {
+				write!(f, "more than one object is named {}", name)
+			}
+		}
+	}
+}
+
+/// transforms closer than this in every element count as unchanged
+const TRANSFORM_EPSILON: f64 = 1e-6;
+
+/// Differences going from `base` to `other`, in the order of `base`'s objects
+/// followed by objects only in `other`. Files with two objects of the same
+/// name are refused.
+pub fn diff(base: &SceneFile, other: &SceneFile) -> Result<Vec<DiffItem>, DiffError> {
+	for file in [base, other] {
+		let mut names = BTreeSet::new();
+		if let Some(record) = file
+			.objects
+			.iter()
+			.find(|record| !names.insert(&record.name))
+		{
+			return Err(DiffError::DuplicateName(record.name.clone()));
+		}
+	}
+	let others: BTreeMap<&str, &ObjectRecord> = other
+		.objects
+		.iter()
+		.map(|record| (record.name.as_str(), record))
+		.collect();
+	let mut items = Vec::new();
+	for record in &base.objects {
+		let changed = match others.get(record.name.as_str()) {
+			Some(changed) => changed,
+			None => {
+				items.push(DiffItem::Removed(record.name.clone()));
+				continue;
+			}
+		};
+
+		let (from, to) = (
+			record.transform.to_f64_array(),
+			changed.transform.to_f64_array(),
+		);
+		if from
+			.iter()
+			.zip(to)
+			.any(|(a, b)| (a - b).abs() > TRANSFORM_EPSILON)
+		{
+			items.push(DiffItem::Transform {
+				name: record.name.clone(),
+				from: record.transform,
+				to: changed.transform,
+			});
+		}
+
+		let keys = record.metadata.keys().chain(changed.metadata.keys());
+		let mut keys: Vec<&String> = keys.collect();
+		keys.sort();
+		keys.dedup();
+		for key in keys {
+			let (from, to) = (record.metadata.get(key), changed.metadata.get(key));
+			if from != to {
+				items.push(DiffItem::Property {
+					name: record.name.clone(),
+					key: key.clone(),
+					from: from.cloned(),
+					to: to.cloned(),
+				});
+			}
+		}
+	}
+	let names: Vec<&str> = base
+		.objects
+		.iter()
+		.map(|record| record.name.as_str())
+		.collect();
+	for record in &other.objects {
+		if !names.contains(&record.name.as_str()) {
+			items.push(DiffItem::Added(record.clone()));
+		}
+	}
+	Ok(items)
+}
+
+/// `base` with `items` from [`diff`] applied to it.
+pub fn merge<'a>(base: &SceneFile, items: impl IntoIterator<Item = &'a DiffItem>) -> SceneFile {
+	let mut merged = base.clone();
+	for item in items {
+		let index = merged
+			.objects
+			.iter()
+			.position(|record| record.name == item.object());
+		match (item, index) {
+			(DiffItem::Added(added), None) => merged.objects.push(added.clone()),
+			(DiffItem::Removed(_), Some(index)) => {
+				merged.objects.remove(index);
+			}
+			(DiffItem::Transform { to, .. }, Some(index)) => merged.objects[index].transform = *to,
+			(DiffItem::Property { key, to, .. }, Some(index)) => match to {
+				Some(value) => {
+					merged.objects[index]
+						.metadata
+						.insert(key.clone(), value.clone());
+				}
+				None => {
+					merged.objects[index].metadata.remove(key);
+				}
+			},
+			_ => {}
+		}
+	}
+	merged.objects.sort_by(|a, b| a.name.cmp(&b.name));
+	merged
+}
+
+/// Window comparing two scene files, e.g. two versions of a scene edited by
+/// different people, with the differences picked one by one to apply.
+pub struct SceneDiffWindow {
+	pub open: bool,
+	base_path: String,
+	other_path: String,
+	merged_path: String,
+	/// the loaded base file and its differences from the other file, with
+	/// whether each is picked
+	compared: Option<(SceneFile, Vec<(DiffItem, bool)>)>,
+	status: Option<String>,
+}
+
+impl Default for SceneDiffWindow {
+	fn default() -> Self {
+		Self {
+			open: false,
+			base_path: "scene.json".into(),
+			other_path: "theirs.json".into(),
+			merged_path: "merged.json".into(),
+			compared: None,
+			status: None,
+		}
+	}
+}
+
+impl SceneDiffWindow {
+	/// Shows the window. Applying to the scene moves objects and sets their
+	/// metadata directly; the names of objects the picked differences remove
+	/// are returned so the caller can delete them.
+	pub fn window(&mut self, ctx: &egui::CtxRef, scene: &mut Scene) -> Vec<String> {
+		let mut removed = Vec::new();
+		let mut open = self.open;
+		egui::Window::new("scene diff")
+			.open(&mut open)
+			.resizable(true)
+			.show(ctx, |ui| {
+				egui::Grid::new("scene_diff_paths")
+					.num_columns(2)
+					.show(ui, |ui| {
+						ui.label("base");
+						ui.text_edit_singleline(&mut self.base_path);
+						ui.end_row();
+						ui.label("other");
+						ui.text_edit_singleline(&mut self.other_path);
+						ui.end_row();
+					});
+				if ui.button("compare").clicked() {
+					self.compare();
+				}
+				if let Some(status) = &self.status {
+					ui.label(status);
+				}
+
+				let (base, items) = match &mut self.compared {
+					Some(compared) => compared,
+					None => return,
+				};
+				ui.separator();
+				if items.is_empty() {
+					ui.label("no differences");
+					return;
+				}
+				ui.horizontal(|ui| {
+					let all = ui.button("pick all").clicked();
+					let none = ui.button("pick none").clicked();
+					if all || none {
+						for (_, picked) in items.iter_mut() {
+							*picked = all;
+						}
+					}
+				});
+				egui::ScrollArea::vertical()
+					.max_height(300.0)
+					.show(ui, |ui| {
+						let mut object = "";
+						for (item, picked) in items.iter_mut() {
+							if item.object() != object {
+								object = item.object();
+								ui.strong(object);
+							}
+							let color = match item {
+								DiffItem::Added(_) => egui::Color32::from_rgb(110, 200, 110),
+								DiffItem::Removed(_) => egui::Color32::from_rgb(230, 100, 100),
+								_ => egui::Color32::from_rgb(230, 190, 90),
+							};
+							ui.checkbox(picked, egui::RichText::new(item.label()).color(color));
+						}
+					});
+
+				ui.separator();
+				let picked = || {
+					items
+						.iter()
+						.filter(|(_, picked)| *picked)
+						.map(|(item, _)| item)
+				};
+				ui.horizontal(|ui| {
+					if ui.button("apply to scene").clicked() {
+						let missing = merge(&SceneFile::capture(scene), picked()).apply(scene);
+						removed = picked()
+							.filter_map(|item| match item {
+								DiffItem::Removed(name) => Some(name.clone()),
+								_ => None,
+							})
+							.collect();
+						self.status = Some(match missing.as_slice() {
+							[] => "applied".into(),
+							missing => format!("applied, no objects named {}", missing.join(", ")),
+						});
+					}
+					ui.text_edit_singleline(&mut self.merged_path);
+					if ui.button("save merged").clicked() {
+						let path = Path::new(&self.merged_path);
+						self.status = Some(match merge(base, picked()).save(path) {
+							Ok(()) => format!("saved {}", self.merged_path),
+							Err(e) => format!("couldn't save: {}", e),
+						});
+					}
+				});
+			});
+		self.open = open;
+		removed
+	}
+
+	fn compare(&mut self) {
+		let base = SceneFile::load(Path::new(&self.base_path));
+		let other = SceneFile::load(Path::new(&self.other_path));
+		match (base, other) {
+			(Ok(base), Ok(other)) => match diff(&base, &other) {
+				Ok(items) => {
+					self.status = Some(format!("{} differences", items.len()));
+					// picked by default, the usual case is taking the other changes
+					let items = items.into_iter().map(|item| (item, true)).collect();
+					self.compared = Some((base, items));
+				}
+				Err(e) => {
+					self.status = Some(format!("couldn't compare: {}", e));
+					self.compared = None;
+				}
+			},
+			(Err(e), _) => self.status = Some(format!("couldn't load {}: {}", self.base_path, e)),
+			(_, Err(e)) => self.status = Some(format!("couldn't load {}: {}", self.other_path, e)),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use glam::DVec3;
+
+	use super::*;
+	use crate::metadata::Metadata;
+
+	fn record(name: &str, x: f64) -> ObjectRecord {
+		ObjectRecord {
+			name: name.into(),
+			transform: Transform::from_f64_translation(DVec3::X * x),
+			metadata: Metadata::new(),
+		}
+	}
+
+	fn file(records: Vec<ObjectRecord>) -> SceneFile {
+		SceneFile { objects: records }
+	}
+
+	fn names(file: &SceneFile) -> Vec<&str> {
+		file.objects
+			.iter()
+			.map(|record| record.name.as_str())
+			.collect()
+	}
+
+	#[test]
+	fn differences_in_base_order_then_added() {
+		let mut tagged = record("b", 2.0);
+		tagged
+			.metadata
+			.insert("team".into(), MetadataValue::String("red".into()));
+		let base = file(vec![record("a", 1.0), record("b", 2.0), record("c", 3.0)]);
+		let other = file(vec![
+			record("d", 4.0),
+			record("a", 1.0 + 1e-9),
+			tagged,
+			record("c", 5.0),
+		]);
+
+		let items = diff(&base, &other).unwrap();
+		let labels: Vec<(&str, String)> = items
+			.iter()
+			.map(|item| (item.object(), item.label()))
+			.collect();
+		assert_eq!(
+			labels,
+			[
+				("b", "team: (none) → red".into()),
+				("c", "moved (3.00, 0.00, 0.00) → (5.00, 0.00, 0.00)".into()),
+				("d", "added".into()),
+			]
+		);
+
+		let gone = diff(&other, &base).unwrap();
+		assert!(matches!(&gone[0], DiffItem::Removed(name) if name == "d"));
+	}
+
+	#[test]
+	fn merging_everything_gives_the_other_file() {
+		let base = file(vec![record("a", 1.0), record("b", 2.0)]);
+		let other = file(vec![record("b", 7.0), record("c", 3.0)]);
+		let items = diff(&base, &other).unwrap();
+		let merged = merge(&base, &items);
+		assert_eq!(names(&merged), ["b", "c"]);
+		assert!(diff(&merged, &other).unwrap().is_empty());
+
+		// only what's picked
+		let removals = items
+			.iter()
+			.filter(|item| matches!(item, DiffItem::Removed(_)));
+		assert_eq!(names(&merge(&base, removals)), ["b"]);
+	}
+
+	#[test]
+	fn namesakes_are_refused() {
+		let base = file(vec![record("a", 1.0)]);
+		let doubled = file(vec![record("a", 1.0), record("b", 2.0), record("a", 3.0)]);
+		let refused = Err(DiffError::DuplicateName("a".into()));
+		assert_eq!(diff(&base, &doubled).map(|items| items.len()), refused);
+		assert_eq!(diff(&doubled, &base).map(|items| items.len()), refused);
+
+		// merging removes the first namesake, not every one
+		let merged = merge(&doubled, &[DiffItem::Removed("a".into())]);
+		assert_eq!(names(&merged), ["a", "b"]);
+		assert_eq!(merged.objects[0].transform.to_mat4().w_axis.x, 3.0);
+	}
+}
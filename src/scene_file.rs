@@ -0,0 +1,382 @@
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+use crate::assets::AssetError;
+use crate::metadata::{metadata_from_json, metadata_to_json, Metadata};
+use crate::scene::{Scene, SceneObject, SceneTransform, Transform};
+
+/// An object as saved in a scene file.
+#[derive(Clone)]
+pub struct ObjectRecord {
+	pub name: String,
+	pub transform: Transform,
+	pub metadata: Metadata,
+}
+
+impl ObjectRecord {
+	pub fn of(object: &SceneObject) -> Self {
+		Self {
+			name: object.name.clone(),
+			transform: object.transform(),
+			metadata: object.metadata.clone(),
+		}
+	}
+
+	pub fn to_json(&self) -> Value {
+		json!({
+			"name": self.name,
+			"transform": self.transform.to_f64_array().to_vec(),
+			"metadata": metadata_to_json(&self.metadata),
+		})
+	}
+
+	fn from_json(value: &Value) -> Option<Self> {
+		let columns = value.get("transform")?.as_array()?;
+		let mut transform = [0.0; 16];
+		if columns.len() != transform.len() {
+			return None;
+		}
+		for (column, value) in transform.iter_mut().zip(columns) {
+			*column = value.as_f64()?;
+		}
+		Some(Self {
+			name: value.get("name")?.as_str()?.into(),
+			transform: Transform::from_f64_array(&transform),
+			metadata: metadata_from_json(value.get("metadata")?)?,
+		})
+	}
+}
+
+/// The editable state of a scene's objects, saved as json. Meshes and
+/// materials come from the code and assets that build the scene, so records
+/// are matched back up with objects by name.
+///
+/// Saves can also append just the changed objects to a patch file next to
+/// the scene file (see [`save_scene`]), which loading replays on top.
+#[derive(Default, Clone)]
+pub struct SceneFile {
+	/// sorted by name so saved files diff cleanly
+	pub objects: Vec<ObjectRecord>,
+}
+
+impl SceneFile {
+	pub fn capture(scene: &Scene) -> Self {
+		let mut objects: Vec<ObjectRecord> = scene
+			.iter()
+			.map(|(_, object)| ObjectRecord::of(object))
+			.collect();
+		objects.sort_by(|a, b| a.name.cmp(&b.name));
+		Self { objects }
+	}
+
+	/// Moves the objects and sets their metadata to match the file. Returns
+	/// the names of records no object matched.
+	pub fn apply(&self, scene: &mut Scene) -> Vec<String> {
+		let mut missing = Vec::new();
+		for record in &self.objects {
+			let id = match scene.find_by_name(&record.name) {
+				Some(id) => id,
+				None => {
+					missing.push(record.name.clone());
+					continue;
+				}
+			};
+			scene.teleport(id, record.transform);
+			if let Some(metadata) = scene.metadata_mut(id) {
+				*metadata = record.metadata.clone();
+			}
+		}
+		missing
+	}
+
+	pub fn to_json(&self) -> Value {
+		json!({
+			"objects": self.objects.iter().map(ObjectRecord::to_json).collect::<Vec<_>>(),
+		})
+	}
+
+	pub fn from_json(value: &Value) -> Option<Self> {
+		let objects = value
+			.get("objects")?
+			.as_array()?
+			.iter()
+			.map(ObjectRecord::from_json)
+			.collect::<Option<_>>()?;
+		Some(Self { objects })
+	}
+
+	/// Writes the whole file, replacing any patch file it had.
+	pub fn save(&self, path: &Path) -> Result<(), AssetError> {
+		let text = serde_json::to_string_pretty(&self.to_json())
+			.map_err(|e| AssetError::Invalid(e.to_string()))?;
+		fs::write(path, text)?;
+		match fs::remove_file(patch_path(path)) {
+			Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+			_ => Ok(()),
+		}
+	}
+
+	/// Reads the file and replays its patch file, if it has one.
+	pub fn load(path: &Path) -> Result<Self, AssetError> {
+		let text = fs::read_to_string(path)?;
+		let value: Value = serde_json::from_str(&text)
+			.map_err(|e| AssetError::Invalid(format!("invalid json: {}", e)))?;
+		let mut file = Self::from_json(&value)
+			.ok_or_else(|| AssetError::Invalid("not a scene file".into()))?;
+
+		let patch = match fs::read_to_string(patch_path(path)) {
+			Ok(patch) => patch,
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(file),
+			Err(e) => return Err(e.into()),
+		};
+		for (i, line) in patch.lines().enumerate() {
+			let applied = serde_json::from_str(line)
+				.ok()
+				.and_then(|line: Value| file.replay(&line));
+			// a save cut short leaves a partial last line
+			if applied.is_none() {
+				log::warn!("{}: skipping bad patch line {}", path.display(), i + 1);
+			}
+		}
+		Ok(file)
+	}
+
+	/// Applies one line of a patch file.
+	fn replay(&mut self, line: &Value) -> Option<()> {
+		if let Some(name) = line.get("remove") {
+			let name = name.as_str()?;
+			self.objects.retain(|record| record.name != name);
+			return Some(());
+		}
+		let record = ObjectRecord::from_json(line.get("set")?)?;
+		match self
+			.objects
+			.iter_mut()
+			.find(|existing| existing.name == record.name)
+		{
+			Some(existing) => *existing = record,
+			None => self.objects.push(record),
+		}
+		Some(())
+	}
+}
+
+/// Patch file the changes of incremental saves are appended to, one json
+/// object per line.
+pub fn patch_path(path: &Path) -> PathBuf {
+	let mut patch = path.as_os_str().to_owned();
+	patch.push(".patch");
+	PathBuf::from(patch)
+}
+
+/// How [`save_scene`] saved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveKind {
+	/// the whole scene was written
+	Full,
+	/// this many changed or removed objects were appended to the patch file
+	Patch(usize),
+}
+
+/// Saves the scene and clears its changes. Incremental saves append only the
+/// objects changed since the last save or load to the patch file, unless
+/// there's no scene file to patch yet or the patch has grown bigger than the
+/// scene file, in which case everything is written again.
+pub fn save_scene(
+	scene: &mut Scene,
+	path: &Path,
+	incremental: bool,
+) -> Result<SaveKind, AssetError> {
+	let base_size = fs::metadata(path).map(|metadata| metadata.len()).ok();
+	let patch_size = fs::metadata(patch_path(path)).map_or(0, |metadata| metadata.len());
+	let patch = match base_size {
+		Some(base_size) => incremental && patch_size <= base_size,
+		None => false,
+	};
+	if !patch {
+		SceneFile::capture(scene).save(path)?;
+		scene.clear_changes();
+		return Ok(SaveKind::Full);
+	}
+
+	let (dirty, removed) = scene.changes();
+	let mut lines = String::new();
+	let mut count = 0;
+	for name in removed {
+		lines += &json!({ "remove": name }).to_string();
+		lines.push('\n');
+		count += 1;
+	}
+	for object in dirty.filter_map(|id| scene.get(id)) {
+		lines += &json!({ "set": ObjectRecord::of(object).to_json() }).to_string();
+		lines.push('\n');
+		count += 1;
+	}
+	if count > 0 {
+		let mut file = OpenOptions::new()
+			.create(true)
+			.read(true)
+			.append(true)
+			.open(patch_path(path))?;
+		// a save cut short leaves a partial last line, which must not swallow
+		// the first line of this one
+		if !ends_with_newline(&mut file)? {
+			lines.insert(0, '\n');
+		}
+		file.write_all(lines.as_bytes())?;
+	}
+	scene.clear_changes();
+	Ok(SaveKind::Patch(count))
+}
+
+/// Whether the file is empty or its last byte is a newline.
+fn ends_with_newline(file: &mut fs::File) -> std::io::Result<bool> {
+	if file.metadata()?.len() == 0 {
+		return Ok(true);
+	}
+	let mut last = [0];
+	file.seek(SeekFrom::End(-1))?;
+	file.read_exact(&mut last)?;
+	Ok(last[0] == b'\n')
+}
+
+#[cfg(test)]
+mod tests {
+	use glam::{Mat4, Vec3};
+	use rend3::types::{MaterialHandle, MeshHandle, Object, ObjectMeshKind};
+
+	use super::*;
+
+	/// A scene file path in the temp directory, with no file or patch there.
+	fn scene_path(test: &str) -> PathBuf {
+		let path = std::env::temp_dir().join(format!("opal-{}-{}.json", test, std::process::id()));
+		let _ = fs::remove_file(&path);
+		let _ = fs::remove_file(patch_path(&path));
+		path
+	}
+
+	fn add(scene: &mut Scene, name: &str, position: Vec3) {
+		let id = scene.reserve_id();
+		scene.insert_hidden(
+			id,
+			name,
+			Object {
+				mesh_kind: ObjectMeshKind::Static(MeshHandle::new(0)),
+				material: MaterialHandle::new(0),
+				transform: Mat4::from_translation(position),
+			},
+			Transform::from_mat4(Mat4::from_translation(position)),
+		);
+	}
+
+	fn move_to(scene: &mut Scene, name: &str, position: Vec3) {
+		let id = scene.find_by_name(name).unwrap();
+		scene.set_transform(id, Transform::from_mat4(Mat4::from_translation(position)));
+	}
+
+	fn position(file: &SceneFile, name: &str) -> Vec3 {
+		let record = file.objects.iter().find(|record| record.name == name);
+		record.unwrap().transform.to_mat4().w_axis.truncate()
+	}
+
+	fn two_objects(path: &Path) -> Scene {
+		let mut scene = Scene::default();
+		add(&mut scene, "a", Vec3::X);
+		add(&mut scene, "b", Vec3::Y);
+		assert_eq!(save_scene(&mut scene, path, true).unwrap(), SaveKind::Full);
+		scene
+	}
+
+	#[test]
+	fn patches_replay_over_the_full_save() {
+		let path = scene_path("patches-replay");
+		let mut scene = two_objects(&path);
+
+		move_to(&mut scene, "a", Vec3::Z);
+		assert_eq!(
+			save_scene(&mut scene, &path, true).unwrap(),
+			SaveKind::Patch(1)
+		);
+		// nothing changed, nothing appended
+		assert_eq!(
+			save_scene(&mut scene, &path, true).unwrap(),
+			SaveKind::Patch(0)
+		);
+		move_to(&mut scene, "b", Vec3::ONE);
+		assert_eq!(
+			save_scene(&mut scene, &path, true).unwrap(),
+			SaveKind::Patch(1)
+		);
+
+		let file = SceneFile::load(&path).unwrap();
+		assert_eq!(file.objects.len(), 2);
+		assert_eq!(position(&file, "a"), Vec3::Z);
+		assert_eq!(position(&file, "b"), Vec3::ONE);
+
+		// a full save folds the patch back in
+		assert_eq!(
+			save_scene(&mut scene, &path, false).unwrap(),
+			SaveKind::Full
+		);
+		assert!(!patch_path(&path).exists());
+		let file = SceneFile::load(&path).unwrap();
+		assert_eq!(position(&file, "a"), Vec3::Z);
+		assert_eq!(position(&file, "b"), Vec3::ONE);
+		fs::remove_file(&path).unwrap();
+	}
+
+	#[test]
+	fn patches_after_a_truncated_tail() {
+		let path = scene_path("truncated-tail");
+		let mut scene = two_objects(&path);
+
+		move_to(&mut scene, "a", Vec3::Z);
+		save_scene(&mut scene, &path, true).unwrap();
+		// cut the last save off partway through its line
+		let patch = fs::read(patch_path(&path)).unwrap();
+		fs::write(patch_path(&path), &patch[..patch.len() / 2]).unwrap();
+
+		move_to(&mut scene, "b", Vec3::ONE);
+		assert_eq!(
+			save_scene(&mut scene, &path, true).unwrap(),
+			SaveKind::Patch(1)
+		);
+
+		// the partial line is skipped, the one after it still applies
+		let file = SceneFile::load(&path).unwrap();
+		assert_eq!(position(&file, "a"), Vec3::X);
+		assert_eq!(position(&file, "b"), Vec3::ONE);
+		fs::remove_file(&path).unwrap();
+		fs::remove_file(patch_path(&path)).unwrap();
+	}
+
+	#[test]
+	fn namesakes_are_patched_separately() {
+		let path = scene_path("namesakes");
+		let mut scene = Scene::default();
+		add(&mut scene, "crate", Vec3::X);
+		add(&mut scene, "crate", Vec3::Y);
+		add(&mut scene, "crate", Vec3::Z);
+		let names: Vec<_> = scene.iter().map(|(_, object)| &object.name).collect();
+		assert_eq!(names, ["crate", "crate 2", "crate 3"]);
+		save_scene(&mut scene, &path, true).unwrap();
+
+		move_to(&mut scene, "crate 2", Vec3::ONE);
+		let id = scene.find_by_name("crate 3").unwrap();
+		scene.remove_object(id);
+		assert_eq!(
+			save_scene(&mut scene, &path, true).unwrap(),
+			SaveKind::Patch(2)
+		);
+
+		let file = SceneFile::load(&path).unwrap();
+		assert_eq!(file.objects.len(), 2);
+		assert_eq!(position(&file, "crate"), Vec3::X);
+		assert_eq!(position(&file, "crate 2"), Vec3::ONE);
+		fs::remove_file(&path).unwrap();
+		fs::remove_file(patch_path(&path)).unwrap();
+	}
+}
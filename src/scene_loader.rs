@@ -0,0 +1,454 @@
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+use glam::{Mat4, Vec4};
+use rend3::types::{Handedness, MaterialHandle, Mesh, MeshHandle, Object, ObjectMeshKind, Texture};
+use rend3::util::typedefs::FastHashMap;
+use rend3::Renderer;
+use rend3_routine::pbr::{AlbedoComponent, PbrMaterial};
+
+use crate::assets::{try_load_mesh, try_load_texture, AssetError, ImportSettings};
+use crate::debug::ColorSpaceAudit;
+use crate::scene::{ObjectId, Scene};
+use crate::scene_file::{ObjectRecord, SceneFile};
+use crate::tasks::{TaskHandle, TaskManager};
+use crate::texture_streaming::TextureStreamer;
+use crate::work_queue::WorkQueue;
+
+/// Metadata key of the obj file an object's mesh is loaded from, relative to
+/// the scene file.
+pub const MESH_KEY: &str = "mesh";
+/// Metadata key of the bmp an object's albedo is streamed from, relative to
+/// the scene file. See [`TextureStreamer`].
+pub const TEXTURE_KEY: &str = "texture";
+
+enum Request {
+	Mesh(String),
+	Texture(String),
+}
+
+enum Loaded {
+	File(Result<SceneFile, AssetError>),
+	Mesh(String, Result<Box<Mesh>, AssetError>),
+	Texture(String, Result<Box<Texture>, AssetError>),
+}
+
+/// Loads a scene file without blocking the event loop. The file and its
+/// meshes are read on a background thread and objects appear as their meshes
+/// arrive, uploading at most [`SceneLoader::vertex_budget`] vertices a frame
+/// and no more than fit in the frame's [`WorkQueue`] budget.
+///
+/// Records naming objects already in the scene are applied to them like
+/// [`SceneFile::apply`]. Other records are added as new objects if their
+/// [`MESH_KEY`] metadata names a mesh file, textured if their [`TEXTURE_KEY`]
+/// metadata names a texture.
+pub struct SceneLoader {
+	pub open: bool,
+	/// most vertices uploaded to the gpu per frame, though at least one mesh
+	/// is always uploaded so big meshes still arrive
+	pub vertex_budget: usize,
+	path: String,
+	material: MaterialHandle,
+
+	requests: Option<Sender<Request>>,
+	results: Option<Receiver<Loaded>>,
+	/// thread reading the current load
+	worker: Option<JoinHandle<()>>,
+	/// uploaded meshes by path, shared by the objects using them
+	meshes: FastHashMap<String, MeshHandle>,
+	/// meshes read but not uploaded yet
+	ready: VecDeque<(String, Box<Mesh>)>,
+	/// materials of streamed textures by path, `None` for ones that failed
+	/// to load
+	textures: FastHashMap<String, Option<MaterialHandle>>,
+	/// records waiting for their mesh or texture
+	waiting: Vec<ObjectRecord>,
+	/// meshes and textures requested from the thread and not received yet
+	pending: usize,
+	/// whether the scene file itself has been read
+	read_file: bool,
+	/// objects the current load added, removed again if it's cancelled
+	added: Vec<ObjectId>,
+	/// objects of a cancelled load, removed on the next update
+	discarded: Vec<ObjectId>,
+	total: usize,
+	status: Option<String>,
+	/// the load's entry in the jobs panel, which can also cancel it
+	task: Option<TaskHandle>,
+}
+
+impl SceneLoader {
+	pub fn new(renderer: &Renderer, color_audit: &mut ColorSpaceAudit) -> Self {
+		let material = color_audit.add_material(
+			renderer,
+			"loaded",
+			PbrMaterial {
+				albedo: AlbedoComponent::Value(Vec4::new(0.6, 0.6, 0.6, 1.0)),
+				..PbrMaterial::default()
+			},
+		);
+		Self {
+			open: false,
+			vertex_budget: 200_000,
+			path: "scene.json".into(),
+			material,
+			requests: None,
+			results: None,
+			worker: None,
+			meshes: FastHashMap::default(),
+			ready: VecDeque::new(),
+			textures: FastHashMap::default(),
+			waiting: Vec::new(),
+			pending: 0,
+			read_file: false,
+			added: Vec::new(),
+			discarded: Vec::new(),
+			total: 0,
+			status: None,
+			task: None,
+		}
+	}
+
+	pub fn is_loading(&self) -> bool {
+		self.results.is_some()
+	}
+
+	/// The scene file loaded or being loaded.
+	pub fn path(&self) -> &str {
+		&self.path
+	}
+
+	/// Starts loading the scene file at `self.path`, cancelling any load in
+	/// progress.
+	pub fn start(
+		&mut self,
+		import: ImportSettings,
+		handedness: Handedness,
+		tasks: &mut TaskManager,
+	) {
+		self.cancel();
+
+		let task = tasks.begin(format!("loading {}", self.path));
+		let cancel = task.token();
+		let path = PathBuf::from(&self.path);
+		let directory = path.parent().map(Path::to_path_buf).unwrap_or_default();
+		let (request_sender, request_receiver) = mpsc::channel::<Request>();
+		let (result_sender, result_receiver) = mpsc::channel();
+		// exits once the loader drops its end of either channel
+		let spawned = thread::Builder::new()
+			.name("scene loader".into())
+			.spawn(move || {
+				if result_sender
+					.send(Loaded::File(SceneFile::load(&path)))
+					.is_err()
+				{
+					return;
+				}
+				for request in request_receiver {
+					if cancel.is_cancelled() {
+						break;
+					}
+					let loaded = match request {
+						Request::Mesh(mesh) => {
+							let loaded =
+								try_load_mesh(&directory.join(&mesh), &import, handedness, &cancel)
+									.map(Box::new);
+							Loaded::Mesh(mesh, loaded)
+						}
+						Request::Texture(texture) => {
+							let loaded =
+								try_load_texture(&directory.join(&texture), true).map(Box::new);
+							Loaded::Texture(texture, loaded)
+						}
+					};
+					if result_sender.send(loaded).is_err() {
+						break;
+					}
+				}
+			});
+		let worker = match spawned {
+			Ok(worker) => worker,
+			Err(e) => {
+				self.status = Some(format!("couldn't start loading: {}", e));
+				task.finish(Err(e.to_string()));
+				return;
+			}
+		};
+
+		self.requests = Some(request_sender);
+		self.results = Some(result_receiver);
+		// a cancelled load's thread stops partway through its current mesh
+		self.worker = Some(worker);
+		self.total = 0;
+		self.status = Some(format!("loading {}", self.path));
+		self.task = Some(task);
+	}
+
+	/// Stops loading. The objects the load added and the meshes it uploaded
+	/// are dropped on the next update, so a cancelled load leaves nothing
+	/// behind.
+	pub fn cancel(&mut self) {
+		self.discarded.append(&mut self.added);
+		self.requests = None;
+		self.results = None;
+		self.meshes.clear();
+		self.ready.clear();
+		self.textures.clear();
+		self.waiting.clear();
+		self.pending = 0;
+		self.read_file = false;
+		if let Some(task) = self.task.take() {
+			task.cancel();
+		}
+	}
+
+	/// Cancels loading and waits for the thread to finish the mesh it's on.
+	pub fn shutdown(&mut self) {
+		self.cancel();
+		if let Some(worker) = self.worker.take() {
+			if worker.join().is_err() {
+				log::error!("the scene loader thread panicked");
+			}
+		}
+	}
+
+	/// Takes in what the thread loaded and adds the objects whose meshes fit
+	/// in this frame's budget. Returns true on the frame the load finishes.
+	pub fn update(
+		&mut self,
+		renderer: &Renderer,
+		scene: &mut Scene,
+		work: &mut WorkQueue,
+		streamer: &mut TextureStreamer,
+	) -> bool {
+		// their meshes are freed once no object uses them
+		for id in self.discarded.drain(..) {
+			scene.remove_object(id);
+		}
+		let results = match &self.results {
+			Some(results) => results,
+			None => return false,
+		};
+		if self.task.as_ref().is_some_and(TaskHandle::is_cancelled) {
+			self.cancel();
+			self.status = Some("cancelled".into());
+			return false;
+		}
+		let received: Vec<Loaded> = results.try_iter().collect();
+		for loaded in received {
+			match loaded {
+				Loaded::File(Ok(file)) => self.begin(scene, file),
+				Loaded::File(Err(e)) => {
+					let status = format!("couldn't load {}: {}", self.path, e);
+					if let Some(task) = self.task.take() {
+						task.finish(Err(e.to_string()));
+					}
+					self.status = Some(status);
+					self.cancel();
+					return false;
+				}
+				Loaded::Mesh(path, Ok(mesh)) => {
+					self.pending -= 1;
+					self.ready.push_back((path, mesh));
+				}
+				Loaded::Mesh(path, Err(e)) => {
+					log::error!("failed to load mesh {}: {}", path, e);
+					self.pending -= 1;
+					let before = self.waiting.len();
+					self.waiting
+						.retain(|record| mesh_path(record) != Some(&path));
+					self.total -= before - self.waiting.len();
+				}
+				Loaded::Texture(path, Ok(texture)) => {
+					self.pending -= 1;
+					let material = streamer.add(renderer, &path, *texture, |texture| PbrMaterial {
+						albedo: AlbedoComponent::Texture(texture),
+						..PbrMaterial::default()
+					});
+					self.textures.insert(path, Some(material));
+				}
+				// the objects still load, untextured
+				Loaded::Texture(path, Err(e)) => {
+					log::error!("failed to load texture {}: {}", path, e);
+					self.pending -= 1;
+					self.textures.insert(path, None);
+				}
+			}
+		}
+
+		let mut uploaded = 0;
+		while let Some((_, mesh)) = self.ready.front() {
+			let vertices = mesh.vertex_positions.len();
+			if uploaded > 0 && (uploaded + vertices > self.vertex_budget || !work.has_time()) {
+				break;
+			}
+			uploaded += vertices;
+			let (path, mesh) = self.ready.pop_front().unwrap();
+			let handle = work.measure(|| scene.add_mesh(renderer, *mesh));
+			self.meshes.insert(path, handle);
+		}
+
+		let (meshes, textures) = (&self.meshes, &self.textures);
+		let (arrived, waiting) =
+			std::mem::take(&mut self.waiting)
+				.into_iter()
+				.partition(|record| {
+					mesh_path(record).is_some_and(|path| meshes.contains_key(path))
+						&& texture_path(record).is_none_or(|path| textures.contains_key(path))
+				});
+		self.waiting = waiting;
+		for record in arrived {
+			self.add(renderer, scene, record);
+		}
+		if let (Some(task), true) = (&self.task, self.read_file) {
+			let progress = match self.total {
+				0 => 1.0,
+				total => self.added.len() as f32 / total as f32,
+			};
+			task.set_progress(
+				progress,
+				format!("{} of {} objects", self.added.len(), self.total),
+			);
+		}
+
+		if self.read_file && self.pending == 0 && self.ready.is_empty() && self.waiting.is_empty() {
+			if let Some(task) = self.task.take() {
+				task.finish(Ok(format!("{} objects added", self.added.len())));
+			}
+			self.status = Some(format!(
+				"loaded {}, {} objects added",
+				self.path,
+				self.added.len()
+			));
+			// they're the scene's now, cancelling can't remove them
+			self.added.clear();
+			self.cancel();
+			return true;
+		}
+		false
+	}
+
+	/// Applies the records of objects already in the scene and requests the
+	/// meshes and textures of the others.
+	fn begin(&mut self, scene: &mut Scene, file: SceneFile) {
+		let (existing, new): (Vec<ObjectRecord>, Vec<ObjectRecord>) = file
+			.objects
+			.into_iter()
+			.partition(|record| scene.find_by_name(&record.name).is_some());
+		SceneFile { objects: existing }.apply(scene);
+
+		let (new, skipped): (Vec<ObjectRecord>, Vec<ObjectRecord>) = new
+			.into_iter()
+			.partition(|record| mesh_path(record).is_some());
+		if !skipped.is_empty() {
+			let names: Vec<&str> = skipped.iter().map(|record| record.name.as_str()).collect();
+			log::warn!("no objects or meshes for {}", names.join(", "));
+		}
+
+		let mut meshes: Vec<&str> = new.iter().filter_map(mesh_path).collect();
+		meshes.sort_unstable();
+		meshes.dedup();
+		let mut textures: Vec<&str> = new.iter().filter_map(texture_path).collect();
+		textures.sort_unstable();
+		textures.dedup();
+		let requests = meshes
+			.into_iter()
+			.map(|path| Request::Mesh(path.into()))
+			.chain(
+				textures
+					.into_iter()
+					.map(|path| Request::Texture(path.into())),
+			);
+		if let Some(sender) = &self.requests {
+			for request in requests {
+				if sender.send(request).is_ok() {
+					self.pending += 1;
+				}
+			}
+		}
+		self.total = new.len();
+		self.waiting = new;
+		self.read_file = true;
+	}
+
+	fn add(&mut self, renderer: &Renderer, scene: &mut Scene, record: ObjectRecord) {
+		let mesh = match mesh_path(&record).and_then(|path| self.meshes.get(path)) {
+			Some(mesh) => mesh.clone(),
+			None => return,
+		};
+		let material = texture_path(&record)
+			.and_then(|path| self.textures.get(path)?.clone())
+			.unwrap_or_else(|| self.material.clone());
+		let object = Object {
+			mesh_kind: ObjectMeshKind::Static(mesh),
+			material,
+			transform: Mat4::IDENTITY,
+		};
+		let id = scene.add_object_at(renderer, &record.name, object, record.transform);
+		if let Some(metadata) = scene.metadata_mut(id) {
+			*metadata = record.metadata;
+		}
+		self.added.push(id);
+	}
+
+	pub fn window(
+		&mut self,
+		ctx: &egui::CtxRef,
+		import: ImportSettings,
+		handedness: Handedness,
+		tasks: &mut TaskManager,
+	) {
+		let mut open = self.open;
+		egui::Window::new("scene loader")
+			.open(&mut open)
+			.resizable(false)
+			.show(ctx, |ui| {
+				ui.horizontal(|ui| {
+					ui.label("scene file");
+					ui.text_edit_singleline(&mut self.path);
+				});
+				ui.add(
+					egui::Slider::new(&mut self.vertex_budget, 1_000..=2_000_000)
+						.logarithmic(true)
+						.text("vertices per frame"),
+				);
+				ui.horizontal(|ui| {
+					if ui.button("load").clicked() {
+						self.start(import, handedness, tasks);
+					}
+					if ui
+						.add_enabled(self.is_loading(), egui::Button::new("cancel"))
+						.clicked()
+					{
+						self.cancel();
+						self.status = Some("cancelled".into());
+					}
+				});
+				if self.is_loading() && self.read_file {
+					let progress = match self.total {
+						0 => 1.0,
+						total => self.added.len() as f32 / total as f32,
+					};
+					ui.add(egui::ProgressBar::new(progress).text(format!(
+						"{} of {} objects",
+						self.added.len(),
+						self.total
+					)));
+				}
+				if let Some(status) = &self.status {
+					ui.label(status);
+				}
+			});
+		self.open = open;
+	}
+}
+
+fn mesh_path(record: &ObjectRecord) -> Option<&str> {
+	record.metadata.get(MESH_KEY)?.as_str()
+}
+
+fn texture_path(record: &ObjectRecord) -> Option<&str> {
+	record.metadata.get(TEXTURE_KEY)?.as_str()
+}
@@ -0,0 +1,269 @@
+use std::fs;
+use std::io;
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use glam::UVec2;
+use rend3::graph::{RenderGraph, RenderTargetHandle};
+use rend3::types::SampleCount;
+use rend3::Renderer;
+use rend3_egui::EguiRenderRoutine;
+use wgpu::{
+	BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Extent3d, ImageCopyBuffer,
+	ImageCopyTexture, ImageDataLayout, Maintain, MapMode, Origin3d, Texture, TextureAspect,
+	TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureView,
+	TextureViewDescriptor, COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+
+/// Commit the binary was built from, set by the build script.
+pub const COMMIT: &str = match option_env!("OPAL_COMMIT") {
+	Some(commit) => commit,
+	None => "unknown",
+};
+
+/// Describes how a screenshot was taken, so captures can be compared
+/// without keeping notes.
+pub struct ScreenshotMetadata {
+	pub scene: String,
+	pub gpu: String,
+	pub settings: String,
+	pub frame_stats: String,
+}
+
+/// Captures the next frame to a bitmap, optionally with a metadata strip
+/// along the bottom.
+///
+/// While a capture is pending the frame is rendered into a texture owned by
+/// this instead of the surface, skipping the ui. The strip is drawn by a
+/// separate egui context so none of the app's windows end up in the image.
+pub struct Screenshots {
+	/// composite the metadata strip into captures
+	pub metadata_strip: bool,
+	/// folder captures are written to
+	pub directory: PathBuf,
+
+	pending: bool,
+	format: TextureFormat,
+	target: Option<(UVec2, Texture, Arc<TextureView>)>,
+
+	egui_routine: EguiRenderRoutine,
+	egui_context: egui::CtxRef,
+	strip: Vec<egui::ClippedMesh>,
+}
+
+impl Screenshots {
+	pub fn new(renderer: &Renderer, format: TextureFormat, samples: SampleCount) -> Self {
+		Self {
+			metadata_strip: true,
+			directory: PathBuf::from("screenshots"),
+			pending: false,
+			format,
+			target: None,
+			egui_routine: EguiRenderRoutine::new(renderer, format, samples, 1, 1, 1.0),
+			egui_context: egui::CtxRef::default(),
+			strip: Vec::new(),
+		}
+	}
+
+	/// Captures the next rendered frame.
+	pub fn request(&mut self) {
+		self.pending = true;
+	}
+
+	pub fn is_pending(&self) -> bool {
+		self.pending
+	}
+
+	/// Texture the pending capture should be rendered into, recreated when the
+	/// resolution changes.
+	pub fn target(&mut self, renderer: &Renderer, resolution: UVec2) -> Arc<TextureView> {
+		match &self.target {
+			Some((size, _, view)) if *size == resolution => view.clone(),
+			_ => {
+				let texture = renderer.device.create_texture(&TextureDescriptor {
+					label: Some("screenshot"),
+					size: Extent3d {
+						width: resolution.x,
+						height: resolution.y,
+						depth_or_array_layers: 1,
+					},
+					mip_level_count: 1,
+					sample_count: 1,
+					dimension: TextureDimension::D2,
+					format: self.format,
+					usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+				});
+				let view = Arc::new(texture.create_view(&TextureViewDescriptor::default()));
+				self.target = Some((resolution, texture, view.clone()));
+				view
+			}
+		}
+	}
+
+	/// Draws the metadata strip over `dst`. Does nothing if the strip is
+	/// turned off.
+	pub fn add_metadata_to_graph<'node>(
+		&'node mut self,
+		graph: &mut RenderGraph<'node>,
+		metadata: &ScreenshotMetadata,
+		resolution: UVec2,
+		scale_factor: f32,
+		dst: RenderTargetHandle,
+	) {
+		if !self.metadata_strip {
+			return;
+		}
+
+		let raw_input = egui::RawInput {
+			screen_rect: Some(egui::Rect::from_min_size(
+				egui::Pos2::ZERO,
+				egui::vec2(resolution.x as f32, resolution.y as f32) / scale_factor,
+			)),
+			pixels_per_point: Some(scale_factor),
+			..egui::RawInput::default()
+		};
+		let (_output, shapes) = self.egui_context.run(raw_input, |ctx| {
+			egui::TopBottomPanel::bottom("screenshot metadata").show(ctx, |ui| {
+				ui.horizontal_wrapped(|ui| {
+					ui.label(format!("scene: {}", metadata.scene));
+					ui.separator();
+					ui.label(format!("commit: {}", COMMIT));
+					ui.separator();
+					ui.label(format!("gpu: {}", metadata.gpu));
+					ui.separator();
+					ui.label(&metadata.settings);
+					ui.separator();
+					ui.label(&metadata.frame_stats);
+				});
+			});
+		});
+		self.strip = self.egui_context.tessellate(shapes);
+
+		self.egui_routine
+			.resize(resolution.x, resolution.y, scale_factor);
+		self.egui_routine.add_to_graph(
+			graph,
+			rend3_egui::Input {
+				clipped_meshes: &self.strip,
+				context: self.egui_context.clone(),
+			},
+			dst,
+		);
+	}
+
+	/// Reads back the rendered capture and writes it to `directory`. Call
+	/// after the graph rendering into [`Self::target`] has executed.
+	pub fn save(&mut self, renderer: &Renderer) -> io::Result<PathBuf> {
+		self.pending = false;
+
+		let (resolution, texture, _) = self
+			.target
+			.as_ref()
+			.ok_or_else(|| io::Error::other("nothing was rendered"))?;
+
+		let swap_red_blue = match self.format {
+			TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => false,
+			TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => true,
+			format => {
+				return Err(io::Error::other(format!(
+					"can't save {:?} screenshots",
+					format
+				)))
+			}
+		};
+
+		// rows of the copy have to be padded to the copy alignment
+		let row_bytes = resolution.x * 4;
+		let padded_row_bytes =
+			row_bytes.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT;
+
+		let buffer = renderer.device.create_buffer(&BufferDescriptor {
+			label: Some("screenshot readback"),
+			size: (padded_row_bytes * resolution.y) as u64,
+			usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+			mapped_at_creation: false,
+		});
+
+		let mut encoder = renderer
+			.device
+			.create_command_encoder(&CommandEncoderDescriptor {
+				label: Some("screenshot readback"),
+			});
+		encoder.copy_texture_to_buffer(
+			ImageCopyTexture {
+				texture,
+				mip_level: 0,
+				origin: Origin3d::ZERO,
+				aspect: TextureAspect::All,
+			},
+			ImageCopyBuffer {
+				buffer: &buffer,
+				layout: ImageDataLayout {
+					offset: 0,
+					bytes_per_row: NonZeroU32::new(padded_row_bytes),
+					rows_per_image: None,
+				},
+			},
+			Extent3d {
+				width: resolution.x,
+				height: resolution.y,
+				depth_or_array_layers: 1,
+			},
+		);
+		renderer.queue.submit(Some(encoder.finish()));
+
+		let slice = buffer.slice(..);
+		let mapping = slice.map_async(MapMode::Read);
+		renderer.device.poll(Maintain::Wait);
+		pollster::block_on(mapping).map_err(|e| io::Error::other(format!("{:?}", e)))?;
+
+		let mut pixels = Vec::with_capacity((row_bytes * resolution.y) as usize);
+		for row in slice.get_mapped_range().chunks(padded_row_bytes as usize) {
+			pixels.extend_from_slice(&row[..row_bytes as usize]);
+		}
+		if swap_red_blue {
+			for pixel in pixels.chunks_mut(4) {
+				pixel.swap(0, 2);
+			}
+		}
+
+		fs::create_dir_all(&self.directory)?;
+		let seconds = SystemTime::now()
+			.duration_since(SystemTime::UNIX_EPOCH)
+			.map_or(0, |time| time.as_secs());
+		let path = self.directory.join(format!("opal-{}.bmp", seconds));
+		fs::write(&path, bitmap(*resolution, &pixels))?;
+
+		Ok(path)
+	}
+}
+
+/// Encodes top-down bgra pixels as a 32 bit bmp.
+fn bitmap(resolution: UVec2, pixels: &[u8]) -> Vec<u8> {
+	const HEADER_SIZE: u32 = 14 + 40;
+	let file_size = HEADER_SIZE + pixels.len() as u32;
+
+	let mut data = Vec::with_capacity(file_size as usize);
+	// file header
+	data.extend_from_slice(b"BM");
+	data.extend_from_slice(&file_size.to_le_bytes());
+	data.extend_from_slice(&0u32.to_le_bytes());
+	data.extend_from_slice(&HEADER_SIZE.to_le_bytes());
+	// info header, negative height for rows stored top to bottom
+	data.extend_from_slice(&40u32.to_le_bytes());
+	data.extend_from_slice(&(resolution.x as i32).to_le_bytes());
+	data.extend_from_slice(&(-(resolution.y as i32)).to_le_bytes());
+	data.extend_from_slice(&1u16.to_le_bytes());
+	data.extend_from_slice(&32u16.to_le_bytes());
+	data.extend_from_slice(&0u32.to_le_bytes());
+	data.extend_from_slice(&(pixels.len() as u32).to_le_bytes());
+	data.extend_from_slice(&2835u32.to_le_bytes());
+	data.extend_from_slice(&2835u32.to_le_bytes());
+	data.extend_from_slice(&0u32.to_le_bytes());
+	data.extend_from_slice(&0u32.to_le_bytes());
+
+	data.extend_from_slice(pixels);
+	data
+}
@@ -0,0 +1,433 @@
+use std::borrow::Cow;
+use std::num::NonZeroU64;
+
+use glam::{Mat4, UVec2, Vec2, Vec3, Vec4};
+use rend3::graph::{
+	DepthHandle, RenderGraph, RenderPassDepthTarget, RenderPassTarget, RenderPassTargets,
+};
+use rend3::types::Handedness;
+use rend3::util::bind_merge::{BindGroupBuilder, BindGroupLayoutBuilder};
+use rend3::Renderer;
+use rend3_routine::base::BaseRenderGraphIntermediateState;
+use wgpu::{
+	BindGroup, BindGroupLayout, BindingType, Buffer, BufferAddress, BufferBindingType,
+	BufferDescriptor, BufferUsages, Color, ColorTargetState, ColorWrites, CompareFunction,
+	DepthStencilState, Face, FragmentState, FrontFace, MultisampleState, PipelineLayoutDescriptor,
+	PrimitiveState, RenderPipeline, RenderPipelineDescriptor, ShaderModuleDescriptor, ShaderSource,
+	ShaderStages, StencilState, TextureFormat, VertexAttribute, VertexBufferLayout, VertexFormat,
+	VertexState, VertexStepMode,
+};
+
+use crate::camera::{self, ClipPlane};
+use crate::debug::DebugDraw;
+use crate::mesh_cache::MeshCache;
+use crate::scene::{ObjectId, Scene, SceneObject};
+use crate::staging::StagingPool;
+
+/// matrix and color, padded to the dynamic offset alignment
+const OBJECT_UNIFORM_SIZE: BufferAddress = 80;
+const OBJECT_UNIFORM_STRIDE: BufferAddress = 256;
+
+/// pick radius of the drag handle, in points
+const HANDLE_RADIUS: f32 = 12.0;
+
+/// Section plane for cutting models open, with a handle in the viewport that
+/// drags it along its normal.
+///
+/// The plane is applied as the cameras' clip plane, so it cuts the whole scene
+/// (see [`crate::camera::ClipPlanes`]). Objects in `capped` get their cut
+/// surfaces filled by [`SectionCapRoutine`], everything else is left hollow.
+pub struct SectionPlane {
+	pub open: bool,
+	pub enabled: bool,
+	pub plane: ClipPlane,
+	pub capped: Vec<ObjectId>,
+	pub cap_color: Vec3,
+	/// width of the drawn plane, and four times the handle's length
+	pub size: f32,
+
+	dragging: bool,
+}
+
+impl Default for SectionPlane {
+	fn default() -> Self {
+		Self {
+			open: false,
+			enabled: false,
+			plane: ClipPlane {
+				normal: Vec3::new(0.0, 0.0, -1.0),
+				distance: 0.0,
+			},
+			capped: Vec::new(),
+			cap_color: Vec3::new(0.8, 0.1, 0.1),
+			size: 4.0,
+			dragging: false,
+		}
+	}
+}
+
+impl SectionPlane {
+	/// Plane the cameras should clip against, if enabled.
+	pub fn clip_plane(&self) -> Option<ClipPlane> {
+		self.enabled.then_some(self.plane)
+	}
+
+	fn center(&self) -> Vec3 {
+		self.plane.normal.normalize_or_zero() * self.plane.distance
+	}
+
+	fn handle(&self) -> Vec3 {
+		self.center() + self.plane.normal.normalize_or_zero() * self.size * 0.25
+	}
+
+	pub fn window(&mut self, ctx: &egui::CtxRef, scene: &Scene) {
+		let mut open = self.open;
+		egui::Window::new("section plane")
+			.open(&mut open)
+			.resizable(false)
+			.show(ctx, |ui| {
+				ui.checkbox(&mut self.enabled, "enabled");
+				ui.add_enabled_ui(self.enabled, |ui| {
+					ui.horizontal(|ui| {
+						ui.label("normal");
+						for (axis, label) in [(Vec3::X, "x"), (Vec3::Y, "y"), (Vec3::Z, "z")] {
+							if ui.button(label).clicked() {
+								self.plane.normal = axis;
+							}
+						}
+						if ui.button("flip").clicked() {
+							self.plane.normal = -self.plane.normal;
+							self.plane.distance = -self.plane.distance;
+						}
+					});
+					ui.horizontal(|ui| {
+						ui.add(egui::DragValue::new(&mut self.plane.normal.x).speed(0.01));
+						ui.add(egui::DragValue::new(&mut self.plane.normal.y).speed(0.01));
+						ui.add(egui::DragValue::new(&mut self.plane.normal.z).speed(0.01));
+					});
+					ui.add(
+						egui::DragValue::new(&mut self.plane.distance)
+							.speed(0.01)
+							.prefix("distance "),
+					);
+					ui.label("drag the arrow in the viewport to move the plane");
+
+					ui.separator();
+					ui.horizontal(|ui| {
+						ui.label("cap color");
+						let mut color = self.cap_color.to_array();
+						ui.color_edit_button_rgb(&mut color);
+						self.cap_color = color.into();
+					});
+					for (id, object) in scene.iter() {
+						let mut capped = self.capped.contains(&id);
+						if ui.checkbox(&mut capped, &object.name).changed() {
+							match capped {
+								true => self.capped.push(id),
+								false => self.capped.retain(|&capped| capped != id),
+							}
+						}
+					}
+				});
+			});
+		self.open = open;
+	}
+
+	/// Drags the plane with the pointer. `view_proj` and `viewport` describe
+	/// the main viewport as it was last rendered, in physical pixels.
+	pub fn drag(&mut self, ctx: &egui::CtxRef, view_proj: Mat4, viewport: (UVec2, UVec2)) {
+		if !self.enabled {
+			self.dragging = false;
+			return;
+		}
+
+		let to_screen = |point| {
+			camera::world_to_screen(view_proj, viewport, point)
+				.map(|pixel| pixel / ctx.pixels_per_point())
+		};
+		let (center, handle) = match (to_screen(self.center()), to_screen(self.handle())) {
+			(Some(center), Some(handle)) => (center, handle),
+			_ => {
+				self.dragging = false;
+				return;
+			}
+		};
+
+		let pointer = &ctx.input().pointer;
+		if !self.dragging && pointer.any_pressed() && !ctx.is_pointer_over_area() {
+			if let Some(pos) = pointer.interact_pos() {
+				self.dragging = Vec2::new(pos.x, pos.y).distance(handle) < HANDLE_RADIUS;
+			}
+		}
+		if !pointer.primary_down() {
+			self.dragging = false;
+		}
+		if !self.dragging {
+			return;
+		}
+
+		// pointer movement along the handle on screen, scaled to world units
+		let axis = handle - center;
+		if axis.length_squared() > f32::EPSILON {
+			let delta = pointer.delta();
+			let moved = Vec2::new(delta.x, delta.y).dot(axis) / axis.length_squared();
+			self.plane.distance += moved * self.size * 0.25;
+		}
+	}
+
+	/// Queues the plane and its handle.
+	pub fn draw_gizmo(&self, debug_draw: &mut DebugDraw) {
+		if !self.enabled {
+			return;
+		}
+		let color = match self.dragging {
+			true => Vec4::new(1.0, 1.0, 0.3, 1.0),
+			false => Vec4::new(1.0, 0.2, 0.6, 1.0),
+		};
+		// nudged to the kept side so the lines aren't clipped by the plane
+		debug_draw.plane(
+			self.plane.normal,
+			self.plane.distance - 0.001,
+			self.size,
+			color,
+		);
+	}
+}
+
+struct Draw {
+	mesh: usize,
+	offset: u32,
+}
+
+/// Fills the cut surfaces of the objects a [`SectionPlane`] caps.
+///
+/// Clipping a closed mesh exposes its back faces, so drawing only the back
+/// faces against the scene depth covers exactly the cut. Expects the same
+/// clipped projection the scene was rendered with and a single sampled depth
+/// buffer.
+pub struct SectionCapRoutine {
+	pipeline: RenderPipeline,
+	bgl: BindGroupLayout,
+	uniforms: Buffer,
+	bg: BindGroup,
+	capacity: usize,
+
+	meshes: MeshCache,
+	draws: Vec<Draw>,
+}
+
+impl SectionCapRoutine {
+	pub fn new(renderer: &Renderer) -> Self {
+		let device = &renderer.device;
+
+		let bgl = BindGroupLayoutBuilder::new()
+			.append(
+				ShaderStages::VERTEX_FRAGMENT,
+				BindingType::Buffer {
+					ty: BufferBindingType::Uniform,
+					has_dynamic_offset: true,
+					min_binding_size: NonZeroU64::new(OBJECT_UNIFORM_SIZE),
+				},
+				None,
+			)
+			.build(device, Some("section cap bgl"));
+
+		let module = device.create_shader_module(&ShaderModuleDescriptor {
+			label: Some("section cap"),
+			source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("section.wgsl"))),
+		});
+
+		let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+			label: Some("section cap"),
+			bind_group_layouts: &[&bgl],
+			push_constant_ranges: &[],
+		});
+
+		let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+			label: Some("section cap"),
+			layout: Some(&layout),
+			vertex: VertexState {
+				module: &module,
+				entry_point: "vs_main",
+				buffers: &[VertexBufferLayout {
+					array_stride: 12,
+					step_mode: VertexStepMode::Vertex,
+					attributes: &[VertexAttribute {
+						format: VertexFormat::Float32x3,
+						offset: 0,
+						shader_location: 0,
+					}],
+				}],
+			},
+			// same winding as rend3's forward pass
+			primitive: PrimitiveState {
+				front_face: match renderer.handedness {
+					Handedness::Left => FrontFace::Cw,
+					Handedness::Right => FrontFace::Ccw,
+				},
+				cull_mode: Some(Face::Front),
+				..PrimitiveState::default()
+			},
+			depth_stencil: Some(DepthStencilState {
+				format: TextureFormat::Depth32Float,
+				depth_write_enabled: false,
+				depth_compare: CompareFunction::GreaterEqual,
+				stencil: StencilState::default(),
+				bias: Default::default(),
+			}),
+			multisample: MultisampleState::default(),
+			fragment: Some(FragmentState {
+				module: &module,
+				entry_point: "fs_main",
+				targets: &[ColorTargetState {
+					format: TextureFormat::Rgba16Float,
+					blend: None,
+					write_mask: ColorWrites::all(),
+				}],
+			}),
+			multiview: None,
+		});
+
+		let capacity = 16;
+		let (uniforms, bg) = create_uniforms(renderer, &bgl, capacity);
+
+		Self {
+			pipeline,
+			bgl,
+			uniforms,
+			bg,
+			capacity,
+			meshes: MeshCache::default(),
+			draws: Vec::new(),
+		}
+	}
+
+	/// Uploads the caps for the viewport about to be rendered. Call once per
+	/// viewport per frame, right before building its graph.
+	pub fn update(
+		&mut self,
+		renderer: &Renderer,
+		staging: &mut StagingPool,
+		view_proj: Mat4,
+		scene: &Scene,
+		section: &SectionPlane,
+	) {
+		self.draws.clear();
+		if !section.enabled {
+			return;
+		}
+
+		let mut data = Vec::new();
+		for &id in &section.capped {
+			let (object, geometry) = match scene.get(id) {
+				Some(
+					object @ SceneObject {
+						geometry: Some(geometry),
+						..
+					},
+				) if object.is_visible() => (object, geometry),
+				_ => continue,
+			};
+
+			self.meshes.upload(renderer, geometry);
+			self.draws.push(Draw {
+				mesh: geometry.key,
+				offset: data.len() as u32,
+			});
+
+			let (_, transform) = object.rendered_transforms();
+			data.extend(
+				(view_proj * transform)
+					.to_cols_array()
+					.iter()
+					.flat_map(|v| v.to_ne_bytes()),
+			);
+			data.extend(
+				section
+					.cap_color
+					.extend(1.0)
+					.to_array()
+					.iter()
+					.flat_map(|v| v.to_ne_bytes()),
+			);
+			data.resize(
+				data.len() + (OBJECT_UNIFORM_STRIDE - OBJECT_UNIFORM_SIZE) as usize,
+				0,
+			);
+		}
+
+		if self.draws.len() > self.capacity {
+			self.capacity = self.draws.len().next_power_of_two();
+			let (uniforms, bg) = create_uniforms(renderer, &self.bgl, self.capacity);
+			self.uniforms = uniforms;
+			self.bg = bg;
+		}
+		if !data.is_empty() {
+			staging.write(renderer, &self.uniforms, 0, &data);
+		}
+	}
+
+	/// Draws the caps into the scene's hdr color. Must be added after forward
+	/// rendering.
+	pub fn add_to_graph<'node>(
+		&'node self,
+		graph: &mut RenderGraph<'node>,
+		state: &BaseRenderGraphIntermediateState,
+	) {
+		if self.draws.is_empty() {
+			return;
+		}
+
+		let mut builder = graph.add_node("Section Caps");
+
+		let color_handle = builder.add_render_target_output(state.color);
+		let depth_handle = builder.add_render_target_input(state.depth);
+
+		let rpass_handle = builder.add_renderpass(RenderPassTargets {
+			targets: vec![RenderPassTarget {
+				color: color_handle,
+				clear: Color::BLACK,
+				resolve: None,
+			}],
+			depth_stencil: Some(RenderPassDepthTarget {
+				target: DepthHandle::RenderTarget(depth_handle),
+				depth_clear: Some(0.0),
+				stencil_clear: None,
+			}),
+		});
+
+		let pt_handle = builder.passthrough_ref(self);
+
+		builder.build(
+			move |pt, _renderer, encoder_or_pass, _temps, _ready, _graph_data| {
+				let this = pt.get(pt_handle);
+				let rpass = encoder_or_pass.get_rpass(rpass_handle);
+
+				rpass.set_pipeline(&this.pipeline);
+				for draw in &this.draws {
+					if let Some(mesh) = this.meshes.get(draw.mesh) {
+						rpass.set_bind_group(0, &this.bg, &[draw.offset]);
+						mesh.draw(rpass);
+					}
+				}
+			},
+		);
+	}
+}
+
+fn create_uniforms(
+	renderer: &Renderer,
+	bgl: &BindGroupLayout,
+	capacity: usize,
+) -> (Buffer, BindGroup) {
+	let buffer = renderer.device.create_buffer(&BufferDescriptor {
+		label: Some("section cap uniforms"),
+		size: capacity as BufferAddress * OBJECT_UNIFORM_STRIDE,
+		usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+		mapped_at_creation: false,
+	});
+	let bg = BindGroupBuilder::new()
+		.append_buffer_with_size(&buffer, OBJECT_UNIFORM_SIZE)
+		.build(&renderer.device, Some("section cap bg"), bgl);
+	(buffer, bg)
+}
@@ -0,0 +1,25 @@
+// Fills the cut surfaces of clipped objects with a hatched cap color.
+
+struct CapUniforms {
+	view_proj_model: mat4x4<f32>;
+	color: vec4<f32>;
+};
+
+[[group(0), binding(0)]]
+var<uniform> object: CapUniforms;
+
+[[stage(vertex)]]
+fn vs_main([[location(0)]] position: vec3<f32>) -> [[builtin(position)]] vec4<f32> {
+	return object.view_proj_model * vec4<f32>(position, 1.0);
+}
+
+// only back faces are drawn, which are seen where the section plane opened up
+// a closed mesh
+[[stage(fragment)]]
+fn fs_main([[builtin(position)]] position: vec4<f32>) -> [[location(0)]] vec4<f32> {
+	let diagonal = i32(position.x + position.y) / 6;
+	if (diagonal % 2 == 0) {
+		return vec4<f32>(object.color.rgb * 0.6, 1.0);
+	}
+	return vec4<f32>(object.color.rgb, 1.0);
+}
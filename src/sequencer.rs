@@ -0,0 +1,1179 @@
+use std::fs;
+use std::path::Path;
+
+use glam::{DVec3, EulerRot, Mat4, Quat, Vec3};
+use rend3::types::{DirectionalLight, DirectionalLightChange, DirectionalLightHandle};
+use rend3::Renderer;
+use serde_json::{json, Value};
+
+use crate::assets::AssetError;
+use crate::camera::CameraPose;
+use crate::cursor::CursorKind;
+use crate::scene::{ObjectId, Position, Scene, ScenePosition, SceneTransform, Transform};
+use crate::teleport::{self, ConsoleCommand, TeleportTarget};
+
+/// A value a track can hold, blended between keys.
+pub trait Keyable: Copy {
+	fn interpolate(a: &Self, b: &Self, t: f32) -> Self;
+	fn to_json(&self) -> Value;
+	fn from_json(value: &Value) -> Option<Self>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Key<T> {
+	pub time: f32,
+	pub value: T,
+}
+
+/// Keys sorted by time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track<T> {
+	pub keys: Vec<Key<T>>,
+}
+
+impl<T> Default for Track<T> {
+	fn default() -> Self {
+		Self { keys: Vec::new() }
+	}
+}
+
+impl<T: Keyable> Track<T> {
+	/// keys closer together than this replace each other
+	const SAME_TIME: f32 = 1e-3;
+
+	/// Adds a key, replacing one at the same time. Returns its index.
+	pub fn insert(&mut self, time: f32, value: T) -> usize {
+		if let Some(index) = self
+			.keys
+			.iter()
+			.position(|key| (key.time - time).abs() < Self::SAME_TIME)
+		{
+			self.keys[index].value = value;
+			return index;
+		}
+		let index = self.keys.partition_point(|key| key.time < time);
+		self.keys.insert(index, Key { time, value });
+		index
+	}
+
+	/// Moves a key to a new time, keeping the keys sorted. Returns its new
+	/// index, or `None` if another key is already at `time`, in which case
+	/// the key stays where it was rather than replacing that one.
+	pub fn retime(&mut self, index: usize, time: f32) -> Option<usize> {
+		let taken = self
+			.keys
+			.iter()
+			.enumerate()
+			.any(|(other, key)| other != index && (key.time - time).abs() < Self::SAME_TIME);
+		if taken || index >= self.keys.len() {
+			return None;
+		}
+		let key = self.keys.remove(index);
+		Some(self.insert(time, key.value))
+	}
+
+	/// Value at `time`, blended between the keys around it and held before
+	/// the first and after the last.
+	pub fn sample(&self, time: f32) -> Option<T> {
+		let next = self.keys.partition_point(|key| key.time <= time);
+		match (next.checked_sub(1), self.keys.get(next)) {
+			(Some(previous), Some(b)) => {
+				let a = &self.keys[previous];
+				let t = (time - a.time) / (b.time - a.time).max(f32::EPSILON);
+				Some(T::interpolate(&a.value, &b.value, t))
+			}
+			(Some(previous), None) => Some(self.keys[previous].value),
+			(None, Some(first)) => Some(first.value),
+			(None, None) => None,
+		}
+	}
+
+	/// Index of the last key at or before `time`.
+	pub fn current(&self, time: f32) -> Option<usize> {
+		self.keys
+			.partition_point(|key| key.time <= time)
+			.checked_sub(1)
+	}
+
+	fn to_json(&self) -> Value {
+		self.keys
+			.iter()
+			.map(|key| json!({ "time": key.time, "value": key.value.to_json() }))
+			.collect()
+	}
+
+	fn from_json(value: &Value) -> Option<Self> {
+		let mut track = Track::default();
+		for key in value.as_array()? {
+			let time = key.get("time")?.as_f64()? as f32;
+			track.insert(time, T::from_json(key.get("value")?)?);
+		}
+		Some(track)
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransformKey {
+	pub translation: Vec3,
+	pub rotation: Quat,
+	pub scale: Vec3,
+}
+
+impl TransformKey {
+	pub fn from_mat4(transform: Mat4) -> Self {
+		let (scale, rotation, translation) = transform.to_scale_rotation_translation();
+		Self {
+			translation,
+			rotation,
+			scale,
+		}
+	}
+
+	pub fn to_mat4(&self) -> Mat4 {
+		Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+	}
+}
+
+impl Keyable for TransformKey {
+	fn interpolate(a: &Self, b: &Self, t: f32) -> Self {
+		Self {
+			translation: a.translation.lerp(b.translation, t),
+			rotation: a.rotation.slerp(b.rotation, t),
+			scale: a.scale.lerp(b.scale, t),
+		}
+	}
+
+	fn to_json(&self) -> Value {
+		json!({
+			"translation": self.translation.to_array(),
+			"rotation": self.rotation.to_array(),
+			"scale": self.scale.to_array(),
+		})
+	}
+
+	fn from_json(value: &Value) -> Option<Self> {
+		let rotation = floats::<4>(value.get("rotation")?)?;
+		Some(Self {
+			translation: Vec3::from(floats(value.get("translation")?)?),
+			rotation: Quat::from_array(rotation).normalize(),
+			scale: Vec3::from(floats(value.get("scale")?)?),
+		})
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightKey {
+	pub color: Vec3,
+	pub intensity: f32,
+	/// direction the light shines in
+	pub direction: Vec3,
+}
+
+impl LightKey {
+	pub fn from_light(light: &DirectionalLight) -> Self {
+		Self {
+			color: light.color,
+			intensity: light.intensity,
+			direction: light.direction.normalize_or_zero(),
+		}
+	}
+}
+
+impl Keyable for LightKey {
+	fn interpolate(a: &Self, b: &Self, t: f32) -> Self {
+		Self {
+			color: a.color.lerp(b.color, t),
+			intensity: a.intensity + (b.intensity - a.intensity) * t,
+			direction: a.direction.lerp(b.direction, t).normalize_or_zero(),
+		}
+	}
+
+	fn to_json(&self) -> Value {
+		json!({
+			"color": self.color.to_array(),
+			"intensity": self.intensity,
+			"direction": self.direction.to_array(),
+		})
+	}
+
+	fn from_json(value: &Value) -> Option<Self> {
+		Some(Self {
+			color: Vec3::from(floats(value.get("color")?)?),
+			intensity: value.get("intensity")?.as_f64()? as f32,
+			direction: Vec3::from(floats(value.get("direction")?)?),
+		})
+	}
+}
+
+/// Camera cuts jump from one pose to the next without blending.
+impl Keyable for CameraPose {
+	fn interpolate(a: &Self, _: &Self, _: f32) -> Self {
+		*a
+	}
+
+	fn to_json(&self) -> Value {
+		json!({
+			"position": self.position.to_f64().to_array(),
+			"orientation": self.orientation.to_array(),
+		})
+	}
+
+	fn from_json(value: &Value) -> Option<Self> {
+		Some(Self {
+			position: Position::from_f64(DVec3::from(doubles(value.get("position")?)?)),
+			orientation: Quat::from_array(floats(value.get("orientation")?)?).normalize(),
+		})
+	}
+}
+
+fn doubles<const N: usize>(value: &Value) -> Option<[f64; N]> {
+	let array = value.as_array()?;
+	if array.len() != N {
+		return None;
+	}
+	let mut doubles = [0.0; N];
+	for (double, value) in doubles.iter_mut().zip(array) {
+		*double = value.as_f64()?;
+	}
+	Some(doubles)
+}
+
+fn floats<const N: usize>(value: &Value) -> Option<[f32; N]> {
+	let array = value.as_array()?;
+	if array.len() != N {
+		return None;
+	}
+	let mut floats = [0.0; N];
+	for (float, value) in floats.iter_mut().zip(array) {
+		*float = value.as_f64()? as f32;
+	}
+	Some(floats)
+}
+
+/// A named point in a sequence that fires when playback passes it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventMarker {
+	pub time: f32,
+	pub name: String,
+	/// run when the event fires, see [`EventCommand::parse`]. Scripts can
+	/// listen for the event by name instead.
+	pub command: String,
+}
+
+/// Engine command an [`EventMarker`] can run.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventCommand {
+	/// moves the camera, see [`teleport::parse_command`]
+	Teleport(TeleportTarget),
+	/// copies an object, at its own position unless given one
+	Spawn {
+		template: String,
+		position: Option<Vec3>,
+	},
+	PlaySound(String),
+	Screenshot,
+	Log(String),
+	/// overrides the cursor over the viewport, `None` gives it back to the
+	/// tools
+	Cursor(Option<CursorKind>),
+	/// shakes the main camera with this much trauma, see
+	/// [`crate::camera::CameraShake`]
+	Shake(f32),
+}
+
+impl EventCommand {
+	/// Parses one of:
+	///
+	/// - `tp <x> <y> <z>`, `goto <name>` or `shake <trauma>`, see
+	///   [`teleport::parse_command`]
+	/// - `spawn <object> [<x> <y> <z>]`
+	/// - `sound <name>`
+	/// - `screenshot`
+	/// - `log <message>`
+	/// - `cursor <kind>`, with a [`CursorKind::label`] or `default`
+	///
+	/// An empty command is `Ok(None)`, for events only scripts act on.
+	pub fn parse(command: &str) -> Result<Option<Self>, String> {
+		let command = command.trim();
+		let (verb, rest) = command.split_once(' ').unwrap_or((command, ""));
+		let rest = rest.trim();
+		let parsed = match verb {
+			"" => return Ok(None),
+			"tp" | "goto" | "shake" => match teleport::parse_command(command)? {
+				ConsoleCommand::Teleport(target) => EventCommand::Teleport(target),
+				ConsoleCommand::Shake(trauma) => EventCommand::Shake(trauma),
+			},
+			"spawn" => {
+				let mut parts = rest.split_whitespace();
+				let template = parts.next().ok_or("usage: spawn <object> [x y z]")?;
+				let coords = parts
+					.map(|part| part.parse().map_err(|_| format!("not a number: {}", part)))
+					.collect::<Result<Vec<f32>, _>>()?;
+				let position = match coords.as_slice() {
+					[] => None,
+					[x, y, z] => Some(Vec3::new(*x, *y, *z)),
+					_ => return Err("usage: spawn <object> [x y z]".into()),
+				};
+				EventCommand::Spawn {
+					template: template.into(),
+					position,
+				}
+			}
+			"sound" if !rest.is_empty() => EventCommand::PlaySound(rest.into()),
+			"sound" => return Err("usage: sound <name>".into()),
+			"screenshot" => EventCommand::Screenshot,
+			"log" => EventCommand::Log(rest.into()),
+			"cursor" => EventCommand::Cursor(match rest {
+				"default" => None,
+				label => Some(
+					CursorKind::from_label(label)
+						.ok_or_else(|| format!("unknown cursor: {}", label))?,
+				),
+			}),
+			_ => return Err(format!("unknown command: {}", verb)),
+		};
+		Ok(Some(parsed))
+	}
+}
+
+/// Tracks animating scene objects, the sun and the camera over time.
+#[derive(Debug, Clone)]
+pub struct Sequence {
+	/// seconds
+	pub length: f32,
+	/// transform tracks of objects by name
+	pub objects: Vec<(String, Track<TransformKey>)>,
+	pub light: Track<LightKey>,
+	pub camera_cuts: Track<CameraPose>,
+	/// sorted by time
+	pub events: Vec<EventMarker>,
+}
+
+impl Default for Sequence {
+	fn default() -> Self {
+		Self {
+			length: 10.0,
+			objects: Vec::new(),
+			light: Track::default(),
+			camera_cuts: Track::default(),
+			events: Vec::new(),
+		}
+	}
+}
+
+impl Sequence {
+	pub fn is_empty(&self) -> bool {
+		self.objects.iter().all(|(_, track)| track.keys.is_empty())
+			&& self.light.keys.is_empty()
+			&& self.camera_cuts.keys.is_empty()
+			&& self.events.is_empty()
+	}
+
+	pub fn to_json(&self) -> Value {
+		let objects: serde_json::Map<String, Value> = self
+			.objects
+			.iter()
+			.map(|(name, track)| (name.clone(), track.to_json()))
+			.collect();
+		let events: Vec<Value> = self
+			.events
+			.iter()
+			.map(
+				|event| json!({ "time": event.time, "name": event.name, "command": event.command }),
+			)
+			.collect();
+		json!({
+			"length": self.length,
+			"objects": objects,
+			"light": self.light.to_json(),
+			"camera_cuts": self.camera_cuts.to_json(),
+			"events": events,
+		})
+	}
+
+	pub fn from_json(value: &Value) -> Option<Self> {
+		let objects = value
+			.get("objects")?
+			.as_object()?
+			.iter()
+			.map(|(name, track)| Some((name.clone(), Track::from_json(track)?)))
+			.collect::<Option<_>>()?;
+		// sequences saved before events existed have none
+		let mut events = Vec::new();
+		for event in value
+			.get("events")
+			.and_then(Value::as_array)
+			.into_iter()
+			.flatten()
+		{
+			events.push(EventMarker {
+				time: event.get("time")?.as_f64()? as f32,
+				name: event.get("name")?.as_str()?.into(),
+				command: event.get("command")?.as_str()?.into(),
+			});
+		}
+		events.sort_by(|a, b| a.time.total_cmp(&b.time));
+		Some(Self {
+			length: value.get("length")?.as_f64()? as f32,
+			objects,
+			light: Track::from_json(value.get("light")?)?,
+			camera_cuts: Track::from_json(value.get("camera_cuts")?)?,
+			events,
+		})
+	}
+
+	pub fn save(&self, path: &Path) -> Result<(), AssetError> {
+		let text = serde_json::to_string_pretty(&self.to_json())
+			.map_err(|e| AssetError::Invalid(e.to_string()))?;
+		fs::write(path, text)?;
+		Ok(())
+	}
+
+	pub fn load(path: &Path) -> Result<Self, AssetError> {
+		let text = fs::read_to_string(path)?;
+		let value: Value = serde_json::from_str(&text)
+			.map_err(|e| AssetError::Invalid(format!("invalid json: {}", e)))?;
+		Self::from_json(&value).ok_or_else(|| AssetError::Invalid("not a sequence".into()))
+	}
+
+	/// Adds an event, keeping them sorted. Returns its index.
+	pub fn add_event(&mut self, event: EventMarker) -> usize {
+		let index = self.events.partition_point(|e| e.time <= event.time);
+		self.events.insert(index, event);
+		index
+	}
+
+	fn object_track(&mut self, name: &str) -> &mut Track<TransformKey> {
+		let index = match self.objects.iter().position(|(n, _)| n == name) {
+			Some(index) => index,
+			None => {
+				self.objects.push((name.into(), Track::default()));
+				self.objects.len() - 1
+			}
+		};
+		&mut self.objects[index].1
+	}
+}
+
+/// Which track a key is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TrackRef {
+	Object(usize),
+	Light,
+	Camera,
+	Event,
+}
+
+/// Plays a [`Sequence`] on the scene and edits it in a timeline panel.
+///
+/// Playback advances with the simulation's fixed timestep, so object
+/// transforms are interpolated and motion blurred like any other movement.
+pub struct Sequencer {
+	pub open: bool,
+	pub sequence: Sequence,
+	/// seconds into the sequence
+	pub time: f32,
+	pub playing: bool,
+	pub looping: bool,
+	pub path: String,
+
+	/// time was changed outside of playback and needs applying
+	scrubbed: bool,
+	/// camera cut last applied, to only cut when it changes
+	applied_cut: Option<usize>,
+	object: String,
+	selected: Option<(TrackRef, usize)>,
+	/// events passed since they were last taken
+	fired: Vec<EventMarker>,
+	error: Option<String>,
+}
+
+impl Default for Sequencer {
+	fn default() -> Self {
+		Self {
+			open: false,
+			sequence: Sequence::default(),
+			time: 0.0,
+			playing: false,
+			looping: true,
+			path: "sequence.json".into(),
+			scrubbed: false,
+			applied_cut: None,
+			object: String::new(),
+			selected: None,
+			fired: Vec::new(),
+			error: None,
+		}
+	}
+}
+
+impl Sequencer {
+	/// Runs one simulation step, call after [`Scene::begin_step`].
+	pub fn step(&mut self, scene: &mut Scene, step: f32) {
+		if !self.playing {
+			return;
+		}
+		let start = self.time;
+		self.time += step;
+		if self.time > self.sequence.length {
+			// fire the rest of the sequence before wrapping around
+			self.fire_events(start, f32::INFINITY);
+			match self.looping {
+				true => {
+					self.time %= self.sequence.length.max(step);
+					self.fire_events(0.0, self.time);
+				}
+				false => {
+					self.time = self.sequence.length;
+					self.playing = false;
+				}
+			}
+		} else {
+			self.fire_events(start, self.time);
+		}
+		self.apply_objects(scene, false);
+	}
+
+	/// Queues the events from `start` up to but not including `end`.
+	fn fire_events(&mut self, start: f32, end: f32) {
+		let events = self.sequence.events.iter();
+		let passed = events.filter(|event| event.time >= start && event.time < end);
+		self.fired.extend(passed.cloned());
+	}
+
+	/// Events playback passed since the last call, in order.
+	pub fn take_events(&mut self) -> Vec<EventMarker> {
+		std::mem::take(&mut self.fired)
+	}
+
+	/// Applies the light and camera tracks, and objects after scrubbing.
+	/// Returns a camera pose when playback reaches a new cut.
+	pub fn update(
+		&mut self,
+		renderer: &Renderer,
+		scene: &mut Scene,
+		light_handle: &DirectionalLightHandle,
+		light: &mut DirectionalLight,
+	) -> Option<CameraPose> {
+		let active = self.playing || self.scrubbed;
+		if self.scrubbed {
+			self.apply_objects(scene, true);
+			self.scrubbed = false;
+		}
+		if !active {
+			return None;
+		}
+
+		if let Some(key) = self.sequence.light.sample(self.time) {
+			light.color = key.color;
+			light.intensity = key.intensity;
+			light.direction = key.direction;
+			renderer.update_directional_light(
+				light_handle,
+				DirectionalLightChange {
+					color: Some(key.color),
+					intensity: Some(key.intensity),
+					direction: Some(key.direction),
+					distance: None,
+				},
+			);
+		}
+
+		let cut = self.sequence.camera_cuts.current(self.time);
+		if cut == self.applied_cut {
+			return None;
+		}
+		self.applied_cut = cut;
+		cut.map(|index| self.sequence.camera_cuts.keys[index].value)
+	}
+
+	/// Moves keyed objects to where they are at the current time. Teleports
+	/// skip interpolation, for jumps in time.
+	fn apply_objects(&self, scene: &mut Scene, teleport: bool) {
+		for (name, track) in &self.sequence.objects {
+			let key = match track.sample(self.time) {
+				Some(key) => key,
+				None => continue,
+			};
+			let id = match find_object(scene, name) {
+				Some(id) => id,
+				None => continue,
+			};
+			let transform = Transform::from_mat4(key.to_mat4());
+			match teleport {
+				true => scene.teleport(id, transform),
+				false => scene.set_transform(id, transform),
+			}
+		}
+	}
+
+	fn seek(&mut self, time: f32) {
+		self.time = time.clamp(0.0, self.sequence.length);
+		self.scrubbed = true;
+		// cut to whatever camera is active at the new time
+		self.applied_cut = None;
+	}
+
+	pub fn window(
+		&mut self,
+		ctx: &egui::CtxRef,
+		scene: &Scene,
+		light: &DirectionalLight,
+		camera: &CameraPose,
+	) {
+		let mut open = self.open;
+		egui::Window::new("sequencer")
+			.open(&mut open)
+			.default_width(520.0)
+			.show(ctx, |ui| {
+				ui.horizontal(|ui| {
+					let label = match self.playing {
+						true => "pause",
+						false => "play",
+					};
+					if ui.button(label).clicked() {
+						self.playing = !self.playing;
+						if self.playing && self.time >= self.sequence.length {
+							self.seek(0.0);
+						}
+					}
+					if ui.button("stop").clicked() {
+						self.playing = false;
+						self.seek(0.0);
+					}
+					ui.checkbox(&mut self.looping, "loop");
+					let mut time = self.time;
+					let max = self.sequence.length;
+					let response = ui.add(
+						egui::DragValue::new(&mut time)
+							.speed(0.01)
+							.clamp_range(0.0..=max)
+							.suffix(" s"),
+					);
+					if response.changed() {
+						self.seek(time);
+					}
+					ui.label("of");
+					ui.add(
+						egui::DragValue::new(&mut self.sequence.length)
+							.speed(0.1)
+							.clamp_range(0.1..=3600.0)
+							.suffix(" s"),
+					);
+				});
+
+				self.timeline(ui);
+
+				ui.separator();
+				self.key_buttons(ui, scene, light, camera);
+				self.selected_key(ui);
+
+				ui.separator();
+				ui.horizontal(|ui| {
+					ui.label("file");
+					ui.text_edit_singleline(&mut self.path);
+					if ui.button("save").clicked() {
+						self.error = self
+							.sequence
+							.save(Path::new(&self.path))
+							.err()
+							.map(|e| e.to_string());
+					}
+					if ui.button("load").clicked() {
+						match Sequence::load(Path::new(&self.path)) {
+							Ok(sequence) => {
+								self.sequence = sequence;
+								self.selected = None;
+								self.error = None;
+								self.seek(0.0);
+							}
+							Err(e) => self.error = Some(e.to_string()),
+						}
+					}
+				});
+				if let Some(error) = &self.error {
+					ui.colored_label(egui::Color32::RED, error);
+				}
+			});
+		self.open = open;
+	}
+
+	/// Rows of keys under a time ruler. Clicking a key selects it, clicking
+	/// or dragging anywhere else moves the playhead.
+	fn timeline(&mut self, ui: &mut egui::Ui) {
+		const LABEL_WIDTH: f32 = 100.0;
+		const ROW_HEIGHT: f32 = 18.0;
+
+		let mut rows: Vec<(String, TrackRef, Vec<f32>)> = self
+			.sequence
+			.objects
+			.iter()
+			.enumerate()
+			.map(|(i, (name, track))| (name.clone(), TrackRef::Object(i), key_times(track)))
+			.collect();
+		rows.push((
+			"light".into(),
+			TrackRef::Light,
+			key_times(&self.sequence.light),
+		));
+		rows.push((
+			"camera cuts".into(),
+			TrackRef::Camera,
+			key_times(&self.sequence.camera_cuts),
+		));
+		rows.push((
+			"events".into(),
+			TrackRef::Event,
+			self.sequence
+				.events
+				.iter()
+				.map(|event| event.time)
+				.collect(),
+		));
+
+		let width = ui.available_width().max(LABEL_WIDTH + 100.0);
+		let height = ROW_HEIGHT * (rows.len() + 1) as f32;
+		let (rect, response) =
+			ui.allocate_exact_size(egui::vec2(width, height), egui::Sense::click_and_drag());
+		let painter = ui.painter_at(rect);
+		let visuals = ui.visuals();
+		let track_left = rect.left() + LABEL_WIDTH;
+		let length = self.sequence.length;
+		let x_at = |time: f32| track_left + time / length * (rect.right() - track_left);
+
+		// ruler with a tick every power of ten that fits about ten of them
+		let tick = 10f32.powf((length / 10.0).log10().ceil());
+		let mut time = 0.0;
+		while time <= length {
+			let x = x_at(time);
+			painter.line_segment(
+				[
+					egui::pos2(x, rect.top() + ROW_HEIGHT * 0.6),
+					egui::pos2(x, rect.bottom()),
+				],
+				visuals.widgets.noninteractive.bg_stroke,
+			);
+			painter.text(
+				egui::pos2(x + 2.0, rect.top()),
+				egui::Align2::LEFT_TOP,
+				format!("{}", time),
+				egui::TextStyle::Small,
+				visuals.text_color(),
+			);
+			time += tick;
+		}
+
+		let mut clicked_key = None;
+		let pointer = response.interact_pointer_pos();
+		for (row, (name, track, times)) in rows.iter().enumerate() {
+			let y = rect.top() + ROW_HEIGHT * (row as f32 + 1.5);
+			painter.text(
+				egui::pos2(rect.left(), y),
+				egui::Align2::LEFT_CENTER,
+				name,
+				egui::TextStyle::Small,
+				visuals.text_color(),
+			);
+			for (index, time) in times.iter().enumerate() {
+				let center = egui::pos2(x_at(*time), y);
+				let selected = self.selected == Some((*track, index));
+				let color = match (selected, track) {
+					(true, _) => egui::Color32::YELLOW,
+					(false, TrackRef::Event) => egui::Color32::from_rgb(90, 170, 255),
+					(false, _) => visuals.text_color(),
+				};
+				let r = 4.0;
+				painter.add(egui::Shape::convex_polygon(
+					vec![
+						center - egui::vec2(0.0, r),
+						center + egui::vec2(r, 0.0),
+						center + egui::vec2(0.0, r),
+						center - egui::vec2(r, 0.0),
+					],
+					color,
+					egui::Stroke::none(),
+				));
+				if let Some(pointer) = pointer {
+					if response.clicked() && pointer.distance(center) <= r + 2.0 {
+						clicked_key = Some((*track, index));
+					}
+				}
+			}
+		}
+
+		let playhead = x_at(self.time);
+		painter.line_segment(
+			[
+				egui::pos2(playhead, rect.top()),
+				egui::pos2(playhead, rect.bottom()),
+			],
+			egui::Stroke::new(2.0, egui::Color32::from_rgb(255, 90, 60)),
+		);
+
+		match (clicked_key, pointer) {
+			(Some(key), _) => self.selected = Some(key),
+			(None, Some(pointer))
+				if (response.clicked() || response.dragged()) && pointer.x >= track_left =>
+			{
+				let time = (pointer.x - track_left) / (rect.right() - track_left) * length;
+				self.seek(time);
+			}
+			_ => {}
+		}
+	}
+
+	/// Buttons keying the current state at the playhead.
+	fn key_buttons(
+		&mut self,
+		ui: &mut egui::Ui,
+		scene: &Scene,
+		light: &DirectionalLight,
+		camera: &CameraPose,
+	) {
+		let time = self.time;
+		ui.horizontal(|ui| {
+			egui::ComboBox::from_id_source("sequencer object")
+				.selected_text(self.object.as_str())
+				.show_ui(ui, |ui| {
+					let mut names: Vec<&str> = scene
+						.iter()
+						.map(|(_, object)| object.name.as_str())
+						.collect();
+					names.sort_unstable();
+					for name in names {
+						ui.selectable_value(&mut self.object, name.to_string(), name);
+					}
+				});
+			let object = find_object(scene, &self.object).and_then(|id| scene.get(id));
+			if ui
+				.add_enabled(object.is_some(), egui::Button::new("key object"))
+				.clicked()
+			{
+				if let Some(object) = object {
+					let key = TransformKey::from_mat4(object.transform().to_mat4());
+					let index = self.sequence.object_track(&self.object).insert(time, key);
+					let track = self
+						.sequence
+						.objects
+						.iter()
+						.position(|(n, _)| *n == self.object);
+					self.selected = track.map(|track| (TrackRef::Object(track), index));
+				}
+			}
+			if ui.button("key light").clicked() {
+				let index = self
+					.sequence
+					.light
+					.insert(time, LightKey::from_light(light));
+				self.selected = Some((TrackRef::Light, index));
+			}
+			if ui.button("cut to camera").clicked() {
+				let index = self.sequence.camera_cuts.insert(time, *camera);
+				self.selected = Some((TrackRef::Camera, index));
+			}
+			if ui.button("add event").clicked() {
+				let index = self.sequence.add_event(EventMarker {
+					time,
+					name: format!("event {}", self.sequence.events.len() + 1),
+					command: String::new(),
+				});
+				self.selected = Some((TrackRef::Event, index));
+			}
+		});
+	}
+
+	/// Fields editing the selected key.
+	fn selected_key(&mut self, ui: &mut egui::Ui) {
+		let (track, index) = match self.selected {
+			Some(selected) => selected,
+			None => return,
+		};
+		let old_time = match self.key_time(track, index) {
+			Some(time) => time,
+			None => {
+				self.selected = None;
+				return;
+			}
+		};
+
+		let mut changed = false;
+		let mut delete = false;
+		let mut new_time = old_time;
+		ui.horizontal(|ui| {
+			ui.label("key at");
+			let max = self.sequence.length;
+			ui.add(
+				egui::DragValue::new(&mut new_time)
+					.speed(0.01)
+					.clamp_range(0.0..=max)
+					.suffix(" s"),
+			);
+			delete = ui.button("delete").clicked();
+		});
+
+		match track {
+			TrackRef::Object(i) => {
+				let key = &mut self.sequence.objects[i].1.keys[index].value;
+				changed |= vec3_row(ui, "position", &mut key.translation, 0.05);
+				let (y, x, z) = key.rotation.to_euler(EulerRot::YXZ);
+				let mut degrees = Vec3::new(x, y, z) * (180.0 / std::f32::consts::PI);
+				if vec3_row(ui, "rotation", &mut degrees, 1.0) {
+					let radians = degrees * (std::f32::consts::PI / 180.0);
+					key.rotation = Quat::from_euler(EulerRot::YXZ, radians.y, radians.x, radians.z);
+					changed = true;
+				}
+				changed |= vec3_row(ui, "scale", &mut key.scale, 0.01);
+			}
+			TrackRef::Light => {
+				let key = &mut self.sequence.light.keys[index].value;
+				ui.horizontal(|ui| {
+					ui.label("color");
+					let mut color = key.color.to_array();
+					if ui.color_edit_button_rgb(&mut color).changed() {
+						key.color = Vec3::from(color);
+						changed = true;
+					}
+					changed |= ui
+						.add(
+							egui::DragValue::new(&mut key.intensity)
+								.speed(0.1)
+								.prefix("intensity "),
+						)
+						.changed();
+				});
+				if vec3_row(ui, "direction", &mut key.direction, 0.01) {
+					key.direction = key.direction.normalize_or_zero();
+					changed = true;
+				}
+			}
+			TrackRef::Camera => {
+				let key = &mut self.sequence.camera_cuts.keys[index].value;
+				let mut position = Vec3::from(key.position.to_vec3a());
+				if vec3_row(ui, "position", &mut position, 0.05) {
+					key.position = Position::from_vec3a(position.into());
+					changed = true;
+				}
+			}
+			TrackRef::Event => {
+				let event = &mut self.sequence.events[index];
+				ui.horizontal(|ui| {
+					ui.label("name");
+					ui.text_edit_singleline(&mut event.name);
+				});
+				ui.horizontal(|ui| {
+					ui.label("command");
+					ui.add(
+						egui::TextEdit::singleline(&mut event.command)
+							.hint_text("spawn cube 0 2 0 / sound name / tp x y z"),
+					);
+				});
+				if let Err(e) = EventCommand::parse(&event.command) {
+					ui.colored_label(egui::Color32::RED, e);
+				}
+			}
+		}
+
+		if delete {
+			match track {
+				TrackRef::Object(i) => {
+					self.sequence.objects[i].1.keys.remove(index);
+				}
+				TrackRef::Light => {
+					self.sequence.light.keys.remove(index);
+				}
+				TrackRef::Camera => {
+					self.sequence.camera_cuts.keys.remove(index);
+				}
+				TrackRef::Event => {
+					self.sequence.events.remove(index);
+				}
+			}
+			self.selected = None;
+			self.edited(track);
+			return;
+		}
+		if new_time != old_time {
+			// keys dragged onto another stay put instead of replacing it
+			let retimed = match track {
+				TrackRef::Object(i) => self.sequence.objects[i].1.retime(index, new_time),
+				TrackRef::Light => self.sequence.light.retime(index, new_time),
+				TrackRef::Camera => self.sequence.camera_cuts.retime(index, new_time),
+				TrackRef::Event => {
+					let event = self.sequence.events.remove(index);
+					Some(self.sequence.add_event(EventMarker {
+						time: new_time,
+						..event
+					}))
+				}
+			};
+			if let Some(index) = retimed {
+				self.selected = Some((track, index));
+				changed = true;
+			}
+		}
+		if changed {
+			self.edited(track);
+		}
+	}
+
+	fn key_time(&self, track: TrackRef, index: usize) -> Option<f32> {
+		let times = match track {
+			TrackRef::Object(i) => key_times(&self.sequence.objects.get(i)?.1),
+			TrackRef::Light => key_times(&self.sequence.light),
+			TrackRef::Camera => key_times(&self.sequence.camera_cuts),
+			TrackRef::Event => self.sequence.events.iter().map(|e| e.time).collect(),
+		};
+		times.get(index).copied()
+	}
+
+	/// Shows an edit to a track at the current time.
+	fn edited(&mut self, track: TrackRef) {
+		self.scrubbed = true;
+		if track == TrackRef::Camera {
+			self.applied_cut = None;
+		}
+	}
+}
+
+fn key_times<T>(track: &Track<T>) -> Vec<f32> {
+	track.keys.iter().map(|key| key.time).collect()
+}
+
+fn find_object(scene: &Scene, name: &str) -> Option<ObjectId> {
+	scene
+		.iter()
+		.find(|(_, object)| object.name == name)
+		.map(|(id, _)| id)
+}
+
+/// Labelled x, y and z fields, returns whether any changed.
+fn vec3_row(ui: &mut egui::Ui, label: &str, value: &mut Vec3, speed: f32) -> bool {
+	ui.horizontal(|ui| {
+		ui.label(label);
+		let mut changed = false;
+		for component in value.as_mut() {
+			changed |= ui
+				.add(egui::DragValue::new(component).speed(speed))
+				.changed();
+		}
+		changed
+	})
+	.inner
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn light(intensity: f32) -> LightKey {
+		LightKey {
+			color: Vec3::ONE,
+			intensity,
+			direction: -Vec3::Y,
+		}
+	}
+
+	fn event(time: f32, name: &str) -> EventMarker {
+		EventMarker {
+			time,
+			name: name.into(),
+			command: String::new(),
+		}
+	}
+
+	fn names(events: Vec<EventMarker>) -> Vec<String> {
+		events.into_iter().map(|event| event.name).collect()
+	}
+
+	#[test]
+	fn tracks_hold_outside_their_keys_and_blend_between() {
+		let mut track = Track::default();
+		assert_eq!(track.sample(1.0), None);
+		track.insert(1.0, light(2.0));
+		track.insert(3.0, light(6.0));
+
+		assert_eq!(track.sample(0.0).unwrap().intensity, 2.0);
+		assert_eq!(track.sample(1.0).unwrap().intensity, 2.0);
+		assert_eq!(track.sample(2.5).unwrap().intensity, 5.0);
+		assert_eq!(track.sample(3.0).unwrap().intensity, 6.0);
+		assert_eq!(track.sample(10.0).unwrap().intensity, 6.0);
+	}
+
+	#[test]
+	fn events_at_the_end_fire_once_a_loop() {
+		let mut scene = Scene::default();
+		let mut sequencer = Sequencer {
+			playing: true,
+			..Sequencer::default()
+		};
+		sequencer.sequence.length = 1.0;
+		sequencer.sequence.add_event(event(0.0, "start"));
+		sequencer.sequence.add_event(event(1.0, "end"));
+
+		// lands right on the end, which hasn't been passed yet
+		for _ in 0..4 {
+			sequencer.step(&mut scene, 0.25);
+		}
+		assert_eq!(sequencer.time, 1.0);
+		assert_eq!(names(sequencer.take_events()), ["start"]);
+
+		sequencer.step(&mut scene, 0.25);
+		assert_eq!(sequencer.time, 0.25);
+		assert_eq!(names(sequencer.take_events()), ["end", "start"]);
+
+		// and again the next time round, stepping over the end this time
+		for _ in 0..3 {
+			sequencer.step(&mut scene, 0.3);
+		}
+		assert!((sequencer.time - 0.15).abs() < 1e-5);
+		assert_eq!(names(sequencer.take_events()), ["end", "start"]);
+	}
+
+	#[test]
+	fn sequences_round_trip_through_json() {
+		let mut sequence = Sequence {
+			length: 12.5,
+			..Sequence::default()
+		};
+		let transform = TransformKey {
+			translation: Vec3::new(1.0, 2.0, 3.0),
+			rotation: Quat::from_rotation_y(0.5),
+			scale: Vec3::splat(2.0),
+		};
+		sequence.object_track("crate").insert(0.5, transform);
+		sequence.object_track("crate").insert(4.0, transform);
+		sequence.light.insert(2.0, light(3.0));
+		let pose = CameraPose {
+			position: Position::from_f64(DVec3::new(10.25, -4.5, 1e3)),
+			orientation: Quat::from_rotation_x(0.25),
+		};
+		sequence.camera_cuts.insert(1.0, pose);
+		sequence.add_event(EventMarker {
+			command: "log hello".into(),
+			..event(3.0, "greet")
+		});
+
+		let path = std::env::temp_dir().join(format!("opal-sequence-{}.json", std::process::id()));
+		sequence.save(&path).unwrap();
+		let loaded = Sequence::load(&path).unwrap();
+		fs::remove_file(&path).unwrap();
+
+		assert_eq!(loaded.length, sequence.length);
+		assert_eq!(loaded.objects.len(), 1);
+		assert_eq!(loaded.objects[0].0, "crate");
+		let keys = &loaded.objects[0].1.keys;
+		assert_eq!(keys.len(), 2);
+		assert_eq!(keys[1].time, 4.0);
+		assert!(keys[1]
+			.value
+			.translation
+			.abs_diff_eq(transform.translation, 1e-6));
+		assert!(keys[1].value.rotation.abs_diff_eq(transform.rotation, 1e-6));
+		assert_eq!(loaded.light, sequence.light);
+		let cut = loaded.camera_cuts.keys[0];
+		assert_eq!(cut.time, 1.0);
+		assert_eq!(cut.value.position, pose.position);
+		assert!(cut.value.orientation.abs_diff_eq(pose.orientation, 1e-6));
+		assert_eq!(loaded.events, sequence.events);
+	}
+}
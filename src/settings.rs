@@ -0,0 +1,758 @@
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::time::Duration;
+
+use glam::{Mat3, Vec3};
+use serde_json::json;
+
+use rend3::types::Handedness;
+
+use crate::assets::{ImportSettings, UpAxis};
+use crate::camera::{ClipPlane, ClipPlanes};
+use crate::config::{load_config_entry, save_config_entry, ConfigError};
+use crate::render_passes::RenderPasses;
+
+/// Color vision deficiency used by the color filter pass.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorBlindMode {
+	Off,
+	Protanopia,
+	Deuteranopia,
+	Tritanopia,
+}
+
+impl ColorBlindMode {
+	pub const ALL: [ColorBlindMode; 4] = [
+		ColorBlindMode::Off,
+		ColorBlindMode::Protanopia,
+		ColorBlindMode::Deuteranopia,
+		ColorBlindMode::Tritanopia,
+	];
+
+	pub fn label(&self) -> &'static str {
+		match self {
+			ColorBlindMode::Off => "off",
+			ColorBlindMode::Protanopia => "protanopia",
+			ColorBlindMode::Deuteranopia => "deuteranopia",
+			ColorBlindMode::Tritanopia => "tritanopia",
+		}
+	}
+
+	/// Linear rgb simulation matrix (Machado et al. 2009, full severity).
+	pub fn simulation_matrix(&self) -> Mat3 {
+		// glam matrices are column major so these are written transposed
+		match self {
+			ColorBlindMode::Off => Mat3::IDENTITY,
+			ColorBlindMode::Protanopia => Mat3::from_cols_array(&[
+				0.152286, 0.114503, -0.003882, //
+				1.052583, 0.786281, -0.048116, //
+				-0.204868, 0.099216, 1.051998,
+			]),
+			ColorBlindMode::Deuteranopia => Mat3::from_cols_array(&[
+				0.367322, 0.280085, -0.011820, //
+				0.860646, 0.672501, 0.042940, //
+				-0.227968, 0.047413, 0.968881,
+			]),
+			ColorBlindMode::Tritanopia => Mat3::from_cols_array(&[
+				1.255528, -0.078411, 0.004733, //
+				-0.076749, 0.930809, 0.691367, //
+				-0.178779, 0.147602, 0.303900,
+			]),
+		}
+	}
+}
+
+/// Whether the color filter shows what the deficiency looks like or tries to
+/// compensate for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorFilterKind {
+	Simulate,
+	Daltonize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AccessibilitySettings {
+	pub color_blind_mode: ColorBlindMode,
+	pub color_filter: ColorFilterKind,
+	/// blend between the unfiltered (0.0) and filtered (1.0) frame
+	pub color_filter_strength: f32,
+	/// swap the ui accent colors for a color-blind safe palette
+	pub safe_ui_palette: bool,
+}
+
+impl Default for AccessibilitySettings {
+	fn default() -> Self {
+		Self {
+			color_blind_mode: ColorBlindMode::Off,
+			color_filter: ColorFilterKind::Daltonize,
+			color_filter_strength: 1.0,
+			safe_ui_palette: false,
+		}
+	}
+}
+
+/// Caps the frame rate, on top of whatever the swapchain's present mode
+/// allows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameLimit {
+	Off,
+	/// the refresh rate of the monitor the window is on
+	MatchRefresh,
+	HalfRefresh,
+	/// frames per second
+	Custom(f32),
+}
+
+impl FrameLimit {
+	pub const CUSTOM_RANGE: RangeInclusive<f32> = 10.0..=1000.0;
+
+	pub fn label(&self) -> &'static str {
+		match self {
+			FrameLimit::Off => "off",
+			FrameLimit::MatchRefresh => "match refresh",
+			FrameLimit::HalfRefresh => "half refresh",
+			FrameLimit::Custom(_) => "custom",
+		}
+	}
+
+	/// Time between frames on a monitor refreshing at `refresh_rate` hz.
+	/// Limits based on the refresh rate are off while it's unknown.
+	pub fn interval(&self, refresh_rate: Option<f32>) -> Option<Duration> {
+		let fps = match *self {
+			FrameLimit::Off => return None,
+			FrameLimit::MatchRefresh => refresh_rate?,
+			FrameLimit::HalfRefresh => refresh_rate? / 2.0,
+			FrameLimit::Custom(fps) => fps,
+		};
+		(fps > 0.0).then(|| Duration::from_secs_f32(1.0 / fps))
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GraphicsSettings {
+	pub motion_blur: bool,
+	/// blur from the camera moving
+	pub camera_motion_blur: bool,
+	/// blur from objects moving
+	pub object_motion_blur: bool,
+	/// portion of the frame the shutter is open for, in degrees (360 is the
+	/// whole frame)
+	pub shutter_angle: f32,
+	pub motion_blur_samples: u32,
+	/// frames the cpu can queue up before waiting on the gpu
+	pub frames_in_flight: u32,
+	/// wait for the gpu to finish rendering the previous frame before
+	/// sampling input. Doesn't wait for it to be presented.
+	pub low_latency: bool,
+	pub frame_limit: FrameLimit,
+	/// hz to pace frames to instead of the monitor's, which is only known
+	/// as the most it supports
+	pub refresh_rate: Option<f32>,
+	/// milliseconds a frame may spend on queued main thread work like
+	/// uploads, see [`crate::work_queue::WorkQueue`]
+	pub work_budget: f32,
+	/// how far from the camera the sun casts shadows. The shadow map is the
+	/// same size either way, so shorter distances give sharper shadows
+	pub shadow_distance: f32,
+}
+
+impl Default for GraphicsSettings {
+	fn default() -> Self {
+		Self {
+			motion_blur: false,
+			camera_motion_blur: true,
+			object_motion_blur: true,
+			shutter_angle: 180.0,
+			motion_blur_samples: 8,
+			frames_in_flight: 2,
+			low_latency: false,
+			frame_limit: FrameLimit::Off,
+			refresh_rate: None,
+			work_budget: 2.0,
+			shadow_distance: 400.0,
+		}
+	}
+}
+
+/// Bundles of the graphics settings that cost the most, from cheapest to
+/// best looking. Changing a setting a preset covers afterwards overrides it,
+/// and the settings show as custom. The renderer has no msaa, ssao, bloom or
+/// render scale yet, so presets cover motion blur and shadows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphicsPreset {
+	Low,
+	/// the defaults
+	Medium,
+	High,
+}
+
+impl GraphicsPreset {
+	pub const ALL: [Self; 3] = [Self::Low, Self::Medium, Self::High];
+
+	pub fn label(self) -> &'static str {
+		match self {
+			Self::Low => "low",
+			Self::Medium => "medium",
+			Self::High => "high",
+		}
+	}
+
+	/// Sets what the preset covers, leaving the other settings alone.
+	pub fn apply(self, graphics: &mut GraphicsSettings) {
+		let (motion_blur, samples, shadow_distance) = match self {
+			Self::Low => (false, 4, 150.0),
+			Self::Medium => (false, 8, 400.0),
+			Self::High => (true, 16, 800.0),
+		};
+		graphics.motion_blur = motion_blur;
+		graphics.motion_blur_samples = samples;
+		graphics.shadow_distance = shadow_distance;
+	}
+
+	/// The preset the settings are at, `None` if one it covers was changed.
+	pub fn matching(graphics: &GraphicsSettings) -> Option<Self> {
+		Self::ALL.into_iter().find(|preset| {
+			let mut applied = *graphics;
+			preset.apply(&mut applied);
+			applied == *graphics
+		})
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraControls {
+	/// base fly speed in world units per second, adjusted with the mouse wheel
+	pub fly_speed: f32,
+	/// speed multiplier while shift is held
+	pub sprint_multiplier: f32,
+	/// speed multiplier while ctrl is held
+	pub slow_multiplier: f32,
+	/// radians per mouse count, or per point the cursor moves without
+	/// `raw_mouse`
+	pub look_sensitivity: f32,
+	/// look with the mouse's own movement instead of the cursor's, bypassing
+	/// the os pointer speed and acceleration
+	pub raw_mouse: bool,
+	/// radians per second
+	pub roll_speed: f32,
+	/// clamp the pitch like an fps camera instead of turning freely with roll
+	pub clamp_pitch: bool,
+	/// in degrees from the horizon
+	pub pitch_limit: f32,
+	/// roll about the view axis while the pitch is clamped, instead of
+	/// keeping the horizon level
+	pub clamped_roll: bool,
+	/// ease movement in and out instead of starting and stopping instantly
+	pub inertia: bool,
+	/// how quickly the camera reaches full speed, per second
+	pub acceleration: f32,
+	/// how quickly the camera stops without input, per second
+	pub damping: f32,
+	/// time constant of the mouse look smoothing in seconds, 0 for none
+	pub look_smoothing: f32,
+}
+
+impl CameraControls {
+	pub const FLY_SPEED_RANGE: RangeInclusive<f32> = 0.01..=1000.0;
+
+	/// Scales the fly speed by `steps` mouse wheel lines.
+	pub fn scroll_fly_speed(&mut self, steps: f32) {
+		let speed = self.fly_speed * 1.2f32.powf(steps);
+		self.fly_speed = speed.clamp(*Self::FLY_SPEED_RANGE.start(), *Self::FLY_SPEED_RANGE.end());
+	}
+
+	/// Limit passed to [`crate::camera::CameraPose::rotate`].
+	pub fn pitch_limit(&self) -> Option<f32> {
+		self.clamp_pitch.then_some(self.pitch_limit.to_radians())
+	}
+
+	/// Whether the roll actions roll the camera.
+	pub fn can_roll(&self) -> bool {
+		!self.clamp_pitch || self.clamped_roll
+	}
+
+	/// Acceleration and damping passed to
+	/// [`crate::camera::CameraMotion::accelerate`].
+	pub fn easing(&self) -> (f32, f32) {
+		match self.inertia {
+			true => (self.acceleration, self.damping),
+			false => (0.0, 0.0),
+		}
+	}
+}
+
+impl Default for CameraControls {
+	fn default() -> Self {
+		Self {
+			fly_speed: 10.0,
+			sprint_multiplier: 4.0,
+			slow_multiplier: 0.25,
+			look_sensitivity: 0.003,
+			raw_mouse: true,
+			roll_speed: 1.5,
+			clamp_pitch: true,
+			pitch_limit: 89.0,
+			clamped_roll: false,
+			inertia: true,
+			acceleration: 8.0,
+			damping: 6.0,
+			look_smoothing: 0.03,
+		}
+	}
+}
+
+/// Editor overlays drawn over the viewport.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InterfaceSettings {
+	/// describe the object under the pointer in a tooltip
+	pub hover_tooltips: bool,
+	/// play without editor overlays, hiding tooltips unless
+	/// `tooltips_in_game_mode` is set
+	pub game_mode: bool,
+	pub tooltips_in_game_mode: bool,
+}
+
+impl InterfaceSettings {
+	pub fn show_hover_tooltips(&self) -> bool {
+		self.hover_tooltips && (!self.game_mode || self.tooltips_in_game_mode)
+	}
+}
+
+impl Default for InterfaceSettings {
+	fn default() -> Self {
+		Self {
+			hover_tooltips: true,
+			game_mode: false,
+			tooltips_in_game_mode: false,
+		}
+	}
+}
+
+/// How the scene keeps its precision far from the world origin.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WorldSettings {
+	/// move the render space origin along with the camera
+	pub floating_origin: bool,
+	/// how far the camera gets from the render space origin before it moves
+	pub rebase_distance: f32,
+}
+
+impl Default for WorldSettings {
+	fn default() -> Self {
+		Self {
+			floating_origin: true,
+			rebase_distance: 1024.0,
+		}
+	}
+}
+
+/// Field of view and clip planes the cameras are made with. Saved to the
+/// config, all but the clip plane.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraSettings {
+	/// vertical, in degrees
+	pub vfov: f32,
+	pub clipping: ClipPlanes,
+}
+
+impl Default for CameraSettings {
+	fn default() -> Self {
+		Self {
+			vfov: 60.0,
+			clipping: ClipPlanes::default(),
+		}
+	}
+}
+
+impl CameraSettings {
+	const CONFIG_KEY: &'static str = "camera";
+	pub const VFOV_RANGE: RangeInclusive<f32> = 10.0..=120.0;
+
+	/// Reads the settings from a config file, the defaults for any missing.
+	pub fn load(path: &Path) -> Self {
+		let mut settings = Self::default();
+		let config = match load_config_entry(path, Self::CONFIG_KEY) {
+			Some(config) => config,
+			None => return settings,
+		};
+		let number = |key| Some(config.get(key)?.as_f64()? as f32);
+		if let Some(vfov) = number("vfov") {
+			settings.vfov = vfov.clamp(*Self::VFOV_RANGE.start(), *Self::VFOV_RANGE.end());
+		}
+		if let Some(near) = number("near") {
+			settings.clipping.near = near.max(0.001);
+		}
+		// null for the infinite far plane
+		settings.clipping.far = number("far").map(|far| far.max(settings.clipping.near * 2.0));
+		settings
+	}
+
+	pub fn save(&self, path: &Path) -> Result<(), ConfigError> {
+		let config = json!({
+			"vfov": self.vfov,
+			"near": self.clipping.near,
+			"far": self.clipping.far,
+		});
+		save_config_entry(path, Self::CONFIG_KEY, config)
+	}
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Settings {
+	pub graphics: GraphicsSettings,
+	pub camera: CameraControls,
+	pub world: WorldSettings,
+	pub interface: InterfaceSettings,
+	pub view: CameraSettings,
+	pub import: ImportSettings,
+	pub accessibility: AccessibilitySettings,
+}
+
+fn frame_limit_ui(ui: &mut egui::Ui, frame_limit: &mut FrameLimit) {
+	// keeps the custom rate when switching to it from a refresh based limit
+	let custom = match *frame_limit {
+		FrameLimit::Custom(fps) => fps,
+		_ => 60.0,
+	};
+	egui::ComboBox::from_label("frame limit")
+		.selected_text(frame_limit.label())
+		.show_ui(ui, |ui| {
+			for limit in [
+				FrameLimit::Off,
+				FrameLimit::MatchRefresh,
+				FrameLimit::HalfRefresh,
+				FrameLimit::Custom(custom),
+			] {
+				ui.selectable_value(frame_limit, limit, limit.label());
+			}
+		});
+	if let FrameLimit::Custom(fps) = frame_limit {
+		ui.add(
+			egui::DragValue::new(fps)
+				.clamp_range(FrameLimit::CUSTOM_RANGE)
+				.suffix(" fps"),
+		);
+	}
+}
+
+/// Overrides the detected refresh rate when the monitor runs slower than
+/// its fastest mode.
+fn refresh_rate_ui(ui: &mut egui::Ui, refresh_rate: &mut Option<f32>) {
+	ui.horizontal(|ui| {
+		let mut set = refresh_rate.is_some();
+		ui.checkbox(&mut set, "refresh rate")
+			.on_hover_text("pace frames to this instead of the fastest the monitor supports");
+		match (set, refresh_rate.as_mut()) {
+			(true, Some(rate)) => {
+				ui.add(
+					egui::DragValue::new(rate)
+						.clamp_range(FrameLimit::CUSTOM_RANGE)
+						.suffix(" hz"),
+				);
+			}
+			(true, None) => *refresh_rate = Some(60.0),
+			(false, _) => *refresh_rate = None,
+		}
+	});
+}
+
+/// Shows the settings window and returns true if anything was changed.
+/// Render passes turned on or off don't count, they aren't saved.
+pub fn settings_window(
+	ctx: &egui::CtxRef,
+	settings: &mut Settings,
+	passes: &mut RenderPasses,
+) -> bool {
+	let before = *settings;
+
+	egui::Window::new("settings")
+		.resizable(true)
+		.show(ctx, |ui| {
+			egui::CollapsingHeader::new("graphics")
+				.default_open(true)
+				.show(ui, |ui| {
+					let graphics = &mut settings.graphics;
+					let preset = GraphicsPreset::matching(graphics);
+					egui::ComboBox::from_label("preset")
+						.selected_text(preset.map_or("custom", GraphicsPreset::label))
+						.show_ui(ui, |ui| {
+							for option in GraphicsPreset::ALL {
+								if ui
+									.selectable_label(preset == Some(option), option.label())
+									.clicked()
+								{
+									option.apply(graphics);
+								}
+							}
+						});
+					ui.checkbox(&mut graphics.motion_blur, "motion blur");
+					ui.add_enabled_ui(graphics.motion_blur, |ui| {
+						ui.horizontal(|ui| {
+							ui.checkbox(&mut graphics.camera_motion_blur, "camera");
+							ui.checkbox(&mut graphics.object_motion_blur, "objects");
+						});
+						ui.add(
+							egui::Slider::new(&mut graphics.shutter_angle, 0.0..=360.0)
+								.text("shutter angle"),
+						);
+						ui.add(
+							egui::Slider::new(&mut graphics.motion_blur_samples, 1..=32)
+								.text("samples"),
+						);
+					});
+					ui.add_enabled_ui(!graphics.low_latency, |ui| {
+						ui.add(
+							egui::Slider::new(&mut graphics.frames_in_flight, 1..=3)
+								.text("frames in flight"),
+						);
+					});
+					ui.checkbox(&mut graphics.low_latency, "low latency (wait for gpu)")
+						.on_hover_text(
+							"wait for the gpu to finish rendering each frame before starting the next. \
+							 Doesn't wait for it to be presented",
+						);
+					ui.add(
+						egui::Slider::new(&mut graphics.shadow_distance, 50.0..=2000.0)
+							.logarithmic(true)
+							.text("shadow distance"),
+					);
+					frame_limit_ui(ui, &mut graphics.frame_limit);
+					refresh_rate_ui(ui, &mut graphics.refresh_rate);
+					ui.add(
+						egui::Slider::new(&mut graphics.work_budget, 0.5..=16.0)
+							.logarithmic(true)
+							.suffix("ms")
+							.text("upload budget"),
+					)
+					.on_hover_text("time a frame may spend on uploads and other queued work");
+					egui::CollapsingHeader::new("render passes")
+						.default_open(false)
+						.show(ui, |ui| passes.ui(ui));
+				});
+			egui::CollapsingHeader::new("camera controls")
+				.default_open(false)
+				.show(ui, |ui| camera_controls_ui(ui, &mut settings.camera));
+			egui::CollapsingHeader::new("world")
+				.default_open(false)
+				.show(ui, |ui| {
+					let world = &mut settings.world;
+					ui.checkbox(&mut world.floating_origin, "floating origin");
+					ui.add_enabled_ui(world.floating_origin, |ui| {
+						ui.add(
+							egui::Slider::new(&mut world.rebase_distance, 16.0..=100_000.0)
+								.logarithmic(true)
+								.text("rebase distance"),
+						);
+					});
+				});
+			egui::CollapsingHeader::new("interface")
+				.default_open(false)
+				.show(ui, |ui| {
+					let interface = &mut settings.interface;
+					ui.checkbox(&mut interface.hover_tooltips, "hover tooltips");
+					ui.checkbox(&mut interface.game_mode, "game mode");
+					ui.add_enabled_ui(interface.hover_tooltips, |ui| {
+						ui.checkbox(
+							&mut interface.tooltips_in_game_mode,
+							"tooltips in game mode",
+						);
+					});
+				});
+			egui::CollapsingHeader::new("view")
+				.default_open(false)
+				.show(ui, |ui| {
+					ui.add(
+						egui::Slider::new(&mut settings.view.vfov, CameraSettings::VFOV_RANGE)
+							.suffix("°")
+							.text("vertical fov"),
+					);
+					clipping_ui(ui, &mut settings.view.clipping);
+				});
+			egui::CollapsingHeader::new("import")
+				.default_open(false)
+				.show(ui, |ui| import_ui(ui, &mut settings.import));
+			egui::CollapsingHeader::new("accessibility")
+				.default_open(true)
+				.show(ui, |ui| {
+					let accessibility = &mut settings.accessibility;
+					egui::ComboBox::from_label("color blindness")
+						.selected_text(accessibility.color_blind_mode.label())
+						.show_ui(ui, |ui| {
+							for mode in ColorBlindMode::ALL {
+								ui.selectable_value(
+									&mut accessibility.color_blind_mode,
+									mode,
+									mode.label(),
+								);
+							}
+						});
+					ui.horizontal(|ui| {
+						ui.radio_value(
+							&mut accessibility.color_filter,
+							ColorFilterKind::Daltonize,
+							"correct",
+						);
+						ui.radio_value(
+							&mut accessibility.color_filter,
+							ColorFilterKind::Simulate,
+							"simulate",
+						);
+					});
+					ui.add(
+						egui::Slider::new(&mut accessibility.color_filter_strength, 0.0..=1.0)
+							.text("strength"),
+					);
+					ui.checkbox(&mut accessibility.safe_ui_palette, "color-blind safe ui");
+				});
+		});
+
+	*settings != before
+}
+
+fn camera_controls_ui(ui: &mut egui::Ui, camera: &mut CameraControls) {
+	ui.label("hold the right mouse button to look around");
+	ui.add(
+		egui::Slider::new(&mut camera.fly_speed, CameraControls::FLY_SPEED_RANGE)
+			.logarithmic(true)
+			.text("fly speed (scroll)"),
+	);
+	ui.add(egui::Slider::new(&mut camera.sprint_multiplier, 1.0..=20.0).text("shift multiplier"));
+	ui.add(egui::Slider::new(&mut camera.slow_multiplier, 0.01..=1.0).text("ctrl multiplier"));
+	ui.add(
+		egui::Slider::new(&mut camera.look_sensitivity, 0.0005..=0.01)
+			.logarithmic(true)
+			.text("look sensitivity"),
+	);
+	ui.checkbox(&mut camera.raw_mouse, "raw mouse input")
+		.on_hover_text(
+			"look with the mouse's own movement instead of the cursor's, without the os \
+		 pointer speed and acceleration",
+		);
+	ui.checkbox(&mut camera.clamp_pitch, "fps style (clamped pitch)");
+	ui.add_enabled_ui(camera.clamp_pitch, |ui| {
+		ui.add(egui::Slider::new(&mut camera.pitch_limit, 0.0..=89.9).text("pitch limit"));
+		ui.checkbox(&mut camera.clamped_roll, "roll");
+	});
+	ui.add_enabled_ui(camera.can_roll(), |ui| {
+		ui.add(egui::Slider::new(&mut camera.roll_speed, 0.1..=5.0).text("roll speed (z / x)"));
+	});
+	ui.add(
+		egui::Slider::new(&mut camera.look_smoothing, 0.0..=0.3)
+			.text("look smoothing")
+			.suffix(" s"),
+	)
+	.on_hover_text("for orbiting too");
+
+	ui.checkbox(&mut camera.inertia, "inertia");
+	ui.add_enabled_ui(camera.inertia, |ui| {
+		ui.add(
+			egui::Slider::new(&mut camera.acceleration, 0.5..=50.0)
+				.logarithmic(true)
+				.text("acceleration"),
+		);
+		ui.add(
+			egui::Slider::new(&mut camera.damping, 0.5..=50.0)
+				.logarithmic(true)
+				.text("damping"),
+		)
+		.on_hover_text("also eases rolling and the orbit camera's pan and zoom");
+	});
+}
+
+fn clipping_ui(ui: &mut egui::Ui, clipping: &mut ClipPlanes) {
+	ui.add(
+		egui::Slider::new(&mut clipping.near, 0.01..=10.0)
+			.logarithmic(true)
+			.text("near"),
+	);
+
+	let mut finite_far = clipping.far.is_some();
+	ui.checkbox(&mut finite_far, "far plane");
+	clipping.far = match finite_far {
+		true => {
+			let far = clipping.far.get_or_insert(1000.0);
+			ui.add(
+				egui::Slider::new(far, clipping.near * 2.0..=ClipPlanes::OBLIQUE_FAR)
+					.logarithmic(true)
+					.text("far"),
+			);
+			Some(far.max(clipping.near * 2.0))
+		}
+		false => None,
+	};
+
+	let mut cutaway = clipping.clip_plane.is_some();
+	ui.checkbox(&mut cutaway, "clip plane");
+	clipping.clip_plane = match cutaway {
+		true => {
+			let plane = clipping.clip_plane.get_or_insert(ClipPlane {
+				normal: Vec3::new(0.0, 0.0, -1.0),
+				distance: 0.0,
+			});
+			ui.horizontal(|ui| {
+				ui.label("normal");
+				ui.add(egui::DragValue::new(&mut plane.normal.x).speed(0.01));
+				ui.add(egui::DragValue::new(&mut plane.normal.y).speed(0.01));
+				ui.add(egui::DragValue::new(&mut plane.normal.z).speed(0.01));
+			});
+			ui.add(
+				egui::DragValue::new(&mut plane.distance)
+					.speed(0.01)
+					.prefix("distance "),
+			);
+			ui.label("cuts away the side the normal points to, only while the camera is on it");
+			Some(*plane)
+		}
+		false => None,
+	};
+}
+
+fn import_ui(ui: &mut egui::Ui, import: &mut ImportSettings) {
+	let unit_label = ImportSettings::UNITS
+		.iter()
+		.find(|(_, scale)| *scale == import.unit_scale)
+		.map_or("custom", |(label, _)| label);
+	egui::ComboBox::from_label("units")
+		.selected_text(unit_label)
+		.show_ui(ui, |ui| {
+			for (label, scale) in ImportSettings::UNITS {
+				ui.selectable_value(&mut import.unit_scale, scale, label);
+			}
+		});
+	ui.add(
+		egui::DragValue::new(&mut import.unit_scale)
+			.speed(0.001)
+			.clamp_range(0.0001..=1000.0)
+			.suffix(" m per unit"),
+	);
+	ui.horizontal(|ui| {
+		ui.label("up");
+		ui.radio_value(&mut import.up_axis, UpAxis::Y, "y");
+		ui.radio_value(&mut import.up_axis, UpAxis::Z, "z");
+	});
+	ui.horizontal(|ui| {
+		ui.label("handedness");
+		ui.radio_value(&mut import.handedness, Handedness::Left, "left");
+		ui.radio_value(&mut import.handedness, Handedness::Right, "right");
+	});
+}
+
+/// Applies the accessibility palette on top of the default egui visuals.
+pub fn apply_ui_palette(ctx: &egui::CtxRef, accessibility: &AccessibilitySettings) {
+	let mut visuals = egui::Visuals::dark();
+
+	if accessibility.safe_ui_palette {
+		// Okabe-Ito palette, distinguishable under all common deficiencies
+		let blue = egui::Color32::from_rgb(0, 114, 178);
+		let orange = egui::Color32::from_rgb(230, 159, 0);
+		let sky = egui::Color32::from_rgb(86, 180, 233);
+
+		visuals.selection.bg_fill = blue;
+		visuals.selection.stroke.color = orange;
+		visuals.hyperlink_color = sky;
+		visuals.widgets.hovered.bg_stroke.color = orange;
+		visuals.widgets.active.bg_fill = blue;
+	}
+
+	ctx.set_visuals(visuals);
+}
@@ -0,0 +1,398 @@
+use std::collections::BTreeMap;
+
+use glam::{Mat4, Vec3};
+
+/// A joint of an animated object's skeleton, named and linked to its parent
+/// with [`crate::scene::Scene::set_skeleton`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Joint {
+	pub name: String,
+	/// index of the joint it hangs off, `None` for a root
+	pub parent: Option<usize>,
+}
+
+/// Maps poses of one skeleton onto another, so an animation authored for
+/// one character plays on others. Joints are matched by name through a
+/// table, falling back to joints with the same name. Each matched joint gets
+/// the source's rotation away from its rest pose, applied to the target's
+/// rest pose, so the target keeps its own proportions. The roots also move
+/// as the source's do, scaled by how much taller or shorter the target's
+/// rest pose is. Unmatched joints stay at rest.
+///
+/// Poses are object space joint transforms, as taken by
+/// [`crate::scene::Scene::set_joint_transforms`].
+/// [`crate::animation::ClipPlayer::retargeted`] retargets a clip a frame at
+/// a time as it plays.
+pub struct Retarget {
+	/// source joint for each target joint
+	sources: Vec<Option<usize>>,
+	source_parents: Vec<Option<usize>>,
+	target_parents: Vec<Option<usize>>,
+	/// rest poses relative to each joint's parent
+	source_rest: Vec<Mat4>,
+	target_rest: Vec<Mat4>,
+	/// target root height over the source's
+	scale: f32,
+}
+
+impl Retarget {
+	/// `names` maps target joint names to source joint names. `source_rest`
+	/// and `target_rest` are each skeleton's rest pose in object space.
+	pub fn new(
+		source: &[Joint],
+		source_rest: &[Mat4],
+		target: &[Joint],
+		target_rest: &[Mat4],
+		names: &BTreeMap<String, String>,
+	) -> Self {
+		let sources = target
+			.iter()
+			.map(|joint| {
+				let name = names.get(&joint.name).unwrap_or(&joint.name);
+				source.iter().position(|source| source.name == *name)
+			})
+			.collect();
+		let parents = |joints: &[Joint]| joints.iter().map(|joint| joint.parent).collect();
+		let source_parents: Vec<_> = parents(source);
+		let target_parents: Vec<_> = parents(target);
+		let height = |parents: &[Option<usize>], rest: &[Mat4]| {
+			parents
+				.iter()
+				.zip(rest)
+				.find(|(parent, _)| parent.is_none())
+				.map_or(0.0, |(_, rest)| rest.w_axis.y)
+		};
+		let source_height = height(&source_parents, source_rest);
+		let scale = match source_height.abs() > f32::EPSILON {
+			true => height(&target_parents, target_rest) / source_height,
+			false => 1.0,
+		};
+		Self {
+			sources,
+			source_rest: to_local(&source_parents, source_rest),
+			target_rest: to_local(&target_parents, target_rest),
+			source_parents,
+			target_parents,
+			scale,
+		}
+	}
+
+	/// Target joints with no source joint to follow.
+	pub fn unmatched(&self) -> usize {
+		self.sources
+			.iter()
+			.filter(|source| source.is_none())
+			.count()
+	}
+
+	/// The target's pose for a pose of the source skeleton.
+	pub fn pose(&self, source_pose: &[Mat4]) -> Vec<Mat4> {
+		let source_local = to_local(&self.source_parents, source_pose);
+		let local: Vec<Mat4> = self
+			.sources
+			.iter()
+			.zip(&self.target_rest)
+			.zip(&self.target_parents)
+			.map(|((source, rest), parent)| {
+				let found = source.and_then(|source| {
+					Some((source_local.get(source)?, self.source_rest.get(source)?))
+				});
+				let (posed, source_rest) = match found {
+					Some(found) => found,
+					None => return *rest,
+				};
+				let (_, posed_rotation, posed_translation) = posed.to_scale_rotation_translation();
+				let (_, source_rotation, source_translation) =
+					source_rest.to_scale_rotation_translation();
+				let (scale, rotation, translation) = rest.to_scale_rotation_translation();
+				let turn = source_rotation.inverse() * posed_rotation;
+				let translation = match parent {
+					Some(_) => translation,
+					None => translation + (posed_translation - source_translation) * self.scale,
+				};
+				Mat4::from_scale_rotation_translation(scale, rotation * turn, translation)
+			})
+			.collect();
+		to_object(&self.target_parents, &local)
+	}
+}
+
+/// Takes the root joint's travel out of an animation, so the object moves
+/// by it instead and the clip plays in place around the object's origin.
+/// Travel is along the ground, and optionally the turn about the up axis,
+/// the root keeps its height and lean. Each [`crate::animation::ClipPlayer`]
+/// keeps one, turned off for clips that should play where they were
+/// authored.
+///
+/// Poses are extracted a frame at a time, in order, and the motion between
+/// them is applied with [`crate::scene::Scene::apply_root_motion`]. When a
+/// clip loops or jumps, [`RootMotion::reset`] keeps the jump from moving the
+/// object.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RootMotion {
+	pub enabled: bool,
+	/// takes the turn about the up axis as well as the travel
+	pub rotation: bool,
+	/// what was taken out of the last pose, in object space
+	previous: Option<Mat4>,
+}
+
+impl Default for RootMotion {
+	fn default() -> Self {
+		Self {
+			enabled: true,
+			rotation: true,
+			previous: None,
+		}
+	}
+}
+
+impl RootMotion {
+	/// Forgets the last pose, so the next one doesn't move the object.
+	pub fn reset(&mut self) {
+		self.previous = None;
+	}
+
+	/// Takes the root's motion out of `pose`, object space joint transforms
+	/// of the skeleton `joints`, moving every joint back with it. Returns how
+	/// far the root went since the last pose, in the object's space, the
+	/// identity while it's turned off or for the first pose.
+	pub fn extract(&mut self, joints: &[Joint], pose: &mut [Mat4]) -> Mat4 {
+		let root = joints
+			.iter()
+			.position(|joint| joint.parent.is_none())
+			.and_then(|root| pose.get(root));
+		let root = match (self.enabled, root) {
+			(true, Some(root)) => *root,
+			_ => {
+				self.previous = None;
+				return Mat4::IDENTITY;
+			}
+		};
+
+		let travel = Vec3::new(root.w_axis.x, 0.0, root.w_axis.z);
+		let facing = root.z_axis.truncate();
+		let yaw = match self.rotation && (facing.x != 0.0 || facing.z != 0.0) {
+			true => facing.x.atan2(facing.z),
+			false => 0.0,
+		};
+		let taken = Mat4::from_translation(travel) * Mat4::from_rotation_y(yaw);
+		let back = taken.inverse();
+		for joint in pose.iter_mut() {
+			*joint = back * *joint;
+		}
+
+		let moved = match self.previous {
+			Some(previous) => previous.inverse() * taken,
+			None => Mat4::IDENTITY,
+		};
+		self.previous = Some(taken);
+		moved
+	}
+}
+
+/// Object space joint transforms relative to each joint's parent instead.
+pub fn to_local(parents: &[Option<usize>], pose: &[Mat4]) -> Vec<Mat4> {
+	pose.iter()
+		.zip(parents)
+		.map(
+			|(joint, parent)| match parent.and_then(|parent| pose.get(parent)) {
+				Some(parent) => parent.inverse() * *joint,
+				None => *joint,
+			},
+		)
+		.collect()
+}
+
+/// The inverse of [`to_local`]. Parents may come after their children.
+pub fn to_object(parents: &[Option<usize>], local: &[Mat4]) -> Vec<Mat4> {
+	fn resolve(
+		joint: usize,
+		parents: &[Option<usize>],
+		local: &[Mat4],
+		pose: &mut [Option<Mat4>],
+		depth: usize,
+	) -> Mat4 {
+		if let Some(resolved) = pose[joint] {
+			return resolved;
+		}
+		// a cycle in a broken skeleton ends at the joint itself
+		let transform = match parents[joint].filter(|parent| *parent < local.len()) {
+			Some(parent) if depth < local.len() => {
+				resolve(parent, parents, local, pose, depth + 1) * local[joint]
+			}
+			_ => local[joint],
+		};
+		pose[joint] = Some(transform);
+		transform
+	}
+
+	let mut pose = vec![None; local.len()];
+	(0..local.len())
+		.map(|joint| resolve(joint, parents, local, &mut pose, 0))
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use std::f32::consts::FRAC_PI_2;
+
+	use glam::Quat;
+
+	use super::*;
+
+	fn joint(name: &str, parent: Option<usize>) -> Joint {
+		Joint {
+			name: name.into(),
+			parent,
+		}
+	}
+
+	fn parents(joints: &[Joint]) -> Vec<Option<usize>> {
+		joints.iter().map(|joint| joint.parent).collect()
+	}
+
+	fn assert_close(a: Vec3, b: Vec3) {
+		assert!(a.abs_diff_eq(b, 1e-4), "{} != {}", a, b);
+	}
+
+	fn position(transform: Mat4) -> Vec3 {
+		transform.w_axis.truncate()
+	}
+
+	/// A leg with its hip a unit up, and one twice the size with its root
+	/// named differently and a tail the source doesn't have.
+	fn legs() -> (Vec<Joint>, Vec<Mat4>, Vec<Joint>, Vec<Mat4>, Retarget) {
+		let source = vec![
+			joint("hips", None),
+			joint("knee", Some(0)),
+			joint("foot", Some(1)),
+		];
+		let source_rest = to_object(
+			&parents(&source),
+			&[
+				Mat4::from_translation(Vec3::Y),
+				Mat4::from_translation(Vec3::new(0.0, -0.5, 0.0)),
+				Mat4::from_translation(Vec3::new(0.0, -0.5, 0.0)),
+			],
+		);
+		let target = vec![
+			joint("pelvis", None),
+			joint("knee", Some(0)),
+			joint("foot", Some(1)),
+			joint("tail", Some(0)),
+		];
+		let target_rest = to_object(
+			&parents(&target),
+			&[
+				Mat4::from_translation(Vec3::Y * 2.0),
+				Mat4::from_translation(Vec3::new(0.0, -1.0, 0.0)),
+				Mat4::from_translation(Vec3::new(0.0, -1.0, 0.0)),
+				Mat4::from_translation(Vec3::new(0.0, 0.0, -0.5)),
+			],
+		);
+		let names = BTreeMap::from([("pelvis".to_string(), "hips".to_string())]);
+		let retarget = Retarget::new(&source, &source_rest, &target, &target_rest, &names);
+		(source, source_rest, target, target_rest, retarget)
+	}
+
+	#[test]
+	fn rest_pose_maps_to_the_target_rest_pose() {
+		let (_, source_rest, _, target_rest, retarget) = legs();
+		for (posed, rest) in retarget.pose(&source_rest).iter().zip(&target_rest) {
+			assert!(posed.abs_diff_eq(*rest, 1e-5), "{} != {}", posed, rest);
+		}
+		assert_eq!(retarget.unmatched(), 1);
+	}
+
+	#[test]
+	fn turns_follow_while_bones_keep_their_length() {
+		let (source, source_rest, _, _, retarget) = legs();
+		let mut local = to_local(&parents(&source), &source_rest);
+		// kick the knee forward a quarter turn
+		local[1] *= Mat4::from_quat(Quat::from_rotation_x(-FRAC_PI_2));
+		let source_pose = to_object(&parents(&source), &local);
+		let source_shin = position(source_pose[2]) - position(source_pose[1]);
+		assert_close(source_shin, Vec3::new(0.0, 0.0, 0.5));
+
+		let pose = retarget.pose(&source_pose);
+		assert_close(position(pose[0]), Vec3::Y * 2.0);
+		assert_close(position(pose[1]), Vec3::Y);
+		assert_close(
+			position(pose[2]) - position(pose[1]),
+			Vec3::new(0.0, 0.0, 1.0),
+		);
+		// the unmatched tail stays at rest on the pelvis
+		assert_close(position(pose[3]), Vec3::new(0.0, 2.0, -0.5));
+	}
+
+	#[test]
+	fn root_travel_scales_with_height() {
+		let (source, source_rest, _, _, retarget) = legs();
+		let mut local = to_local(&parents(&source), &source_rest);
+		local[0] = Mat4::from_translation(Vec3::new(0.5, 1.1, 1.0))
+			* Mat4::from_quat(Quat::from_rotation_y(FRAC_PI_2));
+		let source_pose = to_object(&parents(&source), &local);
+
+		// twice as tall, so it goes twice as far and bobs twice as high
+		let pose = retarget.pose(&source_pose);
+		assert_close(position(pose[0]), Vec3::new(1.0, 2.2, 2.0));
+		let (_, turned, _) = pose[0].to_scale_rotation_translation();
+		assert!(turned.abs_diff_eq(Quat::from_rotation_y(FRAC_PI_2), 1e-5));
+		// the tail turns with the pelvis
+		assert_close(
+			position(pose[3]) - position(pose[0]),
+			Quat::from_rotation_y(FRAC_PI_2) * Vec3::new(0.0, 0.0, -0.5),
+		);
+	}
+
+	#[test]
+	fn extract_takes_the_travel_out_of_the_root() {
+		let joints = [joint("hips", None), joint("foot", Some(0))];
+		let pose_at = |z: f32, yaw: f32| {
+			vec![
+				Mat4::from_translation(Vec3::new(0.2, 1.0, z)) * Mat4::from_rotation_y(yaw),
+				Mat4::from_translation(Vec3::new(0.2, 0.0, z)),
+			]
+		};
+		let mut root_motion = RootMotion::default();
+
+		// the first pose only says where the root starts
+		let mut pose = pose_at(0.5, 0.0);
+		assert_eq!(root_motion.extract(&joints, &mut pose), Mat4::IDENTITY);
+		// the root keeps its height over the object's origin
+		assert_close(position(pose[0]), Vec3::Y);
+		assert_close(position(pose[1]), Vec3::ZERO);
+
+		let mut pose = pose_at(1.5, FRAC_PI_2);
+		let motion = root_motion.extract(&joints, &mut pose);
+		assert!(motion.abs_diff_eq(
+			Mat4::from_translation(Vec3::Z) * Mat4::from_rotation_y(FRAC_PI_2),
+			1e-5
+		));
+		// turned back to face the way the object does
+		assert!(pose[0].abs_diff_eq(Mat4::from_translation(Vec3::Y), 1e-5));
+		assert_close(position(pose[1]), Vec3::ZERO);
+	}
+
+	#[test]
+	fn extract_can_leave_the_turn_or_everything() {
+		let joints = [joint("hips", None)];
+		let turned = Mat4::from_translation(Vec3::Z) * Mat4::from_rotation_y(FRAC_PI_2);
+
+		let mut root_motion = RootMotion {
+			rotation: false,
+			..RootMotion::default()
+		};
+		root_motion.extract(&joints, &mut [Mat4::IDENTITY]);
+		let mut pose = [turned];
+		let motion = root_motion.extract(&joints, &mut pose);
+		assert!(motion.abs_diff_eq(Mat4::from_translation(Vec3::Z), 1e-5));
+		assert!(pose[0].abs_diff_eq(Mat4::from_rotation_y(FRAC_PI_2), 1e-5));
+
+		root_motion.enabled = false;
+		let mut pose = [turned];
+		assert_eq!(root_motion.extract(&joints, &mut pose), Mat4::IDENTITY);
+		assert_eq!(pose, [turned]);
+	}
+}
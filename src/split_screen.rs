@@ -0,0 +1,248 @@
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use glam::UVec2;
+use rend3::graph::{RenderGraph, RenderPassTarget, RenderPassTargets, RenderTargetHandle};
+use rend3::util::bind_merge::{BindGroupBuilder, BindGroupLayoutBuilder};
+use rend3::Renderer;
+use wgpu::{
+	BindGroupLayout, BindingType, Color, ColorTargetState, ColorWrites, Extent3d, FragmentState,
+	MultisampleState, PipelineLayoutDescriptor, PrimitiveState, RenderPipeline,
+	RenderPipelineDescriptor, ShaderModuleDescriptor, ShaderSource, ShaderStages,
+	TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType, TextureUsages,
+	TextureView, TextureViewDescriptor, TextureViewDimension, VertexState,
+};
+
+use crate::camera::FlyCamera;
+
+/// Which half of the screen a camera is shown in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Viewport {
+	Left,
+	Right,
+}
+
+impl Viewport {
+	/// Pixel rect `(offset, size)` of this viewport on a surface of the given
+	/// resolution.
+	pub fn rect(&self, resolution: UVec2) -> (UVec2, UVec2) {
+		let left_width = resolution.x / 2;
+		match self {
+			Viewport::Left => (UVec2::ZERO, UVec2::new(left_width, resolution.y)),
+			Viewport::Right => (
+				UVec2::new(left_width, 0),
+				UVec2::new(resolution.x - left_width, resolution.y),
+			),
+		}
+	}
+}
+
+/// Renders a second camera next to the main one.
+///
+/// rend3 only has a single active camera, so each viewport gets its own
+/// rendergraph. The left viewport is rendered first into a texture owned by
+/// this routine, then the right viewport's graph composites both halves onto
+/// the surface using the render pass viewport.
+pub struct SplitScreen {
+	pub enabled: bool,
+	/// camera shown in the right viewport
+	pub camera: FlyCamera,
+	/// viewport that receives keyboard and mouse input
+	pub input_viewport: Viewport,
+
+	bgl: BindGroupLayout,
+	pipeline: RenderPipeline,
+	format: TextureFormat,
+	left: Option<(UVec2, Arc<TextureView>)>,
+}
+
+impl SplitScreen {
+	pub fn new(renderer: &Renderer, output_format: TextureFormat, camera: FlyCamera) -> Self {
+		let device = &renderer.device;
+
+		let bgl = BindGroupLayoutBuilder::new()
+			.append(
+				ShaderStages::FRAGMENT,
+				BindingType::Texture {
+					sample_type: TextureSampleType::Float { filterable: false },
+					view_dimension: TextureViewDimension::D2,
+					multisampled: false,
+				},
+				None,
+			)
+			.build(device, Some("split screen bgl"));
+
+		let module = device.create_shader_module(&ShaderModuleDescriptor {
+			label: Some("split screen"),
+			source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("split_screen.wgsl"))),
+		});
+
+		let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+			label: Some("split screen composite"),
+			bind_group_layouts: &[&bgl],
+			push_constant_ranges: &[],
+		});
+
+		let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+			label: Some("split screen composite"),
+			layout: Some(&layout),
+			vertex: VertexState {
+				module: &module,
+				entry_point: "vs_main",
+				buffers: &[],
+			},
+			primitive: PrimitiveState::default(),
+			depth_stencil: None,
+			multisample: MultisampleState::default(),
+			fragment: Some(FragmentState {
+				module: &module,
+				entry_point: "fs_main",
+				targets: &[ColorTargetState {
+					format: output_format,
+					blend: None,
+					write_mask: ColorWrites::all(),
+				}],
+			}),
+			multiview: None,
+		});
+
+		Self {
+			enabled: false,
+			camera,
+			input_viewport: Viewport::Left,
+			bgl,
+			pipeline,
+			format: output_format,
+			left: None,
+		}
+	}
+
+	/// Swaps which viewport receives input.
+	pub fn toggle_input_viewport(&mut self) {
+		self.input_viewport = match self.input_viewport {
+			Viewport::Left => Viewport::Right,
+			Viewport::Right => Viewport::Left,
+		};
+	}
+
+	/// True when input should drive `self.camera` instead of the main camera.
+	pub fn routes_input_to_second(&self) -> bool {
+		self.enabled && self.input_viewport == Viewport::Right
+	}
+
+	/// Labels each viewport, marking the one that receives input.
+	pub fn overlay(&self, ctx: &egui::CtxRef, resolution: UVec2, scale_factor: f32) {
+		if !self.enabled {
+			return;
+		}
+
+		for viewport in [Viewport::Left, Viewport::Right] {
+			let (offset, size) = viewport.rect(resolution);
+			let center = (offset.x as f32 + size.x as f32 / 2.0) / scale_factor;
+			let input = match viewport == self.input_viewport {
+				true => " (input, tab to switch)",
+				false => "",
+			};
+
+			egui::Area::new(format!("split screen {:?}", viewport))
+				.fixed_pos(egui::pos2(center, 4.0))
+				.interactable(false)
+				.show(ctx, |ui| {
+					ui.label(format!("{:?}{}", viewport, input));
+				});
+		}
+	}
+
+	/// Texture the left viewport's graph should render into, recreated when
+	/// the viewport size changes.
+	pub fn left_target(&mut self, renderer: &Renderer, resolution: UVec2) -> Arc<TextureView> {
+		match &self.left {
+			Some((size, view)) if *size == resolution => view.clone(),
+			_ => {
+				let texture = renderer.device.create_texture(&TextureDescriptor {
+					label: Some("split screen left"),
+					size: Extent3d {
+						width: resolution.x,
+						height: resolution.y,
+						depth_or_array_layers: 1,
+					},
+					mip_level_count: 1,
+					sample_count: 1,
+					dimension: TextureDimension::D2,
+					format: self.format,
+					usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+				});
+				let view = Arc::new(texture.create_view(&TextureViewDescriptor::default()));
+				self.left = Some((resolution, view.clone()));
+				view
+			}
+		}
+	}
+
+	/// Draws the previously rendered left viewport and `right` side by side
+	/// onto `dst`.
+	pub fn add_composite_to_graph<'node>(
+		&'node self,
+		graph: &mut RenderGraph<'node>,
+		right: RenderTargetHandle,
+		dst: RenderTargetHandle,
+		resolution: UVec2,
+	) {
+		let mut builder = graph.add_node("Split Screen Composite");
+
+		let right_handle = builder.add_render_target_input(right);
+		let output_handle = builder.add_render_target_output(dst);
+
+		let rpass_handle = builder.add_renderpass(RenderPassTargets {
+			targets: vec![RenderPassTarget {
+				color: output_handle,
+				clear: Color::BLACK,
+				resolve: None,
+			}],
+			depth_stencil: None,
+		});
+
+		let pt_handle = builder.passthrough_ref(self);
+
+		builder.build(
+			move |pt, renderer, encoder_or_pass, temps, _ready, graph_data| {
+				let this = pt.get(pt_handle);
+				let rpass = encoder_or_pass.get_rpass(rpass_handle);
+				let right_view = graph_data.get_render_target(right_handle);
+
+				rpass.set_pipeline(&this.pipeline);
+
+				let left_view = this.left.as_ref().map(|(_, view)| &**view);
+				for (viewport, source) in [
+					(Viewport::Left, left_view),
+					(Viewport::Right, Some(right_view)),
+				] {
+					let source = match source {
+						Some(source) => source,
+						None => continue,
+					};
+					let bg = temps.add(BindGroupBuilder::new().append_texture_view(source).build(
+						&renderer.device,
+						Some("split screen bg"),
+						&this.bgl,
+					));
+
+					let (offset, size) = viewport.rect(resolution);
+					rpass.set_viewport(
+						offset.x as f32,
+						offset.y as f32,
+						size.x as f32,
+						size.y as f32,
+						0.0,
+						1.0,
+					);
+					rpass.set_bind_group(0, bg, &[]);
+					rpass.draw(0..3, 0..1);
+				}
+
+				// leave the full surface for whatever draws next in this pass
+				rpass.set_viewport(0.0, 0.0, resolution.x as f32, resolution.y as f32, 0.0, 1.0);
+			},
+		);
+	}
+}
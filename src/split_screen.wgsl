@@ -0,0 +1,28 @@
+// Copies a viewport image into its region of the output. The region itself
+// is selected with the render pass viewport.
+
+struct VertexOutput {
+	[[builtin(position)]] position: vec4<f32>;
+	[[location(0)]] uv: vec2<f32>;
+};
+
+[[group(0), binding(0)]]
+var source: texture_2d<f32>;
+
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] vertex_index: u32) -> VertexOutput {
+	// single triangle covering the whole viewport
+	let x = f32(i32(vertex_index & 1u) * 4 - 1);
+	let y = f32(i32(vertex_index & 2u) * 2 - 1);
+	var out: VertexOutput;
+	out.position = vec4<f32>(x, y, 0.0, 1.0);
+	out.uv = vec2<f32>(x * 0.5 + 0.5, 0.5 - y * 0.5);
+	return out;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+	let size = textureDimensions(source);
+	let texel = clamp(vec2<i32>(in.uv * vec2<f32>(size)), vec2<i32>(0), size - vec2<i32>(1));
+	return textureLoad(source, texel, 0);
+}
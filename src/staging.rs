@@ -0,0 +1,114 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Wake, Waker};
+
+use rend3::Renderer;
+use wgpu::util::StagingBelt;
+use wgpu::{Buffer, BufferAddress, BufferSize, CommandEncoder, CommandEncoderDescriptor};
+
+/// size of each staging buffer, uploads bigger than this get their own
+const CHUNK_SIZE: BufferAddress = 1 << 20;
+
+/// Uploads made through a [`StagingPool`] over a frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StagingStats {
+	pub writes: u32,
+	pub bytes: u64,
+	/// submissions whose staging buffers the gpu hasn't given back yet
+	pub in_flight: usize,
+}
+
+type Recall = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// For futures that are polled once a frame instead of waited on.
+pub(crate) struct NoopWaker;
+
+impl Wake for NoopWaker {
+	fn wake(self: Arc<Self>) {}
+}
+
+/// Reusable staging buffers for data uploaded every frame, like debug lines
+/// and per object uniforms, so uploads don't allocate new buffers each frame
+/// the way [`wgpu::Queue::write_buffer`] does. Buffers come back for reuse
+/// once the gpu is done copying out of them.
+pub struct StagingPool {
+	belt: StagingBelt,
+	/// copies recorded since the last [`StagingPool::submit`]
+	encoder: Option<CommandEncoder>,
+	recalls: Vec<Recall>,
+	waker: Waker,
+	frame: StagingStats,
+	last_frame: StagingStats,
+}
+
+impl Default for StagingPool {
+	fn default() -> Self {
+		Self {
+			belt: StagingBelt::new(CHUNK_SIZE),
+			encoder: None,
+			recalls: Vec::new(),
+			waker: Arc::new(NoopWaker).into(),
+			frame: StagingStats::default(),
+			last_frame: StagingStats::default(),
+		}
+	}
+}
+
+impl StagingPool {
+	/// Copies `data` into `target` at `offset`, which needs
+	/// [`wgpu::BufferUsages::COPY_DST`]. The copy happens on the next
+	/// [`StagingPool::submit`], which has to come before anything reading
+	/// `target` is submitted.
+	pub fn write(
+		&mut self,
+		renderer: &Renderer,
+		target: &Buffer,
+		offset: BufferAddress,
+		data: &[u8],
+	) {
+		let size = match BufferSize::new(data.len() as BufferAddress) {
+			Some(size) => size,
+			None => return,
+		};
+		let encoder = self.encoder.get_or_insert_with(|| {
+			renderer
+				.device
+				.create_command_encoder(&CommandEncoderDescriptor {
+					label: Some("staging uploads"),
+				})
+		});
+		self.belt
+			.write_buffer(encoder, target, offset, size, &renderer.device)
+			.copy_from_slice(data);
+		self.frame.writes += 1;
+		self.frame.bytes += data.len() as u64;
+	}
+
+	/// Submits the copies written so far and starts getting their staging
+	/// buffers back.
+	pub fn submit(&mut self, renderer: &Renderer) {
+		let encoder = match self.encoder.take() {
+			Some(encoder) => encoder,
+			None => return,
+		};
+		self.belt.finish();
+		renderer.queue.submit(Some(encoder.finish()));
+		self.recalls.push(Box::pin(self.belt.recall()));
+	}
+
+	/// Collects the buffers the gpu is done with and keeps this frame's stats
+	/// for [`StagingPool::last_frame`]. Call once a frame, after the frame
+	/// was submitted.
+	pub fn end_frame(&mut self) {
+		let mut context = Context::from_waker(&self.waker);
+		self.recalls
+			.retain_mut(|recall| recall.as_mut().poll(&mut context).is_pending());
+		self.frame.in_flight = self.recalls.len();
+		self.last_frame = std::mem::take(&mut self.frame);
+	}
+
+	pub fn last_frame(&self) -> StagingStats {
+		self.last_frame
+	}
+}
@@ -0,0 +1,103 @@
+use glam::UVec2;
+use rend3::types::{PresentMode, Surface, TextureFormat};
+use rend3::util::output::OutputFrame;
+use rend3::Renderer;
+use wgpu::{SurfaceError, TextureViewDescriptor};
+use winit::window::Window;
+
+/// Acquires the next surface texture to render the frame to, reconfiguring
+/// the surface when it's outdated or lost, like after alt-tabbing out of
+/// exclusive fullscreen. `None` when there's nothing to render to, like while
+/// the window is minimized, and the frame should be skipped.
+///
+/// rend3 would acquire the surface itself as late as it can, but it panics
+/// when that fails, so the frame is acquired up front instead.
+pub fn acquire(
+	renderer: &Renderer,
+	window: &Window,
+	surface: &Surface,
+	format: TextureFormat,
+	resolution: UVec2,
+	present_mode: PresentMode,
+) -> Option<OutputFrame> {
+	let size = window.inner_size();
+	if size.width == 0 || size.height == 0 {
+		return None;
+	}
+	let surface_tex = acquire_with(
+		|| surface.get_current_texture(),
+		|| rend3::configure_surface(surface, &renderer.device, format, resolution, present_mode),
+	)?;
+	let view = surface_tex
+		.texture
+		.create_view(&TextureViewDescriptor::default());
+	Some(OutputFrame::SurfaceAcquired { view, surface_tex })
+}
+
+/// Calls `get` to acquire a texture, calling `reconfigure` and trying once
+/// more when the surface is outdated or lost.
+fn acquire_with<T>(
+	mut get: impl FnMut() -> Result<T, SurfaceError>,
+	mut reconfigure: impl FnMut(),
+) -> Option<T> {
+	for reconfigured in [false, true] {
+		match get() {
+			Ok(texture) => return Some(texture),
+			Err(e @ (SurfaceError::Outdated | SurfaceError::Lost)) if !reconfigured => {
+				log::info!("reconfiguring the surface: {}", e);
+				reconfigure();
+			}
+			// the window may be occluded, try again next frame
+			Err(SurfaceError::Timeout) => return None,
+			Err(e) => {
+				log::warn!("skipping a frame, couldn't acquire the surface: {}", e);
+				return None;
+			}
+		}
+	}
+	None
+}
+
+#[cfg(test)]
+mod tests {
+	use std::cell::Cell;
+
+	use super::*;
+
+	/// Acquires from a surface that fails with `errors` in turn before
+	/// succeeding. Returns what was acquired and how often it was reconfigured.
+	fn acquire_after(errors: &[SurfaceError]) -> (Option<usize>, usize) {
+		let attempts = Cell::new(0);
+		let reconfigures = Cell::new(0);
+		let acquired = acquire_with(
+			|| {
+				let attempt = attempts.get();
+				attempts.set(attempt + 1);
+				match errors.get(attempt) {
+					Some(e) => Err(e.clone()),
+					None => Ok(attempt),
+				}
+			},
+			|| reconfigures.set(reconfigures.get() + 1),
+		);
+		(acquired, reconfigures.get())
+	}
+
+	#[test]
+	fn recovers_from_a_lost_or_outdated_surface() {
+		assert_eq!(acquire_after(&[]), (Some(0), 0));
+		assert_eq!(acquire_after(&[SurfaceError::Outdated]), (Some(1), 1));
+		assert_eq!(acquire_after(&[SurfaceError::Lost]), (Some(1), 1));
+	}
+
+	#[test]
+	fn skips_the_frame_when_it_cant_recover() {
+		// only reconfigures once a frame
+		assert_eq!(
+			acquire_after(&[SurfaceError::Lost, SurfaceError::Outdated]),
+			(None, 1)
+		);
+		assert_eq!(acquire_after(&[SurfaceError::Timeout]), (None, 0));
+		assert_eq!(acquire_after(&[SurfaceError::OutOfMemory]), (None, 0));
+	}
+}
@@ -0,0 +1,258 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// how long a finished task stays in the corner
+const TOAST_DURATION: Duration = Duration::from_secs(4);
+
+/// How a task ended.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TaskOutcome {
+	Done(String),
+	Failed(String),
+	Cancelled,
+}
+
+#[derive(Default)]
+struct Progress {
+	/// `None` until the task knows how much work there is
+	fraction: Option<f32>,
+	message: String,
+	outcome: Option<TaskOutcome>,
+}
+
+/// Flag long running work checks between steps to stop early. Clones share
+/// the flag, so a token can be handed to threads and loaders the work
+/// calls into.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+	pub fn cancel(&self) {
+		self.0.store(true, Ordering::Relaxed);
+	}
+
+	pub fn is_cancelled(&self) -> bool {
+		self.0.load(Ordering::Relaxed)
+	}
+}
+
+struct TaskState {
+	name: String,
+	progress: Mutex<Progress>,
+	cancelled: CancelToken,
+}
+
+impl TaskState {
+	fn progress(&self) -> std::sync::MutexGuard<'_, Progress> {
+		// a task that panicked while reporting left nothing half written
+		self.progress.lock().unwrap_or_else(|e| e.into_inner())
+	}
+}
+
+/// Reports a task's progress to the [`TaskManager`] it was started with. Can
+/// be moved to another thread. Dropping it before [`TaskHandle::finish`],
+/// like when the thread panics, ends the task as cancelled or failed.
+pub struct TaskHandle(Arc<TaskState>);
+
+impl TaskHandle {
+	/// `fraction` from 0 to 1, and what the task is doing now.
+	pub fn set_progress(&self, fraction: f32, message: impl Into<String>) {
+		let mut progress = self.0.progress();
+		progress.fraction = Some(fraction.clamp(0.0, 1.0));
+		progress.message = message.into();
+	}
+
+	/// Whether the user asked to cancel. Tasks should check between steps
+	/// and stop early.
+	pub fn is_cancelled(&self) -> bool {
+		self.0.cancelled.is_cancelled()
+	}
+
+	/// Token cancelled along with the task, for the work it runs.
+	pub fn token(&self) -> CancelToken {
+		self.0.cancelled.clone()
+	}
+
+	/// Cancels the task from the side that owns it, like when it's restarted.
+	pub fn cancel(&self) {
+		self.0.cancelled.cancel();
+	}
+
+	/// Ends the task with a summary of what it did, or why it failed.
+	pub fn finish(self, result: Result<String, String>) {
+		self.0.progress().outcome = Some(match result {
+			_ if self.is_cancelled() => TaskOutcome::Cancelled,
+			Ok(summary) => TaskOutcome::Done(summary),
+			Err(e) => TaskOutcome::Failed(e),
+		});
+	}
+}
+
+impl Drop for TaskHandle {
+	fn drop(&mut self) {
+		let cancelled = self.is_cancelled();
+		let mut progress = self.0.progress();
+		if progress.outcome.is_none() {
+			progress.outcome = Some(match cancelled {
+				true => TaskOutcome::Cancelled,
+				false => TaskOutcome::Failed("stopped without finishing".into()),
+			});
+		}
+	}
+}
+
+struct Task {
+	state: Arc<TaskState>,
+	/// when the outcome was first seen, to time the toast
+	finished: Option<Instant>,
+}
+
+/// Long running work like imports, bakes and exports, shown as a jobs panel
+/// and as toasts in the corner so nothing runs invisibly. Tasks either run
+/// on their own thread with [`TaskManager::spawn`] or are driven by their
+/// owner through the handle from [`TaskManager::begin`].
+#[derive(Default)]
+pub struct TaskManager {
+	pub open: bool,
+	tasks: Vec<Task>,
+}
+
+impl TaskManager {
+	/// Adds a task the caller reports progress for.
+	pub fn begin(&mut self, name: impl Into<String>) -> TaskHandle {
+		let state = Arc::new(TaskState {
+			name: name.into(),
+			progress: Mutex::default(),
+			cancelled: CancelToken::default(),
+		});
+		self.tasks.push(Task {
+			state: state.clone(),
+			finished: None,
+		});
+		TaskHandle(state)
+	}
+
+	/// Runs `work` on a background thread. It returns the task's summary
+	/// when done, or why it failed.
+	pub fn spawn<F>(&mut self, name: impl Into<String>, work: F)
+	where
+		F: FnOnce(&TaskHandle) -> Result<String, String> + Send + 'static,
+	{
+		let name = name.into();
+		let handle = self.begin(name.clone());
+		let spawned = thread::Builder::new().name(name).spawn(move || {
+			let result = work(&handle);
+			handle.finish(result);
+		});
+		if let Err(e) = spawned {
+			// the closure and its handle were dropped, so the task already
+			// shows as failed
+			log::error!("couldn't start a task thread: {}", e);
+		}
+	}
+
+	pub fn running(&self) -> usize {
+		self.tasks
+			.iter()
+			.filter(|task| task.state.progress().outcome.is_none())
+			.count()
+	}
+
+	/// Logs tasks that ended and forgets them once their toast has shown.
+	/// Call once a frame.
+	pub fn update(&mut self) {
+		let now = Instant::now();
+		for task in &mut self.tasks {
+			if task.finished.is_some() {
+				continue;
+			}
+			match &task.state.progress().outcome {
+				Some(TaskOutcome::Done(summary)) => log::info!("{}: {}", task.state.name, summary),
+				Some(TaskOutcome::Failed(e)) => log::error!("{} failed: {}", task.state.name, e),
+				Some(TaskOutcome::Cancelled) => log::info!("{} cancelled", task.state.name),
+				None => continue,
+			}
+			task.finished = Some(now);
+		}
+		self.tasks.retain(|task| {
+			task.finished
+				.is_none_or(|finished| now - finished < TOAST_DURATION)
+		});
+	}
+
+	/// Shows each task's progress, with buttons to cancel running ones.
+	fn task_ui(ui: &mut egui::Ui, task: &Task) {
+		let progress = task.state.progress();
+		ui.horizontal(|ui| {
+			ui.strong(&task.state.name);
+			if progress.outcome.is_none()
+				&& ui
+					.add_enabled(
+						!task.state.cancelled.is_cancelled(),
+						egui::Button::new("cancel").small(),
+					)
+					.clicked()
+			{
+				task.state.cancelled.cancel();
+			}
+		});
+		match &progress.outcome {
+			None => {
+				let bar = match progress.fraction {
+					Some(fraction) => egui::ProgressBar::new(fraction).show_percentage(),
+					None => egui::ProgressBar::new(0.0).animate(true),
+				};
+				ui.add(match progress.message.is_empty() {
+					true => bar,
+					false => bar.text(&progress.message),
+				});
+			}
+			Some(TaskOutcome::Done(summary)) => {
+				ui.label(summary);
+			}
+			Some(TaskOutcome::Failed(e)) => {
+				ui.colored_label(egui::Color32::LIGHT_RED, format!("failed: {}", e));
+			}
+			Some(TaskOutcome::Cancelled) => {
+				ui.label("cancelled");
+			}
+		}
+	}
+
+	pub fn window(&mut self, ctx: &egui::CtxRef) {
+		let mut open = self.open;
+		egui::Window::new("jobs")
+			.open(&mut open)
+			.resizable(false)
+			.show(ctx, |ui| {
+				if self.tasks.is_empty() {
+					ui.label("nothing running");
+				}
+				for task in &self.tasks {
+					Self::task_ui(ui, task);
+					ui.separator();
+				}
+			});
+		self.open = open;
+	}
+
+	/// Shows running and just finished tasks in the bottom right corner while
+	/// the jobs panel is closed.
+	pub fn toasts(&self, ctx: &egui::CtxRef) {
+		if self.open || self.tasks.is_empty() {
+			return;
+		}
+		egui::Area::new("task_toasts")
+			.anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-8.0, -8.0))
+			.show(ctx, |ui| {
+				egui::Frame::popup(ui.style()).show(ui, |ui| {
+					ui.set_max_width(240.0);
+					for task in &self.tasks {
+						Self::task_ui(ui, task);
+					}
+				});
+			});
+	}
+}
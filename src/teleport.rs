@@ -0,0 +1,243 @@
+use glam::Vec3;
+use rend3::types::Handedness;
+
+use crate::camera::CameraPose;
+use crate::scene::{Position, Scene, SceneObject, ScenePosition, SceneTransform};
+
+/// Where a teleport sends the camera.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TeleportTarget {
+	Position(Vec3),
+	/// frames the object with the given name
+	Object(String),
+}
+
+/// A command typed into the console.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConsoleCommand {
+	Teleport(TeleportTarget),
+	/// shakes the main camera with this much trauma, see
+	/// [`crate::camera::CameraShake`]
+	Shake(f32),
+}
+
+/// Console commands as their verb and usage.
+pub const CONSOLE_COMMANDS: [(&str, &str); 3] = [
+	("tp", "tp <x> <y> <z>"),
+	("goto", "goto <object name>"),
+	("shake", "shake <trauma from 0 to 1>"),
+];
+
+fn usage<T>(verb: &str) -> Result<T, String> {
+	let usage = CONSOLE_COMMANDS.iter().find(|(v, _)| *v == verb);
+	Err(format!("usage: {}", usage.map_or(verb, |(_, usage)| usage)))
+}
+
+/// Parses a console command:
+///
+/// - `tp <x> <y> <z>` moves the camera to a position, commas between the
+///   coordinates are allowed so copied coordinates can be pasted in
+/// - `goto <name>` moves the camera in front of an object
+/// - `shake <trauma>`, from 0.0 to 1.0
+pub fn parse_command(command: &str) -> Result<ConsoleCommand, String> {
+	let command = command.trim();
+	let (verb, rest) = command.split_once(' ').unwrap_or((command, ""));
+	let rest = rest.trim();
+	let target = match verb {
+		"tp" => {
+			let coords: Vec<f32> = rest
+				.split(|c: char| c == ',' || c.is_whitespace())
+				.filter(|part| !part.is_empty())
+				.map(|part| part.parse().map_err(|_| format!("not a number: {}", part)))
+				.collect::<Result<_, _>>()?;
+			match coords.as_slice() {
+				[x, y, z] => TeleportTarget::Position(Vec3::new(*x, *y, *z)),
+				_ => return usage(verb),
+			}
+		}
+		"goto" if !rest.is_empty() => TeleportTarget::Object(rest.into()),
+		"shake" => match rest.parse() {
+			Ok(trauma) => return Ok(ConsoleCommand::Shake(trauma)),
+			Err(_) => return usage(verb),
+		},
+		"goto" => return usage(verb),
+		_ => return Err(format!("unknown command: {}", verb)),
+	};
+	Ok(ConsoleCommand::Teleport(target))
+}
+
+/// Camera position for `target`. Objects are approached along the camera's
+/// current view direction, far enough back to see all of them.
+pub fn resolve(
+	target: &TeleportTarget,
+	scene: &Scene,
+	camera: &CameraPose,
+	handedness: Handedness,
+) -> Result<Position, String> {
+	let name = match target {
+		TeleportTarget::Position(position) => return Ok(Position::from_vec3a((*position).into())),
+		TeleportTarget::Object(name) => name,
+	};
+	let object = scene
+		.iter()
+		.map(|(_, object)| object)
+		.find(|object| object.name == *name)
+		.ok_or_else(|| format!("no object named {}", name))?;
+	Ok(frame(object, camera, handedness))
+}
+
+/// Camera position in front of `object` along the camera's current view
+/// direction, far enough back to see all of it.
+pub fn frame(object: &SceneObject, camera: &CameraPose, handedness: Handedness) -> Position {
+	let transform = object.transform().to_mat4();
+	let (center, radius) = match &object.geometry {
+		Some(geometry) if !geometry.positions.is_empty() => {
+			let points: Vec<Vec3> = geometry
+				.positions
+				.iter()
+				.map(|p| transform.transform_point3(*p))
+				.collect();
+			let min = points.iter().fold(Vec3::splat(f32::MAX), |a, b| a.min(*b));
+			let max = points.iter().fold(Vec3::splat(f32::MIN), |a, b| a.max(*b));
+			((min + max) * 0.5, (max - min).length() * 0.5)
+		}
+		_ => (transform.w_axis.truncate(), 1.0),
+	};
+
+	let distance = radius * 2.5 + 1.0;
+	Position::from_vec3a(center.into()).offset(-camera.forward(handedness) * distance)
+}
+
+/// Window with a command line and fields to teleport the camera.
+#[derive(Default)]
+pub struct TeleportWindow {
+	pub open: bool,
+	command: String,
+	position: Vec3,
+	object: String,
+	error: Option<String>,
+	/// whether the window was shown last frame
+	shown: bool,
+}
+
+impl TeleportWindow {
+	/// Shows the window and returns a target if one was chosen this frame.
+	/// `current` fills the coordinate fields when the window opens.
+	pub fn window(
+		&mut self,
+		ctx: &egui::CtxRef,
+		scene: &Scene,
+		current: Vec3,
+	) -> Option<TeleportTarget> {
+		let mut target = None;
+		let just_opened = self.open && !self.shown;
+		let mut open = self.open;
+		egui::Window::new("teleport")
+			.open(&mut open)
+			.resizable(false)
+			.show(ctx, |ui| {
+				if just_opened {
+					self.position = current;
+				}
+
+				let response = ui.add(
+					egui::TextEdit::singleline(&mut self.command).hint_text("tp x y z / goto name"),
+				);
+				if response.lost_focus() && ui.input().key_pressed(egui::Key::Enter) {
+					match parse_command(&self.command) {
+						Ok(ConsoleCommand::Teleport(parsed)) => {
+							target = Some(parsed);
+							self.command.clear();
+						}
+						Ok(_) => self.error = Some("not a teleport, use tp or goto".into()),
+						Err(e) => self.error = Some(e),
+					}
+				}
+
+				ui.separator();
+				ui.horizontal(|ui| {
+					ui.add(egui::DragValue::new(&mut self.position.x).prefix("x "));
+					ui.add(egui::DragValue::new(&mut self.position.y).prefix("y "));
+					ui.add(egui::DragValue::new(&mut self.position.z).prefix("z "));
+					if ui.button("go").clicked() {
+						target = Some(TeleportTarget::Position(self.position));
+					}
+				});
+
+				ui.horizontal(|ui| {
+					egui::ComboBox::from_id_source("teleport object")
+						.selected_text(self.object.as_str())
+						.show_ui(ui, |ui| {
+							let mut names: Vec<&str> = scene
+								.iter()
+								.map(|(_, object)| object.name.as_str())
+								.collect();
+							names.sort_unstable();
+							for name in names {
+								ui.selectable_value(&mut self.object, name.to_string(), name);
+							}
+						});
+					let chosen = !self.object.is_empty();
+					if ui
+						.add_enabled(chosen, egui::Button::new("go to object"))
+						.clicked()
+					{
+						target = Some(TeleportTarget::Object(self.object.clone()));
+					}
+				});
+
+				if let Some(error) = &self.error {
+					ui.colored_label(egui::Color32::RED, error);
+				}
+			});
+		self.open = open;
+		self.shown = open;
+		target
+	}
+
+	/// Shows why the last target couldn't be reached.
+	pub fn set_error(&mut self, error: String) {
+		self.error = Some(error);
+	}
+
+	pub fn clear_error(&mut self) {
+		self.error = None;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_console_commands() {
+		assert_eq!(
+			parse_command(" tp 1, 2.5 -3 "),
+			Ok(ConsoleCommand::Teleport(TeleportTarget::Position(
+				Vec3::new(1.0, 2.5, -3.0)
+			)))
+		);
+		assert_eq!(
+			parse_command("goto  big tree "),
+			Ok(ConsoleCommand::Teleport(TeleportTarget::Object(
+				"big tree".into()
+			)))
+		);
+		assert_eq!(parse_command("shake 0.5"), Ok(ConsoleCommand::Shake(0.5)));
+	}
+
+	#[test]
+	fn bad_commands_give_their_usage() {
+		assert_eq!(parse_command("tp 1 2"), Err("usage: tp <x> <y> <z>".into()));
+		assert_eq!(parse_command("tp 1 two 3"), Err("not a number: two".into()));
+		assert_eq!(
+			parse_command("goto"),
+			Err("usage: goto <object name>".into())
+		);
+		assert_eq!(
+			parse_command("shake lots"),
+			Err("usage: shake <trauma from 0 to 1>".into())
+		);
+		assert_eq!(parse_command("fly"), Err("unknown command: fly".into()));
+	}
+}
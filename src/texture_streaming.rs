@@ -0,0 +1,341 @@
+use std::num::NonZeroU32;
+
+use glam::{UVec2, Vec3};
+use rend3::types::{
+	MaterialHandle, MipmapCount, MipmapSource, Texture, TextureFormat, TextureHandle,
+};
+use rend3::Renderer;
+use rend3_routine::pbr::PbrMaterial;
+
+use crate::scene::Scene;
+use crate::work_queue::WorkQueue;
+
+/// largest side of the mip every texture keeps resident, shown while better
+/// ones stream in and when the budget runs out
+const FALLBACK_SIZE: u32 = 64;
+/// frames a texture no visible object uses keeps streaming, after that it
+/// stays at its fallback mip
+const UNUSED_FRAMES: u32 = 600;
+
+struct MipLevel {
+	size: UVec2,
+	data: Vec<u8>,
+}
+
+struct StreamedTexture {
+	name: String,
+	/// the whole mip chain on the cpu, largest first
+	mips: Vec<MipLevel>,
+	format: TextureFormat,
+	material: MaterialHandle,
+	build_material: Box<dyn Fn(TextureHandle) -> PbrMaterial>,
+	/// largest mip on the gpu, as an index into `mips`
+	resident: usize,
+	/// largest mip worth having for how big the texture is on screen
+	wanted: usize,
+	/// texels across the largest object using it on screen
+	coverage: f32,
+	unused_frames: u32,
+	/// kept alive while the material uses it
+	_handle: TextureHandle,
+}
+
+impl StreamedTexture {
+	fn fallback(&self) -> usize {
+		fallback_level(&self.mips)
+	}
+
+	/// gpu memory with `level` as the largest mip
+	fn bytes(&self, level: usize) -> usize {
+		self.mips[level..].iter().map(|mip| mip.data.len()).sum()
+	}
+
+	/// Uploads the chain from `level` down and points the material at it.
+	fn make_resident(&mut self, renderer: &Renderer, level: usize) {
+		let texture = chain_texture(&self.name, &self.mips, level, self.format);
+		let handle = renderer.add_texture_2d(texture);
+		renderer.update_material(&self.material, (self.build_material)(handle.clone()));
+		self._handle = handle;
+		self.resident = level;
+	}
+}
+
+/// Streams textures in mip by mip as they get bigger on screen, so scenes with
+/// more texture data than fits in video memory still load. Every texture
+/// keeps a small mip resident and gets sharper one mip a frame up to what its
+/// screen coverage needs. When the wanted mips don't fit in the budget the
+/// textures covering the least of the screen are kept blurrier.
+///
+/// Coverage assumes a texture spans each object using it once, from the
+/// object's bounding sphere.
+pub struct TextureStreamer {
+	pub open: bool,
+	/// bytes of gpu memory the streamed textures may use
+	pub budget: usize,
+	textures: Vec<StreamedTexture>,
+}
+
+impl Default for TextureStreamer {
+	fn default() -> Self {
+		Self {
+			open: false,
+			budget: 256 << 20,
+			textures: Vec::new(),
+		}
+	}
+}
+
+impl TextureStreamer {
+	/// Adds a streamed rgba8 texture with its mip 0 in `texture`, and the
+	/// material built around it by `build_material`. Only the fallback mip is
+	/// uploaded until objects using the material show up on screen. Mips
+	/// larger than the device supports are dropped.
+	pub fn add(
+		&mut self,
+		renderer: &Renderer,
+		name: &str,
+		texture: Texture,
+		build_material: impl Fn(TextureHandle) -> PbrMaterial + 'static,
+	) -> MaterialHandle {
+		let mut mips = vec![MipLevel {
+			size: texture.size,
+			data: texture.data,
+		}];
+		while mips.last().unwrap().size.max_element() > 1 {
+			let next = downsample(mips.last().unwrap());
+			mips.push(next);
+		}
+		let limit = renderer.limits.max_texture_dimension_2d;
+		let largest = mips
+			.iter()
+			.position(|mip| mip.size.max_element() <= limit)
+			.unwrap_or(mips.len() - 1);
+		if largest > 0 {
+			log::warn!(
+				"{} is {}x{}, larger than the {}px textures the device supports, streaming it \
+				 from {}x{}",
+				name,
+				texture.size.x,
+				texture.size.y,
+				limit,
+				mips[largest].size.x,
+				mips[largest].size.y
+			);
+			mips.drain(..largest);
+		}
+
+		let fallback = fallback_level(&mips);
+		let handle = renderer.add_texture_2d(chain_texture(name, &mips, fallback, texture.format));
+		let material = renderer.add_material(build_material(handle.clone()));
+
+		self.textures.push(StreamedTexture {
+			name: name.into(),
+			mips,
+			format: texture.format,
+			material: material.clone(),
+			build_material: Box::new(build_material),
+			resident: fallback,
+			wanted: fallback,
+			coverage: 0.0,
+			unused_frames: 0,
+			_handle: handle,
+		});
+		material
+	}
+
+	/// Picks each texture's mip from how big its objects are on screen and
+	/// streams one mip a frame towards it, within the work queue's budget.
+	/// `camera` is in the render space objects are submitted in.
+	pub fn update(
+		&mut self,
+		renderer: &Renderer,
+		scene: &Scene,
+		camera: Vec3,
+		vfov: f32,
+		viewport_height: u32,
+		work: &mut WorkQueue,
+	) {
+		for texture in &mut self.textures {
+			texture.coverage = 0.0;
+		}
+		// pixels per unit of size at a distance of one
+		let pixels_per_radian = viewport_height as f32 / (vfov.to_radians() * 0.5).tan() * 0.5;
+		for (_, object) in scene.visible() {
+			let geometry = match &object.geometry {
+				Some(geometry) => geometry,
+				None => continue,
+			};
+			let idx = object.material().get_raw().idx;
+			let texture = match self
+				.textures
+				.iter_mut()
+				.find(|texture| texture.material.get_raw().idx == idx)
+			{
+				Some(texture) => texture,
+				None => continue,
+			};
+			let (_, transform) = object.rendered_transforms();
+			let center = transform.transform_point3(geometry.center);
+			let scale = transform.to_scale_rotation_translation().0.max_element();
+			let radius = geometry.radius * scale;
+			let distance = (center.distance(camera) - radius)
+				.max(radius * 0.1)
+				.max(1e-3);
+			let pixels = 2.0 * radius / distance * pixels_per_radian;
+			texture.coverage = texture.coverage.max(pixels);
+		}
+
+		for texture in &mut self.textures {
+			let fallback = texture.fallback();
+			texture.wanted = match texture.coverage > 0.0 {
+				// smallest mip with at least a texel per pixel
+				true => texture
+					.mips
+					.iter()
+					.rposition(|mip| mip.size.max_element() as f32 >= texture.coverage)
+					.unwrap_or(0)
+					.min(fallback),
+				false => fallback,
+			};
+			texture.unused_frames = match texture.coverage > 0.0 {
+				true => 0,
+				false => texture.unused_frames + 1,
+			};
+		}
+		self.textures
+			.retain(|texture| texture.unused_frames < UNUSED_FRAMES);
+
+		// over budget, the textures covering the least of the screen give up
+		// detail first
+		let mut total: usize = self
+			.textures
+			.iter()
+			.map(|texture| texture.bytes(texture.wanted))
+			.sum();
+		while total > self.budget {
+			let texture = self
+				.textures
+				.iter_mut()
+				.filter(|texture| texture.wanted < texture.fallback())
+				.min_by(|a, b| a.coverage.total_cmp(&b.coverage));
+			let texture = match texture {
+				Some(texture) => texture,
+				None => break,
+			};
+			total -= texture.bytes(texture.wanted) - texture.bytes(texture.wanted + 1);
+			texture.wanted += 1;
+		}
+
+		// dropping detail frees memory right away, adding it goes a mip at a
+		// time so each step is a small upload
+		let mut first = true;
+		for texture in &mut self.textures {
+			let level = match texture.wanted {
+				wanted if wanted > texture.resident => wanted,
+				wanted if wanted < texture.resident => texture.resident - 1,
+				_ => continue,
+			};
+			if !first && !work.has_time() {
+				break;
+			}
+			first = false;
+			work.measure(|| texture.make_resident(renderer, level));
+		}
+	}
+
+	/// Gpu memory the streamed textures use now.
+	pub fn resident_bytes(&self) -> usize {
+		self.textures
+			.iter()
+			.map(|texture| texture.bytes(texture.resident))
+			.sum()
+	}
+
+	pub fn window(&mut self, ctx: &egui::CtxRef) {
+		let mut open = self.open;
+		egui::Window::new("texture streaming")
+			.open(&mut open)
+			.resizable(false)
+			.show(ctx, |ui| {
+				let mut budget = (self.budget >> 20) as u32;
+				ui.add(
+					egui::Slider::new(&mut budget, 16..=4096)
+						.logarithmic(true)
+						.suffix("MiB")
+						.text("budget"),
+				);
+				self.budget = (budget as usize) << 20;
+				ui.label(format!(
+					"{:.1} MiB resident in {} textures",
+					self.resident_bytes() as f32 / (1 << 20) as f32,
+					self.textures.len()
+				));
+				egui::Grid::new("streamed_textures").show(ui, |ui| {
+					ui.strong("texture");
+					ui.strong("resident");
+					ui.strong("wanted");
+					ui.end_row();
+					for texture in &self.textures {
+						let size = |level: usize| {
+							let size = texture.mips[level].size;
+							format!("{}x{}", size.x, size.y)
+						};
+						ui.label(&texture.name);
+						ui.label(size(texture.resident));
+						ui.label(size(texture.wanted));
+						ui.end_row();
+					}
+				});
+			});
+		self.open = open;
+	}
+}
+
+fn fallback_level(mips: &[MipLevel]) -> usize {
+	mips.iter()
+		.position(|mip| mip.size.max_element() <= FALLBACK_SIZE)
+		.unwrap_or(mips.len() - 1)
+}
+
+/// Texture of the mip chain from `level` down.
+fn chain_texture(name: &str, mips: &[MipLevel], level: usize, format: TextureFormat) -> Texture {
+	let chain = &mips[level..];
+	Texture {
+		label: Some(format!("{} mip {}", name, level)),
+		data: chain
+			.iter()
+			.flat_map(|mip| mip.data.iter().copied())
+			.collect(),
+		format,
+		size: chain[0].size,
+		mip_count: MipmapCount::Specific(NonZeroU32::new(chain.len() as u32).unwrap()),
+		mip_source: MipmapSource::Uploaded,
+	}
+}
+
+/// Next mip of an rgba8 level, averaging 2x2 blocks. Averages the stored
+/// values, which darkens srgb textures' edges slightly.
+fn downsample(mip: &MipLevel) -> MipLevel {
+	let size = (mip.size / 2).max(UVec2::ONE);
+	let mut data = Vec::with_capacity((size.x * size.y * 4) as usize);
+	let texel = |x: u32, y: u32| {
+		let (x, y) = (x.min(mip.size.x - 1), y.min(mip.size.y - 1));
+		let i = ((y * mip.size.x + x) * 4) as usize;
+		&mip.data[i..i + 4]
+	};
+	for y in 0..size.y {
+		for x in 0..size.x {
+			let block = [
+				texel(x * 2, y * 2),
+				texel(x * 2 + 1, y * 2),
+				texel(x * 2, y * 2 + 1),
+				texel(x * 2 + 1, y * 2 + 1),
+			];
+			for channel in 0..4 {
+				let sum: u32 = block.iter().map(|texel| texel[channel] as u32).sum();
+				data.push(((sum + 2) / 4) as u8);
+			}
+		}
+	}
+	MipLevel { size, data }
+}
@@ -0,0 +1,329 @@
+use egui::epaint::ClippedShape;
+use egui::{pos2, vec2, CtxRef, Key, Pos2};
+use winit::dpi::PhysicalSize;
+use winit::event::{
+	ElementState, Event, ModifiersState, MouseButton, MouseScrollDelta, TouchPhase, VirtualKeyCode,
+	WindowEvent,
+};
+
+/// points scrolled per mouse wheel line
+const LINE_HEIGHT: f32 = 24.0;
+
+/// Feeds winit events to egui. Started from egui_winit_platform, which only
+/// passes egui a handful of keys, so shortcuts like ctrl+s and keyboard
+/// navigation between widgets never reached it.
+///
+/// winit doesn't report ime composition yet, composed text arrives as typed
+/// characters.
+pub struct UiPlatform {
+	context: CtxRef,
+	raw_input: egui::RawInput,
+	scale_factor: f64,
+	modifiers: ModifiersState,
+	/// `None` while the pointer is outside the window
+	pointer_pos: Option<Pos2>,
+	/// finger acting as the pointer, the first one down
+	pointer_touch: Option<u64>,
+}
+
+impl UiPlatform {
+	pub fn new(size: PhysicalSize<u32>, scale_factor: f64) -> Self {
+		let raw_input = egui::RawInput {
+			pixels_per_point: Some(scale_factor as f32),
+			screen_rect: Some(screen_rect(size, scale_factor)),
+			..Default::default()
+		};
+		Self {
+			context: CtxRef::default(),
+			raw_input,
+			scale_factor,
+			modifiers: ModifiersState::empty(),
+			pointer_pos: None,
+			pointer_touch: None,
+		}
+	}
+
+	pub fn handle_event<T>(&mut self, event: &Event<T>) {
+		let event = match event {
+			Event::WindowEvent { event, .. } => event,
+			_ => return,
+		};
+		match event {
+			// windows resizes to nothing when minimizing, keep the layout
+			WindowEvent::Resized(PhysicalSize {
+				width: 0,
+				height: 0,
+			}) => {}
+			WindowEvent::Resized(size) => {
+				self.raw_input.screen_rect = Some(screen_rect(*size, self.scale_factor));
+			}
+			WindowEvent::ScaleFactorChanged {
+				scale_factor,
+				new_inner_size,
+			} => {
+				self.scale_factor = *scale_factor;
+				self.raw_input.pixels_per_point = Some(*scale_factor as f32);
+				self.raw_input.screen_rect = Some(screen_rect(**new_inner_size, *scale_factor));
+			}
+			WindowEvent::Focused(false) => {
+				// modifier releases while unfocused aren't reported
+				self.modifiers = ModifiersState::empty();
+				self.raw_input.modifiers = egui::Modifiers::default();
+			}
+			WindowEvent::ModifiersChanged(modifiers) => {
+				self.modifiers = *modifiers;
+				self.raw_input.modifiers = egui_modifiers(*modifiers);
+			}
+			WindowEvent::CursorMoved { position, .. } => {
+				let scale = self.scale_factor as f32;
+				let pos = pos2(position.x as f32 / scale, position.y as f32 / scale);
+				self.pointer_pos = Some(pos);
+				self.raw_input.events.push(egui::Event::PointerMoved(pos));
+			}
+			WindowEvent::CursorLeft { .. } => {
+				self.pointer_pos = None;
+				self.raw_input.events.push(egui::Event::PointerGone);
+			}
+			WindowEvent::MouseInput { state, button, .. } => {
+				let button = match button {
+					MouseButton::Left => egui::PointerButton::Primary,
+					MouseButton::Right => egui::PointerButton::Secondary,
+					MouseButton::Middle => egui::PointerButton::Middle,
+					MouseButton::Other(_) => return,
+				};
+				if let Some(pos) = self.pointer_pos {
+					self.raw_input.events.push(egui::Event::PointerButton {
+						pos,
+						button,
+						pressed: *state == ElementState::Pressed,
+						modifiers: self.raw_input.modifiers,
+					});
+				}
+			}
+			// the first finger down clicks and drags like the mouse
+			WindowEvent::Touch(touch) => {
+				if touch.phase == TouchPhase::Started && self.pointer_touch.is_none() {
+					self.pointer_touch = Some(touch.id);
+				}
+				if self.pointer_touch != Some(touch.id) {
+					return;
+				}
+				let scale = self.scale_factor as f32;
+				let pos = pos2(
+					touch.location.x as f32 / scale,
+					touch.location.y as f32 / scale,
+				);
+				self.pointer_pos = Some(pos);
+				self.raw_input.events.push(egui::Event::PointerMoved(pos));
+				let pressed = match touch.phase {
+					TouchPhase::Started => true,
+					TouchPhase::Moved => return,
+					TouchPhase::Ended | TouchPhase::Cancelled => false,
+				};
+				self.raw_input.events.push(egui::Event::PointerButton {
+					pos,
+					button: egui::PointerButton::Primary,
+					pressed,
+					modifiers: self.raw_input.modifiers,
+				});
+				if !pressed {
+					// a lifted finger doesn't hover
+					self.pointer_touch = None;
+					self.pointer_pos = None;
+					self.raw_input.events.push(egui::Event::PointerGone);
+				}
+			}
+			WindowEvent::MouseWheel { delta, .. } => {
+				// touchpads report the pixels they moved, keep those exact
+				let delta = match delta {
+					MouseScrollDelta::LineDelta(x, y) => vec2(*x, *y) * LINE_HEIGHT,
+					MouseScrollDelta::PixelDelta(delta) => {
+						vec2(delta.x as f32, delta.y as f32) / self.scale_factor as f32
+					}
+				};
+				if self.raw_input.modifiers.command {
+					self.raw_input
+						.events
+						.push(egui::Event::Zoom((delta.y / 200.0).exp()));
+				} else {
+					self.raw_input.events.push(egui::Event::Scroll(delta));
+				}
+			}
+			WindowEvent::KeyboardInput { input, .. } => {
+				let keycode = match input.virtual_keycode {
+					Some(keycode) => keycode,
+					None => return,
+				};
+				let pressed = input.state == ElementState::Pressed;
+				let command = self.raw_input.modifiers.command;
+				match keycode {
+					VirtualKeyCode::C if pressed && command => {
+						self.raw_input.events.push(egui::Event::Copy)
+					}
+					VirtualKeyCode::X if pressed && command => {
+						self.raw_input.events.push(egui::Event::Cut)
+					}
+					// pasting is up to the clipboard, see [`UiPlatform::push_text`]
+					VirtualKeyCode::V if command => {}
+					_ => {
+						if let Some(key) = egui_key(keycode) {
+							self.raw_input.events.push(egui::Event::Key {
+								key,
+								pressed,
+								modifiers: self.raw_input.modifiers,
+							});
+						}
+					}
+				}
+			}
+			// characters typed with ctrl or cmd held are shortcuts
+			WindowEvent::ReceivedCharacter(c)
+				if is_printable(*c) && !self.modifiers.ctrl() && !self.modifiers.logo() =>
+			{
+				self.raw_input.events.push(egui::Event::Text(c.to_string()));
+			}
+			WindowEvent::HoveredFile(path) => {
+				self.raw_input.hovered_files.push(egui::HoveredFile {
+					path: Some(path.clone()),
+					..Default::default()
+				});
+			}
+			WindowEvent::HoveredFileCancelled => self.raw_input.hovered_files.clear(),
+			WindowEvent::DroppedFile(path) => {
+				self.raw_input.hovered_files.clear();
+				self.raw_input.dropped_files.push(egui::DroppedFile {
+					path: Some(path.clone()),
+					..Default::default()
+				});
+			}
+			_ => {}
+		}
+	}
+
+	/// Types `text` into whatever has keyboard focus, like pasted text.
+	pub fn push_text(&mut self, text: &str) {
+		self.raw_input.events.push(egui::Event::Text(text.into()));
+	}
+
+	/// Whether egui should be the only one to see the event, like a click on
+	/// a window or a key typed into a text field.
+	pub fn captures_event<T>(&self, event: &Event<T>) -> bool {
+		match event {
+			Event::WindowEvent { event, .. } => match event {
+				WindowEvent::ReceivedCharacter(_)
+				| WindowEvent::KeyboardInput { .. }
+				| WindowEvent::ModifiersChanged(_) => self.context.wants_keyboard_input(),
+				WindowEvent::MouseWheel { .. } | WindowEvent::MouseInput { .. } => {
+					self.context.wants_pointer_input()
+				}
+				WindowEvent::CursorMoved { .. } => self.context.is_using_pointer(),
+				_ => false,
+			},
+			_ => false,
+		}
+	}
+
+	/// Sets the time animations run on, in seconds from any fixed point.
+	pub fn update_time(&mut self, seconds: f64) {
+		self.raw_input.time = Some(seconds);
+	}
+
+	pub fn begin_frame(&mut self) {
+		self.context.begin_frame(self.raw_input.take());
+	}
+
+	/// Ends the frame. The cursor egui asks for is in the output, for
+	/// [`crate::cursor::Cursor`].
+	pub fn end_frame(&mut self) -> (egui::Output, Vec<ClippedShape>) {
+		self.context.end_frame()
+	}
+
+	pub fn context(&self) -> CtxRef {
+		self.context.clone()
+	}
+}
+
+fn screen_rect(size: PhysicalSize<u32>, scale_factor: f64) -> egui::Rect {
+	let size = vec2(size.width as f32, size.height as f32) / scale_factor as f32;
+	egui::Rect::from_min_size(Pos2::ZERO, size)
+}
+
+fn egui_modifiers(modifiers: ModifiersState) -> egui::Modifiers {
+	egui::Modifiers {
+		alt: modifiers.alt(),
+		ctrl: modifiers.ctrl(),
+		shift: modifiers.shift(),
+		mac_cmd: cfg!(target_os = "macos") && modifiers.logo(),
+		command: match cfg!(target_os = "macos") {
+			true => modifiers.logo(),
+			false => modifiers.ctrl(),
+		},
+	}
+}
+
+fn egui_key(keycode: VirtualKeyCode) -> Option<Key> {
+	use VirtualKeyCode::*;
+	Some(match keycode {
+		Down => Key::ArrowDown,
+		Left => Key::ArrowLeft,
+		Right => Key::ArrowRight,
+		Up => Key::ArrowUp,
+		Escape => Key::Escape,
+		Tab => Key::Tab,
+		Back => Key::Backspace,
+		Return | NumpadEnter => Key::Enter,
+		Space => Key::Space,
+		Insert => Key::Insert,
+		Delete => Key::Delete,
+		Home => Key::Home,
+		End => Key::End,
+		PageUp => Key::PageUp,
+		PageDown => Key::PageDown,
+		Key0 | Numpad0 => Key::Num0,
+		Key1 | Numpad1 => Key::Num1,
+		Key2 | Numpad2 => Key::Num2,
+		Key3 | Numpad3 => Key::Num3,
+		Key4 | Numpad4 => Key::Num4,
+		Key5 | Numpad5 => Key::Num5,
+		Key6 | Numpad6 => Key::Num6,
+		Key7 | Numpad7 => Key::Num7,
+		Key8 | Numpad8 => Key::Num8,
+		Key9 | Numpad9 => Key::Num9,
+		A => Key::A,
+		B => Key::B,
+		C => Key::C,
+		D => Key::D,
+		E => Key::E,
+		F => Key::F,
+		G => Key::G,
+		H => Key::H,
+		I => Key::I,
+		J => Key::J,
+		K => Key::K,
+		L => Key::L,
+		M => Key::M,
+		N => Key::N,
+		O => Key::O,
+		P => Key::P,
+		Q => Key::Q,
+		R => Key::R,
+		S => Key::S,
+		T => Key::T,
+		U => Key::U,
+		V => Key::V,
+		W => Key::W,
+		X => Key::X,
+		Y => Key::Y,
+		Z => Key::Z,
+		_ => return None,
+	})
+}
+
+/// Leaves out control characters and the private use areas special keys
+/// are reported in on some platforms.
+fn is_printable(c: char) -> bool {
+	let private_use = ('\u{e000}'..='\u{f8ff}').contains(&c)
+		|| ('\u{f0000}'..='\u{ffffd}').contains(&c)
+		|| ('\u{100000}'..='\u{10fffd}').contains(&c);
+	!private_use && !c.is_ascii_control()
+}
@@ -0,0 +1,14 @@
+//! Stereo rendering. The [`StereoRig`] turns the main camera into a pair of
+//! eye cameras which are rendered into the two split-screen viewports.
+//!
+//! With the `vr` feature, [`XrDevice`] creates the renderer's device through
+//! an OpenXR runtime, and [`XrTracking`] drives the rig from the headset's
+//! head and eye poses and shows the eyes on its display.
+
+mod rig;
+#[cfg(feature = "vr")]
+mod xr;
+
+pub use rig::{EyePose, Fov, StereoRig};
+#[cfg(feature = "vr")]
+pub use xr::{XrDevice, XrTracking};
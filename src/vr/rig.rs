@@ -0,0 +1,142 @@
+use glam::{Mat4, Quat, Vec3A};
+use rend3::types::{Camera, CameraProjection, Handedness};
+
+use crate::camera::CameraPose;
+
+/// Field of view of one eye as angles in radians from the view direction.
+/// `left` and `down` are negative.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fov {
+	pub left: f32,
+	pub right: f32,
+	pub up: f32,
+	pub down: f32,
+}
+
+impl Fov {
+	/// Reverse-z infinite projection for this (possibly asymmetric) frustum,
+	/// matching what rend3 builds for `CameraProjection::Perspective`.
+	pub fn projection(&self, near: f32, handedness: Handedness) -> Mat4 {
+		let (left, right) = (self.left.tan(), self.right.tan());
+		let (up, down) = (self.up.tan(), self.down.tan());
+
+		let mut projection = Mat4::from_cols_array(&[
+			2.0 / (right - left),
+			0.0,
+			0.0,
+			0.0,
+			//
+			0.0,
+			2.0 / (up - down),
+			0.0,
+			0.0,
+			//
+			-(right + left) / (right - left),
+			-(up + down) / (up - down),
+			0.0,
+			1.0,
+			//
+			0.0,
+			0.0,
+			near,
+			0.0,
+		]);
+		// right handed views look down -z
+		if handedness == Handedness::Right {
+			projection.z_axis = -projection.z_axis;
+		}
+		projection
+	}
+}
+
+/// Pose of one eye relative to the camera, in left-handed space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EyePose {
+	pub position: Vec3A,
+	pub orientation: Quat,
+	pub fov: Fov,
+}
+
+impl EyePose {
+	/// This pose in a space of the given handedness, mirroring the z axis for
+	/// right-handed space.
+	fn in_space(&self, handedness: Handedness) -> Self {
+		match handedness {
+			Handedness::Left => *self,
+			Handedness::Right => {
+				let o = self.orientation;
+				Self {
+					position: self.position * Vec3A::new(1.0, 1.0, -1.0),
+					orientation: Quat::from_xyzw(-o.x, -o.y, o.z, o.w),
+					fov: self.fov,
+				}
+			}
+		}
+	}
+}
+
+/// Splits a camera into a left and right eye.
+///
+/// Without tracking the eyes are offset by half the ipd on either side and
+/// use a symmetric projection, which gives side-by-side stereo on a regular
+/// screen. When a tracking source provides eye poses those are used instead,
+/// placed relative to the camera so the headset moves the view around it.
+pub struct StereoRig {
+	pub enabled: bool,
+	/// interpupillary distance in world units
+	pub ipd: f32,
+	/// tracked eye poses, replacing the fixed ipd offset when set
+	pub tracked_eyes: Option<[EyePose; 2]>,
+}
+
+impl Default for StereoRig {
+	fn default() -> Self {
+		Self {
+			enabled: false,
+			ipd: 0.064,
+			tracked_eyes: None,
+		}
+	}
+}
+
+impl StereoRig {
+	/// Cameras for the left and right eye. `aspect` is the aspect ratio of a
+	/// single eye's viewport.
+	pub fn eye_cameras(
+		&self,
+		camera: &CameraPose,
+		vfov: f32,
+		near: f32,
+		aspect: f32,
+		handedness: Handedness,
+	) -> [Camera; 2] {
+		let view = camera.view_matrix();
+
+		let eyes = self.tracked_eyes.unwrap_or_else(|| {
+			let half_v = (vfov.to_radians() * 0.5).tan();
+			let half_h = (half_v * aspect).atan();
+			let half_v = half_v.atan();
+			let fov = Fov {
+				left: -half_h,
+				right: half_h,
+				up: half_v,
+				down: -half_v,
+			};
+			[-0.5, 0.5].map(|side| EyePose {
+				position: Vec3A::new(side * self.ipd, 0.0, 0.0),
+				orientation: Quat::IDENTITY,
+				fov,
+			})
+		});
+
+		eyes.map(|eye| {
+			let eye = eye.in_space(handedness);
+			let eye_to_camera =
+				Mat4::from_rotation_translation(eye.orientation, eye.position.into());
+			Camera {
+				projection: CameraProjection::Raw(eye.fov.projection(near, handedness)),
+				view: eye_to_camera.inverse() * view,
+			}
+		})
+	}
+}
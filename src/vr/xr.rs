@@ -0,0 +1,523 @@
+use std::ffi::CStr;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail};
+use ash::vk::{self, Handle};
+use glam::{Quat, UVec2, Vec3A};
+use openxr as xr;
+use rend3::types::TextureFormat;
+use rend3::{InstanceAdapterDevice, PotentialAdapter, Renderer, RendererProfile};
+use wgpu::{
+	DeviceDescriptor, Extent3d, TextureDescriptor, TextureDimension, TextureUsages, TextureView,
+	TextureViewDescriptor,
+};
+use wgpu_hal::api::Vulkan;
+use wgpu_hal::Api;
+
+use super::{EyePose, Fov};
+
+const VIEW_TYPE: xr::ViewConfigurationType = xr::ViewConfigurationType::PRIMARY_STEREO;
+/// wgpu-hal checks presentation support on the first queue family only
+const QUEUE_FAMILY: u32 = 0;
+
+type HalInstance = <Vulkan as Api>::Instance;
+type HalDevice = <Vulkan as Api>::Device;
+
+/// The Vulkan device the OpenXR runtime asked for, shared with wgpu. The
+/// runtime picks the gpu the headset is plugged into and may need extensions
+/// on the instance and device, so both are created through it and then
+/// handed to wgpu, rather than wgpu making its own.
+pub struct XrDevice {
+	instance: xr::Instance,
+	system: xr::SystemId,
+	vk_instance: vk::Instance,
+	physical_device: vk::PhysicalDevice,
+	device: vk::Device,
+}
+
+impl XrDevice {
+	/// Connects to the system's OpenXR runtime and creates the renderer's
+	/// device through it, in `profile` if given.
+	pub fn new(profile: Option<RendererProfile>) -> anyhow::Result<(Self, InstanceAdapterDevice)> {
+		let entry = xr::Entry::load()?;
+		if !entry.enumerate_extensions()?.khr_vulkan_enable2 {
+			bail!("the OpenXR runtime can't render with vulkan");
+		}
+		let mut extensions = xr::ExtensionSet::default();
+		extensions.khr_vulkan_enable2 = true;
+		let instance = entry.create_instance(
+			&xr::ApplicationInfo {
+				application_name: "opal",
+				application_version: 0,
+				engine_name: "opal",
+				engine_version: 0,
+			},
+			&extensions,
+			&[],
+		)?;
+		let system = instance.system(xr::FormFactor::HEAD_MOUNTED_DISPLAY)?;
+
+		// wgpu-hal needs vulkan 1.1, which the runtime has to accept
+		let requirements = instance.graphics_requirements::<xr::Vulkan>(system)?;
+		let version = xr::Version::new(1, 1, 0);
+		if version < requirements.min_api_version_supported
+			|| version.major() > requirements.max_api_version_supported.major()
+		{
+			bail!(
+				"the OpenXR runtime needs vulkan {} to {}",
+				requirements.min_api_version_supported,
+				requirements.max_api_version_supported
+			);
+		}
+
+		let vk_entry = unsafe { ash::Entry::load() }?;
+		let driver_version = vk_entry
+			.try_enumerate_instance_version()?
+			.unwrap_or(vk::API_VERSION_1_0);
+		if driver_version < vk::API_VERSION_1_1 {
+			bail!("the vulkan driver doesn't support vulkan 1.1");
+		}
+		// SAFETY: ash's and OpenXR's vkGetInstanceProcAddr are the same function
+		// behind differently typed handles
+		let get_instance_proc_addr = unsafe {
+			std::mem::transmute::<
+				vk::PFN_vkGetInstanceProcAddr,
+				xr::sys::platform::VkGetInstanceProcAddr,
+			>(vk_entry.static_fn().get_instance_proc_addr)
+		};
+
+		let flags = wgpu_hal::InstanceFlags::empty();
+		let instance_extensions = HalInstance::required_extensions(&vk_entry, flags)?;
+		let name = c"opal";
+		let app_info = vk::ApplicationInfo::builder()
+			.application_name(name)
+			.engine_name(name)
+			.api_version(vk::API_VERSION_1_1);
+		let extension_names = pointers(&instance_extensions);
+		let create_info = vk::InstanceCreateInfo::builder()
+			.application_info(&app_info)
+			.enabled_extension_names(&extension_names);
+		let raw_instance = unsafe {
+			instance.create_vulkan_instance(
+				system,
+				get_instance_proc_addr,
+				&*create_info as *const vk::InstanceCreateInfo as *const _,
+			)
+		}?
+		.map_err(vk::Result::from_raw)?;
+		let vk_instance = vk::Instance::from_raw(raw_instance as u64);
+		let ash_instance = unsafe { ash::Instance::load(vk_entry.static_fn(), vk_instance) };
+		let physical_device = vk::PhysicalDevice::from_raw(
+			instance.vulkan_graphics_device(system, raw_instance)? as u64,
+		);
+
+		// the guard hands the instance to wgpu-hal to destroy, and keeps the
+		// runtime's instance alive until then
+		let hal_instance = unsafe {
+			HalInstance::from_raw(
+				vk_entry,
+				ash_instance.clone(),
+				driver_version,
+				instance_extensions,
+				flags,
+				false,
+				Some(Box::new(instance.clone())),
+			)
+		}?;
+		let exposed = hal_instance
+			.expose_adapter(physical_device)
+			.ok_or_else(|| anyhow!("wgpu can't use the headset's gpu"))?;
+		let adapter = PotentialAdapter::new(
+			(),
+			exposed.info.clone(),
+			exposed.capabilities.limits.clone(),
+			exposed.features,
+			profile,
+		)?;
+
+		let families =
+			unsafe { ash_instance.get_physical_device_queue_family_properties(physical_device) };
+		let graphics = families
+			.get(QUEUE_FAMILY as usize)
+			.is_some_and(|family| family.queue_flags.contains(vk::QueueFlags::GRAPHICS));
+		if !graphics {
+			bail!("the headset's gpu can't draw on its first queue family");
+		}
+
+		// the same device wgpu-hal would open itself, created by the runtime
+		let limits = unsafe { ash_instance.get_physical_device_properties(physical_device) }.limits;
+		let uab_types = wgpu_hal::UpdateAfterBindTypes::from_limits(&adapter.limits, &limits);
+		let device_extensions = exposed.adapter.required_device_extensions(adapter.features);
+		let mut device_features = exposed.adapter.physical_device_features(
+			&device_extensions,
+			adapter.features,
+			uab_types,
+		);
+		let queue_infos = [vk::DeviceQueueCreateInfo::builder()
+			.queue_family_index(QUEUE_FAMILY)
+			.queue_priorities(&[1.0])
+			.build()];
+		let extension_names = pointers(&device_extensions);
+		let create_info = device_features.add_to_device_create_builder(
+			vk::DeviceCreateInfo::builder()
+				.queue_create_infos(&queue_infos)
+				.enabled_extension_names(&extension_names),
+		);
+		let raw_device = unsafe {
+			instance.create_vulkan_device(
+				system,
+				get_instance_proc_addr,
+				physical_device.as_raw() as _,
+				&*create_info as *const vk::DeviceCreateInfo as *const _,
+			)
+		}?
+		.map_err(vk::Result::from_raw)?;
+		let device = vk::Device::from_raw(raw_device as u64);
+		let ash_device = unsafe { ash::Device::load(ash_instance.fp_v1_0(), device) };
+		let open = unsafe {
+			exposed.adapter.device_from_raw(
+				ash_device,
+				true,
+				&device_extensions,
+				adapter.features,
+				uab_types,
+				QUEUE_FAMILY,
+				0,
+			)
+		}?;
+
+		let wgpu_instance = unsafe { wgpu::Instance::from_hal::<Vulkan>(hal_instance) };
+		let wgpu_adapter = unsafe { wgpu_instance.create_adapter_from_hal(exposed) };
+		let (wgpu_device, queue) = unsafe {
+			wgpu_adapter.create_device_from_hal(
+				open,
+				&DeviceDescriptor {
+					label: None,
+					features: adapter.features,
+					limits: adapter.limits,
+				},
+				None,
+			)
+		}?;
+
+		let iad = InstanceAdapterDevice {
+			instance: Arc::new(wgpu_instance),
+			adapter: Arc::new(wgpu_adapter),
+			device: Arc::new(wgpu_device),
+			queue: Arc::new(queue),
+			profile: adapter.profile,
+			info: adapter.info,
+		};
+		let device = Self {
+			instance,
+			system,
+			vk_instance,
+			physical_device,
+			device,
+		};
+		Ok((device, iad))
+	}
+}
+
+/// One eye's swapchain, with its images wrapped for wgpu to render into.
+struct Eye {
+	swapchain: xr::Swapchain<xr::Vulkan>,
+	images: Vec<Arc<TextureView>>,
+}
+
+/// A frame begun by [`XrTracking::poll`], for [`XrTracking::render_eyes`] to
+/// fill and submit.
+struct Frame {
+	display_time: xr::Time,
+	/// where the eyes are, `None` when the runtime doesn't want the frame drawn
+	views: Option<[xr::View; 2]>,
+}
+
+/// Head tracking from an OpenXR runtime, and the headset's display. Each
+/// eye gets a swapchain the size the runtime recommends, its images
+/// rendered into by the app and submitted as a projection layer.
+pub struct XrTracking {
+	instance: xr::Instance,
+	session: xr::Session<xr::Vulkan>,
+	frame_waiter: xr::FrameWaiter,
+	frame_stream: xr::FrameStream<xr::Vulkan>,
+	space: xr::Space,
+	blend_mode: xr::EnvironmentBlendMode,
+	eyes: [Eye; 2],
+	frame: Option<Frame>,
+	running: bool,
+	/// per-eye render target size
+	pub eye_resolution: UVec2,
+}
+
+impl XrTracking {
+	/// Starts a session on the device the runtime created, with swapchains in
+	/// `format`, the format the post passes write.
+	pub fn new(
+		device: XrDevice,
+		renderer: &Renderer,
+		format: TextureFormat,
+	) -> Result<Self, xr::sys::Result> {
+		let XrDevice {
+			instance,
+			system,
+			vk_instance,
+			physical_device,
+			device,
+		} = device;
+
+		let views = instance.enumerate_view_configuration_views(system, VIEW_TYPE)?;
+		let eye_resolution = match views.first() {
+			Some(view) => UVec2::new(
+				view.recommended_image_rect_width,
+				view.recommended_image_rect_height,
+			),
+			None => return Err(xr::sys::Result::ERROR_VIEW_CONFIGURATION_TYPE_UNSUPPORTED),
+		};
+		let blend_mode = instance
+			.enumerate_environment_blend_modes(system, VIEW_TYPE)?
+			.first()
+			.copied()
+			.unwrap_or(xr::EnvironmentBlendMode::OPAQUE);
+
+		let (session, frame_waiter, frame_stream) = unsafe {
+			instance.create_session::<xr::Vulkan>(
+				system,
+				&xr::vulkan::SessionCreateInfo {
+					instance: vk_instance.as_raw() as _,
+					physical_device: physical_device.as_raw() as _,
+					device: device.as_raw() as _,
+					queue_family_index: QUEUE_FAMILY,
+					queue_index: 0,
+				},
+			)
+		}?;
+		let space =
+			session.create_reference_space(xr::ReferenceSpaceType::LOCAL, xr::Posef::IDENTITY)?;
+
+		let vk_format = match vk_format(format) {
+			Some(vk_format) => vk_format.as_raw() as u32,
+			None => return Err(xr::sys::Result::ERROR_SWAPCHAIN_FORMAT_UNSUPPORTED),
+		};
+		if !session.enumerate_swapchain_formats()?.contains(&vk_format) {
+			log::error!("the OpenXR runtime can't show {:?}", format);
+			return Err(xr::sys::Result::ERROR_SWAPCHAIN_FORMAT_UNSUPPORTED);
+		}
+		let eye = |label: &str| -> Result<Eye, xr::sys::Result> {
+			let swapchain = session.create_swapchain(&xr::SwapchainCreateInfo {
+				create_flags: xr::SwapchainCreateFlags::EMPTY,
+				usage_flags: xr::SwapchainUsageFlags::COLOR_ATTACHMENT,
+				format: vk_format,
+				sample_count: 1,
+				width: eye_resolution.x,
+				height: eye_resolution.y,
+				face_count: 1,
+				array_size: 1,
+				mip_count: 1,
+			})?;
+			let images = swapchain
+				.enumerate_images()?
+				.into_iter()
+				.map(|image| wrap_image(renderer, image, label, format, eye_resolution))
+				.collect();
+			Ok(Eye { swapchain, images })
+		};
+		let eyes = [eye("left eye")?, eye("right eye")?];
+
+		Ok(Self {
+			instance,
+			session,
+			frame_waiter,
+			frame_stream,
+			space,
+			blend_mode,
+			eyes,
+			frame: None,
+			running: false,
+			eye_resolution,
+		})
+	}
+
+	/// Handles runtime events and begins the headset's next frame, returning
+	/// the eye poses to render it from. `None` while the session isn't
+	/// running or the runtime doesn't want the frame drawn.
+	pub fn poll(&mut self) -> Result<Option<[EyePose; 2]>, xr::sys::Result> {
+		// a frame nothing was rendered for, when the window skipped a redraw
+		if let Some(frame) = self.frame.take() {
+			self.frame_stream
+				.end(frame.display_time, self.blend_mode, &[])?;
+		}
+
+		let mut buffer = xr::EventDataBuffer::new();
+		while let Some(event) = self.instance.poll_event(&mut buffer)? {
+			if let xr::Event::SessionStateChanged(change) = event {
+				match change.state() {
+					xr::SessionState::READY => {
+						self.session.begin(VIEW_TYPE)?;
+						self.running = true;
+					}
+					xr::SessionState::STOPPING => {
+						self.session.end()?;
+						self.running = false;
+					}
+					_ => {}
+				}
+			}
+		}
+
+		if !self.running {
+			return Ok(None);
+		}
+
+		let state = self.frame_waiter.wait()?;
+		self.frame_stream.begin()?;
+		let (_, views) =
+			self.session
+				.locate_views(VIEW_TYPE, state.predicted_display_time, &self.space)?;
+		let views = match (state.should_render, views.as_slice()) {
+			(true, [left, right, ..]) => Some([*left, *right]),
+			_ => None,
+		};
+		self.frame = Some(Frame {
+			display_time: state.predicted_display_time,
+			views,
+		});
+
+		Ok(views.map(|[left, right]| [eye_pose(&left), eye_pose(&right)]))
+	}
+
+	/// Renders the frame [`XrTracking::poll`] began into the eyes'
+	/// swapchains, `render` drawing the left (0) and right (1) eye into the
+	/// target it's given, and submits it to the headset.
+	pub fn render_eyes(
+		&mut self,
+		mut render: impl FnMut(usize, Arc<TextureView>),
+	) -> Result<(), xr::sys::Result> {
+		let frame = match self.frame.take() {
+			Some(frame) => frame,
+			None => return Ok(()),
+		};
+		let views = match frame.views {
+			Some(views) => views,
+			None => {
+				return self
+					.frame_stream
+					.end(frame.display_time, self.blend_mode, &[]);
+			}
+		};
+
+		for (index, eye) in self.eyes.iter_mut().enumerate() {
+			let image = eye.swapchain.acquire_image()?;
+			eye.swapchain.wait_image(xr::Duration::INFINITE)?;
+			render(index, eye.images[image as usize].clone());
+			// the eye's commands are submitted, the runtime waits on them
+			eye.swapchain.release_image()?;
+		}
+
+		let rect = xr::Rect2Di {
+			offset: xr::Offset2Di { x: 0, y: 0 },
+			extent: xr::Extent2Di {
+				width: self.eye_resolution.x as i32,
+				height: self.eye_resolution.y as i32,
+			},
+		};
+		let projection_view = |index: usize| {
+			xr::CompositionLayerProjectionView::new()
+				.pose(views[index].pose)
+				.fov(views[index].fov)
+				.sub_image(
+					xr::SwapchainSubImage::new()
+						.swapchain(&self.eyes[index].swapchain)
+						.image_rect(rect),
+				)
+		};
+		let projection_views = [projection_view(0), projection_view(1)];
+		self.frame_stream.end(
+			frame.display_time,
+			self.blend_mode,
+			&[&xr::CompositionLayerProjection::new()
+				.space(&self.space)
+				.views(&projection_views)],
+		)
+	}
+}
+
+/// Pointers to the names, for vulkan create infos.
+fn pointers(names: &[&'static CStr]) -> Vec<*const std::os::raw::c_char> {
+	names.iter().map(|name| name.as_ptr()).collect()
+}
+
+/// Vulkan format of the 8 bit color formats surfaces come in.
+fn vk_format(format: TextureFormat) -> Option<vk::Format> {
+	match format {
+		TextureFormat::Bgra8UnormSrgb => Some(vk::Format::B8G8R8A8_SRGB),
+		TextureFormat::Rgba8UnormSrgb => Some(vk::Format::R8G8B8A8_SRGB),
+		TextureFormat::Bgra8Unorm => Some(vk::Format::B8G8R8A8_UNORM),
+		TextureFormat::Rgba8Unorm => Some(vk::Format::R8G8B8A8_UNORM),
+		_ => None,
+	}
+}
+
+/// A swapchain image as a texture view to render into. The runtime owns the
+/// image, wgpu only draws to it.
+fn wrap_image(
+	renderer: &Renderer,
+	image: u64,
+	label: &str,
+	format: TextureFormat,
+	resolution: UVec2,
+) -> Arc<TextureView> {
+	let size = Extent3d {
+		width: resolution.x,
+		height: resolution.y,
+		depth_or_array_layers: 1,
+	};
+	// SAFETY: the swapchain was created with this size and format as a color
+	// attachment, and the drop guard keeps wgpu from destroying the image
+	let texture = unsafe {
+		let raw = HalDevice::texture_from_raw(
+			vk::Image::from_raw(image),
+			&wgpu_hal::TextureDescriptor {
+				label: Some(label),
+				size,
+				mip_level_count: 1,
+				sample_count: 1,
+				dimension: TextureDimension::D2,
+				format,
+				usage: wgpu_hal::TextureUses::COLOR_TARGET,
+				memory_flags: wgpu_hal::MemoryFlags::empty(),
+			},
+			Some(Box::new(())),
+		);
+		renderer.device.create_texture_from_hal::<Vulkan>(
+			raw,
+			&TextureDescriptor {
+				label: Some(label),
+				size,
+				mip_level_count: 1,
+				sample_count: 1,
+				dimension: TextureDimension::D2,
+				format,
+				usage: TextureUsages::RENDER_ATTACHMENT,
+			},
+		)
+	};
+	Arc::new(texture.create_view(&TextureViewDescriptor::default()))
+}
+
+/// Converts from OpenXR's right-handed space to the renderer's left-handed
+/// space by mirroring the z axis.
+fn eye_pose(view: &xr::View) -> EyePose {
+	let p = view.pose.position;
+	let o = view.pose.orientation;
+	EyePose {
+		position: Vec3A::new(p.x, p.y, -p.z),
+		orientation: Quat::from_xyzw(-o.x, -o.y, o.z, o.w),
+		fov: Fov {
+			left: view.fov.angle_left,
+			right: view.fov.angle_right,
+			up: view.fov.angle_up,
+			down: view.fov.angle_down,
+		},
+	}
+}
@@ -0,0 +1,107 @@
+use std::sync::Arc;
+use std::time::Instant;
+
+use glam::UVec2;
+use rend3::Renderer;
+use wgpu::{
+	Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureView,
+	TextureViewDescriptor,
+};
+
+/// Features that add or remove passes from the frame graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WarmupStep {
+	pub motion_blur: bool,
+	/// two viewports composited together
+	pub split_screen: bool,
+	pub section: bool,
+}
+
+/// Renders a frame of every combination of [`WarmupStep`] features off
+/// screen, so pipelines, graph targets and driver shader compiles are all
+/// done before they're first needed instead of stalling the frame a feature
+/// is turned on in. Runs at startup and again after assets are imported.
+pub struct Warmup {
+	format: TextureFormat,
+	target: Option<(UVec2, Arc<TextureView>)>,
+	remaining: Vec<WarmupStep>,
+	started: Option<Instant>,
+}
+
+impl Warmup {
+	/// Starts warming up, rendering to targets of the surface's format.
+	pub fn new(format: TextureFormat) -> Self {
+		let mut warmup = Self {
+			format,
+			target: None,
+			remaining: Vec::new(),
+			started: None,
+		};
+		warmup.restart();
+		warmup
+	}
+
+	/// Goes through every step again, e.g. after new assets were imported.
+	pub fn restart(&mut self) {
+		self.remaining.clear();
+		for i in 0..8 {
+			self.remaining.push(WarmupStep {
+				motion_blur: i & 1 != 0,
+				split_screen: i & 2 != 0,
+				section: i & 4 != 0,
+			});
+		}
+		self.started = None;
+	}
+
+	pub fn is_running(&self) -> bool {
+		!self.remaining.is_empty()
+	}
+
+	/// Features to render this frame with, `None` once warmup is done.
+	pub fn next_step(&mut self) -> Option<WarmupStep> {
+		let step = self.remaining.pop();
+		match step {
+			Some(_) => {
+				self.started.get_or_insert_with(Instant::now);
+			}
+			None => {
+				if let Some(started) = self.started.take() {
+					log::info!(
+						"pipeline warmup took {:.1}ms",
+						started.elapsed().as_secs_f32() * 1000.0
+					);
+					// not needed until the next warmup
+					self.target = None;
+				}
+			}
+		}
+		step
+	}
+
+	/// Off screen target that warmup frames are rendered to in place of the
+	/// surface.
+	pub fn target(&mut self, renderer: &Renderer, resolution: UVec2) -> Arc<TextureView> {
+		match &self.target {
+			Some((size, view)) if *size == resolution => view.clone(),
+			_ => {
+				let texture = renderer.device.create_texture(&TextureDescriptor {
+					label: Some("warmup"),
+					size: Extent3d {
+						width: resolution.x,
+						height: resolution.y,
+						depth_or_array_layers: 1,
+					},
+					mip_level_count: 1,
+					sample_count: 1,
+					dimension: TextureDimension::D2,
+					format: self.format,
+					usage: TextureUsages::RENDER_ATTACHMENT,
+				});
+				let view = Arc::new(texture.create_view(&TextureViewDescriptor::default()));
+				self.target = Some((resolution, view.clone()));
+				view
+			}
+		}
+	}
+}
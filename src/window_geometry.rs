@@ -0,0 +1,138 @@
+use std::path::Path;
+
+use serde_json::{json, Value};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::monitor::MonitorHandle;
+use winit::window::{Fullscreen, Window};
+
+use crate::config::{load_config_entry, save_config_entry, ConfigError};
+
+/// smallest restored window, so a bad config can't make it unusable
+const MIN_SIZE: u32 = 200;
+
+/// Where the window was and how big, saved when the app exits and restored
+/// on the next run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowGeometry {
+	/// outer position and inner size from the last time the window was
+	/// neither maximized nor fullscreen, so unmaximizing goes back to it
+	pub position: PhysicalPosition<i32>,
+	pub size: PhysicalSize<u32>,
+	pub maximized: bool,
+	pub fullscreen: bool,
+	/// name of the monitor the window was on
+	pub monitor: Option<String>,
+}
+
+impl WindowGeometry {
+	pub fn capture(window: &Window) -> Self {
+		Self {
+			position: window.outer_position().unwrap_or_default(),
+			size: window.inner_size(),
+			maximized: window.is_maximized(),
+			fullscreen: window.fullscreen().is_some(),
+			monitor: window.current_monitor().and_then(|monitor| monitor.name()),
+		}
+	}
+
+	/// Keeps the position and size up to date as the window moves and
+	/// resizes, ignoring changes from maximizing or going fullscreen.
+	pub fn track(&mut self, window: &Window) {
+		self.maximized = window.is_maximized();
+		self.fullscreen = window.fullscreen().is_some();
+		if let Some(monitor) = window.current_monitor() {
+			self.monitor = monitor.name();
+		}
+		if !self.maximized && !self.fullscreen {
+			if let Ok(position) = window.outer_position() {
+				self.position = position;
+			}
+			self.size = window.inner_size();
+		}
+	}
+
+	/// Moves and sizes the window to match. Falls back to the primary
+	/// monitor when the saved one isn't connected anymore, and leaves the
+	/// position to the os when the window would end up off every monitor.
+	pub fn restore(&self, window: &Window) {
+		let monitors: Vec<MonitorHandle> = window.available_monitors().collect();
+		let saved = monitors
+			.iter()
+			.find(|monitor| self.monitor.is_some() && monitor.name() == self.monitor);
+		let monitor = saved.cloned().or_else(|| window.primary_monitor());
+
+		// keep the window smaller than the monitor it goes on
+		let size = match &monitor {
+			Some(monitor) => PhysicalSize::new(
+				self.size.width.min(monitor.size().width),
+				self.size.height.min(monitor.size().height),
+			),
+			None => self.size,
+		};
+		window.set_inner_size(PhysicalSize::new(
+			size.width.max(MIN_SIZE),
+			size.height.max(MIN_SIZE),
+		));
+
+		let visible = monitors.iter().any(|monitor| {
+			let (min, size) = (monitor.position(), monitor.size());
+			// the title bar corner has to be on the monitor to drag it back
+			let (x, y) = (self.position.x, self.position.y);
+			x >= min.x
+				&& y >= min.y
+				&& x < min.x + size.width as i32
+				&& y < min.y + size.height as i32
+		});
+		if saved.is_some() && visible {
+			window.set_outer_position(self.position);
+		} else if saved.is_none() {
+			log::info!("saved monitor isn't connected, placing the window on the primary one");
+		}
+
+		if self.fullscreen {
+			window.set_fullscreen(Some(Fullscreen::Borderless(monitor)));
+		} else if self.maximized {
+			window.set_maximized(true);
+		}
+	}
+
+	fn to_json(&self) -> Value {
+		json!({
+			"position": [self.position.x, self.position.y],
+			"size": [self.size.width, self.size.height],
+			"maximized": self.maximized,
+			"fullscreen": self.fullscreen,
+			"monitor": self.monitor,
+		})
+	}
+
+	fn from_json(value: &Value) -> Option<Self> {
+		let pair = |key: &str| -> Option<(i64, i64)> {
+			match value.get(key)?.as_array()?.as_slice() {
+				[a, b] => Some((a.as_i64()?, b.as_i64()?)),
+				_ => None,
+			}
+		};
+		let (x, y) = pair("position")?;
+		let (width, height) = pair("size")?;
+		Some(Self {
+			position: PhysicalPosition::new(x as i32, y as i32),
+			size: PhysicalSize::new(width.max(0) as u32, height.max(0) as u32),
+			maximized: value.get("maximized")?.as_bool()?,
+			fullscreen: value.get("fullscreen")?.as_bool()?,
+			monitor: value.get("monitor")?.as_str().map(String::from),
+		})
+	}
+
+	/// Reads the geometry from a config file. `None` if the file or its
+	/// window entry is missing or invalid.
+	pub fn load(path: &Path) -> Option<Self> {
+		Self::from_json(&load_config_entry(path, "window")?)
+	}
+
+	/// Writes the geometry into a config file, keeping the file's other
+	/// entries.
+	pub fn save(&self, path: &Path) -> Result<(), ConfigError> {
+		save_config_entry(path, "window", self.to_json())
+	}
+}
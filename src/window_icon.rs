@@ -0,0 +1,112 @@
+use std::path::Path;
+
+use winit::window::{Icon, Window};
+
+use crate::assets::{try_load_texture, AssetError};
+
+/// size of the icon used when the app doesn't set one
+const GENERATED_SIZE: u32 = 32;
+
+/// Badges drawn over the window icon to show what the app is busy with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IconOverlay {
+	/// red dot in the corner, while input or frames are being recorded
+	Recording,
+}
+
+/// The window and taskbar icon, with an overlay that can change while the app
+/// runs. Wayland has no way for windows to set their icon, the desktop finds
+/// it by the app id instead.
+pub struct WindowIcon {
+	rgba: Vec<u8>,
+	width: u32,
+	height: u32,
+	/// overlay on the icon the window has, `None` until it's applied
+	applied: Option<Option<IconOverlay>>,
+}
+
+impl WindowIcon {
+	/// Loads the icon from an image asset, see [`try_load_texture`].
+	pub fn load(path: &Path) -> Result<Self, AssetError> {
+		let texture = try_load_texture(path, true)?;
+		Ok(Self {
+			rgba: texture.data,
+			width: texture.size.x,
+			height: texture.size.y,
+			applied: None,
+		})
+	}
+
+	/// A teal disc, for when there's no icon asset.
+	pub fn generated() -> Self {
+		let size = GENERATED_SIZE;
+		let radius = size as f32 / 2.0;
+		let mut rgba = Vec::with_capacity((size * size * 4) as usize);
+		for y in 0..size {
+			for x in 0..size {
+				let (dx, dy) = (x as f32 + 0.5 - radius, y as f32 + 0.5 - radius);
+				let distance = (dx * dx + dy * dy).sqrt() / radius;
+				// lighter towards the top left, like the lit side of a ball
+				let shade = 1.0 - (dx + dy) / (4.0 * radius);
+				let alpha = ((1.0 - distance) * radius).clamp(0.0, 1.0);
+				rgba.extend_from_slice(&[
+					(40.0 * shade) as u8,
+					(170.0 * shade).min(255.0) as u8,
+					(170.0 * shade).min(255.0) as u8,
+					(255.0 * alpha) as u8,
+				]);
+			}
+		}
+		Self {
+			rgba,
+			width: size,
+			height: size,
+			applied: None,
+		}
+	}
+
+	/// Gives the window the icon with `overlay` drawn on it. Cheap to call
+	/// every frame, the icon is only rebuilt when the overlay changes.
+	pub fn apply(&mut self, window: &Window, overlay: Option<IconOverlay>) {
+		if self.applied == Some(overlay) {
+			return;
+		}
+		self.applied = Some(overlay);
+
+		let mut rgba = self.rgba.clone();
+		if let Some(IconOverlay::Recording) = overlay {
+			self.draw_dot(&mut rgba, [220, 30, 30, 255]);
+		}
+		let icon = match Icon::from_rgba(rgba, self.width, self.height) {
+			Ok(icon) => icon,
+			Err(e) => {
+				log::warn!("invalid window icon: {}", e);
+				return;
+			}
+		};
+		#[cfg(target_os = "windows")]
+		{
+			use winit::platform::windows::WindowExtWindows;
+			window.set_taskbar_icon(Some(icon.clone()));
+		}
+		window.set_window_icon(Some(icon));
+	}
+
+	/// Fills a dot a third of the icon's size in the bottom right corner.
+	fn draw_dot(&self, rgba: &mut [u8], color: [u8; 4]) {
+		let radius = self.width.min(self.height) as f32 / 6.0;
+		let center = (
+			self.width as f32 - radius - 1.0,
+			self.height as f32 - radius - 1.0,
+		);
+		for y in 0..self.height {
+			for x in 0..self.width {
+				let (dx, dy) = (x as f32 + 0.5 - center.0, y as f32 + 0.5 - center.1);
+				if dx * dx + dy * dy <= radius * radius {
+					let i = ((y * self.width + x) * 4) as usize;
+					rgba[i..i + 4].copy_from_slice(&color);
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,98 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use rend3::Renderer;
+
+use crate::scene::Scene;
+
+/// Work that has to run on the main thread, like finishing gpu uploads.
+pub type MainThreadJob = Box<dyn FnOnce(&Renderer, &mut Scene)>;
+
+/// Spreads main thread work over frames so a burst of it doesn't make one
+/// frame take much longer than the rest. Each frame gets a time budget that
+/// queued jobs and other budgeted work like tile and mesh uploads share.
+/// At least one job runs a frame, so a job longer than the budget still
+/// runs.
+pub struct WorkQueue {
+	jobs: VecDeque<(String, MainThreadJob)>,
+	budget: Duration,
+	/// budgeted time spent so far this frame
+	spent: Duration,
+	/// time spent over the whole previous frame, for the stats
+	last_spent: Duration,
+}
+
+impl Default for WorkQueue {
+	fn default() -> Self {
+		Self {
+			jobs: VecDeque::new(),
+			budget: Duration::from_millis(2),
+			spent: Duration::ZERO,
+			last_spent: Duration::ZERO,
+		}
+	}
+}
+
+impl WorkQueue {
+	/// Queues `job` to run on a later frame with time left in its budget.
+	pub fn push(
+		&mut self,
+		name: impl Into<String>,
+		job: impl FnOnce(&Renderer, &mut Scene) + 'static,
+	) {
+		self.jobs.push_back((name.into(), Box::new(job)));
+	}
+
+	/// Starts a frame's budget. Call before any budgeted work.
+	pub fn begin_frame(&mut self, budget: Duration) {
+		self.budget = budget;
+		self.last_spent = self.spent;
+		self.spent = Duration::ZERO;
+	}
+
+	/// Whether this frame's budget has time left. Budgeted work should do at
+	/// least one piece of work a frame regardless, or it may never finish.
+	pub fn has_time(&self) -> bool {
+		self.spent < self.budget
+	}
+
+	/// Runs `work`, counting its time against the frame's budget.
+	pub fn measure<R>(&mut self, work: impl FnOnce() -> R) -> R {
+		let start = Instant::now();
+		let result = work();
+		self.spent += start.elapsed();
+		result
+	}
+
+	/// Runs queued jobs in order until the budget is used up.
+	pub fn run(&mut self, renderer: &Renderer, scene: &mut Scene) {
+		let mut ran = false;
+		while !ran || self.has_time() {
+			let (name, job) = match self.jobs.pop_front() {
+				Some(job) => job,
+				None => break,
+			};
+			let start = Instant::now();
+			job(renderer, scene);
+			let elapsed = start.elapsed();
+			self.spent += elapsed;
+			if elapsed > self.budget {
+				log::debug!(
+					"{} took {:.1}ms, over the whole frame budget",
+					name,
+					elapsed.as_secs_f32() * 1000.0
+				);
+			}
+			ran = true;
+		}
+	}
+
+	pub fn pending(&self) -> usize {
+		self.jobs.len()
+	}
+
+	/// Budgeted time spent in the previous frame.
+	pub fn last_spent(&self) -> Duration {
+		self.last_spent
+	}
+}